@@ -0,0 +1,302 @@
+//! Content-addressed artifact store.
+//!
+//! With --artifacts (or AGENT_BROWSER_ARTIFACTS), the files commands produce
+//! (screenshots, PDFs, downloads, traces, HARs and recordings) are kept in
+//! `~/.agent-browser/artifacts` (or AGENT_BROWSER_ARTIFACTS_DIR) under the
+//! SHA-256 of their content, so the same file saved twice is stored once. A
+//! screenshot without a path goes only to the store; other files stay where
+//! the command wrote them and are copied in. `index.jsonl` records every
+//! artifact with its kind, original name, session and time.
+//!
+//! Retention: after each new artifact, and on `artifacts gc`, entries older
+//! than AGENT_BROWSER_ARTIFACTS_MAX_AGE (default 30 days) are dropped, then
+//! the oldest until the store fits AGENT_BROWSER_ARTIFACTS_MAX_SIZE, and
+//! files no entry refers to any more are deleted.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::connection::app_data_dir;
+use crate::jobs::now_millis;
+
+/// Age limit used when AGENT_BROWSER_ARTIFACTS_MAX_AGE isn't set
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(30 * 86400);
+
+/// Shortest hash prefix accepted by `artifacts get`
+const MIN_PREFIX: usize = 6;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub hash: String,
+    pub kind: String,
+    /// File name the command saved it as
+    pub name: String,
+    pub size: u64,
+    pub session: String,
+    /// Unix time in milliseconds
+    pub created: u64,
+}
+
+impl Entry {
+    pub fn object_path(&self) -> PathBuf {
+        object_path(&self.hash, &self.name)
+    }
+}
+
+pub fn store_dir() -> PathBuf {
+    match env::var("AGENT_BROWSER_ARTIFACTS_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => app_data_dir().join("artifacts"),
+    }
+}
+
+fn index_path() -> PathBuf {
+    store_dir().join("index.jsonl")
+}
+
+/// Where an object lives: `objects/<first 2 hex>/<hash><.ext>`, keeping the
+/// extension so the file opens with the right program
+pub fn object_path(hash: &str, name: &str) -> PathBuf {
+    let ext = Path::new(name).extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    store_dir().join("objects").join(&hash[..2.min(hash.len())]).join(format!("{}{}", hash, ext))
+}
+
+/// A path for a screenshot taken without one, inside the store so it can be
+/// moved in without copying
+pub fn scratch_path() -> PathBuf {
+    store_dir().join("tmp").join(format!("screenshot-{}.png", crate::commands::gen_id()))
+}
+
+/// The kind of artifact an action produces and the result field holding its path
+pub fn artifact_field(action: &str) -> Option<(&'static str, &'static str)> {
+    match action {
+        "screenshot" => Some(("screenshot", "path")),
+        "pdf" => Some(("pdf", "path")),
+        "download" | "waitfordownload" => Some(("download", "path")),
+        "trace_stop" => Some(("trace", "path")),
+        "har_stop" => Some(("har", "path")),
+        "recording_stop" | "video_stop" => Some(("video", "path")),
+        "recording_restart" => Some(("video", "previousPath")),
+        "gif_stop" => Some(("gif", "path")),
+        _ => None,
+    }
+}
+
+fn hash_file(path: &Path) -> Result<(String, u64), String> {
+    let mut file = fs::File::open(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    let mut size = 0;
+    loop {
+        let n = file.read(&mut buf).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+    Ok((hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect(), size))
+}
+
+pub fn read_index() -> Result<Vec<Entry>, String> {
+    let path = index_path();
+    let source = match fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+    };
+    Ok(source.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+fn write_index(entries: &[Entry]) -> Result<(), String> {
+    let lines: String = entries.iter().filter_map(|e| serde_json::to_string(e).ok()).map(|l| l + "\n").collect();
+    fs::write(index_path(), lines).map_err(|e| format!("Cannot write artifact index: {}", e))
+}
+
+/// Add a file to the store. `owned` files (written for the store) are moved
+/// in; others are copied and left where they are.
+pub fn ingest(path: &Path, kind: &str, session: &str, owned: bool) -> Result<Entry, String> {
+    let (hash, size) = hash_file(path)?;
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let object = object_path(&hash, &name);
+    if let Some(dir) = object.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    }
+    let stored = if object.exists() {
+        // Same content already stored
+        owned.then(|| fs::remove_file(path)).transpose().map(|_| ())
+    } else if owned {
+        fs::rename(path, &object).or_else(|_| fs::copy(path, &object).and_then(|_| fs::remove_file(path)))
+    } else {
+        fs::copy(path, &object).map(|_| ())
+    };
+    stored.map_err(|e| format!("Cannot store {}: {}", path.display(), e))?;
+
+    let entry = Entry { hash, kind: kind.to_string(), name, size, session: session.to_string(), created: now_millis() };
+    let mut index = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(index_path())
+        .map_err(|e| format!("Cannot write artifact index: {}", e))?;
+    let line = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+    writeln!(index, "{}", line).map_err(|e| format!("Cannot write artifact index: {}", e))?;
+    Ok(entry)
+}
+
+/// The entry a hash prefix refers to (the latest, when stored several times)
+pub fn resolve<'a>(entries: &'a [Entry], prefix: &str) -> Result<&'a Entry, String> {
+    if prefix.len() < MIN_PREFIX || !prefix.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Invalid artifact '{}': use at least {} characters of its hash", prefix, MIN_PREFIX));
+    }
+    let prefix = prefix.to_ascii_lowercase();
+    let matches: Vec<&Entry> = entries.iter().filter(|e| e.hash.starts_with(&prefix)).collect();
+    let mut hashes: Vec<&str> = matches.iter().map(|e| e.hash.as_str()).collect();
+    hashes.sort();
+    hashes.dedup();
+    match (matches.last(), hashes.len()) {
+        (None, _) => Err(format!("No artifact {} (see: agent-browser artifacts list)", prefix)),
+        (Some(entry), 1) => Ok(entry),
+        _ => Err(format!("Artifact {} is ambiguous; give more of the hash", prefix)),
+    }
+}
+
+/// The retention policy from the environment
+pub fn policy_from_env() -> Result<(Duration, Option<u64>), String> {
+    let max_age = match env::var("AGENT_BROWSER_ARTIFACTS_MAX_AGE") {
+        Ok(value) if !value.is_empty() => crate::state_gc::parse_duration(&value)?,
+        _ => DEFAULT_MAX_AGE,
+    };
+    let max_size = match env::var("AGENT_BROWSER_ARTIFACTS_MAX_SIZE") {
+        Ok(value) if !value.is_empty() => Some(crate::budget::parse_size("AGENT_BROWSER_ARTIFACTS_MAX_SIZE", &value)?),
+        _ => None,
+    };
+    Ok((max_age, max_size))
+}
+
+/// Indices of the entries retention drops: those older than `max_age`, then
+/// the oldest until the distinct objects fit in `max_size`
+pub fn plan_gc(entries: &[Entry], now: u64, max_age: Duration, max_size: Option<u64>) -> Vec<usize> {
+    let cutoff = now.saturating_sub(max_age.as_millis() as u64);
+    let mut dropped: Vec<usize> = (0..entries.len()).filter(|&i| entries[i].created < cutoff).collect();
+    if let Some(max_size) = max_size {
+        let mut refs: HashMap<&str, usize> = HashMap::new();
+        let mut total = 0;
+        for (_, entry) in entries.iter().enumerate().filter(|(i, _)| !dropped.contains(i)) {
+            let count = refs.entry(&entry.hash).or_default();
+            if *count == 0 {
+                total += entry.size;
+            }
+            *count += 1;
+        }
+        let mut oldest: Vec<usize> = (0..entries.len()).filter(|i| !dropped.contains(i)).collect();
+        oldest.sort_by_key(|&i| entries[i].created);
+        for i in oldest {
+            if total <= max_size {
+                break;
+            }
+            let count = refs.entry(&entries[i].hash).or_default();
+            *count -= 1;
+            if *count == 0 {
+                total -= entries[i].size;
+            }
+            dropped.push(i);
+        }
+    }
+    dropped.sort();
+    dropped
+}
+
+#[derive(Debug, Default)]
+pub struct GcReport {
+    /// Index entries dropped
+    pub entries: usize,
+    /// Stored files deleted
+    pub objects: usize,
+    pub bytes: u64,
+    /// Entries left
+    pub kept: usize,
+}
+
+/// Apply the retention policy, deleting unless `dry_run`
+pub fn gc(max_age: Duration, max_size: Option<u64>, dry_run: bool) -> Result<GcReport, String> {
+    let entries = read_index()?;
+    let dropped = plan_gc(&entries, now_millis(), max_age, max_size);
+    let (gone, kept): (Vec<_>, Vec<_>) = entries.into_iter().enumerate().partition(|(i, _)| dropped.contains(i));
+    let kept: Vec<Entry> = kept.into_iter().map(|(_, e)| e).collect();
+    let mut report = GcReport { entries: gone.len(), kept: kept.len(), ..Default::default() };
+    let mut deleted: Vec<PathBuf> = Vec::new();
+    for (_, entry) in &gone {
+        let object = entry.object_path();
+        if kept.iter().any(|e| e.object_path() == object) || deleted.contains(&object) {
+            continue;
+        }
+        report.objects += 1;
+        report.bytes += entry.size;
+        deleted.push(object);
+    }
+    if dry_run || gone.is_empty() {
+        return Ok(report);
+    }
+    write_index(&kept)?;
+    for object in deleted {
+        let _ = fs::remove_file(object);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(hash: &str, size: u64, created: u64) -> Entry {
+        Entry {
+            hash: hash.to_string(),
+            kind: "screenshot".to_string(),
+            name: "shot.png".to_string(),
+            size,
+            session: "default".to_string(),
+            created,
+        }
+    }
+
+    #[test]
+    fn test_plan_gc_age() {
+        let day = 86_400_000;
+        let entries = vec![entry("aa", 10, 0), entry("bb", 10, 9 * day), entry("cc", 10, 10 * day)];
+        assert_eq!(plan_gc(&entries, 10 * day, Duration::from_secs(2 * 86400), None), vec![0]);
+        assert!(plan_gc(&entries, 10 * day, DEFAULT_MAX_AGE, None).is_empty());
+    }
+
+    #[test]
+    fn test_plan_gc_size_counts_shared_objects_once() {
+        // "aa" is stored once for two entries: 100 + 50 bytes in total
+        let entries = vec![entry("aa", 100, 1), entry("bb", 50, 2), entry("aa", 100, 3)];
+        assert!(plan_gc(&entries, 4, DEFAULT_MAX_AGE, Some(150)).is_empty());
+        // Dropping the first "aa" frees nothing while the third still refers to it
+        assert_eq!(plan_gc(&entries, 4, DEFAULT_MAX_AGE, Some(120)), vec![0, 1]);
+        assert_eq!(plan_gc(&entries, 4, DEFAULT_MAX_AGE, Some(60)), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let entries = vec![entry("abcdef01", 1, 1), entry("abcdef02", 1, 2), entry("123456aa", 1, 3), entry("123456aa", 1, 4)];
+        assert_eq!(resolve(&entries, "123456").unwrap().created, 4);
+        assert!(resolve(&entries, "abcdef").unwrap_err().contains("ambiguous"));
+        assert_eq!(resolve(&entries, "ABCDEF01").unwrap().hash, "abcdef01");
+        assert!(resolve(&entries, "abc").unwrap_err().starts_with("Invalid artifact"));
+        assert!(resolve(&entries, "ffffff").unwrap_err().starts_with("No artifact"));
+    }
+
+    #[test]
+    fn test_artifact_field() {
+        assert_eq!(artifact_field("waitfordownload"), Some(("download", "path")));
+        assert_eq!(artifact_field("recording_restart"), Some(("video", "previousPath")));
+        assert_eq!(artifact_field("state_save"), None);
+    }
+}
@@ -0,0 +1,447 @@
+//! Append-only, hash-chained audit log of commands.
+//!
+//! With --audit, every command sent to the daemon is appended to
+//! `<audit dir>/<session>.jsonl`. Each entry stores the SHA-256 of the previous
+//! entry and its own hash, so editing or removing a line breaks the chain and
+//! is reported by `audit verify`. Secrets and typed text are redacted before writing, and
+//! files produced by screenshot, pdf and download are recorded with a content
+//! hash. The log is also the session history used by `transcript`.
+
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::connection::{app_data_dir, Response};
+
+/// Hash used as `prev` by the first entry of a log
+pub const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Key fragments whose values are never written to the log
+const SECRET_KEYS: &[&str] = &[
+    "password", "passwd", "secret", "token", "auth", "cookie", "credential", "apikey", "api_key",
+];
+
+/// URL parameters that carry credentials besides the ones SECRET_KEYS
+/// catches: OAuth codes, API keys and signatures (`sig`, `X-Amz-Signature`)
+const SECRET_PARAMS: &[&str] = &["code", "key", "sig", "signature"];
+
+/// Actions whose `value`, `text` or `keys` is typed into the page. The CLI
+/// can't tell a password field from any other input, so these are never kept.
+const TYPED_ACTIONS: &[&str] = &["fill", "type", "inserttext", "keyboard", "input_keyboard"];
+
+/// Directory holding audit logs (AGENT_BROWSER_AUDIT_DIR or ~/.agent-browser/audit)
+pub fn audit_dir() -> PathBuf {
+    if let Ok(dir) = env::var("AGENT_BROWSER_AUDIT_DIR") {
+        if !dir.is_empty() {
+            return PathBuf::from(dir);
+        }
+    }
+    app_data_dir().join("audit")
+}
+
+pub fn log_path(session: &str) -> PathBuf {
+    audit_dir().join(format!("{}.jsonl", session))
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Header names use `-` where JSON keys use `_` (X-API-Key, api_key)
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_lowercase().replace('-', "_");
+    SECRET_KEYS.iter().any(|s| key.contains(s))
+}
+
+fn is_secret_param(name: &str) -> bool {
+    let name = name.to_lowercase();
+    is_secret_key(&name)
+        || SECRET_PARAMS.iter().any(|p| {
+            name == *p || name.ends_with(&format!("_{}", p)) || name.ends_with(&format!("-{}", p))
+        })
+}
+
+/// Replace the values of credential-like `name=value` pairs in a query string
+/// or fragment
+fn redact_params(params: &str) -> String {
+    params
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if is_secret_param(name) => format!("{}={}", name, REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Drop `user:password@` and credential-like query and fragment parameters
+/// (`?token=`, `?code=`, `#access_token=`) from URLs
+fn redact_url(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let mut redacted = match rest[..authority_end].rsplit_once('@') {
+        Some((_, host)) => format!("{}://{}@{}", scheme, REDACTED, host),
+        None => format!("{}://{}", scheme, &rest[..authority_end]),
+    };
+    let (before_fragment, fragment) = match rest[authority_end..].split_once('#') {
+        Some((before, fragment)) => (before, Some(fragment)),
+        None => (&rest[authority_end..], None),
+    };
+    match before_fragment.split_once('?') {
+        Some((path, query)) => redacted.push_str(&format!("{}?{}", path, redact_params(query))),
+        None => redacted.push_str(before_fragment),
+    }
+    if let Some(fragment) = fragment {
+        redacted.push_str(&format!("#{}", redact_params(fragment)));
+    }
+    redacted
+}
+
+/// Copy of a command with secret values replaced: any key that looks like a
+/// credential, and text typed by fill, type and keyboard commands (including
+/// the `fill` subaction of find/getby* locators).
+pub fn redact(cmd: &Value) -> Value {
+    match cmd {
+        Value::Object(map) => {
+            let action = map.get("action").and_then(|v| v.as_str()).unwrap_or_default();
+            let subaction = map.get("subaction").and_then(|v| v.as_str());
+            let redacted: Map<String, Value> = map
+                .iter()
+                .map(|(key, value)| {
+                    let typed = match key.as_str() {
+                        "value" => TYPED_ACTIONS.contains(&action) || subaction == Some("fill"),
+                        "text" | "keys" => TYPED_ACTIONS.contains(&action),
+                        _ => false,
+                    };
+                    let value = if is_secret_key(key) || typed {
+                        json!(REDACTED)
+                    } else {
+                        redact(value)
+                    };
+                    (key.clone(), value)
+                })
+                .collect();
+            Value::Object(redacted)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(redact).collect()),
+        Value::String(s) if s.contains("://") => json!(redact_url(s)),
+        other => other.clone(),
+    }
+}
+
+/// Hash of an entry: SHA-256 over its serialized form without the `hash` key
+pub fn entry_hash(entry: &Value) -> String {
+    let mut entry = entry.clone();
+    if let Some(map) = entry.as_object_mut() {
+        map.remove("hash");
+    }
+    sha256_hex(serde_json::to_string(&entry).unwrap_or_default().as_bytes())
+}
+
+/// Files written by the command, with their content hashes
+fn artifacts(resp: &Response) -> Vec<Value> {
+    let Some(path) = resp.data.as_ref().and_then(|d| d.get("path")).and_then(|p| p.as_str()) else {
+        return Vec::new();
+    };
+    match fs::read(path) {
+        Ok(bytes) => vec![json!({ "path": path, "sha256": sha256_hex(&bytes) })],
+        Err(_) => vec![json!({ "path": path, "sha256": null })],
+    }
+}
+
+/// URL and title of the page after the command, when the response reports them
+fn page(resp: &Response) -> Value {
+    let data = resp.data.as_ref();
+    let url = data.and_then(|d| d.get("url")).and_then(|v| v.as_str());
+    let title = data.and_then(|d| d.get("title")).and_then(|v| v.as_str());
+    if url.is_none() && title.is_none() {
+        return Value::Null;
+    }
+    json!({ "url": url.map(redact_url), "title": title })
+}
+
+/// Read all entries of a session's log
+pub fn read_entries(session: &str) -> Result<Vec<Value>, String> {
+    let path = log_path(session);
+    let source = match fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Cannot read audit log '{}': {}", path.display(), e)),
+    };
+    source
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| format!("Audit log line {} is not valid JSON: {}", i + 1, e))
+        })
+        .collect()
+}
+
+/// The last entry of a JSONL log, read back from the end of the file so
+/// appending costs the same however long the log is. Also used for history.
+pub fn last_entry(path: &Path) -> Result<Option<Value>, String> {
+    const CHUNK: u64 = 8192;
+    let cannot_read = |e: std::io::Error| format!("Cannot read log '{}': {}", path.display(), e);
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(cannot_read(e)),
+    };
+    let mut pos = file.metadata().map_err(cannot_read)?.len();
+    let mut tail: Vec<u8> = Vec::new();
+    loop {
+        let end = tail.iter().rposition(|b| !b.is_ascii_whitespace());
+        // The last line is complete once the newline before it has been read
+        let start = match end {
+            Some(end) => tail[..end].iter().rposition(|&b| b == b'\n').map(|i| i + 1),
+            None => None,
+        };
+        if let (Some(end), Some(start)) = (end, start.or((pos == 0).then_some(0))) {
+            return serde_json::from_slice(&tail[start..=end])
+                .map(Some)
+                .map_err(|e| format!("Last entry of '{}' is not valid JSON: {}", path.display(), e));
+        }
+        if pos == 0 {
+            return Ok(None);
+        }
+        let read = CHUNK.min(pos);
+        pos -= read;
+        let mut chunk = vec![0; read as usize];
+        file.seek(SeekFrom::Start(pos)).map_err(cannot_read)?;
+        file.read_exact(&mut chunk).map_err(cannot_read)?;
+        chunk.extend_from_slice(&tail);
+        tail = chunk;
+    }
+}
+
+/// Append an entry for a command and its result
+pub fn record(session: &str, cmd: &Value, result: Result<&Response, &str>) -> Result<(), String> {
+    let path = log_path(session);
+    let (seq, prev) = match last_entry(&path)? {
+        Some(last) => (
+            last.get("seq").and_then(|v| v.as_u64()).unwrap_or(0) + 1,
+            last.get("hash").and_then(|v| v.as_str()).unwrap_or(GENESIS).to_string(),
+        ),
+        None => (1, GENESIS.to_string()),
+    };
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+    let mut command = redact(cmd);
+    if let Some(map) = command.as_object_mut() {
+        map.remove("id");
+    }
+    let (success, error, artifacts, page) = match result {
+        Ok(resp) => (resp.success, resp.error.clone(), artifacts(resp), page(resp)),
+        Err(e) => (false, Some(e.to_string()), Vec::new(), Value::Null),
+    };
+    let mut entry = json!({
+        "seq": seq,
+        "ts": ts,
+        "session": session,
+        "action": cmd.get("action"),
+        "command": command,
+        "success": success,
+        "error": error,
+        "artifacts": artifacts,
+        "page": page,
+        "prev": prev,
+    });
+    entry["hash"] = json!(entry_hash(&entry));
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create audit dir: {}", e))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Cannot open audit log '{}': {}", path.display(), e))?;
+    writeln!(file, "{}", entry).map_err(|e| format!("Cannot write audit log: {}", e))
+}
+
+/// Check that every entry links to the one before it and that its hash
+/// matches its content. Returns the number of entries verified.
+pub fn verify(entries: &[Value]) -> Result<usize, String> {
+    let mut prev = GENESIS.to_string();
+    for (i, entry) in entries.iter().enumerate() {
+        let seq = entry.get("seq").and_then(|v| v.as_u64()).unwrap_or(0);
+        if seq != i as u64 + 1 {
+            return Err(format!("Entry {} has sequence number {} (entries missing or reordered)", i + 1, seq));
+        }
+        if entry.get("prev").and_then(|v| v.as_str()) != Some(prev.as_str()) {
+            return Err(format!("Entry {} does not link to the previous entry", seq));
+        }
+        let hash = entry.get("hash").and_then(|v| v.as_str()).unwrap_or_default();
+        if hash != entry_hash(entry) {
+            return Err(format!("Entry {} was modified (hash mismatch)", seq));
+        }
+        prev = hash.to_string();
+    }
+    Ok(entries.len())
+}
+
+/// Format a Unix timestamp in milliseconds as UTC `YYYY-MM-DD HH:MM:SS`
+pub fn format_timestamp(ms: u64) -> String {
+    let secs = ms / 1000;
+    let (days, rem) = ((secs / 86400) as i64, secs % 86400);
+    // Civil-from-days (Howard Hinnant)
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(count: u64) -> Vec<Value> {
+        let mut prev = GENESIS.to_string();
+        (1..=count)
+            .map(|seq| {
+                let mut entry = json!({ "seq": seq, "action": "click", "prev": prev });
+                entry["hash"] = json!(entry_hash(&entry));
+                prev = entry["hash"].as_str().unwrap().to_string();
+                entry
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_redact() {
+        let cmd = json!({
+            "action": "launch",
+            "proxy": { "server": "http://proxy:8080", "username": "u", "password": "p" },
+            "headers": { "Authorization": "Bearer x", "X-Trace": "1" },
+        });
+        let redacted = redact(&cmd);
+        assert_eq!(redacted["proxy"]["password"], REDACTED);
+        assert_eq!(redacted["proxy"]["username"], "u");
+        assert_eq!(redacted["headers"]["Authorization"], REDACTED);
+        assert_eq!(redacted["headers"]["X-Trace"], "1");
+
+        let cmd = json!({ "action": "navigate", "headers": { "X-API-Key": "k1", "api-key": "k2" } });
+        let redacted = redact(&cmd);
+        assert_eq!(redacted["headers"]["X-API-Key"], REDACTED);
+        assert_eq!(redacted["headers"]["api-key"], REDACTED);
+
+        let nav = json!({ "action": "navigate", "url": "https://bob:pw@example.com/x?y=1" });
+        assert_eq!(redact(&nav)["url"], "https://[REDACTED]@example.com/x?y=1");
+    }
+
+    #[test]
+    fn test_redact_typed_text() {
+        // Any field could be a password field, whatever its selector says
+        let fill = json!({ "action": "fill", "selector": "#pin", "value": "hunter2" });
+        assert_eq!(redact(&fill)["value"], REDACTED);
+        assert_eq!(redact(&fill)["selector"], "#pin");
+        let typed = json!({ "action": "type", "selector": "@e3", "text": "hunter2" });
+        assert_eq!(redact(&typed)["text"], REDACTED);
+        let keys = json!({ "action": "keyboard", "keys": "hunter2" });
+        assert_eq!(redact(&keys)["keys"], REDACTED);
+
+        // Locators stay readable; only the text filled in is dropped
+        let find = json!({ "action": "getbylabel", "label": "PIN", "subaction": "fill", "value": "1234" });
+        assert_eq!(redact(&find)["value"], REDACTED);
+        assert_eq!(redact(&find)["label"], "PIN");
+        let click = json!({ "action": "getbytext", "text": "Sign in", "subaction": "click" });
+        assert_eq!(redact(&click)["text"], "Sign in");
+    }
+
+    #[test]
+    fn test_redact_url_params() {
+        assert_eq!(
+            redact_url("https://app.com/cb?code=abc&state=1#/done"),
+            "https://app.com/cb?code=[REDACTED]&state=1#/done"
+        );
+        assert_eq!(
+            redact_url("https://s3.aws.com/f?X-Amz-Signature=ff&api_key=k&page=2"),
+            "https://s3.aws.com/f?X-Amz-Signature=[REDACTED]&api_key=[REDACTED]&page=2"
+        );
+        assert_eq!(
+            redact_url("https://app.com/#access_token=t&token_type=bearer"),
+            "https://app.com/#access_token=[REDACTED]&token_type=[REDACTED]"
+        );
+        assert_eq!(redact_url("https://app.com/a?sig=1&monkey=2"), "https://app.com/a?sig=[REDACTED]&monkey=2");
+        assert_eq!(redact_url("https://example.com"), "https://example.com");
+    }
+
+    #[test]
+    fn test_last_entry_reads_from_the_end() {
+        let path = env::temp_dir().join(format!("ab-audit-{}.jsonl", std::process::id()));
+        assert_eq!(last_entry(&path), Ok(None));
+        // Lines longer than a read chunk are put back together
+        let long = "x".repeat(20_000);
+        fs::write(&path, format!("{}\n{}\n\n", json!({ "seq": 1 }), json!({ "seq": 2, "html": long })))
+            .unwrap();
+        let last = last_entry(&path).unwrap().unwrap();
+        assert_eq!(last["seq"], 2);
+        fs::write(&path, json!({ "seq": 1 }).to_string()).unwrap();
+        assert_eq!(last_entry(&path).unwrap().unwrap()["seq"], 1);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_verify_chain() {
+        let entries = chain(3);
+        assert_eq!(verify(&entries), Ok(3));
+        assert_eq!(verify(&[]), Ok(0));
+    }
+
+    #[test]
+    fn test_verify_detects_tampering() {
+        let mut edited = chain(3);
+        edited[1]["action"] = json!("navigate");
+        assert!(verify(&edited).unwrap_err().contains("Entry 2 was modified"));
+
+        let mut removed = chain(3);
+        removed.remove(1);
+        assert!(verify(&removed).unwrap_err().contains("missing or reordered"));
+
+        // Rehashing an edited entry still breaks the link from the next one
+        let mut rehashed = chain(3);
+        rehashed[0]["action"] = json!("navigate");
+        rehashed[0]["hash"] = json!(entry_hash(&rehashed[0]));
+        assert!(verify(&rehashed).unwrap_err().contains("Entry 2 does not link"));
+    }
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00");
+        assert_eq!(format_timestamp(1_709_210_096_000), "2024-02-29 12:34:56");
+    }
+}
@@ -0,0 +1,65 @@
+//! Resource-type blocking (`--block images,fonts,media`).
+//!
+//! The types are attached to every command sent to the daemon, which aborts
+//! requests of those types while the command runs and reports how many it
+//! blocked in the response's `meta` (shown with `--output-format v2`).
+
+use serde_json::{json, Value};
+
+/// Resource types as Playwright names them, with the names accepted for each
+const TYPES: &[(&str, &[&str])] = &[
+    ("image", &["image", "images", "img"]),
+    ("font", &["font", "fonts"]),
+    ("media", &["media", "video", "audio"]),
+    ("stylesheet", &["stylesheet", "stylesheets", "css"]),
+    ("script", &["script", "scripts", "js"]),
+];
+
+/// Parse a comma separated list of resource classes
+pub fn parse_resource_types(list: &str) -> Result<Vec<&'static str>, String> {
+    let mut types = Vec::new();
+    for name in list.split(',').map(|t| t.trim().to_lowercase()).filter(|t| !t.is_empty()) {
+        let (kind, _) = TYPES
+            .iter()
+            .find(|(_, names)| names.contains(&name.as_str()))
+            .ok_or_else(|| {
+                format!("Invalid --block type '{}': use images, fonts, media, stylesheets or scripts", name)
+            })?;
+        if !types.contains(kind) {
+            types.push(*kind);
+        }
+    }
+    Ok(types)
+}
+
+/// Attach the types for the daemon to block
+pub fn apply(types: &[&str], cmd: &mut Value) {
+    let action = cmd.get("action").and_then(|v| v.as_str());
+    if types.is_empty() || matches!(action, Some("launch") | Some("close")) {
+        return;
+    }
+    cmd["block"] = json!(types);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resource_types() {
+        assert_eq!(parse_resource_types("images, Fonts,media,img"), Ok(vec!["image", "font", "media"]));
+        assert_eq!(parse_resource_types("css,js"), Ok(vec!["stylesheet", "script"]));
+        assert!(parse_resource_types("images,iframes").is_err());
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut cmd = json!({ "action": "navigate", "url": "https://example.com" });
+        apply(&["image", "font"], &mut cmd);
+        assert_eq!(cmd["block"], json!(["image", "font"]));
+
+        let mut close = json!({ "action": "close" });
+        apply(&["image"], &mut close);
+        assert!(close.get("block").is_none());
+    }
+}
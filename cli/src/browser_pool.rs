@@ -0,0 +1,60 @@
+//! Remote browser pools.
+//!
+//! --pool acquires the session's browser from a pool instead of launching one:
+//! http(s):// URLs are a Selenium Grid 4 hub, where the daemon creates a
+//! WebDriver session and drives it over the grid's CDP endpoint, and ws(s)://
+//! URLs are a CDP pool such as browserless, where each connection gets its
+//! own browser. --pool-capabilities is a JSON object of W3C capabilities (or
+//! the pool's launch options) merged over the ones derived from --headed and
+//! --args. The browser goes back to the pool when the session closes.
+
+use serde_json::{json, Value};
+
+/// The pool as sent with the launch command
+pub fn parse(url: Option<&str>, capabilities: Option<&str>) -> Result<Option<Value>, String> {
+    let Some(url) = url else {
+        return match capabilities {
+            Some(_) => Err("--pool-capabilities requires --pool".to_string()),
+            None => Ok(None),
+        };
+    };
+    let host = ["ws://", "wss://", "http://", "https://"]
+        .iter()
+        .find_map(|scheme| url.strip_prefix(scheme));
+    if host.is_none_or(|h| h.is_empty()) {
+        return Err(format!("Invalid --pool '{}': use a ws(s):// CDP pool or http(s):// Selenium Grid URL", url));
+    }
+    let mut pool = json!({ "url": url });
+    if let Some(capabilities) = capabilities {
+        match serde_json::from_str::<Value>(capabilities) {
+            Ok(caps @ Value::Object(_)) => pool["capabilities"] = caps,
+            Ok(_) => return Err("--pool-capabilities must be a JSON object".to_string()),
+            Err(e) => return Err(format!("Invalid --pool-capabilities JSON: {}", e)),
+        }
+    }
+    Ok(Some(pool))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(None, None), Ok(None));
+        assert_eq!(
+            parse(Some("http://grid:4444"), Some(r#"{"browserVersion":"131"}"#)),
+            Ok(Some(json!({ "url": "http://grid:4444", "capabilities": { "browserVersion": "131" } })))
+        );
+        assert_eq!(parse(Some("wss://pool.example?token=x"), None), Ok(Some(json!({ "url": "wss://pool.example?token=x" }))));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse(Some("grid:4444"), None).unwrap_err().starts_with("Invalid --pool"));
+        assert!(parse(Some("ws://"), None).is_err());
+        assert!(parse(Some("ws://pool"), Some("[]")).unwrap_err().contains("JSON object"));
+        assert!(parse(Some("ws://pool"), Some("{")).unwrap_err().starts_with("Invalid --pool-capabilities"));
+        assert!(parse(None, Some("{}")).unwrap_err().contains("requires --pool"));
+    }
+}
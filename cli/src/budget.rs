@@ -0,0 +1,106 @@
+//! Network budgets.
+//!
+//! --max-bytes and --max-requests are attached to every command sent to the
+//! daemon, which counts the requests and response bytes of the browser while
+//! the command runs. Past a limit further requests are aborted and the
+//! command fails with `"type":"budget_exceeded"`. With --budget-scope session
+//! the counts carry over between commands instead of starting at zero.
+
+use serde_json::{json, Value};
+
+#[derive(Debug, PartialEq)]
+pub struct Budget {
+    pub max_bytes: Option<u64>,
+    pub max_requests: Option<u64>,
+    pub session: bool,
+}
+
+/// Parse a size such as "50MB", "1.5GB", "800KB" or "1024" (bytes) given to `flag`
+pub fn parse_size(flag: &str, value: &str) -> Result<u64, String> {
+    let lower = value.trim().to_ascii_lowercase();
+    let split = lower.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(lower.len());
+    let (number, unit) = lower.split_at(split);
+    let multiplier: u64 = match unit.trim() {
+        "" | "b" => 1,
+        "k" | "kb" => 1024,
+        "m" | "mb" => 1024 * 1024,
+        "g" | "gb" => 1024 * 1024 * 1024,
+        _ => 0,
+    };
+    match number.parse::<f64>() {
+        Ok(n) if n > 0.0 && n.is_finite() && multiplier > 0 => Ok((n * multiplier as f64) as u64),
+        _ => Err(format!("Invalid {} '{}': expected a size like 50MB, 800KB or 1GB", flag, value)),
+    }
+}
+
+impl Budget {
+    /// `None` when neither limit is set
+    pub fn from_flags(
+        max_bytes: Option<&str>,
+        max_requests: Option<&str>,
+        scope: Option<&str>,
+    ) -> Result<Option<Self>, String> {
+        let max_requests = max_requests
+            .map(|n| match n.trim().parse::<u64>() {
+                Ok(n) if n > 0 => Ok(n),
+                _ => Err(format!("Invalid --max-requests '{}': expected a number greater than 0", n)),
+            })
+            .transpose()?;
+        let session = match scope {
+            None | Some("command") => false,
+            Some("session") => true,
+            Some(other) => {
+                return Err(format!("Invalid --budget-scope '{}': use command or session", other))
+            }
+        };
+        let budget = Budget { max_bytes: max_bytes.map(|v| parse_size("--max-bytes", v)).transpose()?, max_requests, session };
+        Ok((budget.max_bytes.is_some() || budget.max_requests.is_some()).then_some(budget))
+    }
+
+    /// Attach the limits for the daemon to enforce
+    pub fn apply(&self, cmd: &mut Value) {
+        if matches!(cmd.get("action").and_then(|v| v.as_str()), Some("launch") | Some("close")) {
+            return;
+        }
+        let mut budget = json!({ "scope": if self.session { "session" } else { "command" } });
+        if let Some(max) = self.max_bytes {
+            budget["maxBytes"] = json!(max);
+        }
+        if let Some(max) = self.max_requests {
+            budget["maxRequests"] = json!(max);
+        }
+        cmd["budget"] = budget;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("--max-bytes", "50MB"), Ok(50 * 1024 * 1024));
+        assert_eq!(parse_size("--max-bytes", "1.5gb"), Ok(1536 * 1024 * 1024));
+        assert_eq!(parse_size("--max-bytes", "800 KB"), Ok(800 * 1024));
+        assert_eq!(parse_size("--max-bytes", "4096"), Ok(4096));
+        assert!(parse_size("--max-bytes", "0MB").is_err());
+        assert!(parse_size("--max-bytes", "50TB").is_err());
+        assert!(parse_size("--max-bytes", "MB").is_err());
+    }
+
+    #[test]
+    fn test_budget_is_attached() {
+        assert_eq!(Budget::from_flags(None, None, Some("session")), Ok(None));
+        assert!(Budget::from_flags(None, Some("0"), None).is_err());
+        assert!(Budget::from_flags(None, Some("5"), Some("tab")).is_err());
+
+        let budget = Budget::from_flags(Some("50MB"), Some("500"), None).unwrap().unwrap();
+        let mut cmd = json!({ "action": "navigate", "url": "https://example.com" });
+        budget.apply(&mut cmd);
+        assert_eq!(cmd["budget"], json!({ "scope": "command", "maxBytes": 52428800, "maxRequests": 500 }));
+
+        let mut launch = json!({ "action": "launch" });
+        budget.apply(&mut launch);
+        assert!(launch.get("budget").is_none());
+    }
+}
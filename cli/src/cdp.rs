@@ -0,0 +1,236 @@
+//! Parsing and resolution of --cdp endpoints.
+//!
+//! --cdp accepts a port (`9222`), `host:port`, a `ws://`/`wss://` URL, an http
+//! URL, or `auto`. An http URL ending in `/json/version` and `auto` are
+//! resolved here to the browser's websocket URL: `auto` asks the common remote
+//! debugging ports on localhost for `/json/version` and uses the first browser
+//! that answers.
+
+use serde_json::{json, Value};
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Ports tried by `--cdp auto`, in order
+pub const DISCOVERY_PORTS: &[u16] = &[9222, 9223, 9224, 9229, 9333];
+
+#[derive(Debug, PartialEq)]
+pub enum Endpoint {
+    Port(u16),
+    /// Passed to the daemon as-is (ws://, wss://, http:// or https://)
+    Url(String),
+    /// An http `/json/version` URL to read the websocket URL from
+    Version(String),
+    Auto,
+}
+
+pub fn parse_endpoint(value: &str) -> Result<Endpoint, String> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("auto") {
+        return Ok(Endpoint::Auto);
+    }
+    if value.chars().all(|c| c.is_ascii_digit()) && !value.is_empty() {
+        return match value.parse::<u32>() {
+            Ok(0) => Err("Invalid CDP port: port must be greater than 0".to_string()),
+            Ok(p) if p > 65535 => Err(format!(
+                "Invalid CDP port: {} is out of range (valid range: 1-65535)",
+                p
+            )),
+            Ok(p) => Ok(Endpoint::Port(p as u16)),
+            Err(_) => Err(format!("Invalid CDP port: {}", value)),
+        };
+    }
+    let lower = value.to_ascii_lowercase();
+    for scheme in ["ws://", "wss://", "http://", "https://"] {
+        if let Some(rest) = lower.strip_prefix(scheme) {
+            let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+            split_host_port(authority).map_err(|e| format!("Invalid CDP URL '{}': {}", value, e))?;
+            let path = &rest[authority.len()..];
+            let is_version = path.trim_end_matches('/').ends_with("/json/version");
+            return Ok(if scheme.starts_with("http") && is_version {
+                Endpoint::Version(value.to_string())
+            } else {
+                Endpoint::Url(value.to_string())
+            });
+        }
+    }
+    if value.contains(':') && !value.contains('/') {
+        split_host_port(value).map_err(|e| format!("Invalid CDP value '{}': {}", value, e))?;
+        return Ok(Endpoint::Url(format!("http://{}", value)));
+    }
+    Err(format!(
+        "Invalid CDP value: '{}' is not a port, host:port, ws:// URL, http endpoint or 'auto'",
+        value
+    ))
+}
+
+/// Split `host[:port]` (ignoring any userinfo), checking the host is present
+/// and the port is in range
+fn split_host_port(authority: &str) -> Result<(&str, Option<u16>), String> {
+    let authority = authority.rsplit_once('@').map(|(_, a)| a).unwrap_or(authority);
+    let (host, port) = if authority.starts_with('[') {
+        match authority.find(']') {
+            Some(close) => (&authority[..=close], authority[close + 1..].strip_prefix(':')),
+            None => return Err("unclosed '[' in host".to_string()),
+        }
+    } else {
+        match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        }
+    };
+    if host.is_empty() {
+        return Err("missing host".to_string());
+    }
+    let port = match port {
+        Some(port) => Some(
+            port.parse::<u16>()
+                .ok()
+                .filter(|p| *p > 0)
+                .ok_or_else(|| format!("invalid port '{}'", port))?,
+        ),
+        None => None,
+    };
+    Ok((host, port))
+}
+
+/// Launch command fields (`cdpPort` or `cdpUrl`) for an endpoint, resolving
+/// `/json/version` URLs and `auto` to a websocket URL
+pub fn launch_fields(endpoint: &Endpoint) -> Result<Value, String> {
+    match endpoint {
+        Endpoint::Port(port) => Ok(json!({ "cdpPort": port })),
+        Endpoint::Url(url) => Ok(json!({ "cdpUrl": url })),
+        Endpoint::Version(url) => {
+            let Some(rest) = url.get(..7).filter(|s| s.eq_ignore_ascii_case("http://")).map(|_| &url[7..]) else {
+                return Err(format!(
+                    "Cannot resolve {}: only http:// /json/version endpoints are supported; pass the ws:// URL instead",
+                    url
+                ));
+            };
+            let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+            let (host, port) = split_host_port(authority)?;
+            let ws = fetch_ws_url(host, port.unwrap_or(80), path, Duration::from_secs(5))
+                .map_err(|e| format!("Cannot resolve {}: {}", url, e))?;
+            Ok(json!({ "cdpUrl": ws }))
+        }
+        Endpoint::Auto => discover(DISCOVERY_PORTS)
+            .map(|ws| json!({ "cdpUrl": ws }))
+            .ok_or_else(|| {
+                let ports: Vec<String> = DISCOVERY_PORTS.iter().map(|p| p.to_string()).collect();
+                format!(
+                    "No browser with remote debugging found on localhost ports {}. \
+Start Chrome with --remote-debugging-port=9222",
+                    ports.join(", ")
+                )
+            }),
+    }
+}
+
+/// Websocket URL of the first browser answering on one of `ports` on localhost
+pub fn discover(ports: &[u16]) -> Option<String> {
+    ports
+        .iter()
+        .find_map(|port| fetch_ws_url("127.0.0.1", *port, "/json/version", Duration::from_millis(300)).ok())
+}
+
+/// GET `path` from an http server and return `webSocketDebuggerUrl` from the
+/// JSON body
+pub fn fetch_ws_url(host: &str, port: u16, path: &str, timeout: Duration) -> Result<String, String> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addr = (host, port)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| format!("cannot resolve host '{}'", host))?;
+    let mut stream = TcpStream::connect_timeout(&addr, timeout).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(timeout)).ok();
+    stream.set_write_timeout(Some(timeout)).ok();
+    let path = if path.is_empty() { "/json/version" } else { path };
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}:{}\r\nAccept: application/json\r\nConnection: close\r\n\r\n",
+        path, host, port
+    );
+    stream.write_all(request.as_bytes()).map_err(|e| e.to_string())?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).map_err(|e| e.to_string())?;
+    parse_version_response(&String::from_utf8_lossy(&response))
+}
+
+/// Extract `webSocketDebuggerUrl` from a raw HTTP response to /json/version
+pub fn parse_version_response(response: &str) -> Result<String, String> {
+    let (head, body) = response.split_once("\r\n\r\n").ok_or("malformed HTTP response")?;
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if status != "200" {
+        return Err(format!("HTTP status {}", status));
+    }
+    let version: Value = serde_json::from_str(body.trim()).map_err(|_| "response is not JSON".to_string())?;
+    version
+        .get("webSocketDebuggerUrl")
+        .and_then(|v| v.as_str())
+        .map(str::to_string)
+        .ok_or_else(|| "response has no webSocketDebuggerUrl".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_parse_endpoint() {
+        assert_eq!(parse_endpoint("9222"), Ok(Endpoint::Port(9222)));
+        assert_eq!(parse_endpoint("auto"), Ok(Endpoint::Auto));
+        assert_eq!(
+            parse_endpoint("ws://localhost:9222/devtools/browser/abc"),
+            Ok(Endpoint::Url("ws://localhost:9222/devtools/browser/abc".to_string()))
+        );
+        assert_eq!(
+            parse_endpoint("10.0.0.5:9222"),
+            Ok(Endpoint::Url("http://10.0.0.5:9222".to_string()))
+        );
+        assert_eq!(
+            parse_endpoint("http://127.0.0.1:9222/json/version"),
+            Ok(Endpoint::Version("http://127.0.0.1:9222/json/version".to_string()))
+        );
+        assert_eq!(
+            parse_endpoint("http://localhost:9222"),
+            Ok(Endpoint::Url("http://localhost:9222".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_endpoint_invalid() {
+        assert!(parse_endpoint("0").unwrap_err().contains("greater than 0"));
+        assert!(parse_endpoint("70000").unwrap_err().contains("out of range"));
+        assert!(parse_endpoint("ws://").unwrap_err().contains("missing host"));
+        assert!(parse_endpoint("localhost:abc").unwrap_err().contains("invalid port"));
+        assert!(parse_endpoint("chrome").unwrap_err().contains("'auto'"));
+    }
+
+    #[test]
+    fn test_parse_version_response() {
+        let ok = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{\"Browser\":\"Chrome/126\",\"webSocketDebuggerUrl\":\"ws://127.0.0.1:9222/devtools/browser/x\"}";
+        assert_eq!(parse_version_response(ok).unwrap(), "ws://127.0.0.1:9222/devtools/browser/x");
+        assert_eq!(parse_version_response("HTTP/1.1 404 Not Found\r\n\r\n").unwrap_err(), "HTTP status 404");
+        assert!(parse_version_response("HTTP/1.1 200 OK\r\n\r\n{}").is_err());
+    }
+
+    #[test]
+    fn test_resolve_version_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let n = stream.read(&mut request).unwrap();
+            assert!(String::from_utf8_lossy(&request[..n]).starts_with("GET /json/version "));
+            let body = format!("{{\"webSocketDebuggerUrl\":\"ws://127.0.0.1:{}/devtools/browser/x\"}}", port);
+            write!(stream, "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        });
+        let endpoint = parse_endpoint(&format!("http://127.0.0.1:{}/json/version", port)).unwrap();
+        let fields = launch_fields(&endpoint).unwrap();
+        assert_eq!(fields["cdpUrl"], format!("ws://127.0.0.1:{}/devtools/browser/x", port));
+        server.join().unwrap();
+    }
+}
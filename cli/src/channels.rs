@@ -0,0 +1,136 @@
+//! System browser discovery for --channel.
+//!
+//! Each channel has the install locations its official packages use on Linux,
+//! macOS and Windows, plus the command names it installs on PATH. The first
+//! existing one is launched through --executable-path. `env` lists every
+//! channel found with its version.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+pub const CHANNELS: &[&str] = &["chrome", "chrome-beta", "edge", "chromium"];
+
+/// Absolute install locations for a channel on this OS
+fn install_paths(channel: &str) -> Vec<PathBuf> {
+    if cfg!(target_os = "macos") {
+        let app = match channel {
+            "chrome" => "Google Chrome.app/Contents/MacOS/Google Chrome",
+            "chrome-beta" => "Google Chrome Beta.app/Contents/MacOS/Google Chrome Beta",
+            "edge" => "Microsoft Edge.app/Contents/MacOS/Microsoft Edge",
+            _ => "Chromium.app/Contents/MacOS/Chromium",
+        };
+        let mut roots = vec![PathBuf::from("/Applications")];
+        roots.extend(dirs::home_dir().map(|home| home.join("Applications")));
+        roots.into_iter().map(|root| root.join(app)).collect()
+    } else if cfg!(windows) {
+        let exe = match channel {
+            "chrome" => r"Google\Chrome\Application\chrome.exe",
+            "chrome-beta" => r"Google\Chrome Beta\Application\chrome.exe",
+            "edge" => r"Microsoft\Edge\Application\msedge.exe",
+            _ => r"Chromium\Application\chrome.exe",
+        };
+        ["LOCALAPPDATA", "PROGRAMFILES", "PROGRAMFILES(X86)"]
+            .iter()
+            .filter_map(env::var_os)
+            .map(|root| PathBuf::from(root).join(exe))
+            .collect()
+    } else {
+        let paths: &[&str] = match channel {
+            "chrome" => &["/opt/google/chrome/chrome"],
+            "chrome-beta" => &["/opt/google/chrome-beta/chrome"],
+            "edge" => &["/opt/microsoft/msedge/msedge"],
+            _ => &["/usr/lib/chromium/chromium", "/snap/bin/chromium"],
+        };
+        paths.iter().map(PathBuf::from).collect()
+    }
+}
+
+/// Commands a channel's packages put on PATH
+fn command_names(channel: &str) -> &'static [&'static str] {
+    match channel {
+        "chrome" => &["google-chrome-stable", "google-chrome"],
+        "chrome-beta" => &["google-chrome-beta"],
+        "edge" => &["microsoft-edge-stable", "microsoft-edge"],
+        _ => &["chromium", "chromium-browser"],
+    }
+}
+
+fn find_on_path(name: &str) -> Option<PathBuf> {
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Executable of an installed channel
+pub fn find(channel: &str) -> Option<PathBuf> {
+    install_paths(channel)
+        .into_iter()
+        .find(|path| path.is_file())
+        .or_else(|| command_names(channel).iter().find_map(|name| find_on_path(name)))
+}
+
+/// First dotted version number in `text`, e.g. "131.0.6778.85"
+pub fn parse_version(text: &str) -> Option<String> {
+    text.split(|c: char| !c.is_ascii_digit() && c != '.')
+        .find(|word| word.split('.').count() >= 3 && word.split('.').all(|n| !n.is_empty()))
+        .map(str::to_string)
+}
+
+/// Version of a browser executable. `--version` prints nothing on Windows,
+/// where the install keeps a directory named after the version instead.
+pub fn version(executable: &Path) -> Option<String> {
+    let output = Command::new(executable)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok();
+    output
+        .and_then(|out| parse_version(&String::from_utf8_lossy(&out.stdout)))
+        .or_else(|| {
+            let entries = executable.parent()?.read_dir().ok()?;
+            entries
+                .flatten()
+                .filter_map(|entry| parse_version(&entry.file_name().to_string_lossy()))
+                .max_by_key(|v| v.split('.').map(|n| n.parse::<u32>().unwrap_or(0)).collect::<Vec<_>>())
+        })
+}
+
+/// Executable for --channel, or an error naming the channels that are installed
+pub fn resolve(channel: &str) -> Result<PathBuf, String> {
+    if !CHANNELS.contains(&channel) {
+        return Err(format!("Invalid --channel '{}': use {}", channel, CHANNELS.join(", ")));
+    }
+    find(channel).ok_or_else(|| {
+        let installed: Vec<&str> = CHANNELS.iter().copied().filter(|c| find(c).is_some()).collect();
+        if installed.is_empty() {
+            format!("{} is not installed, and no other browser channel was found", channel)
+        } else {
+            format!("{} is not installed (found: {})", channel, installed.join(", "))
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("Google Chrome 131.0.6778.85 \n").as_deref(), Some("131.0.6778.85"));
+        assert_eq!(parse_version("Chromium 120.0.6099.224 built on Debian 12").as_deref(), Some("120.0.6099.224"));
+        assert_eq!(parse_version("131.0.2903.70").as_deref(), Some("131.0.2903.70"));
+        assert_eq!(parse_version("chrome.exe"), None);
+        assert_eq!(parse_version("Locales"), None);
+    }
+
+    #[test]
+    fn test_resolve_rejects_unknown_channels() {
+        assert!(resolve("firefox").unwrap_err().contains("chrome, chrome-beta, edge, chromium"));
+        for channel in CHANNELS {
+            assert!(!install_paths(channel).is_empty());
+            assert!(!command_names(channel).is_empty());
+        }
+    }
+}
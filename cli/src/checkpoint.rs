@@ -0,0 +1,221 @@
+//! Named checkpoints of a session's browser state.
+//!
+//! `checkpoint save <name>` records the page URL, cookies, the page origin's
+//! localStorage and sessionStorage, and the scroll position using existing
+//! daemon actions (url, cookies_get, evaluate). `checkpoint restore <name>`
+//! replays them: cookies are replaced, the URL is reopened, storage is written
+//! back and the page reloaded so its scripts see the restored state, then the
+//! scroll position is applied.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::commands::gen_id;
+use crate::connection::app_data_dir;
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    pub name: String,
+    pub url: String,
+    pub cookies: Vec<Value>,
+    pub local_storage: Map<String, Value>,
+    pub session_storage: Map<String, Value>,
+    pub scroll_x: f64,
+    pub scroll_y: f64,
+    /// Unix time in milliseconds
+    pub created: u64,
+}
+
+/// Expression evaluated in the page to read storage and scroll position
+pub const CAPTURE_SCRIPT: &str = "({ local: Object.fromEntries(Object.entries(localStorage)), \
+session: Object.fromEntries(Object.entries(sessionStorage)), scrollX: window.scrollX, scrollY: window.scrollY })";
+
+/// Directory holding a session's checkpoints (under AGENT_BROWSER_CHECKPOINT_DIR
+/// or ~/.agent-browser/checkpoints)
+pub fn checkpoint_dir(session: &str) -> PathBuf {
+    let base = match env::var("AGENT_BROWSER_CHECKPOINT_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => app_data_dir().join("checkpoints"),
+    };
+    base.join(session)
+}
+
+/// Checkpoint names become file names, so keep them to a safe character set
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        && !name.starts_with('.');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid checkpoint name '{}': use letters, digits, '-', '_' and '.'",
+            name
+        ))
+    }
+}
+
+fn checkpoint_path(session: &str, name: &str) -> PathBuf {
+    checkpoint_dir(session).join(format!("{}.json", name))
+}
+
+impl Checkpoint {
+    /// Build a checkpoint from the responses to `url`, `cookies_get` and the
+    /// evaluation of CAPTURE_SCRIPT
+    pub fn from_page(name: &str, url: &str, cookies: &Value, captured: &Value, created: u64) -> Self {
+        let object = |key: &str| captured.get(key).and_then(|v| v.as_object()).cloned().unwrap_or_default();
+        let number = |key: &str| captured.get(key).and_then(|v| v.as_f64()).unwrap_or(0.0);
+        Checkpoint {
+            name: name.to_string(),
+            url: url.to_string(),
+            cookies: cookies.as_array().cloned().unwrap_or_default(),
+            local_storage: object("local"),
+            session_storage: object("session"),
+            scroll_x: number("scrollX"),
+            scroll_y: number("scrollY"),
+            created,
+        }
+    }
+
+    pub fn save(&self, session: &str) -> Result<PathBuf, String> {
+        let path = checkpoint_path(session, &self.name);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Cannot create checkpoint dir: {}", e))?;
+        }
+        let source = serde_json::to_string_pretty(self).unwrap_or_default();
+        fs::write(&path, source).map_err(|e| format!("Cannot write checkpoint: {}", e))?;
+        Ok(path)
+    }
+
+    pub fn load(session: &str, name: &str) -> Result<Self, String> {
+        let path = checkpoint_path(session, name);
+        let source = fs::read_to_string(&path)
+            .map_err(|_| format!("No checkpoint named '{}' in session {}", name, session))?;
+        serde_json::from_str(&source).map_err(|e| format!("Invalid checkpoint '{}': {}", name, e))
+    }
+
+    /// Daemon commands that bring the browser back to this checkpoint, in order
+    pub fn restore_commands(&self) -> Vec<Value> {
+        // Session cookies are reported with expires -1, which can't be set back
+        let cookies: Vec<Value> = self
+            .cookies
+            .iter()
+            .cloned()
+            .map(|mut cookie| {
+                if cookie.get("expires").and_then(|v| v.as_f64()).is_some_and(|e| e < 0.0) {
+                    if let Some(map) = cookie.as_object_mut() {
+                        map.remove("expires");
+                    }
+                }
+                cookie
+            })
+            .collect();
+        let storage = json!({ "local": self.local_storage, "session": self.session_storage });
+        let restore_storage = format!(
+            "(() => {{ const s = {}; localStorage.clear(); sessionStorage.clear(); \
+for (const [k, v] of Object.entries(s.local)) localStorage.setItem(k, v); \
+for (const [k, v] of Object.entries(s.session)) sessionStorage.setItem(k, v); return true; }})()",
+            storage
+        );
+
+        let mut commands = vec![json!({ "id": gen_id(), "action": "cookies_clear" })];
+        if !cookies.is_empty() {
+            commands.push(json!({ "id": gen_id(), "action": "cookies_set", "cookies": cookies }));
+        }
+        commands.push(json!({ "id": gen_id(), "action": "navigate", "url": self.url }));
+        commands.push(json!({ "id": gen_id(), "action": "evaluate", "script": restore_storage }));
+        commands.push(json!({ "id": gen_id(), "action": "reload" }));
+        commands.push(json!({
+            "id": gen_id(),
+            "action": "evaluate",
+            "script": format!("window.scrollTo({}, {})", self.scroll_x, self.scroll_y),
+        }));
+        commands
+    }
+}
+
+/// Names of a session's checkpoints, sorted
+pub fn list(session: &str) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(checkpoint_dir(session))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            name.strip_suffix(".json").map(str::to_string)
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+pub fn delete(session: &str, name: &str) -> Result<(), String> {
+    fs::remove_file(checkpoint_path(session, name))
+        .map_err(|_| format!("No checkpoint named '{}' in session {}", name, session))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cart() -> Checkpoint {
+        let cookies = json!([
+            { "name": "sid", "value": "abc", "domain": "shop.example.com", "path": "/", "expires": -1 },
+            { "name": "pref", "value": "eu", "domain": "shop.example.com", "path": "/", "expires": 1900000000 },
+        ]);
+        let captured = json!({
+            "local": { "cart": "[\"sku-1\"]" },
+            "session": { "step": "2" },
+            "scrollX": 0,
+            "scrollY": 640.5,
+        });
+        Checkpoint::from_page("cart", "https://shop.example.com/cart", &cookies, &captured, 1)
+    }
+
+    #[test]
+    fn test_from_page() {
+        let checkpoint = cart();
+        assert_eq!(checkpoint.cookies.len(), 2);
+        assert_eq!(checkpoint.local_storage["cart"], "[\"sku-1\"]");
+        assert_eq!(checkpoint.session_storage["step"], "2");
+        assert_eq!(checkpoint.scroll_y, 640.5);
+    }
+
+    #[test]
+    fn test_restore_commands() {
+        let commands = cart().restore_commands();
+        let actions: Vec<&str> = commands.iter().map(|c| c["action"].as_str().unwrap()).collect();
+        assert_eq!(
+            actions,
+            vec!["cookies_clear", "cookies_set", "navigate", "evaluate", "reload", "evaluate"]
+        );
+        // Session cookies lose their -1 expiry, persistent ones keep it
+        assert!(commands[1]["cookies"][0].get("expires").is_none());
+        assert_eq!(commands[1]["cookies"][1]["expires"], 1900000000);
+        assert_eq!(commands[2]["url"], "https://shop.example.com/cart");
+        let script = commands[3]["script"].as_str().unwrap();
+        assert!(script.contains(r#""cart":"[\"sku-1\"]""#));
+        assert_eq!(commands[5]["script"], "window.scrollTo(0, 640.5)");
+    }
+
+    #[test]
+    fn test_roundtrip_json() {
+        let checkpoint = cart();
+        let source = serde_json::to_string(&checkpoint).unwrap();
+        assert!(source.contains("\"localStorage\""));
+        assert_eq!(serde_json::from_str::<Checkpoint>(&source).unwrap(), checkpoint);
+    }
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("checkout-a").is_ok());
+        assert!(validate_name("v1.2_b").is_ok());
+        assert!(validate_name("../x").is_err());
+        assert!(validate_name(".hidden").is_err());
+        assert!(validate_name("").is_err());
+    }
+}
@@ -1,7 +1,11 @@
 use serde_json::{json, Value};
 
-use crate::flags::Flags;
-use crate::validation::{is_valid_session_name, session_name_error};
+use crate::flags::{parse_header_line, Flags};
+use crate::janitor;
+use crate::language;
+use crate::ocr;
+use crate::pdf;
+use crate::validation::{is_valid_session_name, normalize_url, session_name_error};
 
 /// Error type for command parsing with contextual information
 #[derive(Debug)]
@@ -56,6 +60,18 @@ impl ParseError {
     }
 }
 
+/// Selectors a command will act on, for checking that they exist before running it.
+/// `wait` is skipped since its selector is expected to appear later.
+pub fn command_selectors(cmd: &Value) -> Vec<&str> {
+    if cmd.get("action").and_then(|v| v.as_str()) == Some("wait") {
+        return Vec::new();
+    }
+    ["selector", "source", "target"]
+        .iter()
+        .filter_map(|key| cmd.get(*key).and_then(|v| v.as_str()))
+        .collect()
+}
+
 pub fn gen_id() -> String {
     format!(
         "r{}",
@@ -82,99 +98,319 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
     match cmd {
         // === Navigation ===
         "open" | "goto" | "navigate" => {
-            let url = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            // Client-side route change: open --spa "#/settings" or open --spa /settings
+            if let Some(idx) = rest.iter().position(|&s| s == "--spa") {
+                let route = rest.get(idx + 1).ok_or_else(|| ParseError::MissingArguments {
+                    context: "open --spa".to_string(),
+                    usage: "open --spa <#hash|/path>",
+                })?;
+                let mode = if route.starts_with('#') { "hash" } else { "history" };
+                return Ok(json!({ "id": id, "action": "spanavigate", "route": route, "mode": mode }));
+            }
+            const USAGE: &str =
+                "open <url> [--wait-until <state>] [--referer <url>] [--new-tab]";
+            const WAIT_STATES: &[&str] = &["load", "domcontentloaded", "networkidle", "commit"];
+            let mut url: Option<&str> = None;
+            let mut wait_until: Option<&str> = None;
+            let mut referer: Option<&str> = None;
+            let mut new_tab = false;
+            let mut i = 0;
+            while i < rest.len() {
+                match rest[i] {
+                    "--wait-until" => {
+                        let state = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                            context: "open --wait-until".to_string(),
+                            usage: USAGE,
+                        })?;
+                        if !WAIT_STATES.contains(state) {
+                            return Err(ParseError::InvalidValue {
+                                message: format!(
+                                    "Invalid --wait-until value '{}'. Valid options: {}",
+                                    state,
+                                    WAIT_STATES.join(", ")
+                                ),
+                                usage: USAGE,
+                            });
+                        }
+                        wait_until = Some(state);
+                        i += 1;
+                    }
+                    "--referer" => {
+                        let r = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                            context: "open --referer".to_string(),
+                            usage: USAGE,
+                        })?;
+                        referer = Some(r);
+                        i += 1;
+                    }
+                    "--new-tab" => new_tab = true,
+                    arg if url.is_none() && !arg.starts_with("--") => url = Some(arg),
+                    _ => {}
+                }
+                i += 1;
+            }
+            let url = url.ok_or_else(|| ParseError::MissingArguments {
                 context: cmd.to_string(),
-                usage: "open <url>",
+                usage: USAGE,
             })?;
-            let url_lower = url.to_lowercase();
-            let url = if url_lower.starts_with("http://") 
-                || url_lower.starts_with("https://")
-                || url_lower.starts_with("about:") 
-                || url_lower.starts_with("data:") 
-                || url_lower.starts_with("file:") {
-                url.to_string()
-            } else {
-                format!("https://{}", url)
-            };
+            let url = normalize_url(url, flags.allow_unsafe_schemes)
+                .map_err(|message| ParseError::InvalidValue { message, usage: USAGE })?;
             let mut nav_cmd = json!({ "id": id, "action": "navigate", "url": url });
-            // If --headers flag is set, include headers (scoped to this origin)
-            if let Some(ref headers_json) = flags.headers {
-                if let Ok(headers) = serde_json::from_str::<serde_json::Value>(headers_json) {
-                    nav_cmd["headers"] = headers;
+            if let Some(state) = wait_until {
+                nav_cmd["waitUntil"] = json!(state);
+            }
+            if let Some(r) = referer {
+                nav_cmd["referer"] = json!(r);
+            }
+            if new_tab {
+                nav_cmd["newTab"] = json!(true);
+            }
+            // If --headers flag is set, include headers (scoped to this origin).
+            // Given after the command (`open <url> --headers ...`) they only apply
+            // to this navigation and leave the session's persistent headers alone.
+            let mut headers = flags
+                .headers
+                .as_ref()
+                .and_then(|h| serde_json::from_str::<serde_json::Value>(h).ok())
+                .filter(|h| h.is_object());
+            // Repeated --header "Name: value" flags are merged on top of --headers
+            for header in &flags.header {
+                let (name, value) =
+                    parse_header_line(header).map_err(|message| ParseError::InvalidValue {
+                        message,
+                        usage: "--header \"Name: value\"",
+                    })?;
+                headers.get_or_insert_with(|| json!({}))[name] = json!(value);
+            }
+            if let Some(headers) = headers {
+                nav_cmd["headers"] = headers;
+                if flags.headers_navigation_only {
+                    nav_cmd["headersScope"] = json!("navigation");
+                }
+            }
+            // --headers-for entries are only attached to requests for their own origin
+            if !flags.headers_for.is_empty() {
+                nav_cmd["originHeaders"] = parse_origin_headers(&flags.headers_for)?;
+            }
+            Ok(nav_cmd)
+        }
+        "back" | "forward" => {
+            let usage = if cmd == "back" { "back [steps]" } else { "forward [steps]" };
+            let mut nav_cmd = json!({ "id": id, "action": cmd });
+            if let Some(steps_str) = rest.first() {
+                let steps = steps_str
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| ParseError::InvalidValue {
+                        message: format!("Invalid steps: '{}' must be a positive integer", steps_str),
+                        usage,
+                    })?;
+                if steps > 1 {
+                    nav_cmd["steps"] = json!(steps);
                 }
             }
             Ok(nav_cmd)
         }
-        "back" => Ok(json!({ "id": id, "action": "back" })),
-        "forward" => Ok(json!({ "id": id, "action": "forward" })),
-        "reload" => Ok(json!({ "id": id, "action": "reload" })),
+        "reload" => {
+            let hard = rest.contains(&"--hard");
+            if hard {
+                Ok(json!({ "id": id, "action": "reload", "ignoreCache": true }))
+            } else {
+                Ok(json!({ "id": id, "action": "reload" }))
+            }
+        }
+        "stop" => Ok(json!({ "id": id, "action": "stop" })),
+        "url" => Ok(json!({ "id": id, "action": "url", "includeTitle": true })),
 
         // === Core Actions ===
+        "click" if rest.contains(&"--at") => parse_pointer("click", &rest, &id),
         "click" => {
-            let new_tab = rest.iter().any(|arg| *arg == "--new-tab");
-            let sel = rest.iter()
-                .find(|arg| **arg != "--new-tab")
-                .ok_or_else(|| ParseError::MissingArguments {
-                    context: "click".to_string(),
-                    usage: "click <selector> [--new-tab]",
-                })?;
+            const USAGE: &str =
+                "click <selector> [--new-tab] [--force] [--expect-navigation|--expect-popup [--wait-until <state>]] [--expect-download [--save-as <path>]] [--expect-clipboard] [--intercept-submit [--allow-submit]]";
+            const WAIT_STATES: &[&str] = &["load", "domcontentloaded", "networkidle", "commit"];
+            let mut sel: Option<&str> = None;
+            let mut new_tab = false;
+            let mut expect_navigation = false;
+            let mut expect_popup = false;
+            let mut expect_download = false;
+            let mut expect_clipboard = false;
+            let mut intercept_submit = false;
+            let mut allow_submit = false;
+            let mut force = false;
+            let mut save_as: Option<&str> = None;
+            let mut wait_until: Option<&str> = None;
+            let mut i = 0;
+            while i < rest.len() {
+                match rest[i] {
+                    "--new-tab" => new_tab = true,
+                    "--expect-navigation" => expect_navigation = true,
+                    "--expect-popup" => expect_popup = true,
+                    "--expect-download" => expect_download = true,
+                    "--expect-clipboard" => expect_clipboard = true,
+                    "--intercept-submit" => intercept_submit = true,
+                    "--allow-submit" => allow_submit = true,
+                    "--force" => force = true,
+                    "--save-as" => {
+                        save_as = Some(*rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                            context: "click --save-as".to_string(),
+                            usage: USAGE,
+                        })?);
+                        i += 1;
+                    }
+                    "--wait-until" => {
+                        let state = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                            context: "click --wait-until".to_string(),
+                            usage: USAGE,
+                        })?;
+                        if !WAIT_STATES.contains(state) {
+                            return Err(ParseError::InvalidValue {
+                                message: format!(
+                                    "Invalid --wait-until value '{}'. Valid options: {}",
+                                    state,
+                                    WAIT_STATES.join(", ")
+                                ),
+                                usage: USAGE,
+                            });
+                        }
+                        wait_until = Some(state);
+                        i += 1;
+                    }
+                    arg if sel.is_none() => sel = Some(arg),
+                    _ => {}
+                }
+                i += 1;
+            }
+            let sel = sel.ok_or_else(|| ParseError::MissingArguments {
+                context: "click".to_string(),
+                usage: USAGE,
+            })?;
+            if wait_until.is_some() && !expect_navigation && !expect_popup {
+                return Err(ParseError::InvalidValue {
+                    message: "--wait-until on click needs --expect-navigation or --expect-popup".to_string(),
+                    usage: USAGE,
+                });
+            }
+            let modes = [new_tab, expect_navigation, expect_popup, expect_download, expect_clipboard, intercept_submit];
+            if modes.iter().filter(|&&set| set).count() > 1 {
+                return Err(ParseError::InvalidValue {
+                    message: "Use only one of --new-tab, --expect-navigation, --expect-popup, --expect-download, \
+                              --expect-clipboard and --intercept-submit"
+                        .to_string(),
+                    usage: USAGE,
+                });
+            }
+            if allow_submit && !intercept_submit {
+                return Err(ParseError::InvalidValue {
+                    message: "--allow-submit on click needs --intercept-submit".to_string(),
+                    usage: USAGE,
+                });
+            }
+            if save_as.is_some() && !expect_download {
+                return Err(ParseError::InvalidValue {
+                    message: "--save-as on click needs --expect-download".to_string(),
+                    usage: USAGE,
+                });
+            }
+            // The download action clicks with the download waiter armed, and
+            // brings the download directory, limits, artifacts and hooks along
+            if expect_download {
+                if force {
+                    return Err(ParseError::InvalidValue {
+                        message: "--force can't be combined with --expect-download".to_string(),
+                        usage: USAGE,
+                    });
+                }
+                let mut cmd = json!({ "id": id, "action": "download", "selector": sel });
+                if let Some(path) = save_as {
+                    cmd["path"] = json!(path);
+                }
+                return Ok(cmd);
+            }
+            let mut cmd = json!({ "id": id, "action": "click", "selector": sel });
             if new_tab {
-                Ok(json!({ "id": id, "action": "click", "selector": sel, "newTab": true }))
-            } else {
-                Ok(json!({ "id": id, "action": "click", "selector": sel }))
+                cmd["newTab"] = json!(true);
+            }
+            if force {
+                cmd["force"] = json!(true);
+            }
+            if expect_navigation {
+                cmd["expectNavigation"] = json!(true);
+            }
+            if expect_popup {
+                cmd["expectPopup"] = json!(true);
+            }
+            if expect_clipboard {
+                cmd["expectClipboard"] = json!(true);
+            }
+            if intercept_submit {
+                cmd["interceptSubmit"] = json!(true);
+            }
+            if allow_submit {
+                cmd["allowSubmit"] = json!(true);
             }
+            if let Some(state) = wait_until {
+                cmd["waitUntil"] = json!(state);
+            }
+            Ok(cmd)
         }
         "dblclick" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "dblclick".to_string(),
                 usage: "dblclick <selector>",
             })?;
             Ok(json!({ "id": id, "action": "dblclick", "selector": sel }))
         }
         "fill" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "fill".to_string(),
                 usage: "fill <selector> <text>",
             })?;
             Ok(json!({ "id": id, "action": "fill", "selector": sel, "value": rest[1..].join(" ") }))
         }
         "type" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let force = rest.contains(&"--force");
+            let rest: Vec<&str> = rest.iter().copied().filter(|arg| *arg != "--force").collect();
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "type".to_string(),
-                usage: "type <selector> <text>",
+                usage: "type <selector> <text> [--force]",
             })?;
-            Ok(json!({ "id": id, "action": "type", "selector": sel, "text": rest[1..].join(" ") }))
+            let mut cmd = json!({ "id": id, "action": "type", "selector": sel, "text": rest[1..].join(" ") });
+            if force {
+                cmd["force"] = json!(true);
+            }
+            Ok(cmd)
         }
         "hover" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "hover".to_string(),
                 usage: "hover <selector>",
             })?;
             Ok(json!({ "id": id, "action": "hover", "selector": sel }))
         }
         "focus" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "focus".to_string(),
                 usage: "focus <selector>",
             })?;
             Ok(json!({ "id": id, "action": "focus", "selector": sel }))
         }
         "check" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "check".to_string(),
                 usage: "check <selector>",
             })?;
             Ok(json!({ "id": id, "action": "check", "selector": sel }))
         }
         "uncheck" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "uncheck".to_string(),
                 usage: "uncheck <selector>",
             })?;
             Ok(json!({ "id": id, "action": "uncheck", "selector": sel }))
         }
         "select" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "select".to_string(),
                 usage: "select <selector> <value...>",
             })?;
@@ -189,8 +425,10 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                 Ok(json!({ "id": id, "action": "select", "selector": sel, "values": values }))
             }
         }
+        "drag" if rest.contains(&"--from") => parse_pointer("drag", &rest, &id),
+        "move" => parse_pointer("move", &rest, &id),
         "drag" => {
-            let src = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let src = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "drag".to_string(),
                 usage: "drag <source> <target>",
             })?;
@@ -201,14 +439,14 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
             Ok(json!({ "id": id, "action": "drag", "source": src, "target": tgt }))
         }
         "upload" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "upload".to_string(),
                 usage: "upload <selector> <files...>",
             })?;
             Ok(json!({ "id": id, "action": "upload", "selector": sel, "files": &rest[1..] }))
         }
         "download" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "download".to_string(),
                 usage: "download <selector> <path>",
             })?;
@@ -221,21 +459,21 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Keyboard ===
         "press" | "key" => {
-            let key = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let key = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "press".to_string(),
                 usage: "press <key>",
             })?;
             Ok(json!({ "id": id, "action": "press", "key": key }))
         }
         "keydown" => {
-            let key = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let key = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "keydown".to_string(),
                 usage: "keydown <key>",
             })?;
             Ok(json!({ "id": id, "action": "keydown", "key": key }))
         }
         "keyup" => {
-            let key = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let key = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "keyup".to_string(),
                 usage: "keyup <key>",
             })?;
@@ -244,15 +482,15 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Scroll ===
         "scroll" => {
-            let dir = rest.get(0).unwrap_or(&"down");
+            let dir = rest.first().unwrap_or(&"down");
             let amount = rest
                 .get(1)
                 .and_then(|s| s.parse::<i32>().ok())
                 .unwrap_or(300);
             Ok(json!({ "id": id, "action": "scroll", "direction": dir, "amount": amount }))
         }
-        "scrollintoview" | "scrollinto" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+        "scrollintoview" | "scrollinto" | "scroll-into-view" => {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "scrollintoview".to_string(),
                 usage: "scrollintoview <selector>",
             })?;
@@ -272,6 +510,23 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                 return Ok(json!({ "id": id, "action": "waitforurl", "url": url }));
             }
 
+            // Check for --route flag: wait --route "**/settings" (includes pushState/hash changes)
+            if let Some(idx) = rest.iter().position(|&s| s == "--route" || s == "-r") {
+                let route = rest
+                    .get(idx + 1)
+                    .ok_or_else(|| ParseError::MissingArguments {
+                        context: "wait --route".to_string(),
+                        usage: "wait --route <pattern> [--timeout ms]",
+                    })?;
+                let mut cmd = json!({ "id": id, "action": "waitforroute", "route": route });
+                if let Some(t) = rest.iter().position(|&s| s == "--timeout") {
+                    if let Some(timeout) = rest.get(t + 1).and_then(|s| s.parse::<u64>().ok()) {
+                        cmd["timeout"] = json!(timeout);
+                    }
+                }
+                return Ok(cmd);
+            }
+
             // Check for --load flag: wait --load networkidle
             if let Some(idx) = rest.iter().position(|&s| s == "--load" || s == "-l") {
                 let state = rest
@@ -330,7 +585,7 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
             }
 
             // Default: selector or timeout
-            if let Some(arg) = rest.get(0) {
+            if let Some(arg) = rest.first() {
                 if arg.parse::<u64>().is_ok() {
                     Ok(
                         json!({ "id": id, "action": "wait", "timeout": arg.parse::<u64>().unwrap() }),
@@ -341,7 +596,7 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
             } else {
                 Err(ParseError::MissingArguments {
                     context: "wait".to_string(),
-                    usage: "wait <selector|ms|--url|--load|--fn|--text>",
+                    usage: "wait <selector|ms|--url|--route|--load|--fn|--text>",
                 })
             }
         }
@@ -351,7 +606,29 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
             // screenshot [selector] [path]
             // selector: @ref or CSS selector
             // path: file path (contains / or . or ends with known extension)
-            let (selector, path) = match (rest.get(0), rest.get(1)) {
+            const USAGE: &str = "screenshot [selector] [path] [--annotate] [--grid [px]]";
+            let annotate = rest.contains(&"--annotate");
+            let mut rest: Vec<&str> = rest.iter().copied().filter(|w| *w != "--annotate").collect();
+            // --grid takes an optional spacing in image pixels, 100 by default
+            let grid = match rest.iter().position(|w| *w == "--grid") {
+                Some(i) => {
+                    let spacing = match rest.get(i + 1).copied().filter(|v| v.chars().all(|c| c.is_ascii_digit())) {
+                        Some(value) => {
+                            let spacing = value.parse::<u64>().ok().filter(|n| (10..=2000).contains(n));
+                            rest.remove(i + 1);
+                            spacing.ok_or_else(|| ParseError::InvalidValue {
+                                message: format!("--grid must be between 10 and 2000 pixels, got '{}'", value),
+                                usage: USAGE,
+                            })?
+                        }
+                        None => 100,
+                    };
+                    rest.remove(i);
+                    Some(spacing)
+                }
+                None => None,
+            };
+            let (selector, path) = match (rest.first(), rest.get(1)) {
                 (Some(first), Some(second)) => {
                     // Two args: first is selector, second is path
                     (Some(*first), Some(*second))
@@ -374,10 +651,17 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                 }
                 _ => (None, None),
             };
-            Ok(json!({ "id": id, "action": "screenshot", "path": path, "selector": selector, "fullPage": flags.full }))
+            let mut cmd = json!({ "id": id, "action": "screenshot", "path": path, "selector": selector, "fullPage": flags.full });
+            if annotate {
+                cmd["annotate"] = json!(true);
+            }
+            if let Some(grid) = grid {
+                cmd["grid"] = json!(grid);
+            }
+            Ok(cmd)
         }
         "pdf" => {
-            let path = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let path = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "pdf".to_string(),
                 usage: "pdf <path>",
             })?;
@@ -397,6 +681,21 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                     "-c" | "--compact" => {
                         obj.insert("compact".to_string(), json!(true));
                     }
+                    "--no-cache" => {
+                        obj.insert("noCache".to_string(), json!(true));
+                    }
+                    "--diff-from" => match rest.get(i + 1) {
+                        Some(from) => {
+                            obj.insert("diffFrom".to_string(), json!(from));
+                            i += 1;
+                        }
+                        None => {
+                            return Err(ParseError::MissingArguments {
+                                context: "snapshot --diff-from".to_string(),
+                                usage: "snapshot --diff-from <id>",
+                            });
+                        }
+                    },
                     "-d" | "--depth" => {
                         if let Some(d) = rest.get(i + 1) {
                             if let Ok(n) = d.parse::<i32>() {
@@ -419,7 +718,142 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
         }
 
         // === Eval ===
-        "eval" => Ok(json!({ "id": id, "action": "evaluate", "script": rest.join(" ") })),
+        "eval" => {
+            let mut words = rest.to_vec();
+            let format = match words.iter().position(|w| *w == "--format") {
+                Some(i) => {
+                    let format = words.get(i + 1).copied().ok_or_else(|| ParseError::MissingArguments {
+                        context: "eval --format".to_string(),
+                        usage: "eval <script> [--format json|csv]",
+                    })?;
+                    words.drain(i..i + 2);
+                    Some(format)
+                }
+                None => None,
+            };
+            let mut cmd = json!({ "id": id, "action": "evaluate", "script": words.join(" ") });
+            match format {
+                None | Some("json") => {}
+                Some("csv") => cmd["format"] = json!("csv"),
+                Some(other) => {
+                    return Err(ParseError::InvalidValue {
+                        message: format!("Invalid --format '{}': expected json or csv", other),
+                        usage: "eval <script> [--format json|csv]",
+                    })
+                }
+            }
+            Ok(cmd)
+        }
+
+        // === Text (DOM, a PDF's pages, or OCR of a screenshot for canvas/image text) ===
+        "text" => {
+            const USAGE: &str =
+                "text [selector] [--pages <range>] [--ocr] [--lang <lang>] [--detect-lang] [--translate-to <lang>]";
+            let mut selector = None;
+            let mut ocr = false;
+            let mut lang = None;
+            let mut pages = None;
+            let mut detect_lang = false;
+            let mut translate_to = None;
+            let mut i = 0;
+            while i < rest.len() {
+                match rest[i] {
+                    "--ocr" => ocr = true,
+                    "--detect-lang" => detect_lang = true,
+                    "--translate-to" => {
+                        let target = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                            context: "text --translate-to".to_string(),
+                            usage: USAGE,
+                        })?;
+                        if !language::valid_lang(target) {
+                            return Err(ParseError::InvalidValue {
+                                message: format!("Invalid --translate-to '{}': expected a language code like en or pt-BR", target),
+                                usage: USAGE,
+                            });
+                        }
+                        translate_to = Some(*target);
+                        i += 1;
+                    }
+                    "--pages" => {
+                        let spec = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                            context: "text --pages".to_string(),
+                            usage: USAGE,
+                        })?;
+                        pdf::parse_pages(spec).map_err(|message| ParseError::InvalidValue { message, usage: USAGE })?;
+                        pages = Some(*spec);
+                        i += 1;
+                    }
+                    "--lang" => {
+                        lang = Some(*rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                            context: "text --lang".to_string(),
+                            usage: USAGE,
+                        })?);
+                        i += 1;
+                    }
+                    word if selector.is_none() => selector = Some(word),
+                    other => {
+                        return Err(ParseError::InvalidValue {
+                            message: format!("Unexpected argument '{}'", other),
+                            usage: USAGE,
+                        })
+                    }
+                }
+                i += 1;
+            }
+            if !ocr {
+                if lang.is_some() {
+                    return Err(ParseError::InvalidValue {
+                        message: "--lang only applies with --ocr".to_string(),
+                        usage: USAGE,
+                    });
+                }
+                // The CLI adds a temp path for the daemon to save a PDF to
+                let mut cmd = json!({ "id": id, "action": "text" });
+                if let Some(selector) = selector {
+                    cmd["selector"] = json!(selector);
+                }
+                if let Some(pages) = pages {
+                    cmd["pages"] = json!(pages);
+                }
+                set_language_options(&mut cmd, detect_lang, translate_to);
+                return Ok(cmd);
+            }
+            if pages.is_some() {
+                return Err(ParseError::InvalidValue {
+                    message: "--pages applies to PDFs, not --ocr".to_string(),
+                    usage: USAGE,
+                });
+            }
+            let lang = lang.unwrap_or(ocr::DEFAULT_LANG);
+            if !ocr::valid_lang(lang) {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Invalid --lang '{}': expected tesseract language names like eng or eng+deu", lang),
+                    usage: USAGE,
+                });
+            }
+            // The daemon sends the image back and the CLI reads the text from it
+            let mut cmd = json!({
+                "id": id,
+                "action": "screenshot",
+                "path": null,
+                "selector": selector,
+                "fullPage": false,
+                "ocr": { "lang": lang },
+            });
+            set_language_options(&mut cmd, detect_lang, translate_to);
+            Ok(cmd)
+        }
+
+        // === Article (readable text, hero image and metadata) ===
+        "article" => {
+            if let Some(extra) = rest.first() {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument '{}' (open the page first)", extra),
+                    usage: "article",
+                });
+            }
+            Ok(json!({ "id": id, "action": "article" }))
+        }
 
         // === Close ===
         "close" | "quit" | "exit" => Ok(json!({ "id": id, "action": "close" })),
@@ -440,7 +874,7 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
             } else {
                 // It's a port number - validate and use cdpPort field
                 let port: u16 = match endpoint.parse::<u32>() {
-                    Ok(p) if p == 0 => {
+                    Ok(0) => {
                         return Err(ParseError::InvalidValue {
                             message: "Invalid port: port must be greater than 0".to_string(),
                             usage: "connect <port|url>",
@@ -475,6 +909,7 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Is (state checks) ===
         "is" => parse_is(&rest, &id),
+        "style" => parse_style("style", &rest, &id),
 
         // === Find (locators) ===
         "find" => parse_find(&rest, &id),
@@ -487,13 +922,28 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Network ===
         "network" => parse_network(&rest, &id),
+        "assert" => parse_assert(&rest, &id),
+        "check-links" => parse_check_links(&rest, &id),
+        "daemon" => parse_daemon(&rest, &id),
+        // show and verify read the audit log without the daemon
+        "audit" => match rest.first().copied() {
+            Some("security") => Ok(json!({ "id": id, "action": "security_audit" })),
+            Some(sub) => Err(ParseError::UnknownSubcommand {
+                subcommand: sub.to_string(),
+                valid_options: &["show", "verify", "security"],
+            }),
+            None => Err(ParseError::MissingArguments {
+                context: "audit".to_string(),
+                usage: "audit <show|verify|security>",
+            }),
+        },
 
         // === Storage ===
         "storage" => parse_storage(&rest, &id),
 
         // === Cookies ===
         "cookies" => {
-            let op = rest.get(0).unwrap_or(&"get");
+            let op = rest.first().unwrap_or(&"get");
             match *op {
                 "set" => {
                     let name = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
@@ -515,7 +965,7 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Tabs ===
         "tab" => {
-            match rest.get(0).map(|s| *s) {
+            match rest.first().copied() {
                 Some("new") => {
                     let mut cmd = json!({ "id": id, "action": "tab_new" });
                     if let Some(url) = rest.get(1) {
@@ -541,7 +991,7 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
         // === Window ===
         "window" => {
             const VALID: &[&str] = &["new"];
-            match rest.get(0).map(|s| *s) {
+            match rest.first().copied() {
                 Some("new") => Ok(json!({ "id": id, "action": "window_new" })),
                 Some(sub) => Err(ParseError::UnknownSubcommand {
                     subcommand: sub.to_string(),
@@ -556,10 +1006,10 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Frame ===
         "frame" => {
-            if rest.get(0).map(|s| *s) == Some("main") {
+            if rest.first().copied() == Some("main") {
                 Ok(json!({ "id": id, "action": "mainframe" }))
             } else {
-                let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+                let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                     context: "frame".to_string(),
                     usage: "frame <selector|main>",
                 })?;
@@ -570,7 +1020,7 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
         // === Dialog ===
         "dialog" => {
             const VALID: &[&str] = &["accept", "dismiss"];
-            match rest.get(0).map(|s| *s) {
+            match rest.first().copied() {
                 Some("accept") => {
                     let mut cmd = json!({ "id": id, "action": "dialog", "response": "accept" });
                     if let Some(prompt_text) = rest.get(1) {
@@ -589,10 +1039,36 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
             }
         }
 
+        // === Queued jobs ===
+        "job" => {
+            // `job logs` and jobs started with --detach are answered by the CLI
+            // from the jobs directory; these go to the daemon's queue
+            const VALID: &[&str] = &["list", "status", "result", "logs", "cancel"];
+            let usage = "job <list|status|result|logs|cancel> [id]";
+            match (rest.first().copied(), rest.get(1)) {
+                (Some("list"), _) => Ok(json!({ "id": id, "action": "job_list" })),
+                (Some(sub @ ("status" | "result" | "cancel")), Some(job_id)) => {
+                    Ok(json!({ "id": id, "action": format!("job_{}", sub), "jobId": job_id }))
+                }
+                (Some("logs"), _) => Err(ParseError::InvalidValue {
+                    message: "job logs only applies to jobs started with --detach".to_string(),
+                    usage,
+                }),
+                (Some("status" | "result" | "cancel"), None) | (None, _) => Err(ParseError::MissingArguments {
+                    context: "job".to_string(),
+                    usage,
+                }),
+                (Some(sub), _) => Err(ParseError::UnknownSubcommand {
+                    subcommand: sub.to_string(),
+                    valid_options: VALID,
+                }),
+            }
+        }
+
         // === Debug ===
         "trace" => {
             const VALID: &[&str] = &["start", "stop"];
-            match rest.get(0).map(|s| *s) {
+            match rest.first().copied() {
                 Some("start") => Ok(json!({ "id": id, "action": "trace_start" })),
                 Some("stop") => {
                     let path = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
@@ -614,8 +1090,8 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === Recording (Playwright native video recording) ===
         "record" => {
-            const VALID: &[&str] = &["start", "stop", "restart"];
-            match rest.get(0).map(|s| *s) {
+            const VALID: &[&str] = &["start", "stop", "restart", "gif"];
+            match rest.first().copied() {
                 Some("start") => {
                     let path = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                         context: "record start".to_string(),
@@ -655,26 +1131,95 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                     }
                     Ok(cmd)
                 }
+                Some("gif") => {
+                    const USAGE: &str = "record gif --out <file.gif|file.png> [--commands <n>] [--delay <ms>]";
+                    if rest.get(1) == Some(&"stop") {
+                        return Ok(json!({ "id": id, "action": "gif_stop" }));
+                    }
+                    let mut cmd = json!({ "id": id, "action": "gif_start" });
+                    let mut i = 1;
+                    while i < rest.len() {
+                        let key = match rest[i] {
+                            "--out" => "path",
+                            "--commands" => "commands",
+                            "--delay" => "delay",
+                            other => {
+                                return Err(ParseError::InvalidValue {
+                                    message: format!("Unknown option '{}'", other),
+                                    usage: USAGE,
+                                })
+                            }
+                        };
+                        let value = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                            context: format!("record gif {}", rest[i]),
+                            usage: USAGE,
+                        })?;
+                        if key == "path" {
+                            cmd[key] = json!(value);
+                        } else {
+                            let n = value.parse::<u64>().ok().filter(|n| *n > 0).ok_or_else(|| {
+                                ParseError::InvalidValue {
+                                    message: format!("{} must be a positive number, got '{}'", rest[i], value),
+                                    usage: USAGE,
+                                }
+                            })?;
+                            cmd[key] = json!(n);
+                        }
+                        i += 2;
+                    }
+                    let path = cmd["path"].as_str().ok_or_else(|| ParseError::MissingArguments {
+                        context: "record gif".to_string(),
+                        usage: USAGE,
+                    })?;
+                    let lower = path.to_lowercase();
+                    if ![".gif", ".png", ".apng"].iter().any(|ext| lower.ends_with(ext)) {
+                        return Err(ParseError::InvalidValue {
+                            message: format!("--out must end in .gif, .png or .apng, got '{}'", path),
+                            usage: USAGE,
+                        });
+                    }
+                    Ok(cmd)
+                }
                 Some(sub) => Err(ParseError::UnknownSubcommand {
                     subcommand: sub.to_string(),
                     valid_options: VALID,
                 }),
                 None => Err(ParseError::MissingArguments {
                     context: "record".to_string(),
-                    usage: "record <start|stop|restart> [path] [url]",
+                    usage: "record <start|stop|restart|gif> [path] [url]",
                 }),
             }
         }
         "console" => {
-            let clear = rest.iter().any(|&s| s == "--clear");
+            let clear = rest.contains(&"--clear");
             Ok(json!({ "id": id, "action": "console", "clear": clear }))
         }
         "errors" => {
-            let clear = rest.iter().any(|&s| s == "--clear");
+            let clear = rest.contains(&"--clear");
             Ok(json!({ "id": id, "action": "errors", "clear": clear }))
         }
+        "janitor" => {
+            let clear = rest.contains(&"--clear");
+            let args: Vec<&str> = rest.iter().copied().filter(|a| *a != "--clear").collect();
+            match args.as_slice() {
+                [] => Ok(json!({ "id": id, "action": "janitor", "clear": clear })),
+                ["run", rules @ ..] => {
+                    let mut cmd = json!({ "id": id, "action": "janitor", "run": true, "clear": clear });
+                    if !rules.is_empty() {
+                        let rules = janitor::parse_rules(&rules.join(","))
+                            .map_err(|message| ParseError::InvalidValue { message, usage: "janitor run [blank,popups,contexts|all]" })?;
+                        cmd["rules"] = json!(rules);
+                    }
+                    Ok(cmd)
+                }
+                [other, ..] => Err(ParseError::UnknownSubcommand {
+                    subcommand: other.to_string(),
+                    valid_options: &["run"],
+                }),
+            }
+        }
         "highlight" => {
-            let sel = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+            let sel = rest.first().ok_or_else(|| ParseError::MissingArguments {
                 context: "highlight".to_string(),
                 usage: "highlight <selector>",
             })?;
@@ -683,8 +1228,8 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
 
         // === State ===
         "state" => {
-            const VALID: &[&str] = &["save", "load", "list", "clear", "show", "clean", "rename"];
-            match rest.get(0).map(|s| *s) {
+            const VALID: &[&str] = &["save", "load", "list", "clear", "show", "clean", "rename", "restore", "migrate"];
+            match rest.first().copied() {
                 Some("save") => {
                     let path = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                         context: "state save".to_string(),
@@ -750,14 +1295,11 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                     
                     let mut i = 1;
                     while i < rest.len() {
-                        match rest[i] {
-                            "--older-than" => {
-                                if let Some(d) = rest.get(i + 1) {
-                                    days = d.parse().ok();
-                                    i += 1;
-                                }
+                        if rest[i] == "--older-than" {
+                            if let Some(d) = rest.get(i + 1) {
+                                days = d.parse().ok();
+                                i += 1;
                             }
-                            _ => {}
                         }
                         i += 1;
                     }
@@ -792,13 +1334,50 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
                     
                     Ok(json!({ "id": id, "action": "state_rename", "oldName": old_name, "newName": new_name }))
                 }
+                Some("restore") => {
+                    // state restore <path> --from-backup [n]
+                    const USAGE: &str = "state restore <path> --from-backup [<n>]";
+                    let path = rest.get(1).filter(|p| !p.starts_with('-')).ok_or_else(|| ParseError::MissingArguments {
+                        context: "state restore".to_string(),
+                        usage: USAGE,
+                    })?;
+                    let Some(at) = rest.iter().position(|a| *a == "--from-backup") else {
+                        return Err(ParseError::MissingArguments {
+                            context: "state restore --from-backup".to_string(),
+                            usage: USAGE,
+                        });
+                    };
+                    let mut cmd = json!({ "id": id, "action": "state_restore", "path": path });
+                    if let Some(n) = rest.get(at + 1) {
+                        match n.parse::<u32>() {
+                            Ok(n) if n > 0 => cmd["backup"] = json!(n),
+                            _ => {
+                                return Err(ParseError::InvalidValue {
+                                    message: format!("Invalid backup number '{}': 1 is the newest backup", n),
+                                    usage: USAGE,
+                                })
+                            }
+                        }
+                    }
+                    Ok(cmd)
+                }
+                Some("migrate") => {
+                    // state migrate [path]; --dry-run reports without writing
+                    let mut cmd = json!({ "id": id, "action": "state_migrate", "dryRun": flags.dry_run });
+                    if let Some(path) = rest.get(1) {
+                        cmd["path"] = json!(path);
+                    }
+                    Ok(cmd)
+                }
+                // Anything else is an element: state <selector>, same as get state
+                Some(sel) if !VALID.contains(&sel) => Ok(json!({ "id": id, "action": "elementstate", "selector": sel })),
                 Some(sub) => Err(ParseError::UnknownSubcommand {
                     subcommand: sub.to_string(),
                     valid_options: VALID,
                 }),
                 None => Err(ParseError::MissingArguments {
                     context: "state".to_string(),
-                    usage: "state <save|load|list|clear|show|clean|rename> ...",
+                    usage: "state <save|load|list|clear|show|clean|rename|restore|migrate> ... | state <selector>",
                 }),
             }
         }
@@ -809,10 +1388,66 @@ pub fn parse_command(args: &[String], flags: &Flags) -> Result<Value, ParseError
     }
 }
 
+/// `text --detect-lang` and `--translate-to`, applied by the CLI to the text read
+fn set_language_options(cmd: &mut Value, detect: bool, translate_to: Option<&str>) {
+    if detect {
+        cmd["detectLang"] = json!(true);
+    }
+    if let Some(target) = translate_to {
+        cmd["translateTo"] = json!(target);
+    }
+}
+
+/// Parse repeated `--headers-for <origin>=<json>` entries into an origin -> headers map
+fn parse_origin_headers(entries: &[String]) -> Result<Value, ParseError> {
+    const USAGE: &str = "open <url> --headers-for <origin>=<json>";
+    let mut map = serde_json::Map::new();
+    for entry in entries {
+        let (origin, headers_json) = entry.split_once('=').ok_or_else(|| ParseError::InvalidValue {
+            message: format!("Invalid --headers-for value '{}': expected <origin>=<json>", entry),
+            usage: USAGE,
+        })?;
+        let origin = origin.trim().trim_end_matches('/').to_lowercase();
+        if origin.is_empty() {
+            return Err(ParseError::InvalidValue {
+                message: format!("Invalid --headers-for value '{}': origin is empty", entry),
+                usage: USAGE,
+            });
+        }
+        let headers: Value = serde_json::from_str(headers_json).map_err(|_| ParseError::InvalidValue {
+            message: format!("Invalid --headers-for headers for '{}': must be a JSON object", origin),
+            usage: USAGE,
+        })?;
+        let Some(headers_obj) = headers.as_object() else {
+            return Err(ParseError::InvalidValue {
+                message: format!("Invalid --headers-for headers for '{}': must be a JSON object", origin),
+                usage: USAGE,
+            });
+        };
+        if let Some((name, value)) = headers_obj.iter().find(|(_, v)| !v.is_string()) {
+            return Err(ParseError::InvalidValue {
+                message: format!(
+                    "Invalid --headers-for headers for '{}': value of \"{}\" must be a string, got {}",
+                    origin, name, value
+                ),
+                usage: USAGE,
+            });
+        }
+        // Merge repeated entries for the same origin
+        let slot = map.entry(origin).or_insert_with(|| json!({}));
+        if let Some(existing) = slot.as_object_mut() {
+            for (k, v) in headers_obj {
+                existing.insert(k.clone(), v.clone());
+            }
+        }
+    }
+    Ok(Value::Object(map))
+}
+
 fn parse_get(rest: &[&str], id: &str) -> Result<Value, ParseError> {
-    const VALID: &[&str] = &["text", "html", "value", "attr", "url", "title", "count", "box", "styles"];
+    const VALID: &[&str] = &["text", "html", "value", "attr", "url", "title", "count", "box", "styles", "state"];
     
-    match rest.get(0).map(|s| *s) {
+    match rest.first().copied() {
         Some("text") => {
             let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                 context: "get text".to_string(),
@@ -861,12 +1496,13 @@ fn parse_get(rest: &[&str], id: &str) -> Result<Value, ParseError> {
             })?;
             Ok(json!({ "id": id, "action": "boundingbox", "selector": sel }))
         }
-        Some("styles") => {
+        Some("styles") => parse_style("get styles", &rest[1..], id),
+        Some("state") => {
             let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
-                context: "get styles".to_string(),
-                usage: "get styles <selector>",
+                context: "get state".to_string(),
+                usage: "get state <selector>",
             })?;
-            Ok(json!({ "id": id, "action": "styles", "selector": sel }))
+            Ok(json!({ "id": id, "action": "elementstate", "selector": sel }))
         }
         Some(sub) => Err(ParseError::UnknownSubcommand {
             subcommand: sub.to_string(),
@@ -874,15 +1510,69 @@ fn parse_get(rest: &[&str], id: &str) -> Result<Value, ParseError> {
         }),
         None => Err(ParseError::MissingArguments {
             context: "get".to_string(),
-            usage: "get <text|html|value|attr|url|title|count|box|styles> [args...]",
+            usage: "get <text|html|value|attr|url|title|count|box|styles|state> [args...]",
         }),
     }
 }
 
+/// `style <selector> [--props a,b]` and `get styles`: computed styles and layout
+fn parse_style(context: &str, rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    let usage = if context == "style" {
+        "style <selector> [--props <list>]"
+    } else {
+        "get styles <selector> [--props <list>]"
+    };
+    let mut sel: Option<&str> = None;
+    let mut props: Option<Vec<&str>> = None;
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--props" => {
+                let list = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("{} --props", context),
+                    usage,
+                })?;
+                let names: Vec<&str> = list.split(',').map(str::trim).filter(|p| !p.is_empty()).collect();
+                let valid = |p: &str| {
+                    let name = p.strip_prefix("--").unwrap_or(p);
+                    name.starts_with(|c: char| c.is_ascii_alphabetic())
+                        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                };
+                if let Some(bad) = names.iter().find(|p| !valid(p)) {
+                    return Err(ParseError::InvalidValue {
+                        message: format!("Invalid CSS property '{}'", bad),
+                        usage,
+                    });
+                }
+                if names.is_empty() {
+                    return Err(ParseError::InvalidValue {
+                        message: "--props needs at least one property, e.g. color,display".to_string(),
+                        usage,
+                    });
+                }
+                props = Some(names);
+                i += 1;
+            }
+            arg if sel.is_none() => sel = Some(arg),
+            _ => {}
+        }
+        i += 1;
+    }
+    let sel = sel.ok_or_else(|| ParseError::MissingArguments {
+        context: context.to_string(),
+        usage,
+    })?;
+    let mut cmd = json!({ "id": id, "action": "styles", "selector": sel });
+    if let Some(props) = props {
+        cmd["props"] = json!(props);
+    }
+    Ok(cmd)
+}
+
 fn parse_is(rest: &[&str], id: &str) -> Result<Value, ParseError> {
     const VALID: &[&str] = &["visible", "enabled", "checked"];
 
-    match rest.get(0).map(|s| *s) {
+    match rest.first().copied() {
         Some("visible") => {
             let sel = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                 context: "is visible".to_string(),
@@ -929,14 +1619,14 @@ fn parse_find(rest: &[&str], id: &str) -> Result<Value, ParseError> {
         "nth",
     ];
 
-    let locator = rest.get(0).ok_or_else(|| ParseError::MissingArguments {
+    let locator = rest.first().ok_or_else(|| ParseError::MissingArguments {
         context: "find".to_string(),
         usage: "find <locator> <value> [action] [text]",
     })?;
 
     let name_idx = rest.iter().position(|&s| s == "--name");
-    let name = name_idx.and_then(|i| rest.get(i + 1).map(|s| *s));
-    let exact = rest.iter().any(|&s| s == "--exact");
+    let name = name_idx.and_then(|i| rest.get(i + 1).copied());
+    let exact = rest.contains(&"--exact");
 
     match *locator {
         "role" | "text" | "label" | "placeholder" | "alt" | "title" | "testid" | "first"
@@ -1032,10 +1722,100 @@ fn parse_find(rest: &[&str], id: &str) -> Result<Value, ParseError> {
     }
 }
 
+/// `click --at x,y`, `move x,y` and `drag --from x,y --to x,y` for vision
+/// agents. Points are screenshot pixels, scaled by the daemon to CSS pixels,
+/// or CSS pixels as given with --css.
+fn parse_pointer(command: &str, rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    let usage = match command {
+        "click" => "click --at <x,y> [--button left|right|middle] [--css]",
+        "move" => "move <x,y> [--css]",
+        _ => "drag --from <x,y> --to <x,y> [--css]",
+    };
+    let point = |value: &str| {
+        value
+            .split_once(',')
+            .and_then(|(x, y)| Some((x.trim().parse::<f64>().ok()?, y.trim().parse::<f64>().ok()?)))
+            .filter(|(x, y)| *x >= 0.0 && *y >= 0.0)
+            .ok_or_else(|| ParseError::InvalidValue {
+                message: format!("Invalid point '{}': expected x,y in pixels, e.g. 412,380", value),
+                usage,
+            })
+    };
+
+    let mut at = None;
+    let mut from = None;
+    let mut to = None;
+    let mut button = None;
+    let mut css = false;
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--css" => css = true,
+            flag @ ("--at" | "--button") if command == "click" => {
+                let value = rest.get(i + 1).copied().ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("click {}", flag),
+                    usage,
+                })?;
+                if flag == "--at" {
+                    at = Some(point(value)?);
+                } else if ["left", "right", "middle"].contains(&value) {
+                    button = Some(value);
+                } else {
+                    return Err(ParseError::InvalidValue {
+                        message: format!("Invalid --button '{}': expected left, right or middle", value),
+                        usage,
+                    });
+                }
+                i += 1;
+            }
+            flag @ ("--from" | "--to") if command == "drag" => {
+                let value = rest.get(i + 1).copied().ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("drag {}", flag),
+                    usage,
+                })?;
+                if flag == "--from" {
+                    from = Some(point(value)?);
+                } else {
+                    to = Some(point(value)?);
+                }
+                i += 1;
+            }
+            word if command == "move" && at.is_none() => at = Some(point(word)?),
+            other => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unexpected argument '{}'", other),
+                    usage,
+                })
+            }
+        }
+        i += 1;
+    }
+
+    let missing = || ParseError::MissingArguments { context: command.to_string(), usage };
+    let mut cmd = match command {
+        "drag" => {
+            let ((from_x, from_y), (to_x, to_y)) = (from.ok_or_else(missing)?, to.ok_or_else(missing)?);
+            json!({ "id": id, "action": "drag_at", "fromX": from_x, "fromY": from_y, "toX": to_x, "toY": to_y })
+        }
+        _ => {
+            let (x, y) = at.ok_or_else(missing)?;
+            let action = if command == "click" { "click_at" } else { "move_at" };
+            json!({ "id": id, "action": action, "x": x, "y": y })
+        }
+    };
+    if let Some(button) = button {
+        cmd["button"] = json!(button);
+    }
+    if css {
+        cmd["css"] = json!(true);
+    }
+    Ok(cmd)
+}
+
 fn parse_mouse(rest: &[&str], id: &str) -> Result<Value, ParseError> {
     const VALID: &[&str] = &["move", "down", "up", "wheel"];
 
-    match rest.get(0).map(|s| *s) {
+    match rest.first().copied() {
         Some("move") => {
             let x_str = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                 context: "mouse move".to_string(),
@@ -1097,7 +1877,7 @@ fn parse_set(rest: &[&str], id: &str) -> Result<Value, ParseError> {
         "media",
     ];
 
-    match rest.get(0).map(|s| *s) {
+    match rest.first().copied() {
         Some("viewport") => {
             let w_str = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                 context: "set viewport".to_string(),
@@ -1183,14 +1963,14 @@ fn parse_set(rest: &[&str], id: &str) -> Result<Value, ParseError> {
             Ok(json!({ "id": id, "action": "credentials", "username": user, "password": pass }))
         }
         Some("media") => {
-            let color = if rest.iter().any(|&s| s == "dark") {
+            let color = if rest.contains(&"dark") {
                 "dark"
-            } else if rest.iter().any(|&s| s == "light") {
+            } else if rest.contains(&"light") {
                 "light"
             } else {
                 "no-preference"
             };
-            let reduced = if rest.iter().any(|&s| s == "reduced-motion") {
+            let reduced = if rest.contains(&"reduced-motion") {
                 "reduce"
             } else {
                 "no-preference"
@@ -1209,19 +1989,43 @@ fn parse_set(rest: &[&str], id: &str) -> Result<Value, ParseError> {
 }
 
 fn parse_network(rest: &[&str], id: &str) -> Result<Value, ParseError> {
-    const VALID: &[&str] = &["route", "unroute", "requests"];
+    const VALID: &[&str] = &["route", "mock", "unroute", "requests"];
 
-    match rest.get(0).map(|s| *s) {
+    match rest.first().copied() {
         Some("route") => {
             let url = rest.get(1).ok_or_else(|| ParseError::MissingArguments {
                 context: "network route".to_string(),
                 usage: "network route <url> [--abort|--body <json>]",
             })?;
-            let abort = rest.iter().any(|&s| s == "--abort");
+            let abort = rest.contains(&"--abort");
             let body_idx = rest.iter().position(|&s| s == "--body");
-            let body = body_idx.and_then(|i| rest.get(i + 1).map(|s| *s));
+            let body = body_idx.and_then(|i| rest.get(i + 1).copied());
             Ok(json!({ "id": id, "action": "route", "url": url, "abort": abort, "body": body }))
         }
+        Some("mock") => {
+            let value = |name: &str| {
+                rest.iter().position(|&s| s == name).and_then(|i| rest.get(i + 1).copied())
+            };
+            let path = value("--from-har").ok_or_else(|| ParseError::MissingArguments {
+                context: "network mock".to_string(),
+                usage: "network mock --from-har <file> [--url <pattern>] [--fallthrough continue|abort]",
+            })?;
+            let not_found = match value("--fallthrough") {
+                None | Some("continue") => "fallback",
+                Some("abort") => "abort",
+                Some(other) => {
+                    return Err(ParseError::InvalidValue {
+                        message: format!("Invalid --fallthrough '{}': use continue or abort", other),
+                        usage: "network mock --from-har <file> [--url <pattern>] [--fallthrough continue|abort]",
+                    })
+                }
+            };
+            let mut cmd = json!({ "id": id, "action": "route_har", "path": path, "notFound": not_found });
+            if let Some(url) = value("--url") {
+                cmd["url"] = json!(url);
+            }
+            Ok(cmd)
+        }
         Some("unroute") => {
             let mut cmd = json!({ "id": id, "action": "unroute" });
             if let Some(url) = rest.get(1) {
@@ -1230,9 +2034,9 @@ fn parse_network(rest: &[&str], id: &str) -> Result<Value, ParseError> {
             Ok(cmd)
         },
         Some("requests") => {
-            let clear = rest.iter().any(|&s| s == "--clear");
+            let clear = rest.contains(&"--clear");
             let filter_idx = rest.iter().position(|&s| s == "--filter");
-            let filter = filter_idx.and_then(|i| rest.get(i + 1).map(|s| *s));
+            let filter = filter_idx.and_then(|i| rest.get(i + 1).copied());
             let mut cmd = json!({ "id": id, "action": "requests", "clear": clear });
             if let Some(f) = filter {
                 cmd["filter"] = json!(f);
@@ -1245,17 +2049,185 @@ fn parse_network(rest: &[&str], id: &str) -> Result<Value, ParseError> {
         }),
         None => Err(ParseError::MissingArguments {
             context: "network".to_string(),
-            usage: "network <route|unroute|requests> [args...]",
+            usage: "network <route|mock|unroute|requests> [args...]",
         }),
     }
 }
 
-fn parse_storage(rest: &[&str], id: &str) -> Result<Value, ParseError> {
-    const VALID: &[&str] = &["local", "session"];
+fn parse_assert(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &["request"];
+    const USAGE: &str = "assert request <url-pattern> [--sent|--not-sent] [--method <m>] [--status <code>] [--body-path <$.path> [--equals <value>]]";
+
+    match rest.first().copied() {
+        Some("request") => {
+            let mut cmd = json!({ "id": id, "action": "assert_request", "sent": true });
+            let mut pattern: Option<&str> = None;
+            let mut i = 1;
+            while i < rest.len() {
+                let value = |name: &str| {
+                    rest.get(i + 1).copied().ok_or_else(|| ParseError::MissingArguments {
+                        context: format!("assert request {}", name),
+                        usage: USAGE,
+                    })
+                };
+                match rest[i] {
+                    "--sent" => cmd["sent"] = json!(true),
+                    "--not-sent" => cmd["sent"] = json!(false),
+                    "--method" => {
+                        cmd["method"] = json!(value("--method")?.to_uppercase());
+                        i += 1;
+                    }
+                    "--status" => {
+                        let status = value("--status")?;
+                        // A code like 204, or a class like 2xx
+                        let valid = status.len() == 3
+                            && matches!(status.as_bytes()[0], b'1'..=b'5')
+                            && (status[1..].bytes().all(|b| b.is_ascii_digit()) || status[1..].eq_ignore_ascii_case("xx"));
+                        if !valid {
+                            return Err(ParseError::InvalidValue {
+                                message: format!("Invalid --status '{}': use a code like 204 or a class like 2xx", status),
+                                usage: USAGE,
+                            });
+                        }
+                        cmd["status"] = json!(status);
+                        i += 1;
+                    }
+                    "--body-path" => {
+                        let path = value("--body-path")?;
+                        if !path.starts_with('$') {
+                            return Err(ParseError::InvalidValue {
+                                message: format!("Invalid --body-path '{}': start it with $, e.g. $.event", path),
+                                usage: USAGE,
+                            });
+                        }
+                        cmd["bodyPath"] = json!(path);
+                        i += 1;
+                    }
+                    "--equals" => {
+                        cmd["equals"] = json!(value("--equals")?);
+                        i += 1;
+                    }
+                    arg if pattern.is_none() && !arg.starts_with("--") => pattern = Some(arg),
+                    other => {
+                        return Err(ParseError::InvalidValue {
+                            message: format!("Unknown assert request option '{}'", other),
+                            usage: USAGE,
+                        })
+                    }
+                }
+                i += 1;
+            }
+            let pattern = pattern.ok_or_else(|| ParseError::MissingArguments {
+                context: "assert request".to_string(),
+                usage: USAGE,
+            })?;
+            if cmd.get("equals").is_some() && cmd.get("bodyPath").is_none() {
+                return Err(ParseError::InvalidValue {
+                    message: "--equals needs --body-path".to_string(),
+                    usage: USAGE,
+                });
+            }
+            cmd["pattern"] = json!(pattern);
+            Ok(cmd)
+        }
+        Some(sub) => Err(ParseError::UnknownSubcommand {
+            subcommand: sub.to_string(),
+            valid_options: VALID,
+        }),
+        None => Err(ParseError::MissingArguments {
+            context: "assert".to_string(),
+            usage: USAGE,
+        }),
+    }
+}
+
+fn parse_check_links(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "check-links [--same-origin] [--concurrency <n>] [--timeout <ms>]";
+    let mut cmd = json!({ "id": id, "action": "check_links" });
+    let mut i = 0;
+    while i < rest.len() {
+        match rest[i] {
+            "--same-origin" => cmd["sameOrigin"] = json!(true),
+            name @ ("--concurrency" | "--timeout") => {
+                let value = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                    context: format!("check-links {}", name),
+                    usage: USAGE,
+                })?;
+                let n = value.parse::<u64>().ok().filter(|n| *n >= 1);
+                match (name, n) {
+                    ("--concurrency", Some(n)) if n <= 64 => cmd["concurrency"] = json!(n),
+                    ("--timeout", Some(n)) => cmd["timeout"] = json!(n),
+                    _ => {
+                        let range = if name == "--concurrency" { "from 1 to 64" } else { "of milliseconds" };
+                        return Err(ParseError::InvalidValue {
+                            message: format!("Invalid {} '{}': use a number {}", name, value, range),
+                            usage: USAGE,
+                        });
+                    }
+                }
+                i += 1;
+            }
+            other => {
+                return Err(ParseError::InvalidValue {
+                    message: format!("Unknown check-links option '{}'", other),
+                    usage: USAGE,
+                })
+            }
+        }
+        i += 1;
+    }
+    Ok(cmd)
+}
+
+/// Most warm daemons one pool keeps, each with its own browser
+const MAX_WARM_COUNT: u64 = 16;
+
+/// `daemon warm` is run by the CLI, which starts the pool's daemons itself
+fn parse_daemon(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "daemon warm [--count <n>]";
+    match rest.first().copied() {
+        Some("warm") => {
+            let mut count = 1;
+            if let Some(i) = rest.iter().position(|a| *a == "--count") {
+                let value = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                    context: "daemon warm --count".to_string(),
+                    usage: USAGE,
+                })?;
+                count = value.parse::<u64>().ok().filter(|n| *n <= MAX_WARM_COUNT).ok_or_else(|| ParseError::InvalidValue {
+                    message: format!("Invalid --count '{}': use 0 to {}", value, MAX_WARM_COUNT),
+                    usage: USAGE,
+                })?;
+            }
+            Ok(json!({ "id": id, "action": "daemon_warm", "count": count }))
+        }
+        Some(sub) => Err(ParseError::UnknownSubcommand {
+            subcommand: sub.to_string(),
+            valid_options: &["warm"],
+        }),
+        None => Err(ParseError::MissingArguments {
+            context: "daemon".to_string(),
+            usage: USAGE,
+        }),
+    }
+}
 
-    match rest.get(0).map(|s| *s) {
+fn parse_storage(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const VALID: &[&str] = &["local", "session", "indexeddb", "origins", "clear"];
+
+    match rest.first().copied() {
+        Some("indexeddb") => parse_indexeddb(&rest[1..], id),
+        Some("origins") => Ok(json!({ "id": id, "action": "storage_origins" })),
+        Some("clear") => match rest.get(1..) {
+            Some(["--origin", origin]) => {
+                Ok(json!({ "id": id, "action": "storage_clear_origin", "origin": origin }))
+            }
+            _ => Err(ParseError::MissingArguments {
+                context: "storage clear".to_string(),
+                usage: "storage clear --origin <origin>",
+            }),
+        },
         Some("local") | Some("session") => {
-            let storage_type = rest.get(0).unwrap();
+            let storage_type = rest.first().unwrap();
             let op = rest.get(1).unwrap_or(&"get");
             let key = rest.get(2);
             let value = rest.get(3);
@@ -1297,6 +2269,63 @@ fn parse_storage(rest: &[&str], id: &str) -> Result<Value, ParseError> {
     }
 }
 
+fn parse_indexeddb(rest: &[&str], id: &str) -> Result<Value, ParseError> {
+    const USAGE: &str = "storage indexeddb <list|dump <db> [store] [--limit <n>]>";
+    match rest.first().copied() {
+        Some("list") => Ok(json!({ "id": id, "action": "indexeddb_list" })),
+        Some("dump") => {
+            let mut cmd = json!({ "id": id, "action": "indexeddb_dump" });
+            let mut names = Vec::new();
+            let mut i = 1;
+            while i < rest.len() {
+                if rest[i] == "--limit" {
+                    let value = rest.get(i + 1).ok_or_else(|| ParseError::MissingArguments {
+                        context: "storage indexeddb dump --limit".to_string(),
+                        usage: USAGE,
+                    })?;
+                    let limit = value.parse::<u64>().ok().filter(|n| *n > 0).ok_or_else(|| ParseError::InvalidValue {
+                        message: format!("Invalid --limit '{}': use a positive number of records", value),
+                        usage: USAGE,
+                    })?;
+                    cmd["limit"] = json!(limit);
+                    i += 1;
+                } else {
+                    names.push(rest[i]);
+                }
+                i += 1;
+            }
+            match names.as_slice() {
+                [db] => cmd["db"] = json!(db),
+                [db, store] => {
+                    cmd["db"] = json!(db);
+                    cmd["store"] = json!(store);
+                }
+                [] => {
+                    return Err(ParseError::MissingArguments {
+                        context: "storage indexeddb dump".to_string(),
+                        usage: USAGE,
+                    })
+                }
+                _ => {
+                    return Err(ParseError::InvalidValue {
+                        message: format!("Unexpected argument '{}'", names[2]),
+                        usage: USAGE,
+                    })
+                }
+            }
+            Ok(cmd)
+        }
+        Some(sub) => Err(ParseError::UnknownSubcommand {
+            subcommand: sub.to_string(),
+            valid_options: &["list", "dump"],
+        }),
+        None => Err(ParseError::MissingArguments {
+            context: "storage indexeddb".to_string(),
+            usage: USAGE,
+        }),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1309,16 +2338,71 @@ mod tests {
             headed: false,
             debug: false,
             headers: None,
+            headers_navigation_only: false,
+            header: Vec::new(),
+            headers_for: Vec::new(),
             executable_path: None,
+            channel: None,
+            containerized: None,
             extensions: Vec::new(),
             cdp: None,
             profile: None,
             proxy: None,
             proxy_bypass: None,
             args: None,
+            browser_args: Vec::new(),
+            host_mappings: Vec::new(),
             user_agent: None,
             provider: None,
             session_name: None,
+            ephemeral: false,
+            read_only: false,
+            wait_lock: None,
+            token: None,
+            state_dir: None,
+            state_ttl: None,
+            save_state_interval: None,
+            template: None,
+            workspace: None,
+            ignore_https_errors: false,
+            extra_ca: None,
+            rotate_profile: None,
+            allow_hosts: None,
+            block_hosts: None,
+            max_download: None,
+            block_download_types: None,
+            scroll_margin: None,
+            upload_dirs: None,
+            max_bytes: None,
+            max_requests: None,
+            budget_scope: None,
+            block: None,
+            cache: None,
+            headless_mode: None,
+            ip_family: None,
+            dns_over_https: None,
+            proxy_pool: None,
+            proxy_rotate: None,
+            max_memory: None,
+            janitor: None,
+            pool: None,
+            pool_capabilities: None,
+            schema: None,
+            output_format: None,
+            strict: true,
+            dry_run: false,
+            no_wait: false,
+            detach: false,
+            confirm_destructive: false,
+            audit: false,
+            unicode_sessions: false,
+            allow_unsafe_schemes: false,
+            policy: None,
+            confirm: None,
+            notify_webhook: None,
+            upload: None,
+            artifacts: false,
+            errors: Vec::new(),
         }
     }
 
@@ -1328,6 +2412,17 @@ mod tests {
 
     // === Cookies Tests ===
 
+    #[test]
+    fn test_eval_format() {
+        let cmd = parse_command(&args("eval [...document.links].map(a => a.href) --format csv"), &default_flags()).unwrap();
+        assert_eq!(cmd["script"], "[...document.links].map(a => a.href)");
+        assert_eq!(cmd["format"], "csv");
+        let cmd = parse_command(&args("eval document.title --format json"), &default_flags()).unwrap();
+        assert!(cmd.get("format").is_none());
+        assert!(parse_command(&args("eval 1 --format xml"), &default_flags()).is_err());
+        assert!(parse_command(&args("eval 1 --format"), &default_flags()).is_err());
+    }
+
     #[test]
     fn test_cookies_get() {
         let cmd = parse_command(&args("cookies"), &default_flags()).unwrap();
@@ -1431,6 +2526,114 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_storage_indexeddb() {
+        let cmd = parse_command(&args("storage indexeddb list"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "indexeddb_list");
+
+        let cmd = parse_command(&args("storage indexeddb dump app drafts --limit 50"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "indexeddb_dump");
+        assert_eq!(cmd["db"], "app");
+        assert_eq!(cmd["store"], "drafts");
+        assert_eq!(cmd["limit"], 50);
+
+        let cmd = parse_command(&args("storage indexeddb dump app"), &default_flags()).unwrap();
+        assert!(cmd.get("store").is_none());
+        assert!(parse_command(&args("storage indexeddb dump"), &default_flags()).is_err());
+        assert!(parse_command(&args("storage indexeddb dump app a b"), &default_flags()).is_err());
+        assert!(parse_command(&args("storage indexeddb dump app --limit 0"), &default_flags()).is_err());
+        assert!(parse_command(&args("storage indexeddb drop app"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_storage_origins() {
+        let cmd = parse_command(&args("storage origins"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "storage_origins");
+
+        let cmd = parse_command(&args("storage clear --origin example.com"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "storage_clear_origin");
+        assert_eq!(cmd["origin"], "example.com");
+        assert!(parse_command(&args("storage clear"), &default_flags()).is_err());
+        assert!(parse_command(&args("storage clear --origin"), &default_flags()).is_err());
+    }
+
+    // === Network Tests ===
+
+    #[test]
+    fn test_network_mock_from_har() {
+        let cmd = parse_command(&args("network mock --from-har session.har --url **/api/**"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "route_har");
+        assert_eq!(cmd["path"], "session.har");
+        assert_eq!(cmd["url"], "**/api/**");
+        assert_eq!(cmd["notFound"], "fallback");
+
+        let cmd = parse_command(&args("network mock --from-har s.har --fallthrough abort"), &default_flags()).unwrap();
+        assert_eq!(cmd["notFound"], "abort");
+        assert!(parse_command(&args("network mock --from-har s.har --fallthrough skip"), &default_flags()).is_err());
+        assert!(parse_command(&args("network mock"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_check_links() {
+        let cmd = parse_command(&args("check-links"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "check_links");
+        assert!(cmd.get("sameOrigin").is_none());
+
+        let cmd = parse_command(&args("check-links --same-origin --concurrency 4 --timeout 5000"), &default_flags()).unwrap();
+        assert_eq!(cmd["sameOrigin"], true);
+        assert_eq!(cmd["concurrency"], 4);
+        assert_eq!(cmd["timeout"], 5000);
+
+        assert!(parse_command(&args("check-links --concurrency 0"), &default_flags()).is_err());
+        assert!(parse_command(&args("check-links --concurrency 100"), &default_flags()).is_err());
+        assert!(parse_command(&args("check-links --timeout"), &default_flags()).is_err());
+        assert!(parse_command(&args("check-links --depth 2"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_audit_security() {
+        let cmd = parse_command(&args("audit security"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "security_audit");
+        assert!(parse_command(&args("audit secure"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_daemon_warm() {
+        let cmd = parse_command(&args("daemon warm"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "daemon_warm");
+        assert_eq!(cmd["count"], 1);
+        let cmd = parse_command(&args("daemon warm --count 0"), &default_flags()).unwrap();
+        assert_eq!(cmd["count"], 0);
+        assert!(parse_command(&args("daemon warm --count 100"), &default_flags()).is_err());
+        assert!(parse_command(&args("daemon warm --count"), &default_flags()).is_err());
+        assert!(parse_command(&args("daemon cool"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_assert_request() {
+        let cmd = parse_command(&args("assert request **/api/track --not-sent"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "assert_request");
+        assert_eq!(cmd["pattern"], "**/api/track");
+        assert_eq!(cmd["sent"], false);
+
+        let cmd = parse_command(
+            &args("assert request **/api/track --method post --status 2xx --body-path $.event --equals purchase"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["sent"], true);
+        assert_eq!(cmd["method"], "POST");
+        assert_eq!(cmd["status"], "2xx");
+        assert_eq!(cmd["bodyPath"], "$.event");
+        assert_eq!(cmd["equals"], "purchase");
+
+        assert!(parse_command(&args("assert request"), &default_flags()).is_err());
+        assert!(parse_command(&args("assert request x --status 20"), &default_flags()).is_err());
+        assert!(parse_command(&args("assert request x --body-path event"), &default_flags()).is_err());
+        assert!(parse_command(&args("assert request x --equals 1"), &default_flags()).is_err());
+        assert!(parse_command(&args("assert response x"), &default_flags()).is_err());
+    }
+
     // === Navigation Tests ===
 
     #[test]
@@ -1447,6 +2650,59 @@ mod tests {
         assert_eq!(cmd["url"], "https://example.com");
     }
 
+    #[test]
+    fn test_navigate_unsafe_scheme() {
+        let err = parse_command(&args("open file:///etc/passwd"), &default_flags()).unwrap_err();
+        assert!(matches!(err, ParseError::InvalidValue { .. }));
+        let mut flags = default_flags();
+        flags.allow_unsafe_schemes = true;
+        let cmd = parse_command(&args("open file:///tmp/page.html"), &flags).unwrap();
+        assert_eq!(cmd["url"], "file:///tmp/page.html");
+    }
+
+    #[test]
+    fn test_navigate_with_options() {
+        let cmd = parse_command(
+            &args("open --new-tab example.com --wait-until networkidle --referer https://google.com"),
+            &default_flags(),
+        )
+        .unwrap();
+        assert_eq!(cmd["url"], "https://example.com");
+        assert_eq!(cmd["waitUntil"], "networkidle");
+        assert_eq!(cmd["referer"], "https://google.com");
+        assert_eq!(cmd["newTab"], true);
+    }
+
+    #[test]
+    fn test_navigate_without_options() {
+        let cmd = parse_command(&args("open example.com"), &default_flags()).unwrap();
+        assert!(cmd.get("waitUntil").is_none());
+        assert!(cmd.get("referer").is_none());
+        assert!(cmd.get("newTab").is_none());
+    }
+
+    #[test]
+    fn test_navigate_invalid_wait_until() {
+        let result = parse_command(&args("open example.com --wait-until idle"), &default_flags());
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+        let result = parse_command(&args("open example.com --referer"), &default_flags());
+        assert!(result.is_err());
+        let result = parse_command(&args("open --new-tab"), &default_flags());
+        assert!(matches!(result, Err(ParseError::MissingArguments { .. })));
+    }
+
+    #[test]
+    fn test_command_selectors() {
+        let cmd = parse_command(&args("click #submit"), &default_flags()).unwrap();
+        assert_eq!(command_selectors(&cmd), vec!["#submit"]);
+        let cmd = parse_command(&args("drag #a #b"), &default_flags()).unwrap();
+        assert_eq!(command_selectors(&cmd), vec!["#a", "#b"]);
+        let cmd = parse_command(&args("wait #spinner"), &default_flags()).unwrap();
+        assert!(command_selectors(&cmd).is_empty());
+        let cmd = parse_command(&args("open example.com"), &default_flags()).unwrap();
+        assert!(command_selectors(&cmd).is_empty());
+    }
+
     #[test]
     fn test_navigate_with_headers() {
         let mut flags = default_flags();
@@ -1484,6 +2740,88 @@ mod tests {
         assert!(cmd.get("headers").is_none());
     }
 
+    #[test]
+    fn test_navigate_with_navigation_only_headers() {
+        let mut flags = default_flags();
+        flags.headers = Some(r#"{"X-Debug": "1"}"#.to_string());
+        flags.headers_navigation_only = true;
+        let cmd = parse_command(&args("open example.com"), &flags).unwrap();
+        assert_eq!(cmd["headers"]["X-Debug"], "1");
+        assert_eq!(cmd["headersScope"], "navigation");
+    }
+
+    #[test]
+    fn test_navigate_with_session_headers_has_no_scope() {
+        let mut flags = default_flags();
+        flags.headers = Some(r#"{"X-Debug": "1"}"#.to_string());
+        let cmd = parse_command(&args("open example.com"), &flags).unwrap();
+        assert!(cmd.get("headersScope").is_none());
+    }
+
+    #[test]
+    fn test_navigate_with_header_flags() {
+        let mut flags = default_flags();
+        flags.headers = Some(r#"{"Authorization": "Bearer token"}"#.to_string());
+        flags.header = vec!["X-One: 1".to_string(), "X-Two:two: parts".to_string()];
+        let cmd = parse_command(&args("open example.com"), &flags).unwrap();
+        assert_eq!(cmd["headers"]["Authorization"], "Bearer token");
+        assert_eq!(cmd["headers"]["X-One"], "1");
+        assert_eq!(cmd["headers"]["X-Two"], "two: parts");
+    }
+
+    #[test]
+    fn test_navigate_with_invalid_header_flag() {
+        let mut flags = default_flags();
+        flags.header = vec!["no-colon".to_string()];
+        let result = parse_command(&args("open example.com"), &flags);
+        assert!(matches!(result, Err(ParseError::InvalidValue { .. })));
+
+        flags.header = vec!["X-One: 1".to_string(), "host: evil.test".to_string()];
+        let err = parse_command(&args("open example.com"), &flags).unwrap_err();
+        assert!(err.format().contains(r#""host" is set by the browser"#));
+    }
+
+    #[test]
+    fn test_navigate_with_headers_for() {
+        let mut flags = default_flags();
+        flags.headers_for = vec![
+            r#"api.example.com={"Authorization":"Bearer token"}"#.to_string(),
+            r#"https://CDN.example.com/={"X-Key":"abc"}"#.to_string(),
+        ];
+        let cmd = parse_command(&args("open example.com"), &flags).unwrap();
+        assert_eq!(cmd["originHeaders"]["api.example.com"]["Authorization"], "Bearer token");
+        assert_eq!(cmd["originHeaders"]["https://cdn.example.com"]["X-Key"], "abc");
+        assert!(cmd.get("headers").is_none());
+    }
+
+    #[test]
+    fn test_navigate_with_headers_for_merges_same_origin() {
+        let mut flags = default_flags();
+        flags.headers_for = vec![
+            r#"api.example.com={"A":"1"}"#.to_string(),
+            r#"api.example.com={"B":"2"}"#.to_string(),
+        ];
+        let cmd = parse_command(&args("open example.com"), &flags).unwrap();
+        assert_eq!(cmd["originHeaders"]["api.example.com"]["A"], "1");
+        assert_eq!(cmd["originHeaders"]["api.example.com"]["B"], "2");
+    }
+
+    #[test]
+    fn test_navigate_with_headers_for_invalid() {
+        let mut flags = default_flags();
+        flags.headers_for = vec!["api.example.com".to_string()];
+        assert!(parse_command(&args("open example.com"), &flags).is_err());
+
+        flags.headers_for = vec![r#"api.example.com=["x"]"#.to_string()];
+        assert!(parse_command(&args("open example.com"), &flags).is_err());
+
+        flags.headers_for = vec![r#"API.example.com={"X-Key":"abc","X-Retries":3}"#.to_string()];
+        let err = parse_command(&args("open example.com"), &flags).unwrap_err();
+        assert!(err.format().contains(
+            r#"headers for 'api.example.com': value of "X-Retries" must be a string, got 3"#
+        ));
+    }
+
     // === Set Headers Tests ===
 
     #[test]
@@ -1535,10 +2873,45 @@ mod tests {
         assert_eq!(cmd["action"], "forward");
     }
 
+    #[test]
+    fn test_back_steps() {
+        let cmd = parse_command(&args("back 3"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "back");
+        assert_eq!(cmd["steps"], 3);
+
+        let cmd = parse_command(&args("forward 1"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "forward");
+        assert!(cmd.get("steps").is_none());
+    }
+
+    #[test]
+    fn test_back_invalid_steps() {
+        assert!(parse_command(&args("back 0"), &default_flags()).is_err());
+        assert!(parse_command(&args("forward two"), &default_flags()).is_err());
+    }
+
     #[test]
     fn test_reload() {
         let cmd = parse_command(&args("reload"), &default_flags()).unwrap();
         assert_eq!(cmd["action"], "reload");
+        assert!(cmd.get("ignoreCache").is_none());
+    }
+
+    #[test]
+    fn test_reload_hard() {
+        let cmd = parse_command(&args("reload --hard"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "reload");
+        assert_eq!(cmd["ignoreCache"], true);
+    }
+
+    #[test]
+    fn test_stop_and_url() {
+        let cmd = parse_command(&args("stop"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "stop");
+
+        let cmd = parse_command(&args("url"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "url");
+        assert_eq!(cmd["includeTitle"], true);
     }
 
     // === Core Actions ===
@@ -1578,12 +2951,98 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_click_expect_navigation() {
+        let cmd = parse_command(&args("click #submit --expect-navigation --wait-until networkidle"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "#submit");
+        assert_eq!(cmd["expectNavigation"], true);
+        assert_eq!(cmd["waitUntil"], "networkidle");
+        let cmd = parse_command(&args("click --expect-navigation @e2"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "@e2");
+        assert!(cmd.get("waitUntil").is_none());
+        assert!(parse_command(&args("click #submit --wait-until load"), &default_flags()).is_err());
+        assert!(parse_command(&args("click #submit --expect-navigation --wait-until idle"), &default_flags()).is_err());
+        assert!(parse_command(&args("click @e1 --new-tab --expect-navigation"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_click_expect_popup() {
+        let cmd = parse_command(&args("click @e4 --expect-popup"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "@e4");
+        assert_eq!(cmd["expectPopup"], true);
+        assert!(cmd.get("expectNavigation").is_none());
+        let cmd = parse_command(&args("click @e4 --expect-popup --wait-until domcontentloaded"), &default_flags()).unwrap();
+        assert_eq!(cmd["waitUntil"], "domcontentloaded");
+        assert!(parse_command(&args("click @e4 --expect-popup --expect-navigation"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_click_expect_download() {
+        let cmd = parse_command(&args("click #export --expect-download --save-as ./report.csv"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "download");
+        assert_eq!(cmd["selector"], "#export");
+        assert_eq!(cmd["path"], "./report.csv");
+        let cmd = parse_command(&args("click #export --expect-download"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "download");
+        assert!(cmd.get("path").is_none());
+        assert!(parse_command(&args("click #export --save-as ./report.csv"), &default_flags()).is_err());
+        assert!(parse_command(&args("click #export --expect-download --save-as"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_click_expect_clipboard() {
+        let cmd = parse_command(&args("click button.copy --expect-clipboard"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "click");
+        assert_eq!(cmd["selector"], "button.copy");
+        assert_eq!(cmd["expectClipboard"], true);
+        assert!(parse_command(&args("click @e2 --expect-clipboard --expect-popup"), &default_flags()).is_err());
+        assert!(parse_command(&args("click @e2 --expect-clipboard --wait-until load"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_click_intercept_submit() {
+        let cmd = parse_command(&args("click #pay --intercept-submit"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "click");
+        assert_eq!(cmd["interceptSubmit"], true);
+        assert!(cmd.get("allowSubmit").is_none());
+        let cmd = parse_command(&args("click #pay --intercept-submit --allow-submit"), &default_flags()).unwrap();
+        assert_eq!(cmd["allowSubmit"], true);
+        assert!(parse_command(&args("click #pay --allow-submit"), &default_flags()).is_err());
+        assert!(parse_command(&args("click #pay --intercept-submit --expect-navigation"), &default_flags()).is_err());
+    }
+
     #[test]
     fn test_click_missing_selector_should_error() {
         let result = parse_command(&args("click"), &default_flags());
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_pointer_at_coordinates() {
+        let cmd = parse_command(&args("click --at 412,380"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "click_at");
+        assert_eq!(cmd["x"], 412.0);
+        assert_eq!(cmd["y"], 380.0);
+        assert!(cmd.get("css").is_none());
+        let cmd = parse_command(&args("click --at 10.5,20 --button right --css"), &default_flags()).unwrap();
+        assert_eq!(cmd["button"], "right");
+        assert_eq!(cmd["css"], true);
+        let cmd = parse_command(&args("move 5,6"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "move_at");
+        assert_eq!(cmd["y"], 6.0);
+        let cmd = parse_command(&args("drag --from 1,2 --to 300,400"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "drag_at");
+        assert_eq!(cmd["fromY"], 2.0);
+        assert_eq!(cmd["toX"], 300.0);
+        // Selector drags are unchanged
+        assert_eq!(parse_command(&args("drag #a #b"), &default_flags()).unwrap()["action"], "drag");
+        assert!(parse_command(&args("click --at 412"), &default_flags()).is_err());
+        assert!(parse_command(&args("click --at -1,5"), &default_flags()).is_err());
+        assert!(parse_command(&args("drag --from 1,2"), &default_flags()).is_err());
+        assert!(parse_command(&args("move"), &default_flags()).is_err());
+        assert!(parse_command(&args("move 1,2 --to 3,4"), &default_flags()).is_err());
+    }
+
     #[test]
     fn test_fill() {
         let cmd = parse_command(&args("fill #input hello world"), &default_flags()).unwrap();
@@ -1592,12 +3051,76 @@ mod tests {
         assert_eq!(cmd["value"], "hello world");
     }
 
+    #[test]
+    fn test_style() {
+        let cmd = parse_command(&args("style .error --props color,display,--brand-color"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "styles");
+        assert_eq!(cmd["selector"], ".error");
+        assert_eq!(cmd["props"], json!(["color", "display", "--brand-color"]));
+        let cmd = parse_command(&args("get styles @e1 --props fontSize"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "@e1");
+        assert_eq!(cmd["props"], json!(["fontSize"]));
+        let cmd = parse_command(&args("get styles button"), &default_flags()).unwrap();
+        assert!(cmd.get("props").is_none());
+        assert!(parse_command(&args("style .error --props color;x"), &default_flags()).is_err());
+        assert!(parse_command(&args("style --props color"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_element_state() {
+        let cmd = parse_command(&args("state @e3"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "elementstate");
+        assert_eq!(cmd["selector"], "@e3");
+        let cmd = parse_command(&args("get state #submit"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "elementstate");
+        assert_eq!(cmd["selector"], "#submit");
+        // The operations keep their meaning
+        assert_eq!(parse_command(&args("state list"), &default_flags()).unwrap()["action"], "state_list");
+        assert!(parse_command(&args("get state"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_state_restore() {
+        let cmd = parse_command(&args("state restore ./auth.json --from-backup"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "state_restore");
+        assert_eq!(cmd["path"], "./auth.json");
+        assert!(cmd.get("backup").is_none());
+        let cmd = parse_command(&args("state restore ./auth.json --from-backup 2"), &default_flags()).unwrap();
+        assert_eq!(cmd["backup"], 2);
+        assert!(parse_command(&args("state restore ./auth.json"), &default_flags()).is_err());
+        assert!(parse_command(&args("state restore ./auth.json --from-backup 0"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_state_migrate() {
+        let cmd = parse_command(&args("state migrate"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "state_migrate");
+        assert_eq!(cmd["dryRun"], false);
+        assert!(cmd.get("path").is_none());
+        let flags = Flags { dry_run: true, ..default_flags() };
+        let cmd = parse_command(&args("state migrate ./auth.json"), &flags).unwrap();
+        assert_eq!(cmd["path"], "./auth.json");
+        assert_eq!(cmd["dryRun"], true);
+    }
+
     #[test]
     fn test_type_command() {
         let cmd = parse_command(&args("type #input some text"), &default_flags()).unwrap();
         assert_eq!(cmd["action"], "type");
         assert_eq!(cmd["selector"], "#input");
         assert_eq!(cmd["text"], "some text");
+        assert!(cmd.get("force").is_none());
+    }
+
+    #[test]
+    fn test_force_skips_actionability_checks() {
+        let cmd = parse_command(&args("type #input --force some text"), &default_flags()).unwrap();
+        assert_eq!(cmd["text"], "some text");
+        assert_eq!(cmd["force"], true);
+        let cmd = parse_command(&args("click #save --force"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "#save");
+        assert_eq!(cmd["force"], true);
+        assert!(parse_command(&args("click #export --expect-download --force"), &default_flags()).is_err());
     }
 
     #[test]
@@ -1727,6 +3250,60 @@ mod tests {
         assert_eq!(cmd["path"], "./button.png");
     }
 
+    #[test]
+    fn test_screenshot_annotate() {
+        let cmd = parse_command(&args("screenshot --annotate page.png"), &default_flags()).unwrap();
+        assert_eq!(cmd["annotate"], true);
+        assert_eq!(cmd["path"], "page.png");
+        assert!(cmd["selector"].is_null());
+        let cmd = parse_command(&args("screenshot page.png"), &default_flags()).unwrap();
+        assert!(cmd.get("annotate").is_none());
+    }
+
+    #[test]
+    fn test_text() {
+        let cmd = parse_command(&args("text"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "text");
+        assert!(cmd.get("selector").is_none());
+        let cmd = parse_command(&args("text --pages 2-4"), &default_flags()).unwrap();
+        assert_eq!(cmd["pages"], "2-4");
+        assert!(parse_command(&args("text --pages 4-2"), &default_flags()).is_err());
+        assert!(parse_command(&args("text --ocr --pages 1"), &default_flags()).is_err());
+        let cmd = parse_command(&args("text --ocr"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "screenshot");
+        assert_eq!(cmd["ocr"]["lang"], "eng");
+        assert!(cmd["selector"].is_null());
+        let cmd = parse_command(&args("text #chart --ocr --lang eng+deu"), &default_flags()).unwrap();
+        assert_eq!(cmd["selector"], "#chart");
+        assert_eq!(cmd["ocr"]["lang"], "eng+deu");
+        assert!(parse_command(&args("text --lang eng"), &default_flags()).is_err());
+        assert!(parse_command(&args("text --ocr --lang ../x"), &default_flags()).is_err());
+        let cmd = parse_command(&args("text --detect-lang --translate-to pt-BR"), &default_flags()).unwrap();
+        assert_eq!(cmd["detectLang"], true);
+        assert_eq!(cmd["translateTo"], "pt-BR");
+        let cmd = parse_command(&args("text --ocr --translate-to en"), &default_flags()).unwrap();
+        assert_eq!(cmd["translateTo"], "en");
+        assert!(parse_command(&args("text --translate-to english"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_article() {
+        let cmd = parse_command(&args("article"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "article");
+        assert!(parse_command(&args("article https://example.com"), &default_flags()).is_err());
+    }
+
+    #[test]
+    fn test_screenshot_grid() {
+        let cmd = parse_command(&args("screenshot --grid 50 page.png"), &default_flags()).unwrap();
+        assert_eq!(cmd["grid"], 50);
+        assert_eq!(cmd["path"], "page.png");
+        let cmd = parse_command(&args("screenshot page.png --grid"), &default_flags()).unwrap();
+        assert_eq!(cmd["grid"], 100);
+        assert_eq!(cmd["path"], "page.png");
+        assert!(parse_command(&args("screenshot --grid 5"), &default_flags()).is_err());
+    }
+
     // === Snapshot ===
 
     #[test]
@@ -1756,6 +3333,24 @@ mod tests {
         assert_eq!(cmd["maxDepth"], 3);
     }
 
+    #[test]
+    fn test_snapshot_no_cache() {
+        let cmd = parse_command(&args("snapshot -i --no-cache"), &default_flags()).unwrap();
+        assert_eq!(cmd["noCache"], true);
+        assert!(parse_command(&args("snapshot"), &default_flags()).unwrap().get("noCache").is_none());
+    }
+
+    #[test]
+    fn test_snapshot_diff_from() {
+        let cmd = parse_command(&args("snapshot -i --diff-from s3"), &default_flags()).unwrap();
+        assert_eq!(cmd["diffFrom"], "s3");
+        assert_eq!(cmd["interactive"], true);
+        assert!(matches!(
+            parse_command(&args("snapshot --diff-from"), &default_flags()),
+            Err(ParseError::MissingArguments { .. })
+        ));
+    }
+
     // === Wait ===
 
     #[test]
@@ -1779,6 +3374,32 @@ mod tests {
         assert_eq!(cmd["url"], "**/dashboard");
     }
 
+    #[test]
+    fn test_wait_route() {
+        let cmd = parse_command(&args("wait --route **/settings"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "waitforroute");
+        assert_eq!(cmd["route"], "**/settings");
+        assert!(cmd.get("timeout").is_none());
+
+        let cmd =
+            parse_command(&args("wait --route #/cart --timeout 5000"), &default_flags()).unwrap();
+        assert_eq!(cmd["route"], "#/cart");
+        assert_eq!(cmd["timeout"], 5000);
+    }
+
+    #[test]
+    fn test_open_spa() {
+        let cmd = parse_command(&args("open --spa #/settings"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "spanavigate");
+        assert_eq!(cmd["route"], "#/settings");
+        assert_eq!(cmd["mode"], "hash");
+
+        let cmd = parse_command(&args("open --spa /settings/profile"), &default_flags()).unwrap();
+        assert_eq!(cmd["mode"], "history");
+
+        assert!(parse_command(&args("open --spa"), &default_flags()).is_err());
+    }
+
     #[test]
     fn test_wait_load() {
         let cmd = parse_command(&args("wait --load networkidle"), &default_flags()).unwrap();
@@ -1851,6 +3472,21 @@ mod tests {
         assert_eq!(cmd["action"], "recording_stop");
     }
 
+    #[test]
+    fn test_record_gif() {
+        let cmd = parse_command(&args("record gif --out repro.gif --commands 5 --delay 400"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "gif_start");
+        assert_eq!(cmd["path"], "repro.gif");
+        assert_eq!(cmd["commands"], 5);
+        assert_eq!(cmd["delay"], 400);
+        let cmd = parse_command(&args("record gif --out repro.apng"), &default_flags()).unwrap();
+        assert!(cmd.get("commands").is_none());
+        assert_eq!(parse_command(&args("record gif stop"), &default_flags()).unwrap()["action"], "gif_stop");
+        assert!(parse_command(&args("record gif"), &default_flags()).is_err());
+        assert!(parse_command(&args("record gif --out repro.webm"), &default_flags()).is_err());
+        assert!(parse_command(&args("record gif --out repro.gif --commands 0"), &default_flags()).is_err());
+    }
+
     #[test]
     fn test_record_restart() {
         let cmd = parse_command(&args("record restart output.webm"), &default_flags()).unwrap();
@@ -2152,4 +3788,25 @@ mod tests {
         assert_eq!(cmd["action"], "launch");
         assert_eq!(cmd["cdpPort"], 1);
     }
+
+    #[test]
+    fn test_job_commands() {
+        let cmd = parse_command(&args("job status job-3"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "job_status");
+        assert_eq!(cmd["jobId"], "job-3");
+        let cmd = parse_command(&args("job result job-3"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "job_result");
+        assert!(matches!(
+            parse_command(&args("job status"), &default_flags()),
+            Err(ParseError::MissingArguments { .. })
+        ));
+        let cmd = parse_command(&args("job cancel job-3"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "job_cancel");
+        let cmd = parse_command(&args("job list"), &default_flags()).unwrap();
+        assert_eq!(cmd["action"], "job_list");
+        assert!(matches!(
+            parse_command(&args("job pause job-3"), &default_flags()),
+            Err(ParseError::UnknownSubcommand { .. })
+        ));
+    }
 }
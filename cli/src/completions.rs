@@ -0,0 +1,410 @@
+//! Shell completion scripts generated from the command registry.
+//!
+//! Session names are completed dynamically: the scripts call
+//! `agent-browser completions --sessions`, which lists the running sessions.
+
+use crate::registry::{CommandSpec, FlagSpec, COMMANDS, DEVICE_PRESETS, GLOBAL_FLAGS, INFO_FLAGS};
+
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish", "powershell"];
+
+/// Global flags whose value is a filesystem path
+const PATH_FLAGS: &[&str] =
+    &["--profile", "--executable-path", "--extension", "--extra-ca", "--rotate-profile"];
+
+/// Generate the completion script for `shell`, or None if it isn't supported
+pub fn generate(shell: &str) -> Option<String> {
+    match shell {
+        "bash" => Some(bash()),
+        "zsh" => Some(zsh()),
+        "fish" => Some(fish()),
+        "powershell" | "pwsh" => Some(powershell()),
+        _ => None,
+    }
+}
+
+fn all_flags() -> impl Iterator<Item = &'static FlagSpec> {
+    GLOBAL_FLAGS.iter().chain(INFO_FLAGS)
+}
+
+fn flag_names() -> Vec<&'static str> {
+    all_flags().flat_map(|f| std::iter::once(f.name).chain(f.short)).collect()
+}
+
+fn value_flag_names() -> Vec<&'static str> {
+    GLOBAL_FLAGS
+        .iter()
+        .filter(|f| f.value.is_some())
+        .flat_map(|f| std::iter::once(f.name).chain(f.short))
+        .collect()
+}
+
+fn names_of(cmd: &CommandSpec) -> Vec<&'static str> {
+    std::iter::once(cmd.name).chain(cmd.aliases.iter().copied()).collect()
+}
+
+/// Subcommands and command-specific flags offered after `cmd`
+fn command_words(cmd: &CommandSpec) -> Vec<&'static str> {
+    cmd.subcommands.iter().copied().chain(cmd.flags.iter().map(|f| f.name)).collect()
+}
+
+/// Quote for POSIX shells using single quotes
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+fn bash() -> String {
+    let commands: Vec<&str> = COMMANDS.iter().flat_map(names_of).collect();
+    let mut cases = String::new();
+    for cmd in COMMANDS {
+        if cmd.subcommands.is_empty() && cmd.flags.is_empty() {
+            continue;
+        }
+        cases.push_str(&format!(
+            "        {})\n            words_for_cmd=\"{}\"\n            ;;\n",
+            names_of(cmd).join("|"),
+            command_words(cmd).join(" ")
+        ));
+    }
+    let devices: Vec<String> = DEVICE_PRESETS.iter().map(|d| d.to_string()).collect();
+
+    format!(
+        r#"# bash completion for agent-browser
+# Install: agent-browser completions bash > /etc/bash_completion.d/agent-browser
+#      or: source <(agent-browser completions bash)
+
+_agent_browser() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}"
+    local prev="${{COMP_WORDS[COMP_CWORD-1]}}"
+    local commands="{commands}"
+    local global_flags="{flags}"
+    local value_flags=" {value_flags} "
+    local cmd="" i w
+
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        w="${{COMP_WORDS[i]}}"
+        if [[ "$value_flags" == *" $w "* ]]; then
+            ((i++))
+        elif [[ "$w" != -* ]]; then
+            cmd="$w"
+            break
+        fi
+    done
+
+    case "$prev" in
+        --session)
+            COMPREPLY=($(compgen -W "$(agent-browser completions --sessions 2>/dev/null)" -- "$cur"))
+            return
+            ;;
+        {path_flags})
+            COMPREPLY=($(compgen -f -- "$cur"))
+            return
+            ;;
+    esac
+
+    if [[ -z "$cmd" ]]; then
+        if [[ "$cur" == -* ]]; then
+            COMPREPLY=($(compgen -W "$global_flags" -- "$cur"))
+        else
+            COMPREPLY=($(compgen -W "$commands" -- "$cur"))
+        fi
+        return
+    fi
+
+    if [[ "$cmd" == set && "$prev" == device ]]; then
+        local IFS=$'\n'
+        COMPREPLY=($(compgen -W "{devices}" -- "$cur"))
+        COMPREPLY=("${{COMPREPLY[@]// /\\ }}")
+        return
+    fi
+
+    local words_for_cmd=""
+    case "$cmd" in
+{cases}    esac
+
+    if [[ "$cur" == -* ]]; then
+        words_for_cmd="$words_for_cmd $global_flags"
+    fi
+    COMPREPLY=($(compgen -W "$words_for_cmd" -- "$cur"))
+}}
+
+complete -o default -F _agent_browser agent-browser
+"#,
+        commands = commands.join(" "),
+        flags = flag_names().join(" "),
+        value_flags = value_flag_names().join(" "),
+        path_flags = PATH_FLAGS.join("|"),
+        devices = devices.join("\n"),
+        cases = cases,
+    )
+}
+
+fn zsh() -> String {
+    let describe = |name: &str, desc: &str| sh_quote(&format!("{}:{}", name, desc.replace(':', "\\:")));
+    let commands: Vec<String> = COMMANDS
+        .iter()
+        .flat_map(|c| names_of(c).into_iter().map(move |n| describe(n, c.summary)))
+        .collect();
+    let flags: Vec<String> = all_flags()
+        .flat_map(|f| std::iter::once(f.name).chain(f.short).map(move |n| describe(n, f.description)))
+        .collect();
+    let mut cases = String::new();
+    for cmd in COMMANDS {
+        if cmd.subcommands.is_empty() && cmd.flags.is_empty() {
+            continue;
+        }
+        cases.push_str(&format!(
+            "        ({})\n            compadd -- {}\n            ;;\n",
+            names_of(cmd).join("|"),
+            command_words(cmd).join(" ")
+        ));
+    }
+    let devices: Vec<String> = DEVICE_PRESETS.iter().map(|d| sh_quote(d)).collect();
+
+    format!(
+        r#"#compdef agent-browser
+# zsh completion for agent-browser
+# Install: agent-browser completions zsh > "${{fpath[1]}}/_agent-browser"
+#      or: source <(agent-browser completions zsh)
+
+_agent_browser() {{
+    local -a commands global_flags
+    commands=(
+        {commands}
+    )
+    global_flags=(
+        {flags}
+    )
+    local cmd="" i w
+
+    for ((i = 2; i < CURRENT; i++)); do
+        w="${{words[i]}}"
+        case "$w" in
+            ({value_flags})
+                ((i++))
+                ;;
+            (-*)
+                ;;
+            (*)
+                cmd="$w"
+                break
+                ;;
+        esac
+    done
+
+    case "${{words[CURRENT-1]}}" in
+        (--session)
+            compadd -- ${{(f)"$(agent-browser completions --sessions 2>/dev/null)"}}
+            return
+            ;;
+        ({path_flags})
+            _files
+            return
+            ;;
+    esac
+
+    if [[ -z "$cmd" ]]; then
+        if [[ "${{words[CURRENT]}}" == -* ]]; then
+            _describe 'option' global_flags
+        else
+            _describe 'command' commands
+        fi
+        return
+    fi
+
+    if [[ "$cmd" == set && "${{words[CURRENT-1]}}" == device ]]; then
+        compadd -- {devices}
+        return
+    fi
+
+    if [[ "${{words[CURRENT]}}" == -* ]]; then
+        _describe 'option' global_flags
+    fi
+    case "$cmd" in
+{cases}    esac
+}}
+
+if [[ "${{zsh_eval_context[-1]}}" == loadautofunc ]]; then
+    _agent_browser "$@"
+else
+    compdef _agent_browser agent-browser
+fi
+"#,
+        commands = commands.join("\n        "),
+        flags = flags.join("\n        "),
+        value_flags = value_flag_names().join("|"),
+        path_flags = PATH_FLAGS.join("|"),
+        devices = devices.join(" "),
+        cases = cases,
+    )
+}
+
+fn fish() -> String {
+    let mut out = String::from(
+        "# fish completion for agent-browser\n\
+         # Install: agent-browser completions fish > ~/.config/fish/completions/agent-browser.fish\n\n\
+         complete -c agent-browser -f\n",
+    );
+    for flag in all_flags() {
+        let mut line = format!("complete -c agent-browser -l {}", flag.name.trim_start_matches("--"));
+        if let Some(short) = flag.short {
+            line.push_str(&format!(" -s {}", short.trim_start_matches('-')));
+        }
+        if flag.name == "--session" {
+            line.push_str(" -x -a '(agent-browser completions --sessions 2>/dev/null)'");
+        } else if PATH_FLAGS.contains(&flag.name) {
+            line.push_str(" -r -F");
+        } else if flag.value.is_some() {
+            line.push_str(" -x");
+        }
+        line.push_str(&format!(" -d {}\n", sh_quote(flag.description)));
+        out.push_str(&line);
+    }
+    out.push('\n');
+    for cmd in COMMANDS {
+        for name in names_of(cmd) {
+            out.push_str(&format!(
+                "complete -c agent-browser -n __fish_use_subcommand -a {} -d {}\n",
+                name,
+                sh_quote(cmd.summary)
+            ));
+        }
+    }
+    out.push('\n');
+    for cmd in COMMANDS {
+        let condition = format!("__fish_seen_subcommand_from {}", names_of(cmd).join(" "));
+        if !cmd.subcommands.is_empty() {
+            out.push_str(&format!(
+                "complete -c agent-browser -n {} -a {}\n",
+                sh_quote(&condition),
+                sh_quote(&cmd.subcommands.join(" "))
+            ));
+        }
+        for flag in cmd.flags {
+            out.push_str(&format!(
+                "complete -c agent-browser -n {} -l {} -d {}\n",
+                sh_quote(&condition),
+                flag.name.trim_start_matches("--"),
+                sh_quote(flag.description)
+            ));
+        }
+    }
+    let devices: Vec<String> = DEVICE_PRESETS.iter().map(|d| format!("'{}'", d)).collect();
+    out.push_str(&format!(
+        "complete -c agent-browser -n '__fish_seen_subcommand_from set; and __fish_seen_subcommand_from device' -a \"{}\"\n",
+        devices.join(" ")
+    ));
+    out
+}
+
+/// Quote for PowerShell single-quoted strings
+fn ps_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+fn powershell() -> String {
+    let ps_list = |items: Vec<&str>| items.iter().map(|s| ps_quote(s)).collect::<Vec<_>>().join(", ");
+    let commands: Vec<String> = COMMANDS
+        .iter()
+        .flat_map(|c| names_of(c).into_iter().map(move |n| format!("{} = {}", ps_quote(n), ps_quote(c.summary))))
+        .collect();
+    let mut words = Vec::new();
+    for cmd in COMMANDS {
+        if cmd.subcommands.is_empty() && cmd.flags.is_empty() {
+            continue;
+        }
+        let list = ps_list(command_words(cmd));
+        for name in names_of(cmd) {
+            words.push(format!("{} = @({})", ps_quote(name), list));
+        }
+    }
+
+    format!(
+        r#"# PowerShell completion for agent-browser
+# Install: agent-browser completions powershell | Out-String | Invoke-Expression
+#     (add the line above to your $PROFILE to load it in every session)
+
+Register-ArgumentCompleter -Native -CommandName agent-browser -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+
+    $commands = [ordered]@{{
+        {commands}
+    }}
+    $globalFlags = @({flags})
+    $valueFlags = @({value_flags})
+    $pathFlags = @({path_flags})
+    $commandWords = @{{
+        {words}
+    }}
+    $devices = @({devices})
+
+    $elements = @($commandAst.CommandElements | Select-Object -Skip 1 | ForEach-Object {{ $_.ToString() }})
+    if ($wordToComplete -ne '' -and $elements.Count -gt 0) {{
+        $elements = @($elements | Select-Object -SkipLast 1)
+    }}
+    $prev = if ($elements.Count -gt 0) {{ $elements[-1] }} else {{ '' }}
+
+    $cmd = $null
+    for ($i = 0; $i -lt $elements.Count; $i++) {{
+        if ($valueFlags -contains $elements[$i]) {{ $i++; continue }}
+        if (-not $elements[$i].StartsWith('-')) {{ $cmd = $elements[$i]; break }}
+    }}
+
+    $candidates = @()
+    if ($prev -eq '--session') {{
+        $candidates = @(agent-browser completions --sessions 2>$null)
+    }} elseif ($pathFlags -contains $prev) {{
+        return
+    }} elseif ($null -eq $cmd) {{
+        if ($wordToComplete.StartsWith('-')) {{ $candidates = $globalFlags }} else {{ $candidates = @($commands.Keys) }}
+    }} elseif ($cmd -eq 'set' -and $prev -eq 'device') {{
+        $candidates = $devices | ForEach-Object {{ "'$_'" }}
+    }} else {{
+        if ($commandWords.Contains($cmd)) {{ $candidates = $commandWords[$cmd] }}
+        if ($wordToComplete.StartsWith('-')) {{ $candidates += $globalFlags }}
+    }}
+
+    $candidates | Where-Object {{ $_ -like "$wordToComplete*" }} | ForEach-Object {{
+        $tooltip = if ($commands.Contains($_)) {{ $commands[$_] }} else {{ $_ }}
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $tooltip)
+    }}
+}}
+"#,
+        commands = commands.join("\n        "),
+        flags = ps_list(flag_names()),
+        value_flags = ps_list(value_flag_names()),
+        path_flags = ps_list(PATH_FLAGS.to_vec()),
+        words = words.join("\n        "),
+        devices = ps_list(DEVICE_PRESETS.to_vec()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_all_shells() {
+        for shell in SHELLS {
+            let script = generate(shell).unwrap();
+            assert!(script.contains("snapshot"), "{} script lists commands", shell);
+            assert!(script.contains("completions --sessions"), "{} completes sessions", shell);
+            assert!(script.contains("iPhone 12"), "{} completes devices", shell);
+        }
+        assert!(generate("tcsh").is_none());
+    }
+
+    #[test]
+    fn test_bash_includes_aliases_and_value_flags() {
+        let script = generate("bash").unwrap();
+        assert!(script.contains("open|goto|navigate)"));
+        assert!(script.contains(" --session "));
+        assert!(script.contains("complete -o default -F _agent_browser agent-browser"));
+    }
+
+    #[test]
+    fn test_zsh_escapes_colons_in_descriptions() {
+        assert_eq!(sh_quote("it's"), r"'it'\''s'");
+        let script = generate("zsh").unwrap();
+        assert!(script.contains("'open:Navigate to URL'"));
+    }
+}
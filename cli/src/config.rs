@@ -0,0 +1,268 @@
+//! User configuration file and session templates.
+//!
+//! The config lives at `~/.agent-browser/config.json` (or AGENT_BROWSER_CONFIG).
+//! `sessionTemplates` maps template names to settings applied when a session's
+//! browser starts with `--template <name>`:
+//!
+//! ```json
+//! {
+//!   "sessionTemplates": {
+//!     "mobile-de": {
+//!       "device": "iPhone 14",
+//!       "headers": { "Accept-Language": "de-DE" },
+//!       "proxy": "http://de.proxy.example:8080",
+//!       "cookies": [{ "name": "consent", "value": "1", "url": "https://example.de" }]
+//!     }
+//!   },
+//!   "browserArgs": ["--disable-gpu"]
+//! }
+//! ```
+//!
+//! `browserArgs` (top level and per template) are Chromium switches added to
+//! every browser launch, ahead of any --browser-arg flags. `hooks` is
+//! described in the hooks module, `translator` in the language module.
+//! `contentHash` lists what the `contentHash` of text and HTML results
+//! ignores: `ignoreSelectors` (regions such as ads) and `ignorePatterns`
+//! (regular expressions such as timestamps).
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::commands::gen_id;
+use crate::connection::app_data_dir;
+use crate::flags::Flags;
+use crate::hooks::Hooks;
+use crate::language::Translator;
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Config {
+    pub session_templates: BTreeMap<String, SessionTemplate>,
+    /// Browser switches added to every browser launch, before --browser-arg
+    pub browser_args: Vec<String>,
+    pub hooks: Hooks,
+    /// Backend for `text --translate-to`
+    pub translator: Option<Translator>,
+    /// What the content hash of `text` and `get text`/`get html` leaves out
+    pub content_hash: ContentHash,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct ContentHash {
+    /// Regions, by CSS selector, such as ad slots or a "last updated" line
+    pub ignore_selectors: Vec<String>,
+    /// Regular expressions (JavaScript syntax), such as timestamps
+    pub ignore_patterns: Vec<String>,
+}
+
+impl ContentHash {
+    /// The `hashIgnore` field of commands returning text or HTML, if anything is ignored
+    pub fn command_field(&self) -> Option<Value> {
+        if self.ignore_selectors.is_empty() && self.ignore_patterns.is_empty() {
+            return None;
+        }
+        Some(json!({ "selectors": self.ignore_selectors, "patterns": self.ignore_patterns }))
+    }
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct SessionTemplate {
+    pub device: Option<String>,
+    pub viewport: Option<Viewport>,
+    pub headers: BTreeMap<String, String>,
+    pub proxy: Option<String>,
+    pub proxy_bypass: Option<String>,
+    pub user_agent: Option<String>,
+    pub browser_args: Vec<String>,
+    /// Cookies set when the session starts; each needs a `url` or a `domain`
+    pub cookies: Vec<Value>,
+}
+
+pub fn config_path() -> PathBuf {
+    if let Ok(path) = env::var("AGENT_BROWSER_CONFIG") {
+        if !path.is_empty() {
+            return PathBuf::from(path);
+        }
+    }
+    app_data_dir().join("config.json")
+}
+
+pub fn parse_config(source: &str) -> Result<Config, String> {
+    let config: Config =
+        serde_json::from_str(source).map_err(|e| format!("Invalid config: {}", e))?;
+    for (name, template) in &config.session_templates {
+        template
+            .validate()
+            .map_err(|e| format!("Invalid session template '{}': {}", name, e))?;
+    }
+    config.hooks.validate().map_err(|e| format!("Invalid hooks: {}", e))?;
+    Ok(config)
+}
+
+/// Load the config file; a missing file is an empty config
+pub fn load_config() -> Result<Config, String> {
+    let path = config_path();
+    match fs::read_to_string(&path) {
+        Ok(source) => parse_config(&source),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+        Err(e) => Err(format!("Cannot read config '{}': {}", path.display(), e)),
+    }
+}
+
+impl Config {
+    pub fn into_template(mut self, name: &str) -> Result<SessionTemplate, String> {
+        self.session_templates.remove(name).ok_or_else(|| {
+            let known: Vec<&str> = self.session_templates.keys().map(|k| k.as_str()).collect();
+            if known.is_empty() {
+                format!(
+                    "Unknown template '{}': no sessionTemplates in {}",
+                    name,
+                    config_path().display()
+                )
+            } else {
+                format!("Unknown template '{}'. Available: {}", name, known.join(", "))
+            }
+        })
+    }
+}
+
+impl SessionTemplate {
+    fn validate(&self) -> Result<(), String> {
+        for cookie in &self.cookies {
+            let has = |key: &str| cookie.get(key).is_some_and(|v| v.is_string());
+            if !has("name") || !has("value") {
+                return Err("cookies need a name and a value".to_string());
+            }
+            if !has("url") && !has("domain") {
+                return Err("cookies need a url or a domain".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Fill launch-time flags the user didn't set explicitly
+    pub fn apply_launch_flags(&self, flags: &mut Flags) {
+        if flags.proxy.is_none() {
+            flags.proxy = self.proxy.clone();
+        }
+        if flags.proxy_bypass.is_none() {
+            flags.proxy_bypass = self.proxy_bypass.clone();
+        }
+        if flags.user_agent.is_none() {
+            flags.user_agent = self.user_agent.clone();
+        }
+        flags.browser_args.splice(0..0, self.browser_args.iter().cloned());
+    }
+
+    /// Commands that configure a freshly started browser. The viewport is
+    /// applied after the device so it can override the device's size.
+    pub fn setup_commands(&self) -> Vec<Value> {
+        let mut commands = Vec::new();
+        if let Some(ref device) = self.device {
+            commands.push(json!({ "id": gen_id(), "action": "device", "device": device }));
+        }
+        if let Some(ref viewport) = self.viewport {
+            commands.push(json!({
+                "id": gen_id(),
+                "action": "viewport",
+                "width": viewport.width,
+                "height": viewport.height,
+            }));
+        }
+        if !self.headers.is_empty() {
+            commands.push(json!({ "id": gen_id(), "action": "headers", "headers": self.headers }));
+        }
+        if !self.cookies.is_empty() {
+            commands.push(json!({ "id": gen_id(), "action": "cookies_set", "cookies": self.cookies }));
+        }
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"{
+        "sessionTemplates": {
+            "mobile-de": {
+                "device": "iPhone 14",
+                "viewport": { "width": 390, "height": 700 },
+                "headers": { "Accept-Language": "de-DE" },
+                "proxy": "http://de.proxy.example:8080",
+                "cookies": [{ "name": "consent", "value": "1", "url": "https://example.de" }]
+            },
+            "desktop": { "userAgent": "Bot/1.0", "browserArgs": ["--force-dark-mode"] }
+        },
+        "browserArgs": ["--disable-gpu"]
+    }"#;
+
+    #[test]
+    fn test_parse_templates() {
+        assert_eq!(parse_config(CONFIG).unwrap().browser_args, vec!["--disable-gpu"]);
+        let mobile = parse_config(CONFIG).unwrap().into_template("mobile-de").unwrap();
+        assert_eq!(mobile.device.as_deref(), Some("iPhone 14"));
+        assert_eq!(mobile.viewport, Some(Viewport { width: 390, height: 700 }));
+        let err = parse_config(CONFIG).unwrap().into_template("tablet").unwrap_err();
+        assert!(err.contains("Available: desktop, mobile-de"));
+    }
+
+    #[test]
+    fn test_setup_commands_order() {
+        let commands = parse_config(CONFIG).unwrap().into_template("mobile-de").unwrap().setup_commands();
+        let actions: Vec<&str> = commands.iter().map(|c| c["action"].as_str().unwrap()).collect();
+        assert_eq!(actions, vec!["device", "viewport", "headers", "cookies_set"]);
+        assert_eq!(commands[2]["headers"]["Accept-Language"], "de-DE");
+        let desktop = parse_config(CONFIG).unwrap().into_template("desktop").unwrap();
+        assert!(desktop.setup_commands().is_empty());
+    }
+
+    #[test]
+    fn test_explicit_flags_win() {
+        let mobile = parse_config(CONFIG).unwrap().into_template("mobile-de").unwrap();
+        let mut flags = crate::flags::parse_flags(&["--proxy".to_string(), "http://mine:1".to_string()]);
+        mobile.apply_launch_flags(&mut flags);
+        assert_eq!(flags.proxy.as_deref(), Some("http://mine:1"));
+
+        let mut flags = crate::flags::parse_flags(&["--browser-arg".to_string(), "--mute-audio".to_string()]);
+        flags.user_agent = None;
+        let desktop = parse_config(CONFIG).unwrap().into_template("desktop").unwrap();
+        desktop.apply_launch_flags(&mut flags);
+        assert_eq!(flags.user_agent.as_deref(), Some("Bot/1.0"));
+        assert_eq!(flags.browser_args, vec!["--force-dark-mode", "--mute-audio"]);
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        let bad_cookie = r#"{ "sessionTemplates": { "x": { "cookies": [{ "name": "a", "value": "b" }] } } }"#;
+        assert!(parse_config(bad_cookie).unwrap_err().contains("url or a domain"));
+        let typo = r#"{ "sessionTemplates": { "x": { "devise": "iPhone 14" } } }"#;
+        assert!(parse_config(typo).is_err());
+        assert_eq!(parse_config("{}").unwrap(), Config::default());
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(parse_config("{}").unwrap().content_hash.command_field(), None);
+        let config = parse_config(
+            r#"{ "contentHash": { "ignoreSelectors": [".ad"], "ignorePatterns": ["\\d{2}:\\d{2}"] } }"#,
+        )
+        .unwrap();
+        let field = config.content_hash.command_field().unwrap();
+        assert_eq!(field["selectors"], json!([".ad"]));
+        assert_eq!(field["patterns"], json!(["\\d{2}:\\d{2}"]));
+        assert!(parse_config(r#"{ "contentHash": { "ignore": [".ad"] } }"#).is_err());
+    }
+}
@@ -2,8 +2,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::env;
 use std::fs;
-use std::io::{BufRead, BufReader, Read, Write};
-use std::net::TcpStream;
+use std::io::{BufRead, BufReader, Lines, Read, Write};
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
 use std::thread;
@@ -11,6 +10,12 @@ use std::time::Duration;
 
 #[cfg(unix)]
 use std::os::unix::net::UnixStream;
+#[cfg(windows)]
+use std::fs::File;
+
+use crate::flags::Flags;
+use crate::framing;
+use crate::state_gc;
 
 #[derive(Serialize)]
 #[allow(dead_code)]
@@ -26,13 +31,17 @@ pub struct Response {
     pub success: bool,
     pub data: Option<Value>,
     pub error: Option<String>,
+    /// How the command ran (e.g. `blocked` counts); printed in the v2 `meta` block
+    #[serde(default, skip_serializing)]
+    pub meta: Option<Value>,
 }
 
 #[allow(dead_code)]
 pub enum Connection {
     #[cfg(unix)]
     Unix(UnixStream),
-    Tcp(TcpStream),
+    #[cfg(windows)]
+    Pipe(File),
 }
 
 impl Read for Connection {
@@ -40,7 +49,8 @@ impl Read for Connection {
         match self {
             #[cfg(unix)]
             Connection::Unix(s) => s.read(buf),
-            Connection::Tcp(s) => s.read(buf),
+            #[cfg(windows)]
+            Connection::Pipe(s) => s.read(buf),
         }
     }
 }
@@ -50,7 +60,8 @@ impl Write for Connection {
         match self {
             #[cfg(unix)]
             Connection::Unix(s) => s.write(buf),
-            Connection::Tcp(s) => s.write(buf),
+            #[cfg(windows)]
+            Connection::Pipe(s) => s.write(buf),
         }
     }
 
@@ -58,7 +69,8 @@ impl Write for Connection {
         match self {
             #[cfg(unix)]
             Connection::Unix(s) => s.flush(),
-            Connection::Tcp(s) => s.flush(),
+            #[cfg(windows)]
+            Connection::Pipe(s) => s.flush(),
         }
     }
 }
@@ -68,7 +80,9 @@ impl Connection {
         match self {
             #[cfg(unix)]
             Connection::Unix(s) => s.set_read_timeout(dur),
-            Connection::Tcp(s) => s.set_read_timeout(dur),
+            // Synchronous pipe handles have no timeouts
+            #[cfg(windows)]
+            Connection::Pipe(_) => Ok(()),
         }
     }
 
@@ -76,13 +90,29 @@ impl Connection {
         match self {
             #[cfg(unix)]
             Connection::Unix(s) => s.set_write_timeout(dur),
-            Connection::Tcp(s) => s.set_write_timeout(dur),
+            #[cfg(windows)]
+            Connection::Pipe(_) => Ok(()),
+        }
+    }
+}
+
+/// Per-user data directory: %APPDATA%\agent-browser on Windows,
+/// ~/.agent-browser elsewhere
+pub fn app_data_dir() -> PathBuf {
+    #[cfg(windows)]
+    {
+        if let Some(appdata) = dirs::data_dir() {
+            return appdata.join("agent-browser");
         }
     }
+    if let Some(home) = dirs::home_dir() {
+        return home.join(".agent-browser");
+    }
+    env::temp_dir().join("agent-browser")
 }
 
 /// Get the base directory for socket/pid files.
-/// Priority: AGENT_BROWSER_SOCKET_DIR > XDG_RUNTIME_DIR > ~/.agent-browser > tmpdir
+/// Priority: AGENT_BROWSER_SOCKET_DIR > XDG_RUNTIME_DIR > app data dir
 pub fn get_socket_dir() -> PathBuf {
     // 1. Explicit override (ignore empty string)
     if let Ok(dir) = env::var("AGENT_BROWSER_SOCKET_DIR") {
@@ -98,13 +128,8 @@ pub fn get_socket_dir() -> PathBuf {
         }
     }
 
-    // 3. Home directory fallback (like Docker Desktop's ~/.docker/run/)
-    if let Some(home) = dirs::home_dir() {
-        return home.join(".agent-browser");
-    }
-
-    // 4. Last resort: temp dir
-    env::temp_dir().join("agent-browser")
+    // 3. ~/.agent-browser (like Docker Desktop's ~/.docker/run/), or %APPDATA% on Windows
+    app_data_dir()
 }
 
 #[cfg(unix)]
@@ -116,50 +141,79 @@ fn get_pid_path(session: &str) -> PathBuf {
     get_socket_dir().join(format!("{}.pid", session))
 }
 
-#[cfg(windows)]
-fn get_port_path(session: &str) -> PathBuf {
-    get_socket_dir().join(format!("{}.port", session))
+/// Whether the session's daemon was started with --ephemeral: it leaves a
+/// `<session>.ephemeral` marker next to its pid file while it runs
+pub fn is_ephemeral_daemon(session: &str) -> bool {
+    get_socket_dir().join(format!("{}.ephemeral", session)).exists()
 }
 
-#[cfg(windows)]
-fn get_port_for_session(session: &str) -> u16 {
+/// Whether the session's daemon was started with --read-only (marked by
+/// `<session>.readonly`)
+pub fn is_read_only_daemon(session: &str) -> bool {
+    get_socket_dir().join(format!("{}.readonly", session)).exists()
+}
+
+/// Where the session's daemon listens: its socket, or its pipe on Windows
+pub fn daemon_address(session: &str) -> String {
+    #[cfg(unix)]
+    return get_socket_path(session).to_string_lossy().to_string();
+    #[cfg(windows)]
+    return get_pipe_name(session);
+}
+
+/// Named pipe the daemon listens on, e.g. `\\.\pipe\agent-browser-1a2b3c4d-default`.
+/// Pipe names are machine-wide, so a hash of the socket dir keeps workspaces
+/// and users apart. Must match getPipeName in daemon.ts.
+#[cfg(any(windows, test))]
+fn get_pipe_name(session: &str) -> String {
+    let dir = get_socket_dir().to_string_lossy().to_string();
     let mut hash: i32 = 0;
-    for c in session.chars() {
-        hash = ((hash << 5).wrapping_sub(hash)).wrapping_add(c as i32);
+    for unit in dir.encode_utf16() {
+        hash = ((hash << 5).wrapping_sub(hash)).wrapping_add(unit as i32);
+    }
+    format!(r"\\.\pipe\agent-browser-{:08x}-{}", hash as u32, session)
+}
+
+/// Open the session's pipe, waiting briefly while all instances are busy
+#[cfg(windows)]
+fn open_pipe(session: &str) -> std::io::Result<File> {
+    const ERROR_PIPE_BUSY: i32 = 231;
+    let name = get_pipe_name(session);
+    let mut attempts = 0;
+    loop {
+        match fs::OpenOptions::new().read(true).write(true).open(&name) {
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) && attempts < 20 => {
+                attempts += 1;
+                thread::sleep(Duration::from_millis(50));
+            }
+            result => return result,
+        }
     }
-    // Correct logic: first take absolute modulo, then cast to u16
-    // Using unsigned_abs() to safely handle i32::MIN
-    49152 + ((hash.unsigned_abs() as u32 % 16383) as u16)
 }
 
 #[cfg(unix)]
-fn is_daemon_running(session: &str) -> bool {
+pub fn is_daemon_running(session: &str) -> bool {
     let pid_path = get_pid_path(session);
     if !pid_path.exists() {
         return false;
     }
     if let Ok(pid_str) = fs::read_to_string(&pid_path) {
         if let Ok(pid) = pid_str.trim().parse::<i32>() {
-            unsafe {
-                return libc::kill(pid, 0) == 0;
-            }
+            // A daemon in another PID namespace (`serve` in a sidecar
+            // container) can't be signalled, but its socket answers
+            return unsafe { libc::kill(pid, 0) == 0 } || daemon_ready(session);
         }
     }
     false
 }
 
 #[cfg(windows)]
-fn is_daemon_running(session: &str) -> bool {
+pub fn is_daemon_running(session: &str) -> bool {
     let pid_path = get_pid_path(session);
     if !pid_path.exists() {
         return false;
     }
-    let port = get_port_for_session(session);
-    TcpStream::connect_timeout(
-        &format!("127.0.0.1:{}", port).parse().unwrap(),
-        Duration::from_millis(100),
-    )
-    .is_ok()
+    open_pipe(session).is_ok()
 }
 
 fn daemon_ready(session: &str) -> bool {
@@ -170,12 +224,7 @@ fn daemon_ready(session: &str) -> bool {
     }
     #[cfg(windows)]
     {
-        let port = get_port_for_session(session);
-        TcpStream::connect_timeout(
-            &format!("127.0.0.1:{}", port).parse().unwrap(),
-            Duration::from_millis(50),
-        )
-        .is_ok()
+        open_pipe(session).is_ok()
     }
 }
 
@@ -185,32 +234,83 @@ pub struct DaemonResult {
     pub already_running: bool,
 }
 
-pub fn ensure_daemon(
-    session: &str,
-    headed: bool,
-    executable_path: Option<&str>,
-    extensions: &[String],
-    args: Option<&str>,
-    user_agent: Option<&str>,
-    proxy: Option<&str>,
-    proxy_bypass: Option<&str>,
-    session_name: Option<&str>,
-) -> Result<DaemonResult, String> {
-    if is_daemon_running(session) && daemon_ready(session) {
-        return Ok(DaemonResult {
-            already_running: true,
-        });
+/// Forward launch-time flags to the daemon process via environment variables
+fn apply_daemon_env(cmd: &mut Command, flags: &Flags) {
+    cmd.env("AGENT_BROWSER_DAEMON", "1")
+        .env("AGENT_BROWSER_SESSION", &flags.session);
+
+    if flags.headed {
+        cmd.env("AGENT_BROWSER_HEADED", "1");
     }
 
-    // Ensure socket directory exists
-    let socket_dir = get_socket_dir();
-    if !socket_dir.exists() {
-        fs::create_dir_all(&socket_dir)
-            .map_err(|e| format!("Failed to create socket directory: {}", e))?;
+    if let Some(ref path) = flags.executable_path {
+        cmd.env("AGENT_BROWSER_EXECUTABLE_PATH", path);
+    }
+
+    if !flags.extensions.is_empty() {
+        cmd.env("AGENT_BROWSER_EXTENSIONS", flags.extensions.join(","));
+    }
+
+    if let Some(ref a) = flags.args {
+        cmd.env("AGENT_BROWSER_ARGS", a);
+    }
+
+    if let Some(ref ua) = flags.user_agent {
+        cmd.env("AGENT_BROWSER_USER_AGENT", ua);
+    }
+
+    if let Some(ref p) = flags.proxy {
+        cmd.env("AGENT_BROWSER_PROXY", p);
+    }
+
+    if let Some(ref pb) = flags.proxy_bypass {
+        cmd.env("AGENT_BROWSER_PROXY_BYPASS", pb);
+    }
+
+    if let Some(ref sn) = flags.session_name {
+        cmd.env("AGENT_BROWSER_SESSION_NAME", sn);
+    }
+
+    if flags.ephemeral {
+        cmd.env("AGENT_BROWSER_EPHEMERAL", "1");
+    }
+
+    if flags.read_only {
+        cmd.env("AGENT_BROWSER_READ_ONLY", "1");
+    }
+
+    // The daemon's own startup cleanup works in days and defaults to 30
+    if let Some(ttl) = flags.state_ttl.as_deref().and_then(|t| state_gc::parse_duration(t).ok()) {
+        cmd.env("AGENT_BROWSER_STATE_EXPIRE_DAYS", state_gc::ttl_days(ttl).to_string());
     }
 
-    let exe_path = env::current_exe().map_err(|e| e.to_string())?;
-    let exe_dir = exe_path.parent().unwrap();
+    if let Some(interval) = flags.save_state_interval.as_deref().and_then(|i| state_gc::parse_duration(i).ok()) {
+        cmd.env("AGENT_BROWSER_SAVE_STATE_INTERVAL_MS", interval.as_millis().to_string());
+    }
+
+    if flags.ignore_https_errors {
+        cmd.env("AGENT_BROWSER_IGNORE_HTTPS_ERRORS", "1");
+    }
+
+    if let Some(ref ca) = flags.extra_ca {
+        // Trusted by the browser through the launch command, and by requests the daemon makes itself
+        cmd.env("AGENT_BROWSER_EXTRA_CA", ca).env("NODE_EXTRA_CA_CERTS", ca);
+    }
+
+    if let Some(ref hosts) = flags.allow_hosts {
+        cmd.env("AGENT_BROWSER_ALLOW_HOSTS", hosts);
+    }
+
+    if let Some(ref hosts) = flags.block_hosts {
+        cmd.env("AGENT_BROWSER_BLOCK_HOSTS", hosts);
+    }
+}
+
+/// Location of the daemon script: AGENT_BROWSER_HOME first, then next to the
+/// executable, then the current directory
+pub fn find_daemon_path() -> Option<PathBuf> {
+    let exe_path = env::current_exe().ok()?;
+    let exe_dir = exe_path.parent()?;
 
     let mut daemon_paths = vec![
         exe_dir.join("daemon.js"),
@@ -225,52 +325,55 @@ pub fn ensure_daemon(
         daemon_paths.insert(1, home_path.join("daemon.js"));
     }
 
-    let daemon_path = daemon_paths
-        .iter()
-        .find(|p| p.exists())
-        .ok_or("Daemon not found. Set AGENT_BROWSER_HOME environment variable or run from project directory.")?;
-
-    // Spawn daemon as a fully detached background process
-    #[cfg(unix)]
-    {
-        use std::os::unix::process::CommandExt;
-
-        let mut cmd = Command::new("node");
-        cmd.arg(daemon_path)
-            .env("AGENT_BROWSER_DAEMON", "1")
-            .env("AGENT_BROWSER_SESSION", session);
+    daemon_paths.into_iter().find(|p| p.exists())
+}
 
-        if headed {
-            cmd.env("AGENT_BROWSER_HEADED", "1");
-        }
+/// `node daemon.js` with the launch-time flags in its environment, after
+/// making sure the socket directory exists
+pub fn daemon_command(flags: &Flags) -> Result<Command, String> {
+    let socket_dir = get_socket_dir();
+    if !socket_dir.exists() {
+        fs::create_dir_all(&socket_dir)
+            .map_err(|e| format!("Failed to create socket directory: {}", e))?;
+    }
 
-        if let Some(path) = executable_path {
-            cmd.env("AGENT_BROWSER_EXECUTABLE_PATH", path);
-        }
+    let daemon_path = find_daemon_path().ok_or(
+        "Daemon not found. Set AGENT_BROWSER_HOME environment variable or run from project directory.",
+    )?;
 
-        if !extensions.is_empty() {
-            cmd.env("AGENT_BROWSER_EXTENSIONS", extensions.join(","));
-        }
+    // On Windows, Command::new handles PATH resolution (node.exe or node.cmd)
+    // and automatically quotes arguments containing spaces.
+    let mut cmd = Command::new("node");
+    cmd.arg(&daemon_path);
+    apply_daemon_env(&mut cmd, flags);
+    Ok(cmd)
+}
 
-        if let Some(a) = args {
-            cmd.env("AGENT_BROWSER_ARGS", a);
-        }
+pub fn ensure_daemon(flags: &Flags) -> Result<DaemonResult, String> {
+    let session = flags.session.as_str();
+    if is_daemon_running(session) && daemon_ready(session) {
+        return Ok(DaemonResult {
+            already_running: true,
+        });
+    }
 
-        if let Some(ua) = user_agent {
-            cmd.env("AGENT_BROWSER_USER_AGENT", ua);
-        }
+    let mut cmd = daemon_command(flags)?;
+    spawn_detached(&mut cmd)?;
 
-        if let Some(p) = proxy {
-            cmd.env("AGENT_BROWSER_PROXY", p);
-        }
+    if wait_ready(session) {
+        return Ok(DaemonResult {
+            already_running: false,
+        });
+    }
 
-        if let Some(pb) = proxy_bypass {
-            cmd.env("AGENT_BROWSER_PROXY_BYPASS", pb);
-        }
+    Err("Daemon failed to start".to_string())
+}
 
-        if let Some(sn) = session_name {
-            cmd.env("AGENT_BROWSER_SESSION_NAME", sn);
-        }
+/// Start a daemon command as a fully detached background process
+pub fn spawn_detached(cmd: &mut Command) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
 
         // Create new process group and session to fully detach
         unsafe {
@@ -292,45 +395,6 @@ pub fn ensure_daemon(
     {
         use std::os::windows::process::CommandExt;
 
-        // On Windows, call node directly. Command::new handles PATH resolution (node.exe or node.cmd)
-        // and automatically quotes arguments containing spaces.
-        let mut cmd = Command::new("node");
-        cmd.arg(daemon_path)
-            .env("AGENT_BROWSER_DAEMON", "1")
-            .env("AGENT_BROWSER_SESSION", session);
-
-        if headed {
-            cmd.env("AGENT_BROWSER_HEADED", "1");
-        }
-
-        if let Some(path) = executable_path {
-            cmd.env("AGENT_BROWSER_EXECUTABLE_PATH", path);
-        }
-
-        if !extensions.is_empty() {
-            cmd.env("AGENT_BROWSER_EXTENSIONS", extensions.join(","));
-        }
-
-        if let Some(a) = args {
-            cmd.env("AGENT_BROWSER_ARGS", a);
-        }
-
-        if let Some(ua) = user_agent {
-            cmd.env("AGENT_BROWSER_USER_AGENT", ua);
-        }
-
-        if let Some(p) = proxy {
-            cmd.env("AGENT_BROWSER_PROXY", p);
-        }
-
-        if let Some(pb) = proxy_bypass {
-            cmd.env("AGENT_BROWSER_PROXY_BYPASS", pb);
-        }
-
-        if let Some(sn) = session_name {
-            cmd.env("AGENT_BROWSER_SESSION_NAME", sn);
-        }
-
         // CREATE_NEW_PROCESS_GROUP | DETACHED_PROCESS
         const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
         const DETACHED_PROCESS: u32 = 0x00000008;
@@ -343,16 +407,18 @@ pub fn ensure_daemon(
             .map_err(|e| format!("Failed to start daemon: {}", e))?;
     }
 
+    Ok(())
+}
+
+/// Wait up to five seconds for a starting daemon to accept connections
+pub fn wait_ready(session: &str) -> bool {
     for _ in 0..50 {
         if daemon_ready(session) {
-            return Ok(DaemonResult {
-                already_running: false,
-            });
+            return true;
         }
         thread::sleep(Duration::from_millis(100));
     }
-
-    Err("Daemon failed to start".to_string())
+    false
 }
 
 fn connect(session: &str) -> Result<Connection, String> {
@@ -365,14 +431,27 @@ fn connect(session: &str) -> Result<Connection, String> {
     }
     #[cfg(windows)]
     {
-        let port = get_port_for_session(session);
-        TcpStream::connect(format!("127.0.0.1:{}", port))
-            .map(Connection::Tcp)
+        open_pipe(session)
+            .map(Connection::Pipe)
             .map_err(|e| format!("Failed to connect: {}", e))
     }
 }
 
+/// Set from --token by main, and inherited by the invocations of `run`
+pub const TOKEN_ENV: &str = "AGENT_BROWSER_TOKEN";
+
+/// The command as sent: with the access token, if there is one
+fn with_token(mut cmd: Value) -> Value {
+    if let Ok(token) = env::var(TOKEN_ENV) {
+        if !token.is_empty() {
+            cmd["token"] = Value::String(token);
+        }
+    }
+    cmd
+}
+
 pub fn send_command(cmd: Value, session: &str) -> Result<Response, String> {
+    let cmd = with_token(cmd);
     let mut stream = connect(session)?;
 
     stream.set_read_timeout(Some(Duration::from_secs(30))).ok();
@@ -380,12 +459,21 @@ pub fn send_command(cmd: Value, session: &str) -> Result<Response, String> {
 
     let mut json_str = serde_json::to_string(&cmd).map_err(|e| e.to_string())?;
     json_str.push('\n');
+    // Large outputs come back as frames rather than one JSON line
+    let framed = framing::enabled();
+    if framed {
+        json_str.insert_str(0, framing::HELLO);
+    }
 
     stream
         .write_all(json_str.as_bytes())
         .map_err(|e| format!("Failed to send: {}", e))?;
 
     let mut reader = BufReader::new(stream);
+    if framed {
+        let response = framing::read_response(&mut reader)?;
+        return serde_json::from_value(response).map_err(|e| format!("Invalid response: {}", e));
+    }
     let mut response_line = String::new();
     reader
         .read_line(&mut response_line)
@@ -394,6 +482,33 @@ pub fn send_command(cmd: Value, session: &str) -> Result<Response, String> {
     serde_json::from_str(&response_line).map_err(|e| format!("Invalid response: {}", e))
 }
 
+/// Send a command whose connection stays open, such as `observe`: returns its
+/// response and the lines the daemon sends after it, until it closes
+pub fn send_streaming(cmd: Value, session: &str) -> Result<(Response, Lines<BufReader<Connection>>), String> {
+    let cmd = with_token(cmd);
+    let mut stream = connect(session)?;
+
+    stream.set_read_timeout(Some(Duration::from_secs(30))).ok();
+    stream.set_write_timeout(Some(Duration::from_secs(5))).ok();
+
+    let mut json_str = serde_json::to_string(&cmd).map_err(|e| e.to_string())?;
+    json_str.push('\n');
+
+    stream
+        .write_all(json_str.as_bytes())
+        .map_err(|e| format!("Failed to send: {}", e))?;
+
+    let mut reader = BufReader::new(stream);
+    let mut response_line = String::new();
+    reader
+        .read_line(&mut response_line)
+        .map_err(|e| format!("Failed to read: {}", e))?;
+    let response = serde_json::from_str(&response_line).map_err(|e| format!("Invalid response: {}", e))?;
+    // Events come whenever the session runs something
+    reader.get_ref().set_read_timeout(None).ok();
+    Ok((response, reader.lines()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -478,6 +593,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(unix)]
     fn test_get_socket_dir_home_fallback() {
         let _guard = EnvGuard::new(&["AGENT_BROWSER_SOCKET_DIR", "XDG_RUNTIME_DIR"]);
 
@@ -491,4 +607,17 @@ mod tests {
             assert!(result.to_string_lossy().ends_with(".agent-browser"));
         }
     }
+
+    #[test]
+    fn test_pipe_name_scoped_by_socket_dir() {
+        let _guard = EnvGuard::new(&["AGENT_BROWSER_SOCKET_DIR"]);
+
+        env::set_var("AGENT_BROWSER_SOCKET_DIR", "/work/acme/run");
+        let acme = get_pipe_name("default");
+        // Same hash as getPipeName in daemon.ts
+        assert_eq!(acme, r"\\.\pipe\agent-browser-dec285a5-default");
+
+        env::set_var("AGENT_BROWSER_SOCKET_DIR", "/work/globex/run");
+        assert_ne!(acme, get_pipe_name("default"));
+    }
 }
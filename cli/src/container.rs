@@ -0,0 +1,103 @@
+//! Launch preset for containers.
+//!
+//! Chromium's sandbox needs user namespaces most container runtimes don't
+//! grant, and Docker's default 64MB /dev/shm is too small for its shared
+//! memory, so inside a container the browser is launched with --no-sandbox
+//! and --disable-dev-shm-usage. Containers are detected from the runtime's
+//! marker files and cgroups; --containerized or AGENT_BROWSER_CONTAINERIZED
+//! overrides the detection.
+
+use std::fs;
+use std::path::Path;
+
+pub const LAUNCH_ARGS: &[&str] = &["--no-sandbox", "--disable-dev-shm-usage"];
+
+/// Below this /dev/shm size, suggest giving the container more
+const SHM_ADVICE_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Whether the markers describe a container: Docker's /.dockerenv, Podman's
+/// /run/.containerenv, the `container` variable systemd-nspawn and Podman set,
+/// or a runtime in PID 1's cgroup path
+pub fn detect_from(dockerenv: bool, containerenv: bool, container_var: Option<&str>, cgroup: &str) -> bool {
+    const RUNTIMES: &[&str] = &["docker", "kubepods", "containerd", "libpod", "lxc"];
+    dockerenv
+        || containerenv
+        || container_var.is_some_and(|v| !v.is_empty())
+        || cgroup.lines().any(|line| RUNTIMES.iter().any(|r| line.contains(r)))
+}
+
+pub fn detect() -> bool {
+    if !cfg!(target_os = "linux") {
+        return false;
+    }
+    detect_from(
+        Path::new("/.dockerenv").exists(),
+        Path::new("/run/.containerenv").exists(),
+        std::env::var("container").ok().as_deref(),
+        &fs::read_to_string("/proc/1/cgroup").unwrap_or_default(),
+    )
+}
+
+/// The --containerized / AGENT_BROWSER_CONTAINERIZED setting, else detection
+pub fn enabled(flag: Option<bool>) -> bool {
+    flag.unwrap_or_else(detect)
+}
+
+/// Add the container switches that aren't already in `args`
+pub fn apply(args: &mut Vec<String>) {
+    for switch in LAUNCH_ARGS {
+        if !args.iter().any(|a| a == switch) {
+            args.push(switch.to_string());
+        }
+    }
+}
+
+/// Size of /dev/shm
+#[cfg(unix)]
+pub fn shm_size() -> Option<u64> {
+    let path = std::ffi::CString::new("/dev/shm").ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    Some(stat.f_blocks as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+pub fn shm_size() -> Option<u64> {
+    None
+}
+
+/// Advice for a /dev/shm too small to run Chromium without --disable-dev-shm-usage
+pub fn shm_advice(size: u64) -> Option<String> {
+    (size < SHM_ADVICE_BYTES).then(|| {
+        format!(
+            "/dev/shm is {}MB, so Chromium uses /tmp for shared memory; run the container with --shm-size=1g (or --ipc=host) for faster rendering",
+            size / 1024 / 1024
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_from() {
+        assert!(detect_from(true, false, None, ""));
+        assert!(detect_from(false, false, Some("podman"), ""));
+        assert!(detect_from(false, false, None, "0::/kubepods/besteffort/pod1234/abcd\n"));
+        assert!(!detect_from(false, false, Some(""), "0::/init.scope\n"));
+    }
+
+    #[test]
+    fn test_apply_and_advice() {
+        let mut args = vec!["--no-sandbox".to_string()];
+        apply(&mut args);
+        assert_eq!(args, vec!["--no-sandbox", "--disable-dev-shm-usage"]);
+        assert!(shm_advice(64 * 1024 * 1024).unwrap().starts_with("/dev/shm is 64MB"));
+        assert_eq!(shm_advice(2 * 1024 * 1024 * 1024), None);
+        assert!(enabled(Some(true)));
+        assert!(!enabled(Some(false)));
+    }
+}
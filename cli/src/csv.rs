@@ -0,0 +1,80 @@
+//! CSV output (`--format csv`) for extracted data.
+//!
+//! An array of objects becomes one row per object under a header row of all
+//! their keys (in alphabetical order, as the JSON arrives without its
+//! original key order). An array of arrays is written as is, so its first
+//! row is the header and sets the column order. Other values become a single
+//! `value` column. Nested values are written as compact JSON, and fields are
+//! quoted as in RFC 4180 when they contain a comma, quote or line break.
+
+use serde_json::Value;
+
+fn field(value: &Value) -> String {
+    let text = match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    if text.contains([',', '"', '\n', '\r']) || text.starts_with(' ') || text.ends_with(' ') {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+fn row<'a>(values: impl IntoIterator<Item = &'a Value>) -> String {
+    let fields: Vec<String> = values.into_iter().map(field).collect();
+    format!("{}\n", fields.join(","))
+}
+
+/// The value as CSV, header row first
+pub fn render(value: &Value) -> String {
+    let rows: Vec<&Value> = match value {
+        Value::Array(items) => items.iter().collect(),
+        other => vec![other],
+    };
+    if !rows.is_empty() && rows.iter().all(|r| r.is_array()) {
+        return rows.iter().map(|r| row(r.as_array().into_iter().flatten())).collect();
+    }
+    if !rows.is_empty() && rows.iter().all(|r| r.is_object()) {
+        let mut columns: Vec<String> = rows.iter().flat_map(|r| r.as_object().into_iter().flatten().map(|(k, _)| k.clone())).collect();
+        columns.sort();
+        columns.dedup();
+        let header: Vec<Value> = columns.iter().map(|c| Value::String(c.clone())).collect();
+        let mut csv = row(&header);
+        for r in rows {
+            csv.push_str(&row(columns.iter().map(|c| r.get(c).unwrap_or(&Value::Null))));
+        }
+        return csv;
+    }
+    let mut csv = "value\n".to_string();
+    for r in rows {
+        csv.push_str(&row([r]));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_objects() {
+        let rows = json!([
+            { "name": "Widget, large", "price": 9.5 },
+            { "name": "Say \"hi\"", "tags": ["a"] },
+        ]);
+        assert_eq!(
+            render(&rows),
+            "name,price,tags\n\"Widget, large\",9.5,\n\"Say \"\"hi\"\"\",,\"[\"\"a\"\"]\"\n"
+        );
+    }
+
+    #[test]
+    fn test_render_arrays_and_scalars() {
+        assert_eq!(render(&json!([["b", "a"], [1, "x\ny"]])), "b,a\n1,\"x\ny\"\n");
+        assert_eq!(render(&json!(["https://a.test/", null])), "value\nhttps://a.test/\n\n");
+        assert_eq!(render(&json!("one")), "value\none\n");
+    }
+}
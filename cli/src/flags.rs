@@ -1,5 +1,7 @@
 use std::env;
 
+use crate::registry;
+
 pub struct Flags {
     pub json: bool,
     pub full: bool,
@@ -7,16 +9,123 @@ pub struct Flags {
     pub debug: bool,
     pub session: String,
     pub headers: Option<String>,
+    /// True when --headers was given after the command name (applies to that navigation only)
+    pub headers_navigation_only: bool,
+    /// Individual `Name: value` headers from repeated --header flags
+    pub header: Vec<String>,
+    pub headers_for: Vec<String>,
     pub executable_path: Option<String>,
+    /// Apply the container launch preset; None detects it
+    pub containerized: Option<bool>,
+    /// Installed browser to launch: chrome, chrome-beta, edge or chromium
+    /// (also self-update's release channel)
+    pub channel: Option<String>,
     pub cdp: Option<String>,
     pub extensions: Vec<String>,
     pub profile: Option<String>,
     pub proxy: Option<String>,
     pub proxy_bypass: Option<String>,
     pub args: Option<String>,
+    /// Browser command-line switches from repeated --browser-arg, passed as-is
+    pub browser_args: Vec<String>,
+    /// "host=address[:port]" mappings from repeated --host-rule (launch-time)
+    pub host_mappings: Vec<String>,
     pub user_agent: Option<String>,
     pub provider: Option<String>,
     pub session_name: Option<String>,
+    /// Keep nothing on disk and shut the daemon down once idle
+    pub ephemeral: bool,
+    /// Block requests, form submissions and clicks that could change data
+    pub read_only: bool,
+    /// Milliseconds to wait for another invocation's session lock
+    pub wait_lock: Option<String>,
+    /// Access token sent with every command to the daemon
+    pub token: Option<String>,
+    /// Workspace scoping sessions, state and config (overrides the active one)
+    pub workspace: Option<String>,
+    /// Root directory for everything kept on disk (overrides workspaces)
+    pub state_dir: Option<String>,
+    /// Session template from the config file, applied when the browser starts
+    pub template: Option<String>,
+    /// Max age of persisted session state files, e.g. "7d"
+    pub state_ttl: Option<String>,
+    /// How often a --session-name daemon saves state between commands, e.g. "30s"
+    pub save_state_interval: Option<String>,
+    pub ignore_https_errors: bool,
+    pub extra_ca: Option<String>,
+    pub rotate_profile: Option<String>,
+    /// Comma separated host patterns the browser may reach (launch-time)
+    pub allow_hosts: Option<String>,
+    /// Comma separated host patterns the browser must not reach (launch-time)
+    pub block_hosts: Option<String>,
+    /// Download size limit in MB
+    pub max_download: Option<String>,
+    /// Comma separated MIME types downloads may not have
+    pub block_download_types: Option<String>,
+    /// Pixels kept clear above elements clicked or scrolled to (sticky headers)
+    pub scroll_margin: Option<String>,
+    /// Directories `upload` may read from, separated like PATH
+    pub upload_dirs: Option<String>,
+    /// Response bytes a command may download, e.g. "50MB"
+    pub max_bytes: Option<String>,
+    /// Requests a command may make
+    pub max_requests: Option<String>,
+    /// Whether the budget is per command (default) or for the whole session
+    pub budget_scope: Option<String>,
+    /// Comma separated resource classes to abort, e.g. "images,fonts,media"
+    pub block: Option<String>,
+    /// How long reads may be answered from the daemon's cache, e.g. "5m"
+    pub cache: Option<String>,
+    /// Chromium headless implementation: "new", "old" or "shell" (launch-time)
+    pub headless_mode: Option<String>,
+    /// IP family for browser connections, "4" or "6" (launch-time)
+    pub ip_family: Option<String>,
+    /// DNS-over-HTTPS resolver URL for the browser (launch-time)
+    pub dns_over_https: Option<String>,
+    /// JSON file of upstream proxies to rotate between (launch-time)
+    pub proxy_pool: Option<String>,
+    /// When the pool's proxy changes: "per-navigation" or "per-session"
+    pub proxy_rotate: Option<String>,
+    /// Browser memory above which it is restarted, e.g. "1.5GB" (launch-time)
+    pub max_memory: Option<String>,
+    /// Janitor rules: comma separated "blank", "popups", "contexts" or "all" (launch-time)
+    pub janitor: Option<String>,
+    /// Selenium Grid or CDP pool URL to acquire the browser from (launch-time)
+    pub pool: Option<String>,
+    /// JSON object of capabilities requested from --pool
+    pub pool_capabilities: Option<String>,
+    /// Command whose output schema to print instead of running anything
+    pub schema: Option<String>,
+    /// JSON output format version ("v1" or "v2"), validated in main
+    pub output_format: Option<String>,
+    /// When false (--no-strict), unknown global flags are ignored instead of reported
+    pub strict: bool,
+    /// Validate and print the command instead of sending it
+    pub dry_run: bool,
+    /// Queue the command in the daemon and print its job id instead of waiting
+    pub no_wait: bool,
+    /// Run the command as a background job and print its id
+    pub detach: bool,
+    /// Block navigations/clicks matching the destructive-action policy
+    pub confirm_destructive: bool,
+    /// Append every command to the session's hash-chained audit log
+    pub audit: bool,
+    /// Accept any session name, encoding it into a file-safe form
+    pub unicode_sessions: bool,
+    /// Let `open` load file: and javascript: URLs
+    pub allow_unsafe_schemes: bool,
+    /// Path to a JSON policy file replacing the built-in rules
+    pub policy: Option<String>,
+    /// Confirmation token for an action the policy would block
+    pub confirm: Option<String>,
+    /// URL receiving started/progress/completed events as JSON POSTs
+    pub notify_webhook: Option<String>,
+    /// s3:// or gs:// prefix that screenshots, PDFs and videos are uploaded to
+    pub upload: Option<String>,
+    /// Keep produced files in the content-addressed artifact store
+    pub artifacts: bool,
+    /// Unknown global flags found while parsing, with suggestions where available
+    pub errors: Vec<String>,
 }
 
 pub fn parse_flags(args: &[String]) -> Flags {
@@ -32,141 +141,434 @@ pub fn parse_flags(args: &[String]) -> Flags {
         debug: false,
         session: env::var("AGENT_BROWSER_SESSION").unwrap_or_else(|_| "default".to_string()),
         headers: None,
+        headers_navigation_only: false,
+        header: Vec::new(),
+        headers_for: Vec::new(),
         executable_path: env::var("AGENT_BROWSER_EXECUTABLE_PATH").ok(),
+        channel: env::var("AGENT_BROWSER_CHANNEL").ok(),
+        containerized: match env::var("AGENT_BROWSER_CONTAINERIZED").as_deref() {
+            Ok("1") | Ok("true") => Some(true),
+            Ok("0") | Ok("false") => Some(false),
+            _ => None,
+        },
         cdp: None,
         extensions: extensions_env,
         profile: env::var("AGENT_BROWSER_PROFILE").ok(),
         proxy: env::var("AGENT_BROWSER_PROXY").ok(),
         proxy_bypass: env::var("AGENT_BROWSER_PROXY_BYPASS").ok(),
         args: env::var("AGENT_BROWSER_ARGS").ok(),
+        browser_args: Vec::new(),
+        host_mappings: env::var("AGENT_BROWSER_HOST_RULES")
+            .map(|v| v.split(',').map(|r| r.trim().to_string()).filter(|r| !r.is_empty()).collect())
+            .unwrap_or_default(),
         user_agent: env::var("AGENT_BROWSER_USER_AGENT").ok(),
         provider: env::var("AGENT_BROWSER_PROVIDER").ok(),
         session_name: env::var("AGENT_BROWSER_SESSION_NAME").ok(),
+        ephemeral: env::var("AGENT_BROWSER_EPHEMERAL")
+            .map(|v| v == "1" || v == "true")
+            .unwrap_or(false),
+        read_only: env::var("AGENT_BROWSER_READ_ONLY")
+            .map(|v| v == "1" || v == "true")
+            .unwrap_or(false),
+        wait_lock: env::var("AGENT_BROWSER_WAIT_LOCK").ok(),
+        token: env::var("AGENT_BROWSER_TOKEN").ok(),
+        state_ttl: env::var("AGENT_BROWSER_STATE_TTL").ok(),
+        save_state_interval: env::var("AGENT_BROWSER_SAVE_STATE_INTERVAL").ok(),
+        template: env::var("AGENT_BROWSER_TEMPLATE").ok(),
+        workspace: env::var("AGENT_BROWSER_WORKSPACE").ok(),
+        state_dir: env::var("AGENT_BROWSER_STATE_ROOT").ok(),
+        ignore_https_errors: env::var("AGENT_BROWSER_IGNORE_HTTPS_ERRORS")
+            .map(|v| v == "1" || v == "true")
+            .unwrap_or(false),
+        extra_ca: env::var("AGENT_BROWSER_EXTRA_CA").ok(),
+        rotate_profile: env::var("AGENT_BROWSER_ROTATE_PROFILE").ok(),
+        allow_hosts: env::var("AGENT_BROWSER_ALLOW_HOSTS").ok(),
+        block_hosts: env::var("AGENT_BROWSER_BLOCK_HOSTS").ok(),
+        max_download: env::var("AGENT_BROWSER_MAX_DOWNLOAD").ok(),
+        block_download_types: env::var("AGENT_BROWSER_BLOCK_DOWNLOAD_TYPES").ok(),
+        scroll_margin: env::var("AGENT_BROWSER_SCROLL_MARGIN").ok(),
+        upload_dirs: env::var("AGENT_BROWSER_UPLOAD_DIRS").ok(),
+        max_bytes: env::var("AGENT_BROWSER_MAX_BYTES").ok(),
+        max_requests: env::var("AGENT_BROWSER_MAX_REQUESTS").ok(),
+        budget_scope: env::var("AGENT_BROWSER_BUDGET_SCOPE").ok(),
+        block: env::var("AGENT_BROWSER_BLOCK").ok(),
+        cache: env::var("AGENT_BROWSER_CACHE").ok(),
+        headless_mode: env::var("AGENT_BROWSER_HEADLESS_MODE").ok(),
+        ip_family: env::var("AGENT_BROWSER_IP_FAMILY").ok(),
+        dns_over_https: env::var("AGENT_BROWSER_DNS_OVER_HTTPS").ok(),
+        proxy_pool: env::var("AGENT_BROWSER_PROXY_POOL").ok(),
+        proxy_rotate: env::var("AGENT_BROWSER_PROXY_ROTATE").ok(),
+        max_memory: env::var("AGENT_BROWSER_MAX_MEMORY").ok(),
+        janitor: env::var("AGENT_BROWSER_JANITOR").ok(),
+        pool: env::var("AGENT_BROWSER_POOL").ok(),
+        pool_capabilities: env::var("AGENT_BROWSER_POOL_CAPABILITIES").ok(),
+        schema: None,
+        output_format: env::var("AGENT_BROWSER_OUTPUT_FORMAT").ok(),
+        strict: true,
+        dry_run: false,
+        no_wait: false,
+        detach: false,
+        confirm_destructive: env::var("AGENT_BROWSER_CONFIRM_DESTRUCTIVE")
+            .map(|v| v == "1" || v == "true")
+            .unwrap_or(false),
+        audit: env::var("AGENT_BROWSER_AUDIT")
+            .map(|v| v == "1" || v == "true")
+            .unwrap_or(false),
+        unicode_sessions: env::var("AGENT_BROWSER_UNICODE_SESSIONS")
+            .map(|v| v == "1" || v == "true")
+            .unwrap_or(false),
+        allow_unsafe_schemes: env::var("AGENT_BROWSER_ALLOW_UNSAFE_SCHEMES")
+            .map(|v| v == "1" || v == "true")
+            .unwrap_or(false),
+        policy: env::var("AGENT_BROWSER_POLICY").ok(),
+        confirm: None,
+        notify_webhook: env::var("AGENT_BROWSER_NOTIFY_WEBHOOK").ok().filter(|v| !v.is_empty()),
+        upload: env::var("AGENT_BROWSER_UPLOAD").ok().filter(|v| !v.is_empty()),
+        artifacts: env::var("AGENT_BROWSER_ARTIFACTS")
+            .map(|v| v == "1" || v == "true")
+            .unwrap_or(false),
+        errors: Vec::new(),
     };
 
+    // Set once the first positional argument (the command name) has been seen
+    let mut command_seen = false;
+    // Set once --headers or --header has been seen, and when they were given
+    // on both sides of the command name
+    let mut headers_seen = false;
+    let mut mixed_headers_scope = false;
+
     let mut i = 0;
     while i < args.len() {
-        match args[i].as_str() {
+        let arg = args[i].as_str();
+        // `--` ends flag parsing; everything after it is positional
+        if arg == "--" {
+            break;
+        }
+        let (name, inline_value) = split_inline_value(arg);
+        // Value-taking flags accept `--flag value` and `--flag=value`
+        let value = if takes_value(name) {
+            match inline_value {
+                Some(v) => Some(v.to_string()),
+                None => {
+                    let next = args.get(i + 1).cloned();
+                    if next.is_some() {
+                        i += 1;
+                    }
+                    next
+                }
+            }
+        } else {
+            None
+        };
+
+        match name {
             "--json" => flags.json = true,
             "--full" | "-f" => flags.full = true,
             "--headed" => flags.headed = true,
             "--debug" => flags.debug = true,
+            "--ignore-https-errors" => flags.ignore_https_errors = true,
             "--session" => {
-                if let Some(s) = args.get(i + 1) {
-                    flags.session = s.clone();
-                    i += 1;
-                }
-            }
-            "--headers" => {
-                if let Some(h) = args.get(i + 1) {
-                    flags.headers = Some(h.clone());
-                    i += 1;
-                }
-            }
-            "--executable-path" => {
-                if let Some(s) = args.get(i + 1) {
-                    flags.executable_path = Some(s.clone());
-                    i += 1;
-                }
-            },
-            "--extension" => {
-                if let Some(s) = args.get(i + 1) {
-                    flags.extensions.push(s.clone());
-                    i += 1;
-                }
-            },
-            "--cdp" => {
-                if let Some(s) = args.get(i + 1) {
-                    flags.cdp = Some(s.clone());
-                    i += 1;
-                }
-            }
-            "--profile" => {
-                if let Some(s) = args.get(i + 1) {
-                    flags.profile = Some(s.clone());
-                    i += 1;
-                }
-            }
-            "--proxy" => {
-                if let Some(p) = args.get(i + 1) {
-                    flags.proxy = Some(p.clone());
-                    i += 1;
+                if let Some(v) = value {
+                    flags.session = v;
                 }
             }
-            "--proxy-bypass" => {
-                if let Some(s) = args.get(i + 1) {
-                    flags.proxy_bypass = Some(s.clone());
-                    i += 1;
+            "--headers" | "--header" => {
+                if let Some(v) = value {
+                    if name == "--headers" {
+                        flags.headers = Some(v);
+                    } else {
+                        flags.header.push(v);
+                    }
+                    // Both flags share one scope, so they must agree on placement
+                    if headers_seen && flags.headers_navigation_only != command_seen {
+                        mixed_headers_scope = true;
+                    }
+                    headers_seen = true;
+                    flags.headers_navigation_only = command_seen;
                 }
             }
-            "--args" => {
-                if let Some(s) = args.get(i + 1) {
-                    flags.args = Some(s.clone());
-                    i += 1;
-                }
+            "--headers-for" => flags.headers_for.extend(value),
+            "--executable-path" => flags.executable_path = value.or(flags.executable_path.take()),
+            "--containerized" => flags.containerized = Some(true),
+            "--channel" => flags.channel = value.or(flags.channel.take()),
+            "--extension" => flags.extensions.extend(value),
+            "--cdp" => flags.cdp = value.or(flags.cdp.take()),
+            "--profile" => flags.profile = value.or(flags.profile.take()),
+            "--proxy" => flags.proxy = value.or(flags.proxy.take()),
+            "--proxy-bypass" => flags.proxy_bypass = value.or(flags.proxy_bypass.take()),
+            "--args" => flags.args = value.or(flags.args.take()),
+            "--browser-arg" => flags.browser_args.extend(value),
+            "--host-rule" => flags.host_mappings.extend(value),
+            "--user-agent" => flags.user_agent = value.or(flags.user_agent.take()),
+            "-p" | "--provider" => flags.provider = value.or(flags.provider.take()),
+            "--session-name" => flags.session_name = value.or(flags.session_name.take()),
+            "--ephemeral" => flags.ephemeral = true,
+            "--read-only" => flags.read_only = true,
+            "--wait-lock" => flags.wait_lock = value.or(flags.wait_lock.take()),
+            "--token" => flags.token = value.or(flags.token.take()),
+            "--state-ttl" => flags.state_ttl = value.or(flags.state_ttl.take()),
+            "--state-dir" => flags.state_dir = value.or(flags.state_dir.take()),
+            "--save-state-interval" => flags.save_state_interval = value.or(flags.save_state_interval.take()),
+            "--template" => flags.template = value.or(flags.template.take()),
+            "--workspace" => flags.workspace = value.or(flags.workspace.take()),
+            "--extra-ca" => flags.extra_ca = value.or(flags.extra_ca.take()),
+            "--rotate-profile" => flags.rotate_profile = value.or(flags.rotate_profile.take()),
+            "--allow-hosts" => flags.allow_hosts = value.or(flags.allow_hosts.take()),
+            "--block-hosts" => flags.block_hosts = value.or(flags.block_hosts.take()),
+            "--max-download" => flags.max_download = value.or(flags.max_download.take()),
+            "--block-download-types" => {
+                flags.block_download_types = value.or(flags.block_download_types.take())
             }
-            "--user-agent" => {
-                if let Some(s) = args.get(i + 1) {
-                    flags.user_agent = Some(s.clone());
-                    i += 1;
+            "--scroll-margin" => flags.scroll_margin = value.or(flags.scroll_margin.take()),
+            "--upload-dirs" => flags.upload_dirs = value.or(flags.upload_dirs.take()),
+            "--max-bytes" => flags.max_bytes = value.or(flags.max_bytes.take()),
+            "--max-requests" => flags.max_requests = value.or(flags.max_requests.take()),
+            "--budget-scope" => flags.budget_scope = value.or(flags.budget_scope.take()),
+            "--block" => flags.block = value.or(flags.block.take()),
+            "--cache" => flags.cache = value.or(flags.cache.take()),
+            "--headless-mode" => flags.headless_mode = value.or(flags.headless_mode.take()),
+            "--ip-family" => flags.ip_family = value.or(flags.ip_family.take()),
+            "--dns-over-https" => flags.dns_over_https = value.or(flags.dns_over_https.take()),
+            "--proxy-pool" => flags.proxy_pool = value.or(flags.proxy_pool.take()),
+            "--rotate" => flags.proxy_rotate = value.or(flags.proxy_rotate.take()),
+            "--max-memory" => flags.max_memory = value.or(flags.max_memory.take()),
+            "--janitor" => flags.janitor = value.or(flags.janitor.take()),
+            "--pool" => flags.pool = value.or(flags.pool.take()),
+            "--pool-capabilities" => flags.pool_capabilities = value.or(flags.pool_capabilities.take()),
+            "--schema" => flags.schema = value.or(flags.schema.take()),
+            "--output-format" => flags.output_format = value.or(flags.output_format.take()),
+            "--no-strict" => flags.strict = false,
+            "--dry-run" => flags.dry_run = true,
+            "--no-wait" => flags.no_wait = true,
+            "--detach" => flags.detach = true,
+            "--confirm-destructive" => flags.confirm_destructive = true,
+            "--audit" => flags.audit = true,
+            "--unicode-sessions" => flags.unicode_sessions = true,
+            "--allow-unsafe-schemes" => flags.allow_unsafe_schemes = true,
+            "--policy" => flags.policy = value.or(flags.policy.take()),
+            "--confirm" => flags.confirm = value.or(flags.confirm.take()),
+            "--notify-webhook" => flags.notify_webhook = value.or(flags.notify_webhook.take()),
+            "--upload" => flags.upload = value.or(flags.upload.take()),
+            "--artifacts" => flags.artifacts = true,
+            _ => {
+                if !arg.starts_with('-') {
+                    command_seen = true;
+                } else if !command_seen && !is_info_flag(name) {
+                    // Flags before the command can only be global, so anything
+                    // unrecognized there is a typo. Flags after the command may
+                    // belong to the command itself and are left to it.
+                    flags.errors.push(unknown_flag_error(name));
                 }
             }
-            "-p" | "--provider" => {
-                if let Some(p) = args.get(i + 1) {
-                    flags.provider = Some(p.clone());
-                    i += 1;
-                }
-            }
-            "--session-name" => {
-                if let Some(s) = args.get(i + 1) {
-                    flags.session_name = Some(s.clone());
-                    i += 1;
-                }
-            }
-            _ => {}
         }
         i += 1;
     }
+    if !flags.strict {
+        flags.errors.clear();
+    }
+    // Invalid headers are reported even with --no-strict
+    if mixed_headers_scope {
+        flags.errors.push(
+            "--headers and --header must all go before the command (session headers) \
+             or all after it (this navigation only)"
+                .to_string(),
+        );
+    }
+    if let Some(ref headers) = flags.headers {
+        if let Err(msg) = validate_headers_json(headers) {
+            flags.errors.push(msg);
+        }
+    }
+    for header in &flags.header {
+        if let Err(msg) = parse_header_line(header) {
+            flags.errors.push(msg);
+        }
+    }
+    if let Some(ref url) = flags.notify_webhook {
+        if let Err(msg) = crate::notify::validate_webhook_url(url) {
+            flags.errors.push(msg);
+        }
+    }
+    if let Some(ref url) = flags.upload {
+        if let Err(msg) = crate::upload::Target::parse(url) {
+            flags.errors.push(msg);
+        }
+    }
     flags
 }
 
+/// Headers the browser controls itself; setting them breaks requests
+const FORBIDDEN_HEADERS: &[&str] = &[
+    "host",
+    "content-length",
+    "connection",
+    "keep-alive",
+    "transfer-encoding",
+    "upgrade",
+    "te",
+    "trailer",
+    "expect",
+];
+
+/// Check a --headers value: a JSON object of string values without headers
+/// the browser sets itself. Errors point at the offending key.
+pub fn validate_headers_json(value: &str) -> Result<(), String> {
+    let parsed: serde_json::Value = serde_json::from_str(value)
+        .map_err(|e| format!("Invalid --headers: not valid JSON ({})", e))?;
+    let Some(object) = parsed.as_object() else {
+        return Err("Invalid --headers: expected a JSON object like {\"Name\": \"value\"}".to_string());
+    };
+    for (name, value) in object {
+        if name.trim().is_empty() {
+            return Err("Invalid --headers: header name at \"\" is empty".to_string());
+        }
+        if !value.is_string() {
+            return Err(format!(
+                "Invalid --headers: value of \"{}\" must be a string, got {}",
+                name, value
+            ));
+        }
+        check_header_name("--headers", name)?;
+    }
+    Ok(())
+}
+
+/// Split a --header value into its name and value, with the same name
+/// checks as --headers. The value may itself contain colons.
+pub fn parse_header_line(header: &str) -> Result<(&str, &str), String> {
+    let (name, value) = header
+        .split_once(':')
+        .map(|(n, v)| (n.trim(), v.trim()))
+        .filter(|(n, _)| !n.is_empty())
+        .ok_or_else(|| format!("Invalid --header '{}': expected \"Name: value\"", header))?;
+    check_header_name("--header", name)?;
+    Ok((name, value))
+}
+
+fn check_header_name(flag: &str, name: &str) -> Result<(), String> {
+    if FORBIDDEN_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+        return Err(format!(
+            "Invalid {}: \"{}\" is set by the browser and can't be overridden",
+            flag, name
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `name` is a global flag that consumes a value
+fn takes_value(name: &str) -> bool {
+    registry::global_flag(name).is_some_and(|f| f.value.is_some())
+}
+
+fn is_info_flag(name: &str) -> bool {
+    registry::INFO_FLAGS.iter().any(|f| f.name == name || f.short == Some(name))
+}
+
+fn unknown_flag_error(name: &str) -> String {
+    match suggest_flag(name) {
+        Some(suggestion) => format!("Unknown flag: {}. Did you mean {}?", name, suggestion),
+        None => format!("Unknown flag: {}", name),
+    }
+}
+
+/// Closest known global flag to `name`, if it is near enough to be a typo
+fn suggest_flag(name: &str) -> Option<&'static str> {
+    let max_distance = (name.len() / 3).clamp(1, 3);
+    registry::GLOBAL_FLAGS
+        .iter()
+        .chain(registry::INFO_FLAGS)
+        .map(|f| (levenshtein(name, f.name), f.name))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, flag)| flag)
+}
+
+/// Edit distance between two strings (insertions, deletions, substitutions)
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        prev = curr;
+    }
+    prev[b.len()]
+}
+
+/// Split `--flag=value` into the flag name and its inline value.
+/// Short flags and bare words are returned unchanged.
+fn split_inline_value(arg: &str) -> (&str, Option<&str>) {
+    if arg.starts_with("--") {
+        if let Some((name, value)) = arg.split_once('=') {
+            return (name, Some(value));
+        }
+    }
+    (arg, None)
+}
+
+/// Index of the `--` terminator, or the length of args if there is none.
+/// Flags are only recognized before this position.
+pub fn flag_end(args: &[String]) -> usize {
+    args.iter().position(|a| a == "--").unwrap_or(args.len())
+}
+
+/// The global flags in args with their values, for commands run on this
+/// invocation's behalf
+pub fn global_args(args: &[String]) -> Vec<String> {
+    let end = flag_end(args);
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < end {
+        let (name, inline_value) = split_inline_value(&args[i]);
+        let take = match registry::global_flag(name) {
+            Some(spec) if spec.value.is_some() && inline_value.is_none() => 2,
+            Some(_) => 1,
+            None => {
+                i += 1;
+                continue;
+            }
+        };
+        result.extend(args[i..(i + take).min(end)].iter().cloned());
+        i += take;
+    }
+    result
+}
+
 pub fn clean_args(args: &[String]) -> Vec<String> {
     let mut result = Vec::new();
-    let mut skip_next = false;
-
-    // Global flags that should be stripped from command args
-    const GLOBAL_FLAGS: &[&str] = &["--json", "--full", "--headed", "--debug"];
-    // Global flags that take a value (need to skip the next arg too)
-    const GLOBAL_FLAGS_WITH_VALUE: &[&str] = &[
-        "--session",
-        "--headers",
-        "--executable-path",
-        "--cdp",
-        "--extension",
-        "--profile",
-        "--proxy",
-        "--proxy-bypass",
-        "--args",
-        "--user-agent",
-        "-p",
-        "--provider",
-        "--session-name",
-    ];
-
-    for arg in args.iter() {
-        if skip_next {
-            skip_next = false;
-            continue;
+
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        // Everything after `--` is passed through untouched
+        if arg == "--" {
+            result.extend(args[i + 1..].iter().cloned());
+            break;
         }
-        if GLOBAL_FLAGS_WITH_VALUE.contains(&arg.as_str()) {
-            skip_next = true;
+        let (name, inline_value) = split_inline_value(arg);
+        // Only strip known global flags, not command-specific flags
+        if let Some(spec) = registry::global_flag(name) {
+            // Skip the separate value argument unless it was given inline
+            i += if spec.value.is_some() && inline_value.is_none() { 2 } else { 1 };
             continue;
         }
-        // Only strip known global flags, not command-specific flags
-        if GLOBAL_FLAGS.contains(&arg.as_str()) || arg == "-f" {
+        // Flags before the command can only be global; unknown ones were either
+        // reported by parse_flags or accepted with --no-strict, so drop them here
+        if result.is_empty() && arg.starts_with('-') {
+            i += 1;
             continue;
         }
-        result.push(arg.clone());
+        // Normalize command-specific `--flag=value` into two arguments
+        if let Some(value) = inline_value {
+            result.push(name.to_string());
+            result.push(value.to_string());
+        } else {
+            result.push(arg.clone());
+        }
+        i += 1;
     }
     result
 }
@@ -264,6 +666,12 @@ mod tests {
         assert_eq!(flags.executable_path, None);
     }
 
+    #[test]
+    fn test_global_args() {
+        let globals = global_args(&args("--session a run flow.ab --headed --var X=1 --proxy=http://p -- --json"));
+        assert_eq!(globals, vec!["--session", "a", "--headed", "--proxy=http://p"]);
+    }
+
     #[test]
     fn test_clean_args_removes_executable_path() {
         let cleaned = clean_args(&args(
@@ -288,4 +696,274 @@ mod tests {
         assert_eq!(flags.session, "test");
         assert_eq!(flags.executable_path, Some("/custom/chrome".to_string()));
     }
+
+    #[test]
+    fn test_parse_ignore_https_errors_flag() {
+        let flags = parse_flags(&args("--ignore-https-errors open https://staging.local"));
+        assert!(flags.ignore_https_errors);
+        let clean = clean_args(&args("--ignore-https-errors open https://staging.local"));
+        assert_eq!(clean, vec!["open", "https://staging.local"]);
+    }
+
+    #[test]
+    fn test_parse_session_mode_flags() {
+        let input = args("--ephemeral open https://example.com");
+        assert!(parse_flags(&input).ephemeral);
+        assert_eq!(clean_args(&input), vec!["open", "https://example.com"]);
+        let input = args("--read-only open https://example.com");
+        assert!(parse_flags(&input).read_only);
+        assert_eq!(clean_args(&input), vec!["open", "https://example.com"]);
+        let input = args("--wait-lock 5000 click #buy");
+        assert_eq!(parse_flags(&input).wait_lock, Some("5000".to_string()));
+        assert_eq!(clean_args(&input), vec!["click", "#buy"]);
+        let input = args("--save-state-interval 30s open example.com");
+        assert_eq!(parse_flags(&input).save_state_interval, Some("30s".to_string()));
+        let input = args("--state-dir /tmp/ci-state open example.com");
+        assert_eq!(parse_flags(&input).state_dir, Some("/tmp/ci-state".to_string()));
+        assert_eq!(clean_args(&input), vec!["open", "example.com"]);
+        let input = args("--token abc123 snapshot");
+        assert_eq!(parse_flags(&input).token, Some("abc123".to_string()));
+        assert_eq!(clean_args(&input), vec!["snapshot"]);
+    }
+
+    #[test]
+    fn test_parse_extra_ca_flag() {
+        let input = args("open https://staging.local --extra-ca ./certs/root.pem");
+        let flags = parse_flags(&input);
+        assert_eq!(flags.extra_ca, Some("./certs/root.pem".to_string()));
+        assert_eq!(clean_args(&input), vec!["open", "https://staging.local"]);
+    }
+
+    #[test]
+    fn test_parse_scroll_margin_flag() {
+        let input = args("click #buy --scroll-margin 80");
+        let flags = parse_flags(&input);
+        assert_eq!(flags.scroll_margin, Some("80".to_string()));
+        assert_eq!(clean_args(&input), vec!["click", "#buy"]);
+    }
+
+    #[test]
+    fn test_parse_headers_for_repeatable() {
+        let input: Vec<String> = vec![
+            "--headers-for".to_string(),
+            r#"api.example.com={"Authorization":"Bearer a"}"#.to_string(),
+            "open".to_string(),
+            "example.com".to_string(),
+            "--headers-for".to_string(),
+            r#"cdn.example.com={"X-Key":"b"}"#.to_string(),
+        ];
+        let flags = parse_flags(&input);
+        assert_eq!(flags.headers_for.len(), 2);
+        assert_eq!(
+            flags.headers_for[0],
+            r#"api.example.com={"Authorization":"Bearer a"}"#
+        );
+        assert_eq!(clean_args(&input), vec!["open", "example.com"]);
+    }
+
+    #[test]
+    fn test_headers_before_command_are_session_level() {
+        let input: Vec<String> = vec![
+            "--headers".to_string(),
+            r#"{"Auth":"token"}"#.to_string(),
+            "open".to_string(),
+            "example.com".to_string(),
+        ];
+        let flags = parse_flags(&input);
+        assert_eq!(flags.headers, Some(r#"{"Auth":"token"}"#.to_string()));
+        assert!(!flags.headers_navigation_only);
+    }
+
+    #[test]
+    fn test_headers_after_command_are_navigation_only() {
+        let input: Vec<String> = vec![
+            "--session".to_string(),
+            "ab".to_string(),
+            "open".to_string(),
+            "example.com".to_string(),
+            "--headers".to_string(),
+            r#"{"X-Debug":"1"}"#.to_string(),
+        ];
+        let flags = parse_flags(&input);
+        assert_eq!(flags.headers, Some(r#"{"X-Debug":"1"}"#.to_string()));
+        assert!(flags.headers_navigation_only);
+    }
+
+    #[test]
+    fn test_clean_args_keeps_open_options() {
+        let input = args("--json open example.com --wait-until commit --referer https://a.b --new-tab");
+        assert_eq!(
+            clean_args(&input),
+            vec!["open", "example.com", "--wait-until", "commit", "--referer", "https://a.b", "--new-tab"]
+        );
+    }
+
+    #[test]
+    fn test_parse_inline_values() {
+        let input = args("--session=work --cdp=9222 open example.com");
+        let flags = parse_flags(&input);
+        assert_eq!(flags.session, "work");
+        assert_eq!(flags.cdp, Some("9222".to_string()));
+        assert_eq!(clean_args(&input), vec!["open", "example.com"]);
+    }
+
+    #[test]
+    fn test_cache_flag_takes_a_ttl() {
+        let input = args("--cache 5m get text main");
+        assert_eq!(parse_flags(&input).cache, Some("5m".to_string()));
+        assert_eq!(clean_args(&input), vec!["get", "text", "main"]);
+        assert_eq!(clean_args(&args("snapshot --no-cache")), vec!["snapshot", "--no-cache"]);
+    }
+
+    #[test]
+    fn test_parse_inline_value_containing_equals() {
+        let input: Vec<String> = vec![
+            r#"--headers-for=api.example.com={"A":"b=c"}"#.to_string(),
+            "open".to_string(),
+            "example.com".to_string(),
+        ];
+        let flags = parse_flags(&input);
+        assert_eq!(flags.headers_for, vec![r#"api.example.com={"A":"b=c"}"#]);
+        assert_eq!(clean_args(&input), vec!["open", "example.com"]);
+    }
+
+    #[test]
+    fn test_parse_repeatable_header_flag() {
+        let input: Vec<String> = vec![
+            "--header".to_string(),
+            "X-One: 1".to_string(),
+            "--header=X-Two: 2".to_string(),
+            "open".to_string(),
+            "example.com".to_string(),
+        ];
+        let flags = parse_flags(&input);
+        assert_eq!(flags.header, vec!["X-One: 1", "X-Two: 2"]);
+        assert!(!flags.headers_navigation_only);
+        assert_eq!(clean_args(&input), vec!["open", "example.com"]);
+    }
+
+    #[test]
+    fn test_header_flags_share_one_scope() {
+        let flags = parse_flags(&args("open example.com --header X-One:1 --headers {}"));
+        assert!(flags.headers_navigation_only);
+        assert!(flags.errors.is_empty());
+
+        for input in [
+            "--headers {} open example.com --header X-One:1",
+            "--header X-One:1 open example.com --headers {}",
+            "--no-strict --header X-One:1 open example.com --header X-Two:2",
+        ] {
+            let flags = parse_flags(&args(input));
+            assert_eq!(flags.errors.len(), 1, "{}", input);
+            assert!(flags.errors[0].contains("must all go before the command"), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_double_dash_stops_flag_parsing() {
+        let input = args("fill #input -- --json --session other");
+        let flags = parse_flags(&input);
+        assert!(!flags.json);
+        assert_eq!(flags.session, parse_flags(&[]).session);
+        assert_eq!(
+            clean_args(&input),
+            vec!["fill", "#input", "--json", "--session", "other"]
+        );
+        assert_eq!(flag_end(&input), 2);
+    }
+
+    #[test]
+    fn test_clean_args_splits_command_inline_values() {
+        let input = args("open example.com --wait-until=load --json");
+        assert_eq!(
+            clean_args(&input),
+            vec!["open", "example.com", "--wait-until", "load"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_flag_suggests_closest() {
+        let flags = parse_flags(&args("--sesion work open example.com"));
+        assert_eq!(flags.errors, vec!["Unknown flag: --sesion. Did you mean --session?"]);
+
+        let flags = parse_flags(&args("--jsno snapshot"));
+        assert_eq!(flags.errors, vec!["Unknown flag: --jsno. Did you mean --json?"]);
+    }
+
+    #[test]
+    fn test_unknown_flag_without_suggestion() {
+        let flags = parse_flags(&args("--completely-different open example.com"));
+        assert_eq!(flags.errors, vec!["Unknown flag: --completely-different"]);
+    }
+
+    #[test]
+    fn test_command_flags_are_not_reported() {
+        let flags = parse_flags(&args("snapshot -i --compact"));
+        assert!(flags.errors.is_empty());
+        let flags = parse_flags(&args("--help"));
+        assert!(flags.errors.is_empty());
+    }
+
+    #[test]
+    fn test_no_strict_ignores_unknown_flags() {
+        let input = args("--no-strict --future-flag open example.com");
+        let flags = parse_flags(&input);
+        assert!(!flags.strict);
+        assert!(flags.errors.is_empty());
+        assert_eq!(clean_args(&input), vec!["open", "example.com"]);
+    }
+
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("--sesion", "--session"), 1);
+        assert_eq!(levenshtein("abc", "abc"), 0);
+        assert_eq!(levenshtein("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_headers_validated_at_parse_time() {
+        let flags = parse_flags(&args(r#"--headers {"X-Team":"qa"} open example.com"#));
+        assert!(flags.errors.is_empty());
+
+        let flags = parse_flags(&args("--headers not-json open example.com"));
+        assert!(flags.errors[0].starts_with("Invalid --headers: not valid JSON"));
+
+        let flags = parse_flags(&args(r#"--no-strict --headers ["X-Team"] open example.com"#));
+        assert!(flags.errors[0].contains("expected a JSON object"));
+
+        let flags = parse_flags(&args(r#"open example.com --headers {"X-Count":3}"#));
+        assert_eq!(
+            flags.errors,
+            vec![r#"Invalid --headers: value of "X-Count" must be a string, got 3"#]
+        );
+
+        let flags = parse_flags(&args(r#"--headers {"HOST":"evil.test"} open example.com"#));
+        assert!(flags.errors[0].contains(r#""HOST" is set by the browser"#));
+    }
+
+    #[test]
+    fn test_header_lines_validated_at_parse_time() {
+        let flags = parse_flags(&args("open example.com --header=X-Team:qa"));
+        assert!(flags.errors.is_empty());
+
+        let flags = parse_flags(&args("--no-strict --header no-colon open example.com"));
+        assert_eq!(flags.errors, vec![r#"Invalid --header 'no-colon': expected "Name: value""#]);
+
+        let flags = parse_flags(&args("open example.com --header=Content-Length:0"));
+        assert_eq!(
+            flags.errors,
+            vec![
+                r#"Invalid --header: "Content-Length" is set by the browser and can't be overridden"#
+            ]
+        );
+    }
+
+    #[test]
+    fn test_browser_arg_repeatable_and_verbatim() {
+        let input = args("--browser-arg --disable-gpu --browser-arg=--disable-features=A,B open example.com");
+        let flags = parse_flags(&input);
+        assert_eq!(flags.browser_args, vec!["--disable-gpu", "--disable-features=A,B"]);
+        assert!(flags.errors.is_empty());
+        assert_eq!(clean_args(&input), vec!["open", "example.com"]);
+    }
 }
@@ -0,0 +1,145 @@
+//! Framed responses from the daemon. After the HELLO line, the daemon sends
+//! each response as frames, `<kind: u8><length: u32 BE><payload>`, instead of
+//! a JSON line: large strings and binary data (screenshots) come first as
+//! blob frames, neither base64-encoded nor JSON-escaped, and a JSON frame with
+//! null in their place ends the response. A blob payload starts with
+//! `<path length: u16 BE><path>`, the JSON array of keys and indexes where
+//! its value goes.
+//!
+//! A daemon from before framing answers HELLO with an error line and keeps
+//! to lines, which `read_response` detects from the first byte.
+//! AGENT_BROWSER_TRANSPORT=json turns framing off.
+
+use serde_json::Value;
+use std::env;
+use std::io::{BufRead, Read};
+
+use crate::transcript::base64_encode;
+
+pub const HELLO: &str = "AB-FRAMES 1\n";
+
+const FRAME_JSON: u8 = 1;
+const FRAME_BINARY: u8 = 2;
+const FRAME_TEXT: u8 = 3;
+
+/// Whether to ask the daemon for frames
+pub fn enabled() -> bool {
+    !matches!(env::var("AGENT_BROWSER_TRANSPORT").as_deref(), Ok("json"))
+}
+
+fn read_frame<R: Read>(reader: &mut R) -> Result<(u8, Vec<u8>), String> {
+    let mut header = [0u8; 5];
+    reader.read_exact(&mut header).map_err(|e| format!("Failed to read: {}", e))?;
+    let length = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    let mut payload = vec![0u8; length];
+    reader.read_exact(&mut payload).map_err(|e| format!("Failed to read: {}", e))?;
+    Ok((header[0], payload))
+}
+
+/// Put a blob's value where its path points in the response
+fn place(target: &mut Value, path: &[Value], value: Value) -> Result<(), String> {
+    let mut slot = target;
+    for step in path {
+        slot = match step {
+            Value::String(key) => slot.get_mut(key.as_str()),
+            Value::Number(index) => index.as_u64().and_then(|i| slot.get_mut(i as usize)),
+            _ => None,
+        }
+        .ok_or_else(|| format!("Invalid response: no place for a blob at {}", Value::from(path.to_vec())))?;
+    }
+    *slot = value;
+    Ok(())
+}
+
+/// Read the frames of one response into its JSON value
+pub fn read_frames<R: Read>(reader: &mut R) -> Result<Value, String> {
+    let mut blobs: Vec<(Vec<Value>, Value)> = Vec::new();
+    loop {
+        let (kind, payload) = read_frame(reader)?;
+        if kind == FRAME_JSON {
+            let mut response: Value =
+                serde_json::from_slice(&payload).map_err(|e| format!("Invalid response: {}", e))?;
+            for (path, value) in blobs {
+                place(&mut response, &path, value)?;
+            }
+            return Ok(response);
+        }
+        if payload.len() < 2 {
+            return Err("Invalid response: truncated blob frame".to_string());
+        }
+        let path_length = u16::from_be_bytes([payload[0], payload[1]]) as usize + 2;
+        let path: Vec<Value> = payload
+            .get(2..path_length)
+            .and_then(|bytes| serde_json::from_slice(bytes).ok())
+            .ok_or("Invalid response: blob frame without a path")?;
+        let bytes = &payload[path_length..];
+        let value = match kind {
+            FRAME_BINARY => Value::String(base64_encode(bytes)),
+            FRAME_TEXT => Value::String(String::from_utf8_lossy(bytes).into_owned()),
+            _ => return Err(format!("Invalid response: unknown frame kind {}", kind)),
+        };
+        blobs.push((path, value));
+    }
+}
+
+/// Read one response after sending HELLO: frames, or lines from a daemon
+/// that doesn't know them, whose first line is its answer to HELLO
+pub fn read_response<R: BufRead>(reader: &mut R) -> Result<Value, String> {
+    let first = reader.fill_buf().map_err(|e| format!("Failed to read: {}", e))?.first().copied();
+    match first {
+        None => Err("Failed to read: connection closed".to_string()),
+        Some(FRAME_JSON | FRAME_BINARY | FRAME_TEXT) => read_frames(reader),
+        Some(_) => {
+            let mut line = String::new();
+            reader.read_line(&mut line).map_err(|e| format!("Failed to read: {}", e))?;
+            line.clear();
+            reader.read_line(&mut line).map_err(|e| format!("Failed to read: {}", e))?;
+            serde_json::from_str(&line).map_err(|e| format!("Invalid response: {}", e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Cursor;
+
+    fn frame(kind: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = vec![kind];
+        out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        out.extend_from_slice(payload);
+        out
+    }
+
+    fn blob(kind: u8, path: &str, bytes: &[u8]) -> Vec<u8> {
+        let mut payload = (path.len() as u16).to_be_bytes().to_vec();
+        payload.extend_from_slice(path.as_bytes());
+        payload.extend_from_slice(bytes);
+        frame(kind, &payload)
+    }
+
+    #[test]
+    fn test_read_frames_places_blobs() {
+        let mut bytes = blob(FRAME_TEXT, r#"["data","html"]"#, "<p>\"é\"</p>\n".as_bytes());
+        bytes.extend(blob(FRAME_BINARY, r#"["data","images",1]"#, b"png"));
+        bytes.extend(frame(FRAME_JSON, br#"{"success":true,"data":{"html":null,"images":["a",null]}}"#));
+        let response = read_response(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!(response["data"], json!({ "html": "<p>\"é\"</p>\n", "images": ["a", "cG5n"] }));
+    }
+
+    #[test]
+    fn test_read_response_falls_back_to_lines() {
+        let lines = "{\"id\":\"unknown\",\"success\":false,\"error\":\"Invalid JSON\"}\n{\"success\":true,\"data\":{\"url\":\"x\"}}\n";
+        let response = read_response(&mut Cursor::new(lines.as_bytes().to_vec())).unwrap();
+        assert_eq!(response["data"]["url"], "x");
+    }
+
+    #[test]
+    fn test_read_frames_rejects_bad_frames() {
+        let mut bytes = blob(FRAME_TEXT, r#"["data","missing","deep"]"#, b"x");
+        bytes.extend(frame(FRAME_JSON, br#"{"data":{}}"#));
+        assert!(read_frames(&mut Cursor::new(bytes)).unwrap_err().contains("no place"));
+        assert!(read_frames(&mut Cursor::new(frame(FRAME_JSON, b"{")[..4].to_vec())).is_err());
+    }
+}
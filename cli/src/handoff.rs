@@ -0,0 +1,134 @@
+//! File handoff for screenshots and PDFs.
+//!
+//! Before sending `screenshot` or `pdf`, the CLI creates an empty directory
+//! and names it in the command's `handoff` field. A daemon that can see it
+//! shares the CLI's filesystem: it writes the file straight to its path, and
+//! a screenshot without one into the directory, and answers with the path
+//! alone (`handoff` for the latter), so no image crosses the socket. A daemon
+//! that can't see it (in another container, sharing only the socket
+//! directory) sends the bytes instead, and the CLI writes them to the path.
+//! Relative paths are resolved against the CLI's working directory, not the
+//! daemon's.
+
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::connection::Response;
+use crate::oauth::random_token;
+use crate::transcript::{base64_decode, base64_encode};
+
+/// Actions whose output file can be handed off
+const ACTIONS: &[&str] = &["screenshot", "pdf"];
+
+/// Resolve the command's path against the working directory and create its
+/// handoff directory; None for other actions or when it can't be created
+pub fn prepare(cmd: &mut Value) -> Option<PathBuf> {
+    if !ACTIONS.contains(&cmd["action"].as_str().unwrap_or("")) {
+        return None;
+    }
+    if let Some(path) = cmd["path"].as_str().filter(|p| Path::new(p).is_relative()) {
+        if let Ok(cwd) = env::current_dir() {
+            cmd["path"] = json!(cwd.join(path).to_string_lossy());
+        }
+    }
+    // A random name: a daemon that sees this directory sees the CLI's files
+    let dir = env::temp_dir().join(format!("agent-browser-handoff-{}", &random_token()[..16]));
+    create_private_dir(&dir).ok()?;
+    Some(dir)
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    fs::DirBuilder::new().mode(0o700).create(dir)
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    fs::create_dir(dir)
+}
+
+/// The command as sent, naming the handoff directory
+pub fn attach(mut cmd: Value, dir: Option<&Path>) -> Value {
+    if let Some(dir) = dir {
+        cmd["handoff"] = json!(dir.to_string_lossy());
+    }
+    cmd
+}
+
+/// Turn a handed-off response into the usual one: read a screenshot the
+/// daemon left in the directory into `base64`, or write bytes a daemon on
+/// another filesystem sent to the path. Removes the directory.
+pub fn finish(resp: &mut Response, dir: &Path) {
+    let result = resp.data.as_mut().filter(|_| resp.success).map_or(Ok(()), receive);
+    let _ = fs::remove_dir_all(dir);
+    if let Err(e) = result {
+        resp.success = false;
+        resp.data = None;
+        resp.error = Some(e);
+    }
+}
+
+fn receive(data: &mut Value) -> Result<(), String> {
+    let Some(object) = data.as_object_mut() else {
+        return Ok(());
+    };
+    if let Some(file) = object.remove("handoff").and_then(|v| v.as_str().map(String::from)) {
+        let bytes = fs::read(&file).map_err(|e| format!("Cannot read {}: {}", file, e))?;
+        object.insert("base64".to_string(), json!(base64_encode(&bytes)));
+    }
+    if let Some(encoded) = object.remove("bytes").and_then(|v| v.as_str().map(String::from)) {
+        let path = object.get("path").and_then(|v| v.as_str()).ok_or("Response has bytes but no path")?;
+        let bytes = base64_decode(&encoded).ok_or("Response bytes are not base64")?;
+        if let Some(parent) = Path::new(path).parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(|e| format!("Cannot create {}: {}", parent.display(), e))?;
+        }
+        fs::write(path, bytes).map_err(|e| format!("Cannot write {}: {}", path, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(data: Value) -> Response {
+        Response { success: true, data: Some(data), ..Default::default() }
+    }
+
+    #[test]
+    fn test_prepare_resolves_relative_paths() {
+        let mut cmd = json!({ "action": "pdf", "path": "out/page.pdf" });
+        let dir = prepare(&mut cmd).unwrap();
+        assert!(dir.is_dir());
+        assert_eq!(cmd["path"], json!(env::current_dir().unwrap().join("out/page.pdf").to_string_lossy()));
+        assert_eq!(attach(cmd, Some(&dir))["handoff"], json!(dir.to_string_lossy()));
+        finish(&mut response(json!({})), &dir);
+        assert!(!dir.exists());
+
+        let mut click = json!({ "action": "click", "selector": "@e1" });
+        assert!(prepare(&mut click).is_none());
+    }
+
+    #[test]
+    fn test_finish_reads_and_writes_files() {
+        let mut cmd = json!({ "action": "screenshot", "path": null });
+        let dir = prepare(&mut cmd).unwrap();
+        let shot = dir.join("screenshot.png");
+        fs::write(&shot, b"png").unwrap();
+        let mut resp = response(json!({ "handoff": shot.to_string_lossy() }));
+        finish(&mut resp, &dir);
+        assert_eq!(resp.data.unwrap(), json!({ "base64": "cG5n" }));
+        assert!(!dir.exists());
+
+        let dir = prepare(&mut cmd).unwrap();
+        let target = env::temp_dir().join(format!("agent-browser-handoff-test-{}", &random_token()[..16])).join("a.pdf");
+        let mut resp = response(json!({ "path": target.to_string_lossy(), "bytes": "JVBERg==" }));
+        finish(&mut resp, &dir);
+        assert_eq!(fs::read(&target).unwrap(), b"%PDF");
+        assert_eq!(resp.data.unwrap(), json!({ "path": target.to_string_lossy() }));
+        let _ = fs::remove_dir_all(target.parent().unwrap());
+    }
+}
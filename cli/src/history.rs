@@ -0,0 +1,213 @@
+//! Command history of a session.
+//!
+//! Every command sent to the daemon is kept in `<data dir>/history/<session>.jsonl`
+//! with the words it was typed as, the command JSON and a one-line summary of
+//! its result. `history` lists the entries and `redo <n>` sends entry n again
+//! through the normal checks. The file is private to the user because redo
+//! needs the original values; listings show secrets redacted as in the audit
+//! log. Closing the session clears its history.
+
+use serde_json::{json, Value};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::audit;
+use crate::connection::{app_data_dir, Response};
+use crate::secrets;
+
+/// Entries kept per session; older ones are dropped
+const MAX_ENTRIES: usize = 1000;
+
+/// How far past MAX_ENTRIES the file may grow before it is trimmed, so most
+/// commands only append a line
+const TRIM_SLACK: usize = 200;
+
+/// Longest result summary stored with an entry
+const SUMMARY_CHARS: usize = 80;
+
+pub fn history_path(session: &str) -> PathBuf {
+    let dir = match std::env::var("AGENT_BROWSER_HISTORY_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => app_data_dir().join("history"),
+    };
+    dir.join(format!("{}.jsonl", session))
+}
+
+pub fn read_entries(session: &str) -> Result<Vec<Value>, String> {
+    let path = history_path(session);
+    let source = match fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Cannot read history '{}': {}", path.display(), e)),
+    };
+    // A line cut short by a crash is skipped rather than failing the listing
+    Ok(source.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+/// One line describing a command's result: the error, or the most telling
+/// field of its data
+pub fn summarize(result: Result<&Response, &str>) -> String {
+    let summary = match result {
+        Err(e) => e.to_string(),
+        Ok(resp) if !resp.success => resp.error.clone().unwrap_or_else(|| "Unknown error".to_string()),
+        Ok(resp) => {
+            let data = resp.data.as_ref().unwrap_or(&Value::Null);
+            ["title", "url", "text", "result", "count", "path", "value"]
+                .iter()
+                .find_map(|key| data.get(key).filter(|v| !v.is_null()))
+                .map(|value| match value {
+                    Value::String(s) => s.clone(),
+                    other => other.to_string(),
+                })
+                .unwrap_or_else(|| match data {
+                    Value::Null => String::new(),
+                    Value::Object(map) if map.is_empty() => String::new(),
+                    other => other.to_string(),
+                })
+        }
+    };
+    let line = summary.lines().next().unwrap_or_default();
+    match line.char_indices().nth(SUMMARY_CHARS) {
+        Some((cut, _)) => format!("{}…", &line[..cut]),
+        None => line.to_string(),
+    }
+}
+
+/// Append a command and its result
+pub fn record(session: &str, words: &[String], cmd: &Value, result: Result<&Response, &str>) -> Result<(), String> {
+    let path = history_path(session);
+    // A last line cut short by a crash falls back to reading the whole file
+    let last = match audit::last_entry(&path) {
+        Ok(last) => last,
+        Err(_) => read_entries(session)?.pop(),
+    };
+    let n = last.and_then(|e| e.get("n").and_then(|v| v.as_u64())).unwrap_or(0) + 1;
+    let ts = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0);
+    let mut command = cmd.clone();
+    if let Some(map) = command.as_object_mut() {
+        map.remove("id");
+        map.remove("noWait");
+    }
+    let success = matches!(result, Ok(resp) if resp.success);
+    let entry = json!({
+        "n": n,
+        "ts": ts,
+        "words": words,
+        "command": command,
+        "success": success,
+        "summary": summarize(result),
+    });
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create history dir: {}", e))?;
+    }
+    append(&path, &entry)
+}
+
+/// Append one entry, trimming the file back to MAX_ENTRIES once it holds
+/// TRIM_SLACK more than that
+fn append(path: &Path, entry: &Value) -> Result<(), String> {
+    secrets::append_private(path, format!("{}\n", entry).as_bytes())
+        .map_err(|e| format!("Cannot write history: {}", e))?;
+    let n = entry.get("n").and_then(|v| v.as_u64()).unwrap_or(0);
+    let held = n.saturating_sub(first_n(path).unwrap_or(n)) + 1;
+    if held as usize <= MAX_ENTRIES + TRIM_SLACK {
+        return Ok(());
+    }
+    let source = fs::read_to_string(path).map_err(|e| format!("Cannot read history '{}': {}", path.display(), e))?;
+    let lines: Vec<&str> = source.lines().filter(|line| serde_json::from_str::<Value>(line).is_ok()).collect();
+    let keep = lines.len().saturating_sub(MAX_ENTRIES);
+    let trimmed: String = lines[keep..].iter().map(|line| format!("{}\n", line)).collect();
+    // Written aside and renamed over, so a crash never leaves half a history
+    let tmp = path.with_extension("jsonl.tmp");
+    secrets::write_private(&tmp, trimmed.as_bytes()).map_err(|e| format!("Cannot write history: {}", e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Cannot write history: {}", e))
+}
+
+/// Number of the oldest entry in the file
+fn first_n(path: &Path) -> Option<u64> {
+    let mut line = String::new();
+    BufReader::new(File::open(path).ok()?).read_line(&mut line).ok()?;
+    serde_json::from_str::<Value>(&line).ok()?.get("n")?.as_u64()
+}
+
+pub fn clear(session: &str) {
+    let _ = fs::remove_file(history_path(session));
+}
+
+/// Entry `n` of the history
+pub fn find(entries: &[Value], n: u64) -> Result<&Value, String> {
+    entries
+        .iter()
+        .find(|e| e.get("n").and_then(|v| v.as_u64()) == Some(n))
+        .ok_or_else(|| format!("No history entry {} (see: agent-browser history)", n))
+}
+
+/// The words an entry was typed as
+pub fn words(entry: &Value) -> Vec<String> {
+    entry["words"].as_array().into_iter().flatten().filter_map(|w| w.as_str()).map(String::from).collect()
+}
+
+/// How an entry is listed: the words it was typed as, or its redacted
+/// command when those would show a secret
+pub fn display(entry: &Value) -> String {
+    let command = &entry["command"];
+    if audit::redact(command) != *command {
+        return audit::redact(command).to_string();
+    }
+    words(entry).join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ok(data: Value) -> Response {
+        Response { success: true, data: Some(data), error: None, meta: None }
+    }
+
+    #[test]
+    fn test_summarize() {
+        assert_eq!(summarize(Ok(&ok(json!({ "url": "https://example.com/", "title": "Example" })))), "Example");
+        assert_eq!(summarize(Ok(&ok(json!({ "count": 3 })))), "3");
+        assert_eq!(summarize(Ok(&ok(json!({})))), "");
+        assert_eq!(summarize(Err("Daemon not running")), "Daemon not running");
+        let failed = Response { success: false, data: None, error: Some("Timeout\nat line 2".to_string()), meta: None };
+        assert_eq!(summarize(Ok(&failed)), "Timeout");
+        let long = summarize(Ok(&ok(json!({ "text": "x".repeat(200) }))));
+        assert_eq!(long.chars().count(), SUMMARY_CHARS + 1);
+    }
+
+    #[test]
+    fn test_append_trims_only_well_past_the_cap() {
+        let path = std::env::temp_dir().join(format!("ab-history-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let count = |path: &Path| fs::read_to_string(path).unwrap().lines().count();
+        for n in 1..=(MAX_ENTRIES + TRIM_SLACK) as u64 {
+            append(&path, &json!({ "n": n })).unwrap();
+        }
+        assert_eq!(count(&path), MAX_ENTRIES + TRIM_SLACK);
+
+        let last = (MAX_ENTRIES + TRIM_SLACK) as u64 + 1;
+        append(&path, &json!({ "n": last })).unwrap();
+        assert_eq!(count(&path), MAX_ENTRIES);
+        assert_eq!(first_n(&path), Some(last + 1 - MAX_ENTRIES as u64));
+        assert_eq!(audit::last_entry(&path).unwrap().unwrap()["n"], last);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_find_and_display() {
+        let entries = vec![
+            json!({ "n": 4, "words": ["open", "example.com"], "command": { "action": "navigate", "url": "https://example.com" } }),
+            json!({ "n": 5, "words": ["fill", "#password", "hunter2"], "command": { "action": "fill", "selector": "#password", "value": "hunter2" } }),
+        ];
+        let entry = find(&entries, 4).unwrap();
+        assert_eq!(words(entry), vec!["open", "example.com"]);
+        assert_eq!(entry["command"]["url"], "https://example.com");
+        assert!(find(&entries, 9).unwrap_err().starts_with("No history entry 9"));
+        assert_eq!(display(&entries[0]), "open example.com");
+        assert!(!display(&entries[1]).contains("hunter2"));
+    }
+}
@@ -0,0 +1,220 @@
+//! Lifecycle hooks from the config file.
+//!
+//! `hooks` in config.json lists, per event, shell commands (`run`) or
+//! agent-browser commands (`command`) to run when the event happens:
+//!
+//! ```json
+//! {
+//!   "hooks": {
+//!     "onNavigate": [{ "command": ["click", "#accept-cookies"], "url": "example.com" }],
+//!     "onDownload": [{ "run": "curl -s -d \"$AGENT_BROWSER_HOOK_DATA\" https://hooks.example.com/dl" }],
+//!     "onDialog": [{ "run": "echo \"$AGENT_BROWSER_HOOK_MESSAGE\" >> dialogs.log" }],
+//!     "onSessionStart": [{ "command": ["set", "viewport", "1280", "800"] }]
+//!   }
+//! }
+//! ```
+//!
+//! Events are seen by the CLI: `onSessionStart` when it starts the session's
+//! daemon, `onNavigate` after `open`, `onDownload` after `download` and
+//! `wait --download`, and `onDialog` for the dialogs the daemon recorded while
+//! a command ran. Snake-case names (`on_navigate`, ...) are accepted too.
+//! Shell hooks get the event in AGENT_BROWSER_HOOK_* variables; commands run in
+//! the same session and don't trigger hooks themselves. A failing hook is
+//! reported as a warning and never fails the command that triggered it.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::process::Command;
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct Hooks {
+    #[serde(alias = "on_navigate")]
+    pub on_navigate: Vec<Hook>,
+    #[serde(alias = "on_download")]
+    pub on_download: Vec<Hook>,
+    #[serde(alias = "on_dialog")]
+    pub on_dialog: Vec<Hook>,
+    #[serde(alias = "on_session_start")]
+    pub on_session_start: Vec<Hook>,
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hook {
+    /// Shell command, run with `sh -c` (`cmd /C` on Windows)
+    pub run: Option<String>,
+    /// agent-browser command and arguments, run in the same session
+    pub command: Option<Vec<String>>,
+    /// Only run when the event's URL contains this text
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Navigate,
+    Download,
+    Dialog,
+    SessionStart,
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::Navigate => "navigate",
+            Event::Download => "download",
+            Event::Dialog => "dialog",
+            Event::SessionStart => "session_start",
+        }
+    }
+}
+
+impl Hooks {
+    pub fn validate(&self) -> Result<(), String> {
+        let all = [
+            ("onNavigate", &self.on_navigate),
+            ("onDownload", &self.on_download),
+            ("onDialog", &self.on_dialog),
+            ("onSessionStart", &self.on_session_start),
+        ];
+        for (event, hooks) in all {
+            for hook in hooks {
+                match (&hook.run, &hook.command) {
+                    (Some(run), None) if !run.trim().is_empty() => {}
+                    (None, Some(command)) if !command.is_empty() => {}
+                    _ => return Err(format!("{} hooks need either a `run` or a `command`", event)),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn for_event(&self, event: Event) -> &[Hook] {
+        match event {
+            Event::Navigate => &self.on_navigate,
+            Event::Download => &self.on_download,
+            Event::Dialog => &self.on_dialog,
+            Event::SessionStart => &self.on_session_start,
+        }
+    }
+}
+
+impl Hook {
+    pub fn matches(&self, data: &Value) -> bool {
+        match &self.url {
+            Some(pattern) => data
+                .get("url")
+                .and_then(|v| v.as_str())
+                .is_some_and(|url| url.contains(pattern.as_str())),
+            None => true,
+        }
+    }
+}
+
+/// Events triggered by a successful response to `action`, with their data
+pub fn events_for(action: &str, data: &Value) -> Vec<(Event, Value)> {
+    let field = |key: &str| data.get(key).cloned().unwrap_or(Value::Null);
+    match action {
+        "navigate" => vec![(Event::Navigate, json!({ "url": field("url"), "title": field("title") }))],
+        "download" | "waitfordownload" => vec![(
+            Event::Download,
+            json!({ "path": field("path"), "filename": field("filename"), "url": field("url") }),
+        )],
+        _ => Vec::new(),
+    }
+}
+
+/// Variables describing an event for shell hooks. Each string field of the
+/// event data is also set on its own, e.g. AGENT_BROWSER_HOOK_URL.
+pub fn hook_env(event: Event, session: &str, data: &Value) -> Vec<(String, String)> {
+    let mut vars = vec![
+        ("AGENT_BROWSER_HOOK_EVENT".to_string(), event.name().to_string()),
+        ("AGENT_BROWSER_HOOK_DATA".to_string(), data.to_string()),
+        ("AGENT_BROWSER_SESSION".to_string(), session.to_string()),
+    ];
+    if let Some(map) = data.as_object() {
+        for (key, value) in map {
+            if let Some(text) = value.as_str() {
+                vars.push((format!("AGENT_BROWSER_HOOK_{}", key.to_uppercase()), text.to_string()));
+            }
+        }
+    }
+    vars
+}
+
+/// Run a shell hook, echoing its output to stderr so --json output stays clean
+pub fn run_shell(script: &str, env: &[(String, String)]) -> Result<(), String> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", script]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", script]);
+        cmd
+    };
+    cmd.envs(env.iter().map(|(k, v)| (k, v)));
+    let output = cmd.output().map_err(|e| format!("Cannot run hook '{}': {}", script, e))?;
+    eprint!("{}", String::from_utf8_lossy(&output.stdout));
+    eprint!("{}", String::from_utf8_lossy(&output.stderr));
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!("Hook '{}' failed ({})", script, output.status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Hooks {
+        serde_json::from_str(source).unwrap()
+    }
+
+    #[test]
+    fn test_parse_and_validate() {
+        let hooks = parse(
+            r##"{ "onNavigate": [{ "command": ["click", "#accept"], "url": "example.com" }],
+                 "on_download": [{ "run": "notify" }] }"##,
+        );
+        assert_eq!(hooks.on_navigate[0].command.as_deref(), Some(&["click".to_string(), "#accept".to_string()][..]));
+        assert_eq!(hooks.for_event(Event::Download)[0].run.as_deref(), Some("notify"));
+        assert!(hooks.validate().is_ok());
+
+        let both = parse(r#"{ "onDialog": [{ "run": "x", "command": ["reload"] }] }"#);
+        assert!(both.validate().unwrap_err().contains("onDialog"));
+        assert!(parse(r#"{ "onSessionStart": [{}] }"#).validate().is_err());
+        assert!(serde_json::from_str::<Hooks>(r#"{ "onClick": [] }"#).is_err());
+    }
+
+    #[test]
+    fn test_events_and_matching() {
+        let events = events_for("navigate", &json!({ "url": "https://shop.example.com/", "title": "Shop" }));
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].0, Event::Navigate);
+        let hook = Hook { url: Some("example.com".to_string()), ..Hook::default() };
+        assert!(hook.matches(&events[0].1));
+        assert!(!hook.matches(&json!({ "url": "https://other.test/" })));
+        assert!(Hook::default().matches(&json!({})));
+        assert_eq!(events_for("waitfordownload", &json!({ "path": "/tmp/a.pdf" }))[0].0, Event::Download);
+        assert!(events_for("click", &json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_hook_env() {
+        let vars = hook_env(Event::Dialog, "ci", &json!({ "type": "confirm", "message": "Sure?" }));
+        let get = |name: &str| vars.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+        assert_eq!(get("AGENT_BROWSER_HOOK_EVENT"), Some("dialog"));
+        assert_eq!(get("AGENT_BROWSER_HOOK_MESSAGE"), Some("Sure?"));
+        assert_eq!(get("AGENT_BROWSER_SESSION"), Some("ci"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_shell() {
+        let env = vec![("AGENT_BROWSER_HOOK_EVENT".to_string(), "navigate".to_string())];
+        assert!(run_shell("test \"$AGENT_BROWSER_HOOK_EVENT\" = navigate", &env).is_ok());
+        assert!(run_shell("exit 3", &env).unwrap_err().contains("failed"));
+    }
+}
@@ -0,0 +1,257 @@
+//! Host allowlist/denylist for --allow-hosts and --block-hosts, and host
+//! mapping for --host-rule.
+//!
+//! The lists are passed to the daemon at launch, which applies them to every
+//! request the browser makes. The CLI also checks navigation URLs up front so
+//! a blocked `open` fails before anything is sent.
+//!
+//! --host-rule mappings become Chromium's `--host-resolver-rules` switch, so
+//! the browser resolves the host to the given address while URLs, cookies and
+//! the Host header keep the original name.
+//!
+//! --ip-family and --dns-over-https are passed to the daemon at launch, which
+//! sends the browser's connections through a local proxy that resolves host
+//! names accordingly.
+
+use serde_json::{json, Value};
+
+/// Parsed --allow-hosts / --block-hosts patterns
+#[derive(Debug, Default, PartialEq)]
+pub struct HostRules {
+    pub allow: Vec<String>,
+    pub block: Vec<String>,
+}
+
+/// A navigation rejected by the host rules
+#[derive(Debug, PartialEq)]
+pub struct HostViolation {
+    pub host: String,
+    pub reason: String,
+}
+
+/// Split a comma separated host list, lowercasing and dropping empty entries
+pub fn parse_host_list(list: &str) -> Result<Vec<String>, String> {
+    list.split(',')
+        .map(|h| h.trim().to_lowercase())
+        .filter(|h| !h.is_empty())
+        .map(|h| {
+            let bare = h.strip_prefix("*.").unwrap_or(&h);
+            if bare.is_empty() || bare.contains(['*', '/', ':', ' ']) {
+                Err(format!(
+                    "Invalid host pattern '{}': use a host name or *.domain",
+                    h
+                ))
+            } else {
+                Ok(h)
+            }
+        })
+        .collect()
+}
+
+/// Parse a --host-rule mapping "host=address[:port]" into (host, address).
+/// The host may be a `*.domain` pattern; IPv6 addresses go in brackets.
+pub fn parse_host_mapping(rule: &str) -> Result<(String, String), String> {
+    let invalid = || {
+        format!(
+            "Invalid --host-rule '{}': use host=address[:port], e.g. staging.example.com=127.0.0.1:8443",
+            rule
+        )
+    };
+    let (host, target) = rule.split_once('=').ok_or_else(invalid)?;
+    let host = parse_host_list(host).ok().filter(|h| h.len() == 1).ok_or_else(invalid)?.remove(0);
+    let target = target.trim();
+    let (address, port) = match target.strip_prefix('[') {
+        Some(rest) => {
+            let (address, after) = rest.split_once(']').ok_or_else(invalid)?;
+            (address, after.strip_prefix(':'))
+        }
+        None => match target.split_once(':') {
+            Some((address, port)) => (address, Some(port)),
+            None => (target, None),
+        },
+    };
+    let address_ok = !address.is_empty()
+        && address.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':'));
+    if !address_ok || port.is_some_and(|p| p.parse::<u16>().map_or(true, |p| p == 0)) {
+        return Err(invalid());
+    }
+    Ok((host, target.to_string()))
+}
+
+/// The `--host-resolver-rules` browser switch for a set of --host-rule mappings
+pub fn resolver_rules_switch(rules: &[String]) -> Result<Option<String>, String> {
+    let maps = rules
+        .iter()
+        .map(|rule| parse_host_mapping(rule).map(|(host, target)| format!("MAP {} {}", host, target)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((!maps.is_empty()).then(|| format!("--host-resolver-rules={}", maps.join(","))))
+}
+
+/// The launch `dns` options for --ip-family and --dns-over-https
+pub fn dns_options(ip_family: Option<&str>, doh_url: Option<&str>) -> Result<Option<Value>, String> {
+    let family = match ip_family.map(|f| f.trim().to_lowercase()) {
+        None => None,
+        Some(f) if f == "4" || f == "ipv4" => Some(4),
+        Some(f) if f == "6" || f == "ipv6" => Some(6),
+        Some(f) => return Err(format!("Invalid --ip-family '{}': use 4 or 6", f)),
+    };
+    if let Some(url) = doh_url {
+        let host = url.strip_prefix("https://").and_then(|rest| rest.split(['/', '?']).next());
+        if host.is_none_or(|h| h.is_empty()) {
+            return Err(format!("Invalid --dns-over-https '{}': use an https:// resolver URL", url));
+        }
+    }
+    if family.is_none() && doh_url.is_none() {
+        return Ok(None);
+    }
+    let mut options = json!({});
+    if let Some(family) = family {
+        options["family"] = json!(family);
+    }
+    if let Some(url) = doh_url {
+        options["dohUrl"] = json!(url);
+    }
+    Ok(Some(options))
+}
+
+impl HostRules {
+    pub fn from_flags(allow: Option<&str>, block: Option<&str>) -> Result<Self, String> {
+        Ok(HostRules {
+            allow: allow.map(parse_host_list).transpose()?.unwrap_or_default(),
+            block: block.map(parse_host_list).transpose()?.unwrap_or_default(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.block.is_empty()
+    }
+
+    /// Check a URL against the rules. Blocked hosts win over allowed ones, and
+    /// URLs without a network host (about:, data:, file:) are always allowed.
+    pub fn check_url(&self, url: &str) -> Result<(), HostViolation> {
+        let Some(host) = url_host(url) else {
+            return Ok(());
+        };
+        if let Some(rule) = self.block.iter().find(|p| host_matches(p, &host)) {
+            return Err(HostViolation {
+                reason: format!("{} matches --block-hosts rule '{}'", host, rule),
+                host,
+            });
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|p| host_matches(p, &host)) {
+            return Err(HostViolation {
+                reason: format!("{} is not in --allow-hosts", host),
+                host,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// `*.example.com` matches any subdomain of example.com (but not example.com
+/// itself); other patterns match the host exactly
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host.len() > domain.len() && host.ends_with(&format!(".{}", domain)),
+        None => host == pattern,
+    }
+}
+
+/// Lowercased host of an http(s) or ws(s) URL
+pub fn url_host(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    if !matches!(scheme.to_lowercase().as_str(), "http" | "https" | "ws" | "wss") {
+        return None;
+    }
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host_port = authority.rsplit_once('@').map(|(_, h)| h).unwrap_or(authority);
+    let host = if let Some(stripped) = host_port.strip_prefix('[') {
+        // IPv6 literal
+        stripped.split(']').next().unwrap_or_default()
+    } else {
+        host_port.split(':').next().unwrap_or_default()
+    };
+    (!host.is_empty()).then(|| host.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_host() {
+        assert_eq!(url_host("https://Docs.rs/serde"), Some("docs.rs".to_string()));
+        assert_eq!(url_host("http://user:pw@app.mycorp.com:8080/x"), Some("app.mycorp.com".to_string()));
+        assert_eq!(url_host("http://[::1]:3000/"), Some("::1".to_string()));
+        assert_eq!(url_host("about:blank"), None);
+        assert_eq!(url_host("file:///tmp/a.html"), None);
+    }
+
+    #[test]
+    fn test_host_matches() {
+        assert!(host_matches("*.mycorp.com", "app.mycorp.com"));
+        assert!(host_matches("*.mycorp.com", "a.b.mycorp.com"));
+        assert!(!host_matches("*.mycorp.com", "mycorp.com"));
+        assert!(!host_matches("*.mycorp.com", "evilmycorp.com"));
+        assert!(host_matches("docs.rs", "docs.rs"));
+        assert!(!host_matches("docs.rs", "sub.docs.rs"));
+    }
+
+    #[test]
+    fn test_allow_list() {
+        let rules = HostRules::from_flags(Some("*.mycorp.com, docs.rs"), None).unwrap();
+        assert!(rules.check_url("https://wiki.mycorp.com/page").is_ok());
+        assert!(rules.check_url("https://docs.rs").is_ok());
+        assert!(rules.check_url("about:blank").is_ok());
+        let err = rules.check_url("https://example.com/").unwrap_err();
+        assert_eq!(err.host, "example.com");
+    }
+
+    #[test]
+    fn test_block_list_wins() {
+        let rules =
+            HostRules::from_flags(Some("*.mycorp.com"), Some("payroll.mycorp.com")).unwrap();
+        assert!(rules.check_url("https://payroll.mycorp.com/").is_err());
+        assert!(rules.check_url("https://wiki.mycorp.com/").is_ok());
+    }
+
+    #[test]
+    fn test_host_mapping() {
+        assert_eq!(
+            parse_host_mapping("Staging.Example.com=127.0.0.1:8443"),
+            Ok(("staging.example.com".to_string(), "127.0.0.1:8443".to_string()))
+        );
+        assert_eq!(parse_host_mapping("*.api.test=[::1]:9000").unwrap().1, "[::1]:9000");
+        assert!(parse_host_mapping("example.com").is_err());
+        assert!(parse_host_mapping("example.com=10.0.0.1:0").is_err());
+        assert!(parse_host_mapping("a.com,b.com=10.0.0.1").is_err());
+        assert!(parse_host_mapping("example.com=10.0.0.1 foo").is_err());
+
+        let rules = vec!["a.test=127.0.0.1".to_string(), "b.test=10.0.0.2:8080".to_string()];
+        assert_eq!(
+            resolver_rules_switch(&rules).unwrap().as_deref(),
+            Some("--host-resolver-rules=MAP a.test 127.0.0.1,MAP b.test 10.0.0.2:8080")
+        );
+        assert_eq!(resolver_rules_switch(&[]), Ok(None));
+    }
+
+    #[test]
+    fn test_dns_options() {
+        assert_eq!(dns_options(None, None), Ok(None));
+        assert_eq!(dns_options(Some("IPv4"), None), Ok(Some(json!({ "family": 4 }))));
+        assert_eq!(
+            dns_options(Some("6"), Some("https://dns.example/dns-query")),
+            Ok(Some(json!({ "family": 6, "dohUrl": "https://dns.example/dns-query" })))
+        );
+        assert!(dns_options(Some("5"), None).is_err());
+        assert!(dns_options(None, Some("http://dns.example/dns-query")).is_err());
+    }
+
+    #[test]
+    fn test_invalid_patterns() {
+        assert!(parse_host_list("https://example.com").is_err());
+        assert!(parse_host_list("*.").is_err());
+        assert!(parse_host_list("a.*.com").is_err());
+        assert_eq!(parse_host_list(" A.com ,,").unwrap(), vec!["a.com"]);
+    }
+}
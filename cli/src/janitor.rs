@@ -0,0 +1,42 @@
+//! Rules for the daemon's janitor.
+//!
+//! With --janitor the daemon sweeps the session every 30 seconds and closes
+//! what the rules call garbage once it has sat unused for a minute: `blank`
+//! about:blank tabs, `popups` the page opened that are missing from the tab
+//! list or whose opener closed, and `contexts` from `window new` with no pages
+//! left. The active tab and the main context are never closed. `janitor`
+//! shows what was closed; `janitor run` sweeps immediately.
+
+pub const RULES: &[&str] = &["blank", "popups", "contexts"];
+
+/// Comma separated rules, or "all"
+pub fn parse_rules(value: &str) -> Result<Vec<&'static str>, String> {
+    let mut rules = Vec::new();
+    for name in value.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        if name == "all" {
+            return Ok(RULES.to_vec());
+        }
+        match RULES.iter().find(|rule| **rule == name) {
+            Some(rule) if !rules.contains(rule) => rules.push(*rule),
+            Some(_) => {}
+            None => return Err(format!("Unknown janitor rule '{}': use {} or all", name, RULES.join(", "))),
+        }
+    }
+    if rules.is_empty() {
+        return Err(format!("No janitor rules given: use {} or all", RULES.join(", ")));
+    }
+    Ok(rules)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rules() {
+        assert_eq!(parse_rules("all"), Ok(vec!["blank", "popups", "contexts"]));
+        assert_eq!(parse_rules("popups, blank,popups"), Ok(vec!["popups", "blank"]));
+        assert!(parse_rules("tabs").unwrap_err().contains("Unknown janitor rule 'tabs'"));
+        assert!(parse_rules(" , ").is_err());
+    }
+}
@@ -0,0 +1,391 @@
+//! Background jobs for long-running commands.
+//!
+//! `--detach` starts the command in a detached copy of the CLI and prints a
+//! job id right away, so the invoking process doesn't have to stay alive for
+//! the whole run. Each job gets a directory under ~/.agent-browser/jobs (or
+//! AGENT_BROWSER_JOBS_DIR) holding `job.json` and the command's combined
+//! output in `output.log`.
+//!
+//! The detached process is a supervisor: it runs the command as its own child
+//! with output redirected to the log, waits for it and records the exit code.
+//! `job cancel` marks the job cancelled and terminates that child.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::connection::app_data_dir;
+
+/// Set in the detached supervisor's environment to the job it runs
+pub const RUN_ENV: &str = "AGENT_BROWSER_JOB_RUN";
+
+/// Finished jobs kept before the oldest are removed
+const MAX_FINISHED_JOBS: usize = 100;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl Status {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Status::Running => "running",
+            Status::Completed => "completed",
+            Status::Failed => "failed",
+            Status::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: String,
+    pub session: String,
+    /// CLI arguments the job runs, without --detach
+    pub args: Vec<String>,
+    pub status: Status,
+    /// Supervisor process
+    pub pid: u32,
+    /// Process running the command, once started
+    pub child_pid: Option<u32>,
+    pub exit_code: Option<i32>,
+    /// Unix time in milliseconds
+    pub started: u64,
+    pub finished: Option<u64>,
+}
+
+/// Directory holding all jobs
+pub fn jobs_dir() -> PathBuf {
+    match env::var("AGENT_BROWSER_JOBS_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => app_data_dir().join("jobs"),
+    }
+}
+
+pub fn log_path(id: &str) -> PathBuf {
+    jobs_dir().join(id).join("output.log")
+}
+
+fn job_path(id: &str) -> PathBuf {
+    jobs_dir().join(id).join("job.json")
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Job ids become directory names; ids given on the command line are checked
+/// so they can't point outside the jobs directory
+pub fn validate_id(id: &str) -> Result<(), String> {
+    if !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        Ok(())
+    } else {
+        Err(format!("Invalid job id '{}'", id))
+    }
+}
+
+/// Arguments for the detached run: everything except --detach
+pub fn detached_args(args: &[String], flag_end: usize) -> Vec<String> {
+    args.iter()
+        .enumerate()
+        .filter(|(i, arg)| *i >= flag_end || arg.as_str() != "--detach")
+        .map(|(_, arg)| arg.clone())
+        .collect()
+}
+
+/// The command a job runs, for listings (`open example.com`)
+pub fn command_line(args: &[String]) -> String {
+    crate::flags::clean_args(args).join(" ")
+}
+
+/// Whether `id` names a job started with --detach rather than one queued in
+/// the daemon with --no-wait
+pub fn exists(id: &str) -> bool {
+    validate_id(id).is_ok() && job_path(id).exists()
+}
+
+impl Job {
+    pub fn load(id: &str) -> Result<Self, String> {
+        validate_id(id)?;
+        let source = fs::read_to_string(job_path(id)).map_err(|_| format!("Unknown job: {}", id))?;
+        let mut job: Job =
+            serde_json::from_str(&source).map_err(|e| format!("Job {} is unreadable: {}", id, e))?;
+        // A supervisor that died without recording an exit code failed (pid 0
+        // means it hasn't recorded itself yet)
+        if job.status == Status::Running && job.pid != 0 && !process_alive(job.pid) {
+            job.status = Status::Failed;
+        }
+        Ok(job)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = job_path(&self.id);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+        }
+        let source = serde_json::to_string_pretty(self).unwrap_or_default();
+        fs::write(&path, source).map_err(|e| format!("Cannot write job {}: {}", self.id, e))
+    }
+
+    pub fn output(&self) -> String {
+        fs::read_to_string(log_path(&self.id)).unwrap_or_default()
+    }
+
+    /// The command's JSON response when it ran with --json
+    pub fn result(&self) -> Option<Value> {
+        let output = self.output();
+        let last = output.lines().rev().find(|l| !l.trim().is_empty())?;
+        serde_json::from_str(last).ok()
+    }
+}
+
+/// All jobs, oldest first
+pub fn list() -> Vec<Job> {
+    let mut jobs: Vec<Job> = fs::read_dir(jobs_dir())
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| Job::load(&entry.file_name().to_string_lossy()).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    jobs.sort_by(|a, b| a.started.cmp(&b.started).then_with(|| a.id.cmp(&b.id)));
+    jobs
+}
+
+fn new_id() -> String {
+    let millis = now_millis();
+    let id = format!("job-{}", millis);
+    if jobs_dir().join(&id).exists() {
+        format!("job-{}-{}", millis, std::process::id())
+    } else {
+        id
+    }
+}
+
+fn prune() {
+    let finished: Vec<Job> = list().into_iter().filter(|j| j.status != Status::Running).collect();
+    let excess = finished.len().saturating_sub(MAX_FINISHED_JOBS);
+    for job in &finished[..excess] {
+        let _ = fs::remove_dir_all(jobs_dir().join(&job.id));
+    }
+}
+
+/// Start `args` as a background job in a detached supervisor
+pub fn spawn(session: &str, args: Vec<String>) -> Result<Job, String> {
+    prune();
+    let id = new_id();
+    let exe = env::current_exe().map_err(|e| format!("Cannot locate agent-browser: {}", e))?;
+    let mut job = Job {
+        id: id.clone(),
+        session: session.to_string(),
+        args,
+        status: Status::Running,
+        pid: 0,
+        child_pid: None,
+        exit_code: None,
+        started: now_millis(),
+        finished: None,
+    };
+    job.save()?;
+
+    // The supervisor records its own pid; saving here could race with it
+    let mut cmd = Command::new(exe);
+    cmd.args(&job.args).env(RUN_ENV, &id);
+    detach(&mut cmd);
+    let spawned = cmd.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).spawn();
+    match spawned {
+        Ok(child) => {
+            job.pid = child.id();
+            Ok(job)
+        }
+        Err(e) => {
+            finish(&mut job, Status::Failed, None);
+            Err(format!("Failed to start job: {}", e))
+        }
+    }
+}
+
+/// Run job `id` in this (detached) process: start the command with its
+/// output going to the job's log, wait for it and record how it ended
+pub fn supervise(id: &str, args: &[String]) -> i32 {
+    let Ok(mut job) = Job::load(id) else {
+        return 1;
+    };
+    job.pid = std::process::id();
+    let mut child = match start(id, args) {
+        Ok(child) => child,
+        Err(msg) => {
+            let _ = fs::write(log_path(id), format!("{}\n", msg));
+            finish(&mut job, Status::Failed, None);
+            return 1;
+        }
+    };
+    job.child_pid = Some(child.id());
+    let _ = job.save();
+
+    let code = child.wait().ok().and_then(|status| status.code());
+    // `job cancel` records the cancellation before terminating the child
+    let cancelled = Job::load(id).map(|j| j.status == Status::Cancelled).unwrap_or(false);
+    let status = match code {
+        _ if cancelled => Status::Cancelled,
+        Some(0) => Status::Completed,
+        _ => Status::Failed,
+    };
+    finish(&mut job, status, code);
+    code.unwrap_or(1)
+}
+
+fn start(id: &str, args: &[String]) -> Result<Child, String> {
+    let log = fs::File::create(log_path(id)).map_err(|e| format!("Cannot create job log: {}", e))?;
+    let stderr = log.try_clone().map_err(|e| format!("Cannot create job log: {}", e))?;
+    let exe = env::current_exe().map_err(|e| format!("Cannot locate agent-browser: {}", e))?;
+    Command::new(exe)
+        .args(args)
+        .env_remove(RUN_ENV)
+        .stdin(Stdio::null())
+        .stdout(log)
+        .stderr(stderr)
+        .spawn()
+        .map_err(|e| format!("Failed to start job: {}", e))
+}
+
+fn finish(job: &mut Job, status: Status, exit_code: Option<i32>) {
+    job.status = status;
+    job.exit_code = exit_code;
+    job.finished = Some(now_millis());
+    let _ = job.save();
+}
+
+/// Cancel a running job: mark it cancelled, then terminate its command
+pub fn cancel(id: &str) -> Result<Job, String> {
+    let mut job = Job::load(id)?;
+    if job.status != Status::Running {
+        return Err(format!("Job {} is already {}", id, job.status.name()));
+    }
+    job.status = Status::Cancelled;
+    job.save()?;
+    match job.child_pid {
+        Some(pid) => terminate(pid),
+        None => terminate(job.pid),
+    }
+    Ok(job)
+}
+
+#[cfg(unix)]
+fn detach(cmd: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    unsafe {
+        cmd.pre_exec(|| {
+            // New session, so the job outlives the terminal that started it
+            libc::setsid();
+            Ok(())
+        });
+    }
+}
+
+#[cfg(windows)]
+fn detach(cmd: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+    const DETACHED_PROCESS: u32 = 0x00000008;
+    cmd.creation_flags(CREATE_NEW_PROCESS_GROUP | DETACHED_PROCESS);
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    pid != 0 && unsafe { libc::kill(pid as i32, 0) == 0 }
+}
+
+#[cfg(windows)]
+fn process_alive(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::{CloseHandle, STILL_ACTIVE};
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle == 0 {
+            return false;
+        }
+        let mut code = 0u32;
+        let alive = GetExitCodeProcess(handle, &mut code) != 0 && code == STILL_ACTIVE as u32;
+        CloseHandle(handle);
+        alive
+    }
+}
+
+#[cfg(unix)]
+fn terminate(pid: u32) {
+    unsafe {
+        libc::kill(pid as i32, libc::SIGTERM);
+    }
+}
+
+#[cfg(windows)]
+fn terminate(pid: u32) {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{OpenProcess, TerminateProcess, PROCESS_TERMINATE};
+    unsafe {
+        let handle = OpenProcess(PROCESS_TERMINATE, 0, pid);
+        if handle != 0 {
+            TerminateProcess(handle, 1);
+            CloseHandle(handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_detached_args() {
+        let all = args(&["--detach", "--session", "ci", "eval", "--", "--detach"]);
+        assert_eq!(detached_args(&all, 4), args(&["--session", "ci", "eval", "--", "--detach"]));
+        assert_eq!(command_line(&args(&["--json", "record", "start", "a.webm"])), "record start a.webm");
+    }
+
+    #[test]
+    fn test_validate_id() {
+        assert!(validate_id("job-1717171717171").is_ok());
+        assert!(validate_id("../secrets").is_err());
+        assert!(validate_id("").is_err());
+    }
+
+    #[test]
+    fn test_job_json_shape() {
+        let job = Job {
+            id: "job-1".to_string(),
+            session: "default".to_string(),
+            args: args(&["open", "example.com"]),
+            status: Status::Completed,
+            pid: 42,
+            child_pid: Some(43),
+            exit_code: Some(0),
+            started: 1,
+            finished: Some(2),
+        };
+        let value = serde_json::to_value(&job).unwrap();
+        assert_eq!(value["status"], "completed");
+        assert_eq!(value["childPid"], 43);
+        assert_eq!(serde_json::from_value::<Job>(value).unwrap(), job);
+    }
+}
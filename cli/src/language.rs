@@ -0,0 +1,254 @@
+//! Language detection and translation for `text --detect-lang` and
+//! `text --translate-to <lang>`.
+//!
+//! Detection runs locally: the script a text is written in settles most
+//! languages, and Latin-script ones are told apart by their commonest words.
+//! Translation goes to a backend set with AGENT_BROWSER_TRANSLATOR or
+//! `translator` in config.json:
+//!
+//! ```json
+//! { "translator": { "command": "trans -b -t \"$AGENT_BROWSER_TRANSLATE_TO\"" } }
+//! { "translator": { "url": "https://translate.example.com/translate" } }
+//! ```
+//!
+//! A command reads the text on stdin and prints the translation, with the
+//! languages in AGENT_BROWSER_TRANSLATE_FROM (`auto` when unknown) and
+//! AGENT_BROWSER_TRANSLATE_TO. A URL is POSTed LibreTranslate's request,
+//! `{"q", "source", "target", "format": "text"}`, and answers with
+//! `{"translatedText"}` (or `{"text"}`). The environment variable takes a URL
+//! or a command.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::env;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Seconds curl may spend on one translation
+const TIMEOUT_SECS: &str = "60";
+
+/// Languages told by their script: code points of the script's letters.
+/// Japanese mixes kana into its kanji, so it's checked before Chinese.
+const SCRIPTS: &[(&str, &[(char, char)])] = &[
+    ("ja", &[('\u{3040}', '\u{30ff}')]),
+    ("ko", &[('\u{ac00}', '\u{d7af}'), ('\u{1100}', '\u{11ff}')]),
+    ("zh", &[('\u{4e00}', '\u{9fff}')]),
+    ("ar", &[('\u{0600}', '\u{06ff}')]),
+    ("he", &[('\u{0590}', '\u{05ff}')]),
+    ("el", &[('\u{0370}', '\u{03ff}')]),
+    ("th", &[('\u{0e00}', '\u{0e7f}')]),
+    ("hi", &[('\u{0900}', '\u{097f}')]),
+    ("ru", &[('\u{0400}', '\u{04ff}')]),
+];
+
+/// Common words of Latin-script languages; the language whose words make up
+/// most of a text wins
+const STOPWORDS: &[(&str, &[&str])] = &[
+    ("en", &["the", "and", "of", "to", "is", "in", "that", "it", "for", "with", "was", "on", "are", "this", "you"]),
+    ("de", &["der", "die", "und", "das", "ist", "nicht", "mit", "den", "ein", "eine", "zu", "von", "auf", "sich", "ich"]),
+    ("fr", &["le", "la", "les", "et", "des", "est", "une", "pour", "dans", "que", "pas", "qui", "sur", "du", "avec"]),
+    ("es", &["el", "los", "las", "y", "es", "una", "para", "con", "que", "por", "del", "se", "como", "pero", "muy"]),
+    ("it", &["il", "di", "che", "e", "la", "per", "una", "sono", "non", "gli", "della", "con", "anche", "questo", "nel"]),
+    ("pt", &["o", "os", "as", "de", "que", "não", "uma", "para", "com", "do", "da", "em", "são", "mais", "muito"]),
+    ("nl", &["de", "het", "een", "en", "van", "is", "niet", "dat", "op", "te", "zijn", "voor", "met", "ook", "maar"]),
+    ("sv", &["och", "att", "det", "som", "en", "är", "på", "för", "med", "inte", "har", "av", "till", "jag", "den"]),
+    ("pl", &["i", "w", "nie", "na", "się", "jest", "że", "do", "to", "z", "jak", "dla", "ale", "są", "od"]),
+    ("tr", &["ve", "bir", "bu", "için", "ile", "da", "de", "çok", "ne", "olarak", "daha", "gibi", "ama", "olan", "var"]),
+];
+
+/// The language of a text as an ISO 639-1 code, with a confidence from 0 to 1,
+/// or None when there's too little text to tell
+pub fn detect(text: &str) -> Option<(&'static str, f64)> {
+    let letters: Vec<char> = text.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.len() < 10 {
+        return None;
+    }
+    for &(lang, ranges) in SCRIPTS {
+        let in_script = letters.iter().filter(|c| ranges.iter().any(|&(lo, hi)| (lo..=hi).contains(*c))).count();
+        let found = in_script as f64 / letters.len() as f64;
+        let enough = if lang == "ja" { 0.1 } else { 0.3 };
+        if found >= enough {
+            // Ukrainian letters that Russian lacks
+            if lang == "ru" && text.chars().any(|c| "іїєґІЇЄҐ".contains(c)) {
+                return Some(("uk", round(found)));
+            }
+            return Some((lang, round(found.max(0.5))));
+        }
+    }
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    let mut counts: Vec<(&str, usize)> = STOPWORDS
+        .iter()
+        .map(|(lang, common)| (*lang, words.iter().filter(|w| common.contains(&w.as_str())).count()))
+        .collect();
+    counts.sort_by_key(|(_, hits)| std::cmp::Reverse(*hits));
+    let (best, hits) = counts[0];
+    if hits == 0 {
+        return None;
+    }
+    // Confidence: how far the winner leads the runner-up
+    let runner_up = counts[1].1;
+    Some((best, round(1.0 - runner_up as f64 / hits as f64).max(0.05)))
+}
+
+fn round(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// A target language for --translate-to: `en`, `pt-BR`, `zh-Hant`...
+pub fn valid_lang(lang: &str) -> bool {
+    let mut parts = lang.split('-');
+    let base = parts.next().unwrap_or("");
+    (2..=3).contains(&base.len())
+        && base.chars().all(|c| c.is_ascii_alphabetic())
+        && parts.all(|p| (2..=8).contains(&p.len()) && p.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Translator {
+    /// Shell command reading the text on stdin, run with `sh -c` (`cmd /C` on Windows)
+    pub command: Option<String>,
+    /// LibreTranslate-compatible endpoint
+    pub url: Option<String>,
+}
+
+impl Translator {
+    /// The backend from AGENT_BROWSER_TRANSLATOR, else from the config file
+    pub fn configured(config: Option<Translator>) -> Option<Translator> {
+        match env::var("AGENT_BROWSER_TRANSLATOR").ok().filter(|v| !v.is_empty()) {
+            Some(value) if value.starts_with("http://") || value.starts_with("https://") => {
+                Some(Translator { command: None, url: Some(value) })
+            }
+            Some(value) => Some(Translator { command: Some(value), url: None }),
+            None => config.filter(|t| t.command.is_some() || t.url.is_some()),
+        }
+    }
+
+    pub fn translate(&self, text: &str, from: Option<&str>, to: &str) -> Result<String, String> {
+        let from = from.unwrap_or("auto");
+        if let Some(url) = &self.url {
+            let body = json!({ "q": text, "source": from, "target": to, "format": "text" });
+            let output = run_with_stdin(
+                Command::new("curl").args([
+                    "-sS",
+                    "--fail-with-body",
+                    "--max-time",
+                    TIMEOUT_SECS,
+                    "-H",
+                    "Content-Type: application/json",
+                    "--data-binary",
+                    "@-",
+                    url,
+                ]),
+                &body.to_string(),
+            )
+            .map_err(|e| format!("Translation request to {} failed: {}", url, e))?;
+            return parse_response(&output).ok_or_else(|| {
+                format!("Translation backend {} answered without translatedText: {}", url, output.trim())
+            });
+        }
+        let script = self.command.as_deref().ok_or("No translation backend configured")?;
+        let mut cmd = if cfg!(windows) {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", script]);
+            cmd
+        } else {
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", script]);
+            cmd
+        };
+        cmd.env("AGENT_BROWSER_TRANSLATE_FROM", from).env("AGENT_BROWSER_TRANSLATE_TO", to);
+        let output =
+            run_with_stdin(&mut cmd, text).map_err(|e| format!("Translation command '{}' failed: {}", script, e))?;
+        Ok(output.trim_end().to_string())
+    }
+}
+
+fn run_with_stdin(cmd: &mut Command, input: &str) -> Result<String, String> {
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // A backend that exits without reading is reported by its status below
+        let _ = stdin.write_all(input.as_bytes());
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = [stderr.trim(), stdout.trim()].into_iter().find(|s| !s.is_empty()).unwrap_or("no output");
+        return Err(format!("{} ({})", reason, output.status));
+    }
+    Ok(stdout)
+}
+
+/// The translation in a LibreTranslate-style answer
+fn parse_response(body: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(body).ok()?;
+    value
+        .get("translatedText")
+        .or_else(|| value.get("text"))
+        .and_then(|v| v.as_str())
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect("The quick brown fox jumps over the lazy dog and the cat").unwrap().0, "en");
+        assert_eq!(detect("Der schnelle braune Fuchs springt über den faulen Hund und die Katze").unwrap().0, "de");
+        assert_eq!(detect("Le renard brun rapide saute par-dessus le chien paresseux et les chats").unwrap().0, "fr");
+        assert_eq!(detect("El zorro marrón salta sobre el perro perezoso y los gatos").unwrap().0, "es");
+        assert_eq!(detect("Быстрая коричневая лиса прыгает через ленивую собаку").unwrap().0, "ru");
+        assert_eq!(detect("Швидка руда лисиця перестрибує через лінивого пса").unwrap().0, "uk");
+        assert_eq!(detect("敏捷的棕色狐狸跳过了懒狗，然后继续奔跑").unwrap().0, "zh");
+        assert_eq!(detect("素早い茶色の狐はのろまな犬を飛び越えました").unwrap().0, "ja");
+        assert_eq!(detect("빠른 갈색 여우가 게으른 개를 뛰어넘었습니다").unwrap().0, "ko");
+        assert!(detect("OK").is_none());
+        assert!(detect("12345 67890 !!!").is_none());
+    }
+
+    #[test]
+    fn test_valid_lang() {
+        assert!(valid_lang("en"));
+        assert!(valid_lang("pt-BR"));
+        assert!(valid_lang("zh-Hant"));
+        assert!(!valid_lang("e"));
+        assert!(!valid_lang("english"));
+        assert!(!valid_lang("en-"));
+        assert!(!valid_lang("../en"));
+    }
+
+    #[test]
+    fn test_parse_response() {
+        assert_eq!(parse_response(r#"{"translatedText":"Hello"}"#).unwrap(), "Hello");
+        assert_eq!(parse_response(r#"{"text":"Hello"}"#).unwrap(), "Hello");
+        assert!(parse_response(r#"{"error":"Unsupported"}"#).is_none());
+        assert!(parse_response("<html>").is_none());
+    }
+
+    #[test]
+    fn test_translate_command() {
+        if cfg!(windows) {
+            return;
+        }
+        let translator = Translator {
+            command: Some("printf '%s:%s:' \"$AGENT_BROWSER_TRANSLATE_FROM\" \"$AGENT_BROWSER_TRANSLATE_TO\"; cat".to_string()),
+            url: None,
+        };
+        assert_eq!(translator.translate("Hallo\n", Some("de"), "en").unwrap(), "de:en:Hallo");
+        assert_eq!(translator.translate("x", None, "en").unwrap(), "auto:en:x");
+        let failing = Translator { command: Some("echo no key >&2; exit 3".to_string()), url: None };
+        assert!(failing.translate("x", None, "en").unwrap_err().contains("no key"));
+    }
+}
@@ -1,29 +1,85 @@
+mod artifacts;
+mod audit;
+mod blocking;
+mod browser_pool;
+mod budget;
+mod channels;
+mod cdp;
+mod checkpoint;
 mod color;
 mod commands;
+mod completions;
+mod config;
+mod csv;
+mod container;
 mod connection;
+mod diagnostics;
 mod flags;
+mod framing;
+mod handoff;
+mod history;
+mod hooks;
+mod hosts;
+mod inbox;
 mod install;
+mod janitor;
+mod jobs;
+mod language;
+mod memo;
+mod notify;
+mod oauth;
+mod observe;
+mod ocr;
 mod output;
+mod pdf;
+mod policy;
+mod proxy_pool;
+mod registry;
+mod rotation;
+mod schema;
+mod script;
+mod simhash;
+mod secrets;
+mod self_update;
+mod state_gc;
+mod tokens;
+mod session_lock;
+mod sessions;
+mod transcript;
+mod transfers;
+mod upload;
 mod validation;
+mod warm;
+mod watch;
+mod workspace;
 
 use serde_json::json;
 use std::env;
 use std::fs;
 use std::process::exit;
-
-#[cfg(unix)]
-use libc;
+use std::time::Instant;
 
 #[cfg(windows)]
 use windows_sys::Win32::Foundation::CloseHandle;
 #[cfg(windows)]
 use windows_sys::Win32::System::Threading::{OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
 
-use commands::{gen_id, parse_command, ParseError};
-use connection::{ensure_daemon, get_socket_dir, send_command};
-use flags::{clean_args, parse_flags};
+use commands::{command_selectors, gen_id, parse_command, ParseError};
+use connection::{
+    ensure_daemon, get_socket_dir, is_daemon_running, is_ephemeral_daemon, is_read_only_daemon, send_command,
+    send_streaming, Response,
+};
+use flags::{clean_args, flag_end, global_args, parse_flags, Flags};
 use install::run_install;
-use output::{print_command_help, print_help, print_response, print_version};
+use output::{
+    json_response, print_command_help, print_help, print_help_json, print_response, print_version,
+    OutputFormat, ResponseMeta,
+};
+use sessions::SessionMeta;
+use validation::{
+    decode_session_name, encode_session_name, is_valid_session_name, is_valid_unicode_session_name,
+    session_name_error, validate_extra_ca,
+};
 
 fn parse_proxy(proxy_str: &str) -> serde_json::Value {
     let Some(protocol_end) = proxy_str.find("://") else {
@@ -55,122 +111,2511 @@ fn parse_proxy(proxy_str: &str) -> serde_json::Value {
     })
 }
 
-fn run_session(args: &[String], session: &str, json_mode: bool) {
-    let subcommand = args.get(1).map(|s| s.as_str());
+/// Names of sessions whose daemon process is still running
+fn list_active_sessions() -> Vec<String> {
+    let socket_dir = get_socket_dir();
+    let mut sessions: Vec<String> = Vec::new();
 
-    match subcommand {
-        Some("list") => {
-            let socket_dir = get_socket_dir();
-            let mut sessions: Vec<String> = Vec::new();
-
-            if let Ok(entries) = fs::read_dir(&socket_dir) {
-                for entry in entries.flatten() {
-                    let name = entry.file_name().to_string_lossy().to_string();
-                    // Look for pid files in socket directory
-                    if name.ends_with(".pid") {
-                        let session_name = name.strip_suffix(".pid").unwrap_or("");
-                        if !session_name.is_empty() {
-                            // Check if session is actually running
-                            let pid_path = socket_dir.join(&name);
-                            if let Ok(pid_str) = fs::read_to_string(&pid_path) {
-                                if let Ok(pid) = pid_str.trim().parse::<u32>() {
-                                    #[cfg(unix)]
-                                    let running = unsafe { libc::kill(pid as i32, 0) == 0 };
-                                    #[cfg(windows)]
-                                    let running = unsafe {
-                                        let handle =
-                                            OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
-                                        if handle != 0 {
-                                            CloseHandle(handle);
-                                            true
-                                        } else {
-                                            false
-                                        }
-                                    };
-                                    if running {
-                                        sessions.push(session_name.to_string());
-                                    }
+    if let Ok(entries) = fs::read_dir(&socket_dir) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // Look for pid files in socket directory
+            if name.ends_with(".pid") {
+                let session_name = name.strip_suffix(".pid").unwrap_or("");
+                if !session_name.is_empty() && !warm::is_warm(session_name) {
+                    // Check if session is actually running
+                    let pid_path = socket_dir.join(&name);
+                    if let Ok(pid_str) = fs::read_to_string(&pid_path) {
+                        if let Ok(pid) = pid_str.trim().parse::<u32>() {
+                            #[cfg(unix)]
+                            let running = unsafe { libc::kill(pid as i32, 0) == 0 };
+                            #[cfg(windows)]
+                            let running = unsafe {
+                                let handle =
+                                    OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+                                if handle != 0 {
+                                    CloseHandle(handle);
+                                    true
+                                } else {
+                                    false
                                 }
+                            };
+                            if running {
+                                sessions.push(session_name.to_string());
                             }
                         }
                     }
                 }
             }
+        }
+    }
+    sessions
+}
 
-            if json_mode {
-                println!(
-                    r#"{{"success":true,"data":{{"sessions":{}}}}}"#,
-                    serde_json::to_string(&sessions).unwrap_or_default()
-                );
+/// Stable codes for the error types scripts match on across releases
+fn error_code(error_type: &str) -> Option<&'static str> {
+    match error_type {
+        "session_busy" => Some("E_SESSION_BUSY"),
+        _ => None,
+    }
+}
+
+/// Report an error the way --json asks for, with its `type` (and `code`) when
+/// callers can branch on it, and exit 1
+fn fail(flags: &Flags, msg: impl std::fmt::Display, error_type: Option<&str>) -> ! {
+    if flags.json {
+        let mut output = json!({ "success": false, "error": msg.to_string() });
+        if let Some(error_type) = error_type {
+            output["type"] = json!(error_type);
+            if let Some(code) = error_code(error_type) {
+                output["code"] = json!(code);
+            }
+        }
+        println!("{}", output);
+    } else {
+        eprintln!("{} {}", color::error_indicator(), msg);
+    }
+    exit(1);
+}
+
+/// Take the session's lock for the rest of this invocation, waiting up to
+/// --wait-lock; fails with E_SESSION_BUSY when another invocation keeps it
+fn lock_session(flags: &Flags, words: &[String]) -> session_lock::SessionLock {
+    let wait = flags.wait_lock.as_deref().map(|ms| {
+        ms.parse::<u64>()
+            .map(std::time::Duration::from_millis)
+            .unwrap_or_else(|_| fail(flags, format!("Invalid --wait-lock '{}': use milliseconds, e.g. 5000", ms), None))
+    });
+    let mut command = words.join(" ");
+    if command.chars().count() > 80 {
+        command = format!("{}...", command.chars().take(77).collect::<String>());
+    }
+    session_lock::acquire(&flags.session, &command, wait).unwrap_or_else(|busy| {
+        let name = decode_session_name(&flags.session);
+        fail(flags, busy.message(&name, session_lock::now_millis()), Some("session_busy"))
+    })
+}
+
+fn run_session(args: &[String], flags: &Flags) {
+    let subcommand = args.get(1).map(|s| s.as_str());
+    // Session arguments are given by name; files use the encoded form
+    let target = args.get(2).map(|name| encode_session_name(name));
+
+    match subcommand {
+        Some("list") => {
+            let sessions = list_active_sessions();
+
+            if flags.json {
+                let names: Vec<String> = sessions.iter().map(|s| decode_session_name(s)).collect();
+                let details: Vec<_> = sessions
+                    .iter()
+                    .zip(&names)
+                    .map(|(id, name)| {
+                        let meta = SessionMeta::load(id);
+                        json!({ "name": name, "tags": meta.tags, "metadata": meta.metadata })
+                    })
+                    .collect();
+                let data = json!({ "sessions": names, "details": details });
+                println!("{}", json!({ "success": true, "data": data }));
             } else if sessions.is_empty() {
                 println!("No active sessions");
             } else {
                 println!("Active sessions:");
                 for s in &sessions {
-                    let marker = if s == session { color::cyan("→") } else { " ".to_string() };
-                    println!("{} {}", marker, s);
+                    let marker = if *s == flags.session { color::cyan("→") } else { " ".to_string() };
+                    let summary = SessionMeta::load(s).summary();
+                    let name = decode_session_name(s);
+                    if summary.is_empty() {
+                        println!("{} {}", marker, name);
+                    } else {
+                        println!("{} {} {}", marker, name, color::dim(&summary));
+                    }
+                }
+            }
+        }
+        Some("tag") | Some("untag") => {
+            let usage = format!(
+                "Usage: agent-browser session {} <session> <key=value|tag>...",
+                subcommand.unwrap_or_default()
+            );
+            let (Some(target), Some(name), Some(_)) = (target, args.get(2), args.get(3)) else {
+                fail(flags, usage, None);
+            };
+            let mut meta = SessionMeta::load(&target);
+            if subcommand == Some("tag") {
+                meta.add(&args[3..]).unwrap_or_else(|msg| fail(flags, msg, None));
+            } else {
+                meta.remove(&args[3..]);
+            }
+            meta.save(&target).unwrap_or_else(|msg| fail(flags, msg, None));
+            if flags.json {
+                let data = json!({ "session": name, "tags": meta.tags, "metadata": meta.metadata });
+                println!("{}", json!({ "success": true, "data": data }));
+            } else {
+                println!("{} {} {}", color::success_indicator(), name, color::dim(&meta.summary()));
+            }
+        }
+        Some("locks") => {
+            let locks = session_lock::list();
+            let now = session_lock::now_millis();
+            if flags.json {
+                let locks: Vec<_> = locks
+                    .iter()
+                    .map(|lock| json!({ "session": decode_session_name(&lock.session), "holder": lock.holder }))
+                    .collect();
+                println!("{}", json!({ "success": true, "data": { "locks": locks } }));
+            } else if locks.is_empty() {
+                println!("No sessions are locked");
+            } else {
+                for lock in &locks {
+                    let name = decode_session_name(&lock.session);
+                    match &lock.holder {
+                        Some(h) => println!(
+                            "{} {} {}",
+                            color::bold(&name),
+                            h.command,
+                            color::dim(&format!("(pid {}, {})", h.pid, session_lock::format_age(now.saturating_sub(h.since))))
+                        ),
+                        None => println!("{} {}", color::bold(&name), color::dim("(held)")),
+                    }
+                }
+            }
+        }
+        Some("templates") => {
+            let config = config::load_config().unwrap_or_else(|msg| fail(flags, msg, None));
+            let names: Vec<&String> = config.session_templates.keys().collect();
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": { "templates": names } }));
+            } else if names.is_empty() {
+                println!("No session templates in {}", config::config_path().display());
+            } else {
+                for name in names {
+                    println!("{}", name);
+                }
+            }
+        }
+        Some("describe") => {
+            let target = target.as_deref().unwrap_or(&flags.session);
+            let name = decode_session_name(target);
+            let meta = SessionMeta::load(target);
+            let running = list_active_sessions().iter().any(|s| s == target);
+            if flags.json {
+                let data = json!({
+                    "session": name,
+                    "running": running,
+                    "tags": meta.tags,
+                    "metadata": meta.metadata,
+                });
+                println!("{}", json!({ "success": true, "data": data }));
+            } else {
+                let state = if running { "running" } else { "not running" };
+                println!("{} ({})", color::bold(&name), state);
+                if !meta.tags.is_empty() {
+                    println!("  tags: {}", meta.tags.join(", "));
+                }
+                for (key, value) in &meta.metadata {
+                    println!("  {}: {}", key, value);
                 }
             }
         }
         None | Some(_) => {
             // Just show current session
-            if json_mode {
-                println!(r#"{{"success":true,"data":{{"session":"{}"}}}}"#, session);
+            let name = decode_session_name(&flags.session);
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": { "session": name } }));
             } else {
-                println!("{}", session);
+                println!("{}", name);
             }
         }
     }
 }
 
-fn main() {
-    // Ignore SIGPIPE to prevent panic when piping to head/tail
-    #[cfg(unix)]
-    unsafe {
-        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
-    }
+fn run_audit(args: &[String], flags: &Flags) {
+    let path = audit::log_path(&flags.session);
+    let entries = audit::read_entries(&flags.session).unwrap_or_else(|msg| fail(flags, msg, None));
 
-    let args: Vec<String> = env::args().skip(1).collect();
-    let flags = parse_flags(&args);
-    let clean = clean_args(&args);
+    match args.get(1).map(|s| s.as_str()) {
+        Some("show") => {
+            if flags.json {
+                let data = json!({ "entries": entries, "path": path });
+                println!("{}", json!({ "success": true, "data": data }));
+            } else if entries.is_empty() {
+                println!("No audit entries for session {} (record with --audit)", flags.session);
+            } else {
+                for entry in &entries {
+                    let ts = entry.get("ts").and_then(|v| v.as_u64()).unwrap_or(0);
+                    let status = if entry.get("success").and_then(|v| v.as_bool()) == Some(true) {
+                        color::success_indicator()
+                    } else {
+                        color::error_indicator()
+                    };
+                    let command = entry.get("command").cloned().unwrap_or_default();
+                    println!(
+                        "{:>4} {} {} {}",
+                        entry.get("seq").and_then(|v| v.as_u64()).unwrap_or(0),
+                        color::dim(&audit::format_timestamp(ts)),
+                        status,
+                        command
+                    );
+                }
+            }
+        }
+        Some("verify") => match audit::verify(&entries) {
+            Ok(count) => {
+                if flags.json {
+                    let data = json!({ "valid": true, "count": count, "path": path });
+                    println!("{}", json!({ "success": true, "data": data }));
+                } else {
+                    println!("{} {} entries verified", color::success_indicator(), count);
+                }
+            }
+            Err(msg) => {
+                if flags.json {
+                    let data = json!({ "valid": false, "path": path });
+                    println!("{}", json!({ "success": false, "data": data, "error": msg }));
+                } else {
+                    eprintln!("{} {}", color::error_indicator(), msg);
+                }
+                exit(1);
+            }
+        },
+        _ => {
+            let msg = "Usage: agent-browser audit <show|verify|security>";
+            fail(flags, msg, None);
+        }
+    }
+}
 
-    let has_help = args.iter().any(|a| a == "--help" || a == "-h");
-    let has_version = args.iter().any(|a| a == "--version" || a == "-V");
+/// token create/list/revoke: manage the access tokens that shared daemons
+/// check, in the tokens file (no daemon needed)
+fn run_token(args: &[String], flags: &Flags) {
+    let usage = "Usage: agent-browser token create <name> [--role <role>] [--sessions <patterns>] | list | revoke <name>";
+    let option = |name: &str| -> Option<&String> {
+        args.iter().position(|a| a == name).map(|i| {
+            args.get(i + 1).unwrap_or_else(|| fail(flags, format!("{} requires a value", name), None))
+        })
+    };
+    let name = || args.get(2).filter(|n| !n.starts_with("--")).unwrap_or_else(|| fail(flags, usage, None));
+    let path = tokens::tokens_path();
+    let mut file = tokens::load(&path).unwrap_or_else(|e| fail(flags, e, None));
+    match args.get(1).map(|s| s.as_str()) {
+        Some("create") => {
+            let name = name();
+            let role = option("--role").map(|s| s.as_str()).unwrap_or("read-only");
+            let sessions = option("--sessions")
+                .map(|s| s.split(',').map(|p| p.trim().to_string()).filter(|p| !p.is_empty()).collect::<Vec<_>>());
+            let token = file.create(name, role, sessions.clone(), jobs::now_millis()).unwrap_or_else(|e| fail(flags, e, None));
+            tokens::save(&path, &file).unwrap_or_else(|e| fail(flags, e, None));
+            if flags.json {
+                let data = json!({ "name": name, "role": role, "sessions": sessions, "token": token });
+                println!("{}", json!({ "success": true, "data": data }));
+            } else {
+                let scope = sessions.map(|s| s.join(", ")).unwrap_or_else(|| "all sessions".to_string());
+                println!("{} Created token {} ({}, {})", color::success_indicator(), color::bold(name), role, scope);
+                println!("{}", token);
+                println!("{}", color::dim("Store it now: only its hash is kept, so it can't be shown again."));
+            }
+        }
+        Some("list") | None => {
+            if flags.json {
+                // Hashes stay in the file
+                let list: Vec<_> = file
+                    .tokens
+                    .iter()
+                    .map(|t| json!({ "name": t.name, "role": t.role, "sessions": t.sessions, "created": t.created }))
+                    .collect();
+                println!("{}", json!({ "success": true, "data": { "tokens": list, "path": path.to_string_lossy() } }));
+            } else if file.tokens.is_empty() {
+                println!("No access tokens: daemons accept commands without one");
+            } else {
+                for t in &file.tokens {
+                    let scope = t.sessions.as_ref().map(|s| s.join(", ")).unwrap_or_else(|| "all sessions".to_string());
+                    println!("{} {} {}", color::bold(&t.name), t.role, color::dim(&scope));
+                }
+            }
+        }
+        Some("revoke") => {
+            let name = name();
+            if !file.revoke(name) {
+                fail(flags, format!("No token named {}", name), None);
+            }
+            tokens::save(&path, &file).unwrap_or_else(|e| fail(flags, e, None));
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": { "name": name, "revoked": true } }));
+            } else {
+                println!("{} Revoked token {}", color::success_indicator(), color::bold(name));
+            }
+        }
+        Some(other) => fail(flags, format!("Unknown token operation '{}'. {}", other, usage), None),
+    }
+}
 
-    if has_help {
-        if let Some(cmd) = clean.get(0) {
-            if print_command_help(cmd) {
-                return;
+/// memo set/get/list/delete/clear: the session's key/value scratchpad
+fn run_memo(args: &[String], flags: &Flags) {
+    let usage = "Usage: agent-browser memo set <key> <value> | get <key> | list | delete <key> | clear";
+    let key = || {
+        let key = args.get(2).unwrap_or_else(|| fail(flags, usage, None));
+        if !memo::valid_key(key) {
+            fail(flags, format!("Invalid memo key '{}': use letters, digits, _, - and .", key), None);
+        }
+        key.clone()
+    };
+    let mut values = memo::load(&flags.session).unwrap_or_else(|e| fail(flags, e, None));
+    match args.get(1).map(|s| s.as_str()) {
+        Some("set") => {
+            let key = key();
+            if args.len() < 4 {
+                fail(flags, usage, None);
+            }
+            let value = args[3..].join(" ");
+            values.insert(key.clone(), value.clone());
+            memo::save(&flags.session, &values).unwrap_or_else(|e| fail(flags, e, None));
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": { "key": key, "value": value } }));
+            } else {
+                println!("{} {} = {}", color::success_indicator(), key, value);
             }
         }
-        print_help();
+        Some("get") => {
+            let key = key();
+            let value = values
+                .get(&key)
+                .unwrap_or_else(|| fail(flags, format!("No memo '{}' in session {}", key, decode_session_name(&flags.session)), None));
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": { "key": key, "value": value } }));
+            } else {
+                println!("{}", value);
+            }
+        }
+        Some("list") | None => {
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": { "memo": values } }));
+            } else if values.is_empty() {
+                println!("No memos in session {}", decode_session_name(&flags.session));
+            } else {
+                for (key, value) in &values {
+                    println!("{} {}", color::bold(key), value);
+                }
+            }
+        }
+        Some("delete") | Some("rm") => {
+            let key = key();
+            let deleted = values.remove(&key).is_some();
+            memo::save(&flags.session, &values).unwrap_or_else(|e| fail(flags, e, None));
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": { "key": key, "deleted": deleted } }));
+            } else if deleted {
+                println!("{} Deleted {}", color::success_indicator(), key);
+            } else {
+                println!("No memo '{}'", key);
+            }
+        }
+        Some("clear") => {
+            let count = values.len();
+            memo::save(&flags.session, &Default::default()).unwrap_or_else(|e| fail(flags, e, None));
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": { "cleared": count } }));
+            } else {
+                println!("{} Cleared {} memo{}", color::success_indicator(), count, if count == 1 { "" } else { "s" });
+            }
+        }
+        Some(other) => fail(flags, format!("Unknown memo command '{}'. {}", other, usage), None),
+    }
+}
+
+fn run_history(args: &[String], flags: &Flags) {
+    if args.iter().any(|a| a == "--clear") {
+        history::clear(&flags.session);
+        if flags.json {
+            println!("{}", json!({ "success": true, "data": { "cleared": true } }));
+        } else {
+            println!("{} History cleared", color::success_indicator());
+        }
         return;
     }
+    let limit = match args.iter().position(|a| a == "--limit").map(|i| args.get(i + 1)) {
+        None => None,
+        Some(value) => match value.and_then(|v| v.parse::<usize>().ok()) {
+            Some(n) => Some(n),
+            None => fail(flags, "--limit needs a number of entries", None),
+        },
+    };
+    let entries = history::read_entries(&flags.session).unwrap_or_else(|msg| fail(flags, msg, None));
+    let shown = &entries[entries.len().saturating_sub(limit.unwrap_or(entries.len()))..];
+
+    if flags.json {
+        let shown: Vec<serde_json::Value> = shown
+            .iter()
+            .map(|entry| {
+                let mut entry = entry.clone();
+                entry["command"] = audit::redact(&entry["command"]);
+                entry["display"] = json!(history::display(&entry));
+                entry.as_object_mut().map(|map| map.remove("words"));
+                entry
+            })
+            .collect();
+        println!("{}", json!({ "success": true, "data": { "entries": shown } }));
+    } else if shown.is_empty() {
+        println!("No commands in session {} yet", flags.session);
+    } else {
+        for entry in shown {
+            let status = if entry["success"].as_bool() == Some(true) {
+                color::success_indicator()
+            } else {
+                color::error_indicator()
+            };
+            let summary = entry["summary"].as_str().unwrap_or_default();
+            let summary = if summary.is_empty() { String::new() } else { format!(" {}", color::dim(&format!("→ {}", summary))) };
+            println!("{:>4} {} {}{}", entry["n"].as_u64().unwrap_or(0), status, history::display(entry), summary);
+        }
+    }
+}
 
-    if has_version {
-        print_version();
+/// The words and command of the history entry `redo <n>` names, with a new id
+fn redo_command(args: &[String], flags: &Flags) -> (Vec<String>, serde_json::Value) {
+    let n = match args.get(1).map(|n| n.parse::<u64>()) {
+        Some(Ok(n)) => n,
+        _ => fail(flags, "Usage: agent-browser redo <n> (see: agent-browser history)", None),
+    };
+    let entries = history::read_entries(&flags.session).unwrap_or_else(|msg| fail(flags, msg, None));
+    let entry = history::find(&entries, n).unwrap_or_else(|msg| fail(flags, msg, None));
+    if !flags.json {
+        eprintln!("{}", color::dim(&format!("→ redo {}: {}", n, history::display(entry))));
+    }
+    let mut cmd = entry["command"].clone();
+    cmd["id"] = json!(gen_id());
+    (history::words(entry), cmd)
+}
+
+fn run_transcript(args: &[String], flags: &Flags) {
+    let value_of = |names: &[&str]| {
+        let idx = args.iter().position(|a| names.contains(&a.as_str()))?;
+        Some(args.get(idx + 1).cloned().unwrap_or_default())
+    };
+
+    let out = value_of(&["--out", "-o"]);
+    let format = match value_of(&["--format"]) {
+        Some(value) => transcript::Format::parse(&value).unwrap_or_else(|| {
+            fail(flags, format!("Invalid --format '{}': expected md or html", value), None)
+        }),
+        None => out.as_deref().map(transcript::Format::from_path).unwrap_or(transcript::Format::Markdown),
+    };
+    let entries = audit::read_entries(&flags.session).unwrap_or_else(|msg| fail(flags, msg, None));
+    if entries.is_empty() {
+        let msg = format!(
+            "No history for session {}. Record commands with --audit (or AGENT_BROWSER_AUDIT=1)",
+            flags.session
+        );
+        fail(flags, msg, None);
+    }
+
+    let report = transcript::render(&flags.session, &entries, format);
+    match out {
+        Some(path) => {
+            if let Err(e) = fs::write(&path, &report) {
+                fail(flags, format!("Cannot write transcript '{}': {}", path, e), None);
+            }
+            if flags.json {
+                let data = json!({ "path": path, "steps": entries.len() });
+                println!("{}", json!({ "success": true, "data": data }));
+            } else {
+                println!("{} Transcript of {} commands saved to {}", color::success_indicator(), entries.len(), path);
+            }
+        }
+        None if flags.json => {
+            let data = json!({ "transcript": report, "steps": entries.len() });
+            println!("{}", json!({ "success": true, "data": data }));
+        }
+        None => print!("{}", report),
+    }
+}
+
+/// Send a command and return its data, turning failures into an error message
+fn request(cmd: serde_json::Value, session: &str) -> Result<serde_json::Value, String> {
+    let resp = send_command(cmd, session)?;
+    if resp.success {
+        Ok(resp.data.unwrap_or_default())
+    } else {
+        Err(resp.error.unwrap_or_else(|| "Unknown error".to_string()))
+    }
+}
+
+fn run_checkpoint(args: &[String], flags: &Flags) {
+    let session = flags.session.as_str();
+    let usage = "Usage: agent-browser checkpoint <save|restore|delete> <name> | checkpoint list";
+    let subcommand = args.get(1).map(|s| s.as_str());
+    let name = args.get(2).map(|s| s.as_str());
+    if let Some(name) = name {
+        checkpoint::validate_name(name).unwrap_or_else(|msg| fail(flags, msg, None));
+    }
+
+    let _lock = matches!(subcommand, Some("save") | Some("restore")).then(|| lock_session(flags, args));
+    match (subcommand, name) {
+        (Some("save"), Some(name)) => {
+            if !is_daemon_running(session) {
+                fail(flags, format!("No browser running for session {}", session), None);
+            }
+            if is_ephemeral_daemon(session) {
+                fail(flags, format!("Session {} is ephemeral: a checkpoint would write its cookies and storage to disk", session), None);
+            }
+            let capture = || -> Result<checkpoint::Checkpoint, String> {
+                let page = request(json!({ "id": gen_id(), "action": "url" }), session)?;
+                let cookies = request(json!({ "id": gen_id(), "action": "cookies_get" }), session)?;
+                let script = checkpoint::CAPTURE_SCRIPT;
+                let captured = request(json!({ "id": gen_id(), "action": "evaluate", "script": script }), session)?;
+                let created = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or(0);
+                Ok(checkpoint::Checkpoint::from_page(
+                    name,
+                    page.get("url").and_then(|v| v.as_str()).unwrap_or("about:blank"),
+                    cookies.get("cookies").unwrap_or(&json!([])),
+                    captured.get("result").unwrap_or(&json!({})),
+                    created,
+                ))
+            };
+            let saved = capture().and_then(|cp| cp.save(session).map(|path| (cp, path)));
+            match saved {
+                Ok((cp, path)) => {
+                    if flags.json {
+                        let data = json!({ "name": name, "url": cp.url, "path": path });
+                        println!("{}", json!({ "success": true, "data": data }));
+                    } else {
+                        println!("{} Checkpoint {} saved ({})", color::success_indicator(), color::bold(name), cp.url);
+                    }
+                }
+                Err(e) => fail(flags, format!("Could not save checkpoint: {}", e), None),
+            }
+        }
+        (Some("restore"), Some(name)) => {
+            let cp = checkpoint::Checkpoint::load(session, name).unwrap_or_else(|msg| fail(flags, msg, None));
+            if let Err(e) = ensure_daemon(flags) {
+                fail(flags, e, None);
+            }
+            let notifier = flags.notify_webhook.as_deref().map(|url| notify::Notifier::new(url, session, "checkpoint"));
+            if let Some(ref notifier) = notifier {
+                warn_notify(notifier.started(), flags);
+            }
+            let commands = cp.restore_commands();
+            let total = commands.len();
+            for (step, cmd) in commands.into_iter().enumerate() {
+                let action = cmd["action"].as_str().unwrap_or_default().to_string();
+                if let Err(e) = request(cmd, session) {
+                    let msg = format!("Could not restore checkpoint: {}", e);
+                    if let Some(ref notifier) = notifier {
+                        warn_notify(notifier.finished(Err(&msg)), flags);
+                    }
+                    fail(flags, msg, None);
+                }
+                if let Some(ref notifier) = notifier {
+                    warn_notify(notifier.progress(step + 1, total, &action), flags);
+                }
+            }
+            if let Some(ref notifier) = notifier {
+                let data = json!({ "name": name, "url": cp.url, "restored": true });
+                warn_notify(notifier.finished(Ok(&data)), flags);
+            }
+            if flags.json {
+                let data = json!({ "name": name, "url": cp.url, "restored": true });
+                println!("{}", json!({ "success": true, "data": data }));
+            } else {
+                println!("{} Restored checkpoint {} ({})", color::success_indicator(), color::bold(name), cp.url);
+            }
+        }
+        (Some("delete"), Some(name)) => {
+            checkpoint::delete(session, name).unwrap_or_else(|msg| fail(flags, msg, None));
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": { "name": name, "deleted": true } }));
+            } else {
+                println!("{} Deleted checkpoint {}", color::success_indicator(), name);
+            }
+        }
+        (Some("list"), _) => {
+            let names = checkpoint::list(session);
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": { "checkpoints": names } }));
+            } else if names.is_empty() {
+                println!("No checkpoints for session {}", session);
+            } else {
+                for name in names {
+                    println!("{}", name);
+                }
+            }
+        }
+        _ => fail(flags, usage, None),
+    }
+}
+
+/// schedule add/list/history/run/remove: recurring script runs kept by the
+/// session's daemon; add reads the script here and sends its commands
+fn run_schedule(args: &[String], flags: &Flags) {
+    let session = flags.session.as_str();
+    let usage = "Usage: agent-browser schedule add <cron> --script <file> [--jitter <duration>] \
+| schedule <list|history|run|remove> [id]";
+    let option = |name: &str| -> Option<&String> {
+        args.iter().position(|a| a == name).map(|i| {
+            args.get(i + 1).unwrap_or_else(|| fail(flags, format!("{} requires a value", name), None))
+        })
+    };
+    let utc = |ms: Option<u64>| ms.map(|ms| format!("{} UTC", audit::format_timestamp(ms)));
+
+    let cmd = match args.get(1).map(|s| s.as_str()) {
+        Some("add") => {
+            let cron = args.get(2).filter(|c| !c.starts_with("--")).unwrap_or_else(|| fail(flags, usage, None));
+            let path = option("--script").unwrap_or_else(|| fail(flags, "schedule add requires --script <file>", None));
+            let jitter_ms = match option("--jitter") {
+                Some(value) => state_gc::parse_duration(value).unwrap_or_else(|e| fail(flags, e, None)).as_millis() as u64,
+                None => 0,
+            };
+            let mut commands = Vec::new();
+            for (line, words) in script::load(path).unwrap_or_else(|e| fail(flags, e, None)) {
+                let cmd = parse_command(&clean_args(&words), flags)
+                    .unwrap_or_else(|e| fail(flags, format!("{}:{}: {}", path, line, e.format().replace('\n', " ")), None));
+                commands.push(cmd);
+            }
+            let script = fs::canonicalize(path).map(|p| p.display().to_string()).unwrap_or_else(|_| path.clone());
+            json!({
+                "id": gen_id(),
+                "action": "schedule_add",
+                "cron": cron,
+                "commands": commands,
+                "script": script,
+                "jitterMs": jitter_ms,
+            })
+        }
+        Some("list") | None => json!({ "id": gen_id(), "action": "schedule_list" }),
+        Some(sub @ ("history" | "run" | "remove")) => {
+            let id = args.get(2).unwrap_or_else(|| fail(flags, format!("Usage: agent-browser schedule {} <id>", sub), None));
+            json!({ "id": gen_id(), "action": format!("schedule_{}", sub), "scheduleId": id })
+        }
+        Some(_) => fail(flags, usage, None),
+    };
+    let action = cmd["action"].as_str().unwrap_or_default().to_string();
+
+    // Schedules live in the session's daemon; starting it re-arms saved ones
+    if let Err(e) = ensure_daemon(flags) {
+        fail(flags, e, None);
+    }
+    let data = request(cmd, session).unwrap_or_else(|e| fail(flags, e, None));
+    if flags.json {
+        println!("{}", json!({ "success": true, "data": data }));
         return;
     }
 
+    let text = |key: &str| data.get(key).and_then(|v| v.as_str()).unwrap_or_default().to_string();
+    let number = |value: &serde_json::Value, key: &str| value.get(key).and_then(|v| v.as_u64());
+    match action.as_str() {
+        "schedule_add" => {
+            println!(
+                "{} Scheduled {} ({}, {} commands)",
+                color::success_indicator(),
+                text("id"),
+                text("cron"),
+                number(&data, "steps").unwrap_or(0)
+            );
+            if let Some(next) = utc(number(&data, "nextRunAt")) {
+                println!("  Next run: {}", next);
+            }
+        }
+        "schedule_list" => {
+            let schedules = data.get("schedules").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+            if schedules.is_empty() {
+                println!("No schedules for session {}", session);
+            }
+            for schedule in &schedules {
+                let field = |key: &str| schedule.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+                let last = match field("lastStatus") {
+                    "success" => format!("{} last run {}", color::success_indicator(), utc(number(schedule, "lastRunAt")).unwrap_or_default()),
+                    "failed" => format!("{} last run failed: {}", color::error_indicator(), field("lastError")),
+                    _ => color::dim("never run"),
+                };
+                println!("{}  {}  {}", color::bold(field("id")), field("cron"), field("script"));
+                println!("    next {}  {}", utc(number(schedule, "nextRunAt")).unwrap_or_default(), last);
+            }
+        }
+        "schedule_history" => {
+            let runs = data.get("history").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+            if runs.is_empty() {
+                println!("{} hasn't run yet", text("id"));
+            }
+            for run in &runs {
+                let ok = run.get("status").and_then(|v| v.as_str()) == Some("success");
+                let started = number(run, "startedAt").unwrap_or(0);
+                let duration = number(run, "finishedAt").unwrap_or(started).saturating_sub(started);
+                let indicator = if ok { color::success_indicator() } else { color::error_indicator() };
+                let error = run.get("error").and_then(|v| v.as_str()).map(|e| format!(": {}", e)).unwrap_or_default();
+                println!(
+                    "{} {}  {} steps in {}ms{}",
+                    indicator,
+                    utc(Some(started)).unwrap_or_default(),
+                    number(run, "steps").unwrap_or(0),
+                    duration,
+                    error
+                );
+            }
+        }
+        "schedule_run" => println!("{} Ran {} ({} steps)", color::success_indicator(), text("id"), number(&data, "steps").unwrap_or(0)),
+        _ => println!("{} Removed {}", color::success_indicator(), text("removed")),
+    }
+}
+
+/// Run one command of a script as its own agent-browser invocation with
+/// `globals`, returning the main value of its result when capturing. Quiet
+/// commands print nothing; their errors come back as the message.
+fn script_command(globals: &[String], words: &[String], capture: bool, quiet: bool) -> Result<String, String> {
+    let exe = env::current_exe().map_err(|e| format!("Cannot locate agent-browser: {}", e))?;
+    let mut command = std::process::Command::new(exe);
+    command.args(globals);
+    if !capture && !quiet {
+        let status = command.args(words).status().map_err(|e| format!("Cannot run command: {}", e))?;
+        if !status.success() {
+            return Err(format!("{} failed", words.first().map(|s| s.as_str()).unwrap_or_default()));
+        }
+        return Ok(String::new());
+    }
+    let stderr = if quiet { std::process::Stdio::null() } else { std::process::Stdio::inherit() };
+    let output = command
+        .arg("--json")
+        .args(words)
+        .stderr(stderr)
+        .output()
+        .map_err(|e| format!("Cannot run command: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let resp = stdout
+        .lines()
+        .rev()
+        .find_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .unwrap_or_default();
+    match resp.get("success").and_then(|v| v.as_bool()) {
+        Some(true) => Ok(script::capture_value(&resp["data"])),
+        _ => Err(resp.get("error").and_then(|v| v.as_str()).unwrap_or("command failed").to_string()),
+    }
+}
+
+/// Run a script with variables and control flow. Each command runs as its
+/// own agent-browser invocation with this one's global flags, so it goes
+/// through the same checks as when typed.
+fn run_script(args: &[String], all_args: &[String], flags: &Flags) {
+    let usage = "Usage: agent-browser run <script> [--var NAME=VALUE]...";
+    let path = args.get(1).filter(|p| !p.starts_with("--")).unwrap_or_else(|| fail(flags, usage, None));
+    let mut vars = std::collections::BTreeMap::new();
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--var" => {
+                let pair = rest.next().unwrap_or_else(|| fail(flags, "--var requires NAME=VALUE", None));
+                let (name, value) = script::parse_var(pair).unwrap_or_else(|e| fail(flags, e, None));
+                vars.insert(name, value);
+            }
+            other => fail(flags, format!("Unknown option '{}'. {}", other, usage), None),
+        }
+    }
+    let statements = script::load_program(path).unwrap_or_else(|e| fail(flags, e, None));
+    let globals = global_args(all_args);
+    // The script's commands run as child invocations sharing this lock
+    let _lock = lock_session(flags, args);
+
+    let mut runner = script::Runner::new(vars, |words: &[String], capture: bool| {
+        script_command(&globals, words, capture, false)
+    });
+    if let Err(e) = runner.run(&statements) {
+        fail(flags, format!("{}: {}", path, e), None);
+    }
+
+    if flags.json {
+        let data = json!({ "script": path, "steps": runner.steps, "vars": runner.vars });
+        println!("{}", json!({ "success": true, "data": data }));
+    } else {
+        println!("{} Ran {} ({} commands)", color::success_indicator(), path, runner.steps);
+    }
+}
+
+/// Run a script once per line of an input file, with the line in ${LINE}
+fn run_map(args: &[String], all_args: &[String], flags: &Flags) {
+    let usage = "Usage: agent-browser map <input> --script <file> [--out <file>] [--continue-on-error] \
+[--dedupe simhash [--threshold <bits>] [--drop]] [--var NAME=VALUE]...";
+    let input = args.get(1).filter(|p| !p.starts_with("--")).unwrap_or_else(|| fail(flags, usage, None));
+    let mut script_path = None;
+    let mut out_path = None;
+    let mut continue_on_error = false;
+    let mut dedupe = false;
+    let mut threshold = None;
+    let mut drop_duplicates = false;
+    let mut vars = std::collections::BTreeMap::new();
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        let mut value = || rest.next().unwrap_or_else(|| fail(flags, format!("{} requires a value", arg), None));
+        match arg.as_str() {
+            "--script" => script_path = Some(value()),
+            "--out" => out_path = Some(value()),
+            "--dedupe" => match value().as_str() {
+                "simhash" => dedupe = true,
+                other => fail(flags, format!("Unknown --dedupe method '{}': expected simhash", other), None),
+            },
+            "--threshold" => {
+                let bits = value();
+                threshold = Some(bits.parse::<u32>().ok().filter(|b| *b <= 64).unwrap_or_else(|| {
+                    fail(flags, format!("Invalid --threshold '{}': expected a number of bits from 0 to 64", bits), None)
+                }));
+            }
+            "--drop" => drop_duplicates = true,
+            "--var" => {
+                let (name, value) = script::parse_var(value()).unwrap_or_else(|e| fail(flags, e, None));
+                vars.insert(name, value);
+            }
+            "--continue-on-error" => continue_on_error = true,
+            other => fail(flags, format!("Unknown option '{}'. {}", other, usage), None),
+        }
+    }
+    if !dedupe && (threshold.is_some() || drop_duplicates) {
+        fail(flags, "--threshold and --drop apply to --dedupe simhash", None);
+    }
+    let threshold = threshold.unwrap_or(simhash::DEFAULT_THRESHOLD);
+    let script_path = script_path.unwrap_or_else(|| fail(flags, "map requires --script <file>", None));
+    let statements = script::load_program(script_path).unwrap_or_else(|e| fail(flags, e, None));
+    let source = fs::read_to_string(input).unwrap_or_else(|e| fail(flags, format!("Cannot read {}: {}", input, e), None));
+    let items = script::map_inputs(&source);
+    if items.is_empty() {
+        fail(flags, format!("{} has no input lines", input), None);
+    }
+    let mut out = out_path.map(|path| {
+        let file = fs::File::create(path).unwrap_or_else(|e| fail(flags, format!("Cannot write {}: {}", path, e), None));
+        std::io::BufWriter::new(file)
+    });
+    let globals = global_args(all_args);
+
+    let total = items.len();
+    let mut failures = Vec::new();
+    let mut done = 0;
+    let mut seen = simhash::Index::default();
+    let mut duplicates = 0;
+    for (index, (line, item)) in items.iter().enumerate() {
+        let mut item_vars = vars.clone();
+        item_vars.insert("LINE".to_string(), item.clone());
+        item_vars.insert("N".to_string(), (index + 1).to_string());
+        let mut runner = script::Runner::new(item_vars, |words: &[String], capture: bool| {
+            script_command(&globals, words, capture, true)
+        });
+        let outcome = runner.run(&statements);
+        let mut record = json!({
+            "n": index + 1,
+            "input": item,
+            "success": outcome.is_ok(),
+            "steps": runner.steps,
+            "vars": runner.vars,
+        });
+        if let Err(ref e) = outcome {
+            record["error"] = json!(e);
+        }
+        // The page the script ended on, compared with the earlier inputs' pages
+        let mut duplicate_of = None;
+        if dedupe && outcome.is_ok() {
+            if let Ok(text) = script_command(&globals, &["text".to_string()], true, true) {
+                let fingerprint = simhash::fingerprint(&text);
+                record["simhash"] = json!(format!("{:016x}", fingerprint));
+                duplicate_of = seen.check(fingerprint, threshold, index + 1).copied();
+                record["duplicateOf"] = json!(duplicate_of);
+            }
+        }
+        if duplicate_of.is_some() {
+            duplicates += 1;
+        }
+        let dropped = drop_duplicates && duplicate_of.is_some();
+        if let (Some(out), Some(path), false) = (out.as_mut(), out_path, dropped) {
+            use std::io::Write;
+            // Written as it goes so a long run can be followed and survives an interrupt
+            if let Err(e) = writeln!(out, "{}", record).and_then(|_| out.flush()) {
+                fail(flags, format!("Cannot write {}: {}", path, e), None);
+            }
+        }
+        done += 1;
+        match outcome {
+            Ok(()) if !flags.json => match duplicate_of {
+                Some(original) => eprintln!(
+                    "[{}/{}] {} {} {}",
+                    index + 1,
+                    total,
+                    color::warning_indicator(),
+                    item,
+                    color::dim(&format!("(duplicate of {})", original))
+                ),
+                None => eprintln!("[{}/{}] {} {}", index + 1, total, color::success_indicator(), item),
+            },
+            Ok(()) => {}
+            Err(e) => {
+                if !flags.json {
+                    eprintln!("[{}/{}] {} {}: {}", index + 1, total, color::error_indicator(), item, e);
+                }
+                failures.push(json!({ "n": index + 1, "line": line, "input": item, "error": e }));
+                if !continue_on_error {
+                    break;
+                }
+            }
+        }
+    }
+
+    let succeeded = done - failures.len();
+    if flags.json {
+        let mut data = json!({
+            "total": total,
+            "processed": done,
+            "succeeded": succeeded,
+            "failed": failures.len(),
+            "failures": failures,
+            "out": out_path,
+        });
+        if dedupe {
+            data["duplicates"] = json!(duplicates);
+        }
+        println!("{}", json!({ "success": failures.is_empty(), "data": data }));
+    } else {
+        let skipped = match total - done {
+            0 => String::new(),
+            n => format!(", {} not run", n),
+        };
+        if failures.is_empty() {
+            println!("{} {} of {} succeeded{}", color::success_indicator(), succeeded, total, skipped);
+        } else {
+            println!("{} {} of {} succeeded, {} failed{}", color::error_indicator(), succeeded, total, failures.len(), skipped);
+            for failure in &failures {
+                let field = |key: &str| failure[key].as_str().unwrap_or_default().to_string();
+                println!("  {}:{} {}: {}", input, failure["line"], field("input"), field("error"));
+            }
+        }
+        if dedupe {
+            let verb = if drop_duplicates { "dropped" } else { "marked" };
+            println!("  Near-duplicates: {} ({})", duplicates, verb);
+        }
+        if let Some(path) = out_path {
+            println!("  Results: {}", path);
+        }
+    }
+    if !failures.is_empty() {
+        exit(1);
+    }
+}
+
+fn run_watch(args: &[String], flags: &Flags) {
+    let session = flags.session.as_str();
+    let usage = "Usage: agent-browser watch <url> --selector <sel> [--interval <duration>] [--count <n>]";
+    let option = |name: &str| -> Option<&String> {
+        args.iter().position(|a| a == name).map(|i| {
+            args.get(i + 1).unwrap_or_else(|| fail(flags, format!("{} requires a value", name), None))
+        })
+    };
+    let url = args.get(1).filter(|u| !u.starts_with("--")).unwrap_or_else(|| fail(flags, usage, None));
+    let selector = option("--selector").unwrap_or_else(|| fail(flags, usage, None));
+    let interval_label = option("--interval").map(|s| s.as_str()).unwrap_or("10m");
+    let interval = state_gc::parse_duration(interval_label).unwrap_or_else(|e| fail(flags, e, None));
+    let count = option("--count").map(|value| match value.parse::<u64>() {
+        Ok(n) if n > 0 => n,
+        _ => fail(flags, format!("Invalid --count '{}': expected a positive number", value), None),
+    });
+
+    let navigate = parse_command(&["open".to_string(), url.clone()], flags).unwrap_or_else(|e| fail(flags, e.format(), None));
+    let url = navigate["url"].as_str().unwrap_or_default().to_string();
+    let host_rules = hosts::HostRules::from_flags(flags.allow_hosts.as_deref(), flags.block_hosts.as_deref())
+        .unwrap_or_else(|e| fail(flags, e, None));
+    if let Err(violation) = host_rules.check_url(&url) {
+        fail(flags, format!("Navigation blocked: {}", violation.reason), None);
+    }
+    if let Err(e) = ensure_daemon(flags) {
+        fail(flags, e, None);
+    }
+
+    let notifier = flags.notify_webhook.as_deref().map(|hook| notify::Notifier::new(hook, session, "watch"));
+    if let Some(ref notifier) = notifier {
+        warn_notify(notifier.started(), flags);
+    }
+    if !flags.json {
+        println!("Watching {} on {} every {} (Ctrl+C to stop)", color::bold(selector), url, interval_label);
+    }
+
+    let mut watcher = watch::Watcher::default();
+    let mut checks = 0;
+    loop {
+        let result = request(json!({ "id": gen_id(), "action": "navigate", "url": url }), session)
+            .and_then(|_| request(json!({ "id": gen_id(), "action": "gettext", "selector": selector }), session))
+            .map(|data| data.get("text").and_then(|v| v.as_str()).unwrap_or_default().to_string());
+        let timestamp = jobs::now_millis();
+        if let Some(event) = watcher.observe(result.as_deref().map_err(String::clone)) {
+            let mut fields = event.fields();
+            fields["url"] = json!(url);
+            fields["selector"] = json!(selector);
+            if flags.json {
+                let mut data = fields.clone();
+                data["event"] = json!(event.name());
+                data["timestamp"] = json!(timestamp);
+                println!("{}", json!({ "success": true, "data": data }));
+            } else {
+                let time = color::dim(&format!("{} UTC", audit::format_timestamp(timestamp)));
+                match &event {
+                    watch::Event::Initial(value) => println!("{} {}", time, value),
+                    watch::Event::Changed { previous, current } => {
+                        println!("{} {} {} {} {}", time, color::warning_indicator(), previous, color::cyan("→"), current)
+                    }
+                    watch::Event::Error(error) => eprintln!("{} {} {}", time, color::error_indicator(), error),
+                }
+            }
+            // The first value is the baseline; only later events are news
+            if let (Some(notifier), false) = (&notifier, matches!(event, watch::Event::Initial(_))) {
+                warn_notify(notifier.send(event.name(), fields), flags);
+            }
+        }
+        checks += 1;
+        if count.is_some_and(|n| checks >= n) {
+            break;
+        }
+        std::thread::sleep(interval);
+    }
+    if let Some(ref notifier) = notifier {
+        warn_notify(notifier.finished(Ok(&json!({ "checks": checks }))), flags);
+    }
+}
+
+fn run_observe(args: &[String], flags: &Flags) {
+    let session = flags.session.as_str();
+    let name = decode_session_name(session);
+    let screenshot_dir = args.iter().position(|a| a == "--screenshots").map(|i| {
+        let dir = args.get(i + 1).unwrap_or_else(|| fail(flags, "--screenshots requires a directory", None));
+        fs::create_dir_all(dir).unwrap_or_else(|e| fail(flags, format!("Cannot create {}: {}", dir, e), None));
+        std::path::PathBuf::from(dir)
+    });
+    // Observing never starts a session, only watches one that's running
+    if !is_daemon_running(session) {
+        fail(flags, format!("Session {} is not running", name), None);
+    }
+
+    let cmd = json!({ "id": gen_id(), "action": "observe", "screenshots": screenshot_dir.is_some() });
+    let (response, lines) = send_streaming(cmd, session).unwrap_or_else(|e| fail(flags, e, None));
+    if !response.success {
+        fail(flags, response.error.unwrap_or_else(|| "Unknown error".to_string()), None);
+    }
+    let data = response.data.unwrap_or_default();
+    if flags.json {
+        println!("{}", json!({ "success": true, "data": data }));
+    } else {
+        let url = data.get("url").and_then(|v| v.as_str()).unwrap_or("no page yet");
+        println!("Observing session {} at {} (Ctrl+C to stop)", color::bold(&name), url);
+    }
+
+    let mut screenshots = 0;
+    for line in lines.map_while(Result::ok) {
+        let Some(event) = observe::parse_event(&line) else {
+            continue;
+        };
+        let time = color::dim(&format!("{} UTC", audit::format_timestamp(jobs::now_millis())));
+        match &event {
+            observe::Event::Screenshot { action, data, .. } => {
+                let Some(dir) = &screenshot_dir else {
+                    continue;
+                };
+                screenshots += 1;
+                let path = observe::screenshot_path(dir, screenshots, action);
+                let bytes = transcript::base64_decode(data).unwrap_or_default();
+                if let Err(e) = fs::write(&path, bytes) {
+                    eprintln!("{} Cannot write {}: {}", color::warning_indicator(), path.display(), e);
+                    continue;
+                }
+                if flags.json {
+                    let data = json!({ "event": "screenshot", "action": action, "path": path.to_string_lossy() });
+                    println!("{}", json!({ "success": true, "data": data }));
+                } else {
+                    println!("{} {}", time, color::dim(&format!("screenshot {}", path.display())));
+                }
+            }
+            _ if flags.json => {
+                let data: serde_json::Value = serde_json::from_str(&line).unwrap_or_default();
+                println!("{}", json!({ "success": true, "data": data }));
+            }
+            observe::Event::Command { action, args, .. } => {
+                println!("{} {} {}", time, color::cyan("→"), observe::describe(action, args))
+            }
+            observe::Event::Result { action, success: true, duration_ms, .. } => {
+                println!("{} {} {} {}", time, color::success_indicator(), action, color::dim(&format!("{}ms", duration_ms)))
+            }
+            observe::Event::Result { action, error, .. } => println!(
+                "{} {} {}: {}",
+                time,
+                color::error_indicator(),
+                action,
+                error.as_deref().unwrap_or("failed")
+            ),
+            observe::Event::Closed => {}
+        }
+        if event == observe::Event::Closed {
+            break;
+        }
+    }
+    if !flags.json {
+        println!("Session {} ended", color::bold(&name));
+    }
+}
+
+fn run_inbox(args: &[String], flags: &Flags) {
+    let session = flags.session.as_str();
+    if args.get(1).map(|s| s.as_str()) != Some("wait") {
+        fail(flags, "Usage: agent-browser inbox wait --source <url> [--from <text>] [--subject <text>] [--open] [--fill <sel>]", None);
+    }
+    let option = |name: &str| -> Option<&String> {
+        args.iter().position(|a| a == name).map(|i| {
+            args.get(i + 1).unwrap_or_else(|| fail(flags, format!("{} requires a value", name), None))
+        })
+    };
+    let has = |name: &str| args.iter().any(|a| a == name);
+
+    let source = option("--source")
+        .cloned()
+        .or_else(|| env::var("AGENT_BROWSER_INBOX").ok().filter(|s| !s.is_empty()))
+        .unwrap_or_else(|| fail(flags, "inbox wait requires --source <url> (or AGENT_BROWSER_INBOX)", None));
+    let source = inbox::Source::parse(&source).unwrap_or_else(|e| fail(flags, e, None));
+    let filter = inbox::Filter { from: option("--from").cloned(), subject: option("--subject").cloned() };
+    let link_pattern = option("--link").map(|s| s.as_str());
+    let fill = option("--fill");
+    let code_length = option("--code-length").map(|value| {
+        value.parse::<usize>().ok().filter(|n| *n > 0).unwrap_or_else(|| fail(flags, format!("Invalid --code-length '{}'", value), None))
+    });
+    let timeout = state_gc::parse_duration(option("--timeout").map(|s| s.as_str()).unwrap_or("2m")).unwrap_or_else(|e| fail(flags, e, None));
+    let interval = state_gc::parse_duration(option("--interval").map(|s| s.as_str()).unwrap_or("5s")).unwrap_or_else(|e| fail(flags, e, None));
+    let want_code = has("--code") || fill.is_some() || code_length.is_some();
+    let want_link = !want_code || has("--open") || link_pattern.is_some();
+
+    let deadline = Instant::now() + timeout;
+    let mut existing: Option<std::collections::HashSet<String>> = None;
+    let found = loop {
+        let messages = inbox::fetch(&source).unwrap_or_else(|e| fail(flags, e, None));
+        // With --ignore-existing, only messages that arrive while waiting count
+        let seen = existing.get_or_insert_with(|| {
+            if has("--ignore-existing") {
+                messages.iter().map(|m| m.id.clone()).collect()
+            } else {
+                Default::default()
+            }
+        });
+        let found = messages.into_iter().filter(|m| !seen.contains(&m.id) && filter.matches(m)).find_map(|m| {
+            let link = inbox::extract_link(&m.body, link_pattern);
+            let code = inbox::extract_code(&m.body, code_length)
+                .or_else(|| inbox::extract_code(&m.subject, code_length));
+            let complete = (!want_link || link.is_some()) && (!want_code || code.is_some());
+            complete.then_some((m, link, code))
+        });
+        if let Some(found) = found {
+            break found;
+        }
+        if Instant::now() + interval > deadline {
+            fail(flags, format!("No matching message arrived within {}s", timeout.as_secs()), None);
+        }
+        std::thread::sleep(interval);
+    };
+    let (message, link, code) = found;
+
+    let opened = has("--open");
+    if opened || fill.is_some() {
+        if let Err(e) = ensure_daemon(flags) {
+            fail(flags, e, None);
+        }
+    }
+    if let (true, Some(link)) = (opened, link.as_ref()) {
+        request(json!({ "id": gen_id(), "action": "navigate", "url": link }), session)
+            .unwrap_or_else(|e| fail(flags, format!("Could not open the link: {}", e), None));
+    }
+    if let (Some(selector), Some(code)) = (fill, code.as_ref()) {
+        request(json!({ "id": gen_id(), "action": "fill", "selector": selector, "value": code }), session)
+            .unwrap_or_else(|e| fail(flags, format!("Could not fill the code: {}", e), None));
+    }
+
+    if flags.json {
+        let data = json!({
+            "from": message.from,
+            "subject": message.subject,
+            "link": link,
+            "code": code,
+            "opened": opened,
+            "filled": fill.is_some(),
+        });
+        println!("{}", json!({ "success": true, "data": data }));
+    } else {
+        println!("{} Message from {}: {}", color::success_indicator(), message.from, color::bold(&message.subject));
+        if let Some(ref link) = link {
+            println!("  Link: {}", link);
+        }
+        if let Some(ref code) = code {
+            println!("  Code: {}", code);
+        }
+        if opened {
+            println!("{} Opened the link", color::success_indicator());
+        }
+        if let Some(selector) = fill {
+            println!("{} Filled the code into {}", color::success_indicator(), selector);
+        }
+    }
+}
+
+fn run_auth(args: &[String], flags: &Flags) {
+    let session = flags.session.as_str();
+    let option = |name: &str| -> Option<&String> {
+        args.iter().position(|a| a == name).map(|i| {
+            args.get(i + 1).unwrap_or_else(|| fail(flags, format!("{} requires a value", name), None))
+        })
+    };
+
+    match args.get(1).map(|s| s.as_str()) {
+        Some("token") => {
+            let name = args.get(2).unwrap_or_else(|| fail(flags, "Usage: agent-browser auth token <name>", None));
+            let secret = secrets::load(name).unwrap_or_else(|e| fail(flags, e, None));
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": secret }));
+            } else {
+                println!("{}", secret.get("accessToken").and_then(|v| v.as_str()).unwrap_or_default());
+            }
+            return;
+        }
+        Some("oauth") => {}
+        _ => fail(flags, "Usage: agent-browser auth oauth --client-id <id> ... | auth token <name>", None),
+    }
+
+    let client_id = option("--client-id").unwrap_or_else(|| fail(flags, "auth oauth requires --client-id", None));
+    let token_url = option("--token-url").unwrap_or_else(|| fail(flags, "auth oauth requires --token-url", None));
+    let scope = option("--scope").map(|s| s.as_str());
+    let timeout = state_gc::parse_duration(option("--timeout").map(|s| s.as_str()).unwrap_or("5m"))
+        .unwrap_or_else(|e| fail(flags, e, None));
+    let name = option("--save").cloned().unwrap_or_else(|| {
+        let host = token_url.split("://").nth(1).and_then(|rest| rest.split(['/', ':', '?']).next());
+        host.unwrap_or("oauth").to_string()
+    });
+    secrets::validate_name(&name).unwrap_or_else(|e| fail(flags, e, None));
+    let device_url = option("--device-url");
+    if device_url.is_some() && args.iter().any(|a| a == "--redirect-capture") {
+        fail(flags, "Use either --device-url or --redirect-capture, not both", None);
+    }
+
+    if let Err(e) = ensure_daemon(flags) {
+        fail(flags, e, None);
+    }
+    let open = |url: &str| {
+        request(json!({ "id": gen_id(), "action": "navigate", "url": url }), session)
+            .unwrap_or_else(|e| fail(flags, format!("Could not open {}: {}", url, e), None));
+    };
+
+    let token = if let Some(device_url) = device_url {
+        let mut fields = vec![("client_id", client_id.as_str())];
+        if let Some(scope) = scope {
+            fields.push(("scope", scope));
+        }
+        let device = oauth::post_form(device_url, &fields).unwrap_or_else(|e| fail(flags, e, None));
+        let field = |key: &str| device.get(key).and_then(|v| v.as_str()).map(|s| s.to_string());
+        let device_code = field("device_code").unwrap_or_else(|| fail(flags, format!("Device authorization failed: {}", device), None));
+        let verification = field("verification_uri_complete")
+            .or_else(|| field("verification_uri"))
+            .unwrap_or_else(|| fail(flags, "Device authorization response has no verification_uri", None));
+        let interval = device.get("interval").and_then(|v| v.as_u64()).unwrap_or(5);
+        if !flags.json {
+            eprintln!(
+                "Enter code {} at {} (opened in session {})",
+                color::bold(&field("user_code").unwrap_or_default()),
+                verification,
+                session
+            );
+        }
+        open(&verification);
+        oauth::poll_device_token(token_url, client_id, &device_code, std::time::Duration::from_secs(interval), timeout)
+    } else {
+        let auth_url = option("--auth-url").unwrap_or_else(|| fail(flags, "auth oauth requires --auth-url (or --device-url)", None));
+        let port = option("--port").map(|p| p.parse::<u16>().unwrap_or_else(|_| fail(flags, format!("Invalid --port '{}'", p), None)));
+        let path = option("--redirect-path").map(|s| s.as_str()).unwrap_or("/callback");
+        let listener = std::net::TcpListener::bind(("127.0.0.1", port.unwrap_or(0)))
+            .unwrap_or_else(|e| fail(flags, format!("Cannot listen for the OAuth redirect: {}", e), None));
+        let port = listener.local_addr().map(|a| a.port()).unwrap_or_default();
+        let redirect_uri = format!("http://127.0.0.1:{}/{}", port, path.trim_start_matches('/'));
+        let verifier = oauth::random_token();
+        let state = oauth::random_token();
+        let url = oauth::authorize_url(&oauth::AuthRequest {
+            auth_url,
+            client_id,
+            redirect_uri: &redirect_uri,
+            scope,
+            state: &state,
+            challenge: &oauth::pkce_challenge(&verifier),
+        });
+        if !flags.json {
+            eprintln!("Waiting for authorization in session {} (redirect to {})", session, redirect_uri);
+        }
+        open(&url);
+        oauth::capture_redirect(&listener, &state, timeout).and_then(|code| {
+            let mut fields = vec![
+                ("grant_type", "authorization_code"),
+                ("code", code.as_str()),
+                ("redirect_uri", redirect_uri.as_str()),
+                ("client_id", client_id.as_str()),
+                ("code_verifier", verifier.as_str()),
+            ];
+            if let Some(secret) = option("--client-secret") {
+                fields.push(("client_secret", secret.as_str()));
+            }
+            oauth::post_form(token_url, &fields).and_then(oauth::token_result)
+        })
+    };
+    let token = token.unwrap_or_else(|e| fail(flags, format!("OAuth failed: {}", e), None));
+
+    let now = jobs::now_millis();
+    let expires_at = token.get("expires_in").and_then(|v| v.as_u64()).map(|secs| now + secs * 1000);
+    let text = |key: &str| token.get(key).cloned().unwrap_or(serde_json::Value::Null);
+    let secret = json!({
+        "accessToken": text("access_token"),
+        "refreshToken": text("refresh_token"),
+        "idToken": text("id_token"),
+        "tokenType": text("token_type"),
+        "scope": text("scope"),
+        "expiresAt": expires_at,
+        "obtainedAt": now,
+        "tokenUrl": token_url,
+        "clientId": client_id,
+    });
+    let path = secrets::save(&name, &secret).unwrap_or_else(|e| fail(flags, e, None));
+    if flags.json {
+        let data = json!({ "name": name, "path": path, "tokenType": secret["tokenType"], "expiresAt": expires_at });
+        println!("{}", json!({ "success": true, "data": data }));
+    } else {
+        println!("{} Saved OAuth token as {}", color::success_indicator(), color::bold(&name));
+        println!("  Use it with: agent-browser auth token {}", name);
+    }
+}
+
+/// TTL for `state gc`: --ttl, then --state-ttl, then AGENT_BROWSER_STATE_EXPIRE_DAYS
+fn resolve_state_ttl(value: Option<&str>) -> Result<std::time::Duration, String> {
+    if let Some(value) = value {
+        return state_gc::parse_duration(value);
+    }
+    match env::var("AGENT_BROWSER_STATE_EXPIRE_DAYS").ok().and_then(|d| d.parse::<u64>().ok()) {
+        Some(days) if days > 0 => Ok(std::time::Duration::from_secs(days * 86400)),
+        _ => Ok(state_gc::DEFAULT_TTL),
+    }
+}
+
+fn run_state_gc(args: &[String], flags: &Flags) {
+    let ttl_arg = args.iter().position(|a| a == "--ttl").and_then(|i| args.get(i + 1));
+    let ttl = resolve_state_ttl(ttl_arg.or(flags.state_ttl.as_ref()).map(|s| s.as_str()));
+    let dir = state_gc::sessions_dir();
+    let report = ttl.and_then(|ttl| state_gc::collect(&dir, ttl, std::time::SystemTime::now(), flags.dry_run));
+    let report = match report {
+        Ok(report) => report,
+        Err(msg) => fail(flags, msg, None),
+    };
+
+    if flags.json {
+        let data = json!({
+            "deleted": report.expired,
+            "reclaimedBytes": report.bytes,
+            "keptCount": report.kept,
+            "dryRun": flags.dry_run,
+            "directory": dir,
+        });
+        println!("{}", json!({ "success": true, "data": data }));
+    } else if report.expired.is_empty() {
+        println!("No expired state files ({} kept)", report.kept);
+    } else {
+        let (verb, reclaimed) = if flags.dry_run { ("Would delete", "to reclaim") } else { ("Deleted", "reclaimed") };
+        for name in &report.expired {
+            println!("  {}", name);
+        }
+        println!(
+            "{} {} {} expired state file(s), {} {} ({} kept)",
+            color::success_indicator(),
+            verb,
+            report.expired.len(),
+            state_gc::format_bytes(report.bytes),
+            reclaimed,
+            report.kept
+        );
+    }
+}
+
+fn run_daemon_warm(flags: &Flags, launch: Option<&serde_json::Value>, count: usize) {
+    let data = match warm::run(flags, launch, count) {
+        Ok(data) => data,
+        Err(msg) => fail(flags, msg, None),
+    };
+
+    if flags.json {
+        println!("{}", json!({ "success": true, "data": data }));
+    } else if count == 0 {
+        println!("{} Stopped {} warm daemon(s)", color::success_indicator(), data["stopped"]);
+    } else {
+        println!(
+            "{} {} of {} warm daemon(s) ready ({} started)",
+            color::success_indicator(),
+            data["ready"],
+            count,
+            data["started"]
+        );
+    }
+}
+
+fn run_artifacts(args: &[String], flags: &Flags) {
+    let option = |name: &str| -> Option<&String> {
+        args.iter().position(|a| a == name).map(|i| {
+            args.get(i + 1).unwrap_or_else(|| fail(flags, format!("{} requires a value", name), None))
+        })
+    };
+    let entries = artifacts::read_index().unwrap_or_else(|e| fail(flags, e, None));
+
+    match args.get(1).map(|s| s.as_str()) {
+        Some("list") | None => {
+            let kind = option("--kind");
+            let listed: Vec<&artifacts::Entry> = entries.iter().filter(|e| kind.is_none_or(|k| &e.kind == k)).collect();
+            if flags.json {
+                let items: Vec<serde_json::Value> = listed
+                    .iter()
+                    .map(|e| {
+                        let mut item = json!(e);
+                        item["path"] = json!(e.object_path());
+                        item
+                    })
+                    .collect();
+                println!("{}", json!({ "success": true, "data": { "artifacts": items, "directory": artifacts::store_dir() } }));
+                return;
+            }
+            if listed.is_empty() {
+                println!("No artifacts (store them with --artifacts)");
+            }
+            for e in &listed {
+                println!(
+                    "{}  {:<10} {:>9}  {}  {}  {}",
+                    color::bold(&e.hash[..12.min(e.hash.len())]),
+                    e.kind,
+                    state_gc::format_bytes(e.size),
+                    audit::format_timestamp(e.created),
+                    color::dim(&e.session),
+                    e.name
+                );
+            }
+        }
+        Some("get") => {
+            let hash = args.get(2).filter(|h| !h.starts_with("--"));
+            let hash = hash.unwrap_or_else(|| fail(flags, "Usage: agent-browser artifacts get <hash> [--out <path>]", None));
+            let entry = artifacts::resolve(&entries, hash).unwrap_or_else(|e| fail(flags, e, None));
+            let object = entry.object_path();
+            if !object.exists() {
+                fail(flags, format!("Artifact {} is missing from {}", &entry.hash[..12], object.display()), None);
+            }
+            let out = option("--out");
+            if let Some(out) = out {
+                fs::copy(&object, out).unwrap_or_else(|e| fail(flags, format!("Cannot write {}: {}", out, e), None));
+            }
+            if flags.json {
+                let mut data = json!(entry);
+                data["path"] = json!(object);
+                data["out"] = json!(out);
+                println!("{}", json!({ "success": true, "data": data }));
+            } else if let Some(out) = out {
+                println!("{} Saved {} to {}", color::success_indicator(), entry.name, out);
+            } else {
+                println!("{}", object.display());
+            }
+        }
+        Some("gc") => {
+            let (mut max_age, mut max_size) = artifacts::policy_from_env().unwrap_or_else(|e| fail(flags, e, None));
+            if let Some(value) = option("--max-age") {
+                max_age = state_gc::parse_duration(value).unwrap_or_else(|e| fail(flags, e, None));
+            }
+            if let Some(value) = option("--max-size") {
+                max_size = Some(budget::parse_size("--max-size", value).unwrap_or_else(|e| fail(flags, e, None)));
+            }
+            let report = artifacts::gc(max_age, max_size, flags.dry_run).unwrap_or_else(|e| fail(flags, e, None));
+            if flags.json {
+                let data = json!({
+                    "removedEntries": report.entries,
+                    "deletedFiles": report.objects,
+                    "reclaimedBytes": report.bytes,
+                    "keptCount": report.kept,
+                    "dryRun": flags.dry_run,
+                });
+                println!("{}", json!({ "success": true, "data": data }));
+            } else if report.entries == 0 {
+                println!("No artifacts past retention ({} kept)", report.kept);
+            } else {
+                let (verb, reclaimed) = if flags.dry_run { ("Would remove", "to reclaim") } else { ("Removed", "reclaimed") };
+                println!(
+                    "{} {} {} artifact(s), {} {} ({} kept)",
+                    color::success_indicator(),
+                    verb,
+                    report.entries,
+                    state_gc::format_bytes(report.bytes),
+                    reclaimed,
+                    report.kept
+                );
+            }
+        }
+        Some(other) => fail(flags, format!("Unknown artifacts operation '{}'. Usage: agent-browser artifacts [list|get|gc]", other), None),
+    }
+}
+
+fn run_workspace(args: &[String], flags: &Flags) {
+    let mut registry = workspace::Registry::load();
+    let name = args.get(2).map(|s| s.as_str());
+
+    match (args.get(1).map(|s| s.as_str()), name) {
+        (Some("init"), Some(name)) => {
+            let dir = args.iter().position(|a| a == "--dir").and_then(|i| args.get(i + 1));
+            let root = registry
+                .init(name, dir.map(std::path::Path::new))
+                .and_then(|root| registry.save().map(|_| root))
+                .unwrap_or_else(|msg| fail(flags, msg, None));
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": { "workspace": name, "path": root } }));
+            } else {
+                println!("{} Created workspace {} in {}", color::success_indicator(), color::bold(name), root.display());
+                println!("  Switch to it with: agent-browser workspace use {}", name);
+            }
+        }
+        (Some("use"), Some(name)) => {
+            registry.root(name).unwrap_or_else(|msg| fail(flags, msg, None));
+            registry.active = Some(name.to_string());
+            registry.save().unwrap_or_else(|msg| fail(flags, msg, None));
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": { "workspace": name } }));
+            } else {
+                println!("{} Using workspace {}", color::success_indicator(), color::bold(name));
+            }
+        }
+        (Some("leave"), _) => {
+            registry.active = None;
+            registry.save().unwrap_or_else(|msg| fail(flags, msg, None));
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": { "workspace": null } }));
+            } else {
+                println!("{} No workspace active", color::success_indicator());
+            }
+        }
+        (Some("list"), _) => {
+            if flags.json {
+                let data = json!({ "active": registry.active, "workspaces": registry.workspaces });
+                println!("{}", json!({ "success": true, "data": data }));
+            } else if registry.workspaces.is_empty() {
+                println!("No workspaces (create one with: agent-browser workspace init <name>)");
+            } else {
+                for (name, root) in &registry.workspaces {
+                    let active = registry.active.as_deref() == Some(name.as_str());
+                    let marker = if active { color::cyan("→") } else { " ".to_string() };
+                    println!("{} {} {}", marker, name, color::dim(&root.display().to_string()));
+                }
+            }
+        }
+        (None, _) => {
+            let current = flags.workspace.as_deref().or(registry.active.as_deref());
+            let root = current.and_then(|name| registry.workspaces.get(name));
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": { "workspace": current, "path": root } }));
+            } else {
+                match (current, root) {
+                    (Some(name), Some(root)) => println!("{} {}", name, color::dim(&root.display().to_string())),
+                    _ => println!("No workspace active"),
+                }
+            }
+        }
+        _ => fail(flags, "Usage: agent-browser workspace [init <name> [--dir <path>] | use <name> | leave | list]", None),
+    }
+}
+
+/// Run the session's daemon in the foreground, for a sidecar container: other
+/// containers reach it through the shared socket directory
+fn run_serve(mut flags: Flags) {
+    if is_daemon_running(&flags.session) {
+        fail(&flags, format!("Session '{}' already has a running daemon", flags.session), None);
+    }
+    if let Some(ref channel) = flags.channel {
+        let path = channels::resolve(channel).unwrap_or_else(|e| fail(&flags, e, None));
+        flags.executable_path = Some(path.to_string_lossy().to_string());
+    }
+
+    // The browser launches on the first command, with the args from the environment
+    let mut args: Vec<String> = config::load_config().map(|c| c.browser_args).unwrap_or_default();
+    args.extend(flags.args.iter().flat_map(|a| a.split(&[',', '\n'][..])).map(|a| a.trim().to_string()));
+    args.extend(flags.browser_args.iter().cloned());
+    args.retain(|a| !a.is_empty());
+    let containerized = container::enabled(flags.containerized);
+    if containerized {
+        container::apply(&mut args);
+    }
+    if !args.is_empty() {
+        flags.args = Some(args.join(","));
+    }
+
+    let mut cmd = connection::daemon_command(&flags).unwrap_or_else(|e| fail(&flags, e, None));
+    let address = connection::daemon_address(&flags.session);
+    let shm_advice = container::shm_size().and_then(container::shm_advice).filter(|_| containerized);
+    if flags.json {
+        println!(
+            "{}",
+            json!({ "success": true, "data": { "session": flags.session, "address": address, "containerized": containerized, "args": args } })
+        );
+    } else {
+        println!("{} Serving session {} at {}", color::success_indicator(), flags.session, address);
+        if !args.is_empty() {
+            println!("  Browser args: {}", args.join(" "));
+        }
+        if let Some(advice) = shm_advice {
+            eprintln!("{} {}", color::warning_indicator(), advice);
+        }
+    }
+
+    // Become the daemon so it gets the container's stop signal directly
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        let error = cmd.exec();
+        fail(&flags, format!("Failed to start daemon: {}", error), None);
+    }
+    #[cfg(windows)]
+    {
+        let status = cmd.status().unwrap_or_else(|e| fail(&flags, format!("Failed to start daemon: {}", e), None));
+        exit(status.code().unwrap_or(1));
+    }
+}
+
+fn run_env(flags: &Flags) {
+    let daemon_path = connection::find_daemon_path();
+    let daemon_running = is_daemon_running(&flags.session);
+    let user_agent = if daemon_running {
+        let cmd = json!({ "id": gen_id(), "action": "evaluate", "script": "navigator.userAgent" });
+        request(cmd, &flags.session)
+            .ok()
+            .and_then(|data| data.get("result").and_then(|v| v.as_str()).map(str::to_string))
+    } else {
+        None
+    };
+    let browser = user_agent.as_deref().and_then(diagnostics::browser_from_user_agent);
+    let workspace = flags
+        .workspace
+        .clone()
+        .or(workspace::Registry::load().active);
+
+    let report = json!({
+        "cli": { "version": env!("CARGO_PKG_VERSION") },
+        "daemon": {
+            "path": daemon_path,
+            "version": daemon_path.as_deref().and_then(diagnostics::daemon_version),
+            "running": daemon_running,
+        },
+        "browser": {
+            "name": browser.as_ref().map(|b| &b.0),
+            "version": browser.as_ref().map(|b| &b.1),
+            "userAgent": user_agent,
+            "executablePath": flags.executable_path,
+            "channel": flags.channel,
+        },
+        "channels": diagnostics::installed_channels(),
+        "os": diagnostics::os_info(),
+        "session": flags.session,
+        "workspace": workspace,
+        "paths": {
+            "appData": connection::app_data_dir(),
+            "socketDir": get_socket_dir(),
+            "stateDir": state_gc::sessions_dir(),
+            "auditDir": audit::audit_dir(),
+        },
+        "config": diagnostics::config_summary(),
+        "tls": diagnostics::tls_summary(flags.ignore_https_errors, flags.extra_ca.as_deref()),
+        "env": diagnostics::redacted_vars(env::vars()),
+    });
+
+    if flags.json {
+        println!("{}", json!({ "success": true, "data": report }));
+        return;
+    }
+    let text = |v: &serde_json::Value| match v {
+        serde_json::Value::Null => "-".to_string(),
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    println!("{}", color::bold("agent-browser environment"));
+    println!("  CLI:        {}", text(&report["cli"]["version"]));
+    println!(
+        "  Daemon:     {} ({})",
+        text(&report["daemon"]["version"]),
+        if daemon_running { "running" } else { "not running" }
+    );
+    println!(
+        "  Browser:    {} {}",
+        text(&report["browser"]["name"]),
+        text(&report["browser"]["version"])
+    );
+    let channels = report["channels"].as_array().cloned().unwrap_or_default();
+    if !channels.is_empty() {
+        println!("  Channels:");
+        for channel in &channels {
+            println!(
+                "    {:<12} {} ({})",
+                text(&channel["channel"]),
+                text(&channel["version"]),
+                text(&channel["path"])
+            );
+        }
+    }
+    println!(
+        "  OS:         {} {}",
+        text(&report["os"]["os"]),
+        text(&report["os"]["arch"])
+    );
+    println!("  Session:    {}", text(&report["session"]));
+    println!("  Workspace:  {}", text(&report["workspace"]));
+    println!("  Config:     {}", text(&report["config"]["path"]));
+    if let Some(error) = report["config"].get("error") {
+        println!("    {} {}", color::warning_indicator(), text(error));
+    }
+    println!("  Socket dir: {}", text(&report["paths"]["socketDir"]));
+    if flags.ignore_https_errors {
+        println!("  TLS:        {} certificate errors ignored (--ignore-https-errors)", color::warning_indicator());
+    } else {
+        println!("  TLS:        certificates verified");
+    }
+    let extra_ca = &report["tls"]["extraCa"];
+    if let Some(error) = extra_ca.get("error") {
+        println!("  Extra CA:   {} {}", color::error_indicator(), text(error));
+    } else if !extra_ca.is_null() {
+        let certificates = extra_ca["certificates"].as_u64().unwrap_or_default();
+        let plural = if certificates == 1 { "" } else { "s" };
+        println!("  Extra CA:   {} ({} certificate{})", text(&extra_ca["path"]), certificates, plural);
+    }
+    let vars = report["env"].as_object().cloned().unwrap_or_default();
+    if !vars.is_empty() {
+        println!("  Environment:");
+        for (name, value) in &vars {
+            println!("    {}={}", name, text(value));
+        }
+    }
+}
+
+fn run_self_update(args: &[String], flags: &Flags) {
+    // --channel is also the global browser channel, so it arrives parsed; the
+    // browser channel in AGENT_BROWSER_CHANNEL doesn't pick a release channel
+    let from_env = env::var("AGENT_BROWSER_CHANNEL").ok();
+    let channel = match flags.channel.as_deref().filter(|c| from_env.as_deref() != Some(*c)) {
+        None => self_update::Channel::Stable,
+        Some(value) => self_update::Channel::parse(value).unwrap_or_else(|e| fail(flags, e, None)),
+    };
+    let check_only = flags.dry_run || args.iter().any(|a| a == "--check");
+    let exe = env::current_exe()
+        .and_then(|p| p.canonicalize())
+        .unwrap_or_else(|e| fail(flags, format!("Cannot locate the running binary: {}", e), None));
+
+    let report = self_update::run(channel, &exe, env!("CARGO_PKG_VERSION"), check_only)
+        .unwrap_or_else(|e| fail(flags, e, None));
+    let newer = self_update::compare_versions(&report.latest, &report.current) == std::cmp::Ordering::Greater;
+
+    if flags.json {
+        let data = json!({
+            "channel": report.channel.as_str(),
+            "currentVersion": report.current,
+            "latestVersion": report.latest,
+            "updateAvailable": newer,
+            "updated": report.updated,
+            "path": report.path,
+        });
+        println!("{}", json!({ "success": true, "data": data }));
+    } else if report.updated {
+        println!(
+            "{} Updated agent-browser {} -> {} ({})",
+            color::success_indicator(),
+            report.current,
+            report.latest,
+            report.path.display()
+        );
+    } else if newer {
+        println!(
+            "agent-browser {} is available on the {} channel (installed: {})",
+            report.latest,
+            report.channel.as_str(),
+            report.current
+        );
+        println!("  Install it with: agent-browser self-update --channel {}", report.channel.as_str());
+    } else {
+        println!(
+            "{} agent-browser {} is up to date ({} channel)",
+            color::success_indicator(),
+            report.current,
+            report.channel.as_str()
+        );
+    }
+}
+
+/// `job` for jobs started with --detach, which live in the jobs directory
+/// rather than the daemon's queue, and `job list`, which shows both kinds.
+/// Returns false when the daemon answers (jobs queued with --no-wait).
+fn run_job(args: &[String], flags: &Flags) -> bool {
+    let sub = args.get(1).map(|s| s.as_str());
+    let detached = args.get(2).filter(|id| jobs::exists(id));
+    let job_arg = || -> jobs::Job {
+        let id = args.get(2).unwrap_or_else(|| {
+            fail(flags, format!("Usage: agent-browser job {} <id>", args[1]), None)
+        });
+        jobs::Job::load(id).unwrap_or_else(|e| fail(flags, e, None))
+    };
+    let status_color = |name: &str| {
+        let padded = format!("{:<9}", name);
+        match name {
+            "queued" | "running" => color::cyan(&padded),
+            "completed" => color::green(&padded),
+            _ => color::red(&padded),
+        }
+    };
+
+    match sub {
+        Some("list") | None => {
+            let detached_jobs = jobs::list();
+            // Jobs queued with --no-wait, if the session's daemon is up
+            let queued: Vec<serde_json::Value> = if is_daemon_running(&flags.session) {
+                send_command(json!({ "id": gen_id(), "action": "job_list" }), &flags.session)
+                    .ok()
+                    .and_then(|resp| resp.data)
+                    .and_then(|data| data.get("jobs").and_then(|j| j.as_array()).cloned())
+                    .unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+            if flags.json {
+                let mut list: Vec<serde_json::Value> = queued
+                    .into_iter()
+                    .map(|mut job| {
+                        job["detached"] = json!(false);
+                        job
+                    })
+                    .collect();
+                list.extend(detached_jobs.iter().map(|job| {
+                    let mut value = json!(job);
+                    value["jobId"] = json!(job.id);
+                    value["command"] = json!(jobs::command_line(&job.args));
+                    value["detached"] = json!(true);
+                    value
+                }));
+                println!("{}", json!({ "success": true, "data": { "jobs": list } }));
+            } else if detached_jobs.is_empty() && queued.is_empty() {
+                println!("No jobs (start one with --no-wait or --detach)");
+            } else {
+                let text = |job: &serde_json::Value, key: &str| {
+                    job.get(key).and_then(|v| v.as_str()).unwrap_or("?").to_string()
+                };
+                for job in &queued {
+                    let enqueued = job.get("enqueuedAt").and_then(|v| v.as_u64()).unwrap_or(0);
+                    println!(
+                        "{}  {}  {}  {} {}",
+                        text(job, "jobId"),
+                        status_color(&text(job, "status")),
+                        color::dim(&audit::format_timestamp(enqueued)),
+                        text(job, "action"),
+                        color::dim(&format!("({}, --no-wait)", flags.session))
+                    );
+                }
+                for job in &detached_jobs {
+                    println!(
+                        "{}  {}  {}  {} {}",
+                        job.id,
+                        status_color(job.status.name()),
+                        color::dim(&audit::format_timestamp(job.started)),
+                        jobs::command_line(&job.args),
+                        color::dim(&format!("({}, --detach)", job.session))
+                    );
+                }
+            }
+        }
+        Some("logs") => {
+            let id = args.get(2).unwrap_or_else(|| fail(flags, "Usage: agent-browser job logs <id>", None));
+            if detached.is_none() {
+                fail(flags, format!("Unknown job: {} (job logs only applies to jobs started with --detach)", id), None);
+            }
+            let job = job_arg();
+            if flags.json {
+                let data = json!({ "jobId": job.id, "status": job.status, "output": job.output() });
+                println!("{}", json!({ "success": true, "data": data }));
+            } else {
+                print!("{}", job.output());
+            }
+        }
+        Some("status") if detached.is_some() => {
+            let job = job_arg();
+            let command = jobs::command_line(&job.args);
+            if flags.json {
+                let data = json!({
+                    "jobId": job.id,
+                    "command": command,
+                    "status": job.status,
+                    "session": job.session,
+                    "exitCode": job.exit_code,
+                    "started": job.started,
+                    "finished": job.finished,
+                });
+                println!("{}", json!({ "success": true, "data": data }));
+            } else {
+                println!("{} {}: {}", job.id, command, job.status.name());
+            }
+        }
+        Some("cancel") if detached.is_some() => {
+            let job = job_arg();
+            let job = jobs::cancel(&job.id).unwrap_or_else(|e| fail(flags, e, None));
+            if flags.json {
+                println!("{}", json!({ "success": true, "data": { "jobId": job.id, "status": job.status } }));
+            } else {
+                println!("{} Cancelled {}", color::success_indicator(), job.id);
+            }
+        }
+        Some("result") if detached.is_some() => {
+            let job = job_arg();
+            if job.status == jobs::Status::Running {
+                fail(flags, format!("Job {} is still running (see: agent-browser job logs {})", job.id, job.id), None);
+            }
+            let ok = job.status == jobs::Status::Completed;
+            if flags.json {
+                let data = json!({
+                    "jobId": job.id,
+                    "status": job.status,
+                    "exitCode": job.exit_code,
+                    "output": job.output(),
+                    "result": job.result(),
+                });
+                let mut output = json!({ "success": ok, "data": data });
+                if !ok {
+                    output["error"] = json!(format!("Job {} {}", job.id, job.status.name()));
+                }
+                println!("{}", output);
+            } else {
+                print!("{}", job.output());
+                if !ok {
+                    let code = job.exit_code.map(|c| format!(" (exit code {})", c)).unwrap_or_default();
+                    eprintln!("{} Job {} {}{}", color::error_indicator(), job.id, job.status.name(), code);
+                }
+            }
+            if !ok {
+                exit(1);
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Whether the command's history and audit entries are written. An
+/// ephemeral session keeps nothing on disk, including for later commands
+/// sent to it without --ephemeral.
+fn keeps_records(flags: &Flags) -> bool {
+    !flags.ephemeral && !is_ephemeral_daemon(&flags.session)
+}
+
+/// Start the command as a background job instead of running it here
+fn run_detached(args: &[String], flags: &Flags) {
+    let job_args = jobs::detached_args(args, flag_end(args));
+    match jobs::spawn(&flags.session, job_args) {
+        Ok(job) => {
+            let command = jobs::command_line(&job.args);
+            if flags.json {
+                let data = json!({ "jobId": job.id, "status": job.status, "command": command });
+                println!("{}", json!({ "success": true, "data": data }));
+            } else {
+                println!("{} Started {} ({})", color::success_indicator(), job.id, command);
+                println!("  Follow it with: agent-browser job logs {}", job.id);
+            }
+        }
+        Err(msg) => fail(flags, msg, None),
+    }
+}
+
+/// Replace the screenshot taken for `text --ocr` with the text read from it
+fn read_ocr(resp: &mut Response, lang: &str) {
+    let Some(encoded) = resp.data.as_ref().filter(|_| resp.success).and_then(|d| d["base64"].as_str()) else {
+        return;
+    };
+    // tesseract reads the image from a file in the temp dir, removed once read
+    let path = env::temp_dir().join(format!("ocr-{}.png", gen_id()));
+    let text = transcript::base64_decode(encoded)
+        .ok_or_else(|| "Screenshot is not base64".to_string())
+        .and_then(|bytes| {
+            secrets::write_private(&path, &bytes).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+        })
+        .and_then(|()| ocr::recognize(&path, lang));
+    let _ = fs::remove_file(&path);
+    match text {
+        Ok(text) => resp.data = Some(json!({ "text": text, "lang": lang })),
+        Err(e) => {
+            resp.success = false;
+            resp.data = None;
+            resp.error = Some(e);
+        }
+    }
+}
+
+/// Replace the PDF the daemon saved for `text` with its text and metadata
+fn read_pdf(resp: &mut Response, pages: Option<pdf::PageRange>) {
+    let Some(saved) = resp.data.as_ref().filter(|_| resp.success).map(|d| d["pdf"].clone()).filter(|p| p.is_object()) else {
+        return;
+    };
+    let Some(path) = saved["path"].as_str() else {
+        return;
+    };
+    let extracted = pdf::extract(std::path::Path::new(path), pages);
+    let _ = fs::remove_file(path);
+    match extracted {
+        Ok(mut data) => {
+            data["url"] = saved["url"].clone();
+            resp.data = Some(data);
+        }
+        Err(e) => {
+            resp.success = false;
+            resp.data = None;
+            resp.error = Some(e);
+        }
+    }
+}
+
+/// Add the language of a `text` result and, with --translate-to, translate
+/// it (page by page for a PDF), keeping the original as `originalText`
+fn detect_and_translate(resp: &mut Response, translation: Option<(&language::Translator, &str)>) {
+    let Some(data) = resp.data.as_mut().filter(|_| resp.success) else {
+        return;
+    };
+    let Some(text) = data["text"].as_str().map(String::from) else {
+        return;
+    };
+    let detected = language::detect(&text);
+    data["language"] = json!(detected.map(|(lang, _)| lang));
+    data["languageConfidence"] = json!(detected.map(|(_, confidence)| confidence));
+    let Some((translator, target)) = translation else {
+        return;
+    };
+    let source = detected.map(|(lang, _)| lang);
+    let base = target.split('-').next().unwrap_or(target).to_ascii_lowercase();
+    data["translatedTo"] = json!(target);
+    // Already in the target language
+    if source == Some(base.as_str()) {
+        data["translated"] = json!(false);
+        return;
+    }
+    let translated = match data.get_mut("pages").and_then(|p| p.as_array_mut()) {
+        Some(pages) => pages
+            .iter_mut()
+            .map(|page| {
+                let original = page["text"].as_str().unwrap_or("").to_string();
+                if original.trim().is_empty() {
+                    return Ok(original);
+                }
+                let translated = translator.translate(&original, source, target)?;
+                page["originalText"] = json!(original);
+                page["text"] = json!(translated);
+                Ok(translated)
+            })
+            .collect::<Result<Vec<_>, String>>()
+            .map(|pages| pages.join("\n\n")),
+        None => translator.translate(&text, source, target),
+    };
+    match translated {
+        Ok(translated) => {
+            data["originalText"] = json!(text);
+            data["text"] = json!(translated);
+            data["translated"] = json!(true);
+        }
+        Err(e) => {
+            resp.success = false;
+            resp.data = None;
+            resp.error = Some(e);
+        }
+    }
+}
+
+/// Keep the file a successful command produced in the artifact store
+/// (--artifacts), adding its hash to the result. A scratch file written only
+/// for the store is moved in, and the result then points at the stored copy.
+/// Storing is best effort: the command's own file is already in place.
+fn store_artifact(resp: &mut Response, action: &str, scratch: Option<&std::path::Path>, flags: &Flags) {
+    let Some((kind, field)) = artifacts::artifact_field(action) else {
+        return;
+    };
+    let Some(data) = resp.data.as_mut().filter(|_| resp.success) else {
+        return;
+    };
+    let Some(path) = data.get(field).and_then(|v| v.as_str()).filter(|p| !p.is_empty()).map(std::path::PathBuf::from) else {
+        return;
+    };
+    let owned = scratch == Some(path.as_path());
+    let stored = artifacts::ingest(&path, kind, &flags.session, owned).map(|entry| {
+        let object = entry.object_path().to_string_lossy().to_string();
+        if owned {
+            data[field] = json!(object);
+        }
+        data["artifact"] = json!({ "hash": entry.hash, "path": object });
+    });
+    let retained = artifacts::policy_from_env().and_then(|(max_age, max_size)| artifacts::gc(max_age, max_size, false));
+    for e in [stored.err(), retained.err()].into_iter().flatten() {
+        if !flags.json {
+            eprintln!("{} Artifact store: {}", color::warning_indicator(), e);
+        }
+    }
+}
+
+/// Upload the file a successful command produced (--upload), adding where it
+/// went to the result; a failed upload fails the command but keeps the file
+fn upload_artifact(resp: &mut Response, action: &str, target: &upload::Target) {
+    if !resp.success {
+        return;
+    }
+    let Some(path) = resp.data.as_ref().and_then(|d| upload::artifact_path(action, d)).map(String::from) else {
+        return;
+    };
+    match upload::upload(target, &path) {
+        Ok(uploaded) => {
+            if let Some(data) = resp.data.as_mut().and_then(|d| d.as_object_mut()) {
+                data.insert("upload".to_string(), uploaded);
+            }
+        }
+        Err(e) => {
+            resp.success = false;
+            resp.error = Some(format!("{} (saved locally to {})", e, path));
+        }
+    }
+}
+
+/// Report an undeliverable webhook event without failing the command
+fn warn_notify(result: Result<(), String>, flags: &Flags) {
+    if let Err(msg) = result {
+        if !flags.json {
+            eprintln!("{} {}", color::warning_indicator(), msg);
+        }
+    }
+}
+
+/// Run the hooks configured for an event. Failures are warnings: a hook never
+/// fails the command that triggered it.
+fn fire_hooks(hooks: &hooks::Hooks, event: hooks::Event, data: &serde_json::Value, flags: &Flags) {
+    for hook in hooks.for_event(event).iter().filter(|h| h.matches(data)) {
+        let result = if let Some(ref script) = hook.run {
+            hooks::run_shell(script, &hooks::hook_env(event, &flags.session, data))
+        } else if let Some(ref args) = hook.command {
+            parse_command(args, flags)
+                .map_err(|e| e.format())
+                .and_then(|cmd| request(cmd, &flags.session).map(|_| ()))
+                .map_err(|e| format!("Hook '{}' failed: {}", args.join(" "), e))
+        } else {
+            Ok(())
+        };
+        if let Err(msg) = result {
+            if !flags.json {
+                eprintln!("{} {} hook: {}", color::warning_indicator(), event.name(), msg);
+            }
+        }
+    }
+}
+
+fn run_completions(args: &[String], flags: &Flags) {
+    let subcommand = args.get(1).map(|s| s.as_str());
+
+    // Used by the generated scripts to complete --session values
+    if subcommand == Some("--sessions") {
+        for session in list_active_sessions() {
+            println!("{}", session);
+        }
+        return;
+    }
+
+    match subcommand.and_then(completions::generate) {
+        Some(script) => print!("{}", script),
+        None => {
+            let msg = format!(
+                "Usage: agent-browser completions <{}>",
+                completions::SHELLS.join("|")
+            );
+            fail(flags, msg, None);
+        }
+    }
+}
+
+/// Print the command that would be sent without running it. Selectors are
+/// checked against the page when the session's browser is already running;
+/// a browser is never launched for a dry run.
+fn run_dry_run(cmd: &serde_json::Value, flags: &Flags) {
+    let browser_running = is_daemon_running(&flags.session);
+    let checks: Vec<(&str, Option<u64>)> = command_selectors(cmd)
+        .into_iter()
+        .map(|selector| {
+            let count = if browser_running {
+                let probe = json!({ "id": gen_id(), "action": "count", "selector": selector });
+                send_command(probe, &flags.session)
+                    .ok()
+                    .filter(|resp| resp.success)
+                    .and_then(|resp| resp.data?.get("count")?.as_u64())
+            } else {
+                None
+            };
+            (selector, count)
+        })
+        .collect();
+    let missing: Vec<&str> = checks
+        .iter()
+        .filter(|(_, count)| *count == Some(0))
+        .map(|(selector, _)| *selector)
+        .collect();
+
+    if flags.json {
+        let selectors: Vec<_> = checks
+            .iter()
+            .map(|(selector, count)| json!({ "selector": selector, "count": count }))
+            .collect();
+        let data = json!({ "dryRun": true, "command": cmd, "selectors": selectors });
+        let error = (!missing.is_empty())
+            .then(|| format!("No elements match: {}", missing.join(", ")));
+        println!("{}", json!({ "success": missing.is_empty(), "data": data, "error": error }));
+    } else {
+        let action = cmd.get("action").and_then(|v| v.as_str()).unwrap_or_default();
+        println!("{} Dry run, not executed: {}", color::warning_indicator(), color::bold(action));
+        println!("{}", serde_json::to_string_pretty(cmd).unwrap_or_default());
+        for (selector, count) in &checks {
+            match count {
+                Some(0) => println!("{} {} matches no elements", color::error_indicator(), selector),
+                Some(n) => println!("{} {} matches {} element(s)", color::success_indicator(), selector, n),
+                None => println!("{} {} not checked (no running browser)", color::warning_indicator(), selector),
+            }
+        }
+    }
+    if !missing.is_empty() {
+        exit(1);
+    }
+}
+
+/// Exit with an error if the command is blocked by the destructive-action policy
+/// and wasn't confirmed with the matching --confirm token
+fn enforce_policy(cmd: &serde_json::Value, flags: &Flags) {
+    let policy = match flags.policy {
+        Some(ref path) => policy::load_policy(path),
+        None => Ok(policy::Policy::builtin()),
+    };
+    let policy = policy.unwrap_or_else(|msg| fail(flags, msg, None));
+
+    // Element text can only be read from a browser that is already running
+    let element_text = policy::text_target(&policy, cmd)
+        .filter(|_| is_daemon_running(&flags.session))
+        .and_then(|selector| {
+            let probe = json!({ "id": gen_id(), "action": "gettext", "selector": selector });
+            let resp = send_command(probe, &flags.session).ok().filter(|r| r.success)?;
+            Some(resp.data?.get("text")?.as_str()?.to_string())
+        });
+
+    if let Some(violation) = policy::check(&policy, cmd, element_text.as_deref()) {
+        if flags.confirm.as_deref() == Some(violation.token.as_str()) {
+            return;
+        }
+        if flags.json {
+            let output = json!({
+                "success": false,
+                "error": violation.message(),
+                "type": "confirmation_required",
+                "confirmToken": violation.token,
+            });
+            println!("{}", output);
+        } else {
+            eprintln!("{} {}", color::error_indicator(), violation.message());
+        }
+        exit(1);
+    }
+}
+
+fn unknown_help_topic(command: &str, json_mode: bool) -> ! {
+    let msg = format!("Unknown command: {}", command);
+    if json_mode {
+        println!("{}", json!({ "success": false, "error": msg }));
+    } else {
+        eprintln!("{} {}", color::error_indicator(), msg);
+    }
+    exit(1);
+}
+
+fn main() {
+    // Ignore SIGPIPE to prevent panic when piping to head/tail
+    #[cfg(unix)]
+    unsafe {
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    // A detached background job: run the command and record how it ends
+    if let Ok(id) = env::var(jobs::RUN_ENV) {
+        exit(jobs::supervise(&id, &args));
+    }
+
+    let mut flags = parse_flags(&args);
+    let clean = clean_args(&args);
+    // The token goes with every command to the daemon, those of `run` scripts included
+    if let Some(ref token) = flags.token {
+        env::set_var(connection::TOKEN_ENV, token);
+    }
+
+    // Arguments after `--` are positional and never treated as flags
+    let flag_args = &args[..flag_end(&args)];
+    let has_help = flag_args.iter().any(|a| a == "--help" || a == "-h");
+    let has_version = flag_args.iter().any(|a| a == "--version" || a == "-V");
+
+    // Handle workspace management before a workspace is applied
+    if clean.first().map(|s| s.as_str()) == Some("workspace") && !has_help {
+        run_workspace(&clean, &flags);
+        return;
+    }
+
+    // Scope sockets, state, logs, downloads and config to --state-dir, or
+    // else to the workspace
+    let registry = workspace::Registry::load();
+    if let Some(dir) = flags.state_dir.as_deref().filter(|d| !d.is_empty()) {
+        match workspace::prepare_state_dir(std::path::Path::new(dir)) {
+            Ok(root) => workspace::activate_state_dir(&root),
+            Err(msg) => fail(&flags, msg, Some("invalid_state_dir")),
+        }
+    } else if let Some(name) = flags.workspace.as_deref().or(registry.active.as_deref()) {
+        match registry.root(name) {
+            Ok(root) => workspace::activate(root),
+            Err(msg) => fail(&flags, msg, None),
+        }
+    }
+
+    let session_ok = if flags.unicode_sessions {
+        is_valid_unicode_session_name(&flags.session)
+    } else {
+        is_valid_session_name(&flags.session)
+    };
+    if !session_ok {
+        let msg = session_name_error(&flags.session);
+        fail(&flags, msg, None);
+    }
+    // Everything below (sockets, state, logs) uses the file-safe form
+    if flags.unicode_sessions {
+        flags.session = encode_session_name(&flags.session);
+    }
+
+    if flag_args.iter().any(|a| a == "--help-json") {
+        let command = clean.first().map(|s| s.as_str()).filter(|c| *c != "help");
+        let command = command.or_else(|| clean.get(1).map(|s| s.as_str()));
+        if !print_help_json(command) {
+            unknown_help_topic(command.unwrap_or_default(), true);
+        }
+        return;
+    }
+
+    let output_format = match flags.output_format.as_deref() {
+        None => OutputFormat::default(),
+        Some(s) => OutputFormat::parse(s).unwrap_or_else(|| {
+            let msg = format!("Invalid --output-format '{}': expected v1 or v2", s);
+            fail(&flags, msg, None);
+        }),
+    };
+
+    if let Some(ref name) = flags.schema {
+        match registry::find_command(name) {
+            Some(spec) => {
+                let schema = schema::response_schema(spec, output_format);
+                println!("{}", serde_json::to_string_pretty(&schema).unwrap_or_default());
+            }
+            None => unknown_help_topic(name, flags.json),
+        }
+        return;
+    }
+
+    // `help [command]` is an alias for `[command] --help`
+    if clean.first().map(|s| s.as_str()) == Some("help") {
+        let topic = clean.get(1).map(|s| s.as_str());
+        if flags.json {
+            if !print_help_json(topic) {
+                unknown_help_topic(topic.unwrap_or_default(), true);
+            }
+            return;
+        }
+        match topic {
+            Some(cmd) if !print_command_help(cmd) => unknown_help_topic(cmd, false),
+            Some(_) => {}
+            None => print_help(),
+        }
+        return;
+    }
+
+    if has_help {
+        if let Some(cmd) = clean.first() {
+            if print_command_help(cmd) {
+                return;
+            }
+        }
+        print_help();
+        return;
+    }
+
+    if has_version {
+        print_version();
+        return;
+    }
+
+    if !flags.errors.is_empty() {
+        for msg in &flags.errors {
+            if flags.json {
+                println!("{}", json!({ "success": false, "error": msg }));
+            } else {
+                eprintln!("{} {}", color::error_indicator(), msg);
+            }
+        }
+        if !flags.json && flags.errors.iter().any(|e| e.starts_with("Unknown flag")) {
+            eprintln!("Use --no-strict to ignore unknown flags");
+        }
+        exit(1);
+    }
+
     if clean.is_empty() {
         print_help();
         return;
     }
 
-    // Handle install separately
-    if clean.get(0).map(|s| s.as_str()) == Some("install") {
-        let with_deps = args.iter().any(|a| a == "--with-deps" || a == "-d");
-        run_install(with_deps);
-        return;
+    // Commands that run here rather than as a single daemon command
+    match clean[0].as_str() {
+        // Jobs started with --detach are read from the jobs directory; the
+        // daemon answers for jobs queued with --no-wait
+        "job" if run_job(&clean, &flags) => {}
+        // Arms are tried in order: anything else with --detach becomes a job
+        _ if flags.detach => run_detached(&args, &flags),
+        "install" => run_install(args.iter().any(|a| a == "--with-deps" || a == "-d")),
+        "session" => run_session(&clean, &flags),
+        // Reads the log, except `audit security`, which inspects the page
+        "audit" if clean.get(1).map(|s| s.as_str()) != Some("security") => run_audit(&clean, &flags),
+        "memo" => run_memo(&clean, &flags),
+        "token" => run_token(&clean, &flags),
+        "history" => run_history(&clean, &flags),
+        "transcript" => run_transcript(&clean, &flags),
+        "checkpoint" => run_checkpoint(&clean, &flags),
+        "auth" => run_auth(&clean, &flags),
+        "inbox" => run_inbox(&clean, &flags),
+        "watch" => run_watch(&clean, &flags),
+        "observe" => run_observe(&clean, &flags),
+        "schedule" => run_schedule(&clean, &flags),
+        "run" => run_script(&clean, &args, &flags),
+        "map" => run_map(&clean, &args, &flags),
+        "artifacts" => run_artifacts(&clean, &flags),
+        "state" if clean.get(1).map(|s| s.as_str()) == Some("gc") => run_state_gc(&clean, &flags),
+        "capabilities" => {
+            let manifest = registry::manifest();
+            println!("{}", serde_json::to_string_pretty(&manifest).unwrap_or_default());
+        }
+        "env" | "doctor" => run_env(&flags),
+        "self-update" => run_self_update(&clean, &flags),
+        "serve" => run_serve(flags),
+        "completions" => run_completions(&clean, &flags),
+        _ => run_command(clean, flags, output_format),
     }
+}
 
-    // Handle session separately (doesn't need daemon)
-    if clean.get(0).map(|s| s.as_str()) == Some("session") {
-        run_session(&clean, &flags.session, flags.json);
-        return;
+/// Parse the command, check it against the flags and policies, and send it
+/// to the session's daemon, starting one if needed
+fn run_command(mut clean: Vec<String>, mut flags: Flags, output_format: OutputFormat) {
+    // `redo <n>` sends a past command again, through the same checks as the first time
+    let redo = (clean.first().map(|s| s.as_str()) == Some("redo")).then(|| redo_command(&clean, &flags));
+    if let Some((ref words, _)) = redo {
+        clean = words.clone();
     }
 
-    let cmd = match parse_command(&clean, &flags) {
+    let parsed = match redo {
+        Some((_, cmd)) => Ok(cmd),
+        None => parse_command(&clean, &flags),
+    };
+    let mut cmd = match parsed {
         Ok(c) => c,
         Err(e) => {
             if flags.json {
@@ -181,11 +2626,12 @@ fn main() {
                     ParseError::InvalidValue { .. } => "invalid_value",
                     ParseError::InvalidSessionName { .. } => "invalid_session_name",
                 };
-                println!(
-                    r#"{{"success":false,"error":"{}","type":"{}"}}"#,
-                    e.format().replace('\n', " "),
-                    error_type
-                );
+                let output = json!({
+                    "success": false,
+                    "error": e.format().replace('\n', " "),
+                    "type": error_type,
+                });
+                println!("{}", output);
             } else {
                 eprintln!("{}", color::red(&e.format()));
             }
@@ -193,39 +2639,465 @@ fn main() {
         }
     };
 
-    let daemon_result = match ensure_daemon(
-        &flags.session,
-        flags.headed,
-        flags.executable_path.as_deref(),
-        &flags.extensions,
-        flags.args.as_deref(),
-        flags.user_agent.as_deref(),
-        flags.proxy.as_deref(),
-        flags.proxy_bypass.as_deref(),
-        flags.session_name.as_deref(),
-    ) {
-        Ok(result) => result,
-        Err(e) => {
+    if let Some(ca_path) = flags.extra_ca.clone() {
+        if let Err(msg) = validate_extra_ca(&ca_path) {
+            fail(&flags, msg, None);
+        }
+        // The daemon reads the file from its own working directory
+        if let Ok(absolute) = fs::canonicalize(&ca_path) {
+            flags.extra_ca = Some(absolute.to_string_lossy().to_string());
+        }
+    }
+
+    // A browser channel is launched as the executable it was found at
+    if let Some(channel) = flags.channel.clone() {
+        let resolved = match flags.executable_path {
+            Some(_) => Err("--channel cannot be combined with --executable-path".to_string()),
+            None => channels::resolve(&channel),
+        };
+        match resolved {
+            Ok(path) => flags.executable_path = Some(path.to_string_lossy().to_string()),
+            Err(msg) => fail(&flags, msg, None),
+        }
+    }
+
+    let host_rules = hosts::HostRules::from_flags(
+        flags.allow_hosts.as_deref(),
+        flags.block_hosts.as_deref(),
+    )
+    .unwrap_or_else(|msg| fail(&flags, msg, None));
+    let host_resolver_rules = hosts::resolver_rules_switch(&flags.host_mappings)
+        .and_then(|rules| {
+            hosts::dns_options(flags.ip_family.as_deref(), flags.dns_over_https.as_deref()).map(|dns| (rules, dns))
+        })
+        .and_then(|(rules, dns)| match (&dns, &flags.proxy) {
+            (Some(_), Some(_)) => Err("--ip-family and --dns-over-https cannot be combined with --proxy".to_string()),
+            _ => Ok((rules, dns)),
+        })
+        .and_then(|(rules, dns)| {
+            let pool = proxy_pool::load(flags.proxy_pool.as_deref(), flags.proxy_rotate.as_deref())?;
+            match (&pool, &flags.proxy, &dns) {
+                (Some(_), Some(_), _) => Err("--proxy-pool cannot be combined with --proxy".to_string()),
+                (Some(_), _, Some(_)) => {
+                    Err("--proxy-pool cannot be combined with --ip-family or --dns-over-https".to_string())
+                }
+                _ => Ok((rules, dns, pool)),
+            }
+        });
+    let (host_resolver_rules, dns_options, proxy_pool) = host_resolver_rules.unwrap_or_else(|msg| fail(&flags, msg, None));
+    let headless_mode = match flags.headless_mode.as_deref() {
+        None => Ok(None),
+        Some(_) if flags.headed => Err("--headless-mode cannot be combined with --headed".to_string()),
+        Some(mode @ ("new" | "old" | "shell")) => Ok(Some(mode.to_string())),
+        Some(other) => Err(format!("Invalid --headless-mode '{}': use new, old or shell", other)),
+    };
+    let headless_mode = headless_mode.unwrap_or_else(|msg| fail(&flags, msg, None));
+    // A pooled browser runs remotely, so local launch options don't apply
+    let browser_pool = browser_pool::parse(flags.pool.as_deref(), flags.pool_capabilities.as_deref()).and_then(|pool| {
+        let local_only = [
+            flags.cdp.as_ref().map(|_| "--cdp"),
+            flags.provider.as_ref().map(|_| "-p"),
+            flags.profile.as_ref().map(|_| "--profile"),
+            if flags.extensions.is_empty() { None } else { Some("--extension") },
+            flags.channel.as_ref().map(|_| "--channel"),
+            flags.executable_path.as_ref().map(|_| "--executable-path").filter(|_| flags.channel.is_none()),
+            flags.headless_mode.as_ref().map(|_| "--headless-mode"),
+            dns_options.as_ref().map(|_| "--ip-family/--dns-over-https"),
+            proxy_pool.as_ref().map(|_| "--proxy-pool"),
+        ];
+        match local_only.into_iter().flatten().next() {
+            Some(flag) if pool.is_some() => Err(format!("--pool cannot be combined with {}", flag)),
+            _ => Ok(pool),
+        }
+    });
+    let browser_pool = browser_pool.unwrap_or_else(|msg| fail(&flags, msg, None));
+    // The daemon watches the memory of a browser it launched itself
+    let max_memory = flags.max_memory.as_deref().map(|size| budget::parse_size("--max-memory", size)).transpose().and_then(|limit| {
+        let remote = [
+            flags.cdp.as_ref().map(|_| "--cdp"),
+            flags.provider.as_ref().map(|_| "-p"),
+            browser_pool.as_ref().map(|_| "--pool"),
+            flags.profile.as_ref().map(|_| "--profile"),
+            if flags.extensions.is_empty() { None } else { Some("--extension") },
+        ];
+        match remote.into_iter().flatten().next() {
+            Some(flag) if limit.is_some() => Err(format!("--max-memory cannot be combined with {}", flag)),
+            _ => Ok(limit),
+        }
+    });
+    let max_memory = max_memory.unwrap_or_else(|msg| fail(&flags, msg, None));
+    let janitor_rules = flags.janitor.as_deref().map(janitor::parse_rules).transpose().unwrap_or_else(|msg| fail(&flags, msg, None));
+    // An ephemeral session only runs in a browser it launched itself, and
+    // never joins a daemon that keeps state
+    if flags.ephemeral {
+        let persistent = [
+            flags.profile.as_ref().map(|_| "--profile"),
+            flags.session_name.as_ref().map(|_| "--session-name"),
+            flags.rotate_profile.as_ref().map(|_| "--rotate-profile"),
+            flags.cdp.as_ref().map(|_| "--cdp"),
+            flags.provider.as_ref().map(|_| "-p"),
+            browser_pool.as_ref().map(|_| "--pool"),
+        ];
+        let error = match persistent.into_iter().flatten().next() {
+            Some(flag) => Some(format!("--ephemeral cannot be combined with {}", flag)),
+            None if is_daemon_running(&flags.session) && !is_ephemeral_daemon(&flags.session) => Some(format!(
+                "Session {} is already running without --ephemeral. Close it or use another --session.",
+                decode_session_name(&flags.session)
+            )),
+            None => None,
+        };
+        if let Some(msg) = error {
+            fail(&flags, msg, None);
+        }
+    }
+    // Read-only is enforced by the daemon, so a running one must have it too
+    if flags.read_only && is_daemon_running(&flags.session) && !is_read_only_daemon(&flags.session) {
+        let msg = format!(
+            "Session {} is already running without --read-only. Close it or use another --session.",
+            decode_session_name(&flags.session)
+        );
+        fail(&flags, msg, None);
+    }
+
+    if cmd.get("action").and_then(|v| v.as_str()) == Some("navigate") {
+        let url = cmd.get("url").and_then(|v| v.as_str()).unwrap_or_default();
+        if let Err(violation) = host_rules.check_url(url) {
+            let msg = format!("Navigation blocked: {}", violation.reason);
             if flags.json {
-                println!(r#"{{"success":false,"error":"{}"}}"#, e);
+                let output = json!({
+                    "success": false,
+                    "error": msg,
+                    "type": "host_blocked",
+                    "host": violation.host,
+                });
+                println!("{}", output);
             } else {
-                eprintln!("{} {}", color::error_indicator(), e);
+                eprintln!("{} {}", color::error_indicator(), msg);
             }
             exit(1);
         }
+    }
+
+    let transfer_policy = transfers::TransferPolicy::from_flags(
+        flags.max_download.as_deref(),
+        flags.block_download_types.as_deref(),
+        flags.upload_dirs.as_deref(),
+    )
+    .unwrap_or_else(|msg| fail(&flags, msg, None));
+
+    if let Err(violation) = transfer_policy.check_upload(&cmd) {
+        if flags.json {
+            let output = json!({
+                "success": false,
+                "error": violation.reason,
+                "type": "upload_blocked",
+                "file": violation.file,
+            });
+            println!("{}", output);
+        } else {
+            eprintln!("{} {}", color::error_indicator(), violation.reason);
+        }
+        exit(1);
+    }
+    transfer_policy.apply_download_limits(&mut cmd);
+    workspace::apply_download_dir(&mut cmd);
+    // With --artifacts, a screenshot without a path goes straight to the store
+    let mut scratch = None;
+    if flags.artifacts && cmd["action"] == "screenshot" && cmd["path"].is_null() {
+        let path = artifacts::scratch_path();
+        if let Some(dir) = path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        cmd["path"] = json!(path.to_string_lossy());
+        scratch = Some(path);
+    }
+    // text --ocr reads the screenshot the daemon sends back
+    let ocr_lang = cmd["ocr"]["lang"].as_str().map(String::from);
+    // text has the daemon save the page to its temp dir if it's a PDF, removed once read
+    let pdf_pages = cmd["pages"].as_str().and_then(|spec| pdf::parse_pages(spec).ok());
+    if cmd["action"] == "text" {
+        cmd["savePdf"] = json!(true);
+    }
+    // Regions and patterns the content hash of text and HTML leaves out
+    if matches!(cmd["action"].as_str(), Some("text" | "gettext" | "innerhtml")) {
+        if let Some(ignore) = config::load_config().ok().and_then(|c| c.content_hash.command_field()) {
+            cmd["hashIgnore"] = ignore;
+        }
+    }
+    // --scroll-margin keeps clicked and scrolled-to elements clear of sticky headers
+    if let Some(ref margin) = flags.scroll_margin {
+        let px = margin.trim().parse::<u32>().unwrap_or_else(|_| {
+            let msg = format!("Invalid --scroll-margin '{}': expected pixels, e.g. 80", margin);
+            fail(&flags, msg, None);
+        });
+        if px > 0 && matches!(cmd["action"].as_str(), Some("click" | "scrollintoview")) {
+            cmd["scrollMargin"] = json!(px);
+        }
+    }
+    // text --detect-lang / --translate-to work on the text once it's read
+    let detect_lang = cmd["detectLang"] == true;
+    let translation = cmd["translateTo"].as_str().map(|target| {
+        let config = config::load_config().map(|c| c.translator).unwrap_or(None);
+        let translator = language::Translator::configured(config).unwrap_or_else(|| {
+            let msg = "--translate-to needs a translation backend: set AGENT_BROWSER_TRANSLATOR to a URL or command, or translator in config.json";
+            fail(&flags, msg, None);
+        });
+        (translator, target.to_string())
+    });
+    // --upload needs a file, so a screenshot without a path is saved to the temp dir
+    let upload_target = flags.upload.as_deref().and_then(|url| upload::Target::parse(url).ok());
+    if upload_target.is_some() && cmd["action"] == "screenshot" && cmd["path"].is_null() {
+        let name = format!("screenshot-{}.png", gen_id());
+        cmd["path"] = json!(env::temp_dir().join(name).to_string_lossy());
+    }
+
+    let network_budget = budget::Budget::from_flags(
+        flags.max_bytes.as_deref(),
+        flags.max_requests.as_deref(),
+        flags.budget_scope.as_deref(),
+    )
+    .unwrap_or_else(|msg| fail(&flags, msg, None));
+    if let Some(ref network_budget) = network_budget {
+        network_budget.apply(&mut cmd);
+    }
+    if let Some(ref list) = flags.block {
+        let types = blocking::parse_resource_types(list).unwrap_or_else(|msg| fail(&flags, msg, None));
+        blocking::apply(&types, &mut cmd);
+    }
+    // The daemon answers reads of the same page from its cache within the TTL
+    if let Some(ref ttl) = flags.cache {
+        let ttl = state_gc::parse_duration(ttl).unwrap_or_else(|msg| fail(&flags, format!("--cache: {}", msg), None));
+        cmd["cache"] = json!(ttl.as_millis() as u64);
+    }
+
+    // Rotate browser identity between navigations when a profile is configured
+    if let Some(ref profile_path) = flags.rotate_profile {
+        if cmd.get("action").and_then(|v| v.as_str()) == Some("navigate") {
+            match rotation::load_profile(profile_path) {
+                Ok(profile) => {
+                    let entry = rotation::next_entry(&flags.session, &profile);
+                    rotation::apply_entry(&mut cmd, entry);
+                }
+                Err(msg) => fail(&flags, msg, None),
+            }
+        }
+    }
+
+    // state migrate takes --dry-run itself, to report what would change
+    if flags.dry_run && cmd["action"] != "state_migrate" {
+        run_dry_run(&cmd, &flags);
+        return;
+    }
+
+    if flags.confirm_destructive || flags.policy.is_some() {
+        enforce_policy(&cmd, &flags);
+    }
+
+    // Expire stale state before a new daemon can load it
+    if let Some(ref ttl) = flags.state_ttl {
+        match state_gc::parse_duration(ttl) {
+            Ok(ttl) if !is_daemon_running(&flags.session) => {
+                let now = std::time::SystemTime::now();
+                let _ = state_gc::collect(&state_gc::sessions_dir(), ttl, now, false);
+            }
+            Ok(_) => {}
+            Err(msg) => fail(&flags, msg, None),
+        }
+    }
+
+    if let Some(Err(msg)) = flags.save_state_interval.as_deref().map(state_gc::parse_duration) {
+        fail(&flags, format!("--save-state-interval: {}", msg), None);
+    }
+
+    // Check --cdp before starting anything; auto and /json/version are resolved when connecting
+    let cdp_endpoint = flags.cdp.as_deref().map(|value| {
+        cdp::parse_endpoint(value).unwrap_or_else(|msg| fail(&flags, msg, None))
+    });
+
+    // A template configures the session's browser when it starts
+    let template = flags.template.as_deref().map(|name| {
+        config::load_config().and_then(|config| config.into_template(name)).unwrap_or_else(|msg| fail(&flags, msg, None))
+    });
+    if let Some(ref template) = template {
+        if !is_daemon_running(&flags.session) {
+            template.apply_launch_flags(&mut flags);
+        }
+    }
+    // browserArgs from the config file go first, so templates and flags can add to them
+    let mut lifecycle_hooks = hooks::Hooks::default();
+    match config::load_config() {
+        Ok(config) => {
+            if !is_daemon_running(&flags.session) {
+                flags.browser_args.splice(0..0, config.browser_args);
+            }
+            lifecycle_hooks = config.hooks;
+        }
+        Err(msg) if !flags.json => eprintln!("{} {}", color::warning_indicator(), msg),
+        Err(_) => {}
+    }
+    // Chromium inside a container needs its sandbox and /dev/shm switches
+    let local_launch = flags.cdp.is_none() && flags.provider.is_none() && browser_pool.is_none();
+    if local_launch && !is_daemon_running(&flags.session) && container::enabled(flags.containerized) {
+        container::apply(&mut flags.browser_args);
+        if let Some(advice) = container::shm_size().and_then(container::shm_advice) {
+            if !flags.json {
+                eprintln!("{} {}", color::warning_indicator(), advice);
+            }
+        }
+    }
+
+    // Launch headed browser or configure browser options (without CDP or provider),
+    // sent once the daemon runs
+    let launch_cmd = if (flags.headed || flags.profile.is_some() || flags.proxy.is_some() || flags.args.is_some() || !flags.browser_args.is_empty() || host_resolver_rules.is_some() || dns_options.is_some() || proxy_pool.is_some() || browser_pool.is_some() || max_memory.is_some() || janitor_rules.is_some() || headless_mode.is_some() || flags.user_agent.is_some() || flags.ignore_https_errors || flags.extra_ca.is_some() || !host_rules.is_empty()) && flags.cdp.is_none() && flags.provider.is_none() {
+        let mut launch_cmd = json!({
+            "id": gen_id(),
+            "action": "launch",
+            "headless": !flags.headed
+        });
+
+        let cmd_obj = launch_cmd.as_object_mut()
+            .expect("json! macro guarantees object type");
+
+        // Add profile path if specified
+        if let Some(ref profile_path) = flags.profile {
+            cmd_obj.insert("profile".to_string(), json!(workspace::resolve_profile(profile_path)));
+        }
+
+        // Set from --executable-path or --channel
+        if let Some(ref path) = flags.executable_path {
+            cmd_obj.insert("executablePath".to_string(), json!(path));
+        }
+
+        if let Some(ref proxy_str) = flags.proxy {
+            let mut proxy_obj = parse_proxy(proxy_str);
+            // Add bypass if specified
+            if let Some(ref bypass) = flags.proxy_bypass {
+                if let Some(obj) = proxy_obj.as_object_mut() {
+                    obj.insert("bypass".to_string(), json!(bypass));
+                }
+            }
+            cmd_obj.insert("proxy".to_string(), proxy_obj);
+        }
+
+        if let Some(ref ua) = flags.user_agent {
+            cmd_obj.insert("userAgent".to_string(), json!(ua));
+        }
+
+        if flags.ignore_https_errors {
+            cmd_obj.insert("ignoreHTTPSErrors".to_string(), json!(true));
+        }
+
+        if let Some(ref ca_path) = flags.extra_ca {
+            cmd_obj.insert("extraCaCerts".to_string(), json!(ca_path));
+        }
+
+        // Chromium version checks happen in the daemon, which knows the binary
+        if let Some(ref mode) = headless_mode {
+            cmd_obj.insert("headlessMode".to_string(), json!(mode));
+        }
+
+        // Resolved by a proxy in the daemon, so it can't be combined with --proxy
+        if let Some(ref dns) = dns_options {
+            cmd_obj.insert("dns".to_string(), dns.clone());
+        }
+
+        // Forwarded through the pool by the same proxy in the daemon
+        if let Some(ref pool) = proxy_pool {
+            cmd_obj.insert("proxyPool".to_string(), pool.clone());
+        }
+
+        // Acquired by the daemon instead of launching a local browser
+        if let Some(ref pool) = browser_pool {
+            cmd_obj.insert("pool".to_string(), pool.clone());
+        }
+
+        // Sampled by the daemon, which restarts the browser between commands
+        if let Some(limit) = max_memory {
+            cmd_obj.insert("maxMemory".to_string(), json!(limit));
+        }
+
+        if let Some(ref rules) = janitor_rules {
+            cmd_obj.insert("janitor".to_string(), json!(rules));
+        }
+
+        // Enforced by the daemon for navigations, subresources and fetches
+        if !host_rules.allow.is_empty() {
+            cmd_obj.insert("allowHosts".to_string(), json!(host_rules.allow));
+        }
+        if !host_rules.block.is_empty() {
+            cmd_obj.insert("blockHosts".to_string(), json!(host_rules.block));
+        }
+
+        // --args is split on commas and newlines; --browser-arg values are kept whole
+        let mut args_vec: Vec<String> = flags
+            .args
+            .iter()
+            .flat_map(|a| a.split(&[',', '\n'][..]))
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        args_vec.extend(flags.browser_args.iter().cloned());
+        args_vec.extend(host_resolver_rules.clone());
+        if !args_vec.is_empty() {
+            cmd_obj.insert("args".to_string(), json!(args_vec));
+        }
+
+        Some(launch_cmd)
+    } else {
+        None
+    };
+
+    // `daemon warm` starts daemons for this launch configuration instead of using the session
+    if cmd.get("action").and_then(|v| v.as_str()) == Some("daemon_warm") {
+        let count = cmd.get("count").and_then(|v| v.as_u64()).unwrap_or(1) as usize;
+        run_daemon_warm(&flags, launch_cmd.as_ref(), count);
+        return;
+    }
+
+    // Job queries answer while other invocations hold the session
+    let job_query = matches!(
+        cmd.get("action").and_then(|v| v.as_str()),
+        Some("job_status") | Some("job_result") | Some("job_list") | Some("job_cancel")
+    );
+    let _lock = (!job_query).then(|| lock_session(&flags, &clean));
+
+    // A warm daemon started for the same launch configuration skips the cold start
+    let daemon_result = if warm::claim(&flags, launch_cmd.as_ref()) {
+        Ok(connection::DaemonResult { already_running: false })
+    } else {
+        ensure_daemon(&flags)
+    };
+    let daemon_result = match daemon_result {
+        Ok(result) => result,
+        Err(e) => fail(&flags, e, None),
     };
 
     // Warn if launch-time options were specified but daemon was already running
     if daemon_result.already_running {
         let has_extensions = !flags.extensions.is_empty();
         let ignored_flags: Vec<&str> = [
-            flags.executable_path.as_ref().map(|_| "--executable-path"),
+            flags.channel.as_ref().map(|_| "--channel"),
+            flags.executable_path.as_ref().map(|_| "--executable-path").filter(|_| flags.channel.is_none()),
             if has_extensions { Some("--extension") } else { None },
             flags.profile.as_ref().map(|_| "--profile"),
             flags.args.as_ref().map(|_| "--args"),
+            if flags.browser_args.is_empty() { None } else { Some("--browser-arg") },
+            if flags.host_mappings.is_empty() { None } else { Some("--host-rule") },
+            flags.headless_mode.as_ref().map(|_| "--headless-mode"),
+            flags.ip_family.as_ref().map(|_| "--ip-family"),
+            flags.dns_over_https.as_ref().map(|_| "--dns-over-https"),
+            flags.proxy_pool.as_ref().map(|_| "--proxy-pool"),
+            flags.pool.as_ref().map(|_| "--pool"),
+            flags.max_memory.as_ref().map(|_| "--max-memory"),
+            flags.janitor.as_ref().map(|_| "--janitor"),
             flags.user_agent.as_ref().map(|_| "--user-agent"),
             flags.proxy.as_ref().map(|_| "--proxy"),
             flags.proxy_bypass.as_ref().map(|_| "--proxy-bypass"),
+            if flags.ignore_https_errors { Some("--ignore-https-errors") } else { None },
+            flags.extra_ca.as_ref().map(|_| "--extra-ca"),
+            flags.allow_hosts.as_ref().map(|_| "--allow-hosts"),
+            flags.block_hosts.as_ref().map(|_| "--block-hosts"),
+            flags.template.as_ref().map(|_| "--template"),
         ]
         .into_iter()
         .flatten()
@@ -243,82 +3115,22 @@ fn main() {
     // Validate mutually exclusive options
     if flags.cdp.is_some() && flags.provider.is_some() {
         let msg = "Cannot use --cdp and -p/--provider together";
-        if flags.json {
-            println!(r#"{{"success":false,"error":"{}"}}"#, msg);
-        } else {
-            eprintln!("\x1b[31m✗\x1b[0m {}", msg);
-        }
-        exit(1);
+        fail(&flags, msg, None);
     }
 
     if flags.provider.is_some() && !flags.extensions.is_empty() {
         let msg = "Cannot use --extension with -p/--provider (extensions require local browser)";
-        if flags.json {
-            println!(r#"{{"success":false,"error":"{}"}}"#, msg);
-        } else {
-            eprintln!("\x1b[31m✗\x1b[0m {}", msg);
-        }
-        exit(1);
+        fail(&flags, msg, None);
     }
 
     // Connect via CDP if --cdp flag is set
-    // Accepts either a port number (e.g., "9222") or a full URL (e.g., "ws://..." or "wss://...")
-    if let Some(ref cdp_value) = flags.cdp {
-        let launch_cmd = if cdp_value.starts_with("ws://")
-            || cdp_value.starts_with("wss://")
-            || cdp_value.starts_with("http://")
-            || cdp_value.starts_with("https://")
-        {
-            // It's a URL - use cdpUrl field
-            json!({
-                "id": gen_id(),
-                "action": "launch",
-                "cdpUrl": cdp_value
-            })
-        } else {
-            // It's a port number - validate and use cdpPort field
-            let cdp_port: u16 = match cdp_value.parse::<u32>() {
-                Ok(p) if p == 0 => {
-                    let msg = "Invalid CDP port: port must be greater than 0".to_string();
-                    if flags.json {
-                        println!(r#"{{"success":false,"error":"{}"}}"#, msg);
-                    } else {
-                        eprintln!("{} {}", color::error_indicator(), msg);
-                    }
-                    exit(1);
-                }
-                Ok(p) if p > 65535 => {
-                    let msg = format!(
-                        "Invalid CDP port: {} is out of range (valid range: 1-65535)",
-                        p
-                    );
-                    if flags.json {
-                        println!(r#"{{"success":false,"error":"{}"}}"#, msg);
-                    } else {
-                        eprintln!("{} {}", color::error_indicator(), msg);
-                    }
-                    exit(1);
-                }
-                Ok(p) => p as u16,
-                Err(_) => {
-                    let msg = format!(
-                        "Invalid CDP value: '{}' is not a valid port number or URL",
-                        cdp_value
-                    );
-                    if flags.json {
-                        println!(r#"{{"success":false,"error":"{}"}}"#, msg);
-                    } else {
-                        eprintln!("{} {}", color::error_indicator(), msg);
-                    }
-                    exit(1);
-                }
-            };
-            json!({
-                "id": gen_id(),
-                "action": "launch",
-                "cdpPort": cdp_port
-            })
-        };
+    // Accepts a port, host:port, a ws:// or http URL, or "auto" to find a local browser
+    if let Some(ref endpoint) = cdp_endpoint {
+        let fields = cdp::launch_fields(endpoint).unwrap_or_else(|msg| fail(&flags, msg, None));
+        let mut launch_cmd = json!({ "id": gen_id(), "action": "launch" });
+        if let (Some(cmd), Some(fields)) = (launch_cmd.as_object_mut(), fields.as_object()) {
+            cmd.extend(fields.clone());
+        }
 
         let err = match send_command(launch_cmd, &flags.session) {
             Ok(resp) if resp.success => None,
@@ -330,12 +3142,7 @@ fn main() {
         };
 
         if let Some(msg) = err {
-            if flags.json {
-                println!(r#"{{"success":false,"error":"{}"}}"#, msg);
-            } else {
-                eprintln!("{} {}", color::error_indicator(), msg);
-            }
-            exit(1);
+            fail(&flags, msg, None);
         }
     }
 
@@ -354,78 +3161,160 @@ fn main() {
         };
 
         if let Some(msg) = err {
-            if flags.json {
-                println!(r#"{{"success":false,"error":"{}"}}"#, msg);
-            } else {
-                eprintln!("\x1b[31m✗\x1b[0m {}", msg);
-            }
-            exit(1);
+            fail(&flags, msg, None);
         }
     }
 
-    // Launch headed browser or configure browser options (without CDP or provider)
-    if (flags.headed || flags.profile.is_some() || flags.proxy.is_some() || flags.args.is_some() || flags.user_agent.is_some()) && flags.cdp.is_none() && flags.provider.is_none() {
-        let mut launch_cmd = json!({
-            "id": gen_id(),
-            "action": "launch",
-            "headless": !flags.headed
-        });
-
-        let cmd_obj = launch_cmd.as_object_mut()
-            .expect("json! macro guarantees object type");
-
-        // Add profile path if specified
-        if let Some(ref profile_path) = flags.profile {
-            cmd_obj.insert("profile".to_string(), json!(profile_path));
+    if let Some(launch_cmd) = launch_cmd {
+        if let Err(e) = send_command(launch_cmd, &flags.session) {
+            if !flags.json {
+                eprintln!("{} Could not configure browser: {}", color::warning_indicator(), e);
+            }
         }
+    }
 
-        if let Some(ref proxy_str) = flags.proxy {
-            let mut proxy_obj = parse_proxy(proxy_str);
-            // Add bypass if specified
-            if let Some(ref bypass) = flags.proxy_bypass {
-                if let Some(obj) = proxy_obj.as_object_mut() {
-                    obj.insert("bypass".to_string(), json!(bypass));
-                }
+    if let Some(ref template) = template.filter(|_| !daemon_result.already_running) {
+        for setup in template.setup_commands() {
+            let result = send_command(setup, &flags.session);
+            let error = match result {
+                Ok(resp) if resp.success => continue,
+                Ok(resp) => resp.error.unwrap_or_default(),
+                Err(e) => e,
+            };
+            if !flags.json {
+                eprintln!("{} Could not apply template: {}", color::warning_indicator(), error);
             }
-            cmd_obj.insert("proxy".to_string(), proxy_obj);
         }
+    }
 
-        if let Some(ref ua) = flags.user_agent {
-            cmd_obj.insert("userAgent".to_string(), json!(ua));
-        }
+    if !daemon_result.already_running {
+        let data = json!({ "session": flags.session });
+        fire_hooks(&lifecycle_hooks, hooks::Event::SessionStart, &data, &flags);
+    }
 
-        if let Some(ref a) = flags.args {
-            // Parse args (comma or newline separated)
-            let args_vec: Vec<String> = a
-                .split(&[',', '\n'][..])
-                .map(|s| s.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .collect();
-            cmd_obj.insert("args".to_string(), json!(args_vec));
-        }
+    // With --no-wait the daemon queues the command and answers with a job id
+    if flags.no_wait {
+        cmd["noWait"] = json!(true);
+    }
+    // Screenshots and PDFs are written where the CLI can read them, not sent over the socket;
+    // a queued command may run after the handoff directory is gone, so it goes without one
+    let handoff_dir = if flags.no_wait { None } else { handoff::prepare(&mut cmd) };
 
-        if let Err(e) = send_command(launch_cmd, &flags.session) {
+    // Extract action for context-specific output handling
+    let action = cmd.get("action").and_then(|v| v.as_str());
+    let started = Instant::now();
+    let meta = |started: Instant| ResponseMeta {
+        action,
+        session: &flags.session,
+        duration_ms: started.elapsed().as_millis(),
+    };
+    let command_name = clean.first().map(|s| s.as_str()).unwrap_or_default();
+    let notifier = flags
+        .notify_webhook
+        .as_deref()
+        .map(|url| notify::Notifier::new(url, &flags.session, command_name));
+    if let Some(ref notifier) = notifier {
+        warn_notify(notifier.started(), &flags);
+    }
+    let mut result = send_command(handoff::attach(cmd.clone(), handoff_dir.as_deref()), &flags.session);
+    if let Some(dir) = handoff_dir.as_deref() {
+        match result.as_mut() {
+            Ok(resp) => handoff::finish(resp, dir),
+            Err(_) => {
+                let _ = fs::remove_dir_all(dir);
+            }
+        }
+    }
+    if let (Ok(resp), Some(lang)) = (result.as_mut(), ocr_lang.as_deref()) {
+        read_ocr(resp, lang);
+    }
+    if let (Ok(resp), "text") = (result.as_mut(), cmd["action"].as_str().unwrap_or("")) {
+        read_pdf(resp, pdf_pages);
+    }
+    if let (Ok(resp), true) = (result.as_mut(), detect_lang || translation.is_some()) {
+        detect_and_translate(resp, translation.as_ref().map(|(t, to)| (t, to.as_str())));
+    }
+    if let (Ok(resp), true, Some(action)) = (result.as_mut(), flags.artifacts, action) {
+        store_artifact(resp, action, scratch.as_deref(), &flags);
+    }
+    if let (Ok(resp), Some(target), Some(action)) = (result.as_mut(), upload_target.as_ref(), action) {
+        upload_artifact(resp, action, target);
+    }
+    if let Some(ref notifier) = notifier {
+        let outcome = match &result {
+            Ok(resp) if resp.success => Ok(resp.data.as_ref().unwrap_or(&serde_json::Value::Null)),
+            Ok(resp) => Err(resp.error.as_deref().unwrap_or("Unknown error")),
+            Err(e) => Err(e.as_str()),
+        };
+        warn_notify(notifier.finished(outcome), &flags);
+    }
+    if keeps_records(&flags) {
+        if flags.audit {
+            if let Err(e) = audit::record(&flags.session, &cmd, result.as_ref().map_err(|e| e.as_str())) {
+                if !flags.json {
+                    eprintln!("{} {}", color::warning_indicator(), e);
+                }
+            }
+        }
+        if action == Some("close") {
+            history::clear(&flags.session);
+        } else if let Err(e) = history::record(&flags.session, &clean, &cmd, result.as_ref().map_err(|e| e.as_str())) {
             if !flags.json {
-                eprintln!("{} Could not configure browser: {}", color::warning_indicator(), e);
+                eprintln!("{} {}", color::warning_indicator(), e);
             }
         }
     }
-
-    match send_command(cmd.clone(), &flags.session) {
-        Ok(resp) => {
+    match result {
+        Ok(mut resp) => {
             let success = resp.success;
-            // Extract action for context-specific output handling
-            let action = cmd
-                .get("action")
-                .and_then(|v| v.as_str());
-            print_response(&resp, flags.json, action);
+            // Report the URL as normalized by `open` (scheme added, IDN encoded)
+            if action == Some("navigate") {
+                if let Some(data) = resp.data.as_mut().and_then(|d| d.as_object_mut()) {
+                    data.insert("normalizedUrl".to_string(), cmd["url"].clone());
+                }
+            }
+            // Extracted data asked for as CSV goes out as CSV in any output mode
+            if success && cmd.get("format").and_then(|v| v.as_str()) == Some("csv") {
+                let result = resp.data.as_ref().and_then(|d| d.get("result")).unwrap_or(&serde_json::Value::Null);
+                print!("{}", csv::render(result));
+            } else if flags.json && output_format != OutputFormat::V1 {
+                let value = json_response(&resp, output_format, &meta(started));
+                println!("{}", serde_json::to_string(&value).unwrap_or_default());
+            } else {
+                print_response(&resp, flags.json, action);
+            }
+            if let (false, Some(hash)) = (flags.json, resp.data.as_ref().and_then(|d| d["artifact"]["hash"].as_str())) {
+                println!("  Stored as artifact {}", &hash[..12.min(hash.len())]);
+            }
+            if let (false, Some(url)) = (flags.json, resp.data.as_ref().and_then(|d| d["upload"]["url"].as_str())) {
+                println!("  Uploaded to {}", url);
+            }
+            if let (false, Some(gif)) = (flags.json, resp.data.as_ref().map(|d| &d["gif"]).filter(|g| g.is_object())) {
+                println!("  Saved {} ({} frames)", gif["path"].as_str().unwrap_or_default(), gif["frames"]);
+            }
+            if let (true, false, Some(action), Some(data)) = (success, flags.no_wait, action, resp.data.as_ref()) {
+                for (event, data) in hooks::events_for(action, data) {
+                    fire_hooks(&lifecycle_hooks, event, &data, &flags);
+                }
+            }
+            if !lifecycle_hooks.on_dialog.is_empty() {
+                let cmd = json!({ "id": gen_id(), "action": "dialog_events", "clear": true });
+                let events = request(cmd, &flags.session).unwrap_or_default();
+                for dialog in events["events"].as_array().into_iter().flatten() {
+                    fire_hooks(&lifecycle_hooks, hooks::Event::Dialog, dialog, &flags);
+                }
+            }
             if !success {
                 exit(1);
             }
         }
         Err(e) => {
-            if flags.json {
-                println!(r#"{{"success":false,"error":"{}"}}"#, e);
+            if flags.json && output_format != OutputFormat::V1 {
+                let resp = Response { success: false, data: None, error: Some(e), meta: None };
+                let value = json_response(&resp, output_format, &meta(started));
+                println!("{}", serde_json::to_string(&value).unwrap_or_default());
+            } else if flags.json {
+                println!("{}", json!({ "success": false, "error": e }));
             } else {
                 eprintln!("{} {}", color::error_indicator(), e);
             }
@@ -438,6 +3327,21 @@ fn main() {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_ephemeral_keeps_no_records() {
+        let args = |line: &str| line.split_whitespace().map(String::from).collect::<Vec<_>>();
+        let flags = parse_flags(&args("--session keeps-records-test --ephemeral open example.com"));
+        assert!(!keeps_records(&flags));
+        let flags = parse_flags(&args("--session keeps-records-test open example.com"));
+        assert!(keeps_records(&flags));
+    }
+
+    #[test]
+    fn test_error_code() {
+        assert_eq!(error_code("session_busy"), Some("E_SESSION_BUSY"));
+        assert_eq!(error_code("invalid_state_dir"), None);
+    }
+
     #[test]
     fn test_parse_proxy_simple() {
         let result = parse_proxy("http://proxy.com:8080");
@@ -0,0 +1,80 @@
+//! Per-session key/value scratchpad for `memo set/get/list/delete/clear`.
+//!
+//! Multi-invocation workflows stash intermediate values (an order id, a
+//! page number) with `memo set` and read them back with `memo get`, e.g. as
+//! `$(memo get cart_id)` in a script. The values live with the persisted
+//! session state, in `memo/<session>.json` under AGENT_BROWSER_STATE_DIR
+//! (~/.agent-browser/sessions), so they outlast the session's daemon until
+//! cleared.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::state_gc::sessions_dir;
+
+fn memo_path(session: &str) -> PathBuf {
+    sessions_dir().join("memo").join(format!("{}.json", session))
+}
+
+/// Keys are letters, digits, `_`, `-` and `.`
+pub fn valid_key(key: &str) -> bool {
+    !key.is_empty() && key.len() <= 128 && key.chars().all(|c| c.is_ascii_alphanumeric() || "_-.".contains(c))
+}
+
+pub fn load(session: &str) -> Result<BTreeMap<String, String>, String> {
+    load_file(&memo_path(session))
+}
+
+/// Write the session's memo, removing the file once it's empty
+pub fn save(session: &str, values: &BTreeMap<String, String>) -> Result<(), String> {
+    save_file(&memo_path(session), values)
+}
+
+fn load_file(path: &Path) -> Result<BTreeMap<String, String>, String> {
+    match fs::read_to_string(path) {
+        Ok(source) => serde_json::from_str(&source).map_err(|e| format!("Memo file {} is unreadable: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(format!("Cannot read {}: {}", path.display(), e)),
+    }
+}
+
+fn save_file(path: &Path, values: &BTreeMap<String, String>) -> Result<(), String> {
+    if values.is_empty() {
+        let _ = fs::remove_file(path);
+        return Ok(());
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    }
+    let source = serde_json::to_string_pretty(values).unwrap_or_default();
+    fs::write(path, source).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_key() {
+        assert!(valid_key("cart_id"));
+        assert!(valid_key("step-2.result"));
+        assert!(!valid_key(""));
+        assert!(!valid_key("a b"));
+        assert!(!valid_key("../x"));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("ab-memo-test-{}", std::process::id()));
+        let path = dir.join("memo").join("default.json");
+        let mut values = BTreeMap::new();
+        values.insert("cart_id".to_string(), "123".to_string());
+        save_file(&path, &values).unwrap();
+        assert_eq!(load_file(&path).unwrap(), values);
+        save_file(&path, &BTreeMap::new()).unwrap();
+        assert!(!path.exists());
+        assert!(load_file(&path).unwrap().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
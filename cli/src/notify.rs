@@ -0,0 +1,184 @@
+//! Webhook notifications for long-running commands.
+//!
+//! With --notify-webhook <url> (or AGENT_BROWSER_NOTIFY_WEBHOOK), a command
+//! POSTs JSON events to the URL as it runs: `started`, `progress` for each
+//! step of multi-step commands such as `checkpoint restore`, and `completed`
+//! or `failed` at the end, so an orchestrator can wait for the webhook
+//! instead of polling the CLI process. `watch` sends its `change` and `error`
+//! events the same way. Every event carries the session, the
+//! command, a run id shared by all events of one invocation and a timestamp.
+//! Delivery goes through `curl` with a short timeout; an undeliverable event
+//! is a warning, never a failure of the command itself.
+
+use serde_json::{json, Map, Value};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::commands::gen_id;
+
+/// Seconds curl may spend delivering one event
+const TIMEOUT_SECS: &str = "10";
+
+pub fn validate_webhook_url(url: &str) -> Result<(), String> {
+    let lower = url.to_ascii_lowercase();
+    let rest = lower
+        .strip_prefix("https://")
+        .or_else(|| lower.strip_prefix("http://"))
+        .ok_or_else(|| format!("Invalid --notify-webhook '{}': must be an http:// or https:// URL", url))?;
+    if rest.split(['/', '?', '#']).next().unwrap_or_default().is_empty() {
+        return Err(format!("Invalid --notify-webhook '{}': missing host", url));
+    }
+    Ok(())
+}
+
+pub struct Notifier {
+    url: String,
+    session: String,
+    command: String,
+    run_id: String,
+    started: Instant,
+}
+
+impl Notifier {
+    pub fn new(url: &str, session: &str, command: &str) -> Self {
+        Notifier {
+            url: url.to_string(),
+            session: session.to_string(),
+            command: command.to_string(),
+            run_id: gen_id(),
+            started: Instant::now(),
+        }
+    }
+
+    /// Event body: the common fields plus `fields`
+    pub fn payload(&self, event: &str, fields: Value) -> Value {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let mut body = Map::new();
+        body.insert("event".to_string(), json!(event));
+        body.insert("runId".to_string(), json!(self.run_id));
+        body.insert("session".to_string(), json!(self.session));
+        body.insert("command".to_string(), json!(self.command));
+        body.insert("timestamp".to_string(), json!(timestamp));
+        if let Value::Object(fields) = fields {
+            body.extend(fields);
+        }
+        Value::Object(body)
+    }
+
+    pub fn started(&self) -> Result<(), String> {
+        self.send("started", json!({}))
+    }
+
+    /// Step `step` of `total` finished
+    pub fn progress(&self, step: usize, total: usize, detail: &str) -> Result<(), String> {
+        self.send("progress", json!({ "step": step, "total": total, "detail": detail }))
+    }
+
+    /// `completed` or `failed`, with the run's duration and the command's
+    /// data or error
+    pub fn finished(&self, result: Result<&Value, &str>) -> Result<(), String> {
+        let duration_ms = self.started.elapsed().as_millis() as u64;
+        match result {
+            Ok(data) => self.send("completed", json!({ "durationMs": duration_ms, "data": data })),
+            Err(error) => self.send("failed", json!({ "durationMs": duration_ms, "error": error })),
+        }
+    }
+
+    /// Any other event, e.g. `change` from `watch`
+    pub fn send(&self, event: &str, fields: Value) -> Result<(), String> {
+        post_json(&self.url, &self.payload(event, fields))
+    }
+}
+
+/// POST a JSON body with curl
+pub fn post_json(url: &str, body: &Value) -> Result<(), String> {
+    let mut child = Command::new("curl")
+        .args([
+            "-fsS",
+            "-o",
+            if cfg!(windows) { "NUL" } else { "/dev/null" },
+            "--max-time",
+            TIMEOUT_SECS,
+            "-X",
+            "POST",
+            "-H",
+            "Content-Type: application/json",
+            "--data-binary",
+            "@-",
+            url,
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Cannot run curl to notify webhook: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(body.to_string().as_bytes()).map_err(|e| e.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Webhook {} not notified: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn test_validate_webhook_url() {
+        assert!(validate_webhook_url("https://hooks.example.com/ab").is_ok());
+        assert!(validate_webhook_url("http://localhost:8080").is_ok());
+        assert!(validate_webhook_url("ftp://example.com").unwrap_err().contains("http://"));
+        assert!(validate_webhook_url("https:///path").unwrap_err().contains("missing host"));
+    }
+
+    #[test]
+    fn test_payload() {
+        let notifier = Notifier::new("https://hooks.example.com", "ci", "checkpoint");
+        let body = notifier.payload("progress", json!({ "step": 2, "total": 5 }));
+        assert_eq!(body["event"], "progress");
+        assert_eq!(body["session"], "ci");
+        assert_eq!(body["command"], "checkpoint");
+        assert_eq!(body["step"], 2);
+        assert_eq!(body["runId"], notifier.payload("started", json!({}))["runId"]);
+    }
+
+    #[test]
+    fn test_post_json() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            while !String::from_utf8_lossy(&request).contains("\"event\":\"completed\"") {
+                let n = stream.read(&mut buf).unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream.write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n").unwrap();
+            String::from_utf8_lossy(&request).to_string()
+        });
+        let notifier = Notifier::new(&format!("http://127.0.0.1:{}/hook", port), "ci", "wait");
+        notifier.finished(Ok(&json!({ "waited": true }))).unwrap();
+        let request = server.join().unwrap();
+        assert!(request.starts_with("POST /hook "));
+        assert!(request.contains("application/json"));
+    }
+}
@@ -0,0 +1,287 @@
+//! OAuth flows run through the session's browser.
+//!
+//! Redirect capture (authorization code with PKCE): a listener on a loopback
+//! port serves as the redirect URI, the session's browser is sent to the
+//! authorization URL, and once the provider redirects back the code is
+//! exchanged for a token at the token endpoint.
+//!
+//! Device flow: the device authorization endpoint hands out a user code and a
+//! verification URL, which is opened in the session while the token endpoint
+//! is polled until the user (or agent) approves.
+//!
+//! Token requests go through `curl`, like the rest of the CLI's HTTP.
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::transcript::base64_encode;
+
+const DEVICE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Percent-encode everything but RFC 3986 unreserved characters
+pub fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+pub fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                out.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Value of `key` in a query string
+pub fn query_param(query: &str, key: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+        (percent_decode(k) == key).then(|| percent_decode(v))
+    })
+}
+
+fn base64_url(bytes: &[u8]) -> String {
+    base64_encode(bytes).replace('+', "-").replace('/', "_").trim_end_matches('=').to_string()
+}
+
+/// 32 random bytes, base64url encoded (PKCE verifier and state)
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 32];
+    let from_os = std::fs::File::open("/dev/urandom").and_then(|mut f| f.read_exact(&mut bytes));
+    if from_os.is_err() {
+        // No /dev/urandom (Windows): hash what varies between runs
+        let seed = format!("{:?}{}{:p}", std::time::SystemTime::now(), std::process::id(), &bytes);
+        bytes.copy_from_slice(&Sha256::digest(seed.as_bytes()));
+    }
+    base64_url(&bytes)
+}
+
+/// PKCE S256 challenge for a verifier
+pub fn pkce_challenge(verifier: &str) -> String {
+    base64_url(&Sha256::digest(verifier.as_bytes()))
+}
+
+pub struct AuthRequest<'a> {
+    pub auth_url: &'a str,
+    pub client_id: &'a str,
+    pub redirect_uri: &'a str,
+    pub scope: Option<&'a str>,
+    pub state: &'a str,
+    pub challenge: &'a str,
+}
+
+pub fn authorize_url(req: &AuthRequest) -> String {
+    let mut params = vec![
+        ("response_type", "code"),
+        ("client_id", req.client_id),
+        ("redirect_uri", req.redirect_uri),
+        ("state", req.state),
+        ("code_challenge", req.challenge),
+        ("code_challenge_method", "S256"),
+    ];
+    if let Some(scope) = req.scope {
+        params.push(("scope", scope));
+    }
+    let query: Vec<String> = params.iter().map(|(k, v)| format!("{}={}", k, percent_encode(v))).collect();
+    let separator = if req.auth_url.contains('?') { '&' } else { '?' };
+    format!("{}{}{}", req.auth_url, separator, query.join("&"))
+}
+
+/// Wait for the provider's redirect on `listener` and return the code.
+/// Requests to other paths (favicon) are answered and ignored.
+pub fn capture_redirect(listener: &TcpListener, state: &str, timeout: Duration) -> Result<String, String> {
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        let (stream, _) = match listener.accept() {
+            Ok(conn) => conn,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                if Instant::now() > deadline {
+                    return Err(format!("No OAuth redirect within {}s", timeout.as_secs()));
+                }
+                thread::sleep(Duration::from_millis(100));
+                continue;
+            }
+            Err(e) => return Err(format!("OAuth listener failed: {}", e)),
+        };
+        let _ = stream.set_nonblocking(false);
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+        let mut line = String::new();
+        let _ = BufReader::new(&stream).read_line(&mut line);
+        let target = line.split_whitespace().nth(1).unwrap_or_default();
+        let query = target.split_once('?').map(|(_, q)| q).unwrap_or_default();
+
+        let result = if let Some(error) = query_param(query, "error") {
+            let detail = query_param(query, "error_description").map(|d| format!(": {}", d));
+            Some(Err(format!("Authorization failed ({}){}", error, detail.unwrap_or_default())))
+        } else if let Some(code) = query_param(query, "code") {
+            if query_param(query, "state").as_deref() == Some(state) {
+                Some(Ok(code))
+            } else {
+                Some(Err("OAuth redirect had the wrong state parameter".to_string()))
+            }
+        } else {
+            None
+        };
+        let body = match &result {
+            Some(Ok(_)) => "Authorization complete. You can close this window.",
+            Some(Err(_)) => "Authorization failed. Check agent-browser's output.",
+            None => "",
+        };
+        let status = if result.is_some() { "200 OK" } else { "404 Not Found" };
+        let mut stream = stream;
+        let _ = write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        if let Some(result) = result {
+            return result;
+        }
+    }
+}
+
+/// POST a form with curl and parse the JSON answer. OAuth errors come back as
+/// 4xx with a JSON body, so the body is returned whatever the status.
+pub fn post_form(url: &str, fields: &[(&str, &str)]) -> Result<Value, String> {
+    let mut cmd = Command::new("curl");
+    cmd.args(["-sS", "--max-time", "30", "-H", "Accept: application/json", "-X", "POST", url]);
+    for (key, value) in fields {
+        cmd.arg("--data-urlencode").arg(format!("{}={}", key, value));
+    }
+    let output = cmd.output().map_err(|e| format!("Cannot run curl for the token request: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("Token request to {} failed: {}", url, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let body = String::from_utf8_lossy(&output.stdout);
+    serde_json::from_str(&body).map_err(|_| format!("Token endpoint returned non-JSON: {}", body.trim()))
+}
+
+/// The token response, or its OAuth error
+pub fn token_result(response: Value) -> Result<Value, String> {
+    if response.get("access_token").is_some() {
+        return Ok(response);
+    }
+    let error = response.get("error").and_then(|v| v.as_str()).unwrap_or("no access_token in response");
+    let detail = response.get("error_description").and_then(|v| v.as_str());
+    Err(match detail {
+        Some(detail) => format!("{}: {}", error, detail),
+        None => error.to_string(),
+    })
+}
+
+/// Poll the token endpoint for a device code until it's approved, denied or
+/// expires. `interval` grows on `slow_down` as RFC 8628 asks.
+pub fn poll_device_token(
+    token_url: &str,
+    client_id: &str,
+    device_code: &str,
+    mut interval: Duration,
+    timeout: Duration,
+) -> Result<Value, String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        thread::sleep(interval);
+        let response = post_form(
+            token_url,
+            &[("grant_type", DEVICE_GRANT), ("device_code", device_code), ("client_id", client_id)],
+        )?;
+        match response.get("error").and_then(|v| v.as_str()) {
+            Some("authorization_pending") => {}
+            Some("slow_down") => interval += Duration::from_secs(5),
+            _ => return token_result(response),
+        }
+        if Instant::now() > deadline {
+            return Err(format!("Device authorization not approved within {}s", timeout.as_secs()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpStream;
+
+    #[test]
+    fn test_percent_encoding() {
+        assert_eq!(percent_encode("read:user repo"), "read%3Auser%20repo");
+        assert_eq!(percent_decode("a%2Fb+c"), "a/b c");
+        assert_eq!(query_param("code=abc%3D&state=xyz", "code").as_deref(), Some("abc="));
+        assert_eq!(query_param("code=abc", "state"), None);
+    }
+
+    #[test]
+    fn test_pkce_challenge() {
+        // RFC 7636 appendix B
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        assert_eq!(pkce_challenge(verifier), "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM");
+        assert_eq!(random_token().len(), 43);
+    }
+
+    #[test]
+    fn test_authorize_url() {
+        let url = authorize_url(&AuthRequest {
+            auth_url: "https://auth.example.com/authorize?audience=api",
+            client_id: "cli",
+            redirect_uri: "http://127.0.0.1:8123/callback",
+            scope: Some("openid email"),
+            state: "s1",
+            challenge: "c1",
+        });
+        assert!(url.starts_with("https://auth.example.com/authorize?audience=api&response_type=code"));
+        assert!(url.contains("redirect_uri=http%3A%2F%2F127.0.0.1%3A8123%2Fcallback"));
+        assert!(url.ends_with("&scope=openid%20email"));
+    }
+
+    #[test]
+    fn test_capture_redirect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let client = thread::spawn(move || {
+            for path in ["/favicon.ico", "/callback?code=c0de&state=s1"] {
+                let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+                write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+                let mut response = String::new();
+                let _ = stream.read_to_string(&mut response);
+            }
+        });
+        let code = capture_redirect(&listener, "s1", Duration::from_secs(5));
+        client.join().unwrap();
+        assert_eq!(code.as_deref(), Ok("c0de"));
+    }
+
+    #[test]
+    fn test_token_result() {
+        let error = serde_json::json!({ "error": "invalid_grant", "error_description": "Code expired" });
+        assert_eq!(token_result(error).unwrap_err(), "invalid_grant: Code expired");
+        assert!(token_result(serde_json::json!({ "access_token": "t" })).is_ok());
+    }
+}
@@ -0,0 +1,116 @@
+//! Supervising a running session with `observe`.
+//!
+//! The observer attaches to the session's daemon on a connection of its own
+//! and reads a line for every command the session runs, whoever sent it:
+//! one when it starts and one with its outcome, plus a JPEG of the page after
+//! it with `--screenshots <dir>`. It takes no session lock and the daemon
+//! refuses commands on an observing connection, so watching never gets in
+//! the agent's way or acts for it.
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Command {
+        id: String,
+        action: String,
+        #[serde(default)]
+        args: Map<String, Value>,
+    },
+    Result {
+        id: String,
+        action: String,
+        success: bool,
+        #[serde(default)]
+        error: Option<String>,
+        #[serde(rename = "durationMs")]
+        duration_ms: u64,
+    },
+    Screenshot {
+        id: String,
+        action: String,
+        /// Base64 JPEG
+        data: String,
+    },
+    /// The session was closed
+    Closed,
+}
+
+/// A line from the daemon, or None for one this version doesn't know
+pub fn parse_event(line: &str) -> Option<Event> {
+    serde_json::from_str(line).ok()
+}
+
+/// "click #buy", "fill #password value=[hidden]": URLs and selectors bare,
+/// other arguments as key=value
+pub fn describe(action: &str, args: &Map<String, Value>) -> String {
+    let text = |value: &Value| match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let bare = ["url", "selector"];
+    let mut words = vec![action.to_string()];
+    words.extend(bare.iter().filter_map(|key| args.get(*key)).map(text));
+    words.extend(
+        args.iter()
+            .filter(|(key, _)| !bare.contains(&key.as_str()))
+            .map(|(key, value)| format!("{}={}", key, text(value))),
+    );
+    words.join(" ")
+}
+
+/// `<dir>/0001-click.jpg`: numbered so the files sort in the order taken
+pub fn screenshot_path(dir: &Path, seq: usize, action: &str) -> PathBuf {
+    let action: String = action.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    dir.join(format!("{:04}-{}.jpg", seq, action))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_event() {
+        let line = r##"{"event":"command","id":"7","action":"click","args":{"selector":"#buy"}}"##;
+        let Some(Event::Command { action, args, .. }) = parse_event(line) else {
+            panic!("expected a command event");
+        };
+        assert_eq!(describe(&action, &args), "click #buy");
+
+        let line = r#"{"event":"result","id":"7","action":"click","success":false,"error":"Timeout","durationMs":30012}"#;
+        assert_eq!(
+            parse_event(line),
+            Some(Event::Result {
+                id: "7".to_string(),
+                action: "click".to_string(),
+                success: false,
+                error: Some("Timeout".to_string()),
+                duration_ms: 30012,
+            })
+        );
+        assert_eq!(parse_event(r#"{"event":"closed"}"#), Some(Event::Closed));
+        assert_eq!(parse_event(r#"{"event":"something_new"}"#), None);
+    }
+
+    #[test]
+    fn test_describe() {
+        let args = json!({ "url": "https://example.com", "waitUntil": "load", "timeout": 5000 });
+        assert_eq!(
+            describe("navigate", args.as_object().unwrap()),
+            "navigate https://example.com timeout=5000 waitUntil=load"
+        );
+        assert_eq!(describe("reload", &Map::new()), "reload");
+    }
+
+    #[test]
+    fn test_screenshot_path() {
+        assert_eq!(
+            screenshot_path(Path::new("shots"), 12, "tab_new"),
+            PathBuf::from("shots/0012-tab_new.jpg")
+        );
+    }
+}
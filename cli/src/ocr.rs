@@ -0,0 +1,87 @@
+//! OCR for `text --ocr`.
+//!
+//! Pages that draw their text into a canvas or ship it as images leave DOM
+//! extraction with nothing to read. `text --ocr` screenshots the viewport (or
+//! an element) to a temp file and reads it with the `tesseract` command-line
+//! tool, which must be installed along with the language data asked for.
+
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+pub const DEFAULT_LANG: &str = "eng";
+
+/// A tesseract language: data file names such as `eng` or `chi_sim`,
+/// several joined with `+`
+pub fn valid_lang(lang: &str) -> bool {
+    !lang.is_empty()
+        && lang
+            .split('+')
+            .all(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+}
+
+/// Text read from an image
+pub fn recognize(image: &Path, lang: &str) -> Result<String, String> {
+    let output = Command::new("tesseract")
+        .arg(image)
+        .arg("stdout")
+        .args(["-l", lang])
+        .output()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => {
+                "OCR needs tesseract on PATH (apt install tesseract-ocr, or brew install tesseract)".to_string()
+            }
+            _ => format!("Cannot run tesseract: {}", e),
+        })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("Failed loading language") {
+            return Err(format!(
+                "tesseract has no '{}' language data (install it, e.g. apt install tesseract-ocr-{})",
+                lang,
+                lang.split('+').next().unwrap_or(lang)
+            ));
+        }
+        let reason = stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("unknown error");
+        return Err(format!("OCR failed: {}", reason.trim()));
+    }
+    Ok(tidy(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Tesseract's output without trailing spaces, the form feed it ends pages
+/// with, or runs of blank lines
+fn tidy(raw: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in raw.split('\n').map(|l| l.trim_end_matches(['\u{c}', ' ', '\t', '\r'])) {
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_lang() {
+        assert!(valid_lang("eng"));
+        assert!(valid_lang("eng+deu"));
+        assert!(valid_lang("chi_sim"));
+        assert!(!valid_lang(""));
+        assert!(!valid_lang("eng+"));
+        assert!(!valid_lang("../eng"));
+    }
+
+    #[test]
+    fn test_tidy() {
+        assert_eq!(tidy("Total  \n\n\n\n$42.00\n\n\u{c}"), "Total\n\n$42.00");
+        assert_eq!(tidy("\n\nHello\r\n"), "Hello");
+        assert_eq!(tidy("\u{c}"), "");
+    }
+}
@@ -0,0 +1,154 @@
+//! PDF text extraction for `text` when the page is a PDF.
+//!
+//! Headless Chromium downloads a PDF rather than showing it, so `open` on a
+//! PDF link reports `pdf: true` and `text` finds no DOM to read. Instead the
+//! daemon saves the PDF (fetched with the session's cookies) to a temp file,
+//! and its text and metadata are read with `pdftotext` and `pdfinfo` from
+//! poppler, one entry per page.
+
+use serde_json::{json, Map, Value};
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+/// Pages from `--pages`: `3`, `1-5` or `2-` (to the end)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageRange {
+    pub first: u32,
+    pub last: Option<u32>,
+}
+
+pub fn parse_pages(spec: &str) -> Result<PageRange, String> {
+    let invalid = || format!("Invalid --pages '{}': expected a page or range like 3, 1-5 or 2-", spec);
+    let number = |s: &str| s.trim().parse::<u32>().ok().filter(|n| *n > 0).ok_or_else(invalid);
+    let range = match spec.split_once('-') {
+        None => PageRange { first: number(spec)?, last: Some(number(spec)?) },
+        Some((first, "")) => PageRange { first: number(first)?, last: None },
+        Some((first, last)) => PageRange { first: number(first)?, last: Some(number(last)?) },
+    };
+    if range.last.is_some_and(|last| last < range.first) {
+        return Err(invalid());
+    }
+    Ok(range)
+}
+
+fn run(tool: &str, args: &[&str], pdf: &Path) -> Result<String, String> {
+    let mut command = Command::new(tool);
+    command.args(args).arg(pdf);
+    if tool == "pdftotext" {
+        // To stdout
+        command.arg("-");
+    }
+    let output = command.output().map_err(|e| match e.kind() {
+        ErrorKind::NotFound => format!(
+            "Reading PDFs needs {} from poppler on PATH (apt install poppler-utils, or brew install poppler)",
+            tool
+        ),
+        _ => format!("Cannot run {}: {}", tool, e),
+    })?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("unknown error");
+        return Err(format!("{} failed: {}", tool, reason.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// `pdfinfo` output as metadata: `Title`, `Author`, `Page size`... become
+/// `title`, `author`, `pageSize`, and `Pages` a number
+pub fn parse_info(output: &str) -> Map<String, Value> {
+    let mut info = Map::new();
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        let mut words = key.split_whitespace();
+        let Some(first) = words.next() else {
+            continue;
+        };
+        let mut name = first.to_lowercase();
+        for word in words {
+            let mut chars = word.chars();
+            if let Some(c) = chars.next() {
+                name.push(c.to_ascii_uppercase());
+                name.push_str(&chars.as_str().to_lowercase());
+            }
+        }
+        let value = match value.parse::<u64>() {
+            Ok(n) if name == "pages" => json!(n),
+            _ => json!(value),
+        };
+        info.insert(name, value);
+    }
+    info
+}
+
+/// `pdftotext` output split at its form feeds into pages numbered from `first`
+pub fn split_pages(text: &str, first: u32) -> Vec<(u32, String)> {
+    let mut pages: Vec<&str> = text.split('\u{c}').collect();
+    // Every page ends with a form feed, leaving an empty piece after the last
+    if pages.len() > 1 && pages.last().is_some_and(|p| p.trim().is_empty()) {
+        pages.pop();
+    }
+    pages.into_iter().zip(first..).map(|(page, n)| (n, page.trim_end().to_string())).collect()
+}
+
+/// Text per page and metadata of a PDF file
+pub fn extract(pdf: &Path, pages: Option<PageRange>) -> Result<Value, String> {
+    let metadata = parse_info(&run("pdfinfo", &[], pdf)?);
+    let total = metadata.get("pages").and_then(|v| v.as_u64()).unwrap_or(0) as u32;
+    let range = pages.unwrap_or(PageRange { first: 1, last: None });
+    if total > 0 && range.first > total {
+        return Err(format!("The PDF has {} page{}", total, if total == 1 { "" } else { "s" }));
+    }
+    let first = range.first.to_string();
+    let last = range.last.map(|l| l.min(total.max(1)).to_string());
+    let mut args = vec!["-f", first.as_str()];
+    if let Some(last) = last.as_deref() {
+        args.extend(["-l", last]);
+    }
+    let text = run("pdftotext", &args, pdf)?;
+    let pages: Vec<Value> =
+        split_pages(&text, range.first).into_iter().map(|(page, text)| json!({ "page": page, "text": text })).collect();
+    let joined = pages.iter().filter_map(|p| p["text"].as_str()).collect::<Vec<_>>().join("\n\n");
+    Ok(json!({ "text": joined, "pages": pages, "metadata": metadata }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pages() {
+        assert_eq!(parse_pages("3").unwrap(), PageRange { first: 3, last: Some(3) });
+        assert_eq!(parse_pages("1-5").unwrap(), PageRange { first: 1, last: Some(5) });
+        assert_eq!(parse_pages("2-").unwrap(), PageRange { first: 2, last: None });
+        assert!(parse_pages("0").is_err());
+        assert!(parse_pages("5-1").is_err());
+        assert!(parse_pages("a-b").is_err());
+        assert!(parse_pages("").is_err());
+    }
+
+    #[test]
+    fn test_parse_info() {
+        let info = parse_info(
+            "Title:           Annual Report\nAuthor:          ACME\nCreator:         \nPages:           12\nPage size:       612 x 792 pts (letter)\n",
+        );
+        assert_eq!(info["title"], "Annual Report");
+        assert_eq!(info["author"], "ACME");
+        assert_eq!(info["pages"], 12);
+        assert_eq!(info["pageSize"], "612 x 792 pts (letter)");
+        assert!(!info.contains_key("creator"));
+    }
+
+    #[test]
+    fn test_split_pages() {
+        let pages = split_pages("First page\n\u{c}Second page\n\n\u{c}", 3);
+        assert_eq!(pages, vec![(3, "First page".to_string()), (4, "Second page".to_string())]);
+        assert_eq!(split_pages("", 1), vec![(1, String::new())]);
+    }
+}
@@ -0,0 +1,217 @@
+//! Destructive-action confirmation policy.
+//!
+//! With --confirm-destructive (or a --policy file), navigations and clicks that
+//! match dangerous URL, selector or element text patterns are blocked. The error
+//! carries a token derived from the action and its target; re-running the same
+//! command with `--confirm <token>` lets it through.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Policy {
+    /// Glob patterns matched against navigation URLs
+    pub urls: Vec<String>,
+    /// Glob patterns matched against the selector passed to click/dblclick
+    pub selectors: Vec<String>,
+    /// Glob patterns matched against the clicked element's text
+    pub text: Vec<String>,
+}
+
+impl Policy {
+    /// Rules used by --confirm-destructive when no --policy file is given
+    pub fn builtin() -> Self {
+        let list = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        Policy {
+            urls: list(&[
+                "**/delete*",
+                "**/remove*",
+                "**/destroy*",
+                "**/checkout*",
+                "**/purchase*",
+                "**/unsubscribe*",
+            ]),
+            selectors: list(&["*delete*", "*remove*", "*destroy*", "*purchase*", "*checkout*"]),
+            text: list(&[
+                "delete*",
+                "remove*",
+                "purchase*",
+                "buy now*",
+                "place order*",
+                "pay",
+                "pay *",
+                "confirm payment*",
+                "unsubscribe*",
+            ]),
+        }
+    }
+}
+
+/// A command blocked by the policy
+#[derive(Debug, PartialEq)]
+pub struct Violation {
+    pub action: String,
+    pub target: String,
+    pub rule: String,
+    pub token: String,
+}
+
+impl Violation {
+    pub fn message(&self) -> String {
+        format!(
+            "Blocked destructive {} on \"{}\" (matches \"{}\"). Re-run with --confirm {} to proceed",
+            self.action, self.target, self.rule, self.token
+        )
+    }
+}
+
+/// Parse a policy from its JSON source
+pub fn parse_policy(source: &str) -> Result<Policy, String> {
+    serde_json::from_str(source).map_err(|e| format!("Invalid policy: {}", e))
+}
+
+/// Load a policy from disk
+pub fn load_policy(path: &str) -> Result<Policy, String> {
+    let source =
+        fs::read_to_string(path).map_err(|e| format!("Cannot read policy '{}': {}", path, e))?;
+    parse_policy(&source)
+}
+
+/// Case-insensitive glob match where `*` (and `**`) match any run of characters
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            while p < pattern.len() && pattern[p] == '*' {
+                p += 1;
+            }
+            backtrack = Some((p, t));
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((bp, bt)) = backtrack {
+            p = bp;
+            t = bt + 1;
+            backtrack = Some((bp, bt + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Short token binding a confirmation to one action on one target (FNV-1a)
+pub fn confirmation_token(action: &str, target: &str) -> String {
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in action.bytes().chain([0]).chain(target.bytes()) {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    format!("{:08x}", hash)
+}
+
+/// Selector whose element text should be checked, for clicks when text rules exist
+pub fn text_target<'a>(policy: &Policy, cmd: &'a Value) -> Option<&'a str> {
+    if policy.text.is_empty() {
+        return None;
+    }
+    match cmd.get("action").and_then(|v| v.as_str()) {
+        Some("click") | Some("dblclick") => cmd.get("selector").and_then(|v| v.as_str()),
+        _ => None,
+    }
+}
+
+/// Check a command against the policy. `element_text` is the text of the
+/// element a click targets, when it could be read from the page.
+pub fn check(policy: &Policy, cmd: &Value, element_text: Option<&str>) -> Option<Violation> {
+    let action = cmd.get("action").and_then(|v| v.as_str())?;
+    let (target, candidates): (&str, Vec<(&String, &str)>) = match action {
+        "navigate" => {
+            let url = cmd.get("url").and_then(|v| v.as_str())?;
+            (url, policy.urls.iter().map(|r| (r, url)).collect())
+        }
+        "click" | "dblclick" => {
+            let selector = cmd.get("selector").and_then(|v| v.as_str())?;
+            let mut rules: Vec<(&String, &str)> =
+                policy.selectors.iter().map(|r| (r, selector)).collect();
+            if let Some(text) = element_text.map(str::trim) {
+                rules.extend(policy.text.iter().map(|r| (r, text)));
+            }
+            (selector, rules)
+        }
+        _ => return None,
+    };
+    let (rule, _) = candidates.into_iter().find(|(rule, value)| glob_match(rule, value))?;
+    Some(Violation {
+        action: action.to_string(),
+        target: target.to_string(),
+        rule: rule.clone(),
+        token: confirmation_token(action, target),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("**/delete", "https://app.example.com/items/5/delete"));
+        assert!(glob_match("*delete*", "#Delete-Button"));
+        assert!(glob_match("pay", "Pay"));
+        assert!(!glob_match("pay", "Payment history"));
+        assert!(!glob_match("**/delete", "https://app.example.com/deleted-items"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_builtin_blocks_navigation() {
+        let policy = Policy::builtin();
+        let cmd = json!({ "action": "navigate", "url": "https://shop.example.com/checkout" });
+        let violation = check(&policy, &cmd, None).unwrap();
+        assert_eq!(violation.rule, "**/checkout*");
+        assert_eq!(violation.token, confirmation_token("navigate", "https://shop.example.com/checkout"));
+
+        let cmd = json!({ "action": "navigate", "url": "https://example.com/docs" });
+        assert!(check(&policy, &cmd, None).is_none());
+    }
+
+    #[test]
+    fn test_builtin_blocks_clicks_by_selector_and_text() {
+        let policy = Policy::builtin();
+        let cmd = json!({ "action": "click", "selector": "button.delete" });
+        assert!(check(&policy, &cmd, None).is_some());
+
+        let cmd = json!({ "action": "click", "selector": "@e4" });
+        assert!(check(&policy, &cmd, None).is_none());
+        assert!(check(&policy, &cmd, Some("  Purchase now ")).is_some());
+        assert_eq!(text_target(&policy, &cmd), Some("@e4"));
+    }
+
+    #[test]
+    fn test_other_actions_are_allowed() {
+        let policy = Policy::builtin();
+        let cmd = json!({ "action": "fill", "selector": "#delete-reason", "value": "x" });
+        assert!(check(&policy, &cmd, None).is_none());
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        let policy = parse_policy(r#"{ "urls": ["**/admin/**"] }"#).unwrap();
+        assert_eq!(policy.urls, vec!["**/admin/**"]);
+        assert!(policy.selectors.is_empty());
+        assert!(parse_policy("not json").is_err());
+    }
+
+    #[test]
+    fn test_token_depends_on_target() {
+        assert_ne!(confirmation_token("click", "#a"), confirmation_token("click", "#b"));
+        assert_eq!(confirmation_token("click", "#a").len(), 8);
+    }
+}
@@ -0,0 +1,148 @@
+//! Request header/UA rotation profiles.
+//!
+//! A rotation profile is a JSON file listing browser identities (user agent,
+//! Accept-Language, viewport). Each navigation picks the next identity and the
+//! daemon applies it before loading the page. The cursor is persisted per
+//! session next to the daemon socket so rotation continues across invocations.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::connection::get_socket_dir;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum RotationStrategy {
+    #[default]
+    RoundRobin,
+    Random,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Viewport {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RotationEntry {
+    pub user_agent: Option<String>,
+    pub accept_language: Option<String>,
+    pub viewport: Option<Viewport>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RotationProfile {
+    #[serde(default)]
+    pub strategy: RotationStrategy,
+    pub profiles: Vec<RotationEntry>,
+}
+
+/// Parse a rotation profile from its JSON source
+pub fn parse_profile(source: &str) -> Result<RotationProfile, String> {
+    let profile: RotationProfile =
+        serde_json::from_str(source).map_err(|e| format!("Invalid rotation profile: {}", e))?;
+    if profile.profiles.is_empty() {
+        return Err("Invalid rotation profile: \"profiles\" must not be empty".to_string());
+    }
+    Ok(profile)
+}
+
+/// Load a rotation profile from disk
+pub fn load_profile(path: &str) -> Result<RotationProfile, String> {
+    let source = fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read rotation profile '{}': {}", path, e))?;
+    parse_profile(&source)
+}
+
+/// Pick the entry index to use after `previous` (None on first use)
+pub fn next_index(previous: Option<usize>, len: usize, strategy: RotationStrategy) -> usize {
+    match strategy {
+        RotationStrategy::RoundRobin => previous.map(|p| (p + 1) % len).unwrap_or(0),
+        RotationStrategy::Random => {
+            let nanos = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.subsec_nanos() as usize)
+                .unwrap_or(0);
+            nanos % len
+        }
+    }
+}
+
+/// Advance the session's rotation cursor and return the selected entry
+pub fn next_entry<'a>(session: &str, profile: &'a RotationProfile) -> &'a RotationEntry {
+    let cursor_path = get_socket_dir().join(format!("{}.rotate", session));
+    let previous = fs::read_to_string(&cursor_path)
+        .ok()
+        .and_then(|s| s.trim().parse::<usize>().ok());
+    let index = next_index(previous, profile.profiles.len(), profile.strategy);
+    let _ = fs::create_dir_all(get_socket_dir());
+    let _ = fs::write(&cursor_path, index.to_string());
+    &profile.profiles[index]
+}
+
+/// Attach the selected identity to a navigate command
+pub fn apply_entry(cmd: &mut Value, entry: &RotationEntry) {
+    let mut rotation = json!({});
+    if let Some(ref ua) = entry.user_agent {
+        rotation["userAgent"] = json!(ua);
+    }
+    if let Some(ref lang) = entry.accept_language {
+        rotation["acceptLanguage"] = json!(lang);
+    }
+    if let Some(ref vp) = entry.viewport {
+        rotation["viewport"] = json!({ "width": vp.width, "height": vp.height });
+    }
+    cmd["rotation"] = rotation;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: &str = r#"{
+        "profiles": [
+            { "userAgent": "UA-1", "acceptLanguage": "en-US", "viewport": { "width": 1280, "height": 800 } },
+            { "userAgent": "UA-2" }
+        ]
+    }"#;
+
+    #[test]
+    fn test_parse_profile_defaults_to_round_robin() {
+        let profile = parse_profile(PROFILE).unwrap();
+        assert_eq!(profile.strategy, RotationStrategy::RoundRobin);
+        assert_eq!(profile.profiles.len(), 2);
+        assert_eq!(profile.profiles[0].accept_language.as_deref(), Some("en-US"));
+    }
+
+    #[test]
+    fn test_parse_profile_rejects_empty() {
+        assert!(parse_profile(r#"{ "profiles": [] }"#).is_err());
+        assert!(parse_profile("not json").is_err());
+    }
+
+    #[test]
+    fn test_round_robin_wraps() {
+        let s = RotationStrategy::RoundRobin;
+        assert_eq!(next_index(None, 3, s), 0);
+        assert_eq!(next_index(Some(0), 3, s), 1);
+        assert_eq!(next_index(Some(2), 3, s), 0);
+    }
+
+    #[test]
+    fn test_apply_entry() {
+        let profile = parse_profile(PROFILE).unwrap();
+        let mut cmd = json!({ "action": "navigate", "url": "https://example.com" });
+        apply_entry(&mut cmd, &profile.profiles[0]);
+        assert_eq!(cmd["rotation"]["userAgent"], "UA-1");
+        assert_eq!(cmd["rotation"]["acceptLanguage"], "en-US");
+        assert_eq!(cmd["rotation"]["viewport"]["width"], 1280);
+
+        let mut cmd = json!({ "action": "navigate" });
+        apply_entry(&mut cmd, &profile.profiles[1]);
+        assert!(cmd["rotation"].get("viewport").is_none());
+    }
+}
@@ -0,0 +1,169 @@
+//! JSON Schemas for `--json` command output.
+//!
+//! Every command prints the same envelope (`success`, `data`, `error`); the
+//! shape of `data` comes from the command's output fields in the registry.
+
+use serde_json::{json, Value};
+
+use crate::output::OutputFormat;
+use crate::registry::CommandSpec;
+
+/// JSON Schema for the `--json` output of `cmd` in the given output format
+pub fn response_schema(cmd: &CommandSpec, format: OutputFormat) -> Value {
+    let mut schema = json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": format!("agent-browser/{}/{}.json", env!("CARGO_PKG_VERSION"), cmd.name),
+        "title": format!("agent-browser {} --json", cmd.name),
+        "type": "object",
+        "required": ["success", "data", "error"],
+        "properties": {
+            "success": { "type": "boolean" },
+            "data": { "anyOf": [{ "type": "null" }, cmd.output_schema()] },
+            "error": { "type": ["string", "null"] },
+        },
+    });
+    if format == OutputFormat::V2 {
+        schema["required"] = json!(["success", "data", "error", "meta"]);
+        schema["properties"]["meta"] = json!({
+            "type": "object",
+            "required": ["formatVersion", "cliVersion", "session", "durationMs"],
+            "properties": {
+                "formatVersion": { "type": "integer" },
+                "cliVersion": { "type": "string" },
+                "action": { "type": ["string", "null"] },
+                "session": { "type": "string" },
+                "durationMs": { "type": "integer" },
+                "blocked": { "type": "object", "additionalProperties": { "type": "integer" } },
+                "cache": { "type": "boolean" },
+                "cacheAge": { "type": "integer" },
+            },
+        });
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connection::Response;
+    use crate::registry::{find_command, COMMANDS};
+
+    /// Minimal validator for the subset of JSON Schema used in this module
+    fn validate(schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+        if let Some(options) = schema.get("anyOf").and_then(|v| v.as_array()) {
+            return if options.iter().any(|s| validate(s, value, path).is_ok()) {
+                Ok(())
+            } else {
+                Err(format!("{}: matches no schema in anyOf", path))
+            };
+        }
+        if let Some(expected) = schema.get("type") {
+            let types: Vec<&str> = match expected {
+                Value::Array(list) => list.iter().filter_map(|t| t.as_str()).collect(),
+                other => other.as_str().into_iter().collect(),
+            };
+            let matches = types.iter().any(|t| match *t {
+                "null" => value.is_null(),
+                "boolean" => value.is_boolean(),
+                "string" => value.is_string(),
+                "integer" => value.is_i64() || value.is_u64(),
+                "number" => value.is_number(),
+                "array" => value.is_array(),
+                "object" => value.is_object(),
+                _ => false,
+            });
+            if !matches {
+                return Err(format!("{}: expected {:?}, got {}", path, types, value));
+            }
+        }
+        if let Some(required) = schema.get("required").and_then(|v| v.as_array()) {
+            for key in required.iter().filter_map(|k| k.as_str()) {
+                if value.get(key).is_none() {
+                    return Err(format!("{}: missing required property {}", path, key));
+                }
+            }
+        }
+        if let (Some(properties), Some(object)) =
+            (schema.get("properties").and_then(|v| v.as_object()), value.as_object())
+        {
+            for (key, property_schema) in properties {
+                if let Some(property) = object.get(key) {
+                    validate(property_schema, property, &format!("{}.{}", path, key))?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check(command: &str, data: Value) -> Result<(), String> {
+        let resp = Response { success: true, data: Some(data), error: None, meta: None };
+        let output = serde_json::to_value(&resp).unwrap();
+        let schema = response_schema(find_command(command).unwrap(), OutputFormat::V1);
+        validate(&schema, &output, "$")
+    }
+
+    #[test]
+    fn test_every_command_has_a_schema() {
+        for cmd in COMMANDS {
+            let schema = response_schema(cmd, OutputFormat::V1);
+            assert_eq!(schema["title"], format!("agent-browser {} --json", cmd.name));
+            assert_eq!(schema["properties"]["data"]["anyOf"][1]["type"], "object");
+        }
+    }
+
+    #[test]
+    fn test_daemon_responses_match_schemas() {
+        check("open", json!({ "url": "https://example.com/", "title": "Example Domain" })).unwrap();
+        check("snapshot", json!({ "snapshot": "- heading \"Hi\" [ref=e1]", "refs": { "e1": {} } }))
+            .unwrap();
+        check("click", json!({ "clicked": true })).unwrap();
+        check("get", json!({ "count": 3 })).unwrap();
+        check("is", json!({ "visible": false })).unwrap();
+        check("tab", json!({ "tabs": [], "active": 0 })).unwrap();
+        check("eval", json!({ "result": [1, 2, 3] })).unwrap();
+        check("screenshot", json!({ "path": "/tmp/shot.png" })).unwrap();
+        check("set", json!({ "latitude": 51.5, "longitude": -0.12 })).unwrap();
+    }
+
+    #[test]
+    fn test_schema_rejects_shape_changes() {
+        assert!(check("click", json!({ "clicked": "yes" })).is_err());
+        assert!(check("get", json!({ "count": "3" })).is_err());
+        assert!(check("snapshot", json!({ "refs": [] })).is_err());
+    }
+
+    #[test]
+    fn test_v2_response_matches_schema() {
+        use crate::output::{json_response, ResponseMeta};
+        let resp = Response { success: true, data: Some(json!({ "clicked": true })), error: None, meta: None };
+        let meta = ResponseMeta { action: Some("click"), session: "default", duration_ms: 12 };
+        let output = json_response(&resp, OutputFormat::V2, &meta);
+        let click = find_command("click").unwrap();
+        validate(&response_schema(click, OutputFormat::V2), &output, "$").unwrap();
+        assert_eq!(output["meta"]["durationMs"], 12);
+
+        // Daemon meta (--block counts) joins the CLI's
+        let blocked = Response {
+            success: true,
+            data: Some(json!({ "clicked": true })),
+            error: None,
+            meta: Some(json!({ "blocked": { "image": 4 } })),
+        };
+        let output = json_response(&blocked, OutputFormat::V2, &meta);
+        validate(&response_schema(click, OutputFormat::V2), &output, "$").unwrap();
+        assert_eq!(output["meta"]["blocked"]["image"], 4);
+
+        // v1 output lacks the meta block that v2 requires
+        let v1 = json_response(&resp, OutputFormat::V1, &meta);
+        assert!(v1.get("meta").is_none());
+        assert!(validate(&response_schema(click, OutputFormat::V2), &v1, "$").is_err());
+    }
+
+    #[test]
+    fn test_error_response_matches_schema() {
+        let resp = Response { success: false, data: None, error: Some("Timeout".to_string()), meta: None };
+        let output = serde_json::to_value(&resp).unwrap();
+        let schema = response_schema(find_command("click").unwrap(), OutputFormat::V1);
+        validate(&schema, &output, "$").unwrap();
+    }
+}
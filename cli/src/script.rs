@@ -0,0 +1,579 @@
+//! Command scripts (`.ab` files).
+//!
+//! A script holds one agent-browser command per line, written as on the
+//! command line without the `agent-browser` prefix:
+//!
+//! ```text
+//! # Daily report
+//! open https://dashboard.example.com
+//! wait --load networkidle
+//! screenshot "reports/daily report.png"
+//! ```
+//!
+//! Words are split like a POSIX shell does: single quotes keep everything
+//! literal, double quotes allow `\"` and `\\`, and a backslash outside quotes
+//! escapes the next character. Blank lines and lines starting with `#` are
+//! skipped.
+//!
+//! Scripts started with `agent-browser run` can also use variables and
+//! simple control flow:
+//!
+//! ```text
+//! set BASE https://shop.example.com
+//! open ${BASE}/cart
+//! set TITLE $(get title)
+//! if ${TITLE} contains Empty
+//!   open ${BASE}/products
+//! end
+//! foreach ITEM in apples pears
+//!   fill "#search" ${ITEM}
+//!   press Enter
+//! end
+//! ```
+//!
+//! `${NAME}` is replaced by the variable (or environment variable) NAME and
+//! `$(command)` by the main value of the command's result, outside single
+//! quotes. A replaced value is always one word, even when it has spaces.
+//! Variable names given to `set` are upper case so they can't be mistaken
+//! for `set viewport` and the other `set` commands.
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+
+/// Part of a word: literal text, a `${NAME}` reference or a `$(command)` capture
+#[derive(Debug, Clone, PartialEq)]
+pub enum Part {
+    Text(String),
+    Var(String),
+    Capture(Vec<Word>),
+}
+
+pub type Word = Vec<Part>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Command { line: usize, words: Vec<Word> },
+    Set { line: usize, name: String, value: Vec<Word> },
+    If { line: usize, condition: Vec<Word>, then: Vec<Statement>, otherwise: Vec<Statement> },
+    Foreach { line: usize, name: String, items: Vec<Word>, body: Vec<Statement> },
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    /// Whether `$` starts a reference or capture
+    expand: bool,
+}
+
+impl Lexer {
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.get(self.pos).copied();
+        self.pos += 1;
+        c
+    }
+
+    /// Words up to the end of the line, or up to the `)` closing a capture
+    fn words(&mut self, in_capture: bool) -> Result<Vec<Word>, String> {
+        let mut words = Vec::new();
+        let mut word = Word::new();
+        let mut in_word = false;
+        loop {
+            let Some(c) = self.next() else {
+                if in_capture {
+                    return Err("unterminated $(".to_string());
+                }
+                break;
+            };
+            match c {
+                ')' if in_capture => break,
+                '\'' => {
+                    in_word = true;
+                    loop {
+                        match self.next() {
+                            Some('\'') => break,
+                            Some(c) => push_text(&mut word, c),
+                            None => return Err("unterminated single quote".to_string()),
+                        }
+                    }
+                }
+                '"' => {
+                    in_word = true;
+                    loop {
+                        match self.next() {
+                            Some('"') => break,
+                            Some('\\') => match self.next() {
+                                Some(c @ ('"' | '\\')) => push_text(&mut word, c),
+                                Some('$') if self.expand => push_text(&mut word, '$'),
+                                Some(c) => {
+                                    push_text(&mut word, '\\');
+                                    push_text(&mut word, c);
+                                }
+                                None => return Err("unterminated double quote".to_string()),
+                            },
+                            Some('$') if self.expand => self.dollar(&mut word)?,
+                            Some(c) => push_text(&mut word, c),
+                            None => return Err("unterminated double quote".to_string()),
+                        }
+                    }
+                }
+                '\\' => {
+                    in_word = true;
+                    if let Some(c) = self.next() {
+                        push_text(&mut word, c);
+                    }
+                }
+                '$' if self.expand => {
+                    in_word = true;
+                    self.dollar(&mut word)?;
+                }
+                c if c.is_whitespace() => {
+                    if in_word {
+                        words.push(std::mem::take(&mut word));
+                        in_word = false;
+                    }
+                }
+                c => {
+                    in_word = true;
+                    push_text(&mut word, c);
+                }
+            }
+        }
+        if in_word {
+            words.push(word);
+        }
+        Ok(words)
+    }
+
+    /// What follows a `$`: `{NAME}`, `(command)` or nothing special
+    fn dollar(&mut self, word: &mut Word) -> Result<(), String> {
+        match self.chars.get(self.pos) {
+            Some('{') => {
+                self.pos += 1;
+                let mut name = String::new();
+                loop {
+                    match self.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err("unterminated ${".to_string()),
+                    }
+                }
+                if !is_name(&name) {
+                    return Err(format!("invalid variable name '{}'", name));
+                }
+                word.push(Part::Var(name));
+            }
+            Some('(') => {
+                self.pos += 1;
+                let command = self.words(true)?;
+                if command.is_empty() {
+                    return Err("empty $()".to_string());
+                }
+                word.push(Part::Capture(command));
+            }
+            _ => push_text(word, '$'),
+        }
+        Ok(())
+    }
+}
+
+fn push_text(word: &mut Word, c: char) {
+    match word.last_mut() {
+        Some(Part::Text(text)) => text.push(c),
+        _ => word.push(Part::Text(c.to_string())),
+    }
+}
+
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Names `set` accepts: upper case, unlike the `set` commands
+fn is_set_name(name: &str) -> bool {
+    is_name(name) && !name.chars().any(|c| c.is_ascii_lowercase())
+}
+
+/// Split one line into words
+pub fn split_line(line: &str) -> Result<Vec<String>, String> {
+    let mut lexer = Lexer { chars: line.chars().collect(), pos: 0, expand: false };
+    let words = lexer.words(false)?;
+    Ok(words
+        .into_iter()
+        .map(|word| {
+            word.into_iter()
+                .map(|part| match part {
+                    Part::Text(text) => text,
+                    _ => String::new(),
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// The commands of a script with their (1-based) line numbers
+pub fn parse(source: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let mut commands = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let words = split_line(trimmed).map_err(|e| format!("line {}: {}", index + 1, e))?;
+        commands.push((index + 1, words));
+    }
+    Ok(commands)
+}
+
+pub fn load(path: &str) -> Result<Vec<(usize, Vec<String>)>, String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("Cannot read script {}: {}", path, e))?;
+    let commands = parse(&source).map_err(|e| format!("{}: {}", path, e))?;
+    if commands.is_empty() {
+        return Err(format!("Script {} has no commands", path));
+    }
+    Ok(commands)
+}
+
+/// The word as plain text, when it has no references or captures
+fn literal(word: &Word) -> Option<&str> {
+    match word.as_slice() {
+        [Part::Text(text)] => Some(text),
+        _ => None,
+    }
+}
+
+/// A block being parsed: the script itself, or an `if`/`foreach` waiting for `end`
+enum Block {
+    Script(Vec<Statement>),
+    If { line: usize, condition: Vec<Word>, then: Vec<Statement>, otherwise: Option<Vec<Statement>> },
+    Foreach { line: usize, name: String, items: Vec<Word>, body: Vec<Statement> },
+}
+
+impl Block {
+    fn push(&mut self, statement: Statement) {
+        match self {
+            Block::Script(body) | Block::Foreach { body, .. } => body.push(statement),
+            Block::If { otherwise: Some(body), .. } | Block::If { then: body, .. } => body.push(statement),
+        }
+    }
+}
+
+/// The statements of a script run with `agent-browser run`
+pub fn parse_program(source: &str) -> Result<Vec<Statement>, String> {
+    let mut blocks = vec![Block::Script(Vec::new())];
+    for (index, text) in source.lines().enumerate() {
+        let line = index + 1;
+        let trimmed = text.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let err = |e: String| format!("line {}: {}", line, e);
+        let mut lexer = Lexer { chars: trimmed.chars().collect(), pos: 0, expand: true };
+        let mut words = lexer.words(false).map_err(err)?;
+        let keyword = words.first().and_then(literal).unwrap_or_default().to_string();
+        let name = words.get(1).and_then(literal).unwrap_or_default().to_string();
+        match keyword.as_str() {
+            "set" if is_set_name(&name) => {
+                let value = words.split_off(2);
+                blocks.last_mut().unwrap().push(Statement::Set { line, name, value });
+            }
+            "if" => {
+                if words.len() < 2 {
+                    return Err(err("if needs a condition".to_string()));
+                }
+                let condition = words.split_off(1);
+                blocks.push(Block::If { line, condition, then: Vec::new(), otherwise: None });
+            }
+            "foreach" => {
+                if !is_name(&name) || words.get(2).and_then(literal) != Some("in") {
+                    return Err(err("usage: foreach NAME in <items...>".to_string()));
+                }
+                let items = words.split_off(3);
+                blocks.push(Block::Foreach { line, name, items, body: Vec::new() });
+            }
+            "else" => match blocks.last_mut() {
+                Some(Block::If { otherwise: otherwise @ None, .. }) if words.len() == 1 => {
+                    *otherwise = Some(Vec::new());
+                }
+                _ => return Err(err("else without if".to_string())),
+            },
+            "end" if words.len() == 1 => {
+                let statement = match blocks.pop() {
+                    Some(Block::If { line, condition, then, otherwise }) => {
+                        Statement::If { line, condition, then, otherwise: otherwise.unwrap_or_default() }
+                    }
+                    Some(Block::Foreach { line, name, items, body }) => Statement::Foreach { line, name, items, body },
+                    _ => return Err(err("end without if or foreach".to_string())),
+                };
+                blocks.last_mut().unwrap().push(statement);
+            }
+            _ => blocks.last_mut().unwrap().push(Statement::Command { line, words }),
+        }
+    }
+    match blocks.pop() {
+        Some(Block::Script(statements)) => Ok(statements),
+        Some(Block::If { line, .. }) => Err(format!("line {}: if without end", line)),
+        Some(Block::Foreach { line, .. }) => Err(format!("line {}: foreach without end", line)),
+        None => Ok(Vec::new()),
+    }
+}
+
+pub fn load_program(path: &str) -> Result<Vec<Statement>, String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("Cannot read script {}: {}", path, e))?;
+    let statements = parse_program(&source).map_err(|e| format!("{}: {}", path, e))?;
+    if statements.is_empty() {
+        return Err(format!("Script {} has no commands", path));
+    }
+    Ok(statements)
+}
+
+/// A `--var NAME=VALUE` pair
+pub fn parse_var(pair: &str) -> Result<(String, String), String> {
+    match pair.split_once('=') {
+        Some((name, value)) if is_name(name) => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!("Invalid --var '{}': use NAME=VALUE", pair)),
+    }
+}
+
+/// The inputs `map` runs its script for: the file's lines, trimmed, with
+/// their (1-based) line numbers. Blank lines and lines starting with `#` are
+/// skipped.
+pub fn map_inputs(source: &str) -> Vec<(usize, String)> {
+    source
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(n, line)| (n, line.to_string()))
+        .collect()
+}
+
+/// The value `$(command)` stands for: the data itself when it's a string or
+/// has a single field, else its most telling field, else compact JSON
+pub fn capture_value(data: &Value) -> String {
+    let value = match data {
+        Value::Object(map) if map.len() == 1 => map.values().next().unwrap_or(data),
+        Value::Object(map) => ["text", "html", "result", "value", "title", "url", "count", "path"]
+            .iter()
+            .find_map(|key| map.get(*key))
+            .unwrap_or(data),
+        _ => data,
+    };
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Whether a condition value counts as true: anything but empty, false, 0 and null
+fn truthy(value: &str) -> bool {
+    !matches!(value, "" | "false" | "0" | "null")
+}
+
+/// An `if` condition: `<value>`, `<a> == <b>`, `<a> != <b>` or
+/// `<a> contains <b>`, optionally preceded by `not`
+fn test(words: &[String]) -> Result<bool, String> {
+    let (negate, words) = match words.split_first() {
+        Some((first, rest)) if first == "not" => (true, rest),
+        _ => (false, words),
+    };
+    let result = match words {
+        [value] => truthy(value),
+        [a, op, b] if op == "==" => a == b,
+        [a, op, b] if op == "!=" => a != b,
+        [a, op, b] if op == "contains" => a.contains(b.as_str()),
+        _ => return Err(format!("invalid condition '{}': use <a> == <b>, != or contains", words.join(" "))),
+    };
+    Ok(result != negate)
+}
+
+/// Runs statements, sending each command through `exec(words, capture)`,
+/// which returns the captured value when `capture` is set
+pub struct Runner<F> {
+    pub vars: BTreeMap<String, String>,
+    /// Commands run so far, captures included
+    pub steps: usize,
+    exec: F,
+}
+
+impl<F: FnMut(&[String], bool) -> Result<String, String>> Runner<F> {
+    pub fn new(vars: BTreeMap<String, String>, exec: F) -> Self {
+        Runner { vars, steps: 0, exec }
+    }
+
+    pub fn run(&mut self, statements: &[Statement]) -> Result<(), String> {
+        for statement in statements {
+            match statement {
+                Statement::Command { line, words } => {
+                    let words = self.expand(words).map_err(|e| format!("line {}: {}", line, e))?;
+                    self.steps += 1;
+                    (self.exec)(&words, false).map_err(|e| format!("line {}: {}", line, e))?;
+                }
+                Statement::Set { line, name, value } => {
+                    let value = self.expand(value).map_err(|e| format!("line {}: {}", line, e))?;
+                    self.vars.insert(name.clone(), value.join(" "));
+                }
+                Statement::If { line, condition, then, otherwise } => {
+                    let condition = self.expand(condition).map_err(|e| format!("line {}: {}", line, e))?;
+                    let holds = test(&condition).map_err(|e| format!("line {}: {}", line, e))?;
+                    self.run(if holds { then } else { otherwise })?;
+                }
+                Statement::Foreach { line, name, items, body } => {
+                    for item in self.expand(items).map_err(|e| format!("line {}: {}", line, e))? {
+                        self.vars.insert(name.clone(), item);
+                        self.run(body)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn expand(&mut self, words: &[Word]) -> Result<Vec<String>, String> {
+        let mut expanded = Vec::new();
+        for word in words {
+            let mut text = String::new();
+            for part in word {
+                match part {
+                    Part::Text(s) => text.push_str(s),
+                    Part::Var(name) => match self.vars.get(name).cloned().or_else(|| env::var(name).ok()) {
+                        Some(value) => text.push_str(&value),
+                        None => return Err(format!("undefined variable {}", name)),
+                    },
+                    Part::Capture(command) => {
+                        let command = self.expand(command)?;
+                        self.steps += 1;
+                        let value = (self.exec)(&command, true)
+                            .map_err(|e| format!("$({}) failed: {}", command.join(" "), e))?;
+                        text.push_str(&value);
+                    }
+                }
+            }
+            expanded.push(text);
+        }
+        Ok(expanded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_split_line() {
+        assert_eq!(split_line("click '#a b'").unwrap(), vec!["click", "#a b"]);
+        assert_eq!(
+            split_line(r#"fill "input[name=\"q\"]" hello\ world"#).unwrap(),
+            vec!["fill", r#"input[name="q"]"#, "hello world"]
+        );
+        assert_eq!(split_line("eval ''").unwrap(), vec!["eval", ""]);
+        assert_eq!(split_line("type '#q' ${X}").unwrap(), vec!["type", "#q", "${X}"]);
+        assert!(split_line("type \"unterminated").is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        let commands = parse("# setup\nopen example.com\n\n  snapshot -i\n").unwrap();
+        assert_eq!(commands, vec![
+            (2, vec!["open".to_string(), "example.com".to_string()]),
+            (4, vec!["snapshot".to_string(), "-i".to_string()]),
+        ]);
+        assert!(parse("open 'x").unwrap_err().starts_with("line 1"));
+    }
+
+    fn text(s: &str) -> Word {
+        vec![Part::Text(s.to_string())]
+    }
+
+    #[test]
+    fn test_parse_program() {
+        let statements = parse_program("set TITLE $(get text h1)\nset viewport 800 600\nopen \"${BASE}/a\" '${BASE}'\n").unwrap();
+        assert_eq!(statements, vec![
+            Statement::Set {
+                line: 1,
+                name: "TITLE".to_string(),
+                value: vec![vec![Part::Capture(vec![text("get"), text("text"), text("h1")])]],
+            },
+            Statement::Command { line: 2, words: vec![text("set"), text("viewport"), text("800"), text("600")] },
+            Statement::Command {
+                line: 3,
+                words: vec![text("open"), vec![Part::Var("BASE".to_string()), Part::Text("/a".to_string())], text("${BASE}")],
+            },
+        ]);
+
+        let nested = parse_program("foreach U in a b\n  if ${U} == a\n    open ${U}\n  else\n    reload\n  end\nend\n").unwrap();
+        let Statement::Foreach { name, items, body, .. } = &nested[0] else { panic!("expected foreach") };
+        assert_eq!((name.as_str(), items.len()), ("U", 2));
+        let Statement::If { then, otherwise, .. } = &body[0] else { panic!("expected if") };
+        assert_eq!((then.len(), otherwise.len()), (1, 1));
+    }
+
+    #[test]
+    fn test_parse_program_errors() {
+        assert_eq!(parse_program("if ${A}\nopen x\n").unwrap_err(), "line 1: if without end");
+        assert_eq!(parse_program("end\n").unwrap_err(), "line 1: end without if or foreach");
+        assert_eq!(parse_program("foreach X in a\nelse\nend\n").unwrap_err(), "line 2: else without if");
+        assert!(parse_program("foreach x a b\nend").unwrap_err().contains("usage: foreach"));
+        assert!(parse_program("open $(get url").unwrap_err().contains("unterminated $("));
+        assert!(parse_program("open ${1X}").unwrap_err().contains("invalid variable name"));
+    }
+
+    #[test]
+    fn test_runner() {
+        let source = "set SITE example.com\nset TITLE $(get title)\nif ${TITLE} contains Example\n  foreach P in a 'b c'\n    open \"${SITE}/${P}\"\n  end\nelse\n  reload\nend\nif not $(is visible '#x')\n  close\nend\n";
+        let statements = parse_program(source).unwrap();
+        let mut sent = Vec::new();
+        let mut runner = Runner::new(BTreeMap::new(), |words: &[String], capture: bool| {
+            sent.push(words.join(" "));
+            Ok(match (capture, words[0].as_str()) {
+                (true, "get") => "Example Domain".to_string(),
+                (true, _) => "false".to_string(),
+                _ => String::new(),
+            })
+        });
+        runner.run(&statements).unwrap();
+        assert_eq!(runner.steps, 5);
+        assert_eq!(runner.vars["P"], "b c");
+        assert_eq!(sent, vec!["get title", "open example.com/a", "open example.com/b c", "is visible #x", "close"]);
+    }
+
+    #[test]
+    fn test_runner_errors() {
+        let run = |source: &str| {
+            let statements = parse_program(source).unwrap();
+            Runner::new(BTreeMap::new(), |words: &[String], _| match words[0].as_str() {
+                "click" => Err("Element not found".to_string()),
+                _ => Ok(String::new()),
+            })
+            .run(&statements)
+        };
+        assert_eq!(run("open x\nclick '#missing'\n").unwrap_err(), "line 2: Element not found");
+        assert_eq!(run("open ${AB_SCRIPT_UNSET}").unwrap_err(), "line 1: undefined variable AB_SCRIPT_UNSET");
+        assert!(run("if a b\nend").unwrap_err().starts_with("line 1: invalid condition"));
+        assert_eq!(run("set X $(click a)").unwrap_err(), "line 1: $(click a) failed: Element not found");
+    }
+
+    #[test]
+    fn test_parse_var_and_map_inputs() {
+        assert_eq!(parse_var("USER=a=b").unwrap(), ("USER".to_string(), "a=b".to_string()));
+        assert!(parse_var("USER").is_err());
+        assert!(parse_var("1X=a").is_err());
+        assert_eq!(
+            map_inputs("# urls\nhttps://a.test\n\n  https://b.test  \n"),
+            vec![(2, "https://a.test".to_string()), (4, "https://b.test".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_capture_value() {
+        assert_eq!(capture_value(&json!({ "text": "Hello" })), "Hello");
+        assert_eq!(capture_value(&json!({ "visible": true })), "true");
+        assert_eq!(capture_value(&json!({ "url": "https://a.test/", "title": "A" })), "A");
+        assert_eq!(capture_value(&json!({ "a": 1, "b": 2 })), r#"{"a":1,"b":2}"#);
+        assert_eq!(capture_value(&Value::Null), "");
+    }
+}
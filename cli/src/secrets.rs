@@ -0,0 +1,95 @@
+//! Named secrets stored for later commands.
+//!
+//! Each secret is a JSON file in ~/.agent-browser/secrets (or
+//! AGENT_BROWSER_SECRETS_DIR), readable only by the owner on Unix. `auth
+//! oauth` stores tokens here and `auth token <name>` prints them for scripts.
+
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::connection::app_data_dir;
+
+pub fn secrets_dir() -> PathBuf {
+    match env::var("AGENT_BROWSER_SECRETS_DIR") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => app_data_dir().join("secrets"),
+    }
+}
+
+/// Secret names become file names, so keep them to a safe character set
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        && !name.starts_with('.');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid secret name '{}': use letters, digits, '-', '_' and '.'", name))
+    }
+}
+
+fn secret_path(name: &str) -> PathBuf {
+    secrets_dir().join(format!("{}.json", name))
+}
+
+pub fn save(name: &str, value: &Value) -> Result<PathBuf, String> {
+    validate_name(name)?;
+    let dir = secrets_dir();
+    fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    let path = secret_path(name);
+    let source = serde_json::to_string_pretty(value).unwrap_or_default();
+    write_private(&path, source.as_bytes()).map_err(|e| format!("Cannot write secret {}: {}", name, e))?;
+    Ok(path)
+}
+
+pub fn load(name: &str) -> Result<Value, String> {
+    validate_name(name)?;
+    let source = fs::read_to_string(secret_path(name)).map_err(|_| format!("No secret named {}", name))?;
+    serde_json::from_str(&source).map_err(|e| format!("Secret {} is unreadable: {}", name, e))
+}
+
+/// Write a file only the user can read
+#[cfg(unix)]
+pub fn write_private(path: &std::path::Path, bytes: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    let mut file = fs::OpenOptions::new().write(true).create(true).truncate(true).mode(0o600).open(path)?;
+    // An existing file keeps its mode, so tighten it explicitly
+    file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    file.write_all(bytes)
+}
+
+#[cfg(windows)]
+pub fn write_private(path: &std::path::Path, bytes: &[u8]) -> std::io::Result<()> {
+    // %APPDATA% is already private to the user
+    fs::write(path, bytes)
+}
+
+/// Append to a file only the user can read, creating it if needed
+#[cfg(unix)]
+pub fn append_private(path: &std::path::Path, bytes: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    let mut file = fs::OpenOptions::new().append(true).create(true).mode(0o600).open(path)?;
+    file.write_all(bytes)
+}
+
+#[cfg(windows)]
+pub fn append_private(path: &std::path::Path, bytes: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    fs::OpenOptions::new().append(true).create(true).open(path)?.write_all(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("github.token").is_ok());
+        assert!(validate_name("../x").is_err());
+        assert!(validate_name(".hidden").is_err());
+    }
+}
@@ -0,0 +1,337 @@
+//! In-place upgrades of the native binary.
+//!
+//! `self-update` reads the channel's release feed, a JSON document listing the
+//! latest version and, per platform binary, its download URL and SHA-256:
+//!
+//! ```json
+//! {
+//!   "version": "0.8.0",
+//!   "assets": {
+//!     "agent-browser-linux-x64": { "url": "https://…/agent-browser-linux-x64", "sha256": "…" }
+//!   }
+//! }
+//! ```
+//!
+//! Each binary is signed with minisign (`<url>.minisig`). The download must
+//! match both the feed's hash and a signature by the release key before it
+//! replaces the running executable. The replacement is written next to the
+//! executable and renamed over it, so an interrupted update leaves the old
+//! binary in place. Downloads go through `curl`, which ships with macOS, most
+//! Linux images and Windows 10+.
+
+use minisign_verify::{PublicKey, Signature};
+use serde::Deserialize;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::audit::sha256_hex;
+
+const RELEASES_URL: &str = "https://github.com/vercel-labs/agent-browser/releases";
+
+/// Release signing key, baked in by the release workflow
+const BUILD_PUBLIC_KEY: Option<&str> = option_env!("AGENT_BROWSER_UPDATE_PUBKEY");
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channel {
+    Stable,
+    Nightly,
+}
+
+impl Channel {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "stable" => Ok(Channel::Stable),
+            "nightly" => Ok(Channel::Nightly),
+            _ => Err(format!("Invalid channel '{}': use stable or nightly", value)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Channel::Stable => "stable",
+            Channel::Nightly => "nightly",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct Asset {
+    pub url: String,
+    pub sha256: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct Release {
+    pub version: String,
+    pub assets: BTreeMap<String, Asset>,
+}
+
+/// Feed URL for a channel (AGENT_BROWSER_UPDATE_FEED overrides both)
+pub fn feed_url(channel: Channel) -> String {
+    match env::var("AGENT_BROWSER_UPDATE_FEED") {
+        Ok(url) if !url.is_empty() => url,
+        _ => match channel {
+            Channel::Stable => format!("{}/latest/download/release.json", RELEASES_URL),
+            Channel::Nightly => format!("{}/download/nightly/release.json", RELEASES_URL),
+        },
+    }
+}
+
+/// Key release binaries must be signed with (AGENT_BROWSER_UPDATE_PUBKEY at
+/// run time for self-hosted feeds, otherwise the key the binary was built with)
+pub fn public_key() -> Result<String, String> {
+    env::var("AGENT_BROWSER_UPDATE_PUBKEY")
+        .ok()
+        .filter(|k| !k.is_empty())
+        .or(BUILD_PUBLIC_KEY.map(str::to_string))
+        .ok_or_else(|| {
+            "This build has no release signing key, so updates can't be verified. \
+Set AGENT_BROWSER_UPDATE_PUBKEY or reinstall from a release build."
+                .to_string()
+        })
+}
+
+/// Name of this platform's binary, as published by the release workflow
+pub fn asset_name() -> String {
+    let os = match env::consts::OS {
+        "macos" => "darwin",
+        "windows" => "win32",
+        other => other,
+    };
+    let arch = match env::consts::ARCH {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        other => other,
+    };
+    let ext = if cfg!(windows) { ".exe" } else { "" };
+    format!("agent-browser-{}-{}{}", os, arch, ext)
+}
+
+pub fn parse_release(source: &[u8]) -> Result<Release, String> {
+    serde_json::from_slice(source).map_err(|e| format!("Invalid release feed: {}", e))
+}
+
+/// Compare `major.minor.patch[-pre]` versions. A release sorts after its
+/// prereleases; prerelease parts compare numerically where both are numbers,
+/// so `0.8.0-nightly.20261016` < `0.8.0-nightly.20261017` < `0.8.0`.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn split(version: &str) -> (Vec<u64>, Option<&str>) {
+        let version = version.trim().trim_start_matches('v');
+        let version = version.split('+').next().unwrap_or_default();
+        let (core, pre) = match version.split_once('-') {
+            Some((core, pre)) => (core, Some(pre)),
+            None => (version, None),
+        };
+        (core.split('.').map(|n| n.parse().unwrap_or(0)).collect(), pre)
+    }
+    let (a_core, a_pre) = split(a);
+    let (b_core, b_pre) = split(b);
+    let len = a_core.len().max(b_core.len());
+    let part = |core: &[u64], i: usize| core.get(i).copied().unwrap_or(0);
+    for i in 0..len {
+        match part(&a_core, i).cmp(&part(&b_core, i)) {
+            Ordering::Equal => {}
+            other => return other,
+        }
+    }
+    match (a_pre, b_pre) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a), Some(b)) => {
+            for (x, y) in a.split('.').zip(b.split('.')) {
+                let order = match (x.parse::<u64>(), y.parse::<u64>()) {
+                    (Ok(x), Ok(y)) => x.cmp(&y),
+                    _ => x.cmp(y),
+                };
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+            a.split('.').count().cmp(&b.split('.').count())
+        }
+    }
+}
+
+/// Check a download against the feed's SHA-256 and its minisign signature
+pub fn verify(bytes: &[u8], sha256: &str, signature: &str, public_key: &str) -> Result<(), String> {
+    let actual = sha256_hex(bytes);
+    if !actual.eq_ignore_ascii_case(sha256.trim()) {
+        return Err(format!("Checksum mismatch: expected {}, got {}", sha256.trim(), actual));
+    }
+    let key = PublicKey::from_base64(public_key.trim())
+        .or_else(|_| PublicKey::decode(public_key))
+        .map_err(|e| format!("Invalid release signing key: {}", e))?;
+    let signature = Signature::decode(signature).map_err(|e| format!("Invalid signature file: {}", e))?;
+    key.verify(bytes, &signature, false)
+        .map_err(|e| format!("Signature verification failed: {}", e))
+}
+
+/// GET a URL with curl
+pub fn fetch(url: &str) -> Result<Vec<u8>, String> {
+    let output = Command::new("curl")
+        .args(["-fsSL", "--retry", "2", "--max-time", "300", url])
+        .output()
+        .map_err(|e| format!("Cannot run curl (needed to download updates): {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Download of {} failed: {}", url, stderr.trim()));
+    }
+    Ok(output.stdout)
+}
+
+/// npm installs ship the binary alongside the daemon they were built with, so
+/// they're upgraded through npm instead
+pub fn is_npm_install(exe: &Path) -> bool {
+    exe.components().any(|c| c.as_os_str() == "node_modules")
+}
+
+/// Replace `exe` with `bytes`. The new binary is written next to it and
+/// renamed into place; on Windows the running executable is moved aside to
+/// `<name>.old` first, since it can't be overwritten while running.
+pub fn replace_binary(exe: &Path, bytes: &[u8]) -> Result<(), String> {
+    let name = exe.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let staged = exe.with_file_name(format!(".{}.update", name));
+    fs::write(&staged, bytes).map_err(|e| format!("Cannot write {}: {}", staged.display(), e))?;
+    if let Ok(meta) = fs::metadata(exe) {
+        let _ = fs::set_permissions(&staged, meta.permissions());
+    }
+
+    swap_in(&staged, exe).inspect_err(|_| {
+        let _ = fs::remove_file(&staged);
+    })
+}
+
+#[cfg(not(windows))]
+fn swap_in(staged: &Path, exe: &Path) -> Result<(), String> {
+    fs::rename(staged, exe).map_err(|e| format!("Cannot replace {}: {}", exe.display(), e))
+}
+
+#[cfg(windows)]
+fn swap_in(staged: &Path, exe: &Path) -> Result<(), String> {
+    let name = exe.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let old = exe.with_file_name(format!("{}.old", name));
+    let _ = fs::remove_file(&old);
+    fs::rename(exe, &old).map_err(|e| format!("Cannot move {} aside: {}", exe.display(), e))?;
+    fs::rename(staged, exe).map_err(|e| {
+        let _ = fs::rename(&old, exe);
+        format!("Cannot replace {}: {}", exe.display(), e)
+    })
+}
+
+/// Outcome of `self-update`
+#[derive(Debug, PartialEq)]
+pub struct UpdateReport {
+    pub current: String,
+    pub latest: String,
+    pub channel: Channel,
+    pub path: PathBuf,
+    /// Whether a newer version was installed
+    pub updated: bool,
+}
+
+/// Check the feed and, unless `check_only`, install a newer binary over `exe`
+pub fn run(channel: Channel, exe: &Path, current: &str, check_only: bool) -> Result<UpdateReport, String> {
+    let release = parse_release(&fetch(&feed_url(channel))?)?;
+    let mut report = UpdateReport {
+        current: current.to_string(),
+        latest: release.version.clone(),
+        channel,
+        path: exe.to_path_buf(),
+        updated: false,
+    };
+    if check_only || compare_versions(&release.version, current) != Ordering::Greater {
+        return Ok(report);
+    }
+    if is_npm_install(exe) {
+        return Err(format!(
+            "agent-browser was installed with npm; update with: npm install -g agent-browser@{}",
+            release.version
+        ));
+    }
+    let name = asset_name();
+    let asset = release
+        .assets
+        .get(&name)
+        .ok_or_else(|| format!("Release {} has no binary for this platform ({})", release.version, name))?;
+    let key = public_key()?;
+    let bytes = fetch(&asset.url)?;
+    let signature = fetch(&format!("{}.minisig", asset.url))?;
+    verify(&bytes, &asset.sha256, &String::from_utf8_lossy(&signature), &key)?;
+    replace_binary(exe, &bytes)?;
+    report.updated = true;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Test vector from the minisign-verify crate: signature of b"test"
+    const KEY: &str = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    const SIGNATURE: &str = "untrusted comment: signature from minisign secret key
+RUQf6LRCGA9i559r3g7V1qNyJDApGip8MfqcadIgT9CuhV3EMhHoN1mGTkUidF/z7SrlQgXdy8ofjb7bNJJylDOocrCo8KLzZwo=
+trusted comment: timestamp:1556193335\tfile:test
+y/rUw2y8/hOUYjZU71eHp/Wo1KZ40fGy2VJEDl34XMJM+TX48Ss/17u3IvIfbVR1FkZZSNCisQbuQY+bHwhEBg==";
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("0.8.0", "0.7.6"), Ordering::Greater);
+        assert_eq!(compare_versions("0.7.6", "v0.7.6"), Ordering::Equal);
+        assert_eq!(compare_versions("0.7.10", "0.7.9"), Ordering::Greater);
+        assert_eq!(compare_versions("0.8.0-nightly.20261016", "0.8.0"), Ordering::Less);
+        assert_eq!(
+            compare_versions("0.8.0-nightly.20261017", "0.8.0-nightly.20261016"),
+            Ordering::Greater
+        );
+        assert_eq!(compare_versions("0.8.0-nightly.20261016", "0.7.6"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_verify() {
+        let sha = sha256_hex(b"test");
+        assert!(verify(b"test", &sha, SIGNATURE, KEY).is_ok());
+        assert!(verify(b"tost", &sha, SIGNATURE, KEY).unwrap_err().contains("Checksum mismatch"));
+        let tampered_sha = sha256_hex(b"tost");
+        assert!(verify(b"tost", &tampered_sha, SIGNATURE, KEY)
+            .unwrap_err()
+            .contains("Signature verification failed"));
+        assert!(verify(b"test", &sha, "garbage", KEY).unwrap_err().contains("Invalid signature"));
+    }
+
+    #[test]
+    fn test_parse_release() {
+        let release = parse_release(
+            br#"{"version":"0.8.0","assets":{"agent-browser-linux-x64":{"url":"https://example.com/a","sha256":"ab"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(release.version, "0.8.0");
+        assert_eq!(release.assets["agent-browser-linux-x64"].url, "https://example.com/a");
+        assert!(parse_release(b"{\"version\":\"0.8.0\"}").is_err());
+    }
+
+    #[test]
+    fn test_channel_and_names() {
+        assert_eq!(Channel::parse("nightly"), Ok(Channel::Nightly));
+        assert!(Channel::parse("beta").is_err());
+        assert!(asset_name().starts_with("agent-browser-"));
+        assert!(is_npm_install(Path::new("/usr/lib/node_modules/agent-browser/bin/agent-browser-linux-x64")));
+        assert!(!is_npm_install(Path::new("/usr/local/bin/agent-browser")));
+    }
+
+    #[test]
+    fn test_replace_binary() {
+        let dir = env::temp_dir().join(format!("ab-self-update-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let exe = dir.join("agent-browser");
+        fs::write(&exe, "old").unwrap();
+        replace_binary(&exe, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&exe).unwrap(), "new");
+        assert!(!dir.join(".agent-browser.update").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+//! Advisory locks keeping concurrent invocations on one session apart.
+//!
+//! The daemon runs one command at a time, but invocations that send several
+//! (a checkpoint, a `run` script) would still interleave with each other.
+//! Each invocation that talks to a session's daemon holds `<session>.lock`
+//! next to the session's socket until it exits, waiting for it first: for as
+//! long as it takes, or up to `--wait-lock` before failing with
+//! E_SESSION_BUSY. The holder writes who it is into the file, which is
+//! emptied on release, for `session locks`.
+
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::connection::get_socket_dir;
+
+/// Set for the child invocations of a lock holder (the commands of a `run`
+/// script) to the session whose lock they share
+const HELD_ENV: &str = "AGENT_BROWSER_SESSION_LOCK";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Holder {
+    pub pid: u32,
+    pub command: String,
+    /// When the lock was taken, in ms since the epoch
+    pub since: u64,
+}
+
+/// A held lock, released when dropped or when the process exits
+#[derive(Debug)]
+pub struct SessionLock {
+    file: Option<File>,
+}
+
+impl Drop for SessionLock {
+    fn drop(&mut self) {
+        if let Some(file) = self.file.take() {
+            let _ = file.set_len(0);
+        }
+    }
+}
+
+/// The lock was still held by another invocation when the wait ran out
+#[derive(Debug)]
+pub struct Busy {
+    /// Unknown where the platform's locks keep others from reading the file
+    pub holder: Option<Holder>,
+    pub waited: Duration,
+}
+
+impl Busy {
+    pub fn message(&self, session: &str, now: u64) -> String {
+        let holder = match &self.holder {
+            Some(h) => format!(
+                "pid {} has held it for {} running \"{}\"",
+                h.pid,
+                format_age(now.saturating_sub(h.since)),
+                h.command
+            ),
+            None => "another invocation holds it".to_string(),
+        };
+        format!(
+            "Session {} is busy: {} (waited {}ms; raise --wait-lock to wait longer)",
+            session,
+            holder,
+            self.waited.as_millis()
+        )
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct LockInfo {
+    pub session: String,
+    pub holder: Option<Holder>,
+}
+
+fn lock_path(dir: &Path, session: &str) -> PathBuf {
+    dir.join(format!("{}.lock", session))
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// "850ms", "12s", "3m"
+pub fn format_age(ms: u64) -> String {
+    match ms {
+        0..=999 => format!("{}ms", ms),
+        1000..=119_999 => format!("{}s", ms / 1000),
+        _ => format!("{}m", ms / 60_000),
+    }
+}
+
+/// Take the session's lock, waiting up to `wait` (None: until it's free).
+/// Invocations started by the holder share its lock instead.
+pub fn acquire(session: &str, command: &str, wait: Option<Duration>) -> Result<SessionLock, Busy> {
+    if env::var(HELD_ENV).as_deref() == Ok(session) {
+        return Ok(SessionLock { file: None });
+    }
+    let lock = acquire_in(&get_socket_dir(), session, command, wait)?;
+    env::set_var(HELD_ENV, session);
+    Ok(lock)
+}
+
+fn acquire_in(dir: &Path, session: &str, command: &str, wait: Option<Duration>) -> Result<SessionLock, Busy> {
+    let _ = fs::create_dir_all(dir);
+    let path = lock_path(dir, session);
+    // Locking is advisory: a socket dir it can't be created in doesn't stop the command
+    let Ok(file) = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path) else {
+        return Ok(SessionLock { file: None });
+    };
+    let started = Instant::now();
+    loop {
+        match file.try_lock() {
+            Ok(()) => break,
+            Err(TryLockError::WouldBlock) => {
+                if wait.is_some_and(|wait| started.elapsed() >= wait) {
+                    return Err(Busy { holder: read_holder(&path), waited: started.elapsed() });
+                }
+                thread::sleep(POLL_INTERVAL);
+            }
+            Err(TryLockError::Error(_)) => return Ok(SessionLock { file: None }),
+        }
+    }
+    let holder = Holder { pid: std::process::id(), command: command.to_string(), since: now_millis() };
+    let _ = file.set_len(0);
+    let _ = (&file).write_all(serde_json::to_string(&holder).unwrap_or_default().as_bytes());
+    Ok(SessionLock { file: Some(file) })
+}
+
+fn read_holder(path: &Path) -> Option<Holder> {
+    fs::read_to_string(path).ok().and_then(|source| serde_json::from_str(&source).ok())
+}
+
+/// Sessions whose lock is held right now
+pub fn list() -> Vec<LockInfo> {
+    list_in(&get_socket_dir())
+}
+
+fn list_in(dir: &Path) -> Vec<LockInfo> {
+    let mut locks = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else {
+        return locks;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(session) = name.strip_suffix(".lock") else {
+            continue;
+        };
+        let path = entry.path();
+        // Released cleanly; a crashed holder leaves its details, so check the lock
+        if fs::metadata(&path).map(|m| m.len() == 0).unwrap_or(true) {
+            continue;
+        }
+        let Ok(file) = File::open(&path) else {
+            continue;
+        };
+        if let Err(TryLockError::WouldBlock) = file.try_lock() {
+            locks.push(LockInfo { session: session.to_string(), holder: read_holder(&path) });
+        }
+    }
+    locks.sort_by(|a, b| a.session.cmp(&b.session));
+    locks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("agent-browser-lock-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_second_acquire_is_busy_until_release() {
+        let dir = temp_dir("busy");
+        let lock = acquire_in(&dir, "default", "open example.com", Some(Duration::ZERO)).unwrap();
+        let busy = acquire_in(&dir, "default", "click #buy", Some(Duration::from_millis(60))).unwrap_err();
+        let holder = busy.holder.clone().unwrap();
+        assert_eq!(holder.pid, std::process::id());
+        assert_eq!(holder.command, "open example.com");
+        assert!(busy.waited >= Duration::from_millis(60));
+        assert_eq!(list_in(&dir), vec![LockInfo { session: "default".to_string(), holder: Some(holder) }]);
+        assert!(busy.message("default", busy.holder.as_ref().unwrap().since + 4000).contains("held it for 4s"));
+
+        drop(lock);
+        assert!(list_in(&dir).is_empty());
+        assert!(acquire_in(&dir, "default", "click #buy", Some(Duration::ZERO)).is_ok());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_sessions_lock_separately() {
+        let dir = temp_dir("separate");
+        let _a = acquire_in(&dir, "a", "open a.test", Some(Duration::ZERO)).unwrap();
+        let _b = acquire_in(&dir, "b", "open b.test", Some(Duration::ZERO)).unwrap();
+        let sessions: Vec<String> = list_in(&dir).into_iter().map(|l| l.session).collect();
+        assert_eq!(sessions, vec!["a", "b"]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(850), "850ms");
+        assert_eq!(format_age(12_400), "12s");
+        assert_eq!(format_age(180_000), "3m");
+    }
+}
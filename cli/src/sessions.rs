@@ -0,0 +1,117 @@
+//! Tags and key/value metadata attached to sessions.
+//!
+//! Stored next to the session's socket and pid files as `<session>.meta.json`
+//! so `session list` can report them without asking each daemon.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::connection::get_socket_dir;
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct SessionMeta {
+    pub tags: Vec<String>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+fn meta_path(session: &str) -> PathBuf {
+    get_socket_dir().join(format!("{}.meta.json", session))
+}
+
+impl SessionMeta {
+    /// Metadata of a session, empty when none was set or the file is unreadable
+    pub fn load(session: &str) -> Self {
+        fs::read_to_string(meta_path(session))
+            .ok()
+            .and_then(|source| serde_json::from_str(&source).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, session: &str) -> Result<(), String> {
+        let path = meta_path(session);
+        if self.tags.is_empty() && self.metadata.is_empty() {
+            let _ = fs::remove_file(&path);
+            return Ok(());
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+        }
+        let source = serde_json::to_string_pretty(self).unwrap_or_default();
+        fs::write(&path, source).map_err(|e| format!("Cannot write session metadata: {}", e))
+    }
+
+    /// Apply `key=value` pairs (metadata) and bare words (tags)
+    pub fn add(&mut self, items: &[String]) -> Result<(), String> {
+        for item in items {
+            match item.split_once('=') {
+                Some((key, _)) if key.trim().is_empty() => {
+                    return Err(format!("Invalid metadata '{}': key is empty", item));
+                }
+                Some((key, value)) => {
+                    self.metadata.insert(key.trim().to_string(), value.to_string());
+                }
+                None if !self.tags.contains(item) => self.tags.push(item.clone()),
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove metadata keys and tags by name (`key` or `key=...` both work)
+    pub fn remove(&mut self, items: &[String]) {
+        for item in items {
+            let key = item.split_once('=').map(|(k, _)| k).unwrap_or(item);
+            self.metadata.remove(key.trim());
+            self.tags.retain(|tag| tag != item);
+        }
+    }
+
+    /// One-line summary for human output, e.g. `[ci] env=staging owner=bot-3`
+    pub fn summary(&self) -> String {
+        let tags = (!self.tags.is_empty()).then(|| format!("[{}]", self.tags.join(", ")));
+        let pairs = self.metadata.iter().map(|(k, v)| format!("{}={}", k, v));
+        tags.into_iter().chain(pairs).collect::<Vec<_>>().join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_add_tags_and_metadata() {
+        let mut meta = SessionMeta::default();
+        meta.add(&args(&["env=staging", "owner=bot-3", "ci", "ci", "note=a=b"])).unwrap();
+        assert_eq!(meta.tags, vec!["ci"]);
+        assert_eq!(meta.metadata["env"], "staging");
+        assert_eq!(meta.metadata["note"], "a=b");
+        assert_eq!(meta.summary(), "[ci] env=staging note=a=b owner=bot-3");
+        assert!(meta.add(&args(&["=x"])).is_err());
+    }
+
+    #[test]
+    fn test_remove() {
+        let mut meta = SessionMeta::default();
+        meta.add(&args(&["env=staging", "owner=bot-3", "ci"])).unwrap();
+        meta.remove(&args(&["env", "owner=bot-3", "ci"]));
+        assert_eq!(meta, SessionMeta::default());
+        assert_eq!(meta.summary(), "");
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let mut meta = SessionMeta::default();
+        meta.add(&args(&["env=staging", "ci"])).unwrap();
+        let value = serde_json::to_value(&meta).unwrap();
+        assert_eq!(value, serde_json::json!({ "tags": ["ci"], "metadata": { "env": "staging" } }));
+        let parsed: SessionMeta = serde_json::from_str(r#"{ "tags": ["x"] }"#).unwrap();
+        assert!(parsed.metadata.is_empty());
+    }
+}
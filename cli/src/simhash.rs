@@ -0,0 +1,110 @@
+//! Near-duplicate detection for `map --dedupe simhash`.
+//!
+//! A simhash is a 64-bit fingerprint of a text's three-word shingles in
+//! which similar texts differ in few bits, so pages that only differ in a
+//! date, a counter or a tracking parameter land a few bits apart while
+//! different pages are around 32 apart.
+
+/// Bits two fingerprints may differ in and still count as duplicates,
+/// when --threshold isn't given
+pub const DEFAULT_THRESHOLD: u32 = 3;
+
+/// Words per shingle
+const SHINGLE: usize = 3;
+
+/// FNV-1a: stable across runs and platforms, unlike the std hasher
+fn hash(text: &str) -> u64 {
+    text.bytes()
+        .fold(0xcbf29ce484222325, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+/// Fingerprint of a text, ignoring case, punctuation and whitespace
+pub fn fingerprint(text: &str) -> u64 {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    let shingles: Vec<String> = if words.len() < SHINGLE {
+        vec![words.join(" ")]
+    } else {
+        words.windows(SHINGLE).map(|w| w.join(" ")).collect()
+    };
+    let mut weights = [0i64; 64];
+    for shingle in &shingles {
+        let h = hash(shingle);
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            *weight += if h >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    }
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, w)| **w > 0)
+        .fold(0, |fp, (bit, _)| fp | 1 << bit)
+}
+
+/// Bits in which two fingerprints differ
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Fingerprints seen so far, with what they belong to
+#[derive(Default)]
+pub struct Index<T> {
+    seen: Vec<(u64, T)>,
+}
+
+impl<T> Index<T> {
+    /// The first earlier item within `threshold` bits of `fingerprint`, if
+    /// any; otherwise `fingerprint` is remembered for `item`
+    pub fn check(&mut self, fingerprint: u64, threshold: u32, item: T) -> Option<&T> {
+        match self.seen.iter().position(|(fp, _)| distance(*fp, fingerprint) <= threshold) {
+            Some(i) => Some(&self.seen[i].1),
+            None => {
+                self.seen.push((fingerprint, item));
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTICLE: &str = "The city council met on Tuesday to discuss the new budget for parks, \
+        libraries and road repairs. After a long debate the members agreed to raise library \
+        funding by ten percent while postponing the road repairs until next spring, citing \
+        rising material costs and a shortage of contractors in the region.";
+
+    #[test]
+    fn test_near_duplicates_are_close() {
+        let a = fingerprint(ARTICLE);
+        let b = fingerprint(&ARTICLE.replace("Tuesday", "Wednesday"));
+        let c = fingerprint(&format!("{} Updated 10:42.", ARTICLE));
+        assert!(distance(a, b) <= DEFAULT_THRESHOLD, "{}", distance(a, b));
+        assert!(distance(a, c) <= DEFAULT_THRESHOLD, "{}", distance(a, c));
+        let other = fingerprint(
+            "Our new espresso machine grinds beans fresh for every cup and heats up in under \
+             thirty seconds, with a steam wand for milk and a removable tank that is easy to fill.",
+        );
+        assert!(distance(a, other) > 16, "{}", distance(a, other));
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_case_and_punctuation() {
+        assert_eq!(fingerprint("Hello, World! How are you?"), fingerprint("hello world how are you"));
+        assert_eq!(fingerprint(ARTICLE), fingerprint(ARTICLE));
+    }
+
+    #[test]
+    fn test_index() {
+        let mut index = Index::default();
+        assert_eq!(index.check(0b1111, 1, 1), None);
+        assert_eq!(index.check(0b1110, 1, 2), Some(&1));
+        assert_eq!(index.check(0b0000, 1, 3), None);
+        // Duplicates aren't remembered, so they match the original only
+        assert_eq!(index.check(0b1101, 1, 4), Some(&1));
+    }
+}
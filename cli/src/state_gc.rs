@@ -0,0 +1,184 @@
+//! Expiry of persisted session state files.
+//!
+//! With --session-name, the daemon saves cookies and storage to
+//! `~/.agent-browser/sessions/<name>-<session>.json`. `state gc` deletes the
+//! files that haven't been written for longer than the TTL (--state-ttl, or
+//! AGENT_BROWSER_STATE_EXPIRE_DAYS, default 30 days), with their
+//! `<file>.bak.<n>` backups, and reports the space reclaimed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::connection::app_data_dir;
+
+/// TTL used when neither --state-ttl nor AGENT_BROWSER_STATE_EXPIRE_DAYS is set
+pub const DEFAULT_TTL: Duration = Duration::from_secs(30 * 86400);
+
+/// Directory of persisted state (AGENT_BROWSER_STATE_DIR or ~/.agent-browser/sessions)
+pub fn sessions_dir() -> PathBuf {
+    if let Ok(dir) = std::env::var("AGENT_BROWSER_STATE_DIR") {
+        if !dir.is_empty() {
+            return PathBuf::from(dir);
+        }
+    }
+    app_data_dir().join("sessions")
+}
+
+/// Parse a duration such as `7d`, `12h`, `30m`, `45s` or `2w`. A bare number
+/// is a number of days.
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" | "" => 86400,
+        "w" => 7 * 86400,
+        _ => 0,
+    };
+    match number.parse::<u64>() {
+        Ok(n) if n > 0 && seconds > 0 => Ok(Duration::from_secs(n * seconds)),
+        _ => Err(format!(
+            "Invalid duration '{}': use a number with s, m, h, d or w (e.g. 7d)",
+            value
+        )),
+    }
+}
+
+/// TTL in whole days for the daemon's own startup cleanup, rounded up
+pub fn ttl_days(ttl: Duration) -> u64 {
+    ttl.as_secs().div_ceil(86400)
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct GcReport {
+    /// File names of the expired state files
+    pub expired: Vec<String>,
+    /// Total size of the expired files in bytes
+    pub bytes: u64,
+    /// Number of state files left
+    pub kept: usize,
+}
+
+/// Find state files in `dir` older than `ttl` and delete them unless `dry_run`
+pub fn collect(dir: &Path, ttl: Duration, now: SystemTime, dry_run: bool) -> Result<GcReport, String> {
+    let mut report = GcReport::default();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+        Err(e) => return Err(format!("Cannot read {}: {}", dir.display(), e)),
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(meta) = entry.metadata() else { continue };
+        if !name.ends_with(".json") || !meta.is_file() {
+            continue;
+        }
+        let modified = meta.modified().unwrap_or(now);
+        let age = now.duration_since(modified).unwrap_or_default();
+        if age <= ttl {
+            report.kept += 1;
+            continue;
+        }
+        if !dry_run {
+            fs::remove_file(entry.path()).map_err(|e| format!("Cannot delete {}: {}", name, e))?;
+        }
+        report.bytes += meta.len() + remove_backups(dir, &name, dry_run)?;
+        report.expired.push(name);
+    }
+    report.expired.sort();
+    Ok(report)
+}
+
+/// Delete the backups of a state file unless `dry_run`, returning their size
+fn remove_backups(dir: &Path, name: &str, dry_run: bool) -> Result<u64, String> {
+    let prefix = format!("{}.bak.", name);
+    let mut bytes = 0;
+    for entry in fs::read_dir(dir).map_err(|e| format!("Cannot read {}: {}", dir.display(), e))?.flatten() {
+        let backup = entry.file_name().to_string_lossy().to_string();
+        if !backup.starts_with(&prefix) {
+            continue;
+        }
+        bytes += entry.metadata().map(|m| m.len()).unwrap_or(0);
+        if !dry_run {
+            fs::remove_file(entry.path()).map_err(|e| format!("Cannot delete {}: {}", backup, e))?;
+        }
+    }
+    Ok(bytes)
+}
+
+/// Human readable size, e.g. `1.5 MB`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("7d"), Ok(Duration::from_secs(7 * 86400)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_duration("2w"), Ok(Duration::from_secs(14 * 86400)));
+        assert_eq!(parse_duration("3"), Ok(Duration::from_secs(3 * 86400)));
+        assert!(parse_duration("0d").is_err());
+        assert!(parse_duration("7 days").is_err());
+        assert!(parse_duration("d").is_err());
+    }
+
+    #[test]
+    fn test_ttl_days() {
+        assert_eq!(ttl_days(Duration::from_secs(7 * 86400)), 7);
+        assert_eq!(ttl_days(Duration::from_secs(3600)), 1);
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MB");
+    }
+
+    #[test]
+    fn test_collect_expired() {
+        let dir = std::env::temp_dir().join(format!("ab-state-gc-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("old-default.json"), "{}").unwrap();
+        fs::write(dir.join("old-default.json.bak.1"), "{}").unwrap();
+        fs::write(dir.join("notes.txt"), "x").unwrap();
+
+        // Everything was just written, so with "now" a day ahead a 1h TTL expires it
+        let later = SystemTime::now() + Duration::from_secs(86400);
+        let ttl = Duration::from_secs(3600);
+        let report = collect(&dir, ttl, later, true).unwrap();
+        assert_eq!(report.expired, vec!["old-default.json"]);
+        assert_eq!(report.bytes, 4);
+        assert!(dir.join("old-default.json").exists());
+
+        let report = collect(&dir, Duration::from_secs(7 * 86400), later, false).unwrap();
+        assert_eq!((report.expired.len(), report.kept), (0, 1));
+
+        collect(&dir, ttl, later, false).unwrap();
+        assert!(!dir.join("old-default.json").exists());
+        assert!(!dir.join("old-default.json.bak.1").exists());
+        assert!(dir.join("notes.txt").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(collect(&dir, ttl, later, false).unwrap(), GcReport::default());
+    }
+}
@@ -0,0 +1,140 @@
+//! Access tokens for daemons shared by several agents: `token create`,
+//! `token list` and `token revoke`.
+//!
+//! Tokens live in tokens.json in the app data directory (or
+//! AGENT_BROWSER_TOKENS_FILE), which every daemon rereads for each command.
+//! Once it lists a token, commands must carry one (--token or
+//! AGENT_BROWSER_TOKEN): its role limits the actions it may run and its
+//! session patterns the sessions it can use. Only a SHA-256 of each token is
+//! kept, so `create` prints the token the one time it exists.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::connection::app_data_dir;
+use crate::oauth::random_token;
+use crate::secrets::write_private;
+
+/// From least to most: read the page, use it, manage the session
+pub const ROLES: &[&str] = &["read-only", "interact", "admin"];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AccessToken {
+    pub name: String,
+    pub sha256: String,
+    pub role: String,
+    /// Session names with * wildcards; every session when absent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sessions: Option<Vec<String>>,
+    /// ms since the epoch
+    #[serde(default)]
+    pub created: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct TokenFile {
+    #[serde(default)]
+    pub tokens: Vec<AccessToken>,
+}
+
+pub fn tokens_path() -> PathBuf {
+    match env::var("AGENT_BROWSER_TOKENS_FILE") {
+        Ok(path) if !path.is_empty() => PathBuf::from(path),
+        _ => app_data_dir().join("tokens.json"),
+    }
+}
+
+pub fn hash(token: &str) -> String {
+    Sha256::digest(token.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn load(path: &Path) -> Result<TokenFile, String> {
+    match fs::read_to_string(path) {
+        Ok(source) => serde_json::from_str(&source).map_err(|e| format!("Tokens file {} is unreadable: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(TokenFile::default()),
+        Err(e) => Err(format!("Cannot read {}: {}", path.display(), e)),
+    }
+}
+
+/// Write the file, readable by the user only. With no tokens left it stays,
+/// empty, so daemons go back to accepting commands without one.
+pub fn save(path: &Path, file: &TokenFile) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    }
+    let source = serde_json::to_string_pretty(file).unwrap_or_default();
+    write_private(path, source.as_bytes()).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+}
+
+impl TokenFile {
+    /// Add a token and return it; it can't be recovered from the file later
+    pub fn create(&mut self, name: &str, role: &str, sessions: Option<Vec<String>>, now: u64) -> Result<String, String> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("Invalid token name '{}': use letters, digits, '-' and '_'", name));
+        }
+        if !ROLES.contains(&role) {
+            return Err(format!("Invalid role '{}': use {}", role, ROLES.join(", ")));
+        }
+        if self.tokens.iter().any(|t| t.name == name) {
+            return Err(format!("A token named {} already exists; revoke it first", name));
+        }
+        let token = random_token();
+        self.tokens.push(AccessToken {
+            name: name.to_string(),
+            sha256: hash(&token),
+            role: role.to_string(),
+            sessions,
+            created: now,
+        });
+        Ok(token)
+    }
+
+    /// Remove a token by name; false when there was none
+    pub fn revoke(&mut self, name: &str) -> bool {
+        let before = self.tokens.len();
+        self.tokens.retain(|t| t.name != name);
+        self.tokens.len() < before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_and_revoke() {
+        let mut file = TokenFile::default();
+        let token = file.create("agent-a", "interact", Some(vec!["agent-a-*".to_string()]), 1).unwrap();
+        assert_eq!(file.tokens[0].sha256, hash(&token));
+        assert_ne!(file.tokens[0].sha256, token);
+        assert!(file.create("agent-a", "admin", None, 2).unwrap_err().contains("already exists"));
+        assert!(file.create("ops", "owner", None, 2).unwrap_err().contains("Invalid role"));
+        assert!(file.create("a b", "admin", None, 2).is_err());
+
+        assert!(file.revoke("agent-a"));
+        assert!(!file.revoke("agent-a"));
+        assert!(file.tokens.is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = env::temp_dir().join(format!("agent-browser-tokens-{}", std::process::id()));
+        let path = dir.join("tokens.json");
+        assert!(load(&path).unwrap().tokens.is_empty());
+        let mut file = TokenFile::default();
+        file.create("viewer", "read-only", None, 5).unwrap();
+        save(&path, &file).unwrap();
+        let source = fs::read_to_string(&path).unwrap();
+        assert!(!source.contains("sessions"));
+        assert_eq!(load(&path).unwrap().tokens, file.tokens);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_hash_is_sha256_hex() {
+        assert_eq!(hash("abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+}
@@ -0,0 +1,325 @@
+//! Session transcripts rendered from the audit log.
+//!
+//! `transcript` turns the entries recorded with --audit into a Markdown or
+//! HTML report: one step per command with its result, the page it ended on
+//! and any screenshots it produced. HTML reports embed screenshots so the
+//! file can be shared on its own.
+
+use serde_json::Value;
+use std::fs;
+
+use crate::audit;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "md" | "markdown" => Some(Format::Markdown),
+            "html" => Some(Format::Html),
+            _ => None,
+        }
+    }
+
+    /// Format implied by an output file name, defaulting to Markdown
+    pub fn from_path(path: &str) -> Self {
+        let lower = path.to_lowercase();
+        if lower.ends_with(".html") || lower.ends_with(".htm") {
+            Format::Html
+        } else {
+            Format::Markdown
+        }
+    }
+}
+
+/// One command of the transcript, extracted from an audit entry
+struct Step<'a> {
+    seq: u64,
+    time: String,
+    action: &'a str,
+    args: String,
+    success: bool,
+    error: Option<&'a str>,
+    title: Option<&'a str>,
+    url: Option<&'a str>,
+    files: Vec<&'a str>,
+}
+
+impl<'a> Step<'a> {
+    fn from_entry(entry: &'a Value) -> Self {
+        let str_at = |value: &'a Value, key: &str| value.get(key).and_then(|v| v.as_str());
+        let mut command = entry.get("command").cloned().unwrap_or_default();
+        if let Some(map) = command.as_object_mut() {
+            map.remove("action");
+        }
+        let page = entry.get("page").unwrap_or(&Value::Null);
+        let artifacts = entry.get("artifacts").and_then(|v| v.as_array());
+        Step {
+            seq: entry.get("seq").and_then(|v| v.as_u64()).unwrap_or(0),
+            time: audit::format_timestamp(entry.get("ts").and_then(|v| v.as_u64()).unwrap_or(0)),
+            action: str_at(entry, "action").unwrap_or("unknown"),
+            args: serde_json::to_string(&command).unwrap_or_default(),
+            success: entry.get("success").and_then(|v| v.as_bool()).unwrap_or(false),
+            error: str_at(entry, "error"),
+            title: str_at(page, "title"),
+            url: str_at(page, "url"),
+            files: artifacts.into_iter().flatten().filter_map(|a| str_at(a, "path")).collect(),
+        }
+    }
+}
+
+fn is_image(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    [".png", ".jpg", ".jpeg", ".webp", ".gif"].iter().any(|ext| lower.ends_with(ext))
+}
+
+fn summary(steps: &[Step]) -> String {
+    let failed = steps.iter().filter(|s| !s.success).count();
+    match (steps.first(), steps.last()) {
+        (Some(first), Some(last)) => format!(
+            "{} commands from {} to {} UTC, {} succeeded, {} failed",
+            steps.len(),
+            first.time,
+            last.time,
+            steps.len() - failed,
+            failed
+        ),
+        _ => "No commands recorded".to_string(),
+    }
+}
+
+pub fn render(session: &str, entries: &[Value], format: Format) -> String {
+    let steps: Vec<Step> = entries.iter().map(Step::from_entry).collect();
+    match format {
+        Format::Markdown => render_markdown(session, &steps),
+        Format::Html => render_html(session, &steps),
+    }
+}
+
+fn render_markdown(session: &str, steps: &[Step]) -> String {
+    let mut out = format!("# agent-browser transcript: {}\n\n{}\n", session, summary(steps));
+    for step in steps {
+        let status = if step.success { "✓" } else { "✗" };
+        out.push_str(&format!("\n## {}. {} {}\n\n", step.seq, step.action, status));
+        out.push_str(&format!("- Time: {} UTC\n", step.time));
+        if step.args != "{}" {
+            out.push_str(&format!("- Command: `{}`\n", step.args.replace('`', "'")));
+        }
+        match (step.title, step.url) {
+            (Some(title), Some(url)) => out.push_str(&format!("- Page: {} ({})\n", title, url)),
+            (Some(title), None) => out.push_str(&format!("- Page: {}\n", title)),
+            (None, Some(url)) => out.push_str(&format!("- Page: {}\n", url)),
+            (None, None) => {}
+        }
+        if let Some(error) = step.error {
+            out.push_str(&format!("- Error: {}\n", error));
+        }
+        for file in &step.files {
+            if is_image(file) {
+                out.push_str(&format!("\n![{} {}]({})\n", step.action, step.seq, file));
+            } else {
+                out.push_str(&format!("- File: {}\n", file));
+            }
+        }
+    }
+    out
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Standard base64 back to bytes; None for anything that isn't base64
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut bits, mut n) = (0u32, 0u32);
+    for c in text.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        bits = bits << 6 | value as u32;
+        n += 6;
+        if n >= 8 {
+            n -= 8;
+            out.push((bits >> n) as u8);
+            bits &= (1 << n) - 1;
+        }
+    }
+    Some(out)
+}
+
+/// Screenshot as a data URI, or its path when the file can't be read
+fn image_src(path: &str) -> String {
+    let mime = match path.rsplit('.').next().map(|e| e.to_lowercase()).as_deref() {
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        _ => "image/png",
+    };
+    match fs::read(path) {
+        Ok(bytes) => format!("data:{};base64,{}", mime, base64_encode(&bytes)),
+        Err(_) => escape_html(path),
+    }
+}
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:960px;margin:2em auto;padding:0 1em;color:#222}\
+section{border-top:1px solid #ddd;padding:.5em 0}\
+h2 .ok{color:#1a7f37}h2 .fail{color:#cf222e}\
+code{background:#f4f4f4;padding:.1em .3em;word-break:break-all}\
+img{max-width:100%;border:1px solid #ddd}.meta{color:#666}";
+
+fn render_html(session: &str, steps: &[Step]) -> String {
+    let title = format!("agent-browser transcript: {}", escape_html(session));
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n<p class=\"meta\">{}</p>\n",
+        title,
+        STYLE,
+        title,
+        summary(steps)
+    );
+    for step in steps {
+        let status = if step.success {
+            "<span class=\"ok\">✓</span>"
+        } else {
+            "<span class=\"fail\">✗</span>"
+        };
+        out.push_str(&format!(
+            "<section>\n<h2>{}. {} {}</h2>\n<p class=\"meta\">{} UTC</p>\n",
+            step.seq,
+            escape_html(step.action),
+            status,
+            step.time
+        ));
+        if step.args != "{}" {
+            out.push_str(&format!("<p><code>{}</code></p>\n", escape_html(&step.args)));
+        }
+        if step.title.is_some() || step.url.is_some() {
+            let title = step.title.or(step.url).unwrap_or_default();
+            match step.url {
+                Some(url) => out.push_str(&format!(
+                    "<p>Page: <a href=\"{}\">{}</a></p>\n",
+                    escape_html(url),
+                    escape_html(title)
+                )),
+                None => out.push_str(&format!("<p>Page: {}</p>\n", escape_html(title))),
+            }
+        }
+        if let Some(error) = step.error {
+            out.push_str(&format!("<p>Error: {}</p>\n", escape_html(error)));
+        }
+        for file in &step.files {
+            if is_image(file) {
+                out.push_str(&format!(
+                    "<img src=\"{}\" alt=\"{}\">\n",
+                    image_src(file),
+                    escape_html(file)
+                ));
+            } else {
+                out.push_str(&format!("<p>File: <code>{}</code></p>\n", escape_html(file)));
+            }
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entries() -> Vec<Value> {
+        vec![
+            json!({
+                "seq": 1, "ts": 0, "action": "navigate", "success": true,
+                "command": { "action": "navigate", "url": "https://example.com" },
+                "page": { "url": "https://example.com/", "title": "Example <Domain>" },
+                "artifacts": [],
+            }),
+            json!({
+                "seq": 2, "ts": 1000, "action": "screenshot", "success": true,
+                "command": { "action": "screenshot", "path": "/tmp/missing-shot.png" },
+                "page": null,
+                "artifacts": [{ "path": "/tmp/missing-shot.png", "sha256": null }],
+            }),
+            json!({
+                "seq": 3, "ts": 2000, "action": "click", "success": false, "error": "Timeout",
+                "command": { "action": "click", "selector": "#buy" },
+                "artifacts": [],
+            }),
+        ]
+    }
+
+    #[test]
+    fn test_markdown_transcript() {
+        let md = render("default", &entries(), Format::Markdown);
+        assert!(md.starts_with("# agent-browser transcript: default\n"));
+        assert!(md.contains("3 commands from 1970-01-01 00:00:00 to 1970-01-01 00:00:02 UTC, 2 succeeded, 1 failed"));
+        assert!(md.contains("## 1. navigate ✓"));
+        assert!(md.contains("- Page: Example <Domain> (https://example.com/)"));
+        assert!(md.contains("![screenshot 2](/tmp/missing-shot.png)"));
+        assert!(md.contains("## 3. click ✗"));
+        assert!(md.contains("- Error: Timeout"));
+    }
+
+    #[test]
+    fn test_html_transcript_escapes() {
+        let html = render("default", &entries(), Format::Html);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("Example &lt;Domain&gt;"));
+        assert!(html.contains("<code>{&quot;selector&quot;:&quot;#buy&quot;}</code>"));
+        // Unreadable screenshots fall back to their path
+        assert!(html.contains("<img src=\"/tmp/missing-shot.png\""));
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64_decode("Zm8="), Some(b"fo".to_vec()));
+        assert_eq!(base64_decode(&base64_encode(&[0, 255, 128, 7])), Some(vec![0, 255, 128, 7]));
+        assert_eq!(base64_decode("not base64!"), None);
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(Format::parse("MD"), Some(Format::Markdown));
+        assert_eq!(Format::parse("pdf"), None);
+        assert_eq!(Format::from_path("report.HTML"), Format::Html);
+        assert_eq!(Format::from_path("report.md"), Format::Markdown);
+    }
+}
@@ -0,0 +1,167 @@
+//! Download and upload limits.
+//!
+//! --max-download and --block-download-types are attached to every download
+//! command and enforced by the daemon, which sees the response size and MIME
+//! type. --upload-dirs is checked here: every file passed to `upload` must
+//! resolve to a path inside one of the allowed directories.
+
+use crate::budget::parse_size;
+use serde_json::{json, Value};
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Limits parsed from --max-download, --block-download-types and --upload-dirs
+#[derive(Debug, Default, PartialEq)]
+pub struct TransferPolicy {
+    pub max_download_bytes: Option<u64>,
+    pub blocked_types: Vec<String>,
+    pub upload_dirs: Vec<PathBuf>,
+}
+
+/// An upload rejected by --upload-dirs
+#[derive(Debug, PartialEq)]
+pub struct UploadViolation {
+    pub file: String,
+    pub reason: String,
+}
+
+/// Split a comma separated list of MIME types or `type/*` patterns. The daemon
+/// matches them against the download's Content-Type, ignoring parameters.
+pub fn parse_mime_list(list: &str) -> Result<Vec<String>, String> {
+    list.split(',')
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .map(|t| match t.split_once('/') {
+            Some((kind, sub)) if !kind.is_empty() && !sub.is_empty() && kind != "*" => Ok(t),
+            _ => Err(format!(
+                "Invalid MIME type '{}': use type/subtype or type/*",
+                t
+            )),
+        })
+        .collect()
+}
+
+impl TransferPolicy {
+    pub fn from_flags(
+        max_download: Option<&str>,
+        blocked_types: Option<&str>,
+        upload_dirs: Option<&str>,
+    ) -> Result<Self, String> {
+        let upload_dirs = match upload_dirs {
+            Some(dirs) => env::split_paths(dirs)
+                .filter(|d| !d.as_os_str().is_empty())
+                .map(|d| {
+                    d.canonicalize()
+                        .map_err(|e| format!("Invalid --upload-dirs entry '{}': {}", d.display(), e))
+                })
+                .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        Ok(TransferPolicy {
+            max_download_bytes: max_download
+                .map(|size| parse_size("--max-download", size))
+                .transpose()?,
+            blocked_types: blocked_types.map(parse_mime_list).transpose()?.unwrap_or_default(),
+            upload_dirs,
+        })
+    }
+
+    /// Check the files of an upload command against --upload-dirs
+    pub fn check_upload(&self, cmd: &Value) -> Result<(), UploadViolation> {
+        if self.upload_dirs.is_empty() || cmd.get("action").and_then(|v| v.as_str()) != Some("upload") {
+            return Ok(());
+        }
+        let files = cmd.get("files").and_then(|v| v.as_array()).into_iter().flatten();
+        for file in files.filter_map(|f| f.as_str()) {
+            let resolved = Path::new(file).canonicalize().map_err(|e| UploadViolation {
+                file: file.to_string(),
+                reason: format!("Cannot read upload file '{}': {}", file, e),
+            })?;
+            if !self.upload_dirs.iter().any(|dir| resolved.starts_with(dir)) {
+                return Err(UploadViolation {
+                    file: file.to_string(),
+                    reason: format!("Upload blocked: '{}' is outside --upload-dirs", file),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Attach the download limits to download commands for the daemon to enforce
+    pub fn apply_download_limits(&self, cmd: &mut Value) {
+        let action = cmd.get("action").and_then(|v| v.as_str());
+        if !matches!(action, Some("download") | Some("waitfordownload")) {
+            return;
+        }
+        if let Some(max) = self.max_download_bytes {
+            cmd["maxBytes"] = json!(max);
+        }
+        if !self.blocked_types.is_empty() {
+            cmd["blockedTypes"] = json!(self.blocked_types);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_max_download_takes_sizes() {
+        let policy = TransferPolicy::from_flags(Some("10MB"), None, None).unwrap();
+        assert_eq!(policy.max_download_bytes, Some(10 * 1024 * 1024));
+        let policy = TransferPolicy::from_flags(Some("512kb"), None, None).unwrap();
+        assert_eq!(policy.max_download_bytes, Some(512 * 1024));
+        assert!(TransferPolicy::from_flags(Some("0"), None, None).is_err());
+        assert!(TransferPolicy::from_flags(Some("10 parsecs"), None, None).is_err());
+    }
+
+    #[test]
+    fn test_parse_mime_list() {
+        let types = parse_mime_list("application/x-msdownload, Video/*").unwrap();
+        assert_eq!(types, vec!["application/x-msdownload", "video/*"]);
+        assert!(parse_mime_list("exe").is_err());
+        assert!(parse_mime_list("*/*").is_err());
+    }
+
+    #[test]
+    fn test_download_limits_are_attached() {
+        let policy = TransferPolicy::from_flags(Some("10MB"), Some("video/*"), None).unwrap();
+        let mut cmd = json!({ "action": "download", "selector": "#a", "path": "./a.mp4" });
+        policy.apply_download_limits(&mut cmd);
+        assert_eq!(cmd["maxBytes"], 10 * 1024 * 1024);
+        assert_eq!(cmd["blockedTypes"], json!(["video/*"]));
+
+        let mut click = json!({ "action": "click", "selector": "#a" });
+        policy.apply_download_limits(&mut click);
+        assert!(click.get("maxBytes").is_none());
+    }
+
+    #[test]
+    fn test_upload_dirs() {
+        let root = env::temp_dir().join(format!("ab-upload-test-{}", std::process::id()));
+        let allowed = root.join("allowed");
+        fs::create_dir_all(&allowed).unwrap();
+        fs::write(allowed.join("ok.txt"), "ok").unwrap();
+        fs::write(root.join("secret.txt"), "no").unwrap();
+
+        let dirs = allowed.to_str().unwrap();
+        let policy = TransferPolicy::from_flags(None, None, Some(dirs)).unwrap();
+        let ok = allowed.join("ok.txt");
+        let cmd = json!({ "action": "upload", "selector": "#f", "files": [ok] });
+        assert!(policy.check_upload(&cmd).is_ok());
+
+        // Escaping with .. is resolved before the check
+        let escape = allowed.join("../secret.txt");
+        let cmd = json!({ "action": "upload", "selector": "#f", "files": [ok, escape] });
+        let err = policy.check_upload(&cmd).unwrap_err();
+        assert!(err.reason.contains("outside --upload-dirs"));
+
+        let missing = allowed.join("missing.txt");
+        let cmd = json!({ "action": "upload", "selector": "#f", "files": [missing] });
+        assert!(policy.check_upload(&cmd).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,312 @@
+//! Artifact upload to object storage (--upload).
+//!
+//! With --upload s3://bucket/prefix or gs://bucket/prefix, the files that
+//! screenshot, pdf, video/recording stop and record gif stop produce are
+//! uploaded under the prefix once written, and the object's URI and HTTPS URL
+//! are added to the result as `upload`. Credentials come from the usual places:
+//!
+//! - S3: AWS_ACCESS_KEY_ID / AWS_SECRET_ACCESS_KEY (/ AWS_SESSION_TOKEN), else
+//!   the ECS container or EC2 instance metadata endpoints; the region from
+//!   AWS_REGION or AWS_DEFAULT_REGION, and AWS_ENDPOINT_URL_S3 or
+//!   AWS_ENDPOINT_URL for S3-compatible stores such as MinIO.
+//! - GCS: GOOGLE_OAUTH_ACCESS_TOKEN, else the GCE metadata server, else
+//!   `gcloud auth print-access-token`.
+//!
+//! Requests go through `curl` (7.75+ for S3 signing), with credentials passed
+//! on its stdin rather than its command line.
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::oauth::percent_encode;
+
+/// Seconds curl may spend on one upload
+const TIMEOUT_SECS: &str = "300";
+
+/// Seconds to wait for a metadata endpoint, which only answers on cloud hosts
+const METADATA_TIMEOUT_SECS: &str = "2";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Store {
+    S3,
+    Gcs,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub store: Store,
+    pub bucket: String,
+    /// Key prefix without leading or trailing slashes, possibly empty
+    pub prefix: String,
+}
+
+impl Target {
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let (store, rest) = if let Some(rest) = url.strip_prefix("s3://") {
+            (Store::S3, rest)
+        } else if let Some(rest) = url.strip_prefix("gs://") {
+            (Store::Gcs, rest)
+        } else {
+            return Err(format!("Invalid --upload '{}': use s3://bucket/prefix or gs://bucket/prefix", url));
+        };
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() {
+            return Err(format!("Invalid --upload '{}': missing bucket", url));
+        }
+        Ok(Target { store, bucket: bucket.to_string(), prefix: prefix.trim_matches('/').to_string() })
+    }
+
+    /// The object key for a local file: the prefix and the file name
+    pub fn key(&self, path: &Path) -> String {
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        match self.prefix.as_str() {
+            "" => name,
+            prefix => format!("{}/{}", prefix, name),
+        }
+    }
+
+    pub fn uri(&self, key: &str) -> String {
+        let scheme = match self.store {
+            Store::S3 => "s3",
+            Store::Gcs => "gs",
+        };
+        format!("{}://{}/{}", scheme, self.bucket, key)
+    }
+}
+
+/// The file a command's result refers to, when it produced an artifact
+pub fn artifact_path<'a>(action: &str, data: &'a Value) -> Option<&'a str> {
+    let field = match action {
+        "screenshot" | "pdf" | "recording_stop" | "video_stop" | "gif_stop" => "path",
+        "recording_restart" => "previousPath",
+        _ => return None,
+    };
+    data.get(field).and_then(|v| v.as_str()).filter(|p| !p.is_empty())
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let ext = path.extension().map(|e| e.to_string_lossy().to_ascii_lowercase()).unwrap_or_default();
+    match ext.as_str() {
+        "png" | "apng" => "image/png",
+        "gif" => "image/gif",
+        "jpg" | "jpeg" => "image/jpeg",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "webm" => "video/webm",
+        "mp4" => "video/mp4",
+        "json" => "application/json",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// A key's path segments percent-encoded, slashes kept
+fn encode_key(key: &str) -> String {
+    key.split('/').map(percent_encode).collect::<Vec<_>>().join("/")
+}
+
+/// A value quoted for a curl config file
+fn config_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+struct AwsCredentials {
+    key_id: String,
+    secret: String,
+    token: Option<String>,
+}
+
+/// Credentials in the JSON the ECS and EC2 metadata endpoints return
+fn aws_credentials_from_json(value: &Value) -> Option<AwsCredentials> {
+    let field = |name: &str| value.get(name).and_then(|v| v.as_str()).map(String::from);
+    Some(AwsCredentials { key_id: field("AccessKeyId")?, secret: field("SecretAccessKey")?, token: field("Token") })
+}
+
+/// A metadata request with curl; None on any failure
+fn fetch(url: &str, method: &str, headers: &[&str]) -> Option<String> {
+    let mut cmd = Command::new("curl");
+    cmd.args(["-fsS", "--max-time", METADATA_TIMEOUT_SECS, "-X", method, url]);
+    for header in headers {
+        cmd.arg("-H").arg(header);
+    }
+    let output = cmd.stderr(Stdio::null()).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn aws_credentials() -> Result<AwsCredentials, String> {
+    if let (Ok(key_id), Ok(secret)) = (env::var("AWS_ACCESS_KEY_ID"), env::var("AWS_SECRET_ACCESS_KEY")) {
+        return Ok(AwsCredentials { key_id, secret, token: env::var("AWS_SESSION_TOKEN").ok() });
+    }
+    if let Ok(uri) = env::var("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI") {
+        let body = fetch(&format!("http://169.254.170.2{}", uri), "GET", &[]);
+        if let Some(creds) = body.and_then(|b| serde_json::from_str(&b).ok()).and_then(|v| aws_credentials_from_json(&v)) {
+            return Ok(creds);
+        }
+    }
+    // EC2 instance metadata (IMDSv2)
+    let base = "http://169.254.169.254/latest";
+    let creds = fetch(&format!("{}/api/token", base), "PUT", &["X-aws-ec2-metadata-token-ttl-seconds: 300"])
+        .and_then(|token| {
+            let auth = format!("X-aws-ec2-metadata-token: {}", token);
+            let roles_url = format!("{}/meta-data/iam/security-credentials/", base);
+            let role = fetch(&roles_url, "GET", &[&auth])?.lines().next()?.to_string();
+            fetch(&format!("{}{}", roles_url, role), "GET", &[&auth])
+        })
+        .and_then(|body| serde_json::from_str(&body).ok())
+        .and_then(|v| aws_credentials_from_json(&v));
+    creds.ok_or_else(|| "No AWS credentials: set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY".to_string())
+}
+
+fn gcs_token() -> Result<String, String> {
+    if let Ok(token) = env::var("GOOGLE_OAUTH_ACCESS_TOKEN") {
+        return Ok(token);
+    }
+    let url = "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+    let from_metadata = fetch(url, "GET", &["Metadata-Flavor: Google"])
+        .and_then(|body| serde_json::from_str::<Value>(&body).ok())
+        .and_then(|v| v.get("access_token").and_then(|t| t.as_str()).map(String::from));
+    if let Some(token) = from_metadata {
+        return Ok(token);
+    }
+    let output = Command::new("gcloud").args(["auth", "print-access-token"]).stderr(Stdio::null()).output();
+    match output {
+        Ok(output) if output.status.success() => Ok(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        _ => Err("No GCS credentials: set GOOGLE_OAUTH_ACCESS_TOKEN or log in with gcloud".to_string()),
+    }
+}
+
+/// The S3 URL for a key: path style under a custom endpoint, else the
+/// bucket's virtual-hosted AWS endpoint
+fn s3_url(bucket: &str, key: &str, region: &str, endpoint: Option<&str>) -> String {
+    match endpoint {
+        Some(endpoint) => format!("{}/{}/{}", endpoint.trim_end_matches('/'), bucket, encode_key(key)),
+        None => format!("https://{}.s3.{}.amazonaws.com/{}", bucket, region, encode_key(key)),
+    }
+}
+
+/// Run curl with `config` (credentials) on stdin; the response body on failure
+fn curl_upload(args: &[String], config: &str) -> Result<(), String> {
+    let mut child = Command::new("curl")
+        .args(["-sS", "--max-time", TIMEOUT_SECS, "-o", "-", "-w", "\n%{http_code}", "-K", "-"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Cannot run curl to upload: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(config.as_bytes()).map_err(|e| e.to_string())?;
+    }
+    let output = child.wait_with_output().map_err(|e| e.to_string())?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (body, status) = stdout.trim_end().rsplit_once('\n').unwrap_or(("", stdout.trim()));
+    match status.parse::<u16>() {
+        Ok(200..=299) => Ok(()),
+        _ => Err(format!("HTTP {}: {}", status, body.trim())),
+    }
+}
+
+/// Upload a local file; returns `{ uri, url }` for the result
+pub fn upload(target: &Target, path: &str) -> Result<Value, String> {
+    let file = Path::new(path);
+    let key = target.key(file);
+    let content_type = format!("Content-Type: {}", content_type(file));
+    let url = match target.store {
+        Store::S3 => {
+            let creds = aws_credentials()?;
+            let region = env::var("AWS_REGION").or_else(|_| env::var("AWS_DEFAULT_REGION")).unwrap_or_else(|_| "us-east-1".to_string());
+            let endpoint = env::var("AWS_ENDPOINT_URL_S3").or_else(|_| env::var("AWS_ENDPOINT_URL")).ok();
+            let url = s3_url(&target.bucket, &key, &region, endpoint.as_deref());
+            let bytes = fs::read(file).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+            let hash: String = Sha256::digest(&bytes).iter().map(|b| format!("{:02x}", b)).collect();
+            let mut config = format!("user = {}\n", config_string(&format!("{}:{}", creds.key_id, creds.secret)));
+            if let Some(token) = creds.token {
+                config.push_str(&format!("header = {}\n", config_string(&format!("x-amz-security-token: {}", token))));
+            }
+            let args = [
+                "--aws-sigv4".to_string(),
+                format!("aws:amz:{}:s3", region),
+                "-H".to_string(),
+                format!("x-amz-content-sha256: {}", hash),
+                "-H".to_string(),
+                content_type,
+                "-T".to_string(),
+                path.to_string(),
+                url.clone(),
+            ];
+            curl_upload(&args, &config).map_err(|e| format!("Upload to {} failed: {}", target.uri(&key), e))?;
+            url
+        }
+        Store::Gcs => {
+            let token = gcs_token()?;
+            let config = format!("header = {}\n", config_string(&format!("Authorization: Bearer {}", token)));
+            let api = format!(
+                "https://storage.googleapis.com/upload/storage/v1/b/{}/o?uploadType=media&name={}",
+                target.bucket,
+                percent_encode(&key)
+            );
+            let args = [
+                "-X".to_string(),
+                "POST".to_string(),
+                "-H".to_string(),
+                content_type,
+                "--data-binary".to_string(),
+                format!("@{}", path),
+                api,
+            ];
+            curl_upload(&args, &config).map_err(|e| format!("Upload to {} failed: {}", target.uri(&key), e))?;
+            format!("https://storage.googleapis.com/{}/{}", target.bucket, encode_key(&key))
+        }
+    };
+    Ok(json!({ "uri": target.uri(&key), "url": url }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_target() {
+        let target = Target::parse("s3://artifacts/runs/42/").unwrap();
+        assert_eq!(target, Target { store: Store::S3, bucket: "artifacts".to_string(), prefix: "runs/42".to_string() });
+        assert_eq!(target.key(Path::new("/tmp/shot 1.png")), "runs/42/shot 1.png");
+        assert_eq!(target.uri("runs/42/a.png"), "s3://artifacts/runs/42/a.png");
+        let bare = Target::parse("gs://bucket").unwrap();
+        assert_eq!(bare.key(Path::new("page.pdf")), "page.pdf");
+        assert_eq!(bare.uri("page.pdf"), "gs://bucket/page.pdf");
+        assert!(Target::parse("https://bucket").unwrap_err().starts_with("Invalid --upload"));
+        assert!(Target::parse("s3:///prefix").unwrap_err().contains("missing bucket"));
+    }
+
+    #[test]
+    fn test_artifact_path() {
+        assert_eq!(artifact_path("pdf", &json!({ "path": "a.pdf" })), Some("a.pdf"));
+        assert_eq!(artifact_path("recording_restart", &json!({ "path": "b.webm", "previousPath": "a.webm" })), Some("a.webm"));
+        assert_eq!(artifact_path("recording_stop", &json!({ "path": "", "frames": 0 })), None);
+        assert_eq!(artifact_path("screenshot", &json!({ "base64": "..." })), None);
+        assert_eq!(artifact_path("state_save", &json!({ "path": "s.json" })), None);
+    }
+
+    #[test]
+    fn test_urls() {
+        assert_eq!(s3_url("b", "runs/shot 1.png", "eu-west-1", None), "https://b.s3.eu-west-1.amazonaws.com/runs/shot%201.png");
+        assert_eq!(s3_url("b", "a.png", "us-east-1", Some("http://minio:9000/")), "http://minio:9000/b/a.png");
+        assert_eq!(content_type(Path::new("x.JPG")), "image/jpeg");
+        assert_eq!(config_string(r#"a"b\c"#), r#""a\"b\\c""#);
+    }
+
+    #[test]
+    fn test_aws_credentials_from_json() {
+        let creds = aws_credentials_from_json(&json!({ "AccessKeyId": "AK", "SecretAccessKey": "SK", "Token": "T" })).unwrap();
+        assert_eq!((creds.key_id.as_str(), creds.secret.as_str(), creds.token.as_deref()), ("AK", "SK", Some("T")));
+        assert!(aws_credentials_from_json(&json!({ "Code": "Success" })).is_none());
+    }
+}
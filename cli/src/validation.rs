@@ -1,12 +1,474 @@
-/// Check if a session name is valid (alphanumeric, hyphens, and underscores only)
+/// Device names Windows reserves in every directory, regardless of extension
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Check if a name is a Windows device name (`con`, `NUL`, `com1.json`, ...),
+/// which can't be used as a file name there
+pub fn is_windows_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name);
+    WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+/// Longest session name (and longest encoded --unicode-sessions name). Names
+/// end up in socket paths, which Unix limits to about 104 bytes.
+pub const MAX_SESSION_NAME_LEN: usize = 64;
+
+/// Check if a session name is valid (ASCII alphanumeric, hyphens, and
+/// underscores only, and not a Windows device name, so session files work on
+/// every platform and match the daemon's check). See --unicode-sessions for
+/// other names.
 pub fn is_valid_session_name(name: &str) -> bool {
-    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    !name.is_empty()
+        && name.len() <= MAX_SESSION_NAME_LEN
+        && !name.starts_with('-')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && !is_windows_reserved_name(name)
+}
+
+/// Rewrite a session name into the conventional form: trimmed, lowercase,
+/// whitespace as hyphens, no leading hyphens, at most MAX_SESSION_NAME_LEN
+/// bytes. Returns the name and a description of each change made.
+pub fn normalize_session_name(name: &str) -> (String, Vec<String>) {
+    let mut warnings = Vec::new();
+    let trimmed = name.trim();
+    if trimmed != name {
+        warnings.push("trimmed surrounding whitespace".to_string());
+    }
+    let mut normalized = trimmed.to_lowercase();
+    if normalized != trimmed {
+        warnings.push("lowercased".to_string());
+    }
+    if normalized.contains(char::is_whitespace) {
+        normalized = normalized.split_whitespace().collect::<Vec<_>>().join("-");
+        warnings.push("replaced spaces with hyphens".to_string());
+    }
+    if normalized.starts_with('-') {
+        normalized = normalized.trim_start_matches('-').to_string();
+        warnings.push("removed leading hyphens".to_string());
+    }
+    if normalized.len() > MAX_SESSION_NAME_LEN {
+        let mut cut = MAX_SESSION_NAME_LEN;
+        while !normalized.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        normalized.truncate(cut);
+        warnings.push(format!("truncated to {} characters", MAX_SESSION_NAME_LEN));
+    }
+    (normalized, warnings)
 }
 
 /// Generate error message for invalid session name
 pub fn session_name_error(name: &str) -> String {
-    format!(
-        "Invalid session name '{}'. Only alphanumeric characters, hyphens, and underscores are allowed.",
-        name
-    )
+    let reason = if is_windows_reserved_name(name) {
+        "It is a reserved device name on Windows.".to_string()
+    } else if name.len() > MAX_SESSION_NAME_LEN {
+        format!("Session names are limited to {} characters.", MAX_SESSION_NAME_LEN)
+    } else if name.starts_with('-') {
+        "Session names can't start with a hyphen.".to_string()
+    } else if name.is_ascii() {
+        "Only alphanumeric characters, hyphens, and underscores are allowed.".to_string()
+    } else {
+        "Only alphanumeric characters, hyphens, and underscores are allowed. \
+Use --unicode-sessions for other names."
+            .to_string()
+    };
+    let (normalized, changes) = normalize_session_name(name);
+    if normalized != name && is_valid_session_name(&normalized) {
+        return format!(
+            "Invalid session name '{}'. {} Did you mean '{}' ({})?",
+            name,
+            reason,
+            normalized,
+            changes.join(", ")
+        );
+    }
+    format!("Invalid session name '{}'. {}", name, reason)
+}
+
+/// Check a session name for --unicode-sessions: any text without control
+/// characters or a leading hyphen, since it is encoded before it reaches the
+/// filesystem
+pub fn is_valid_unicode_session_name(name: &str) -> bool {
+    !name.trim().is_empty() && !name.starts_with('-') && !name.chars().any(char::is_control)
+}
+
+/// File-safe form of a session name. Names that already pass
+/// `is_valid_session_name` are unchanged; otherwise every byte outside
+/// `[A-Za-z0-9_-]` is percent-encoded (so `/`, `\` and `.` can't form a path),
+/// and names whose encoding is too long keep a prefix plus `~` and a hash.
+pub fn encode_session_name(name: &str) -> String {
+    if is_valid_session_name(name) {
+        return name.to_string();
+    }
+    let mut encoded = String::new();
+    for byte in name.bytes() {
+        let safe = byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_';
+        // A leading device name like "CON" is escaped to "%43ON"
+        if safe && !(encoded.is_empty() && is_windows_reserved_name(name)) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    if encoded.len() <= MAX_SESSION_NAME_LEN {
+        return encoded;
+    }
+    let hash = crate::audit::sha256_hex(name.as_bytes());
+    let mut cut = MAX_SESSION_NAME_LEN - 17;
+    // Don't split a %XX escape
+    while encoded[..cut].rfind('%').is_some_and(|i| i + 3 > cut) {
+        cut -= 1;
+    }
+    format!("{}~{}", &encoded[..cut], &hash[..16])
+}
+
+/// Readable form of an encoded session name, for display. Hashed names show
+/// the decoded prefix followed by `~<hash>`.
+pub fn decode_session_name(encoded: &str) -> String {
+    if !encoded.contains('%') {
+        return encoded.to_string();
+    }
+    let (body, hash) = match encoded.split_once('~') {
+        Some((body, hash)) => (body, Some(hash)),
+        None => (encoded, None),
+    };
+    let mut bytes = Vec::new();
+    let mut rest = body.as_bytes();
+    while let Some((&first, tail)) = rest.split_first() {
+        let hex = tail.get(..2).and_then(|h| std::str::from_utf8(h).ok());
+        match (first, hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                bytes.push(byte);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(first);
+                rest = tail;
+            }
+        }
+    }
+    let decoded = String::from_utf8_lossy(&bytes).to_string();
+    match hash {
+        Some(hash) => format!("{}~{}", decoded, hash),
+        None => decoded,
+    }
+}
+
+/// Schemes `open` passes to the browser unchanged
+const PASSTHROUGH_SCHEMES: &[&str] = &["about", "data", "blob", "view-source"];
+
+/// Schemes that read local files or run script in the current page; only
+/// opened with --allow-unsafe-schemes
+const UNSAFE_SCHEMES: &[&str] = &["file", "javascript"];
+
+/// Scheme of a URL, lowercased. Text before a ':' only counts as a scheme
+/// when followed by "//" or when it is a known scheme, so `localhost:3000`
+/// has none.
+fn url_scheme(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once(':')?;
+    let valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    let scheme = scheme.to_ascii_lowercase();
+    let known = PASSTHROUGH_SCHEMES.contains(&scheme.as_str()) || UNSAFE_SCHEMES.contains(&scheme.as_str());
+    (valid && (rest.starts_with("//") || known)).then_some(scheme)
+}
+
+/// Validate and normalize a URL given to `open`: adds https:// when there is
+/// no scheme, lowercases the scheme and host, converts international domain
+/// names to punycode, and rejects file: and javascript: URLs unless
+/// `allow_unsafe` is set.
+pub fn normalize_url(input: &str, allow_unsafe: bool) -> Result<String, String> {
+    let url = input.trim();
+    if url.is_empty() {
+        return Err("Invalid URL: empty".to_string());
+    }
+    match url_scheme(url).as_deref() {
+        None => normalize_http_url(&format!("https://{}", url), input),
+        Some("http") | Some("https") => normalize_http_url(url, input),
+        Some(scheme) if UNSAFE_SCHEMES.contains(&scheme) => {
+            if allow_unsafe {
+                Ok(url.to_string())
+            } else {
+                Err(format!(
+                    "Refusing to open {}: URL '{}'. Pass --allow-unsafe-schemes to allow it.",
+                    scheme, input
+                ))
+            }
+        }
+        Some(scheme) if PASSTHROUGH_SCHEMES.contains(&scheme) => Ok(url.to_string()),
+        Some(scheme) => Err(format!(
+            "Invalid URL '{}': unsupported scheme '{}' (use http or https)",
+            input, scheme
+        )),
+    }
+}
+
+fn normalize_http_url(url: &str, input: &str) -> Result<String, String> {
+    let invalid = |reason: &str| format!("Invalid URL '{}': {}", input, reason);
+    let (scheme, rest) = url.split_once("://").unwrap_or(("https", url));
+    let end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, tail) = rest.split_at(end);
+    let (userinfo, host_port) = match authority.rsplit_once('@') {
+        Some((user, host)) => (Some(user), host),
+        None => (None, authority),
+    };
+    let (host, port) = if host_port.starts_with('[') {
+        // IPv6 literal
+        let close = host_port.find(']').ok_or_else(|| invalid("unclosed '[' in host"))?;
+        let port = host_port[close + 1..].strip_prefix(':');
+        (&host_port[..=close], port)
+    } else {
+        match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host_port, None),
+        }
+    };
+    if host.is_empty() {
+        return Err(invalid("missing host"));
+    }
+    if host.chars().any(|c| c.is_whitespace() || "<>\"{}|\\^`".contains(c)) {
+        return Err(invalid("host contains invalid characters"));
+    }
+    if let Some(port) = port {
+        if port.parse::<u16>().is_err() {
+            return Err(invalid(&format!("invalid port '{}'", port)));
+        }
+    }
+    let mut labels = Vec::new();
+    for label in host.split('.') {
+        let label = label.to_lowercase();
+        if label.is_ascii() {
+            labels.push(label);
+        } else {
+            let encoded = punycode_encode(&label).ok_or_else(|| invalid("host can't be encoded"))?;
+            labels.push(format!("xn--{}", encoded));
+        }
+    }
+
+    let mut normalized = format!("{}://", scheme.to_ascii_lowercase());
+    if let Some(userinfo) = userinfo {
+        normalized.push_str(userinfo);
+        normalized.push('@');
+    }
+    normalized.push_str(&labels.join("."));
+    if let Some(port) = port {
+        normalized.push(':');
+        normalized.push_str(port);
+    }
+    normalized.push_str(tail);
+    Ok(normalized)
+}
+
+/// Punycode (RFC 3492) encoding of one domain label, without the `xn--` prefix
+fn punycode_encode(label: &str) -> Option<String> {
+    const BASE: u32 = 36;
+    const T_MIN: u32 = 1;
+    const T_MAX: u32 = 26;
+    const SKEW: u32 = 38;
+    const DAMP: u32 = 700;
+
+    fn adapt(delta: u32, points: u32, first: bool) -> u32 {
+        let mut delta = if first { delta / DAMP } else { delta / 2 };
+        delta += delta / points;
+        let mut k = 0;
+        while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+            delta /= BASE - T_MIN;
+            k += BASE;
+        }
+        k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+    }
+    fn digit(d: u32) -> char {
+        if d < 26 {
+            (b'a' + d as u8) as char
+        } else {
+            (b'0' + (d - 26) as u8) as char
+        }
+    }
+
+    let code_points: Vec<u32> = label.chars().map(|c| c as u32).collect();
+    let mut output: String = label.chars().filter(char::is_ascii).collect();
+    let basic = output.len() as u32;
+    if basic > 0 {
+        output.push('-');
+    }
+    let (mut n, mut delta, mut bias, mut handled) = (128u32, 0u32, 72u32, basic);
+    while (handled as usize) < code_points.len() {
+        let m = *code_points.iter().filter(|&&c| c >= n).min()?;
+        delta = delta.checked_add((m - n).checked_mul(handled + 1)?)?;
+        n = m;
+        for &c in &code_points {
+            if c < n {
+                delta = delta.checked_add(1)?;
+            }
+            if c == n {
+                let mut q = delta;
+                let mut k = BASE;
+                loop {
+                    let t = if k <= bias {
+                        T_MIN
+                    } else if k >= bias + T_MAX {
+                        T_MAX
+                    } else {
+                        k - bias
+                    };
+                    if q < t {
+                        break;
+                    }
+                    output.push(digit(t + (q - t) % (BASE - t)));
+                    q = (q - t) / (BASE - t);
+                    k += BASE;
+                }
+                output.push(digit(q));
+                bias = adapt(delta, handled + 1, handled == basic);
+                delta = 0;
+                handled += 1;
+            }
+        }
+        delta += 1;
+        n += 1;
+    }
+    Some(output)
+}
+
+/// Check that an extra CA bundle exists and holds PEM-encoded certificates,
+/// and count them
+pub fn validate_extra_ca(path: &str) -> Result<usize, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Cannot read --extra-ca file '{}': {}", path, e))?;
+    let certificates = contents.matches("-----BEGIN CERTIFICATE-----").count();
+    if certificates == 0 {
+        return Err(format!(
+            "Invalid --extra-ca file '{}': expected PEM-encoded certificate(s)",
+            path
+        ));
+    }
+    Ok(certificates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_reserved_names() {
+        for name in ["CON", "nul", "Com1", "lpt9", "aux.json", "PRN.tar.gz"] {
+            assert!(is_windows_reserved_name(name), "{}", name);
+        }
+        for name in ["console", "com10", "null", "lpt", "my-con"] {
+            assert!(!is_windows_reserved_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_is_valid_session_name() {
+        assert!(is_valid_session_name("agent_1-a"));
+        assert!(!is_valid_session_name(""));
+        assert!(!is_valid_session_name("../x"));
+        assert!(!is_valid_session_name("con"));
+        assert!(session_name_error("NUL").contains("reserved device name"));
+        assert!(session_name_error("a/b").contains("Only alphanumeric"));
+    }
+
+    #[test]
+    fn test_session_name_limits() {
+        assert!(is_valid_session_name(&"a".repeat(MAX_SESSION_NAME_LEN)));
+        assert!(!is_valid_session_name(&"a".repeat(MAX_SESSION_NAME_LEN + 1)));
+        assert!(!is_valid_session_name("-x"));
+        assert!(is_valid_session_name("x-"));
+        assert!(session_name_error("--json").contains("can't start with a hyphen"));
+        assert!(session_name_error(&"a".repeat(70)).contains("limited to 64"));
+    }
+
+    #[test]
+    fn test_normalize_session_name() {
+        assert_eq!(normalize_session_name("agent1"), ("agent1".to_string(), vec![]));
+        let (name, warnings) = normalize_session_name(" Client  A ");
+        assert_eq!(name, "client-a");
+        assert_eq!(
+            warnings,
+            vec!["trimmed surrounding whitespace", "lowercased", "replaced spaces with hyphens"]
+        );
+        let (name, warnings) = normalize_session_name("--Prod");
+        assert_eq!(name, "prod");
+        assert_eq!(warnings, vec!["lowercased", "removed leading hyphens"]);
+        assert_eq!(normalize_session_name(&"x".repeat(80)).0.len(), MAX_SESSION_NAME_LEN);
+        assert_eq!(
+            session_name_error("Client A"),
+            "Invalid session name 'Client A'. Only alphanumeric characters, hyphens, and underscores \
+are allowed. Did you mean 'client-a' (lowercased, replaced spaces with hyphens)?"
+        );
+    }
+
+    #[test]
+    fn test_encode_session_name() {
+        assert_eq!(encode_session_name("agent1"), "agent1");
+        assert_eq!(encode_session_name("Client A"), "Client%20A");
+        assert_eq!(encode_session_name("../etc"), "%2E%2E%2Fetc");
+        assert_eq!(encode_session_name("CON"), "%43ON");
+        let encoded = encode_session_name("クライアントA");
+        assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '%'));
+        assert_eq!(decode_session_name(&encoded), "クライアントA");
+        assert_eq!(decode_session_name("Client%20A"), "Client A");
+        assert_eq!(decode_session_name("%43ON"), "CON");
+    }
+
+    #[test]
+    fn test_encode_long_session_name() {
+        let name = "プロジェクト".repeat(10);
+        let encoded = encode_session_name(&name);
+        assert!(encoded.len() <= MAX_SESSION_NAME_LEN);
+        assert_ne!(encoded, encode_session_name(&"プロジェクト".repeat(11)));
+        let shown = decode_session_name(&encoded);
+        assert!(shown.starts_with("プロ"), "{}", shown);
+        assert!(!shown.contains('%'));
+    }
+
+    #[test]
+    fn test_is_valid_unicode_session_name() {
+        assert!(is_valid_unicode_session_name("クライアントA"));
+        assert!(is_valid_unicode_session_name("../etc"));
+        assert!(!is_valid_unicode_session_name("  "));
+        assert!(!is_valid_unicode_session_name("-クライアント"));
+        assert!(!is_valid_unicode_session_name("a\nb"));
+    }
+
+    #[test]
+    fn test_normalize_url_adds_scheme() {
+        assert_eq!(normalize_url("example.com", false).unwrap(), "https://example.com");
+        assert_eq!(normalize_url("localhost:3000/a?b=1", false).unwrap(), "https://localhost:3000/a?b=1");
+        assert_eq!(normalize_url(" HTTP://Example.COM/Path ", false).unwrap(), "http://example.com/Path");
+        assert_eq!(normalize_url("about:blank", false).unwrap(), "about:blank");
+        assert_eq!(
+            normalize_url("https://user:pw@[::1]:8080/x", false).unwrap(),
+            "https://user:pw@[::1]:8080/x"
+        );
+    }
+
+    #[test]
+    fn test_normalize_url_unsafe_schemes() {
+        let err = normalize_url("file:///etc/passwd", false).unwrap_err();
+        assert!(err.contains("--allow-unsafe-schemes"), "{}", err);
+        assert!(normalize_url("JavaScript:alert(1)", false).is_err());
+        assert_eq!(normalize_url("file:///tmp/a.html", true).unwrap(), "file:///tmp/a.html");
+        assert!(normalize_url("ftp://example.com", false).unwrap_err().contains("unsupported scheme"));
+    }
+
+    #[test]
+    fn test_normalize_url_invalid() {
+        assert!(normalize_url("", false).is_err());
+        assert!(normalize_url("https://", false).unwrap_err().contains("missing host"));
+        assert!(normalize_url("example.com:99999", false).unwrap_err().contains("invalid port"));
+        assert!(normalize_url("exa mple.com", false).unwrap_err().contains("invalid characters"));
+    }
+
+    #[test]
+    fn test_normalize_url_punycode() {
+        assert_eq!(normalize_url("bücher.de/x", false).unwrap(), "https://xn--bcher-kva.de/x");
+        assert_eq!(normalize_url("https://MÜNCHEN.de", false).unwrap(), "https://xn--mnchen-3ya.de");
+        assert_eq!(normalize_url("例え.テスト", false).unwrap(), "https://xn--r8jz45g.xn--zckzah");
+    }
 }
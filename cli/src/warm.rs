@@ -0,0 +1,240 @@
+//! Warm pool: spare daemons whose browser is already launched, so a new
+//! session skips the cold start.
+//!
+//! `daemon warm --count N` records the pool in `warm.json` in the socket
+//! directory and starts daemons under hidden session names
+//! (`.warm-<key>-<id>`) until N are running. A session that starts with the
+//! same launch configuration claims one instead of starting its own: the
+//! daemon moves to the session's socket and pid file (`warm_claim`), and a
+//! detached `daemon warm` tops the pool up again.
+//!
+//! The launch configuration is compared as a key, a hash of the daemon's
+//! environment and the launch command. Sessions that differ start cold as
+//! before, and so do sessions with --cdp, -p, --session-name or --ephemeral.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use std::thread;
+
+use crate::audit::sha256_hex;
+use crate::commands::gen_id;
+use crate::connection::{
+    daemon_command, get_socket_dir, is_daemon_running, send_command, spawn_detached, wait_ready, TOKEN_ENV,
+};
+use crate::flags::Flags;
+use crate::oauth::random_token;
+
+/// Session names of warm daemons start with this; user sessions can't
+pub const PREFIX: &str = ".warm-";
+
+const POOL_FILE: &str = "warm.json";
+
+/// Characters of the key in a warm daemon's name
+const KEY_LENGTH: usize = 12;
+
+#[derive(Serialize, Deserialize)]
+struct Pool {
+    count: usize,
+    key: String,
+    /// Arguments of the `daemon warm` that created the pool, to top it up with
+    args: Vec<String>,
+}
+
+pub fn is_warm(session: &str) -> bool {
+    session.starts_with(PREFIX)
+}
+
+fn pool_path() -> PathBuf {
+    get_socket_dir().join(POOL_FILE)
+}
+
+fn load() -> Option<Pool> {
+    serde_json::from_str(&fs::read_to_string(pool_path()).ok()?).ok()
+}
+
+/// Why sessions with these flags can't come from a pool
+pub fn unsupported(flags: &Flags) -> Option<&'static str> {
+    if flags.cdp.is_some() {
+        Some("--cdp")
+    } else if flags.provider.is_some() {
+        Some("-p/--provider")
+    } else if flags.session_name.is_some() {
+        Some("--session-name")
+    } else if flags.ephemeral {
+        Some("--ephemeral")
+    } else {
+        None
+    }
+}
+
+/// Variables that differ per session or invocation, not per launch
+const PER_SESSION_VARS: &[&str] = &["AGENT_BROWSER_SESSION", "AGENT_BROWSER_SESSION_LOCK", TOKEN_ENV];
+
+/// Hash of everything a daemon's browser is launched with: the AGENT_BROWSER_*
+/// environment it gets, and the launch command sent once it runs (without
+/// its id)
+fn launch_key(command: &Command, launch: Option<&Value>) -> String {
+    let mut vars: BTreeMap<String, String> = env::vars()
+        .filter(|(name, _)| name.starts_with("AGENT_BROWSER_") && !PER_SESSION_VARS.contains(&name.as_str()))
+        .collect();
+    for (name, value) in command.get_envs() {
+        let name = name.to_string_lossy().to_string();
+        match value {
+            Some(value) => vars.insert(name, value.to_string_lossy().to_string()),
+            None => vars.remove(&name),
+        };
+    }
+    vars.retain(|name, _| !PER_SESSION_VARS.contains(&name.as_str()));
+    let mut launch = launch.cloned();
+    if let Some(object) = launch.as_mut().and_then(|l| l.as_object_mut()) {
+        object.remove("id");
+    }
+    let daemon: Vec<String> = command.get_args().map(|a| a.to_string_lossy().to_string()).collect();
+    sha256_hex(json!({ "daemon": daemon, "env": vars, "launch": launch }).to_string().as_bytes())
+}
+
+/// Running warm daemons: those started for `key`, and the others
+fn running(key: &str) -> (Vec<String>, Vec<String>) {
+    let mut sessions: Vec<String> = fs::read_dir(get_socket_dir())
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_string_lossy().strip_suffix(".pid").map(String::from))
+        .filter(|session| is_warm(session) && is_daemon_running(session))
+        .collect();
+    sessions.sort();
+    let own = format!("{}{}-", PREFIX, &key[..KEY_LENGTH]);
+    sessions.into_iter().partition(|session| session.starts_with(&own))
+}
+
+fn close(session: &str) {
+    let _ = send_command(json!({ "id": gen_id(), "action": "close" }), session);
+}
+
+/// Start a warm daemon and launch its browser
+fn start(flags: &Flags, key: &str, launch: Option<&Value>) -> Result<(), String> {
+    let session = format!("{}{}-{}", PREFIX, &key[..KEY_LENGTH], &random_token()[..8]);
+    let mut command = daemon_command(flags)?;
+    command.env("AGENT_BROWSER_SESSION", &session).env("AGENT_BROWSER_WARM", "1");
+    spawn_detached(&mut command)?;
+    if !wait_ready(&session) {
+        return Err("Daemon failed to start".to_string());
+    }
+    // The launch command the session would send, or the launch its first command would do
+    let mut cmd = launch.cloned().unwrap_or_else(|| json!({ "action": "prelaunch" }));
+    cmd["id"] = json!(gen_id());
+    match send_command(cmd, &session) {
+        Ok(resp) if resp.success => Ok(()),
+        Ok(resp) => {
+            close(&session);
+            Err(resp.error.unwrap_or_else(|| "Launch failed".to_string()))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// `daemon warm --count <n>`: keep n warm daemons for this launch
+/// configuration, or stop the pool with 0
+pub fn run(flags: &Flags, launch: Option<&Value>, count: usize) -> Result<Value, String> {
+    if let Some(flag) = unsupported(flags) {
+        return Err(format!("{} can't be used with a warm pool", flag));
+    }
+    let command = daemon_command(flags)?;
+    let key = launch_key(&command, launch);
+    let (own, stale) = running(&key);
+
+    if count == 0 {
+        let _ = fs::remove_file(pool_path());
+        let stopped = own.len() + stale.len();
+        thread::scope(|scope| {
+            for session in own.iter().chain(&stale) {
+                scope.spawn(move || close(session));
+            }
+        });
+        return Ok(json!({ "count": 0, "ready": 0, "started": 0, "stopped": stopped }));
+    }
+
+    let pool = Pool { count, key: key.clone(), args: env::args().skip(1).collect() };
+    let saved = serde_json::to_string_pretty(&pool).map_err(|e| e.to_string())?;
+    fs::write(pool_path(), saved).map_err(|e| format!("Cannot write {}: {}", pool_path().display(), e))?;
+
+    // Daemons of an earlier configuration, and any over the count, go
+    let extra: Vec<&String> = stale.iter().chain(own.iter().skip(count)).collect();
+    let kept = own.len().min(count);
+    let results: Vec<Result<(), String>> = thread::scope(|scope| {
+        for session in &extra {
+            scope.spawn(move || close(session));
+        }
+        let starts: Vec<_> = (kept..count).map(|_| scope.spawn(|| start(flags, &key, launch))).collect();
+        starts.into_iter().map(|s| s.join().unwrap_or_else(|_| Err("Launch failed".to_string()))).collect()
+    });
+    let started = results.iter().filter(|r| r.is_ok()).count();
+    if started == 0 && kept == 0 {
+        if let Some(Err(e)) = results.into_iter().find(|r| r.is_err()) {
+            return Err(e);
+        }
+    }
+    Ok(json!({ "count": count, "ready": kept + started, "started": started, "stopped": extra.len() }))
+}
+
+/// Hand a warm daemon to the session when the pool was started for its
+/// launch configuration, then top the pool up in the background. False when
+/// the session has to start its own daemon.
+pub fn claim(flags: &Flags, launch: Option<&Value>) -> bool {
+    if unsupported(flags).is_some() || is_warm(&flags.session) || is_daemon_running(&flags.session) {
+        return false;
+    }
+    let Some(pool) = load().filter(|pool| pool.count > 0) else {
+        return false;
+    };
+    let Ok(command) = daemon_command(flags) else {
+        return false;
+    };
+    if launch_key(&command, launch) != pool.key {
+        return false;
+    }
+    let claimed = running(&pool.key).0.iter().any(|warm| {
+        let cmd = json!({ "id": gen_id(), "action": "warm_claim", "session": flags.session });
+        matches!(send_command(cmd, warm), Ok(resp) if resp.success)
+    });
+    if claimed {
+        if let Ok(exe) = env::current_exe() {
+            let _ = spawn_detached(Command::new(exe).args(&pool.args));
+        }
+    }
+    claimed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::parse_flags;
+
+    #[test]
+    fn test_launch_key_ignores_session_and_id() {
+        let mut a = Command::new("node");
+        a.arg("daemon.js").env("AGENT_BROWSER_SESSION", "one").env("AGENT_BROWSER_HEADED", "1");
+        let mut b = Command::new("node");
+        b.arg("daemon.js").env("AGENT_BROWSER_SESSION", ".warm-x").env("AGENT_BROWSER_HEADED", "1");
+        let launch = json!({ "id": "1", "action": "launch", "headless": false });
+        let relaunch = json!({ "id": "2", "action": "launch", "headless": false });
+        assert_eq!(launch_key(&a, Some(&launch)), launch_key(&b, Some(&relaunch)));
+        assert_ne!(launch_key(&a, Some(&launch)), launch_key(&a, None));
+
+        b.env_remove("AGENT_BROWSER_HEADED");
+        assert_ne!(launch_key(&a, None), launch_key(&b, None));
+    }
+
+    #[test]
+    fn test_unsupported_flags() {
+        let flags = |args: &[&str]| parse_flags(&args.iter().map(|a| a.to_string()).collect::<Vec<_>>());
+        assert_eq!(unsupported(&flags(&["--headed"])), None);
+        assert_eq!(unsupported(&flags(&["--ephemeral"])), Some("--ephemeral"));
+        assert_eq!(unsupported(&flags(&["--cdp", "9222", "--ephemeral"])), Some("--cdp"));
+    }
+}
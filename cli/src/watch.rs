@@ -0,0 +1,81 @@
+//! Content-change monitoring for `watch`.
+//!
+//! `watch <url> --selector <sel>` reloads the page every interval, reads the
+//! selector's text and reports when it differs from the previous check. Text
+//! is compared with whitespace collapsed, so reflowed markup doesn't count as
+//! a change. A check that fails (page down, element missing) is reported as
+//! an error and the next check compares against the last value seen.
+
+use serde_json::{json, Value};
+
+/// Collapse runs of whitespace and trim, so only visible changes count
+pub fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// First value seen
+    Initial(String),
+    Changed { previous: String, current: String },
+    Error(String),
+}
+
+impl Event {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::Initial(_) => "initial",
+            Event::Changed { .. } => "change",
+            Event::Error(_) => "error",
+        }
+    }
+
+    /// Fields describing the event, for JSON output and webhooks
+    pub fn fields(&self) -> Value {
+        match self {
+            Event::Initial(value) => json!({ "current": value }),
+            Event::Changed { previous, current } => json!({ "previous": previous, "current": current }),
+            Event::Error(error) => json!({ "error": error }),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Watcher {
+    last: Option<String>,
+}
+
+impl Watcher {
+    /// Record the outcome of a check; `None` when nothing changed
+    pub fn observe(&mut self, result: Result<&str, String>) -> Option<Event> {
+        let value = match result {
+            Ok(text) => normalize(text),
+            Err(error) => return Some(Event::Error(error)),
+        };
+        match self.last.replace(value.clone()) {
+            None => Some(Event::Initial(value)),
+            Some(previous) if previous != value => Some(Event::Changed { previous, current: value }),
+            Some(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("  $19.99\n   USD "), "$19.99 USD");
+    }
+
+    #[test]
+    fn test_observe() {
+        let mut watcher = Watcher::default();
+        assert_eq!(watcher.observe(Ok("$19.99")), Some(Event::Initial("$19.99".to_string())));
+        assert_eq!(watcher.observe(Ok(" $19.99\n")), None);
+        assert_eq!(watcher.observe(Err("timeout".to_string())).map(|e| e.name()), Some("error"));
+        let change = watcher.observe(Ok("$17.49")).unwrap();
+        assert_eq!(change.fields(), json!({ "previous": "$19.99", "current": "$17.49" }));
+    }
+}
@@ -0,0 +1,308 @@
+//! Workspaces: per-project directories that scope sessions and their files.
+//!
+//! `workspace init <name>` creates a directory (by default
+//! `~/.agent-browser/workspaces/<name>`) and records it in
+//! `~/.agent-browser/workspaces.json`. While a workspace is active (`workspace
+//! use`, --workspace or AGENT_BROWSER_WORKSPACE), the socket directory, saved
+//! state, audit logs, checkpoints, downloads and config file all live inside
+//! it, so the same session names can be used for different projects without
+//! colliding. Paths are passed on through the usual AGENT_BROWSER_* variables,
+//! which the daemon inherits; variables set explicitly by the user win.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::connection::app_data_dir;
+use crate::validation::is_valid_session_name;
+
+/// Environment variables pointing into a workspace, relative to its root
+const SCOPED_PATHS: &[(&str, &str)] = &[
+    ("AGENT_BROWSER_SOCKET_DIR", "run"),
+    ("AGENT_BROWSER_STATE_DIR", "sessions"),
+    ("AGENT_BROWSER_AUDIT_DIR", "audit"),
+    ("AGENT_BROWSER_CHECKPOINT_DIR", "checkpoints"),
+    ("AGENT_BROWSER_JOBS_DIR", "jobs"),
+    ("AGENT_BROWSER_SCHEDULE_DIR", "schedules"),
+    ("AGENT_BROWSER_SECRETS_DIR", "secrets"),
+    ("AGENT_BROWSER_DOWNLOAD_DIR", "downloads"),
+    ("AGENT_BROWSER_CONFIG", "config.json"),
+];
+
+/// What `--state-dir` scopes beyond a workspace's paths: everything else the
+/// CLI keeps on disk, so nothing from a CI run lands in the home directory
+const STATE_DIR_PATHS: &[(&str, &str)] = &[
+    ("AGENT_BROWSER_ARTIFACTS_DIR", "artifacts"),
+    ("AGENT_BROWSER_HISTORY_DIR", "history"),
+    ("AGENT_BROWSER_PROFILES_DIR", "profiles"),
+];
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Registry {
+    /// Workspace used when neither --workspace nor AGENT_BROWSER_WORKSPACE is set
+    pub active: Option<String>,
+    pub workspaces: BTreeMap<String, PathBuf>,
+}
+
+fn base_dir() -> PathBuf {
+    app_data_dir()
+}
+
+pub fn registry_path() -> PathBuf {
+    base_dir().join("workspaces.json")
+}
+
+impl Registry {
+    /// Load the registry; a missing or unreadable file is an empty registry
+    pub fn load() -> Self {
+        fs::read_to_string(registry_path())
+            .ok()
+            .and_then(|source| serde_json::from_str(&source).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = registry_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+        }
+        let source = serde_json::to_string_pretty(self).unwrap_or_default();
+        fs::write(&path, source).map_err(|e| format!("Cannot write workspace registry: {}", e))
+    }
+
+    pub fn root(&self, name: &str) -> Result<&PathBuf, String> {
+        self.workspaces.get(name).ok_or_else(|| {
+            format!(
+                "Unknown workspace '{}'. Create it with: agent-browser workspace init {}",
+                name, name
+            )
+        })
+    }
+
+    /// Register a workspace and create its directory layout
+    pub fn init(&mut self, name: &str, dir: Option<&Path>) -> Result<PathBuf, String> {
+        if !is_valid_session_name(name) {
+            return Err(format!(
+                "Invalid workspace name '{}'. Only alphanumeric characters, hyphens, and underscores are allowed.",
+                name
+            ));
+        }
+        let root = match dir {
+            Some(dir) => dir.to_path_buf(),
+            None => base_dir().join("workspaces").join(name),
+        };
+        create_layout(&root)?;
+        let root = root.canonicalize().unwrap_or(root);
+        self.workspaces.insert(name.to_string(), root.clone());
+        Ok(root)
+    }
+}
+
+/// Create the workspace directories and an empty config file
+pub fn create_layout(root: &Path) -> Result<(), String> {
+    for (_, relative) in SCOPED_PATHS.iter().filter(|(_, r)| !r.ends_with(".json")) {
+        let dir = root.join(relative);
+        fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    }
+    let config = root.join("config.json");
+    if !config.exists() {
+        fs::write(&config, "{\n  \"sessionTemplates\": {}\n}\n")
+            .map_err(|e| format!("Cannot write {}: {}", config.display(), e))?;
+    }
+    Ok(())
+}
+
+/// Variables to set for a workspace rooted at `root`
+pub fn scoped_env(root: &Path) -> Vec<(&'static str, PathBuf)> {
+    SCOPED_PATHS
+        .iter()
+        .map(|(var, relative)| (*var, root.join(relative)))
+        .collect()
+}
+
+/// Point the AGENT_BROWSER_* path variables at the workspace, keeping any the
+/// user set explicitly
+pub fn activate(root: &Path) {
+    for (var, path) in scoped_env(root) {
+        if env::var(var).map(|v| v.is_empty()).unwrap_or(true) {
+            env::set_var(var, path);
+        }
+    }
+}
+
+/// Check and create a `--state-dir` root with its layout, returning it as an
+/// absolute path. It must be a directory only the user can write to: it
+/// holds cookies, saved logins and secrets.
+pub fn prepare_state_dir(dir: &Path) -> Result<PathBuf, String> {
+    if dir.as_os_str().is_empty() {
+        return Err("--state-dir requires a directory".to_string());
+    }
+    let root = std::path::absolute(dir).map_err(|e| format!("Invalid --state-dir {}: {}", dir.display(), e))?;
+    if root.exists() && !root.is_dir() {
+        return Err(format!("--state-dir {} is not a directory", root.display()));
+    }
+    create_private_dir(&root)?;
+    check_private_dir(&root)?;
+    create_layout(&root)?;
+    for (_, relative) in STATE_DIR_PATHS {
+        let dir = root.join(relative);
+        fs::create_dir_all(&dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))?;
+    }
+    Ok(root)
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> Result<(), String> {
+    use std::os::unix::fs::DirBuilderExt;
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .map_err(|e| format!("Cannot create {}: {}", dir.display(), e))
+}
+
+#[cfg(windows)]
+fn create_private_dir(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {}", dir.display(), e))
+}
+
+#[cfg(unix)]
+fn check_private_dir(dir: &Path) -> Result<(), String> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = fs::metadata(dir).map_err(|e| format!("Cannot read {}: {}", dir.display(), e))?;
+    if meta.uid() != unsafe { libc::geteuid() } {
+        return Err(format!("--state-dir {} belongs to another user", dir.display()));
+    }
+    if meta.mode() & 0o022 != 0 {
+        return Err(format!(
+            "--state-dir {} is writable by other users; restrict it with: chmod 700 {}",
+            dir.display(),
+            dir.display()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn check_private_dir(_dir: &Path) -> Result<(), String> {
+    // Directories under the user's profile are private to them already
+    Ok(())
+}
+
+/// Point every AGENT_BROWSER_* path variable into a `--state-dir` root,
+/// keeping any the user set explicitly
+pub fn activate_state_dir(root: &Path) {
+    activate(root);
+    for (var, relative) in STATE_DIR_PATHS {
+        if env::var(var).map(|v| v.is_empty()).unwrap_or(true) {
+            env::set_var(var, root.join(relative));
+        }
+    }
+}
+
+/// A `--profile` given as a bare name lives in AGENT_BROWSER_PROFILES_DIR
+/// (set by --state-dir); paths are used as they are
+pub fn resolve_profile(profile: &str) -> String {
+    let bare = !profile.is_empty() && !profile.contains(['/', '\\']) && profile != "." && profile != ".." && !profile.starts_with('~');
+    match env::var("AGENT_BROWSER_PROFILES_DIR") {
+        Ok(dir) if bare && !dir.is_empty() => Path::new(&dir).join(profile).to_string_lossy().to_string(),
+        _ => profile.to_string(),
+    }
+}
+
+/// Resolve a relative download path against AGENT_BROWSER_DOWNLOAD_DIR
+pub fn apply_download_dir(cmd: &mut Value) {
+    let action = cmd.get("action").and_then(|v| v.as_str());
+    if !matches!(action, Some("download") | Some("waitfordownload")) {
+        return;
+    }
+    let Some(dir) = env::var("AGENT_BROWSER_DOWNLOAD_DIR").ok().filter(|d| !d.is_empty()) else {
+        return;
+    };
+    if let Some(path) = cmd.get("path").and_then(|v| v.as_str()) {
+        if let Some(resolved) = resolve_download_path(Path::new(&dir), path) {
+            if let Some(parent) = resolved.parent() {
+                let _ = fs::create_dir_all(parent);
+            }
+            cmd["path"] = json!(resolved);
+        }
+    }
+}
+
+/// `dir/path` for relative paths, None for absolute ones
+pub fn resolve_download_path(dir: &Path, path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    (!path.is_absolute()).then(|| dir.join(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scoped_env() {
+        let vars = scoped_env(Path::new("/work/acme"));
+        let get = |name: &str| vars.iter().find(|(v, _)| *v == name).unwrap().1.clone();
+        assert_eq!(get("AGENT_BROWSER_SOCKET_DIR"), PathBuf::from("/work/acme/run"));
+        assert_eq!(get("AGENT_BROWSER_STATE_DIR"), PathBuf::from("/work/acme/sessions"));
+        assert_eq!(get("AGENT_BROWSER_CONFIG"), PathBuf::from("/work/acme/config.json"));
+    }
+
+    #[test]
+    fn test_init_creates_layout() {
+        let root = env::temp_dir().join(format!("ab-workspace-{}", std::process::id()));
+        let mut registry = Registry::default();
+        let created = registry.init("acme", Some(&root)).unwrap();
+        assert!(created.join("downloads").is_dir());
+        assert!(created.join("config.json").is_file());
+        assert_eq!(registry.root("acme").unwrap(), &created);
+        assert!(registry.root("globex").unwrap_err().contains("workspace init globex"));
+        assert!(registry.init("../evil", Some(&root)).is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_prepare_state_dir() {
+        let base = env::temp_dir().join(format!("ab-state-dir-{}", std::process::id()));
+        let root = prepare_state_dir(&base.join("ci")).unwrap();
+        assert!(root.is_absolute());
+        assert!(root.join("sessions").is_dir());
+        assert!(root.join("artifacts").is_dir());
+        assert!(root.join("profiles").is_dir());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&root).unwrap().permissions().mode() & 0o777, 0o700);
+            fs::set_permissions(&root, fs::Permissions::from_mode(0o777)).unwrap();
+            assert!(prepare_state_dir(&root).unwrap_err().contains("chmod 700"));
+        }
+        let file = base.join("file");
+        fs::write(&file, "").unwrap();
+        assert!(prepare_state_dir(&file).unwrap_err().contains("not a directory"));
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_download_path() {
+        let dir = Path::new("/work/acme/downloads");
+        assert_eq!(
+            resolve_download_path(dir, "reports/q1.pdf"),
+            Some(PathBuf::from("/work/acme/downloads/reports/q1.pdf"))
+        );
+        assert_eq!(resolve_download_path(dir, "/tmp/q1.pdf"), None);
+    }
+
+    #[test]
+    fn test_registry_json() {
+        let mut registry = Registry::default();
+        registry.workspaces.insert("acme".to_string(), PathBuf::from("/work/acme"));
+        registry.active = Some("acme".to_string());
+        let source = serde_json::to_string(&registry).unwrap();
+        assert_eq!(serde_json::from_str::<Registry>(&source).unwrap(), registry);
+        assert_eq!(serde_json::from_str::<Registry>("{}").unwrap(), Registry::default());
+    }
+}
@@ -0,0 +1,101 @@
+import { describe, it, expect } from 'vitest';
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import {
+  authorize,
+  commandRole,
+  hashToken,
+  loadTokens,
+  requiredRole,
+  sessionVisible,
+  type AccessToken,
+} from './access-tokens.js';
+
+const tokens: AccessToken[] = [
+  { name: 'viewer', sha256: hashToken('view-secret'), role: 'read-only' },
+  {
+    name: 'agent-a',
+    sha256: hashToken('a-secret'),
+    role: 'interact',
+    sessions: ['agent-a', 'agent-a-*'],
+  },
+  { name: 'ops', sha256: hashToken('ops-secret'), role: 'admin' },
+];
+
+function command(action: string, token?: string) {
+  return { id: '1', action, token };
+}
+
+describe('access tokens', () => {
+  it('ranks actions by the role they need', () => {
+    expect(requiredRole('snapshot')).toBe('read-only');
+    expect(requiredRole('click')).toBe('interact');
+    expect(requiredRole('close')).toBe('admin');
+  });
+
+  it('needs interact for read actions that write to a path', () => {
+    expect(commandRole({ action: 'screenshot' })).toBe('read-only');
+    expect(commandRole({ action: 'screenshot', path: null })).toBe('read-only');
+    expect(commandRole({ action: 'pdf', path: '/tmp/page.pdf' })).toBe('interact');
+    expect(commandRole({ action: 'close', path: '/tmp/x' })).toBe('admin');
+  });
+
+  it('refuses a path to read-only tokens', () => {
+    const screenshot = { ...command('screenshot', 'view-secret'), path: '/home/me/.bashrc' };
+    const denied = authorize(tokens, screenshot, 'default');
+    expect(denied?.error).toBe(
+      'Token viewer (read-only) cannot run screenshot with a path: it needs interact'
+    );
+    expect(denied?.data).toEqual({ type: 'forbidden', role: 'read-only', required: 'interact' });
+    expect(authorize(tokens, command('screenshot', 'view-secret'), 'default')).toBeNull();
+    const pdf = { ...command('pdf', 'a-secret'), path: 'page.pdf' };
+    expect(authorize(tokens, pdf, 'agent-a')).toBeNull();
+  });
+
+  it('matches sessions against wildcard patterns', () => {
+    expect(sessionVisible(undefined, 'anything')).toBe(true);
+    expect(sessionVisible(['agent-a-*'], 'agent-a-checkout')).toBe(true);
+    expect(sessionVisible(['agent-a-*'], 'agent-b')).toBe(false);
+    expect(sessionVisible(['a.b'], 'axb')).toBe(false);
+  });
+
+  it('lets everything through while no tokens exist', () => {
+    expect(authorize([], command('close'), 'default')).toBeNull();
+  });
+
+  it('requires a known token once tokens exist', () => {
+    expect(authorize(tokens, command('url'), 'default')?.data).toEqual({ type: 'unauthorized' });
+    expect(authorize(tokens, command('url', 'guess'), 'default')?.error).toBe(
+      'Invalid access token'
+    );
+    expect(authorize(tokens, command('url', 'view-secret'), 'default')).toBeNull();
+  });
+
+  it('limits tokens to their role and sessions', () => {
+    expect(authorize(tokens, command('click', 'view-secret'), 'default')?.data).toEqual({
+      type: 'forbidden',
+      role: 'read-only',
+      required: 'interact',
+    });
+    expect(authorize(tokens, command('click', 'a-secret'), 'agent-a-checkout')).toBeNull();
+    expect(authorize(tokens, command('close', 'a-secret'), 'agent-a')?.data?.type).toBe(
+      'forbidden'
+    );
+    expect(authorize(tokens, command('url', 'a-secret'), 'agent-b')?.data).toEqual({
+      type: 'session_not_visible',
+    });
+    expect(authorize(tokens, command('close', 'ops-secret'), 'agent-b')).toBeNull();
+  });
+
+  it('reads no tokens from a missing file and fails on a broken one', () => {
+    const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'tokens-test-'));
+    const file = path.join(dir, 'tokens.json');
+    expect(loadTokens(file)).toEqual([]);
+    fs.writeFileSync(file, '{ not json');
+    expect(() => loadTokens(file)).toThrow();
+    fs.writeFileSync(file, JSON.stringify({ tokens }));
+    expect(loadTokens(file).map((t) => t.name)).toEqual(['viewer', 'agent-a', 'ops']);
+    fs.rmSync(dir, { recursive: true, force: true });
+  });
+});
@@ -0,0 +1,243 @@
+/**
+ * Access tokens for daemons shared by several agents (`serve` with a shared
+ * socket directory). Once the tokens file lists a token, every command must
+ * carry one: its role decides which actions it may run, and its session
+ * patterns which sessions it can use at all. The file holds SHA-256 hashes
+ * only; `agent-browser token create` shows the token itself once.
+ */
+
+import * as crypto from 'crypto';
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import type { ErrorResponse } from './types.js';
+
+export type Role = 'read-only' | 'interact' | 'admin';
+
+const ROLE_RANK: Record<Role, number> = { 'read-only': 0, interact: 1, admin: 2 };
+
+export interface AccessToken {
+  name: string;
+  sha256: string;
+  role: Role;
+  /** Session names, with * wildcards; all sessions when missing */
+  sessions?: string[];
+}
+
+/** Actions that only read the page or the session */
+const READ_ACTIONS = new Set([
+  'url',
+  'title',
+  'getattribute',
+  'gettext',
+  'text',
+  'article',
+  'isvisible',
+  'isenabled',
+  'ischecked',
+  'elementstate',
+  'count',
+  'boundingbox',
+  'styles',
+  'innertext',
+  'innerhtml',
+  'inputvalue',
+  'content',
+  'snapshot',
+  'screenshot',
+  'pdf',
+  'wait',
+  'cookies_get',
+  'storage_get',
+  'indexeddb_list',
+  'indexeddb_dump',
+  'storage_origins',
+  'requests',
+  'assert_request',
+  'responsebody',
+  'security_audit',
+  'console',
+  'errors',
+  'dialog_events',
+  'tab_list',
+  'state_list',
+  'state_show',
+  'schedule_list',
+  'schedule_history',
+  'job_status',
+  'job_result',
+  'job_list',
+  'observe',
+]);
+
+/**
+ * Actions that manage the session rather than use it: its lifecycle, its
+ * saved state and schedules, browser-wide settings and recordings
+ */
+const ADMIN_ACTIONS = new Set([
+  'launch',
+  'prelaunch',
+  'warm_claim',
+  'close',
+  'state_save',
+  'state_load',
+  'state_clear',
+  'state_clean',
+  'state_rename',
+  'state_restore',
+  'state_migrate',
+  'schedule_add',
+  'schedule_remove',
+  'schedule_run',
+  'janitor',
+  'cookies_clear',
+  'storage_clear',
+  'storage_clear_origin',
+  'credentials',
+  'headers',
+  'useragent',
+  'offline',
+  'route',
+  'route_har',
+  'unroute',
+  'addinitscript',
+  'expose',
+  'trace_start',
+  'trace_stop',
+  'har_start',
+  'har_stop',
+  'video_start',
+  'video_stop',
+  'recording_start',
+  'recording_stop',
+  'recording_restart',
+  'screencast_start',
+  'screencast_stop',
+]);
+
+/** The least role that may run an action */
+export function requiredRole(action: string): Role {
+  if (READ_ACTIONS.has(action)) return 'read-only';
+  if (ADMIN_ACTIONS.has(action)) return 'admin';
+  return 'interact';
+}
+
+/**
+ * The least role that may run a command. screenshot and pdf only read the
+ * page, but with a `path` they write a file wherever the caller says, so they
+ * need interact.
+ */
+export function commandRole(command: { action: string; path?: unknown }): Role {
+  const role = requiredRole(command.action);
+  return role === 'read-only' && command.path != null ? 'interact' : role;
+}
+
+export function roleAllows(role: Role, required: Role): boolean {
+  return ROLE_RANK[role] >= ROLE_RANK[required];
+}
+
+/** Whether a session matches the token's patterns (* matches any run of characters) */
+export function sessionVisible(patterns: string[] | undefined, session: string): boolean {
+  if (!patterns) return true;
+  return patterns.some((pattern) => {
+    const source = pattern
+      .split('*')
+      .map((part) => part.replace(/[.+?^${}()|[\]\\]/g, '\\$&'))
+      .join('.*');
+    return new RegExp(`^${source}$`).test(session);
+  });
+}
+
+export function hashToken(token: string): string {
+  return crypto.createHash('sha256').update(token).digest('hex');
+}
+
+/**
+ * Path of the tokens file: AGENT_BROWSER_TOKENS_FILE, or tokens.json in
+ * ~/.agent-browser (%APPDATA%\agent-browser on Windows)
+ */
+export function getTokensFile(): string {
+  if (process.env.AGENT_BROWSER_TOKENS_FILE) return process.env.AGENT_BROWSER_TOKENS_FILE;
+  if (process.platform === 'win32' && process.env.APPDATA) {
+    return path.join(process.env.APPDATA, 'agent-browser', 'tokens.json');
+  }
+  return path.join(os.homedir(), '.agent-browser', 'tokens.json');
+}
+
+/** Tokens from the file, none when it doesn't exist; throws when it can't be read */
+export function loadTokens(file: string): AccessToken[] {
+  let source: string;
+  try {
+    source = fs.readFileSync(file, 'utf-8');
+  } catch (err) {
+    if ((err as NodeJS.ErrnoException).code === 'ENOENT') return [];
+    throw err;
+  }
+  const parsed = JSON.parse(source) as { tokens?: AccessToken[] };
+  return parsed.tokens ?? [];
+}
+
+function findToken(tokens: AccessToken[], presented: string): AccessToken | undefined {
+  const hash = Buffer.from(hashToken(presented));
+  return tokens.find((token) => {
+    const expected = Buffer.from(token.sha256);
+    return expected.length === hash.length && crypto.timingSafeEqual(expected, hash);
+  });
+}
+
+function denied(id: string, error: string, data: Record<string, unknown>): ErrorResponse {
+  return { id, success: false, error, data };
+}
+
+/**
+ * Check a command against the tokens: null when it may run, otherwise the
+ * error to answer with
+ */
+export function authorize(
+  tokens: AccessToken[],
+  command: { id: string; action: string; token?: string; path?: unknown },
+  session: string
+): ErrorResponse | null {
+  if (tokens.length === 0) return null;
+  const token = command.token ? findToken(tokens, command.token) : undefined;
+  if (!token) {
+    const error = command.token
+      ? 'Invalid access token'
+      : 'This daemon requires an access token: pass --token or set AGENT_BROWSER_TOKEN';
+    return denied(command.id, error, { type: 'unauthorized' });
+  }
+  if (!sessionVisible(token.sessions, session)) {
+    return denied(command.id, `Session ${session} is not available to token ${token.name}`, {
+      type: 'session_not_visible',
+    });
+  }
+  const required = commandRole(command);
+  if (!roleAllows(token.role, required)) {
+    const what =
+      required === requiredRole(command.action) ? command.action : `${command.action} with a path`;
+    return denied(
+      command.id,
+      `Token ${token.name} (${token.role}) cannot run ${what}: it needs ${required}`,
+      { type: 'forbidden', role: token.role, required }
+    );
+  }
+  return null;
+}
+
+/** authorize() against the tokens file; while it can't be read, nothing runs */
+export function checkAccess(
+  command: { id: string; action: string; token?: string; path?: unknown },
+  session: string
+): ErrorResponse | null {
+  const file = getTokensFile();
+  let tokens: AccessToken[];
+  try {
+    tokens = loadTokens(file);
+  } catch (err) {
+    const message = err instanceof Error ? err.message : String(err);
+    return denied(command.id, `Tokens file ${file} is unreadable: ${message}`, {
+      type: 'unauthorized',
+    });
+  }
+  return authorize(tokens, command, session);
+}
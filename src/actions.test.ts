@@ -1,5 +1,5 @@
 import { describe, it, expect } from 'vitest';
-import { toAIFriendlyError } from './actions.js';
+import { failedActionabilityCheck, toAIFriendlyError } from './actions.js';
 
 describe('toAIFriendlyError', () => {
   describe('element blocked by overlay', () => {
@@ -37,3 +37,40 @@ describe('toAIFriendlyError', () => {
     });
   });
 });
+
+describe('failedActionabilityCheck', () => {
+  const callLog = (...lines: string[]) =>
+    'TimeoutError: locator.click: Timeout 5000ms exceeded.\nCall log:\n' + lines.join('\n');
+
+  it('reports the last check still failing', () => {
+    const message = callLog(
+      "  - waiting for locator('#save')",
+      '    - locator resolved to <button id="save">Save</button>',
+      '  - attempting click action',
+      '    - waiting for element to be visible, enabled and stable',
+      '      - element is not visible',
+      '    - retrying click action',
+      '      - element is not stable'
+    );
+    expect(failedActionabilityCheck(message)).toBe('stable');
+    expect(toAIFriendlyError(new Error(message), '#save').message).toContain('kept moving');
+  });
+
+  it('reports an element covered by another', () => {
+    const message = callLog(
+      '      - element is visible, enabled and stable',
+      '      - <div class="modal-backdrop"></div> intercepts pointer events'
+    );
+    expect(failedActionabilityCheck(message)).toBe('not covered');
+  });
+
+  it('reports a selector that never matched as not attached', () => {
+    expect(failedActionabilityCheck(callLog("  - waiting for locator('#missing')"))).toBe(
+      'attached'
+    );
+  });
+
+  it('ignores errors that are not timeouts', () => {
+    expect(failedActionabilityCheck('Element is not an <input>')).toBeNull();
+  });
+});
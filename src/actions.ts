@@ -1,7 +1,12 @@
+import * as crypto from 'crypto';
 import * as fs from 'fs';
+import * as os from 'os';
 import * as path from 'path';
-import type { Page, Frame } from 'playwright-core';
+import type { Page, Frame, Locator, Download } from 'playwright-core';
 import type { BrowserManager, ScreencastFrame } from './browser.js';
+import { ariaRoleAndName } from './snapshot.js';
+import { diffSnapshots, SNAPSHOT_HISTORY_SIZE } from './snapshot-diff.js';
+import { BinaryData } from './framing.js';
 import {
   getSessionsDir,
   readStateFile,
@@ -9,7 +14,14 @@ import {
   isEncryptedPayload,
   listStateFiles,
   cleanupExpiredStates,
+  getStateBackupCount,
+  getStateBackupPath,
+  removeStateFile,
+  restoreStateBackup,
+  writeStateFile,
+  CorruptStateError,
 } from './state-utils.js';
+import { STATE_VERSION, UnsupportedStateVersionError } from './state-migrations.js';
 import type {
   Command,
   Response,
@@ -42,12 +54,20 @@ import type {
   WindowNewCommand,
   CookiesSetCommand,
   StorageGetCommand,
+  IndexedDbListCommand,
+  IndexedDbDumpCommand,
+  StorageOriginsCommand,
+  StorageClearOriginCommand,
   StorageSetCommand,
   StorageClearCommand,
   DialogCommand,
   PdfCommand,
   RouteCommand,
+  RouteHarCommand,
   RequestsCommand,
+  AssertRequestCommand,
+  SecurityAuditCommand,
+  CheckLinksCommand,
   DownloadCommand,
   GeolocationCommand,
   PermissionsCommand,
@@ -55,9 +75,12 @@ import type {
   DeviceCommand,
   GetAttributeCommand,
   GetTextCommand,
+  TextCommand,
+  ArticleCommand,
   IsVisibleCommand,
   IsEnabledCommand,
   IsCheckedCommand,
+  ElementStateCommand,
   CountCommand,
   BoundingBoxCommand,
   StylesCommand,
@@ -70,7 +93,11 @@ import type {
   StateShowCommand,
   StateCleanCommand,
   StateRenameCommand,
+  StateRestoreCommand,
+  StateMigrateCommand,
   ConsoleCommand,
+  DialogEventsCommand,
+  JanitorCommand,
   ErrorsCommand,
   KeyboardCommand,
   WheelCommand,
@@ -94,6 +121,8 @@ import type {
   GetByTestIdCommand,
   NthCommand,
   WaitForUrlCommand,
+  WaitForRouteCommand,
+  SpaNavigateCommand,
   WaitForLoadStateCommand,
   SetContentCommand,
   TimezoneCommand,
@@ -102,6 +131,9 @@ import type {
   MouseMoveCommand,
   MouseDownCommand,
   MouseUpCommand,
+  ClickAtCommand,
+  MoveAtCommand,
+  DragAtCommand,
   WaitForFunctionCommand,
   ScrollIntoViewCommand,
   AddInitScriptCommand,
@@ -119,8 +151,11 @@ import type {
   RecordingStartCommand,
   RecordingStopCommand,
   RecordingRestartCommand,
+  GifStartCommand,
+  GifStopCommand,
   NavigateData,
   ScreenshotData,
+  RefAnnotation,
   EvaluateData,
   ContentData,
   TabListData,
@@ -132,10 +167,50 @@ import type {
   RecordingStartData,
   RecordingStopData,
   RecordingRestartData,
+  GifStartData,
+  GifStopData,
   InputEventData,
   StylesData,
 } from './types.js';
 import { successResponse, errorResponse } from './protocol.js';
+import { JANITOR_RULES } from './janitor.js';
+import { DEFAULT_GIF_COMMANDS, DEFAULT_GIF_DELAY_MS } from './gif.js';
+import { buildArticle, collectArticle, type ArticleData } from './article.js';
+import { elementContentHash } from './content-hash.js';
+import { checkRequests } from './request-assert.js';
+import { encodeSubmission, type FormSubmission } from './form-submit.js';
+import {
+  cookieIssues,
+  cspReport,
+  mixedContent,
+  thirdPartyOrigins,
+  type PageResources,
+} from './security-audit.js';
+import {
+  DEFAULT_LINK_CONCURRENCY,
+  checkLink,
+  isBroken,
+  linkTargets,
+  mapConcurrent,
+  type LinkFetch,
+  type LinkResult,
+} from './link-check.js';
+import { insideForm } from './read-only.js';
+import {
+  addCookieOrigins,
+  cookieMatchesHost,
+  normalizeOrigin,
+  originReports,
+  storageAmount,
+  type OriginData,
+} from './storage-origins.js';
+import { hostBlockedResponse } from './host-rules.js';
+import {
+  checkDownload,
+  downloadBlockedResponse,
+  type DownloadLimits,
+  type DownloadViolation,
+} from './download-limits.js';
 
 // Callback for screencast frames - will be set by the daemon when streaming is active
 let screencastFrameCallback: ((frame: ScreencastFrame) => void) | null = null;
@@ -153,7 +228,14 @@ export function setScreencastFrameCallback(
 // Snapshot response type
 interface SnapshotData {
   snapshot: string;
+  /** Id a later snapshot can pass as diffFrom */
+  snapshotId: string;
   refs?: Record<string, { role: string; name?: string }>;
+  /** With diffFrom: the snapshot compared against and how many lines differ */
+  diffFrom?: string;
+  added?: number;
+  removed?: number;
+  changed?: number;
 }
 
 /**
@@ -184,6 +266,18 @@ export function toAIFriendlyError(error: unknown, selector: string): Error {
     );
   }
 
+  // Handle element found but never still or enabled before the timeout
+  const check = failedActionabilityCheck(message);
+  if (check === 'stable') {
+    return new Error(
+      `Element "${selector}" kept moving (animating or resizing). ` +
+        `Wait for the animation to finish and try again.`
+    );
+  }
+  if (check === 'enabled') {
+    return new Error(`Element "${selector}" is disabled.`);
+  }
+
   // Handle element not visible
   if (message.includes('not visible') && !message.includes('Timeout')) {
     return new Error(
@@ -207,6 +301,50 @@ export function toAIFriendlyError(error: unknown, selector: string): Error {
   return error instanceof Error ? error : new Error(message);
 }
 
+/** Checks Playwright makes before clicking, as they appear in its call log */
+const ACTIONABILITY_CHECKS: Array<[string, string[]]> = [
+  ['not covered', ['intercepts pointer events']],
+  ['attached', ['element is not attached', 'element was detached']],
+  ['visible', ['element is not visible']],
+  ['stable', ['element is not stable']],
+  ['enabled', ['element is not enabled', 'element is disabled']],
+  ['editable', ['element is not editable']],
+];
+
+/**
+ * The actionability check that was still failing when an interaction timed
+ * out: the last one the call log reports, or `attached` when the selector
+ * never matched anything.
+ */
+export function failedActionabilityCheck(message: string): string | null {
+  if (!message.includes('Timeout')) return null;
+  const lines = message.split('\n').reverse();
+  for (const line of lines) {
+    for (const [check, patterns] of ACTIONABILITY_CHECKS) {
+      if (patterns.some((pattern) => line.includes(pattern))) return check;
+    }
+  }
+  if (message.includes('waiting for') && !message.includes('resolved to')) return 'attached';
+  return null;
+}
+
+/** toAIFriendlyError for click and type, naming the check that failed */
+function actionabilityError(error: unknown, selector: string): Error {
+  const friendly = toAIFriendlyError(error, selector);
+  const check = failedActionabilityCheck(error instanceof Error ? error.message : String(error));
+  if (!check) return friendly;
+  return new Error(`${friendly.message} (failed check: ${check}; --force skips the checks)`);
+}
+
+function clickOptions(command: ClickCommand) {
+  return {
+    button: command.button,
+    clickCount: command.clickCount,
+    delay: command.delay,
+    force: command.force,
+  };
+}
+
 /**
  * Execute a command and return a response
  */
@@ -215,6 +353,9 @@ export async function executeCommand(command: Command, browser: BrowserManager):
     switch (command.action) {
       case 'launch':
         return await handleLaunch(command, browser);
+      case 'prelaunch':
+        // The daemon launches the browser before running any other command
+        return successResponse(command.id, { launched: browser.isLaunched() });
       case 'navigate':
         return await handleNavigate(command, browser);
       case 'click':
@@ -285,6 +426,14 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleCookiesClear(command, browser);
       case 'storage_get':
         return await handleStorageGet(command, browser);
+      case 'indexeddb_list':
+        return await handleIndexedDbList(command, browser);
+      case 'indexeddb_dump':
+        return await handleIndexedDbDump(command, browser);
+      case 'storage_origins':
+        return await handleStorageOrigins(command, browser);
+      case 'storage_clear_origin':
+        return await handleStorageClearOrigin(command, browser);
       case 'storage_set':
         return await handleStorageSet(command, browser);
       case 'storage_clear':
@@ -295,10 +444,18 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handlePdf(command, browser);
       case 'route':
         return await handleRoute(command, browser);
+      case 'route_har':
+        return await handleRouteHar(command, browser);
       case 'unroute':
         return await handleUnroute(command, browser);
       case 'requests':
         return await handleRequests(command, browser);
+      case 'assert_request':
+        return await handleAssertRequest(command, browser);
+      case 'security_audit':
+        return await handleSecurityAudit(command, browser);
+      case 'check_links':
+        return await handleCheckLinks(command, browser);
       case 'download':
         return await handleDownload(command, browser);
       case 'geolocation':
@@ -317,6 +474,8 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleForward(command, browser);
       case 'reload':
         return await handleReload(command, browser);
+      case 'stop':
+        return await handleStop(command, browser);
       case 'url':
         return await handleUrl(command, browser);
       case 'title':
@@ -325,12 +484,18 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleGetAttribute(command, browser);
       case 'gettext':
         return await handleGetText(command, browser);
+      case 'text':
+        return await handleText(command, browser);
+      case 'article':
+        return await handleArticle(command, browser);
       case 'isvisible':
         return await handleIsVisible(command, browser);
       case 'isenabled':
         return await handleIsEnabled(command, browser);
       case 'ischecked':
         return await handleIsChecked(command, browser);
+      case 'elementstate':
+        return await handleElementState(command, browser);
       case 'count':
         return await handleCount(command, browser);
       case 'boundingbox':
@@ -363,8 +528,16 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleStateClean(command);
       case 'state_rename':
         return await handleStateRename(command);
+      case 'state_restore':
+        return await handleStateRestore(command);
+      case 'state_migrate':
+        return await handleStateMigrate(command);
       case 'console':
         return await handleConsole(command, browser);
+      case 'dialog_events':
+        return await handleDialogEvents(command, browser);
+      case 'janitor':
+        return await handleJanitor(command, browser);
       case 'errors':
         return await handleErrors(command, browser);
       case 'keyboard':
@@ -417,6 +590,10 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleNth(command, browser);
       case 'waitforurl':
         return await handleWaitForUrl(command, browser);
+      case 'waitforroute':
+        return await handleWaitForRoute(command, browser);
+      case 'spanavigate':
+        return await handleSpaNavigate(command, browser);
       case 'waitforloadstate':
         return await handleWaitForLoadState(command, browser);
       case 'setcontent':
@@ -431,6 +608,12 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleMouseMove(command, browser);
       case 'mousedown':
         return await handleMouseDown(command, browser);
+      case 'click_at':
+        return await handleClickAt(command, browser);
+      case 'move_at':
+        return await handleMoveAt(command, browser);
+      case 'drag_at':
+        return await handleDragAt(command, browser);
       case 'mouseup':
         return await handleMouseUp(command, browser);
       case 'bringtofront':
@@ -469,6 +652,10 @@ export async function executeCommand(command: Command, browser: BrowserManager):
         return await handleRecordingStop(command, browser);
       case 'recording_restart':
         return await handleRecordingRestart(command, browser);
+      case 'gif_start':
+        return await handleGifStart(command, browser);
+      case 'gif_stop':
+        return await handleGifStop(command, browser);
       default: {
         // TypeScript narrows to never here, but we handle it for safety
         const unknownCommand = command as { id: string; action: string };
@@ -493,26 +680,93 @@ async function handleNavigate(
   command: NavigateCommand,
   browser: BrowserManager
 ): Promise<Response<NavigateData>> {
+  // Open the tab first so headers and identity below apply to it
+  const tab = command.newTab ? (await browser.newTab()).index : undefined;
   const page = browser.getPage();
 
-  // If headers are provided, set up scoped headers for this origin
+  // If headers are provided, set up scoped headers for this origin, or send
+  // them with this navigation only (`open <url> --headers`)
+  let removeNavigationHeaders: (() => Promise<void>) | null = null;
   if (command.headers && Object.keys(command.headers).length > 0) {
-    await browser.setScopedHeaders(command.url, command.headers);
+    if (command.headersScope === 'navigation') {
+      removeNavigationHeaders = await browser.setNavigationHeaders(command.headers);
+    } else {
+      await browser.setScopedHeaders(command.url, command.headers);
+    }
+  }
+  // --headers-for: each origin's headers only go to requests for that origin
+  for (const [origin, headers] of Object.entries(command.originHeaders ?? {})) {
+    await browser.setScopedHeaders(origin, headers);
+  }
+  if (command.rotation) {
+    await browser.applyRotation(command.rotation);
   }
 
-  await page.goto(command.url, {
-    waitUntil: command.waitUntil ?? 'load',
-  });
+  browser.takeHostViolation();
+  try {
+    await page.goto(command.url, {
+      waitUntil: command.waitUntil ?? 'load',
+      referer: command.referer,
+    });
+  } catch (error) {
+    // --allow-hosts / --block-hosts refused the page or a redirect on the way
+    const blocked = browser.takeHostViolation();
+    if (blocked) return hostBlockedResponse(command.id, blocked);
+    // Headless Chromium has no PDF viewer: opening a PDF starts a download instead
+    if (!/Download is starting|net::ERR_ABORTED/.test(String(error))) throw error;
+    if (!(await browser.fetchPdf(command.url))) throw error;
+    browser.notePdf(page, command.url);
+    const name = decodeURIComponent(new URL(command.url).pathname.split('/').pop() ?? '');
+    return successResponse(command.id, { url: command.url, title: name, pdf: true, tab });
+  } finally {
+    await removeNavigationHeaders?.();
+  }
 
   return successResponse(command.id, {
     url: page.url(),
     title: await page.title(),
+    tab,
   });
 }
 
+/**
+ * --read-only refuses clicks on anything in a form: submit buttons, but also
+ * controls whose click handlers save or send data
+ */
+async function assertClickAllowed(
+  browser: BrowserManager,
+  locator: Locator,
+  selector: string
+): Promise<void> {
+  if (browser.isReadOnly() && (await locator.evaluate(insideForm))) {
+    throw new Error(`Read-only session: "${selector}" is inside a form, so clicking it is blocked`);
+  }
+}
+
 async function handleClick(command: ClickCommand, browser: BrowserManager): Promise<Response> {
   // Support both refs (@e1) and regular selectors
   const locator = browser.getLocator(command.selector);
+  await assertClickAllowed(browser, locator, command.selector);
+
+  if (command.scrollMargin && !command.newTab) {
+    try {
+      await scrollIntoViewWithMargin(locator, command.scrollMargin);
+    } catch (error) {
+      throw actionabilityError(error, command.selector);
+    }
+  }
+  if (command.expectNavigation) {
+    return await clickAndWaitForNavigation(command, browser, locator);
+  }
+  if (command.expectPopup) {
+    return await clickAndCapturePopup(command, browser, locator);
+  }
+  if (command.expectClipboard) {
+    return await clickAndCaptureClipboard(command, browser, locator);
+  }
+  if (command.interceptSubmit) {
+    return await clickAndInterceptSubmit(command, browser, locator);
+  }
 
   try {
     // If --new-tab flag is set, get the href and open in a new tab
@@ -542,31 +796,311 @@ async function handleClick(command: ClickCommand, browser: BrowserManager): Prom
       });
     }
 
-    await locator.click({
-      button: command.button,
-      clickCount: command.clickCount,
-      delay: command.delay,
-    });
+    await locator.click(clickOptions(command));
   } catch (error) {
-    throw toAIFriendlyError(error, command.selector);
+    throw actionabilityError(error, command.selector);
   }
 
   return successResponse(command.id, { clicked: true });
 }
 
+/**
+ * Click with the navigation waiter armed first: with a separate `wait` the
+ * load can finish before the waiter exists and the wait then hangs.
+ */
+async function clickAndWaitForNavigation(
+  command: ClickCommand,
+  browser: BrowserManager,
+  locator: Locator
+): Promise<Response> {
+  const page = browser.getPage();
+  const navigation = page.waitForNavigation({ waitUntil: command.waitUntil ?? 'load' });
+  // Handled below; this keeps a failed click from leaving it unhandled
+  navigation.catch(() => {});
+  try {
+    await locator.click(clickOptions(command));
+  } catch (error) {
+    throw actionabilityError(error, command.selector);
+  }
+  try {
+    await navigation;
+  } catch (error) {
+    const message = error instanceof Error ? error.message.split('\n')[0] : String(error);
+    throw new Error(`Clicked "${command.selector}" but no navigation followed (${message})`);
+  }
+  return successResponse(command.id, {
+    clicked: true,
+    url: page.url(),
+    title: await page.title(),
+  });
+}
+
+/**
+ * Click and catch the window it opens (OAuth, share dialogs): the popup is
+ * registered as a tab and becomes the active one.
+ */
+async function clickAndCapturePopup(
+  command: ClickCommand,
+  browser: BrowserManager,
+  locator: Locator
+): Promise<Response> {
+  const popupEvent = browser.getPage().waitForEvent('popup');
+  // Handled below; this keeps a failed click from leaving it unhandled
+  popupEvent.catch(() => {});
+  try {
+    await locator.click(clickOptions(command));
+  } catch (error) {
+    throw actionabilityError(error, command.selector);
+  }
+  let popup: Page;
+  try {
+    popup = await popupEvent;
+  } catch (error) {
+    const message = error instanceof Error ? error.message.split('\n')[0] : String(error);
+    throw new Error(`Clicked "${command.selector}" but no popup opened (${message})`);
+  }
+  const waitUntil = command.waitUntil ?? 'load';
+  if (waitUntil !== 'commit') {
+    await popup.waitForLoadState(waitUntil);
+  }
+  const { index, total } = await browser.adoptPage(popup);
+  return successResponse(command.id, {
+    clicked: true,
+    popup: true,
+    index,
+    total,
+    url: popup.url(),
+    title: await popup.title(),
+  });
+}
+
+/** How long a copy button gets to write to the clipboard after the click */
+const CLIPBOARD_WAIT_MS = 2000;
+
+/**
+ * Click and return what it wrote to the clipboard. The page's clipboard
+ * writes (navigator.clipboard.writeText/write and copy events, which is how
+ * execCommand('copy') works) are recorded while the click runs, so this
+ * works in headless browsers without clipboard permissions, and the writes
+ * still go through.
+ */
+async function clickAndCaptureClipboard(
+  command: ClickCommand,
+  browser: BrowserManager,
+  locator: Locator
+): Promise<Response> {
+  const page = browser.getPage();
+  await page.evaluate(() => {
+    type Capture = { text: string | null; restore: () => void };
+    const win = window as unknown as { __agentBrowserClipboard?: Capture };
+    win.__agentBrowserClipboard?.restore();
+    const clipboard = navigator.clipboard;
+    const writeText = clipboard?.writeText;
+    const write = clipboard?.write;
+    const capture: Capture = { text: null, restore: () => {} };
+    if (clipboard && writeText) {
+      clipboard.writeText = (text: string) => {
+        capture.text = String(text);
+        return writeText.call(clipboard, text);
+      };
+    }
+    if (clipboard && write) {
+      clipboard.write = async (items: ClipboardItem[]) => {
+        for (const item of items) {
+          if (item.types.includes('text/plain')) {
+            capture.text = await (await item.getType('text/plain')).text();
+            break;
+          }
+        }
+        return write.call(clipboard, items);
+      };
+    }
+    // Runs after the page's own copy handlers, which may have set the data
+    const onCopy = (event: ClipboardEvent) => {
+      const data = event.clipboardData?.getData('text/plain');
+      capture.text = data || String(window.getSelection() ?? '');
+    };
+    window.addEventListener('copy', onCopy);
+    capture.restore = () => {
+      if (clipboard && writeText) clipboard.writeText = writeText;
+      if (clipboard && write) clipboard.write = write;
+      window.removeEventListener('copy', onCopy);
+      delete win.__agentBrowserClipboard;
+    };
+    win.__agentBrowserClipboard = capture;
+  });
+  const captured = () =>
+    page.evaluate(() => {
+      const win = window as unknown as { __agentBrowserClipboard?: { text: string | null } };
+      return win.__agentBrowserClipboard?.text ?? null;
+    });
+  const restore = () =>
+    page
+      .evaluate(() => {
+        const win = window as unknown as { __agentBrowserClipboard?: { restore: () => void } };
+        win.__agentBrowserClipboard?.restore();
+      })
+      .catch(() => {});
+
+  try {
+    await locator.click(clickOptions(command));
+  } catch (error) {
+    await restore();
+    throw actionabilityError(error, command.selector);
+  }
+  let text: string | null = null;
+  const deadline = Date.now() + CLIPBOARD_WAIT_MS;
+  // Copy buttons often write after a promise or timer, so poll for a bit
+  while (text === null && Date.now() < deadline) {
+    text = await captured().catch(() => null);
+    if (text === null) await page.waitForTimeout(100);
+  }
+  await restore();
+  if (text === null) {
+    throw new Error(`Clicked "${command.selector}" but nothing was written to the clipboard`);
+  }
+  return successResponse(command.id, { clicked: true, clipboard: text });
+}
+
+/** How long a click gets to submit a form (scripts may validate first) */
+const SUBMIT_WAIT_MS = 2000;
+
+/** Per page: who gets the next submission the page reports */
+const submitListeners = new WeakMap<Page, (submission: FormSubmission) => void>();
+
+/**
+ * Click and return what the form submission it starts would send. A
+ * capturing submit listener reads the form before the page's own handlers
+ * run and, unless allowSubmit is set, cancels it so neither the browser nor
+ * those handlers send anything. form.submit(), which fires no event, is
+ * caught too. The page reports through a binding, so the submission is seen
+ * even when it navigates away.
+ */
+async function clickAndInterceptSubmit(
+  command: ClickCommand,
+  browser: BrowserManager,
+  locator: Locator
+): Promise<Response> {
+  const page = browser.getPage();
+  const allow = command.allowSubmit ?? false;
+  if (!submitListeners.has(page)) {
+    await page.exposeBinding('__agentBrowserSubmitted', (_source, submission: FormSubmission) =>
+      submitListeners.get(page)?.(submission)
+    );
+  }
+  let resolveSubmitted: (submission: FormSubmission) => void = () => {};
+  const submitted = new Promise<FormSubmission>((resolve) => (resolveSubmitted = resolve));
+  submitListeners.set(page, (submission) => resolveSubmitted(submission));
+
+  await page.evaluate((allow) => {
+    type Hooks = { restore: () => void };
+    const win = window as unknown as {
+      __agentBrowserSubmit?: Hooks;
+      __agentBrowserSubmitted: (submission: unknown) => void;
+    };
+    win.__agentBrowserSubmit?.restore();
+    const report = (form: HTMLFormElement, submitter: HTMLElement | null) => {
+      const override = (name: string) => submitter?.getAttribute(name) || null;
+      let data: FormData;
+      try {
+        data = new FormData(form, submitter);
+      } catch {
+        data = new FormData(form);
+      }
+      const fields = Array.from(data, ([name, value]) =>
+        typeof value === 'string'
+          ? { name, value }
+          : { name, file: { name: value.name, size: value.size, type: value.type } }
+      );
+      const action = override('formaction');
+      win.__agentBrowserSubmitted({
+        method: override('formmethod') ?? form.method,
+        action: action ? new URL(action, document.baseURI).href : form.action,
+        enctype: override('formenctype') ?? form.enctype,
+        target: override('formtarget') ?? form.target,
+        fields,
+      });
+    };
+    const onSubmit = (event: SubmitEvent) => {
+      if (!(event.target instanceof HTMLFormElement)) return;
+      report(event.target, event.submitter);
+      if (!allow) {
+        event.preventDefault();
+        event.stopImmediatePropagation();
+      }
+    };
+    const submit = HTMLFormElement.prototype.submit;
+    HTMLFormElement.prototype.submit = function (this: HTMLFormElement) {
+      report(this, null);
+      if (allow) submit.call(this);
+    };
+    window.addEventListener('submit', onSubmit, true);
+    win.__agentBrowserSubmit = {
+      restore: () => {
+        window.removeEventListener('submit', onSubmit, true);
+        HTMLFormElement.prototype.submit = submit;
+        delete win.__agentBrowserSubmit;
+      },
+    };
+  }, allow);
+  const restore = () =>
+    page
+      .evaluate(() => {
+        const win = window as unknown as { __agentBrowserSubmit?: { restore: () => void } };
+        win.__agentBrowserSubmit?.restore();
+      })
+      .catch(() => {});
+
+  let submission: FormSubmission | null;
+  try {
+    await locator.click(clickOptions(command));
+    submission = await Promise.race([
+      submitted,
+      new Promise<null>((resolve) => setTimeout(() => resolve(null), SUBMIT_WAIT_MS)),
+    ]);
+  } catch (error) {
+    throw actionabilityError(error, command.selector);
+  } finally {
+    submitListeners.set(page, () => {});
+    await restore();
+  }
+  if (!submission) {
+    // The browser doesn't submit a form with invalid fields
+    const invalid = await locator
+      .evaluate((el) => {
+        const form = (el as HTMLButtonElement).form ?? el.closest('form');
+        if (!form || form.checkValidity()) return [];
+        const fields = Array.from(form.elements) as HTMLInputElement[];
+        return fields
+          .filter((field) => field.checkValidity && !field.checkValidity())
+          .map((field) => field.name || field.id || field.tagName.toLowerCase());
+      })
+      .catch(() => []);
+    const reason = invalid.length > 0 ? ` (invalid fields: ${invalid.join(', ')})` : '';
+    throw new Error(`Clicked "${command.selector}" but no form was submitted${reason}`);
+  }
+  return successResponse(command.id, {
+    clicked: true,
+    submit: encodeSubmission(submission, !allow),
+  });
+}
+
 async function handleType(command: TypeCommand, browser: BrowserManager): Promise<Response> {
   const locator = browser.getLocator(command.selector);
 
   try {
     if (command.clear) {
-      await locator.fill('');
+      await locator.fill('', { force: command.force });
+    } else if (!command.force) {
+      // Typing only focuses the element, so check it could take a click first
+      await locator.click({ trial: true });
     }
 
     await locator.pressSequentially(command.text, {
       delay: command.delay,
     });
   } catch (error) {
-    throw toAIFriendlyError(error, command.selector);
+    throw actionabilityError(error, command.selector);
   }
 
   return successResponse(command.id, { typed: true });
@@ -584,6 +1118,20 @@ async function handlePress(command: PressCommand, browser: BrowserManager): Prom
   return successResponse(command.id, { pressed: true });
 }
 
+/**
+ * Whether the daemon sees the handoff directory the CLI created for a
+ * command, and so shares its filesystem: files are then written in place and
+ * only their paths sent back. Clients that send none are taken to share it.
+ */
+function sharesFilesystem(handoff: string | undefined): boolean {
+  if (handoff === undefined) return true;
+  try {
+    return fs.statSync(handoff).isDirectory();
+  } catch {
+    return false;
+  }
+}
+
 async function handleScreenshot(
   command: ScreenshotCommand,
   browser: BrowserManager
@@ -604,22 +1152,204 @@ async function handleScreenshot(
     target = browser.getLocator(command.selector);
   }
 
+  const overlaid = command.annotate || command.grid !== undefined;
+  const { area, scale } = overlaid
+    ? await screenshotArea(browser, page, command.fullPage, command.selector)
+    : { area: null, scale: 1 };
   try {
-    if (command.path) {
+    const annotations =
+      area && command.annotate ? await drawRefAnnotations(browser, page, area, scale) : undefined;
+    if (area && command.grid !== undefined) {
+      await drawGrid(page, area, scale, command.grid);
+    }
+    const shared = sharesFilesystem(command.handoff);
+    if (command.path && shared) {
       await target.screenshot({ ...options, path: command.path });
-      return successResponse(command.id, { path: command.path });
-    } else {
-      const buffer = await target.screenshot(options);
-      return successResponse(command.id, { base64: buffer.toString('base64') });
+      return successResponse(command.id, { path: command.path, annotations });
+    } else if (command.handoff && shared) {
+      const file = path.join(command.handoff, `screenshot.${command.format ?? 'png'}`);
+      await target.screenshot({ ...options, path: file });
+      return successResponse(command.id, { handoff: file, annotations });
+    }
+    const buffer = await target.screenshot(options);
+    if (command.path) {
+      const bytes = new BinaryData(buffer);
+      return successResponse(command.id, { path: command.path, bytes, annotations });
     }
+    // Base64 in JSON, raw bytes in framed responses
+    return successResponse(command.id, { base64: new BinaryData(buffer), annotations });
   } catch (error) {
     if (command.selector) {
       throw toAIFriendlyError(error, command.selector);
     }
     throw error;
+  } finally {
+    if (overlaid) {
+      await page
+        .evaluate((id) => document.getElementById(id)?.remove(), SCREENSHOT_OVERLAY_ID)
+        .catch(() => {});
+    }
   }
 }
 
+const SCREENSHOT_OVERLAY_ID = '__agent_browser_overlay__';
+
+interface PageBox {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+/**
+ * The part of the page a screenshot is about to capture, in CSS pixels from
+ * the top of the document (the viewport, the whole page, or the element when
+ * a selector is given), and the device pixel ratio that turns those into
+ * image pixels.
+ */
+async function screenshotArea(
+  browser: BrowserManager,
+  page: Page,
+  fullPage?: boolean,
+  selector?: string
+): Promise<{ area: PageBox; scale: number }> {
+  const view = await page.evaluate(() => ({
+    scrollX: window.scrollX,
+    scrollY: window.scrollY,
+    width: window.innerWidth,
+    height: window.innerHeight,
+    pageWidth: document.documentElement.scrollWidth,
+    pageHeight: document.documentElement.scrollHeight,
+    scale: window.devicePixelRatio || 1,
+  }));
+  let area = fullPage
+    ? { x: 0, y: 0, width: view.pageWidth, height: view.pageHeight }
+    : { x: view.scrollX, y: view.scrollY, width: view.width, height: view.height };
+  if (selector) {
+    const box = await browser.getLocator(selector).boundingBox({ timeout: 5000 });
+    if (!box) throw new Error(`Element ${selector} is not visible`);
+    area = { ...box, x: box.x + view.scrollX, y: box.y + view.scrollY };
+  }
+  return { area, scale: view.scale };
+}
+
+/**
+ * Overlay a labeled box on every ref of the latest snapshot that is in the
+ * captured area, for `screenshot --annotate`. Returns the refs drawn, with
+ * boxes in pixels of the image about to be taken.
+ */
+async function drawRefAnnotations(
+  browser: BrowserManager,
+  page: Page,
+  area: PageBox,
+  scale: number
+): Promise<RefAnnotation[]> {
+  const refs = Object.entries(browser.getRefMap());
+  if (refs.length === 0) {
+    throw new Error('No refs to annotate. Run snapshot first.');
+  }
+  const scroll = await page.evaluate(() => ({ x: window.scrollX, y: window.scrollY }));
+  const measured = await Promise.all(
+    refs.map(async ([ref, data]) => {
+      const box = await browser
+        .getLocatorFromRef(ref)
+        ?.boundingBox({ timeout: 1000 })
+        .catch(() => null);
+      if (!box || box.width === 0 || box.height === 0) return null;
+      // Measured against the viewport; the overlay works in page coordinates
+      const pageBox = { ...box, x: box.x + scroll.x, y: box.y + scroll.y };
+      return { ref, role: data.role, name: data.name, box: pageBox };
+    })
+  );
+  const drawn = measured.filter((a): a is RefAnnotation => {
+    if (!a) return false;
+    const { x, y, width, height } = a.box;
+    return (
+      x < area.x + area.width &&
+      x + width > area.x &&
+      y < area.y + area.height &&
+      y + height > area.y
+    );
+  });
+
+  await page.evaluate(
+    ({ id, boxes }) => {
+      // Shared by --annotate and --grid, removed after the capture
+      const root =
+        document.getElementById(id) ??
+        document.documentElement.appendChild(Object.assign(document.createElement('div'), { id }));
+      root.style.cssText =
+        'position:absolute;left:0;top:0;width:0;height:0;z-index:2147483647;pointer-events:none';
+      for (const { ref, box } of boxes) {
+        const outline = document.createElement('div');
+        outline.style.cssText =
+          `position:absolute;left:${box.x}px;top:${box.y}px;width:${box.width}px;` +
+          `height:${box.height}px;border:2px solid #e5197e;box-sizing:border-box`;
+        const label = document.createElement('div');
+        label.textContent = ref;
+        // Above the box, or just inside it at the top of the page
+        label.style.cssText =
+          `position:absolute;left:-2px;top:${box.y >= 16 ? -16 : 0}px;padding:0 3px;` +
+          'font:bold 11px/14px monospace;color:#fff;background:#e5197e;white-space:nowrap';
+        outline.appendChild(label);
+        root.appendChild(outline);
+      }
+    },
+    { id: SCREENSHOT_OVERLAY_ID, boxes: drawn }
+  );
+
+  return drawn.map((a) => ({
+    ...a,
+    box: {
+      x: Math.round((a.box.x - area.x) * scale),
+      y: Math.round((a.box.y - area.y) * scale),
+      width: Math.round(a.box.width * scale),
+      height: Math.round(a.box.height * scale),
+    },
+  }));
+}
+
+/**
+ * Overlay a coordinate grid on the captured area for `screenshot --grid`:
+ * a line every `spacing` image pixels, labeled along the top and left edges
+ * with image pixel offsets, which are what `click --at` takes for a
+ * viewport screenshot.
+ */
+async function drawGrid(page: Page, area: PageBox, scale: number, spacing: number): Promise<void> {
+  await page.evaluate(
+    ({ id, area, step, spacing }) => {
+      // Shared by --annotate and --grid, removed after the capture
+      const root =
+        document.getElementById(id) ??
+        document.documentElement.appendChild(Object.assign(document.createElement('div'), { id }));
+      root.style.cssText =
+        'position:absolute;left:0;top:0;width:0;height:0;z-index:2147483647;pointer-events:none';
+      const add = (css: string, text?: string) => {
+        const el = document.createElement('div');
+        el.style.cssText = `position:absolute;${css}`;
+        if (text) el.textContent = text;
+        root.appendChild(el);
+      };
+      const line = 'background:rgba(0,102,204,0.45)';
+      const label =
+        'padding:0 2px;font:10px/12px monospace;color:#fff;background:rgba(0,102,204,0.8);' +
+        'white-space:nowrap';
+      for (let i = 0; i * step <= area.width; i++) {
+        const left = area.x + i * step;
+        add(`left:${left}px;top:${area.y}px;width:1px;height:${area.height}px;${line}`);
+        if (i > 0) add(`left:${left + 2}px;top:${area.y + 1}px;${label}`, String(i * spacing));
+      }
+      for (let i = 0; i * step <= area.height; i++) {
+        const top = area.y + i * step;
+        add(`left:${area.x}px;top:${top}px;width:${area.width}px;height:1px;${line}`);
+        if (i > 0) add(`left:${area.x + 1}px;top:${top + 2}px;${label}`, String(i * spacing));
+      }
+      add(`left:${area.x + 1}px;top:${area.y + 1}px;${label}`, '0');
+    },
+    { id: SCREENSHOT_OVERLAY_ID, area, step: spacing / scale, spacing }
+  );
+}
+
 async function handleSnapshot(
   command: Command & {
     action: 'snapshot';
@@ -627,27 +1357,68 @@ async function handleSnapshot(
     maxDepth?: number;
     compact?: boolean;
     selector?: string;
+    noCache?: boolean;
+    diffFrom?: string;
   },
   browser: BrowserManager
 ): Promise<Response<SnapshotData>> {
-  // Use enhanced snapshot with refs and optional filtering
-  const { tree, refs } = await browser.getSnapshot({
+  const options = {
     interactive: command.interactive,
     maxDepth: command.maxDepth,
     compact: command.compact,
     selector: command.selector,
-  });
+  };
+  const previous = command.diffFrom ? browser.getSnapshotById(command.diffFrom) : undefined;
+  if (command.diffFrom && !previous) {
+    return errorResponse(
+      command.id,
+      `Unknown snapshot ${command.diffFrom}: only the last ${SNAPSHOT_HISTORY_SIZE} snapshots ` +
+        `are kept, and none after the browser closes`
+    );
+  }
+  if (previous && JSON.stringify(previous.options) !== JSON.stringify(options)) {
+    return errorResponse(
+      command.id,
+      `Snapshot ${previous.id} was taken with different options; ` +
+        `diff against a snapshot taken with the same -i/-c/-d/-s`
+    );
+  }
+
+  // Use enhanced snapshot with refs and optional filtering
+  const snapshot = await browser.getSnapshot({ ...options, cache: !command.noCache });
+  const { tree, refs, cached, id } = snapshot;
 
   // Simplify refs for output (just role and name)
-  const simpleRefs: Record<string, { role: string; name?: string }> = {};
-  for (const [ref, data] of Object.entries(refs)) {
-    simpleRefs[ref] = { role: data.role, name: data.name };
-  }
+  const simplify = (refMap: typeof refs) => {
+    const simpleRefs: Record<string, { role: string; name?: string }> = {};
+    for (const [ref, data] of Object.entries(refMap)) {
+      simpleRefs[ref] = { role: data.role, name: data.name };
+    }
+    return Object.keys(simpleRefs).length > 0 ? simpleRefs : undefined;
+  };
 
-  return successResponse(command.id, {
-    snapshot: tree || 'Empty page',
-    refs: Object.keys(simpleRefs).length > 0 ? simpleRefs : undefined,
-  });
+  let response: Response<SnapshotData>;
+  if (previous) {
+    const diff = diffSnapshots(previous.tree, snapshot);
+    response = successResponse(command.id, {
+      snapshot: diff.tree || `(no changes since ${previous.id})`,
+      snapshotId: id,
+      diffFrom: previous.id,
+      added: diff.added,
+      removed: diff.removed,
+      changed: diff.changed,
+      refs: simplify(diff.refs),
+    });
+  } else {
+    response = successResponse(command.id, {
+      snapshot: tree || 'Empty page',
+      snapshotId: id,
+      refs: simplify(refs),
+    });
+  }
+  // The page hasn't changed since this snapshot was taken
+  if (cached) response.meta = { ...response.meta, cache: true };
+  return response;
 }
 
 async function handleEvaluate(
@@ -874,6 +1645,7 @@ async function handleDoubleClick(
   browser: BrowserManager
 ): Promise<Response> {
   const locator = browser.getLocator(command.selector);
+  await assertClickAllowed(browser, locator, command.selector);
   try {
     await locator.dblclick();
   } catch (error) {
@@ -1056,6 +1828,131 @@ async function handleStorageGet(
   }
 }
 
+/** Records per object store `storage indexeddb dump` returns by default */
+const INDEXEDDB_DUMP_LIMIT = 1000;
+
+async function handleIndexedDbList(
+  command: IndexedDbListCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const databases = await browser.getPage().evaluate(async () => {
+    const done = <T>(request: IDBRequest<T>) =>
+      new Promise<T>((resolve, reject) => {
+        request.onsuccess = () => resolve(request.result);
+        request.onerror = () => reject(request.error);
+      });
+    const found = [];
+    for (const info of await indexedDB.databases()) {
+      if (!info.name) continue;
+      const db = await done(indexedDB.open(info.name));
+      const names = Array.from(db.objectStoreNames);
+      const stores = [];
+      if (names.length > 0) {
+        const tx = db.transaction(names, 'readonly');
+        for (const name of names) {
+          const store = tx.objectStore(name);
+          stores.push({
+            name,
+            keyPath: store.keyPath,
+            autoIncrement: store.autoIncrement,
+            indexes: Array.from(store.indexNames),
+            count: await done(store.count()),
+          });
+        }
+      }
+      db.close();
+      found.push({ name: info.name, version: db.version, stores });
+    }
+    return found;
+  });
+  return successResponse(command.id, { databases });
+}
+
+/**
+ * Export an IndexedDB database's records as JSON. Values that JSON can't
+ * hold are tagged: dates as {$date}, Maps and Sets as {$map}/{$set},
+ * binary data as {$bytes: length} and Blobs/Files as {$blob: {size, type}}.
+ */
+async function handleIndexedDbDump(
+  command: IndexedDbDumpCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const limit = command.limit ?? INDEXEDDB_DUMP_LIMIT;
+  const dump = await browser.getPage().evaluate(
+    async ({ name, only, limit }) => {
+      const done = <T>(request: IDBRequest<T>) =>
+        new Promise<T>((resolve, reject) => {
+          request.onsuccess = () => resolve(request.result);
+          request.onerror = () => reject(request.error);
+        });
+      const toJson = (value: unknown, seen: WeakSet<object>): unknown => {
+        if (value === undefined) return null;
+        if (typeof value === 'bigint') return value.toString();
+        if (value === null || typeof value !== 'object') return value;
+        if (value instanceof Date) {
+          return { $date: isNaN(value.getTime()) ? null : value.toISOString() };
+        }
+        if (value instanceof Blob) return { $blob: { size: value.size, type: value.type } };
+        if (value instanceof ArrayBuffer) return { $bytes: value.byteLength };
+        if (ArrayBuffer.isView(value)) return { $bytes: value.byteLength };
+        if (value instanceof RegExp) return { $regexp: String(value) };
+        // Structured clones may be cyclic
+        if (seen.has(value)) return '[circular]';
+        seen.add(value);
+        if (value instanceof Map) {
+          return { $map: Array.from(value, ([k, v]) => [toJson(k, seen), toJson(v, seen)]) };
+        }
+        if (value instanceof Set) return { $set: Array.from(value, (v) => toJson(v, seen)) };
+        if (Array.isArray(value)) return value.map((v) => toJson(v, seen));
+        return Object.fromEntries(Object.entries(value).map(([k, v]) => [k, toJson(v, seen)]));
+      };
+
+      const known = (await indexedDB.databases()).map((info) => info.name);
+      // Opening a database that doesn't exist would create it
+      if (!known.includes(name)) {
+        const names = known.filter(Boolean).join(', ') || 'none';
+        return { error: `No IndexedDB database "${name}" (databases: ${names})` };
+      }
+      const db = await done(indexedDB.open(name));
+      try {
+        const names = Array.from(db.objectStoreNames);
+        if (only && !names.includes(only)) {
+          return { error: `No object store "${only}" in "${name}" (stores: ${names.join(', ')})` };
+        }
+        const wanted = only ? [only] : names;
+        const stores = [];
+        if (wanted.length > 0) {
+          const tx = db.transaction(wanted, 'readonly');
+          for (const storeName of wanted) {
+            const store = tx.objectStore(storeName);
+            const count = await done(store.count());
+            const keys = await done(store.getAllKeys(null, limit));
+            const values = await done(store.getAll(null, limit));
+            stores.push({
+              name: storeName,
+              keyPath: store.keyPath,
+              count,
+              truncated: count > keys.length,
+              records: keys.map((key, i) => ({
+                key: toJson(key, new WeakSet()),
+                value: toJson(values[i], new WeakSet()),
+              })),
+            });
+          }
+        }
+        return { db: name, version: db.version, stores };
+      } finally {
+        db.close();
+      }
+    },
+    { name: command.db, only: command.store ?? null, limit }
+  );
+  if ('error' in dump) {
+    throw new Error(dump.error);
+  }
+  return successResponse(command.id, dump);
+}
+
 async function handleStorageSet(
   command: StorageSetCommand,
   browser: BrowserManager
@@ -1080,6 +1977,104 @@ async function handleStorageClear(
   return successResponse(command.id, { cleared: true });
 }
 
+/** The http(s) origin a page is on, null for about:blank, data: and the like */
+function pageOrigin(page: Page): string | null {
+  try {
+    const url = new URL(page.url());
+    return url.protocol === 'http:' || url.protocol === 'https:' ? url.origin : null;
+  } catch {
+    return null;
+  }
+}
+
+/**
+ * Every origin holding data in this session. localStorage and IndexedDB
+ * come from the context's storage state, sessionStorage from the open tabs
+ * (it lives per tab), and IndexedDB and cache sizes from Chromium's usage
+ * figures, which other engines don't report.
+ */
+async function handleStorageOrigins(
+  command: StorageOriginsCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const context = browser.getPage().context();
+  const state = await context.storageState({ indexedDB: true });
+  const origins = new Map<string, OriginData>();
+  const entry = (origin: string): OriginData => {
+    if (!origins.has(origin)) origins.set(origin, {});
+    return origins.get(origin)!;
+  };
+  for (const { origin, localStorage } of state.origins) {
+    entry(origin).localStorage = storageAmount(localStorage.map((item) => [item.name, item.value]));
+  }
+  for (const page of context.pages()) {
+    const origin = pageOrigin(page);
+    if (!origin) continue;
+    const items = await page
+      .evaluate(() => {
+        const items: [string, string][] = [];
+        for (let i = 0; i < sessionStorage.length; i++) {
+          const key = sessionStorage.key(i)!;
+          items.push([key, sessionStorage.getItem(key) ?? '']);
+        }
+        return items;
+      })
+      .catch(() => []);
+    const amount = storageAmount(items);
+    const data = entry(origin);
+    data.sessionStorage = {
+      keys: (data.sessionStorage?.keys ?? 0) + amount.keys,
+      bytes: (data.sessionStorage?.bytes ?? 0) + amount.bytes,
+    };
+  }
+  const cookies = await context.cookies();
+  addCookieOrigins(origins, cookies);
+
+  const cdp = await browser.getCDPSession().catch(() => null);
+  if (cdp) {
+    for (const [origin, data] of origins) {
+      const usage = await cdp.send('Storage.getUsageAndQuota', { origin }).catch(() => null);
+      if (!usage) continue;
+      const bytes = (type: string) =>
+        usage.usageBreakdown.find((item) => item.storageType === type)?.usage ?? 0;
+      data.indexedDB = bytes('indexeddb');
+      data.cacheStorage = bytes('cache_storage');
+    }
+  }
+  return successResponse(command.id, { origins: originReports(origins, cookies) });
+}
+
+/**
+ * Clear everything one origin has stored: the cookies sent to its host,
+ * its localStorage, IndexedDB, cache storage and service workers (through
+ * Chromium), and sessionStorage in the tabs open on it.
+ */
+async function handleStorageClearOrigin(
+  command: StorageClearOriginCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const origin = normalizeOrigin(command.origin);
+  const host = new URL(origin).hostname;
+  const context = browser.getPage().context();
+  const cdp = await browser.getCDPSession().catch(() => null);
+  if (!cdp) {
+    throw new Error('storage clear --origin requires a Chromium-based browser');
+  }
+  // Chromium's 'all' takes the origin's own cookies too, so list them first
+  const cookies = (await context.cookies()).filter((c) => cookieMatchesHost(c.domain, host));
+  await cdp.send('Storage.clearDataForOrigin', { origin, storageTypes: 'all' });
+  for (const cookie of cookies) {
+    await context.clearCookies({ name: cookie.name, domain: cookie.domain, path: cookie.path });
+  }
+  let tabs = 0;
+  for (const page of context.pages()) {
+    if (pageOrigin(page) !== origin) continue;
+    await page.evaluate(() => sessionStorage.clear()).catch(() => {});
+    tabs++;
+  }
+  return successResponse(command.id, { origin, cookies: cookies.length, tabs });
+}
+
 async function handleDialog(command: DialogCommand, browser: BrowserManager): Promise<Response> {
   browser.setDialogHandler(command.response, command.promptText);
   return successResponse(command.id, { handler: 'set', response: command.response });
@@ -1087,10 +2082,12 @@ async function handleDialog(command: DialogCommand, browser: BrowserManager): Pr
 
 async function handlePdf(command: PdfCommand, browser: BrowserManager): Promise<Response> {
   const page = browser.getPage();
-  await page.pdf({
-    path: command.path,
-    format: command.format ?? 'Letter',
-  });
+  const format = command.format ?? 'Letter';
+  if (!sharesFilesystem(command.handoff)) {
+    const buffer = await page.pdf({ format });
+    return successResponse(command.id, { path: command.path, bytes: new BinaryData(buffer) });
+  }
+  await page.pdf({ path: command.path, format });
   return successResponse(command.id, { path: command.path });
 }
 
@@ -1104,6 +2101,22 @@ async function handleRoute(command: RouteCommand, browser: BrowserManager): Prom
   return successResponse(command.id, { routed: command.url });
 }
 
+async function handleRouteHar(
+  command: RouteHarCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const { url, entries } = await browser.addHarRoute(command.path, {
+    url: command.url,
+    notFound: command.notFound,
+  });
+  return successResponse(command.id, {
+    routed: url,
+    har: command.path,
+    entries,
+    fallthrough: command.notFound ?? 'fallback',
+  });
+}
+
 async function handleUnroute(
   command: Command & { action: 'unroute'; url?: string },
   browser: BrowserManager
@@ -1116,18 +2129,151 @@ async function handleRequests(
   command: RequestsCommand,
   browser: BrowserManager
 ): Promise<Response> {
+  // Start tracking if not already, so `--clear` also begins a fresh capture
+  browser.startRequestTracking();
+
   if (command.clear) {
     browser.clearRequests();
     return successResponse(command.id, { cleared: true });
   }
 
-  // Start tracking if not already
-  browser.startRequestTracking();
-
   const requests = browser.getRequests(command.filter);
   return successResponse(command.id, { requests });
 }
 
+async function handleAssertRequest(
+  command: AssertRequestCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  browser.startRequestTracking();
+  const check = checkRequests(browser.getRequests(), command);
+  if (!check.passed) {
+    throw new Error(`Assertion failed: ${check.message}`);
+  }
+  return successResponse(command.id, {
+    passed: true,
+    pattern: command.pattern,
+    sent: command.sent,
+    matched: check.matches.length,
+    matches: check.matches.map(({ method, url, status }) => ({ method, url, status })),
+  });
+}
+
+async function handleSecurityAudit(
+  command: SecurityAuditCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const page = browser.getPage();
+  const url = page.url();
+  const resources: PageResources = await page.evaluate(() => {
+    const selector =
+      '[src], link[rel~="stylesheet" i][href], link[rel~="preload" i][href], ' +
+      'link[rel~="modulepreload" i][href], object[data], form[action]';
+    const urlAttributes: Record<string, string> = { FORM: 'action', OBJECT: 'data' };
+    const refs: { tag: string; url: string }[] = [];
+    for (const el of Array.from(document.querySelectorAll(selector))) {
+      const attribute = urlAttributes[el.tagName] ?? (el.hasAttribute('src') ? 'src' : 'href');
+      const value = el.getAttribute(attribute) ?? '';
+      try {
+        refs.push({ tag: el.tagName.toLowerCase(), url: new URL(value, document.baseURI).href });
+      } catch {
+        // Not a URL
+      }
+    }
+    const loaded = performance.getEntriesByType('resource').map((entry) => entry.name);
+    const metaCsp = Array.from(document.querySelectorAll('meta[http-equiv]'))
+      .filter((m) => m.getAttribute('http-equiv')?.toLowerCase() === 'content-security-policy')
+      .map((m) => m.getAttribute('content') ?? '');
+    return { refs, loaded, metaCsp };
+  });
+
+  const cookies = /^https?:/.test(url)
+    ? (await page.context().cookies(url)).map((cookie) => ({
+        name: cookie.name,
+        domain: cookie.domain,
+        path: cookie.path,
+        secure: cookie.secure,
+        httpOnly: cookie.httpOnly,
+        sameSite: cookie.sameSite,
+        issues: cookieIssues(cookie),
+      }))
+    : [];
+  const mixed = mixedContent(url, resources);
+  const csp = cspReport(browser.getDocumentHeaders(page), resources.metaCsp);
+  const thirdParty = thirdPartyOrigins(url, [
+    ...resources.loaded,
+    ...resources.refs.filter((ref) => ref.tag !== 'form').map((ref) => ref.url),
+  ]);
+  return successResponse(command.id, {
+    url,
+    cookies,
+    mixedContent: mixed,
+    csp,
+    thirdParty,
+    summary: {
+      insecureCookies: cookies.filter((cookie) => cookie.issues.length > 0).length,
+      mixedContent: mixed.length,
+      cspIssues: csp.issues.length,
+      thirdPartyOrigins: thirdParty.length,
+    },
+  });
+}
+
+async function handleCheckLinks(
+  command: CheckLinksCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const page = browser.getPage();
+  const url = page.url();
+  if (!/^https?:/.test(url)) {
+    throw new Error(`check-links needs a page loaded over http(s), not ${url}`);
+  }
+  const found = await page.evaluate(() =>
+    Array.from(document.querySelectorAll('a[href], area[href]')).map((el) => ({
+      href: (el as HTMLAnchorElement).href,
+      text: (el.textContent ?? '').replace(/\s+/g, ' ').slice(0, 80),
+    }))
+  );
+  const targets = linkTargets(found, url, command.sameOrigin ?? false);
+  // A context of its own, so the session's cookies don't go to every link
+  const request = await browser.newRequestContext();
+  const fetch: LinkFetch = async (target, method) => {
+    const response = await request.fetch(target, {
+      method,
+      maxRedirects: 0,
+      failOnStatusCode: false,
+      timeout: command.timeout ?? 10000,
+    });
+    const status = response.status();
+    const location = response.headers()['location'] ?? null;
+    await response.dispose();
+    return { status, location };
+  };
+  let links: LinkResult[];
+  try {
+    links = await mapConcurrent(
+      targets,
+      command.concurrency ?? DEFAULT_LINK_CONCURRENCY,
+      (link) => checkLink(link, fetch, (target) => browser.checkHost(target)?.reason ?? null)
+    );
+  } finally {
+    await request.dispose();
+  }
+  const broken = links.filter(isBroken);
+  const blocked = links.filter((link) => link.blocked);
+  const redirected = links.filter(
+    (link) => !isBroken(link) && !link.blocked && link.redirects.length > 0
+  );
+  return successResponse(command.id, {
+    url,
+    checked: links.length,
+    ok: links.length - broken.length - redirected.length - blocked.length,
+    broken,
+    redirected,
+    blocked,
+  });
+}
+
 async function handleDownload(
   command: DownloadCommand,
   browser: BrowserManager
@@ -1135,13 +2281,91 @@ async function handleDownload(
   const page = browser.getPage();
   const locator = browser.getLocator(command.selector);
 
-  const [download] = await Promise.all([page.waitForEvent('download'), locator.click()]);
+  // The waiter is armed before the click, so a quick download isn't missed
+  const responses = watchResponseHeaders(page);
+  let download: Download;
+  try {
+    [download] = await Promise.all([page.waitForEvent('download'), locator.click()]);
+  } finally {
+    responses.stop();
+  }
+  const violation = await enforceDownloadLimits(browser, command, download, responses.get);
+  if (violation) return downloadBlockedResponse(command.id, violation);
 
-  await download.saveAs(command.path);
+  let filePath: string;
+  if (command.path) {
+    filePath = command.path;
+    await download.saveAs(filePath);
+  } else {
+    filePath = (await download.path()) || download.suggestedFilename();
+  }
   return successResponse(command.id, {
-    path: command.path,
+    path: filePath,
     suggestedFilename: download.suggestedFilename(),
+    url: download.url(),
+    size: fs.existsSync(filePath) ? fs.statSync(filePath).size : null,
+  });
+}
+
+/**
+ * Response headers seen while waiting for a download, to learn its type and
+ * size before the file is kept
+ */
+function watchResponseHeaders(page: Page): {
+  get: (url: string) => Record<string, string> | undefined;
+  stop: () => void;
+} {
+  const seen = new Map<string, Record<string, string>>();
+  const listener = (response: { url(): string; headers(): Record<string, string> }) => {
+    seen.set(response.url(), response.headers());
+  };
+  page.context().on('response', listener);
+  return {
+    get: (url) => seen.get(url),
+    stop: () => page.context().off('response', listener),
+  };
+}
+
+/**
+ * --block-download-types and --max-download: a download whose headers show a
+ * blocked type or too large a body is cancelled, and a finished file over the
+ * limit is deleted
+ */
+async function enforceDownloadLimits(
+  browser: BrowserManager,
+  limits: DownloadLimits,
+  download: Download,
+  responseHeaders: (url: string) => Record<string, string> | undefined
+): Promise<DownloadViolation | null> {
+  if (limits.maxBytes === undefined && !limits.blockedTypes?.length) return null;
+
+  // Not every download's response is reported to the page, so ask the server,
+  // unless the host rules refuse it: the request API skips the browser's routes
+  let headers = responseHeaders(download.url());
+  if (!headers && /^https?:/.test(download.url()) && !browser.checkHost(download.url())) {
+    headers = await download
+      .page()
+      .context()
+      .request.head(download.url())
+      .then((response) => response.headers())
+      .catch(() => undefined);
+  }
+  const length = Number(headers?.['content-length']);
+  const early = checkDownload(limits, {
+    contentType: headers?.['content-type'],
+    size: Number.isFinite(length) ? length : null,
   });
+  if (early) {
+    await download.cancel();
+    return early;
+  }
+
+  const file = await download.path();
+  const late = checkDownload(limits, { size: file ? fs.statSync(file).size : null });
+  if (late) {
+    await download.delete();
+  }
+  return late;
 }
 
 async function handleGeolocation(
@@ -1211,7 +2435,10 @@ async function handleBack(
   browser: BrowserManager
 ): Promise<Response> {
   const page = browser.getPage();
-  await page.goBack();
+  // At the start of the history goBack does nothing, so extra steps are harmless
+  for (let step = 0; step < (command.steps ?? 1); step++) {
+    await page.goBack();
+  }
   return successResponse(command.id, { url: page.url() });
 }
 
@@ -1220,7 +2447,9 @@ async function handleForward(
   browser: BrowserManager
 ): Promise<Response> {
   const page = browser.getPage();
-  await page.goForward();
+  for (let step = 0; step < (command.steps ?? 1); step++) {
+    await page.goForward();
+  }
   return successResponse(command.id, { url: page.url() });
 }
 
@@ -1229,7 +2458,26 @@ async function handleReload(
   browser: BrowserManager
 ): Promise<Response> {
   const page = browser.getPage();
-  await page.reload();
+  if (command.ignoreCache) {
+    // Playwright's reload always revalidates; CDP can skip the cache
+    const cdp = await browser.getCDPSession().catch(() => {
+      throw new Error('reload --hard is only supported in Chromium');
+    });
+    const loaded = page.waitForEvent('load');
+    await cdp.send('Page.reload', { ignoreCache: true });
+    await loaded;
+  } else {
+    await page.reload();
+  }
+  return successResponse(command.id, { url: page.url() });
+}
+
+async function handleStop(
+  command: Command & { action: 'stop' },
+  browser: BrowserManager
+): Promise<Response> {
+  const page = browser.getPage();
+  await page.evaluate(() => window.stop());
   return successResponse(command.id, { url: page.url() });
 }
 
@@ -1238,6 +2486,9 @@ async function handleUrl(
   browser: BrowserManager
 ): Promise<Response> {
   const page = browser.getPage();
+  if (command.includeTitle) {
+    return successResponse(command.id, { url: page.url(), title: await page.title() });
+  }
   return successResponse(command.id, { url: page.url() });
 }
 
@@ -1262,7 +2513,36 @@ async function handleGetAttribute(
 async function handleGetText(command: GetTextCommand, browser: BrowserManager): Promise<Response> {
   const locator = browser.getLocator(command.selector);
   const text = await locator.textContent();
-  return successResponse(command.id, { text });
+  const hash = await elementContentHash(locator, text ?? '', command.hashIgnore);
+  return successResponse(command.id, { text, contentHash: hash });
+}
+
+async function handleText(command: TextCommand, browser: BrowserManager): Promise<Response> {
+  const page = browser.getPage();
+  // A PDF has no DOM text; the CLI extracts it from the file
+  const pdfUrl = command.selector ? null : await browser.pdfUrl(page);
+  if (pdfUrl && command.savePdf) {
+    const pdf = await browser.fetchPdf(pdfUrl);
+    if (pdf) {
+      // A file of the daemon's choosing, which the CLI removes once read
+      const file = path.join(os.tmpdir(), `agent-browser-pdf-${crypto.randomUUID()}.pdf`);
+      fs.writeFileSync(file, pdf, { mode: 0o600 });
+      return successResponse(command.id, {
+        pdf: { path: file, url: pdfUrl, bytes: pdf.length },
+      });
+    }
+  }
+  const locator = browser.getLocator(command.selector ?? 'body');
+  const text = await locator.innerText();
+  const hash = await elementContentHash(locator, text, command.hashIgnore);
+  return successResponse(command.id, { text, contentHash: hash });
+}
+
+async function handleArticle(
+  command: ArticleCommand,
+  browser: BrowserManager
+): Promise<Response<ArticleData>> {
+  return successResponse(command.id, buildArticle(await collectArticle(browser.getPage())));
 }
 
 async function handleIsVisible(
@@ -1292,6 +2572,45 @@ async function handleIsChecked(
   return successResponse(command.id, { checked });
 }
 
+async function handleElementState(
+  command: ElementStateCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const matches = browser.getLocator(command.selector);
+  const count = await matches.count();
+  if (count === 0) {
+    throw new Error(
+      `Element "${command.selector}" not found. Run 'snapshot' to see current page elements.`
+    );
+  }
+  const locator = matches.first();
+  const [visible, enabled, editable, checked, focused, box, ariaTree] = await Promise.all([
+    locator.isVisible(),
+    locator.isEnabled(),
+    // Both throw for elements they don't apply to (a <div>)
+    locator.isEditable().catch(() => false),
+    locator.isChecked().catch(() => null),
+    locator.evaluate((el) => {
+      let active = document.activeElement;
+      while (active?.shadowRoot?.activeElement) active = active.shadowRoot.activeElement;
+      return active === el;
+    }),
+    locator.boundingBox(),
+    locator.ariaSnapshot().catch(() => ''),
+  ]);
+  return successResponse(command.id, {
+    selector: command.selector,
+    count,
+    visible,
+    enabled,
+    editable,
+    checked,
+    focused,
+    box,
+    ...ariaRoleAndName(ariaTree),
+  });
+}
+
 async function handleCount(command: CountCommand, browser: BrowserManager): Promise<Response> {
   const page = browser.getPage();
   const count = await page.locator(command.selector).count();
@@ -1313,10 +2632,26 @@ async function handleStyles(
 ): Promise<Response<StylesData>> {
   const page = browser.getPage();
 
-  // Shared extraction logic as a string to be eval'd in browser context
-  const extractStylesScript = `(function(el) {
+  // Shared extraction logic as a string to be eval'd in browser context.
+  // Requested props may be camelCase or kebab-case and keep the name given.
+  const extractStylesScript = `(function(el, props) {
     const s = getComputedStyle(el);
     const r = el.getBoundingClientRect();
+    const cssName = (p) =>
+      p.startsWith('--') ? p : p.replace(/[A-Z]/g, (c) => '-' + c.toLowerCase());
+    const styles = props
+      ? Object.fromEntries(props.map((p) => [p, s.getPropertyValue(cssName(p))]))
+      : {
+          fontSize: s.fontSize,
+          fontWeight: s.fontWeight,
+          fontFamily: s.fontFamily.split(',')[0].trim().replace(/"/g, ''),
+          color: s.color,
+          backgroundColor: s.backgroundColor,
+          borderRadius: s.borderRadius,
+          border: s.border !== 'none' && s.borderWidth !== '0px' ? s.border : null,
+          boxShadow: s.boxShadow !== 'none' ? s.boxShadow : null,
+          padding: s.padding,
+        };
     return {
       tag: el.tagName.toLowerCase(),
       text: el.innerText?.trim().slice(0, 80) || null,
@@ -1326,41 +2661,46 @@ async function handleStyles(
         width: Math.round(r.width),
         height: Math.round(r.height),
       },
-      styles: {
-        fontSize: s.fontSize,
-        fontWeight: s.fontWeight,
-        fontFamily: s.fontFamily.split(',')[0].trim().replace(/"/g, ''),
-        color: s.color,
-        backgroundColor: s.backgroundColor,
-        borderRadius: s.borderRadius,
-        border: s.border !== 'none' && s.borderWidth !== '0px' ? s.border : null,
-        boxShadow: s.boxShadow !== 'none' ? s.boxShadow : null,
+      styles,
+      layout: {
+        display: s.display,
+        position: s.position,
+        visibility: s.visibility,
+        opacity: Number(s.opacity),
+        zIndex: s.zIndex,
+        margin: s.margin,
         padding: s.padding,
+        inViewport: r.bottom > 0 && r.right > 0 && r.top < innerHeight && r.left < innerWidth,
+        overflowing: el.scrollWidth > el.clientWidth || el.scrollHeight > el.clientHeight,
       },
     };
   })`;
+  const props = command.props ?? null;
 
   // Check if it's a ref - single element
   if (browser.isRef(command.selector)) {
     const locator = browser.getLocator(command.selector);
-    const element = (await locator.evaluate((el, script) => {
-      const fn = eval(script);
-      return fn(el);
-    }, extractStylesScript)) as StylesData['elements'][0];
-    return successResponse(command.id, { elements: [element] });
+    const element = (await locator.evaluate(
+      (el, { script, props }) => {
+        const fn = eval(script);
+        return fn(el, props);
+      },
+      { script: extractStylesScript, props }
+    )) as StylesData['elements'][0];
+    return successResponse(command.id, { elements: [element], ...(props ? { props } : {}) });
   }
 
   // CSS selector - can match multiple elements
   const elements = (await page.$$eval(
     command.selector,
-    (els, script) => {
+    (els, { script, props }) => {
       const fn = eval(script);
-      return els.map((el) => fn(el));
+      return els.map((el) => fn(el, props));
     },
-    extractStylesScript
+    { script: extractStylesScript, props }
   )) as StylesData['elements'];
 
-  return successResponse(command.id, { elements });
+  return successResponse(command.id, { elements, ...(props ? { props } : {}) });
 }
 
 // Advanced handlers
@@ -1453,6 +2793,15 @@ async function handleStateLoad(
     return errorResponse(command.id, `State file not found: ${command.path}`);
   }
 
+  // Refuse a truncated or too new file rather than silently starting without its state
+  try {
+    readStateFile(command.path);
+  } catch (e) {
+    if (e instanceof CorruptStateError || e instanceof UnsupportedStateVersionError) {
+      return errorResponse(command.id, e.message);
+    }
+  }
+
   // Launch browser with loaded state
   await browser.launch({
     id: command.id,
@@ -1521,13 +2870,13 @@ async function handleStateClear(command: StateClearCommand): Promise<Response> {
 
   if (command.all) {
     for (const file of files) {
-      fs.unlinkSync(path.join(sessionsDir, file));
+      removeStateFile(path.join(sessionsDir, file));
       deleted.push(file);
     }
   } else if (command.sessionName) {
     for (const file of files) {
       if (file.startsWith(`${command.sessionName}-`)) {
-        fs.unlinkSync(path.join(sessionsDir, file));
+        removeStateFile(path.join(sessionsDir, file));
         deleted.push(file);
       }
     }
@@ -1555,7 +2904,7 @@ async function handleStateShow(command: StateShowCommand): Promise<Response> {
   }
 
   try {
-    const { data: state, wasEncrypted } = readStateFile(filepath);
+    const { data: state, wasEncrypted, version } = readStateFile(filepath);
     const stats = fs.statSync(filepath);
 
     // Extract summary info from storage state (with type assertions)
@@ -1575,6 +2924,7 @@ async function handleStateShow(command: StateShowCommand): Promise<Response> {
       size: stats.size,
       modified: stats.mtime.toISOString(),
       encrypted: wasEncrypted,
+      version,
       summary: {
         cookiesCount,
         originsCount,
@@ -1618,8 +2968,12 @@ async function handleStateRename(command: StateRenameCommand): Promise<Response>
     return errorResponse(command.id, `Destination already exists: ${command.newName}.json`);
   }
 
-  // Rename the file
+  // Rename the file, and its backups with it
   fs.renameSync(oldPath, newPath);
+  for (let n = 1; n <= getStateBackupCount(); n++) {
+    const backup = getStateBackupPath(oldPath, n);
+    if (fs.existsSync(backup)) fs.renameSync(backup, getStateBackupPath(newPath, n));
+  }
 
   return successResponse(command.id, {
     renamed: true,
@@ -1629,6 +2983,43 @@ async function handleStateRename(command: StateRenameCommand): Promise<Response>
   });
 }
 
+async function handleStateRestore(command: StateRestoreCommand): Promise<Response> {
+  try {
+    const { backup, from } = restoreStateBackup(command.path, command.backup);
+    return successResponse(command.id, { restored: true, path: command.path, backup, from });
+  } catch (e) {
+    return errorResponse(command.id, (e as Error).message);
+  }
+}
+
+async function handleStateMigrate(command: StateMigrateCommand): Promise<Response> {
+  if (command.path && !fs.existsSync(command.path)) {
+    return errorResponse(command.id, `State file not found: ${command.path}`);
+  }
+  const sessionsDir = getSessionsDir();
+  const files = command.path
+    ? [command.path]
+    : listStateFiles().map((file) => path.join(sessionsDir, file));
+
+  const results = files.map((file) => {
+    try {
+      const { data, wasEncrypted, wasCompressed, version, changes } = readStateFile(file);
+      if (changes.length > 0 && !command.dryRun) {
+        writeStateFile(file, data, { encrypt: wasEncrypted, compress: wasCompressed });
+      }
+      return { path: file, from: version, to: STATE_VERSION, changes };
+    } catch (e) {
+      return { path: file, error: (e as Error).message };
+    }
+  });
+
+  return successResponse(command.id, {
+    migrations: results,
+    version: STATE_VERSION,
+    dryRun: command.dryRun === true,
+  });
+}
+
 async function handleConsole(command: ConsoleCommand, browser: BrowserManager): Promise<Response> {
   if (command.clear) {
     browser.clearConsoleMessages();
@@ -1639,6 +3030,29 @@ async function handleConsole(command: ConsoleCommand, browser: BrowserManager):
   return successResponse(command.id, { messages });
 }
 
+async function handleDialogEvents(
+  command: DialogEventsCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const events = [...browser.getDialogEvents()];
+  if (command.clear) {
+    browser.clearDialogEvents();
+  }
+  return successResponse(command.id, { events });
+}
+
+async function handleJanitor(command: JanitorCommand, browser: BrowserManager): Promise<Response> {
+  // Without --janitor rules, a manual sweep cleans up everything
+  const configured = browser.getJanitorLog().rules;
+  const rules = command.rules ?? (configured.length > 0 ? configured : JANITOR_RULES);
+  const swept = command.run ? await browser.sweepGarbage(rules) : undefined;
+  const log = browser.getJanitorLog();
+  if (command.clear) {
+    browser.clearJanitorLog();
+  }
+  return successResponse(command.id, { ...log, ...(swept && { swept }) });
+}
+
 async function handleErrors(command: ErrorsCommand, browser: BrowserManager): Promise<Response> {
   if (command.clear) {
     browser.clearPageErrors();
@@ -1672,6 +3086,7 @@ async function handleWheel(command: WheelCommand, browser: BrowserManager): Prom
 
 async function handleTap(command: TapCommand, browser: BrowserManager): Promise<Response> {
   const page = browser.getPage();
+  await assertClickAllowed(browser, browser.getLocator(command.selector), command.selector);
   await page.tap(command.selector);
   return successResponse(command.id, { tapped: true });
 }
@@ -1735,8 +3150,10 @@ async function handleInnerHtml(
   browser: BrowserManager
 ): Promise<Response> {
   const page = browser.getPage();
-  const html = await page.locator(command.selector).innerHTML();
-  return successResponse(command.id, { html });
+  const locator = page.locator(command.selector);
+  const html = await locator.innerHTML();
+  const hash = await elementContentHash(locator, html, command.hashIgnore, true);
+  return successResponse(command.id, { html, contentHash: hash });
 }
 
 async function handleInputValue(
@@ -1941,6 +3358,50 @@ async function handleWaitForUrl(
   return successResponse(command.id, { url: page.url() });
 }
 
+/**
+ * "#/cart" matches the hash and "/settings" the path; anything else is a URL
+ * glob. waitForURL also sees same-document (pushState/hash) navigations.
+ */
+function routeMatcher(route: string): string | ((url: URL) => boolean) {
+  if (route.startsWith('#')) return (url) => url.hash === route;
+  if (route.startsWith('/')) return (url) => url.pathname === route;
+  return route;
+}
+
+async function handleWaitForRoute(
+  command: WaitForRouteCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const page = browser.getPage();
+  // Route changes never fire load, so only wait for the URL to commit
+  await page.waitForURL(routeMatcher(command.route), {
+    timeout: command.timeout,
+    waitUntil: 'commit',
+  });
+  return successResponse(command.id, { url: page.url() });
+}
+
+async function handleSpaNavigate(
+  command: SpaNavigateCommand,
+  browser: BrowserManager
+): Promise<Response> {
+  const page = browser.getPage();
+  const url = await page.evaluate(
+    ({ route, mode }) => {
+      if (mode === 'hash') {
+        location.hash = route;
+      } else {
+        history.pushState(history.state, '', route);
+        // pushState does not fire popstate, which is what routers listen for
+        dispatchEvent(new PopStateEvent('popstate', { state: history.state }));
+      }
+      return location.href;
+    },
+    { route: command.route, mode: command.mode }
+  );
+  return successResponse(command.id, { url });
+}
+
 async function handleWaitForLoadState(
   command: WaitForLoadStateCommand,
   browser: BrowserManager
@@ -2017,6 +3478,70 @@ async function handleMouseUp(command: MouseUpCommand, browser: BrowserManager):
   return successResponse(command.id, { up: true });
 }
 
+/**
+ * Screenshot pixels to the CSS pixels the mouse works in. A screenshot is
+ * devicePixelRatio times the CSS size, so an emulated phone or a HiDPI
+ * headed browser would otherwise put every click off by that factor.
+ */
+async function cssScale(page: Page, css?: boolean): Promise<number> {
+  if (css) return 1;
+  return await page.evaluate(() => window.devicePixelRatio || 1);
+}
+
+/** A short description of the element at a CSS point, e.g. `button#save "Save"` */
+async function elementAtPoint(page: Page, x: number, y: number): Promise<string | null> {
+  return await page
+    .evaluate(
+      ([px, py]) => {
+        const el = document.elementFromPoint(px, py);
+        if (!el) return null;
+        const id = el.id ? `#${el.id}` : '';
+        const text = (el.textContent ?? '').trim().replace(/\s+/g, ' ').slice(0, 40);
+        return `${el.tagName.toLowerCase()}${id}${text ? ` "${text}"` : ''}`;
+      },
+      [x, y]
+    )
+    .catch(() => null);
+}
+
+async function handleClickAt(command: ClickAtCommand, browser: BrowserManager): Promise<Response> {
+  const page = browser.getPage();
+  const scale = await cssScale(page, command.css);
+  const [x, y] = [command.x / scale, command.y / scale];
+  // Described before the click, which may navigate away
+  const element = await elementAtPoint(page, x, y);
+  await page.mouse.click(x, y, { button: command.button ?? 'left' });
+  return successResponse(command.id, { clicked: true, x, y, scale, element });
+}
+
+async function handleMoveAt(command: MoveAtCommand, browser: BrowserManager): Promise<Response> {
+  const page = browser.getPage();
+  const scale = await cssScale(page, command.css);
+  const [x, y] = [command.x / scale, command.y / scale];
+  await page.mouse.move(x, y);
+  return successResponse(command.id, {
+    moved: true,
+    x,
+    y,
+    scale,
+    element: await elementAtPoint(page, x, y),
+  });
+}
+
+async function handleDragAt(command: DragAtCommand, browser: BrowserManager): Promise<Response> {
+  const page = browser.getPage();
+  const scale = await cssScale(page, command.css);
+  const from = { x: command.fromX / scale, y: command.fromY / scale };
+  const to = { x: command.toX / scale, y: command.toY / scale };
+  const element = await elementAtPoint(page, from.x, from.y);
+  await page.mouse.move(from.x, from.y);
+  await page.mouse.down();
+  // Intermediate moves so pages that track dragover/pointermove see a drag
+  await page.mouse.move(to.x, to.y, { steps: 10 });
+  await page.mouse.up();
+  return successResponse(command.id, { dragged: true, from, to, scale, element });
+}
+
 async function handleBringToFront(
   command: Command & { action: 'bringtofront' },
   browser: BrowserManager
@@ -2039,11 +3564,36 @@ async function handleScrollIntoView(
   command: ScrollIntoViewCommand,
   browser: BrowserManager
 ): Promise<Response> {
-  const page = browser.getPage();
-  await page.locator(command.selector).scrollIntoViewIfNeeded();
+  const locator = browser.getLocator(command.selector);
+  try {
+    if (command.scrollMargin) {
+      await scrollIntoViewWithMargin(locator, command.scrollMargin);
+    } else {
+      await locator.scrollIntoViewIfNeeded();
+    }
+  } catch (error) {
+    throw toAIFriendlyError(error, command.selector);
+  }
   return successResponse(command.id, { scrolled: true });
 }
 
+/**
+ * Scroll an element into view at least `margin` pixels below the top, so a
+ * sticky header doesn't cover it (and catch the click meant for it). An
+ * element already in view below the margin isn't moved.
+ */
+async function scrollIntoViewWithMargin(locator: Locator, margin: number): Promise<void> {
+  await locator.evaluate((el: Element, margin: number) => {
+    const rect = el.getBoundingClientRect();
+    if (rect.top >= margin && rect.bottom <= window.innerHeight) return;
+    const style = (el as HTMLElement).style;
+    const previous = style?.scrollMarginTop ?? '';
+    if (style) style.scrollMarginTop = `${margin}px`;
+    el.scrollIntoView({ block: 'start', inline: 'nearest' });
+    if (style) style.scrollMarginTop = previous;
+  }, margin);
+}
+
 async function handleAddInitScript(
   command: AddInitScriptCommand,
   browser: BrowserManager
@@ -2088,7 +3638,15 @@ async function handleWaitForDownload(
   browser: BrowserManager
 ): Promise<Response> {
   const page = browser.getPage();
-  const download = await page.waitForEvent('download', { timeout: command.timeout });
+  const responses = watchResponseHeaders(page);
+  let download: Download;
+  try {
+    download = await page.waitForEvent('download', { timeout: command.timeout });
+  } finally {
+    responses.stop();
+  }
+  const violation = await enforceDownloadLimits(browser, command, download, responses.get);
+  if (violation) return downloadBlockedResponse(command.id, violation);
 
   let filePath: string;
   if (command.path) {
@@ -2239,3 +3797,23 @@ async function handleRecordingRestart(
     stopped: result.stopped,
   });
 }
+
+async function handleGifStart(
+  command: GifStartCommand,
+  browser: BrowserManager
+): Promise<Response<GifStartData>> {
+  const commands = command.commands ?? DEFAULT_GIF_COMMANDS;
+  await browser.startGifCapture(command.path, commands, command.delay ?? DEFAULT_GIF_DELAY_MS);
+  return successResponse(command.id, { started: true, path: command.path, commands });
+}
+
+async function handleGifStop(
+  command: GifStopCommand,
+  browser: BrowserManager
+): Promise<Response<GifStopData>> {
+  const result = await browser.finishGifCapture();
+  if (!result) {
+    return errorResponse(command.id, 'No GIF capture is running (start one with: record gif)');
+  }
+  return successResponse(command.id, result);
+}
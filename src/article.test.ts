@@ -0,0 +1,86 @@
+import { describe, it, expect } from 'vitest';
+import { buildArticle, jsonLdArticles, type PageArticle } from './article.js';
+
+function page(overrides: Partial<PageArticle> = {}): PageArticle {
+  return {
+    url: 'https://news.example.com/2024/story?utm_source=feed',
+    title: 'Story | Example News',
+    lang: 'en',
+    canonical: null,
+    meta: {},
+    jsonLd: [],
+    blocks: ['First paragraph, with a comma.', 'Second paragraph.'],
+    images: [],
+    ...overrides,
+  };
+}
+
+describe('article', () => {
+  it('finds articles in JSON-LD graphs and skips broken blocks', () => {
+    const found = jsonLdArticles([
+      '{ not json',
+      JSON.stringify({
+        '@context': 'https://schema.org',
+        '@graph': [{ '@type': 'WebPage' }, { '@type': ['NewsArticle'], headline: 'Hi' }],
+      }),
+    ]);
+    expect(found).toEqual([{ '@type': ['NewsArticle'], headline: 'Hi' }]);
+  });
+
+  it('prefers JSON-LD over meta tags', () => {
+    const article = buildArticle(
+      page({
+        meta: { 'og:title': 'OG title', author: 'Meta Author', 'og:image': '/og.jpg' },
+        jsonLd: [
+          JSON.stringify({
+            '@type': 'Article',
+            headline: 'LD headline',
+            author: [{ '@type': 'Person', name: 'Ada' }, { name: 'Grace' }],
+            datePublished: '2024-05-01T08:00:00Z',
+            image: { '@type': 'ImageObject', url: 'https://cdn.example.com/hero.jpg' },
+          }),
+        ],
+      })
+    );
+    expect(article.title).toBe('LD headline');
+    expect(article.author).toBe('Ada, Grace');
+    expect(article.publishedTime).toBe('2024-05-01T08:00:00Z');
+    expect(article.heroImage).toBe('https://cdn.example.com/hero.jpg');
+  });
+
+  it('falls back to meta tags and the document', () => {
+    const article = buildArticle(
+      page({
+        canonical: '/2024/story',
+        meta: {
+          'og:title': 'OG title',
+          'article:author': 'Meta Author',
+          'article:published_time': '2024-05-01',
+          'og:image': '/og.jpg',
+          'og:site_name': 'Example News',
+        },
+        images: [
+          { src: 'https://news.example.com/og.jpg', alt: 'Hero' },
+          { src: 'https://news.example.com/chart.png', alt: 'Chart' },
+        ],
+      })
+    );
+    expect(article.canonicalUrl).toBe('https://news.example.com/2024/story');
+    expect(article.title).toBe('OG title');
+    expect(article.author).toBe('Meta Author');
+    expect(article.siteName).toBe('Example News');
+    expect(article.heroImage).toBe('https://news.example.com/og.jpg');
+    // The hero isn't repeated among the body images
+    expect(article.images).toEqual([{ src: 'https://news.example.com/chart.png', alt: 'Chart' }]);
+  });
+
+  it('joins the text blocks and counts words', () => {
+    const article = buildArticle(page());
+    expect(article.text).toBe('First paragraph, with a comma.\n\nSecond paragraph.');
+    expect(article.wordCount).toBe(7);
+    expect(article.title).toBe('Story | Example News');
+    expect(article.canonicalUrl).toBe(article.url);
+    expect(article.excerpt).toBe('First paragraph, with a comma.');
+    expect(article.author).toBeNull();
+  });
+});
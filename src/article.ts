@@ -0,0 +1,242 @@
+import type { Page } from 'playwright-core';
+
+/**
+ * Article extraction for `article`: the readable text of a news or blog
+ * page with its hero image, author, dates and canonical URL, so ingestion
+ * pipelines get one document instead of scraping each site.
+ *
+ * Metadata comes from JSON-LD first, then Open Graph / article meta tags.
+ * The body is the container scoring highest on paragraph text, in the
+ * spirit of Readability, minus navigation, sharing and comment blocks.
+ */
+
+export interface ArticleImage {
+  src: string;
+  alt: string;
+}
+
+export interface ArticleData {
+  url: string;
+  canonicalUrl: string;
+  title: string;
+  author: string | null;
+  publishedTime: string | null;
+  modifiedTime: string | null;
+  siteName: string | null;
+  lang: string | null;
+  excerpt: string | null;
+  heroImage: string | null;
+  images: ArticleImage[];
+  text: string;
+  wordCount: number;
+}
+
+/** What the page script collects; metadata is resolved outside the page */
+export interface PageArticle {
+  url: string;
+  title: string;
+  lang: string | null;
+  canonical: string | null;
+  /** Meta tag content by lowercased name or property */
+  meta: Record<string, string>;
+  /** Raw JSON-LD script contents */
+  jsonLd: string[];
+  blocks: string[];
+  images: ArticleImage[];
+}
+
+const ARTICLE_TYPES =
+  /^(Article|NewsArticle|BlogPosting|Report|ScholarlyArticle|TechArticle|LiveBlogPosting)$/;
+
+type JsonObject = Record<string, unknown>;
+
+/** Article-typed objects in JSON-LD blocks, including inside @graph and arrays */
+export function jsonLdArticles(blocks: string[]): JsonObject[] {
+  const found: JsonObject[] = [];
+  const visit = (value: unknown): void => {
+    if (Array.isArray(value)) {
+      value.forEach(visit);
+      return;
+    }
+    if (!value || typeof value !== 'object') return;
+    const object = value as JsonObject;
+    const types = ([] as unknown[]).concat(object['@type'] ?? []);
+    if (types.some((t) => typeof t === 'string' && ARTICLE_TYPES.test(t))) found.push(object);
+    if (object['@graph']) visit(object['@graph']);
+  };
+  for (const block of blocks) {
+    try {
+      visit(JSON.parse(block));
+    } catch {
+      // Sites ship broken JSON-LD often enough; skip it
+    }
+  }
+  return found;
+}
+
+/** A name from a JSON-LD person/organization, a list of them, or a string */
+function personName(value: unknown): string | null {
+  if (typeof value === 'string') return value.trim() || null;
+  if (Array.isArray(value)) {
+    const names = value.map(personName).filter((n): n is string => !!n);
+    return names.length ? names.join(', ') : null;
+  }
+  if (value && typeof value === 'object') return personName((value as JsonObject).name);
+  return null;
+}
+
+/** A URL from a JSON-LD image: a string, an ImageObject, or a list of either */
+function imageUrl(value: unknown): string | null {
+  if (typeof value === 'string') return value || null;
+  if (Array.isArray(value)) return value.length ? imageUrl(value[0]) : null;
+  if (value && typeof value === 'object') return imageUrl((value as JsonObject).url);
+  return null;
+}
+
+function stringValue(value: unknown): string | null {
+  return typeof value === 'string' && value.trim() ? value.trim() : null;
+}
+
+function absolute(url: string | null, base: string): string | null {
+  if (!url) return null;
+  try {
+    return new URL(url, base).href;
+  } catch {
+    return null;
+  }
+}
+
+/** Resolve metadata (JSON-LD over meta tags over the document) and join the text */
+export function buildArticle(page: PageArticle): ArticleData {
+  const ld = jsonLdArticles(page.jsonLd)[0] ?? {};
+  const meta = (...names: string[]) =>
+    names.map((n) => page.meta[n]?.trim()).find((v): v is string => !!v) ?? null;
+  const text = page.blocks.join('\n\n');
+  const heroImage = absolute(
+    imageUrl(ld.image) ??
+      meta('og:image', 'og:image:url', 'twitter:image') ??
+      page.images[0]?.src ??
+      null,
+    page.url
+  );
+  return {
+    url: page.url,
+    canonicalUrl:
+      absolute(page.canonical ?? meta('og:url') ?? stringValue(ld.url), page.url) ?? page.url,
+    title: stringValue(ld.headline) ?? meta('og:title', 'twitter:title') ?? page.title.trim(),
+    author:
+      personName(ld.author) ??
+      meta('author', 'article:author', 'parsely-author', 'sailthru.author'),
+    publishedTime:
+      stringValue(ld.datePublished) ??
+      meta('article:published_time', 'date', 'pubdate', 'publishdate', 'dc.date'),
+    modifiedTime: stringValue(ld.dateModified) ?? meta('article:modified_time', 'og:updated_time'),
+    siteName: meta('og:site_name', 'application-name') ?? personName(ld.publisher),
+    lang: page.lang || meta('og:locale'),
+    excerpt:
+      meta('description', 'og:description', 'twitter:description') ??
+      stringValue(ld.description) ??
+      (page.blocks[0]?.slice(0, 300) || null),
+    heroImage,
+    images: page.images.filter((image) => image.src !== heroImage),
+    text,
+    wordCount: text.split(/\s+/).filter(Boolean).length,
+  };
+}
+
+/** Collect the article's text, images and metadata tags from the page */
+export async function collectArticle(page: Page): Promise<PageArticle> {
+  return await page.evaluate(() => {
+    const clean = (s: string | null | undefined) => (s ?? '').replace(/\s+/g, ' ').trim();
+    const meta: Record<string, string> = {};
+    for (const tag of Array.from(document.querySelectorAll('meta[content]'))) {
+      const key = (tag.getAttribute('property') ?? tag.getAttribute('name') ?? '').toLowerCase();
+      if (key && !(key in meta)) meta[key] = tag.getAttribute('content') ?? '';
+    }
+    const jsonLd = Array.from(
+      document.querySelectorAll('script[type="application/ld+json"]'),
+      (s) => s.textContent ?? ''
+    );
+
+    // Work on a copy without the page furniture
+    const body = document.body.cloneNode(true) as HTMLElement;
+    const furniture =
+      'script, style, noscript, template, iframe, svg, form, nav, header, footer, aside, ' +
+      '[role=navigation], [role=banner], [role=contentinfo], [role=complementary], ' +
+      '[hidden], [aria-hidden=true]';
+    body.querySelectorAll(furniture).forEach((el) => el.remove());
+    const unlikely =
+      /comment|share|social|related|promo|sidebar|newsletter|cookie|subscribe|advert|sponsor|popup|modal|breadcrumb/i;
+    const likely = /article|content|body|post|entry|story|main|text/i;
+    body.querySelectorAll('[class], [id]').forEach((el) => {
+      const names = `${el.className} ${el.id}`;
+      if (unlikely.test(names) && !likely.test(names) && el.tagName !== 'BODY') el.remove();
+    });
+
+    // Score the containers of paragraphs by how much prose they hold
+    const scores = new Map<Element, number>();
+    for (const p of Array.from(body.querySelectorAll('p, pre, td'))) {
+      const text = clean(p.textContent);
+      if (text.length < 25) continue;
+      const score = 1 + text.split(',').length + Math.min(Math.floor(text.length / 100), 3);
+      const parent = p.parentElement;
+      if (parent) scores.set(parent, (scores.get(parent) ?? 0) + score);
+      const grandparent = parent?.parentElement;
+      if (grandparent) scores.set(grandparent, (scores.get(grandparent) ?? 0) + score / 2);
+    }
+    let best: Element = body;
+    let bestScore = 0;
+    for (const [el, score] of scores) {
+      const text = clean(el.textContent).length || 1;
+      const links = Array.from(el.querySelectorAll('a')).reduce(
+        (n, a) => n + clean(a.textContent).length,
+        0
+      );
+      const adjusted = score * (1 - links / text);
+      if (adjusted > bestScore) {
+        best = el;
+        bestScore = adjusted;
+      }
+    }
+    // A single <article> holding the winner is the better boundary
+    const article = best.closest('article');
+    if (article && body.querySelectorAll('article').length === 1) best = article;
+
+    const blocks: string[] = [];
+    for (const el of Array.from(
+      best.querySelectorAll('h1, h2, h3, h4, h5, h6, p, li, blockquote, pre, figcaption')
+    )) {
+      // Nested blocks (a <p> in a <li>) are read with their parent
+      const outer = el.parentElement?.closest('p, li, blockquote, pre, figcaption');
+      if (outer && best.contains(outer)) continue;
+      const text = el.tagName === 'PRE' ? (el.textContent ?? '').trim() : clean(el.textContent);
+      if (text) blocks.push(text);
+    }
+    if (blocks.length === 0 && clean(best.textContent)) blocks.push(clean(best.textContent));
+
+    const images: { src: string; alt: string }[] = [];
+    for (const img of Array.from(best.querySelectorAll('img'))) {
+      const src = (img as HTMLImageElement).src;
+      const width = Number(img.getAttribute('width') ?? 0);
+      const height = Number(img.getAttribute('height') ?? 0);
+      // Skip tracking pixels, icons and inline placeholders
+      if (!src || src.startsWith('data:') || (width && width < 100) || (height && height < 100)) {
+        continue;
+      }
+      if (!images.some((i) => i.src === src)) {
+        images.push({ src, alt: clean(img.getAttribute('alt')) });
+      }
+    }
+
+    return {
+      url: location.href,
+      title: document.title,
+      lang: document.documentElement.lang || null,
+      canonical: document.querySelector('link[rel="canonical"]')?.getAttribute('href') ?? null,
+      meta,
+      jsonLd,
+      blocks,
+      images,
+    };
+  });
+}
@@ -0,0 +1,34 @@
+import { describe, it, expect } from 'vitest';
+import { cdpPoolUrl, poolBackend, seleniumCapabilities } from './browser-pool.js';
+
+describe('browser pool', () => {
+  it('maps launch options onto Selenium capabilities', () => {
+    const caps = seleniumCapabilities(
+      { browserVersion: '131', 'goog:chromeOptions': { args: ['--lang=de'] } },
+      { headless: true, args: ['--window-size=1280,720'] }
+    );
+    expect(caps).toEqual({
+      browserName: 'chrome',
+      browserVersion: '131',
+      'se:cdpEnabled': true,
+      'goog:chromeOptions': { args: ['--window-size=1280,720', '--headless=new', '--lang=de'] },
+    });
+    const headed = seleniumCapabilities({}, { headless: false });
+    expect(headed['goog:chromeOptions']).toEqual({ args: [] });
+  });
+
+  it('passes capabilities to CDP pools as launch options', () => {
+    const url = new URL(
+      cdpPoolUrl('wss://pool.example/chromium?token=abc', { stealth: true }, { headless: false })
+    );
+    expect(url.searchParams.get('token')).toBe('abc');
+    expect(JSON.parse(url.searchParams.get('launch')!)).toEqual({ stealth: true, headless: false });
+    expect(cdpPoolUrl('ws://pool:3000/', {}, {})).toBe('ws://pool:3000/');
+  });
+
+  it('picks the backend from the URL scheme', () => {
+    expect(poolBackend('wss://pool.example').name).toBe('cdp');
+    expect(poolBackend('http://grid:4444/wd/hub').name).toBe('selenium');
+    expect(() => poolBackend('ftp://grid')).toThrow('Unsupported --pool URL');
+  });
+});
@@ -0,0 +1,145 @@
+/**
+ * Capabilities requested for a pooled browser (--pool-capabilities), in W3C
+ * WebDriver form: browserName, browserVersion, platformName and vendor
+ * extensions such as "se:recordVideo"
+ */
+export type PoolCapabilities = Record<string, unknown>;
+
+/** Launch options that carry over to a pooled browser */
+export interface PoolLaunchOptions {
+  headless?: boolean;
+  args?: string[];
+}
+
+/** A browser acquired from a pool, held until released */
+export interface PoolLease {
+  backend: 'selenium' | 'cdp';
+  id: string | null;
+  /** CDP WebSocket URL to connect to */
+  endpoint: string;
+  release: () => Promise<void>;
+}
+
+/**
+ * A source of remote browsers (--pool): acquire one per session, release it
+ * when the session closes
+ */
+export interface PoolBackend {
+  name: 'selenium' | 'cdp';
+  acquire: (capabilities: PoolCapabilities, launch: PoolLaunchOptions) => Promise<PoolLease>;
+}
+
+/**
+ * W3C capabilities for a new Selenium Grid session. Chrome options carry the
+ * launch args; explicit capabilities win over the derived ones.
+ */
+export function seleniumCapabilities(
+  capabilities: PoolCapabilities,
+  launch: PoolLaunchOptions
+): PoolCapabilities {
+  const args = [...(launch.args ?? [])];
+  if (launch.headless !== false) args.push('--headless=new');
+  const requested = capabilities['goog:chromeOptions'] as { args?: string[] } | undefined;
+  return {
+    browserName: 'chrome',
+    'se:cdpEnabled': true,
+    ...capabilities,
+    'goog:chromeOptions': {
+      ...requested,
+      args: [...args, ...(requested?.args ?? [])],
+    },
+  };
+}
+
+/**
+ * The pool's WebSocket URL with the capabilities as a `launch` query
+ * parameter, the convention browserless and compatible CDP pools use
+ */
+export function cdpPoolUrl(
+  url: string,
+  capabilities: PoolCapabilities,
+  launch: PoolLaunchOptions
+): string {
+  const target = new URL(url);
+  const options: Record<string, unknown> = { ...capabilities };
+  if (launch.headless !== undefined && options.headless === undefined) {
+    options.headless = launch.headless;
+  }
+  if (launch.args?.length) {
+    options.args = [...launch.args, ...((options.args as string[] | undefined) ?? [])];
+  }
+  if (Object.keys(options).length > 0) {
+    target.searchParams.set('launch', JSON.stringify(options));
+  }
+  return target.toString();
+}
+
+/**
+ * Selenium Grid 4: a WebDriver session per lease, driven over the CDP
+ * endpoint the grid proxies ("se:cdp"). Deleting the session releases it.
+ */
+function seleniumBackend(gridUrl: string): PoolBackend {
+  const base = gridUrl.replace(/\/+$/, '');
+  return {
+    name: 'selenium',
+    acquire: async (capabilities, launch) => {
+      const response = await fetch(`${base}/session`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({
+          capabilities: { alwaysMatch: seleniumCapabilities(capabilities, launch) },
+        }),
+      });
+      const body = (await response.json().catch(() => ({}))) as {
+        value?: {
+          sessionId?: string;
+          capabilities?: Record<string, unknown>;
+          message?: string;
+        };
+      };
+      if (!response.ok || !body.value?.sessionId) {
+        const reason = body.value?.message ?? response.statusText;
+        throw new Error(`Selenium Grid could not provide a browser: ${reason}`);
+      }
+      const sessionId = body.value.sessionId;
+      const release = async () => {
+        await fetch(`${base}/session/${sessionId}`, { method: 'DELETE' });
+      };
+      const cdp = body.value.capabilities?.['se:cdp'];
+      if (typeof cdp !== 'string') {
+        await release().catch(() => {});
+        throw new Error(
+          'Selenium Grid session has no CDP endpoint (se:cdp); is the node Chromium?'
+        );
+      }
+      return { backend: 'selenium', id: sessionId, endpoint: cdp, release };
+    },
+  };
+}
+
+/**
+ * CDP pools (browserless, generic): every WebSocket connection gets its own
+ * browser, returned to the pool when the connection closes
+ */
+function cdpBackend(poolUrl: string): PoolBackend {
+  return {
+    name: 'cdp',
+    acquire: async (capabilities, launch) => ({
+      backend: 'cdp',
+      id: null,
+      endpoint: cdpPoolUrl(poolUrl, capabilities, launch),
+      release: async () => {},
+    }),
+  };
+}
+
+/**
+ * The backend for a --pool URL: ws(s):// for CDP pools, http(s):// for
+ * Selenium Grid 4
+ */
+export function poolBackend(url: string): PoolBackend {
+  const protocol = new URL(url).protocol;
+  if (protocol === 'ws:' || protocol === 'wss:') return cdpBackend(url);
+  if (protocol === 'http:' || protocol === 'https:') return seleniumBackend(url);
+  throw new Error(`Unsupported --pool URL ${url}: use ws(s):// or http(s)://`);
+}
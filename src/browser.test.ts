@@ -1,7 +1,52 @@
 import { describe, it, expect, beforeAll, afterAll, vi } from 'vitest';
+import http from 'node:http';
+import type { AddressInfo } from 'node:net';
 import { BrowserManager } from './browser.js';
+import { executeCommand } from './actions.js';
 import { chromium } from 'playwright-core';
 
+/**
+ * Local server for tests that need to see the requests the browser sends.
+ * Paths answer with their entry in `pages` (HTML, a redirect or a file to
+ * download), or an empty page.
+ */
+async function startServer() {
+  const pages: Record<
+    string,
+    string | { redirect: string } | { attachment: string; type: string }
+  > = {};
+  const requests: { host: string; path: string; headers: http.IncomingHttpHeaders }[] = [];
+  const server = http.createServer((req, res) => {
+    const path = req.url ?? '/';
+    requests.push({ host: req.headers.host ?? '', path, headers: req.headers });
+    const page = pages[path];
+    if (typeof page === 'object' && 'redirect' in page) {
+      res.writeHead(302, { location: page.redirect }).end();
+    } else if (typeof page === 'object') {
+      res
+        .writeHead(200, {
+          'content-type': page.type,
+          'content-disposition': `attachment; filename="${path.slice(1)}"`,
+        })
+        .end(page.attachment);
+    } else {
+      res.writeHead(200, { 'content-type': 'text/html' }).end(page ?? '<title>ok</title>');
+    }
+  });
+  await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+  const port = (server.address() as AddressInfo).port;
+  return {
+    pages,
+    requests,
+    /** The same server under two origins */
+    origin: `http://127.0.0.1:${port}`,
+    otherOrigin: `http://localhost:${port}`,
+    /** The last request for a path */
+    request: (path: string) => requests.filter((r) => r.path === path).pop(),
+    close: () => new Promise<void>((resolve) => server.close(() => resolve())),
+  };
+}
+
 describe('BrowserManager', () => {
   let browser: BrowserManager;
 
@@ -661,3 +706,277 @@ describe('BrowserManager', () => {
     });
   });
 });
+
+describe('navigate', () => {
+  let browser: BrowserManager;
+  let server: Awaited<ReturnType<typeof startServer>>;
+
+  beforeAll(async () => {
+    browser = new BrowserManager();
+    await browser.launch({ id: 'test', action: 'launch', headless: true });
+    server = await startServer();
+  });
+
+  afterAll(async () => {
+    await browser.close();
+    await server.close();
+  });
+
+  it('attaches --headers-for headers only to requests for their origin', async () => {
+    server.pages['/scoped'] = `<img src="${server.otherOrigin}/scoped-pixel">`;
+    const response = await executeCommand(
+      {
+        id: '1',
+        action: 'navigate',
+        url: `${server.origin}/scoped`,
+        originHeaders: { [new URL(server.origin).host]: { 'x-token': 'secret' } },
+      },
+      browser
+    );
+    expect(response.success).toBe(true);
+    expect(server.request('/scoped')?.headers['x-token']).toBe('secret');
+    expect(server.request('/scoped-pixel')?.headers['x-token']).toBeUndefined();
+    await browser.clearScopedHeaders();
+  });
+
+  it('sends navigation-scoped headers with that navigation only', async () => {
+    server.pages['/once'] = `<img src="${server.origin}/once-pixel">`;
+    const response = await executeCommand(
+      {
+        id: '1',
+        action: 'navigate',
+        url: `${server.origin}/once`,
+        headers: { 'x-debug': '1' },
+        headersScope: 'navigation',
+      },
+      browser
+    );
+    expect(response.success).toBe(true);
+    expect(server.request('/once')?.headers['x-debug']).toBe('1');
+    expect(server.request('/once-pixel')?.headers['x-debug']).toBeUndefined();
+
+    await executeCommand({ id: '2', action: 'navigate', url: `${server.origin}/after` }, browser);
+    expect(server.request('/after')?.headers['x-debug']).toBeUndefined();
+  });
+
+  it('presents the rotated identity to the site', async () => {
+    const rotation = {
+      userAgent: 'RotatedAgent/1.0',
+      acceptLanguage: 'fr-FR',
+      viewport: { width: 800, height: 600 },
+    };
+    const url = `${server.origin}/rotated`;
+    const response = await executeCommand({ id: '1', action: 'navigate', url, rotation }, browser);
+    expect(response.success).toBe(true);
+    expect(server.request('/rotated')?.headers['user-agent']).toBe('RotatedAgent/1.0');
+    expect(server.request('/rotated')?.headers['accept-language']).toContain('fr-FR');
+    const page = browser.getPage();
+    expect(await page.evaluate(() => navigator.userAgent)).toBe('RotatedAgent/1.0');
+    expect(page.viewportSize()).toEqual({ width: 800, height: 600 });
+  });
+
+  it('goes back and forward several steps', async () => {
+    for (const path of ['/h1', '/h2', '/h3']) {
+      await executeCommand({ id: path, action: 'navigate', url: server.origin + path }, browser);
+    }
+    const back = await executeCommand({ id: '1', action: 'back', steps: 2 }, browser);
+    expect(back.success && back.data).toEqual({ url: `${server.origin}/h1` });
+    const forward = await executeCommand({ id: '2', action: 'forward', steps: 2 }, browser);
+    expect(forward.success && forward.data).toEqual({ url: `${server.origin}/h3` });
+  });
+
+  it('bypasses the cache on a hard reload', async () => {
+    await executeCommand({ id: '1', action: 'navigate', url: `${server.origin}/hard` }, browser);
+    const reload = await executeCommand({ id: '2', action: 'reload', ignoreCache: true }, browser);
+    expect(reload.success).toBe(true);
+    expect(server.request('/hard')?.headers['cache-control']).toBe('no-cache');
+  });
+
+  it('returns the title with the url, and stops loading', async () => {
+    await executeCommand({ id: '1', action: 'navigate', url: `${server.origin}/titled` }, browser);
+    const url = await executeCommand({ id: '2', action: 'url', includeTitle: true }, browser);
+    expect(url.success && url.data).toEqual({ url: `${server.origin}/titled`, title: 'ok' });
+    const stop = await executeCommand({ id: '3', action: 'stop' }, browser);
+    expect(stop.success).toBe(true);
+  });
+
+  it('sends the referer and opens a new tab on request', async () => {
+    server.pages['/from'] = '<title>from</title>';
+    const tabs = browser.getPages().length;
+    const response = await executeCommand(
+      {
+        id: '1',
+        action: 'navigate',
+        url: `${server.origin}/from`,
+        waitUntil: 'commit',
+        referer: 'https://search.example/',
+        newTab: true,
+      },
+      browser
+    );
+    expect(response.success && response.data).toMatchObject({ tab: tabs });
+    expect(browser.getPages().length).toBe(tabs + 1);
+    expect(server.request('/from')?.headers.referer).toBe('https://search.example/');
+    await executeCommand({ id: '2', action: 'tab_close' }, browser);
+  });
+
+  it('changes and waits for client-side routes without a reload', async () => {
+    server.pages['/app'] = `<script>
+      addEventListener('popstate', () => (document.title = location.pathname));
+    </script>`;
+    await executeCommand({ id: '1', action: 'navigate', url: `${server.origin}/app` }, browser);
+
+    const waiting = executeCommand({ id: '2', action: 'waitforroute', route: '#/cart' }, browser);
+    const hash = await executeCommand(
+      { id: '3', action: 'spanavigate', route: '#/cart', mode: 'hash' },
+      browser
+    );
+    expect(hash.success && hash.data).toEqual({ url: `${server.origin}/app#/cart` });
+    expect((await waiting).success).toBe(true);
+
+    const pushed = await executeCommand(
+      { id: '4', action: 'spanavigate', route: '/settings', mode: 'history' },
+      browser
+    );
+    expect(pushed.success && pushed.data).toEqual({ url: `${server.origin}/settings` });
+    const route = await executeCommand(
+      { id: '5', action: 'waitforroute', route: '/settings', timeout: 1000 },
+      browser
+    );
+    expect(route.success).toBe(true);
+    expect(await browser.getPage().title()).toBe('/settings');
+    expect(server.request('/settings')).toBeUndefined();
+  });
+});
+
+describe('host rules', () => {
+  let browser: BrowserManager;
+  let server: Awaited<ReturnType<typeof startServer>>;
+
+  beforeAll(async () => {
+    browser = new BrowserManager();
+    await browser.launch({
+      id: 'test',
+      action: 'launch',
+      headless: true,
+      blockHosts: ['localhost'],
+    });
+    // The daemon does this between commands
+    await browser.enforceHostRules();
+    server = await startServer();
+  });
+
+  afterAll(async () => {
+    await browser.close();
+    await server.close();
+  });
+
+  it('refuses subresources from blocked hosts', async () => {
+    server.pages['/embeds'] = `<img src="${server.otherOrigin}/tracker">`;
+    const response = await executeCommand(
+      { id: '1', action: 'navigate', url: `${server.origin}/embeds` },
+      browser
+    );
+    expect(response.success).toBe(true);
+    expect(server.request('/tracker')).toBeUndefined();
+    const reason = "localhost matches --block-hosts rule 'localhost'";
+    expect(browser.getAndClearWarnings()).toEqual([
+      `Host rules: blocked ${server.otherOrigin}/tracker (${reason})`,
+    ]);
+  });
+
+  it('refuses redirects to blocked hosts', async () => {
+    server.pages['/hop'] = { redirect: `${server.otherOrigin}/landing` };
+    const response = await executeCommand(
+      { id: '1', action: 'navigate', url: `${server.origin}/hop` },
+      browser
+    );
+    expect(response.success).toBe(false);
+    expect(!response.success && response.data).toEqual({ type: 'host_blocked', host: 'localhost' });
+    expect(server.request('/hop')).toBeDefined();
+    expect(server.request('/landing')).toBeUndefined();
+  });
+
+  it('lists links to blocked hosts without requesting them', async () => {
+    const links = `<a href="/fine">fine</a><a href="${server.otherOrigin}/elsewhere">x</a>`;
+    server.pages['/links'] = links;
+    await executeCommand({ id: '1', action: 'navigate', url: `${server.origin}/links` }, browser);
+    await browser.getPage().context().addCookies([{ name: 'sid', value: '1', url: server.origin }]);
+    const response = await executeCommand({ id: '2', action: 'check_links' }, browser);
+    expect(response.success && response.data).toMatchObject({ checked: 2, ok: 1, broken: [] });
+    const blocked = response.success ? (response.data as { blocked: unknown[] }).blocked : [];
+    expect(blocked).toMatchObject([{ url: `${server.otherOrigin}/elsewhere`, status: null }]);
+    expect(server.request('/elsewhere')).toBeUndefined();
+    expect(server.request('/fine')?.headers.cookie).toBeUndefined();
+  });
+
+  it("doesn't fetch PDFs from blocked hosts", async () => {
+    server.pages['/doc.pdf'] = { attachment: '%PDF-1.4', type: 'application/pdf' };
+    expect(await browser.fetchPdf(`${server.origin}/doc.pdf`)).not.toBeNull();
+    expect(await browser.fetchPdf(`${server.otherOrigin}/doc.pdf`)).toBeNull();
+    expect(server.requests.filter((r) => r.path === '/doc.pdf').length).toBe(1);
+  });
+
+  it('follows redirects within the allowed hosts', async () => {
+    server.pages['/moved'] = { redirect: `${server.origin}/here` };
+    server.pages['/here'] = '<title>here</title>';
+    const response = await executeCommand(
+      { id: '1', action: 'navigate', url: `${server.origin}/moved` },
+      browser
+    );
+    expect(response.success && response.data).toMatchObject({
+      url: `${server.origin}/here`,
+      title: 'here',
+    });
+  });
+});
+
+describe('download limits', () => {
+  let browser: BrowserManager;
+  let server: Awaited<ReturnType<typeof startServer>>;
+
+  beforeAll(async () => {
+    browser = new BrowserManager();
+    await browser.launch({ id: 'test', action: 'launch', headless: true });
+    server = await startServer();
+    server.pages['/files'] = `
+      <a id="csv" href="/report.csv">csv</a>
+      <a id="exe" href="/setup.exe">exe</a>`;
+    server.pages['/report.csv'] = { attachment: 'a,b\n'.repeat(1000), type: 'text/csv' };
+    server.pages['/setup.exe'] = { attachment: 'MZ', type: 'application/x-msdownload' };
+    await executeCommand({ id: '1', action: 'navigate', url: `${server.origin}/files` }, browser);
+  });
+
+  afterAll(async () => {
+    await browser.close();
+    await server.close();
+  });
+
+  it('refuses downloads of blocked types', async () => {
+    const response = await executeCommand(
+      { id: '1', action: 'download', selector: '#exe', blockedTypes: ['application/*'] },
+      browser
+    );
+    expect(response.success).toBe(false);
+    expect(!response.success && response.data).toEqual({
+      type: 'download_blocked',
+      contentType: 'application/x-msdownload',
+    });
+  });
+
+  it('refuses downloads over the size limit', async () => {
+    const response = await executeCommand(
+      { id: '1', action: 'download', selector: '#csv', maxBytes: 1000 },
+      browser
+    );
+    expect(!response.success && response.data).toEqual({ type: 'download_blocked', size: 4000 });
+  });
+
+  it('keeps downloads within the limits', async () => {
+    const response = await executeCommand(
+      { id: '1', action: 'download', selector: '#csv', maxBytes: 4000, blockedTypes: ['video/*'] },
+      browser
+    );
+    expect(response.success && response.data).toMatchObject({ size: 4000 });
+  });
+});
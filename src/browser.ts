@@ -3,6 +3,8 @@ import {
   firefox,
   webkit,
   devices,
+  request as apiRequest,
+  type APIRequestContext,
   type Browser,
   type BrowserContext,
   type Page,
@@ -16,10 +18,37 @@ import {
 } from 'playwright-core';
 import path from 'node:path';
 import os from 'node:os';
-import { existsSync, mkdirSync, rmSync } from 'node:fs';
-import type { LaunchCommand } from './types.js';
+import { existsSync, mkdirSync, rmSync, writeFileSync } from 'node:fs';
+import type { LaunchCommand, NavigateCommand } from './types.js';
 import { type RefMap, type EnhancedSnapshot, getEnhancedSnapshot, parseRef } from './snapshot.js';
-import { safeHeaderMerge } from './state-utils.js';
+import { SnapshotCache, readSnapshotCacheKey } from './snapshot-cache.js';
+import { SnapshotHistory, type SnapshotHistoryEntry } from './snapshot-diff.js';
+import {
+  safeHeaderMerge,
+  parseStateContent,
+  writeStateFile,
+  isCompressedPayload,
+  decompressState,
+} from './state-utils.js';
+import { migrateState } from './state-migrations.js';
+import { loadHar, findHarEntry, harResponse } from './har.js';
+import { NetworkBudget, type BudgetLimits, type BudgetViolation } from './budget.js';
+import { startLocalProxy, type LocalProxy } from './local-proxy.js';
+import { ProxyPool } from './proxy-pool.js';
+import { chromiumMajorVersion, headlessLaunch, type HeadlessLaunch } from './headless.js';
+import { poolBackend, type PoolLease } from './browser-pool.js';
+import { formatBytes, processMemory, MEMORY_SAMPLE_INTERVAL_MS } from './memory-limit.js';
+import { animationFormat, encodeAnimation, type AnimationFormat } from './gif.js';
+import { isMutatingMethod, READ_ONLY_FORM_SCRIPT } from './read-only.js';
+import {
+  contextsToClose,
+  tabsToClose,
+  JANITOR_INTERVAL_MS,
+  type CleanupEntry,
+  type JanitorRule,
+} from './janitor.js';
+import { extraCaArgs } from './tls.js';
+import { HostRules, type HostViolation } from './host-rules.js';
 
 // Screencast frame data from CDP
 export interface ScreencastFrame {
@@ -57,6 +86,9 @@ interface TrackedRequest {
   headers: Record<string, string>;
   timestamp: number;
   resourceType: string;
+  postData: string | null;
+  /** Response status, once the response arrives */
+  status: number | null;
 }
 
 interface ConsoleMessage {
@@ -70,6 +102,14 @@ interface PageError {
   timestamp: number;
 }
 
+interface DialogEvent {
+  type: string;
+  message: string;
+  response: 'accept' | 'dismiss';
+  url: string;
+  timestamp: number;
+}
+
 /**
  * Manages the Playwright browser lifecycle with multiple tabs/windows
  */
@@ -81,19 +121,54 @@ export class BrowserManager {
   private browserbaseApiKey: string | null = null;
   private browserUseSessionId: string | null = null;
   private browserUseApiKey: string | null = null;
+  private poolLease: PoolLease | null = null;
   private contexts: BrowserContext[] = [];
   private pages: Page[] = [];
   private activePageIndex: number = 0;
   private activeFrame: Frame | null = null;
   private dialogHandler: ((dialog: Dialog) => Promise<void>) | null = null;
+  private dialogResponse: 'accept' | 'dismiss' | null = null;
+  private dialogEvents: DialogEvent[] = [];
   private trackedRequests: TrackedRequest[] = [];
+  private requestTrackedPages = new WeakSet<Page>();
   private routes: Map<string, (route: Route) => Promise<void>> = new Map();
+  private readOnly = false;
+  private readOnlyContexts = new WeakSet<BrowserContext>();
+  private hostRules: HostRules | null = null;
+  private hostRuleContexts = new WeakSet<BrowserContext>();
+  private hostViolation: HostViolation | null = null;
   private consoleMessages: ConsoleMessage[] = [];
   private pageErrors: PageError[] = [];
   private isRecordingHar: boolean = false;
   private refMap: RefMap = {};
   private lastSnapshot: string = '';
+  private snapshotCache = new SnapshotCache();
+  private snapshotHistory = new SnapshotHistory();
   private scopedHeaderRoutes: Map<string, (route: Route) => Promise<void>> = new Map();
+  private sessionBudget: NetworkBudget | null = null;
+  private commandBudget: NetworkBudget | null = null;
+  private budgetContext: BrowserContext | null = null;
+  private blockedTypes: Set<string> = new Set();
+  private blockedCounts: Record<string, number> = {};
+  private blockContext: BrowserContext | null = null;
+  private localProxy: LocalProxy | null = null;
+  private proxyPool: ProxyPool | null = null;
+  private memoryLimit: number | null = null;
+  private memoryTimer: ReturnType<typeof setInterval> | null = null;
+  private memoryExceeded: number | null = null;
+  private relaunchOptions: LaunchCommand | null = null;
+  private restoreState: Awaited<ReturnType<BrowserContext['storageState']>> | undefined;
+  private janitorRules: JanitorRule[] = [];
+  private janitorTimer: ReturnType<typeof setInterval> | null = null;
+  private janitorLog: CleanupEntry[] = [];
+  private lastActivity: WeakMap<Page | BrowserContext, number> = new WeakMap();
+  private openers: WeakMap<Page, Page> = new WeakMap();
+  private pdfPages: WeakMap<Page, { url: string; shownUrl: string }> = new WeakMap();
+  private documentHeaders: WeakMap<Page, Record<string, string>> = new WeakMap();
+  // --ignore-https-errors, for contexts opened after launch
+  private ignoreHTTPSErrors = false;
+  // User agent before --rotate-profile overrode it
+  private defaultUserAgent: string | null = null;
 
   // CDP session for screencast and input injection
   private cdpSession: CDPSession | null = null;
@@ -107,6 +182,16 @@ export class BrowserManager {
   private recordingPage: Page | null = null;
   private recordingOutputPath: string = '';
   private recordingTempDir: string = '';
+
+  // GIF/APNG capture state (record gif)
+  private gifCapture: {
+    path: string;
+    format: AnimationFormat;
+    delayMs: number;
+    remaining: number;
+    frames: Buffer[];
+  } | null = null;
+
   private launchWarnings: string[] = [];
 
   /**
@@ -126,19 +211,35 @@ export class BrowserManager {
   }
 
   /**
-   * Get enhanced snapshot with refs and cache the ref map
+   * Get enhanced snapshot with refs and cache the ref map. While the page's
+   * DOM generation is unchanged, the last snapshot taken with the same
+   * options is returned (cached: true) unless cache is false. Each snapshot
+   * is recorded under a new id, which a later snapshot can diff against.
    */
   async getSnapshot(options?: {
     interactive?: boolean;
     maxDepth?: number;
     compact?: boolean;
     selector?: string;
-  }): Promise<EnhancedSnapshot> {
+    cache?: boolean;
+  }): Promise<EnhancedSnapshot & { cached: boolean; id: string }> {
     const page = this.getPage();
-    const snapshot = await getEnhancedSnapshot(page, options);
+    const { cache, ...snapshotOptions } = options ?? {};
+    const key = cache === false ? null : await readSnapshotCacheKey(page, snapshotOptions);
+    const cached = this.snapshotCache.get(key);
+    const snapshot = cached ?? (await getEnhancedSnapshot(page, snapshotOptions));
+    this.snapshotCache.set(key, snapshot);
     this.refMap = snapshot.refs;
     this.lastSnapshot = snapshot.tree;
-    return snapshot;
+    const id = this.snapshotHistory.add(snapshot.tree, snapshotOptions);
+    return { ...snapshot, cached: cached !== null, id };
+  }
+
+  /**
+   * A recent snapshot by id, undefined once it has aged out of the history
+   */
+  getSnapshotById(id: string): SnapshotHistoryEntry | undefined {
+    return this.snapshotHistory.get(id);
   }
 
   /**
@@ -266,6 +367,7 @@ export class BrowserManager {
       page.removeListener('dialog', this.dialogHandler);
     }
 
+    this.dialogResponse = response;
     this.dialogHandler = async (dialog: Dialog) => {
       if (response === 'accept') {
         await dialog.accept(promptText);
@@ -286,6 +388,21 @@ export class BrowserManager {
       page.removeListener('dialog', this.dialogHandler);
       this.dialogHandler = null;
     }
+    this.dialogResponse = null;
+  }
+
+  /**
+   * Get dialogs shown since the last clear
+   */
+  getDialogEvents(): DialogEvent[] {
+    return this.dialogEvents;
+  }
+
+  /**
+   * Clear recorded dialogs
+   */
+  clearDialogEvents(): void {
+    this.dialogEvents = [];
   }
 
   /**
@@ -293,14 +410,25 @@ export class BrowserManager {
    */
   startRequestTracking(): void {
     const page = this.getPage();
+    if (this.requestTrackedPages.has(page)) return;
+    this.requestTrackedPages.add(page);
     page.on('request', (request: Request) => {
-      this.trackedRequests.push({
+      const tracked: TrackedRequest = {
         url: request.url(),
         method: request.method(),
         headers: request.headers(),
         timestamp: Date.now(),
         resourceType: request.resourceType(),
-      });
+        postData: request.postData(),
+        status: null,
+      };
+      this.trackedRequests.push(tracked);
+      request
+        .response()
+        .then((response) => {
+          tracked.status = response?.status() ?? null;
+        })
+        .catch(() => {});
     });
   }
 
@@ -349,12 +477,334 @@ export class BrowserManager {
           headers: options.response.headers,
         });
       } else {
-        await route.continue();
+        await route.fallback();
+      }
+    };
+
+    this.routes.set(url, handler);
+    await page.route(url, handler);
+  }
+
+  /**
+   * Serve requests matching url from a recorded HAR. Requests the HAR has no
+   * entry for fall through to the network (or other routes), or are aborted.
+   */
+  async addHarRoute(
+    harPath: string,
+    options: { url?: string; notFound?: 'fallback' | 'abort' }
+  ): Promise<{ url: string; entries: number }> {
+    const page = this.getPage();
+    const entries = loadHar(harPath);
+    const url = options.url ?? '**/*';
+
+    const handler = async (route: Route) => {
+      const request = route.request();
+      const entry = findHarEntry(entries, request.method(), request.url(), request.postData());
+      if (entry) {
+        await route.fulfill(harResponse(entry));
+      } else if (options.notFound === 'abort') {
+        await route.abort();
+      } else {
+        await route.fallback();
       }
     };
 
+    const existing = this.routes.get(url);
+    if (existing) {
+      await page.unroute(url, existing);
+    }
     this.routes.set(url, handler);
     await page.route(url, handler);
+    return { url, entries: entries.length };
+  }
+
+  /**
+   * Sample the memory of the browser and all its processes every few seconds,
+   * remembering when it goes over --max-memory
+   */
+  private startMemoryMonitor(limit: number): void {
+    if (process.platform === 'win32') {
+      this.launchWarnings.push('--max-memory is not supported on Windows and was ignored');
+      return;
+    }
+    this.memoryLimit = limit;
+    this.memoryTimer = setInterval(async () => {
+      if (!this.browser || this.memoryExceeded !== null) return;
+      try {
+        const cdp = await this.browser.newBrowserCDPSession();
+        const info = (await cdp.send('SystemInfo.getProcessInfo')) as {
+          processInfo: { id: number }[];
+        };
+        await cdp.detach().catch(() => {});
+        const used = await processMemory(info.processInfo.map((p) => p.id));
+        if (used !== null && used > limit) this.memoryExceeded = used;
+      } catch {
+        // The browser is closing or being restarted
+      }
+    }, MEMORY_SAMPLE_INTERVAL_MS);
+    this.memoryTimer.unref();
+  }
+
+  /**
+   * Turn on --read-only for the rest of the session
+   */
+  setReadOnly(): void {
+    this.readOnly = true;
+  }
+
+  isReadOnly(): boolean {
+    return this.readOnly;
+  }
+
+  /**
+   * Guard every context against changes (--read-only): requests that aren't
+   * GET, HEAD or OPTIONS are aborted and reported as warnings, and form
+   * submissions are cancelled. Called between commands, so contexts opened
+   * since the last one are covered before anything runs in them.
+   */
+  async enforceReadOnly(): Promise<void> {
+    if (!this.readOnly) return;
+    for (const context of this.contexts) {
+      if (this.readOnlyContexts.has(context)) continue;
+      this.readOnlyContexts.add(context);
+      await context.route('**/*', async (route: Route) => {
+        const request = route.request();
+        if (isMutatingMethod(request.method())) {
+          this.launchWarnings.push(`Read-only: blocked ${request.method()} ${request.url()}`);
+          await route.abort('blockedbyclient');
+        } else {
+          await route.fallback();
+        }
+      });
+      await context.addInitScript(READ_ONLY_FORM_SCRIPT);
+      for (const page of context.pages()) {
+        await page.evaluate(READ_ONLY_FORM_SCRIPT).catch(() => {});
+      }
+    }
+  }
+
+  /**
+   * Restart a browser that went over --max-memory, keeping its cookies,
+   * storage and open tabs. Called between commands so nothing is interrupted.
+   */
+  async enforceMemoryLimit(): Promise<void> {
+    const used = this.memoryExceeded;
+    const limit = this.memoryLimit;
+    const options = this.relaunchOptions;
+    if (used === null || limit === null || !options) return;
+
+    const state = await this.contexts[0]?.storageState().catch(() => undefined);
+    const urls = this.pages.filter((page) => !page.isClosed()).map((page) => page.url());
+    const active = this.activePageIndex;
+
+    await this.close();
+    this.restoreState = state;
+    await this.launch(options);
+    for (const [index, url] of urls.entries()) {
+      if (index > 0) await this.newTab();
+      if (url !== 'about:blank') {
+        await this.getPage()
+          .goto(url)
+          .catch(() => {});
+      }
+    }
+    if (this.pages.length > 0) await this.switchTo(Math.min(active, this.pages.length - 1));
+
+    const warning =
+      `Browser was using ${formatBytes(used)}, over --max-memory ${formatBytes(limit)}; ` +
+      `restarted it and restored ${urls.length} tab(s) with cookies and storage`;
+    this.launchWarnings.push(warning);
+    console.error(`[WARN] ${warning}`);
+  }
+
+  /**
+   * Sweep for stray tabs, popups and contexts every so often (--janitor)
+   */
+  private startJanitor(rules: JanitorRule[]): void {
+    this.janitorRules = rules;
+    this.janitorTimer = setInterval(() => {
+      this.sweepGarbage().catch(() => {});
+    }, JANITOR_INTERVAL_MS);
+    this.janitorTimer.unref();
+  }
+
+  /**
+   * Close the tabs, popups and contexts the rules call garbage and log them.
+   * The active tab and the session's main context are never closed.
+   */
+  async sweepGarbage(rules: JanitorRule[] = this.janitorRules): Promise<CleanupEntry[]> {
+    const now = Date.now();
+    const idleMs = (target: Page | BrowserContext) => {
+      if (!this.lastActivity.has(target)) this.lastActivity.set(target, now);
+      return now - this.lastActivity.get(target)!;
+    };
+    const active = this.pages[this.activePageIndex];
+    if (active) this.lastActivity.set(active, now);
+
+    const pages = this.contexts.flatMap((context) => context.pages());
+    for (const page of pages) {
+      if (!this.openers.has(page)) {
+        const opener = await page.opener().catch(() => null);
+        if (opener) this.openers.set(page, opener);
+      }
+    }
+    const tabs = pages.map((page) => ({
+      url: page.url(),
+      active: page === active,
+      tracked: this.pages.includes(page),
+      openerClosed: this.openers.get(page)?.isClosed() ?? false,
+      idleMs: idleMs(page),
+    }));
+    const contexts = this.contexts.map((context, index) => {
+      const open = context.pages().length;
+      // Idle time of a context counts from when its last page closed
+      if (open > 0) this.lastActivity.delete(context);
+      return {
+        pages: open,
+        primary: index === 0 || context === this.recordingContext,
+        idleMs: open > 0 ? 0 : idleMs(context),
+      };
+    });
+
+    const cleaned: CleanupEntry[] = [];
+    const time = new Date(now).toISOString();
+    for (const { index, kind, reason } of tabsToClose(tabs, rules)) {
+      const page = pages[index];
+      await page.close().catch(() => {});
+      cleaned.push({ time, kind, url: tabs[index].url, reason });
+    }
+    const emptyContexts = contextsToClose(contexts, rules).map((index) => this.contexts[index]);
+    for (const context of emptyContexts) {
+      await context.close().catch(() => {});
+      this.contexts.splice(this.contexts.indexOf(context), 1);
+      cleaned.push({ time, kind: 'context', reason: 'no open pages' });
+    }
+    // Closing tabs before the active one shifts its index
+    if (active && this.pages.includes(active)) {
+      this.activePageIndex = this.pages.indexOf(active);
+    }
+
+    this.janitorLog.push(...cleaned);
+    this.janitorLog.splice(0, Math.max(0, this.janitorLog.length - 200));
+    for (const entry of cleaned) {
+      console.error(
+        `[janitor] closed ${entry.kind}${entry.url ? ` ${entry.url}` : ''}: ${entry.reason}`
+      );
+    }
+    return cleaned;
+  }
+
+  /**
+   * The janitor's rules and everything it has closed
+   */
+  getJanitorLog(): { rules: JanitorRule[]; cleanup: CleanupEntry[] } {
+    return { rules: this.janitorRules, cleanup: [...this.janitorLog] };
+  }
+
+  clearJanitorLog(): void {
+    this.janitorLog = [];
+  }
+
+  /**
+   * Switch to the next healthy proxy of the pool before a navigation when
+   * rotating per navigation. Returns the proxy the navigation will use.
+   */
+  rotateProxyForNavigation(): string | null {
+    if (!this.proxyPool) return null;
+    if (this.proxyPool.rotate === 'per-navigation') {
+      this.proxyPool.next();
+      // Tunnels kept alive by the browser would stay on the previous proxy
+      this.localProxy?.dropTunnels();
+    }
+    return this.proxyPool.current().server;
+  }
+
+  /**
+   * Start counting network usage against a budget: for the next command, or
+   * for the rest of the session
+   */
+  async beginNetworkBudget(limits: BudgetLimits, scope: 'command' | 'session'): Promise<void> {
+    if (scope === 'session') {
+      if (this.sessionBudget) {
+        this.sessionBudget.limits = limits;
+      } else {
+        this.sessionBudget = new NetworkBudget(limits);
+      }
+    } else {
+      this.commandBudget = new NetworkBudget(limits);
+    }
+
+    const context = this.contexts[0];
+    if (!context || context === this.budgetContext) return;
+    this.budgetContext = context;
+
+    const activeBudgets = () =>
+      [this.sessionBudget, this.commandBudget].filter((b): b is NetworkBudget => b !== null);
+    await context.route('**/*', async (route: Route) => {
+      // Count against every active budget, then refuse if any is spent
+      const admitted = activeBudgets().map((budget) => budget.admit());
+      if (admitted.includes(false)) {
+        await route.abort('blockedbyclient');
+      } else {
+        await route.fallback();
+      }
+    });
+    context.on('requestfinished', async (request: Request) => {
+      const budgets = activeBudgets();
+      if (budgets.length === 0) return;
+      try {
+        const sizes = await request.sizes();
+        for (const budget of budgets) {
+          budget.addBytes(sizes.responseHeadersSize + sizes.responseBodySize);
+        }
+      } catch {
+        // Page or context closed before the sizes were read
+      }
+    });
+  }
+
+  /**
+   * Stop the command budget and return the budget that ran out, if any
+   */
+  endNetworkBudget(): { budget: NetworkBudget; violation: BudgetViolation } | null {
+    const budgets = [this.commandBudget, this.sessionBudget];
+    this.commandBudget = null;
+    for (const budget of budgets) {
+      const violation = budget?.exceeded();
+      if (budget && violation) return { budget, violation };
+    }
+    return null;
+  }
+
+  /**
+   * Abort requests for these resource types until endResourceBlocking
+   */
+  async beginResourceBlocking(types: string[]): Promise<void> {
+    this.blockedTypes = new Set(types);
+    this.blockedCounts = {};
+
+    const context = this.contexts[0];
+    if (!context || context === this.blockContext) return;
+    this.blockContext = context;
+    await context.route('**/*', async (route: Route) => {
+      const type = route.request().resourceType();
+      if (this.blockedTypes.has(type)) {
+        this.blockedCounts[type] = (this.blockedCounts[type] ?? 0) + 1;
+        await route.abort('blockedbyclient');
+      } else {
+        await route.fallback();
+      }
+    });
+  }
+
+  /**
+   * Stop blocking and return how many requests of each type were aborted
+   */
+  endResourceBlocking(): Record<string, number> {
+    const counts = this.blockedCounts;
+    this.blockedTypes = new Set();
+    this.blockedCounts = {};
+    return counts;
   }
 
   /**
@@ -542,7 +992,7 @@ export class BrowserManager {
     // Create handler that adds headers to matching requests
     const handler = async (route: Route) => {
       const requestHeaders = route.request().headers();
-      await route.continue({
+      await route.fallback({
         headers: safeHeaderMerge(requestHeaders, headers),
       });
     };
@@ -552,6 +1002,121 @@ export class BrowserManager {
     await page.route(urlPattern, handler);
   }
 
+  /**
+   * Add headers to the main frame's navigation requests until the returned
+   * function is called, for headers that only go with one navigation
+   */
+  async setNavigationHeaders(headers: Record<string, string>): Promise<() => Promise<void>> {
+    const page = this.getPage();
+    const handler = async (route: Route) => {
+      const request = route.request();
+      if (request.isNavigationRequest() && request.frame() === page.mainFrame()) {
+        await route.fallback({ headers: safeHeaderMerge(request.headers(), headers) });
+      } else {
+        await route.fallback();
+      }
+    };
+    await page.route('**/*', handler);
+    return () => page.unroute('**/*', handler);
+  }
+
+  /**
+   * Present the page as the next identity of a rotation profile
+   * (--rotate-profile). Chromium overrides the user agent and Accept-Language
+   * through CDP, so navigator.userAgent changes too; other browsers only get
+   * the request headers.
+   */
+  async applyRotation(rotation: NonNullable<NavigateCommand['rotation']>): Promise<void> {
+    const page = this.getPage();
+    if (rotation.viewport) {
+      await page.setViewportSize(rotation.viewport);
+    }
+    if (!rotation.userAgent && !rotation.acceptLanguage) return;
+    // Entries without a user agent go back to the browser's own
+    this.defaultUserAgent ??= await page.evaluate(() => navigator.userAgent);
+    try {
+      const cdp = await this.getCDPSession();
+      await cdp.send('Network.setUserAgentOverride', {
+        userAgent: rotation.userAgent ?? this.defaultUserAgent,
+        ...(rotation.acceptLanguage && { acceptLanguage: rotation.acceptLanguage }),
+      });
+    } catch {
+      await page.setExtraHTTPHeaders({
+        ...(rotation.userAgent && { 'User-Agent': rotation.userAgent }),
+        ...(rotation.acceptLanguage && { 'Accept-Language': rotation.acceptLanguage }),
+      });
+    }
+  }
+
+  /**
+   * Apply --allow-hosts / --block-hosts for the rest of the session
+   */
+  setHostRules(rules: HostRules): void {
+    this.hostRules = rules.isEmpty() ? null : rules;
+  }
+
+  /**
+   * The main-frame navigation the host rules refused since the last call
+   */
+  takeHostViolation(): HostViolation | null {
+    const violation = this.hostViolation;
+    this.hostViolation = null;
+    return violation;
+  }
+
+  /**
+   * The host rules' verdict on a URL the daemon requests itself, outside the
+   * browser's routes
+   */
+  checkHost(url: string): HostViolation | null {
+    return this.hostRules?.check(url) ?? null;
+  }
+
+  /**
+   * Abort requests to hosts outside --allow-hosts or in --block-hosts,
+   * reporting each as a warning. Redirect hops aren't routed, so documents are
+   * fetched without following redirects and refused if the Location is
+   * blocked. Called between commands, ahead of the per-command routes, so this
+   * handler runs after them and fetches with their changes applied.
+   */
+  async enforceHostRules(): Promise<void> {
+    const rules = this.hostRules;
+    if (!rules) return;
+    for (const context of this.contexts) {
+      if (this.hostRuleContexts.has(context)) continue;
+      this.hostRuleContexts.add(context);
+      await context.route('**/*', async (route: Route) => {
+        const request = route.request();
+        let violation = rules.check(request.url());
+        if (!violation && request.resourceType() === 'document') {
+          const response = await route.fetch({ maxRedirects: 0 }).catch(() => null);
+          if (!response) {
+            await route.fallback();
+            return;
+          }
+          const location = response.headers()['location'];
+          if (location && response.status() >= 300 && response.status() < 400) {
+            violation = rules.check(new URL(location, request.url()).href);
+          }
+          if (!violation) {
+            await route.fulfill({ response });
+            return;
+          }
+        }
+        if (violation) {
+          this.launchWarnings.push(`Host rules: blocked ${request.url()} (${violation.reason})`);
+          // Service worker requests have no frame, but they never navigate
+          if (request.isNavigationRequest() && !request.frame().parentFrame()) {
+            this.hostViolation = violation;
+          }
+          await route.abort('blockedbyclient');
+        } else {
+          await route.fallback();
+        }
+      });
+    }
+  }
+
   /**
    * Clear scoped headers for an origin (or all if no origin specified)
    */
@@ -610,7 +1175,8 @@ export class BrowserManager {
   async saveStorageState(path: string): Promise<void> {
     const context = this.contexts[0];
     if (context) {
-      await context.storageState({ path });
+      // Plain JSON, as Playwright writes it, but atomic and with backups
+      writeStateFile(path, await context.storageState(), { encrypt: false });
     }
   }
 
@@ -834,6 +1400,41 @@ export class BrowserManager {
     }
   }
 
+  /**
+   * Acquire a browser from a Selenium Grid or CDP pool and connect to it.
+   * The lease is released when the browser is closed.
+   */
+  private async connectToPool(options: LaunchCommand): Promise<void> {
+    const pool = options.pool!;
+    const lease = await poolBackend(pool.url).acquire(pool.capabilities ?? {}, {
+      headless: options.headless,
+      args: options.args,
+    });
+
+    try {
+      const browser = await chromium.connectOverCDP(lease.endpoint).catch((error) => {
+        throw new Error(
+          `Failed to connect to pooled browser: ${error instanceof Error ? error.message : String(error)}`
+        );
+      });
+      const context = browser.contexts()[0] ?? (await browser.newContext());
+      const page = context.pages()[0] ?? (await context.newPage());
+
+      this.poolLease = lease;
+      this.browser = browser;
+      this.contexts.push(context);
+      this.pages.push(page);
+      this.activePageIndex = 0;
+      this.setupPageTracking(page);
+      this.setupContextTracking(context);
+    } catch (error) {
+      await lease.release().catch((releaseError) => {
+        console.error('Failed to release pooled browser during cleanup:', releaseError);
+      });
+      throw error;
+    }
+  }
+
   /**
    * Launch the browser with the specified options
    * If already launched, this is a no-op (browser stays open)
@@ -852,6 +1453,10 @@ export class BrowserManager {
       throw new Error('Profile cannot be used with CDP connection');
     }
 
+    if (options.allowHosts || options.blockHosts) {
+      this.setHostRules(new HostRules(options.allowHosts ?? [], options.blockHosts ?? []));
+    }
+
     if (this.isLaunched()) {
       const needsRelaunch =
         (!cdpEndpoint && this.cdpEndpoint !== null) ||
@@ -868,6 +1473,14 @@ export class BrowserManager {
       return;
     }
 
+    if (options.pool) {
+      if (options.provider) {
+        throw new Error('--pool cannot be combined with a provider');
+      }
+      await this.connectToPool(options);
+      return;
+    }
+
     // Cloud browser providers require explicit opt-in via -p flag or AGENT_BROWSER_PROVIDER env var
     // -p flag takes precedence over env var
     const provider = options.provider ?? process.env.AGENT_BROWSER_PROVIDER;
@@ -880,14 +1493,59 @@ export class BrowserManager {
       return;
     }
 
+    // Options as given, to launch the same way after a --max-memory restart
+    const requested = options;
+
+    // Connections go through a local proxy that resolves host names itself
+    // or forwards to the current proxy of the pool
+    if (options.dns || options.proxyPool) {
+      if (options.proxy) {
+        throw new Error(
+          '--ip-family, --dns-over-https and --proxy-pool cannot be combined with --proxy'
+        );
+      }
+      if (options.dns && options.proxyPool) {
+        throw new Error('--ip-family and --dns-over-https cannot be combined with --proxy-pool');
+      }
+      if (options.proxyPool) {
+        this.proxyPool = new ProxyPool(options.proxyPool.proxies, options.proxyPool.rotate);
+      }
+      this.localProxy = await startLocalProxy({
+        dns: options.dns,
+        pool: this.proxyPool ?? undefined,
+      });
+      options = { ...options, proxy: { server: this.localProxy.url } };
+    }
+
+    this.ignoreHTTPSErrors = options.ignoreHTTPSErrors ?? false;
     const browserType = options.browser ?? 'chromium';
     if (hasExtensions && browserType !== 'chromium') {
       throw new Error('Extensions are only supported in Chromium');
     }
 
+    if (options.maxMemory && (browserType !== 'chromium' || hasExtensions || hasProfile)) {
+      throw new Error('--max-memory only applies to Chromium without --extension or --profile');
+    }
+
     const launcher =
       browserType === 'firefox' ? firefox : browserType === 'webkit' ? webkit : chromium;
     const viewport = options.viewport ?? { width: 1280, height: 720 };
+    if (options.extraCaCerts && browserType !== 'chromium') {
+      throw new Error('--extra-ca only applies to Chromium');
+    }
+    const caArgs = options.extraCaCerts ? extraCaArgs(options.extraCaCerts) : [];
+
+    // Pick the headless implementation the installed Chromium supports
+    let headless: HeadlessLaunch = { args: [] };
+    if (options.headlessMode && options.headless !== false && !hasExtensions) {
+      if (browserType !== 'chromium') {
+        throw new Error('--headless-mode only applies to Chromium');
+      }
+      const version = chromiumMajorVersion(options.executablePath ?? chromium.executablePath());
+      headless = headlessLaunch(options.headlessMode, version, !!options.executablePath);
+      if (headless.warning) this.launchWarnings.push(headless.warning);
+    }
+    const launchArgs = [...(options.args ?? []), ...headless.args, ...caArgs];
 
     let context: BrowserContext;
     if (hasExtensions) {
@@ -896,7 +1554,7 @@ export class BrowserManager {
       const session = process.env.AGENT_BROWSER_SESSION || 'default';
       // Combine extension args with custom args
       const extArgs = [`--disable-extensions-except=${extPaths}`, `--load-extension=${extPaths}`];
-      const allArgs = options.args ? [...extArgs, ...options.args] : extArgs;
+      const allArgs = [...extArgs, ...(options.args ?? []), ...caArgs];
       context = await launcher.launchPersistentContext(
         path.join(os.tmpdir(), `agent-browser-ext-${session}`),
         {
@@ -906,6 +1564,7 @@ export class BrowserManager {
           viewport,
           extraHTTPHeaders: options.headers,
           userAgent: options.userAgent,
+          ignoreHTTPSErrors: options.ignoreHTTPSErrors,
           ...(options.proxy && { proxy: options.proxy }),
         }
       );
@@ -917,8 +1576,12 @@ export class BrowserManager {
       context = await launcher.launchPersistentContext(profilePath, {
         headless: options.headless ?? true,
         executablePath: options.executablePath,
+        ...(headless.channel && { channel: headless.channel }),
+        ...(headless.args.length + caArgs.length > 0 && { args: [...headless.args, ...caArgs] }),
         viewport,
         extraHTTPHeaders: options.headers,
+        ignoreHTTPSErrors: options.ignoreHTTPSErrors,
+        ...(options.proxy && { proxy: options.proxy }),
       });
       this.isPersistentContext = true;
     } else {
@@ -926,7 +1589,8 @@ export class BrowserManager {
       this.browser = await launcher.launch({
         headless: options.headless ?? true,
         executablePath: options.executablePath,
-        args: options.args,
+        ...(headless.channel && { channel: headless.channel }),
+        args: launchArgs.length > 0 ? launchArgs : undefined,
       });
       this.cdpEndpoint = null;
 
@@ -956,7 +1620,7 @@ export class BrowserManager {
           const fs = await import('fs');
           if (fs.existsSync(options.autoStateFilePath)) {
             const content = fs.readFileSync(options.autoStateFilePath, 'utf8');
-            const parsed = JSON.parse(content);
+            const parsed = parseStateContent(content, options.autoStateFilePath);
 
             // Check if file is encrypted
             if (isEncryptedPayload(parsed)) {
@@ -986,15 +1650,27 @@ export class BrowserManager {
                 console.error(`[WARN] ${warning}`);
               }
             } else {
-              // Plain text file - use file path directly
-              storageState = options.autoStateFilePath;
+              // Plain text file, without its checksum
+              storageState = parsed as typeof storageState;
               if (process.env.AGENT_BROWSER_DEBUG === '1') {
                 console.error(`[DEBUG] Auto-loading session state: ${options.autoStateFilePath}`);
               }
             }
+
+            // Large auto-saved state is stored gzipped
+            if (isCompressedPayload(storageState)) {
+              storageState = decompressState(storageState) as typeof storageState;
+            }
+            // Bring state saved by older releases up to the current format
+            if (storageState && typeof storageState === 'object') {
+              storageState = migrateState(storageState).state;
+            }
           }
         } catch (err) {
-          // Invalid or corrupted state file - fall back to fresh browser
+          // Invalid, corrupted or too new state file - fall back to fresh browser
+          const warning = `${err instanceof Error ? err.message : String(err)}. Starting fresh.`;
+          this.launchWarnings.push(warning);
+          console.error(`[WARN] ${warning}`);
           if (process.env.AGENT_BROWSER_DEBUG === '1') {
             console.error(`[DEBUG] Failed to load state file, starting fresh:`, err);
           }
@@ -1008,9 +1684,16 @@ export class BrowserManager {
         viewport,
         extraHTTPHeaders: options.headers,
         userAgent: options.userAgent,
-        storageState: storageState,
+        ignoreHTTPSErrors: options.ignoreHTTPSErrors,
+        storageState: this.restoreState ?? storageState,
         ...(options.proxy && { proxy: options.proxy }),
       });
+      this.restoreState = undefined;
+
+      if (options.maxMemory) {
+        this.relaunchOptions = requested;
+        this.startMemoryMonitor(options.maxMemory);
+      }
     }
 
     context.setDefaultTimeout(60000);
@@ -1020,6 +1703,10 @@ export class BrowserManager {
     this.pages.push(page);
     this.activePageIndex = 0;
     this.setupPageTracking(page);
+
+    if (options.janitor?.length) {
+      this.startJanitor(options.janitor);
+    }
   }
 
   /**
@@ -1115,6 +1802,34 @@ export class BrowserManager {
       });
     });
 
+    page.on('dialog', async (dialog) => {
+      const handled = !!this.dialogHandler && page.listeners('dialog').includes(this.dialogHandler);
+      this.dialogEvents.push({
+        type: dialog.type(),
+        message: dialog.message(),
+        response: handled ? (this.dialogResponse ?? 'dismiss') : 'dismiss',
+        url: page.url(),
+        timestamp: Date.now(),
+      });
+      // Listening turns off Playwright's auto-dismiss, so keep that default
+      // unless the dialog command installed a handler on this page
+      if (!handled) {
+        await dialog.dismiss().catch(() => {});
+      }
+    });
+
+    // Response headers of the page's document, for audit security
+    page.on('response', (response) => {
+      if (response.request().isNavigationRequest() && response.frame() === page.mainFrame()) {
+        this.documentHeaders.set(page, response.headers());
+      }
+    });
+
+    this.lastActivity.set(page, Date.now());
+    page.on('framenavigated', (frame) => {
+      if (frame === page.mainFrame()) this.lastActivity.set(page, Date.now());
+    });
+
     page.on('close', () => {
       const index = this.pages.indexOf(page);
       if (index !== -1) {
@@ -1131,11 +1846,25 @@ export class BrowserManager {
    */
   private setupContextTracking(context: BrowserContext): void {
     context.on('page', (page) => {
+      // A popup caught by `click --expect-popup` may be registered already
+      if (this.pages.includes(page)) return;
       this.pages.push(page);
       this.setupPageTracking(page);
     });
   }
 
+  /**
+   * Register a page the browser opened (a popup) as a tab and switch to it
+   */
+  async adoptPage(page: Page): Promise<{ index: number; total: number }> {
+    if (!this.pages.includes(page)) {
+      this.pages.push(page);
+      this.setupPageTracking(page);
+    }
+    await this.switchTo(this.pages.indexOf(page));
+    return { index: this.activePageIndex, total: this.pages.length };
+  }
+
   /**
    * Create a new tab in the current context
    */
@@ -1171,6 +1900,7 @@ export class BrowserManager {
 
     const context = await this.browser.newContext({
       viewport: viewport ?? { width: 1280, height: 720 },
+      ignoreHTTPSErrors: this.ignoreHTTPSErrors,
     });
     context.setDefaultTimeout(60000);
     this.contexts.push(context);
@@ -1542,6 +2272,7 @@ export class BrowserManager {
         size: viewport,
       },
       storageState,
+      ignoreHTTPSErrors: this.ignoreHTTPSErrors,
     });
     this.recordingContext.setDefaultTimeout(10000);
 
@@ -1668,6 +2399,115 @@ export class BrowserManager {
     return { previousPath, stopped };
   }
 
+  /**
+   * Start capturing an animated GIF/APNG: a frame now and one after each of
+   * the next `commands` commands, written to `outputPath` after the last
+   */
+  async startGifCapture(outputPath: string, commands: number, delayMs: number): Promise<void> {
+    if (this.gifCapture) {
+      throw new Error(
+        `A GIF capture to ${this.gifCapture.path} is already running. Run 'record gif stop' first.`
+      );
+    }
+    const format = animationFormat(outputPath);
+    if (!format) {
+      throw new Error('GIF output must end in .gif, .png or .apng');
+    }
+    this.gifCapture = { path: outputPath, format, delayMs, remaining: commands, frames: [] };
+    await this.captureGifFrame();
+  }
+
+  isCapturingGif(): boolean {
+    return this.gifCapture !== null;
+  }
+
+  private async captureGifFrame(): Promise<void> {
+    if (!this.gifCapture) return;
+    try {
+      this.gifCapture.frames.push(await this.getPage().screenshot({ type: 'png' }));
+    } catch {
+      // No page to show (closed, or the browser is relaunching): skip the frame
+    }
+  }
+
+  /**
+   * Take the frame for a command that just ran. Returns the saved animation
+   * once the capture has seen all its commands.
+   */
+  async gifCommandDone(): Promise<{ path: string; frames: number } | null> {
+    if (!this.gifCapture) return null;
+    await this.captureGifFrame();
+    this.gifCapture.remaining--;
+    return this.gifCapture.remaining > 0 ? null : this.finishGifCapture();
+  }
+
+  /** Write the frames captured so far and end the capture */
+  async finishGifCapture(): Promise<{ path: string; frames: number } | null> {
+    const capture = this.gifCapture;
+    if (!capture) return null;
+    this.gifCapture = null;
+    const outputPath = path.resolve(capture.path);
+    mkdirSync(path.dirname(outputPath), { recursive: true });
+    writeFileSync(outputPath, encodeAnimation(capture.frames, capture.format, capture.delayMs));
+    return { path: capture.path, frames: capture.frames.length };
+  }
+
+  /**
+   * Remember that opening `url` in a page turned out to be a PDF. Headless
+   * Chromium doesn't display it, so the page stays on what it showed before.
+   */
+  notePdf(page: Page, url: string): void {
+    this.pdfPages.set(page, { url, shownUrl: page.url() });
+  }
+
+  /**
+   * The PDF a page is showing: one opened in headless mode while the page
+   * hasn't moved on since, or one in the built-in viewer of a headed browser
+   */
+  async pdfUrl(page: Page): Promise<string | null> {
+    const noted = this.pdfPages.get(page);
+    if (noted && page.url() === noted.shownUrl) return noted.url;
+    const contentType = await page.evaluate(() => document.contentType).catch(() => null);
+    return contentType === 'application/pdf' ? page.url() : null;
+  }
+
+  /**
+   * Response headers (lowercased) of the document the page last navigated
+   * to, or null when it hasn't loaded one
+   */
+  getDocumentHeaders(page: Page): Record<string, string> | null {
+    return this.documentHeaders.get(page) ?? null;
+  }
+
+  /**
+   * A request context without the session's cookies but with its TLS
+   * settings, for requests made about the page rather than as it (check-links).
+   * The caller disposes it.
+   */
+  async newRequestContext(): Promise<APIRequestContext> {
+    return apiRequest.newContext({ ignoreHTTPSErrors: this.ignoreHTTPSErrors });
+  }
+
+  /**
+   * Fetch a URL with the session's cookies and return it if it's a PDF
+   * (by content type or the %PDF header). The request API bypasses the
+   * browser's routes, so the host rules are checked here.
+   */
+  async fetchPdf(url: string): Promise<Buffer | null> {
+    if (this.checkHost(url)) return null;
+    try {
+      const response = await this.getPage().context().request.get(url);
+      if (!response.ok()) return null;
+      const body = await response.body();
+      const type = response.headers()['content-type'] ?? '';
+      return type.includes('application/pdf') || body.subarray(0, 5).toString() === '%PDF-'
+        ? body
+        : null;
+    } catch {
+      return null;
+    }
+  }
+
   /**
    * Close the browser and clean up
    */
@@ -1676,6 +2516,10 @@ export class BrowserManager {
     if (this.recordingContext) {
       await this.stopRecording();
     }
+    // Save a GIF capture with the frames it has
+    if (this.gifCapture) {
+      await this.finishGifCapture().catch(() => {});
+    }
 
     // Stop screencast if active
     if (this.screencastActive) {
@@ -1702,6 +2546,15 @@ export class BrowserManager {
         }
       );
       this.browser = null;
+    } else if (this.poolLease) {
+      // Disconnecting returns a CDP pool's browser; Selenium Grid needs the session deleted
+      if (this.browser) {
+        await this.browser.close().catch(() => {});
+        this.browser = null;
+      }
+      await this.poolLease.release().catch((error) => {
+        console.error('Failed to release pooled browser:', error);
+      });
     } else if (this.cdpEndpoint !== null) {
       // CDP: only disconnect, don't close external app's pages
       if (this.browser) {
@@ -1722,17 +2575,45 @@ export class BrowserManager {
       }
     }
 
+    if (this.localProxy) {
+      await this.localProxy.close();
+      this.localProxy = null;
+    }
+    this.proxyPool = null;
+
+    if (this.memoryTimer) {
+      clearInterval(this.memoryTimer);
+      this.memoryTimer = null;
+    }
+    this.memoryLimit = null;
+    this.memoryExceeded = null;
+    this.relaunchOptions = null;
+
+    if (this.janitorTimer) {
+      clearInterval(this.janitorTimer);
+      this.janitorTimer = null;
+    }
+    this.janitorRules = [];
+    this.janitorLog = [];
+
     this.pages = [];
     this.contexts = [];
+    this.budgetContext = null;
+    this.blockContext = null;
+    this.sessionBudget = null;
+    this.commandBudget = null;
     this.cdpEndpoint = null;
     this.browserbaseSessionId = null;
     this.browserbaseApiKey = null;
     this.browserUseSessionId = null;
     this.browserUseApiKey = null;
+    this.poolLease = null;
     this.isPersistentContext = false;
     this.activePageIndex = 0;
     this.refMap = {};
     this.lastSnapshot = '';
+    this.snapshotCache.clear();
+    this.snapshotHistory.clear();
     this.frameCallback = null;
   }
 }
@@ -0,0 +1,30 @@
+import { describe, it, expect } from 'vitest';
+import { NetworkBudget, budgetErrorResponse } from './budget.js';
+
+describe('NetworkBudget', () => {
+  it('refuses requests past --max-requests', () => {
+    const budget = new NetworkBudget({ maxRequests: 2 });
+    expect([budget.admit(), budget.admit(), budget.admit()]).toEqual([true, true, false]);
+    expect(budget.exceeded()).toEqual({ limit: 'requests', used: 3, max: 2 });
+    expect(budget.blocked).toBe(1);
+  });
+
+  it('refuses requests once --max-bytes is passed', () => {
+    const budget = new NetworkBudget({ maxBytes: 1000 });
+    expect(budget.admit()).toBe(true);
+    budget.addBytes(1000);
+    expect(budget.exceeded()).toBeNull();
+    budget.addBytes(1);
+    expect(budget.admit()).toBe(false);
+    expect(budget.exceeded()?.limit).toBe('bytes');
+  });
+
+  it('builds a structured error', () => {
+    const budget = new NetworkBudget({ maxBytes: 50 * 1024 * 1024 });
+    budget.admit();
+    budget.addBytes(60 * 1024 * 1024);
+    const response = budgetErrorResponse('1', budget.exceeded()!, budget);
+    expect(response.error).toBe('Network budget exceeded: 60.0 MB downloaded (max 50.0 MB)');
+    expect(response.data).toMatchObject({ type: 'budget_exceeded', limit: 'bytes', requests: 1 });
+  });
+});
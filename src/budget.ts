@@ -0,0 +1,85 @@
+import type { ErrorResponse } from './types.js';
+
+export interface BudgetLimits {
+  maxBytes?: number;
+  maxRequests?: number;
+}
+
+export interface BudgetViolation {
+  limit: 'bytes' | 'requests';
+  used: number;
+  max: number;
+}
+
+/**
+ * Network usage counted against --max-bytes and --max-requests. Once a limit
+ * is passed, further requests are refused so a runaway page stops downloading.
+ */
+export class NetworkBudget {
+  requests = 0;
+  bytes = 0;
+  /** Requests refused after the budget was spent */
+  blocked = 0;
+
+  constructor(public limits: BudgetLimits) {}
+
+  exceeded(): BudgetViolation | null {
+    const { maxBytes, maxRequests } = this.limits;
+    if (maxBytes !== undefined && this.bytes > maxBytes) {
+      return { limit: 'bytes', used: this.bytes, max: maxBytes };
+    }
+    if (maxRequests !== undefined && this.requests > maxRequests) {
+      return { limit: 'requests', used: this.requests, max: maxRequests };
+    }
+    return null;
+  }
+
+  /**
+   * Count a request; false when it should be aborted
+   */
+  admit(): boolean {
+    this.requests++;
+    if (this.exceeded()) {
+      this.blocked++;
+      return false;
+    }
+    return true;
+  }
+
+  addBytes(bytes: number): void {
+    this.bytes += Math.max(0, bytes);
+  }
+}
+
+function formatBytes(bytes: number): string {
+  if (bytes < 1024) return `${bytes} B`;
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`;
+  if (bytes < 1024 * 1024 * 1024) return `${(bytes / 1024 / 1024).toFixed(1)} MB`;
+  return `${(bytes / 1024 / 1024 / 1024).toFixed(1)} GB`;
+}
+
+/**
+ * Error response for a command whose network budget ran out
+ */
+export function budgetErrorResponse(
+  id: string,
+  violation: BudgetViolation,
+  budget: NetworkBudget
+): ErrorResponse {
+  const usage =
+    violation.limit === 'bytes'
+      ? `${formatBytes(violation.used)} downloaded (max ${formatBytes(violation.max)})`
+      : `${violation.used} requests (max ${violation.max})`;
+  return {
+    id,
+    success: false,
+    error: `Network budget exceeded: ${usage}`,
+    data: {
+      type: 'budget_exceeded',
+      ...violation,
+      requests: budget.requests,
+      bytes: budget.bytes,
+      blocked: budget.blocked,
+    },
+  };
+}
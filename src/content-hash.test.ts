@@ -0,0 +1,26 @@
+import { describe, it, expect } from 'vitest';
+import { checkPatterns, contentHash, normalizeContent } from './content-hash.js';
+
+describe('content hash', () => {
+  it('collapses whitespace before hashing', () => {
+    expect(normalizeContent('  Price:\n\n  $42 \t')).toBe('Price: $42');
+    expect(contentHash('Price: $42')).toBe(contentHash('Price:\n   $42\n'));
+    expect(contentHash('Price: $42')).toMatch(/^sha256:[0-9a-f]{64}$/);
+  });
+
+  it('changes when the content does', () => {
+    expect(contentHash('Price: $42')).not.toBe(contentHash('Price: $43'));
+  });
+
+  it('leaves out ignored patterns', () => {
+    const patterns = ['Updated \\d{1,2}:\\d{2}'];
+    expect(normalizeContent('News Updated 10:42 today', patterns)).toBe('News today');
+    expect(contentHash('News Updated 10:42', patterns)).toBe(
+      contentHash('News Updated 11:05', patterns)
+    );
+  });
+
+  it('rejects invalid patterns', () => {
+    expect(() => checkPatterns(['('])).toThrow('Invalid contentHash pattern');
+  });
+});
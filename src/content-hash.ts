@@ -0,0 +1,62 @@
+import { createHash } from 'node:crypto';
+import type { Locator } from 'playwright-core';
+import type { HashIgnore } from './types.js';
+
+/**
+ * Change fingerprints for `text` and `get text`/`get html`: a hash of the
+ * content that stays the same across visits unless the page really changed.
+ * Whitespace is collapsed, and the regions (selectors) and patterns (regular
+ * expressions, e.g. timestamps) from `contentHash` in the CLI's config are
+ * left out.
+ */
+
+/** The content as hashed: ignored patterns removed, whitespace collapsed */
+export function normalizeContent(content: string, patterns: string[] = []): string {
+  let normalized = content;
+  for (const pattern of patterns) {
+    normalized = normalized.replace(new RegExp(pattern, 'g'), ' ');
+  }
+  return normalized.replace(/\s+/g, ' ').trim();
+}
+
+export function contentHash(content: string, patterns: string[] = []): string {
+  const digest = createHash('sha256').update(normalizeContent(content, patterns)).digest('hex');
+  return `sha256:${digest}`;
+}
+
+/** Check the patterns up front, so a bad one fails the command clearly */
+export function checkPatterns(patterns: string[]): void {
+  for (const pattern of patterns) {
+    try {
+      new RegExp(pattern, 'g');
+    } catch (error) {
+      throw new Error(`Invalid contentHash pattern ${JSON.stringify(pattern)}: ${String(error)}`);
+    }
+  }
+}
+
+/**
+ * Hash of an element's content, `content` being what the command returns.
+ * With ignored regions, the element's text (or HTML) is read again from a
+ * copy without them.
+ */
+export async function elementContentHash(
+  locator: Locator,
+  content: string,
+  ignore: HashIgnore | undefined,
+  html = false
+): Promise<string> {
+  const patterns = ignore?.patterns ?? [];
+  checkPatterns(patterns);
+  const selectors = ignore?.selectors ?? [];
+  if (selectors.length === 0) return contentHash(content, patterns);
+  const kept = await locator.evaluate(
+    (el, { selectors, html }) => {
+      const copy = el.cloneNode(true) as Element;
+      copy.querySelectorAll(selectors.join(', ')).forEach((region) => region.remove());
+      return html ? copy.innerHTML : (copy.textContent ?? '');
+    },
+    { selectors, html }
+  );
+  return contentHash(kept, patterns);
+}
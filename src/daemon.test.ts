@@ -1,7 +1,7 @@
 import { describe, it, expect, beforeEach, afterEach } from 'vitest';
 import * as os from 'os';
 import * as path from 'path';
-import { getSocketDir } from './daemon.js';
+import { getEphemeralFile, getPipeName, getReadOnlyFile, getSocketDir } from './daemon.js';
 
 describe('getSocketDir', () => {
   const originalEnv = { ...process.env };
@@ -58,3 +58,37 @@ describe('getSocketDir', () => {
     });
   });
 });
+
+describe('getPipeName', () => {
+  const originalEnv = { ...process.env };
+
+  afterEach(() => {
+    process.env = { ...originalEnv };
+  });
+
+  it('should match the CLI pipe name for the same socket dir', () => {
+    process.env.AGENT_BROWSER_SOCKET_DIR = '/work/acme/run';
+    expect(getPipeName('default')).toBe('\\\\.\\pipe\\agent-browser-dec285a5-default');
+  });
+
+  it('should differ between socket dirs', () => {
+    process.env.AGENT_BROWSER_SOCKET_DIR = '/work/acme/run';
+    const acme = getPipeName('default');
+    process.env.AGENT_BROWSER_SOCKET_DIR = '/work/globex/run';
+    expect(getPipeName('default')).not.toBe(acme);
+  });
+});
+
+describe('session mode markers', () => {
+  const originalEnv = { ...process.env };
+
+  afterEach(() => {
+    process.env = { ...originalEnv };
+  });
+
+  it('should sit next to the session pid file, where the CLI looks for it', () => {
+    process.env.AGENT_BROWSER_SOCKET_DIR = '/work/acme/run';
+    expect(getEphemeralFile('lookup')).toBe(path.join('/work/acme/run', 'lookup.ephemeral'));
+    expect(getReadOnlyFile('lookup')).toBe(path.join('/work/acme/run', 'lookup.readonly'));
+  });
+});
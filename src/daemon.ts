@@ -1,16 +1,24 @@
 import * as net from 'net';
+import * as crypto from 'crypto';
 import * as fs from 'fs';
 import * as path from 'path';
 import * as os from 'os';
 import { BrowserManager } from './browser.js';
-import { parseCommand, serializeResponse, errorResponse } from './protocol.js';
+import { parseCommand, serializeResponse, errorResponse, successResponse } from './protocol.js';
+import { FRAMES_HELLO, encodeFrames } from './framing.js';
+import { CommandQueue, jobQueryResponse } from './job-queue.js';
+import { Scheduler, scheduleResponse, isScheduleCommand, getScheduleDir } from './scheduler.js';
+import { budgetErrorResponse } from './budget.js';
+import { ReadCache, readCacheKey } from './read-cache.js';
+import { ObserverHub, type Observer } from './observers.js';
+import { checkAccess } from './access-tokens.js';
+import type { Command, Response, WarmClaimCommand } from './types.js';
 import { executeCommand } from './actions.js';
 import { StreamServer } from './stream-server.js';
+import { HostRules, parseHostList } from './host-rules.js';
 import {
   getSessionsDir,
   ensureSessionsDir,
-  getEncryptionKey,
-  encryptData,
   isEncryptedPayload,
   decryptData,
   ENCRYPTION_KEY_ENV,
@@ -32,13 +40,23 @@ let streamServer: StreamServer | null = null;
 // Default stream port (can be overridden with AGENT_BROWSER_STREAM_PORT)
 const DEFAULT_STREAM_PORT = 9223;
 
+// An --ephemeral daemon shuts down after this long without commands
+const EPHEMERAL_IDLE_MS = 5 * 60 * 1000;
+const EPHEMERAL_IDLE_CHECK_MS = 10 * 1000;
+
+// Digest of the state last written to each file, so saves that would
+// rewrite the same state are skipped
+const savedStateDigests = new Map<string, string>();
+
 /**
- * Save state to file with optional encryption.
+ * Save state to file with optional encryption, atomically and with backups.
+ * Large state is compressed, and nothing is written when the state hasn't
+ * changed since the last save.
  */
 async function saveStateToFile(
   browser: BrowserManager,
   filepath: string
-): Promise<{ encrypted: boolean }> {
+): Promise<{ saved: boolean; encrypted: boolean; compressed: boolean }> {
   // First get the storage state from Playwright
   const context = browser.getContext();
   if (!context) {
@@ -46,17 +64,19 @@ async function saveStateToFile(
   }
 
   const state = await context.storageState();
-  const jsonData = JSON.stringify(state, null, 2);
-
-  const key = getEncryptionKey();
-  if (key) {
-    const encrypted = encryptData(jsonData, key);
-    fs.writeFileSync(filepath, JSON.stringify(encrypted, null, 2));
-    return { encrypted: true };
+  const digest = crypto.createHash('sha256').update(JSON.stringify(state)).digest('hex');
+  if (savedStateDigests.get(filepath) === digest && fs.existsSync(filepath)) {
+    return { saved: false, encrypted: false, compressed: false };
   }
+  const result = writeStateFile(filepath, state, { compress: true });
+  savedStateDigests.set(filepath, digest);
+  return { saved: true, ...result };
+}
 
-  fs.writeFileSync(filepath, jsonData);
-  return { encrypted: false };
+/** --save-state-interval in ms, or 0 to save only on close */
+function getSaveStateInterval(): number {
+  const ms = parseInt(process.env.AGENT_BROWSER_SAVE_STATE_INTERVAL_MS ?? '', 10);
+  return Number.isFinite(ms) && ms > 0 ? ms : 0;
 }
 
 const AUTO_EXPIRE_ENV = 'AGENT_BROWSER_STATE_EXPIRE_DAYS';
@@ -99,22 +119,26 @@ export function getSession(): string {
 }
 
 /**
- * Get port number for TCP mode (Windows)
- * Uses a hash of the session name to get a consistent port
+ * Get the named pipe for a session (Windows).
+ * Pipe names are machine-wide, so a hash of the socket dir keeps workspaces
+ * and users apart. Must match get_pipe_name in cli/src/connection.rs.
  */
-function getPortForSession(session: string): number {
+export function getPipeName(session?: string): string {
+  const sess = session ?? currentSession;
+  const dir = getSocketDir();
   let hash = 0;
-  for (let i = 0; i < session.length; i++) {
-    hash = (hash << 5) - hash + session.charCodeAt(i);
+  for (let i = 0; i < dir.length; i++) {
+    hash = (hash << 5) - hash + dir.charCodeAt(i);
     hash |= 0;
   }
-  // Port range 49152-65535 (dynamic/private ports)
-  return 49152 + (Math.abs(hash) % 16383);
+  const hex = (hash >>> 0).toString(16).padStart(8, '0');
+  return `\\\\.\\pipe\\agent-browser-${hex}-${sess}`;
 }
 
 /**
  * Get the base directory for socket/pid files.
- * Priority: AGENT_BROWSER_SOCKET_DIR > XDG_RUNTIME_DIR > ~/.agent-browser > tmpdir
+ * Priority: AGENT_BROWSER_SOCKET_DIR > XDG_RUNTIME_DIR > %APPDATA% (Windows)
+ * > ~/.agent-browser > tmpdir
  */
 export function getSocketDir(): string {
   // 1. Explicit override
@@ -127,35 +151,32 @@ export function getSocketDir(): string {
     return path.join(process.env.XDG_RUNTIME_DIR, 'agent-browser');
   }
 
-  // 3. Home directory fallback (like Docker Desktop's ~/.docker/run/)
+  // 3. Per-user app data on Windows
+  if (isWindows && process.env.APPDATA) {
+    return path.join(process.env.APPDATA, 'agent-browser');
+  }
+
+  // 4. Home directory fallback (like Docker Desktop's ~/.docker/run/)
   const homeDir = os.homedir();
   if (homeDir) {
     return path.join(homeDir, '.agent-browser');
   }
 
-  // 4. Last resort: temp dir
+  // 5. Last resort: temp dir
   return path.join(os.tmpdir(), 'agent-browser');
 }
 
 /**
- * Get the socket path for the current session (Unix) or port (Windows)
+ * Get the socket path for the current session (Unix) or named pipe (Windows)
  */
 export function getSocketPath(session?: string): string {
   const sess = session ?? currentSession;
   if (isWindows) {
-    return String(getPortForSession(sess));
+    return getPipeName(sess);
   }
   return path.join(getSocketDir(), `${sess}.sock`);
 }
 
-/**
- * Get the port file path for Windows (stores the port number)
- */
-export function getPortFile(session?: string): string {
-  const sess = session ?? currentSession;
-  return path.join(getSocketDir(), `${sess}.port`);
-}
-
 /**
  * Get the PID file path for the current session
  */
@@ -185,14 +206,14 @@ export function isDaemonRunning(session?: string): boolean {
 
 /**
  * Get connection info for the current session
- * Returns { type: 'unix', path: string } or { type: 'tcp', port: number }
+ * Returns { type: 'unix', path: string } or { type: 'pipe', path: string }
  */
 export function getConnectionInfo(
   session?: string
-): { type: 'unix'; path: string } | { type: 'tcp'; port: number } {
+): { type: 'unix'; path: string } | { type: 'pipe'; path: string } {
   const sess = session ?? currentSession;
   if (isWindows) {
-    return { type: 'tcp', port: getPortForSession(sess) };
+    return { type: 'pipe', path: getPipeName(sess) };
   }
   return { type: 'unix', path: path.join(getSocketDir(), `${sess}.sock`) };
 }
@@ -203,13 +224,15 @@ export function getConnectionInfo(
 export function cleanupSocket(session?: string): void {
   const pidFile = getPidFile(session);
   const streamPortFile = getStreamPortFile(session);
+  const ephemeralFile = getEphemeralFile(session);
+  const readOnlyFile = getReadOnlyFile(session);
   try {
     if (fs.existsSync(pidFile)) fs.unlinkSync(pidFile);
     if (fs.existsSync(streamPortFile)) fs.unlinkSync(streamPortFile);
-    if (isWindows) {
-      const portFile = getPortFile(session);
-      if (fs.existsSync(portFile)) fs.unlinkSync(portFile);
-    } else {
+    if (fs.existsSync(ephemeralFile)) fs.unlinkSync(ephemeralFile);
+    if (fs.existsSync(readOnlyFile)) fs.unlinkSync(readOnlyFile);
+    // Named pipes disappear with the process, only Unix sockets leave a file
+    if (!isWindows) {
       const socketPath = getSocketPath(session);
       if (fs.existsSync(socketPath)) fs.unlinkSync(socketPath);
     }
@@ -226,6 +249,23 @@ export function getStreamPortFile(session?: string): string {
   return path.join(getSocketDir(), `${sess}.stream`);
 }
 
+/**
+ * Get the marker file an --ephemeral daemon keeps while it runs, so clients
+ * know the session must not write state to disk
+ */
+export function getEphemeralFile(session?: string): string {
+  const sess = session ?? currentSession;
+  return path.join(getSocketDir(), `${sess}.ephemeral`);
+}
+
+/**
+ * Get the marker file a --read-only daemon keeps while it runs
+ */
+export function getReadOnlyFile(session?: string): string {
+  const sess = session ?? currentSession;
+  return path.join(getSocketDir(), `${sess}.readonly`);
+}
+
 /**
  * Start the daemon server
  * @param options.streamPort Port for WebSocket stream server (0 to disable)
@@ -245,6 +285,22 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
 
   const browser = new BrowserManager();
   let shuttingDown = false;
+  // --ephemeral: no state is saved, and the daemon exits once idle
+  const ephemeral = process.env.AGENT_BROWSER_EPHEMERAL === '1';
+  let runningCommands = 0;
+  let lastCommandAt = Date.now();
+  // --read-only: nothing that could change data on a server gets through
+  const readOnly = process.env.AGENT_BROWSER_READ_ONLY === '1';
+  if (readOnly) {
+    browser.setReadOnly();
+  }
+  // --allow-hosts / --block-hosts, also given to auto-launched browsers
+  browser.setHostRules(
+    new HostRules(
+      parseHostList(process.env.AGENT_BROWSER_ALLOW_HOSTS),
+      parseHostList(process.env.AGENT_BROWSER_BLOCK_HOSTS)
+    )
+  );
 
   // Start stream server if port is specified (or use default if env var is set)
   const streamPort =
@@ -262,8 +318,280 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
     fs.writeFileSync(streamPortFile, streamPort.toString());
   }
 
-  const server = net.createServer((socket) => {
+  const queue = new CommandQueue();
+
+  // Responses to reads sent with --cache
+  const readCache = new ReadCache();
+
+  // Scheduled runs go through the same queue as commands from clients
+  const startScheduler = () => {
+    const started = new Scheduler(
+      (command) => queue.run(() => runCommand(command)),
+      path.join(getScheduleDir(), `${currentSession}.json`)
+    );
+    started.start();
+    return started;
+  };
+  let scheduler = startScheduler();
+
+  // A spare daemon started by `daemon warm`, until a session claims it
+  let warm = process.env.AGENT_BROWSER_WARM === '1';
+
+  // Clients attached with `observe`, told about every command
+  const observers = new ObserverHub();
+
+  /**
+   * With --session-name, save the session's state to its auto-save file.
+   * Never fails: a state that can't be saved must not stop a close.
+   */
+  async function autoSaveState(): Promise<void> {
+    // SECURITY: Validate session name to prevent path traversal attacks
+    const sessionNameRaw = process.env.AGENT_BROWSER_SESSION_NAME;
+    const sessionName =
+      !ephemeral && sessionNameRaw && isValidSessionName(sessionNameRaw)
+        ? sessionNameRaw
+        : undefined;
+    const sessionId = process.env.AGENT_BROWSER_SESSION || 'default';
+    if (!sessionName || !browser.isLaunched()) return;
+
+    try {
+      const autoStatePath = getAutoStateFilePath(sessionName, sessionId);
+      if (!autoStatePath) return;
+      // Written with owner read/write only (0o600)
+      const { saved, encrypted, compressed } = await saveStateToFile(browser, autoStatePath);
+      if (process.env.AGENT_BROWSER_DEBUG === '1') {
+        const notes = [encrypted && 'encrypted', compressed && 'compressed'].filter(Boolean);
+        console.error(
+          saved
+            ? `Auto-saved session state: ${autoStatePath}` +
+                (notes.length > 0 ? ` (${notes.join(', ')})` : '')
+            : `Session state unchanged, not saved: ${autoStatePath}`
+        );
+      }
+    } catch (err) {
+      // Non-blocking: don't fail close if save fails
+      if (process.env.AGENT_BROWSER_DEBUG === '1') {
+        console.error(`Failed to auto-save session state:`, err);
+      }
+    }
+  }
+
+  /**
+   * Run one command, keeping track of when the daemon was last busy and
+   * telling observers about it
+   */
+  async function runCommand(command: Command): Promise<Response> {
+    runningCommands++;
+    const started = Date.now();
+    observers.commandStarted(command);
+    try {
+      const response = await runQueuedCommand(command);
+      observers.commandFinished(command, response, Date.now() - started);
+      await screenshotForObservers(command);
+      return response;
+    } catch (err) {
+      const message = err instanceof Error ? err.message : String(err);
+      observers.commandFinished(command, errorResponse(command.id, message), Date.now() - started);
+      throw err;
+    } finally {
+      runningCommands--;
+      lastCommandAt = Date.now();
+    }
+  }
+
+  /**
+   * The page after a command, for observers that asked for screenshots
+   */
+  async function screenshotForObservers(command: Command): Promise<void> {
+    if (!observers.wantsScreenshots() || !browser.isLaunched()) return;
+    try {
+      const image = await browser.getPage().screenshot({ type: 'jpeg', quality: 60 });
+      observers.broadcast({
+        event: 'screenshot',
+        id: command.id,
+        action: command.action,
+        data: image.toString('base64'),
+      });
+    } catch {
+      // Page closed or navigating: the next command sends one
+    }
+  }
+
+  /**
+   * Run one command against the browser, launching it first if needed.
+   * Commands reach this through the queue, so only one runs at a time.
+   */
+  async function runQueuedCommand(command: Command): Promise<Response> {
+    // An ephemeral session writes nothing to disk that would outlive it
+    if (ephemeral && command.action === 'state_save') {
+      return errorResponse(command.id, 'state save is disabled in an --ephemeral session');
+    }
+    if (ephemeral && command.action === 'launch' && command.profile) {
+      return errorResponse(command.id, '--profile cannot be used in an --ephemeral session');
+    }
+
+    // Auto-launch browser if not already launched and this isn't a launch command
+    if (!browser.isLaunched() && command.action !== 'launch' && command.action !== 'close') {
+      const extensions = process.env.AGENT_BROWSER_EXTENSIONS
+        ? process.env.AGENT_BROWSER_EXTENSIONS.split(',')
+            .map((p) => p.trim())
+            .filter(Boolean)
+        : undefined;
+
+      // Parse args from env (comma or newline separated)
+      const argsEnv = process.env.AGENT_BROWSER_ARGS;
+      const args = argsEnv
+        ? argsEnv
+            .split(/[,\n]/)
+            .map((a) => a.trim())
+            .filter((a) => a.length > 0)
+        : undefined;
+
+      // Parse proxy from env
+      const proxyServer = process.env.AGENT_BROWSER_PROXY;
+      const proxyBypass = process.env.AGENT_BROWSER_PROXY_BYPASS;
+      const proxy = proxyServer
+        ? {
+            server: proxyServer,
+            ...(proxyBypass && { bypass: proxyBypass }),
+          }
+        : undefined;
+
+      // Check for auto-load state
+      // SECURITY: Validate session name to prevent path traversal attacks
+      const sessionNameRaw = process.env.AGENT_BROWSER_SESSION_NAME;
+      const sessionName =
+        !ephemeral && sessionNameRaw && isValidSessionName(sessionNameRaw)
+          ? sessionNameRaw
+          : undefined;
+      if (sessionNameRaw && !sessionName && process.env.AGENT_BROWSER_DEBUG === '1') {
+        console.error(`[SECURITY] Invalid session name rejected: ${sessionNameRaw}`);
+      }
+      const sessionId = process.env.AGENT_BROWSER_SESSION || 'default';
+      let autoStatePath: string | undefined;
+      try {
+        autoStatePath = sessionName
+          ? (getAutoStateFilePath(sessionName, sessionId) ?? undefined)
+          : undefined;
+      } catch {
+        // Validation already done above, this is a safety net
+        autoStatePath = undefined;
+      }
+
+      await browser.launch({
+        id: 'auto',
+        action: 'launch' as const,
+        headless: process.env.AGENT_BROWSER_HEADED !== '1',
+        executablePath: process.env.AGENT_BROWSER_EXECUTABLE_PATH,
+        extensions: extensions,
+        args,
+        userAgent: process.env.AGENT_BROWSER_USER_AGENT,
+        proxy,
+        ignoreHTTPSErrors: process.env.AGENT_BROWSER_IGNORE_HTTPS_ERRORS === '1',
+        extraCaCerts: process.env.AGENT_BROWSER_EXTRA_CA || undefined,
+        autoStateFilePath:
+          autoStatePath && fs.existsSync(autoStatePath) ? autoStatePath : undefined,
+      });
+    }
+
+    // Handle explicit launch with auto-load state
+    if (command.action === 'launch') {
+      // SECURITY: Validate session name to prevent path traversal attacks
+      const sessionNameRaw = process.env.AGENT_BROWSER_SESSION_NAME;
+      const sessionName =
+        !ephemeral && sessionNameRaw && isValidSessionName(sessionNameRaw)
+          ? sessionNameRaw
+          : undefined;
+      const sessionId = process.env.AGENT_BROWSER_SESSION || 'default';
+
+      if (sessionName && !command.autoStateFilePath) {
+        try {
+          const autoStatePath = getAutoStateFilePath(sessionName, sessionId);
+          if (autoStatePath && fs.existsSync(autoStatePath)) {
+            command.autoStateFilePath = autoStatePath;
+          }
+        } catch {
+          // Invalid session name, ignore
+        }
+      }
+    }
+
+    // Handle close command specially
+    if (command.action === 'close') {
+      // Auto-save state before closing
+      await autoSaveState();
+      return await executeCommand(command, browser);
+    }
+
+    // --cache: the same read of the same page within the TTL is answered again
+    const cacheKey = command.cache ? readCacheKey(browser.getPage().url(), command) : null;
+    const cached = readCache.get(cacheKey, command.cache ?? 0, command.id);
+    if (cached) return cached;
+
+    // A browser over --max-memory is restarted before the next command
+    if (command.action !== 'launch') {
+      await browser.enforceMemoryLimit();
+    }
+
+    // Registered before the per-command routes so it sees their changes
+    await browser.enforceHostRules();
+    if (command.budget && command.action !== 'launch') {
+      const { scope = 'command', ...limits } = command.budget;
+      await browser.beginNetworkBudget(limits, scope);
+    }
+    if (command.block && command.action !== 'launch') {
+      await browser.beginResourceBlocking(command.block);
+    }
+    await browser.enforceReadOnly();
+    // Pick the pool's proxy before the page starts connecting
+    const proxyServer = command.action === 'navigate' ? browser.rotateProxyForNavigation() : null;
+
+    const response = await executeCommand(command, browser);
+    if (proxyServer && response.success && response.data) {
+      (response.data as Record<string, unknown>).proxy = proxyServer;
+    }
+    // A running `record gif` takes its frame once the command is done
+    if (
+      browser.isCapturingGif() &&
+      command.action !== 'gif_start' &&
+      command.action !== 'gif_stop'
+    ) {
+      const gif = await browser.gifCommandDone();
+      if (gif && response.success && response.data) {
+        (response.data as Record<string, unknown>).gif = gif;
+      }
+    }
+    if (command.block && command.action !== 'launch') {
+      response.meta = { ...response.meta, blocked: browser.endResourceBlocking() };
+    }
+
+    // A spent budget fails the command even if the page got far enough
+    const spent = command.budget ? browser.endNetworkBudget() : null;
+    if (spent) {
+      const error = budgetErrorResponse(command.id, spent.violation, spent.budget);
+      return response.meta ? { ...error, meta: response.meta } : error;
+    }
+
+    readCache.set(cacheKey, response);
+
+    // Add any launch warnings to the response
+    const warnings = browser.getAndClearWarnings();
+    if (warnings.length > 0 && response.success && response.data) {
+      (response.data as Record<string, unknown>).warnings = warnings;
+    }
+
+    return response;
+  }
+
+  const handleConnection = (socket: net.Socket) => {
     let buffer = '';
+    // Set once the client attaches with `observe`
+    let observer: Observer | null = null;
+    // Set by FRAMES_HELLO: responses go out as frames instead of lines
+    let framed = false;
+    const reply = (response: Response) => {
+      socket.write(framed ? encodeFrames(response) : serializeResponse(response) + '\n');
+    };
 
     socket.on('data', async (data) => {
       buffer += data.toString();
@@ -276,139 +604,100 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
         buffer = buffer.slice(newlineIdx + 1);
 
         if (!line.trim()) continue;
+        if (line === FRAMES_HELLO) {
+          framed = true;
+          continue;
+        }
 
         try {
           const parseResult = parseCommand(line);
 
           if (!parseResult.success) {
             const resp = errorResponse(parseResult.id ?? 'unknown', parseResult.error);
-            socket.write(serializeResponse(resp) + '\n');
+            reply(resp);
             continue;
           }
 
-          // Auto-launch browser if not already launched and this isn't a launch command
-          if (
-            !browser.isLaunched() &&
-            parseResult.command.action !== 'launch' &&
-            parseResult.command.action !== 'close'
-          ) {
-            const extensions = process.env.AGENT_BROWSER_EXTENSIONS
-              ? process.env.AGENT_BROWSER_EXTENSIONS.split(',')
-                  .map((p) => p.trim())
-                  .filter(Boolean)
-              : undefined;
-
-            // Parse args from env (comma or newline separated)
-            const argsEnv = process.env.AGENT_BROWSER_ARGS;
-            const args = argsEnv
-              ? argsEnv
-                  .split(/[,\n]/)
-                  .map((a) => a.trim())
-                  .filter((a) => a.length > 0)
-              : undefined;
-
-            // Parse proxy from env
-            const proxyServer = process.env.AGENT_BROWSER_PROXY;
-            const proxyBypass = process.env.AGENT_BROWSER_PROXY_BYPASS;
-            const proxy = proxyServer
-              ? {
-                  server: proxyServer,
-                  ...(proxyBypass && { bypass: proxyBypass }),
-                }
-              : undefined;
-
-            // Check for auto-load state
-            // SECURITY: Validate session name to prevent path traversal attacks
-            const sessionNameRaw = process.env.AGENT_BROWSER_SESSION_NAME;
-            const sessionName =
-              sessionNameRaw && isValidSessionName(sessionNameRaw) ? sessionNameRaw : undefined;
-            if (sessionNameRaw && !sessionName && process.env.AGENT_BROWSER_DEBUG === '1') {
-              console.error(`[SECURITY] Invalid session name rejected: ${sessionNameRaw}`);
-            }
-            const sessionId = process.env.AGENT_BROWSER_SESSION || 'default';
-            let autoStatePath: string | undefined;
-            try {
-              autoStatePath = sessionName
-                ? (getAutoStateFilePath(sessionName, sessionId) ?? undefined)
-                : undefined;
-            } catch {
-              // Validation already done above, this is a safety net
-              autoStatePath = undefined;
-            }
+          const command = parseResult.command;
+
+          // An observer watches the session; nothing it sends runs
+          if (observer) {
+            const resp = errorResponse(
+              command.id,
+              `This connection is observing session ${currentSession} and can't run commands`
+            );
+            reply(resp);
+            continue;
+          }
+
+          // Once access tokens exist, each command needs one that may run it here
+          const target = command.action === 'warm_claim' ? command.session : currentSession;
+          const denied = checkAccess(command, target);
+          if (denied) {
+            reply(denied);
+            continue;
+          }
 
-            await browser.launch({
-              id: 'auto',
-              action: 'launch' as const,
-              headless: process.env.AGENT_BROWSER_HEADED !== '1',
-              executablePath: process.env.AGENT_BROWSER_EXECUTABLE_PATH,
-              extensions: extensions,
-              args,
-              userAgent: process.env.AGENT_BROWSER_USER_AGENT,
-              proxy,
-              autoStateFilePath:
-                autoStatePath && fs.existsSync(autoStatePath) ? autoStatePath : undefined,
+          if (command.action === 'warm_claim') {
+            reply(await claimWarm(command));
+            continue;
+          }
+
+          if (command.action === 'observe') {
+            observer = {
+              screenshots: command.screenshots ?? false,
+              write: (line) => socket.write(line),
+            };
+            observers.add(observer);
+            const resp = successResponse(command.id, {
+              session: currentSession,
+              url: browser.isLaunched() ? browser.getPage().url() : null,
+              observers: observers.size,
             });
+            reply(resp);
+            continue;
           }
 
-          // Handle explicit launch with auto-load state
-          if (parseResult.command.action === 'launch') {
-            // SECURITY: Validate session name to prevent path traversal attacks
-            const sessionNameRaw = process.env.AGENT_BROWSER_SESSION_NAME;
-            const sessionName =
-              sessionNameRaw && isValidSessionName(sessionNameRaw) ? sessionNameRaw : undefined;
-            const sessionId = process.env.AGENT_BROWSER_SESSION || 'default';
-
-            if (sessionName && !parseResult.command.autoStateFilePath) {
-              try {
-                const autoStatePath = getAutoStateFilePath(sessionName, sessionId);
-                if (autoStatePath && fs.existsSync(autoStatePath)) {
-                  parseResult.command.autoStateFilePath = autoStatePath;
-                }
-              } catch {
-                // Invalid session name, ignore
-              }
-            }
+          // Job queries are answered right away instead of waiting in the queue
+          if (
+            command.action === 'job_status' ||
+            command.action === 'job_result' ||
+            command.action === 'job_list' ||
+            command.action === 'job_cancel'
+          ) {
+            reply(jobQueryResponse(queue, command));
+            continue;
           }
 
-          // Handle close command specially
-          if (parseResult.command.action === 'close') {
-            // Auto-save state before closing
-            // SECURITY: Validate session name to prevent path traversal attacks
-            const sessionNameRaw = process.env.AGENT_BROWSER_SESSION_NAME;
-            const sessionName =
-              sessionNameRaw && isValidSessionName(sessionNameRaw) ? sessionNameRaw : undefined;
-            const sessionId = process.env.AGENT_BROWSER_SESSION || 'default';
-
-            if (sessionName && browser.isLaunched()) {
-              try {
-                const autoStatePath = getAutoStateFilePath(sessionName, sessionId);
-                if (autoStatePath) {
-                  try {
-                    const { encrypted } = await saveStateToFile(browser, autoStatePath);
-                    // Set file permissions to owner read/write only (0o600)
-                    fs.chmodSync(autoStatePath, 0o600);
-                    if (process.env.AGENT_BROWSER_DEBUG === '1') {
-                      console.error(
-                        `Auto-saved session state: ${autoStatePath}${encrypted ? ' (encrypted)' : ''}`
-                      );
-                    }
-                  } catch (err) {
-                    // Non-blocking: don't fail close if save fails
-                    if (process.env.AGENT_BROWSER_DEBUG === '1') {
-                      console.error(`Failed to auto-save session state:`, err);
-                    }
-                  }
-                }
-              } catch {
-                // Invalid session name, ignore
-              }
-            }
+          // Schedules are managed by the daemon; their runs queue their own commands
+          if (isScheduleCommand(command)) {
+            const resp =
+              ephemeral && command.action === 'schedule_add'
+                ? errorResponse(command.id, 'Schedules are disabled in an --ephemeral session')
+                : await scheduleResponse(scheduler, command);
+            reply(resp);
+            continue;
+          }
+
+          // Queue the command and return its job id instead of waiting for it
+          if (command.noWait && command.action !== 'close') {
+            const job = queue.enqueue(command.action, () => runCommand(command));
+            const resp = successResponse(command.id, {
+              jobId: job.id,
+              status: job.status,
+              position: queue.position(job.id),
+            });
+            reply(resp);
+            continue;
+          }
 
-            const response = await executeCommand(parseResult.command, browser);
-            socket.write(serializeResponse(response) + '\n');
+          const response = await queue.run(() => runCommand(command));
+          reply(response);
 
+          if (command.action === 'close') {
             if (!shuttingDown) {
               shuttingDown = true;
+              observers.broadcast({ event: 'closed' });
               setTimeout(() => {
                 server.close();
                 cleanupSocket();
@@ -417,19 +706,9 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
             }
             return;
           }
-
-          const response = await executeCommand(parseResult.command, browser);
-
-          // Add any launch warnings to the response
-          const warnings = browser.getAndClearWarnings();
-          if (warnings.length > 0 && response.success && response.data) {
-            (response.data as Record<string, unknown>).warnings = warnings;
-          }
-
-          socket.write(serializeResponse(response) + '\n');
         } catch (err) {
           const message = err instanceof Error ? err.message : String(err);
-          socket.write(serializeResponse(errorResponse('error', message)) + '\n');
+          reply(errorResponse('error', message));
         }
       }
     });
@@ -437,20 +716,74 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
     socket.on('error', () => {
       // Client disconnected, ignore
     });
-  });
+
+    socket.on('close', () => {
+      if (observer) observers.remove(observer);
+    });
+  };
+
+  let server = net.createServer(handleConnection);
+
+  /**
+   * Hand this warm daemon to a session: it listens on the session's socket,
+   * takes its pid file and schedules, and gives up the warm name's files
+   */
+  async function claimWarm(command: WarmClaimCommand): Promise<Response> {
+    if (!warm) {
+      return errorResponse(command.id, `Session ${currentSession} is not a warm daemon`);
+    }
+    if (isDaemonRunning(command.session)) {
+      return errorResponse(command.id, `Session ${command.session} is already running`);
+    }
+    warm = false;
+    const next = net.createServer(handleConnection);
+    try {
+      cleanupSocket(command.session);
+      await new Promise<void>((resolve, reject) => {
+        next.once('error', reject);
+        next.listen(getSocketPath(command.session), () => resolve());
+      });
+    } catch (err) {
+      warm = true;
+      const message = err instanceof Error ? err.message : String(err);
+      return errorResponse(command.id, `Cannot take over session ${command.session}: ${message}`);
+    }
+
+    const previous = currentSession;
+    setSession(command.session);
+    process.env.AGENT_BROWSER_SESSION = command.session;
+    delete process.env.AGENT_BROWSER_WARM;
+    fs.writeFileSync(getPidFile(), process.pid.toString());
+    if (readOnly) {
+      fs.writeFileSync(getReadOnlyFile(), '');
+    }
+    if (streamServer) {
+      fs.writeFileSync(getStreamPortFile(), streamPort.toString());
+    }
+    next.on('error', onServerError);
+    server.close();
+    server = next;
+    cleanupSocket(previous);
+    scheduler.stop();
+    scheduler = startScheduler();
+    return successResponse(command.id, { session: command.session, warm: previous });
+  }
 
   const pidFile = getPidFile();
 
   // Write PID file before listening
   fs.writeFileSync(pidFile, process.pid.toString());
+  if (ephemeral) {
+    fs.writeFileSync(getEphemeralFile(), '');
+  }
+  if (readOnly) {
+    fs.writeFileSync(getReadOnlyFile(), '');
+  }
 
   if (isWindows) {
-    // Windows: use TCP socket on localhost
-    const port = getPortForSession(currentSession);
-    const portFile = getPortFile();
-    fs.writeFileSync(portFile, port.toString());
-    server.listen(port, '127.0.0.1', () => {
-      // Daemon is ready on TCP port
+    // Windows: use a named pipe
+    server.listen(getPipeName(), () => {
+      // Daemon is ready
     });
   } else {
     // Unix: use Unix domain socket
@@ -460,11 +793,12 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
     });
   }
 
-  server.on('error', (err) => {
+  const onServerError = (err: Error) => {
     console.error('Server error:', err);
     cleanupSocket();
     process.exit(1);
-  });
+  };
+  server.on('error', onServerError);
 
   // Handle shutdown signals
   const shutdown = async () => {
@@ -490,6 +824,24 @@ export async function startDaemon(options?: { streamPort?: number }): Promise<vo
     process.exit(0);
   };
 
+  // The browser and its temporary profile go once no command has come in for a while
+  if (ephemeral) {
+    setInterval(() => {
+      if (runningCommands === 0 && Date.now() - lastCommandAt >= EPHEMERAL_IDLE_MS) {
+        void shutdown();
+      }
+    }, EPHEMERAL_IDLE_CHECK_MS).unref();
+  }
+
+  // --save-state-interval: also save between commands, so a daemon that is
+  // killed loses at most one interval of state
+  const saveStateInterval = getSaveStateInterval();
+  if (saveStateInterval > 0) {
+    setInterval(() => {
+      if (runningCommands === 0) void queue.run(() => autoSaveState());
+    }, saveStateInterval).unref();
+  }
+
   process.on('SIGINT', shutdown);
   process.on('SIGTERM', shutdown);
   process.on('SIGHUP', shutdown);
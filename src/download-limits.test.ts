@@ -0,0 +1,31 @@
+import { describe, it, expect } from 'vitest';
+import { checkDownload, downloadBlockedResponse, mimeMatches } from './download-limits.js';
+
+describe('download limits', () => {
+  it('matches MIME types and type/* patterns, ignoring parameters', () => {
+    expect(mimeMatches('video/*', 'video/mp4')).toBe(true);
+    expect(mimeMatches('application/pdf', 'Application/PDF; charset=binary')).toBe(true);
+    expect(mimeMatches('video/*', 'application/video')).toBe(false);
+  });
+
+  it('refuses blocked types and oversized files', () => {
+    const limits = { maxBytes: 1000, blockedTypes: ['application/*'] };
+    expect(checkDownload(limits, { contentType: 'text/csv', size: 1000 })).toBeNull();
+    expect(checkDownload(limits, { contentType: 'application/zip' })).toEqual({
+      reason: "Download blocked: application/zip matches --block-download-types 'application/*'",
+      contentType: 'application/zip',
+    });
+    expect(checkDownload(limits, { size: 1001 })?.size).toBe(1001);
+    expect(checkDownload({}, { contentType: 'video/mp4', size: 1e9 })).toBeNull();
+  });
+
+  it('reports blocked downloads with their details', () => {
+    const response = downloadBlockedResponse('1', { reason: 'too big', size: 5 });
+    expect(response).toEqual({
+      id: '1',
+      success: false,
+      error: 'too big',
+      data: { type: 'download_blocked', size: 5 },
+    });
+  });
+});
@@ -0,0 +1,60 @@
+import type { ErrorResponse } from './types.js';
+
+/** --max-download and --block-download-types, attached to download commands */
+export interface DownloadLimits {
+  maxBytes?: number;
+  blockedTypes?: string[];
+}
+
+export interface DownloadViolation {
+  reason: string;
+  contentType?: string;
+  size?: number;
+}
+
+/**
+ * Whether a Content-Type matches a blocked `type/subtype` or `type/*`
+ * pattern, ignoring parameters such as charset
+ */
+export function mimeMatches(pattern: string, contentType: string): boolean {
+  const mime = contentType.split(';')[0].trim().toLowerCase();
+  if (pattern.endsWith('/*')) {
+    return mime.startsWith(pattern.slice(0, -1));
+  }
+  return mime === pattern;
+}
+
+/**
+ * Check what is known about a download against its limits. Called with the
+ * response headers before the file is kept, and with the saved size after.
+ */
+export function checkDownload(
+  limits: DownloadLimits,
+  download: { contentType?: string | null; size?: number | null }
+): DownloadViolation | null {
+  const { contentType, size } = download;
+  if (contentType) {
+    const rule = limits.blockedTypes?.find((pattern) => mimeMatches(pattern, contentType));
+    if (rule) {
+      return {
+        reason: `Download blocked: ${contentType} matches --block-download-types '${rule}'`,
+        contentType,
+      };
+    }
+  }
+  if (limits.maxBytes !== undefined && size != null && size > limits.maxBytes) {
+    return {
+      reason: `Download blocked: ${size} bytes is over --max-download (${limits.maxBytes} bytes)`,
+      size,
+    };
+  }
+  return null;
+}
+
+/**
+ * Error response for a download refused by its limits
+ */
+export function downloadBlockedResponse(id: string, violation: DownloadViolation): ErrorResponse {
+  const { reason, ...details } = violation;
+  return { id, success: false, error: reason, data: { type: 'download_blocked', ...details } };
+}
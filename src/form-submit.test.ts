@@ -0,0 +1,57 @@
+import { describe, it, expect } from 'vitest';
+import { encodeSubmission, type FormSubmission } from './form-submit.js';
+
+function form(overrides: Partial<FormSubmission> = {}): FormSubmission {
+  return {
+    method: 'post',
+    action: 'https://shop.example.com/checkout?step=2',
+    enctype: 'application/x-www-form-urlencoded',
+    target: '',
+    fields: [
+      { name: 'email', value: 'ada@example.com' },
+      { name: 'note', value: 'a&b c' },
+    ],
+    ...overrides,
+  };
+}
+
+describe('intercepted form submissions', () => {
+  it('url-encodes POST bodies', () => {
+    const submit = encodeSubmission(form(), true);
+    expect(submit.method).toBe('POST');
+    expect(submit.url).toBe('https://shop.example.com/checkout?step=2');
+    expect(submit.body).toBe('email=ada%40example.com&note=a%26b+c');
+    expect(submit.cancelled).toBe(true);
+  });
+
+  it('puts GET fields in the query, replacing the action query', () => {
+    const submit = encodeSubmission(form({ method: 'get' }), false);
+    expect(submit.method).toBe('GET');
+    expect(submit.url).toBe(
+      'https://shop.example.com/checkout?email=ada%40example.com&note=a%26b+c'
+    );
+    expect(submit.body).toBeNull();
+    expect(submit.cancelled).toBe(false);
+  });
+
+  it('encodes text/plain and leaves multipart bodies out', () => {
+    expect(encodeSubmission(form({ enctype: 'text/plain' }), true).body).toBe(
+      'email=ada@example.com\r\nnote=a&b c\r\n'
+    );
+    const upload = encodeSubmission(
+      form({
+        enctype: 'multipart/form-data',
+        fields: [{ name: 'cv', file: { name: 'cv.pdf', size: 1024, type: 'application/pdf' } }],
+      }),
+      true
+    );
+    expect(upload.body).toBeNull();
+    expect(upload.fields).toEqual([
+      { name: 'cv', file: { name: 'cv.pdf', size: 1024, type: 'application/pdf' } },
+    ]);
+  });
+
+  it('treats dialog and unknown methods as GET', () => {
+    expect(encodeSubmission(form({ method: 'dialog' }), true).method).toBe('GET');
+  });
+});
@@ -0,0 +1,52 @@
+/**
+ * `click --intercept-submit`: what a form submission would send. The page
+ * reports the form's method, action, encoding and fields (with the button
+ * that submitted it); the body is encoded here the way the browser would.
+ */
+
+export type FormField =
+  | { name: string; value: string }
+  | { name: string; file: { name: string; size: number; type: string } };
+
+/** The submission as read in the page */
+export interface FormSubmission {
+  method: string;
+  action: string;
+  enctype: string;
+  target: string;
+  fields: FormField[];
+}
+
+export interface InterceptedSubmit extends FormSubmission {
+  /** The request URL: the action, with the fields as its query for GET */
+  url: string;
+  /** The request body, null for GET and for multipart (files, boundaries) */
+  body: string | null;
+  /** Whether the submission was stopped */
+  cancelled: boolean;
+}
+
+function fieldValue(field: FormField): string {
+  return 'value' in field ? field.value : field.file.name;
+}
+
+export function encodeSubmission(
+  submission: FormSubmission,
+  cancelled: boolean
+): InterceptedSubmit {
+  const method = submission.method.toUpperCase() === 'POST' ? 'POST' : 'GET';
+  const pairs = submission.fields.map((field) => [field.name, fieldValue(field)]);
+  const query = new URLSearchParams(pairs).toString();
+  let url = submission.action;
+  let body: string | null = null;
+  if (method === 'GET') {
+    const target = new URL(submission.action);
+    target.search = query;
+    url = target.href;
+  } else if (submission.enctype === 'text/plain') {
+    body = pairs.map(([name, value]) => `${name}=${value}\r\n`).join('');
+  } else if (submission.enctype !== 'multipart/form-data') {
+    body = query;
+  }
+  return { ...submission, method, url, body, cancelled };
+}
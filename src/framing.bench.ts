@@ -0,0 +1,27 @@
+import { randomBytes } from 'crypto';
+import { bench, describe } from 'vitest';
+import { BinaryData, encodeFrames } from './framing.js';
+import { serializeResponse, successResponse } from './protocol.js';
+
+// What the daemon does per response: a 3 MB screenshot and 2.6 MB of HTML,
+// as one JSON line and as frames. `pnpm bench` runs these.
+const png = randomBytes(3 * 1024 * 1024);
+const html = '<div class="row"><a href="/item?id=1&amp;x=2">Item "one"</a>\n</div>\n'.repeat(40000);
+
+describe('screenshot response', () => {
+  bench('JSON line', () => {
+    serializeResponse(successResponse('1', { base64: new BinaryData(png) }));
+  });
+  bench('frames', () => {
+    encodeFrames(successResponse('1', { base64: new BinaryData(png) }));
+  });
+});
+
+describe('HTML response', () => {
+  bench('JSON line', () => {
+    serializeResponse(successResponse('1', { html }));
+  });
+  bench('frames', () => {
+    encodeFrames(successResponse('1', { html }));
+  });
+});
@@ -0,0 +1,59 @@
+import { describe, it, expect } from 'vitest';
+import {
+  BinaryData,
+  BLOB_MIN_BYTES,
+  encodeFrames,
+  FRAME_BINARY,
+  FRAME_JSON,
+  FRAME_TEXT,
+} from './framing.js';
+import { serializeResponse, successResponse } from './protocol.js';
+
+/** The frames in a buffer, as the CLI reads them */
+function readFrames(buffer: Buffer) {
+  const frames: Array<{ kind: number; path?: unknown; payload: Buffer }> = [];
+  let offset = 0;
+  while (offset < buffer.length) {
+    const kind = buffer.readUInt8(offset);
+    const length = buffer.readUInt32BE(offset + 1);
+    let payload = buffer.subarray(offset + 5, offset + 5 + length);
+    offset += 5 + length;
+    if (kind === FRAME_JSON) {
+      frames.push({ kind, payload });
+      continue;
+    }
+    const pathLength = payload.readUInt16BE(0);
+    const path = JSON.parse(payload.subarray(2, 2 + pathLength).toString());
+    payload = payload.subarray(2 + pathLength);
+    frames.push({ kind, path, payload });
+  }
+  return frames;
+}
+
+describe('framing', () => {
+  it('sends a small response as a single JSON frame', () => {
+    const response = successResponse('1', { url: 'https://example.com' });
+    const frames = readFrames(encodeFrames(response));
+    expect(frames.length).toBe(1);
+    expect(frames[0].kind).toBe(FRAME_JSON);
+    expect(frames[0].payload.toString()).toBe(serializeResponse(response));
+  });
+
+  it('moves large strings and binary data into blob frames', () => {
+    const html = '<p>"quoted"</p>\n'.repeat(BLOB_MIN_BYTES);
+    const png = Buffer.from([0x89, 0x50, 0x4e, 0x47, 0x00, 0xff]);
+    const response = successResponse('1', { html, images: [new BinaryData(png)] });
+    const frames = readFrames(encodeFrames(response));
+    expect(frames.map((frame) => frame.kind)).toEqual([FRAME_TEXT, FRAME_BINARY, FRAME_JSON]);
+    expect(frames[0].path).toEqual(['data', 'html']);
+    expect(frames[0].payload.toString()).toBe(html);
+    expect(frames[1].path).toEqual(['data', 'images', 0]);
+    expect(frames[1].payload).toEqual(png);
+    expect(JSON.parse(frames[2].payload.toString()).data).toEqual({ html: null, images: [null] });
+  });
+
+  it('serializes binary data as base64 in JSON lines', () => {
+    const response = successResponse('1', { base64: new BinaryData(Buffer.from('png')) });
+    expect(JSON.parse(serializeResponse(response)).data.base64).toBe('cG5n');
+  });
+});
@@ -0,0 +1,75 @@
+/**
+ * Framed responses, the compact alternative to one JSON line per response.
+ * A client that sends FRAMES_HELLO as its first line gets every response on
+ * that connection as frames: `<kind: u8><length: u32 BE><payload>`. Strings of
+ * BLOB_MIN_BYTES or more, and binary data such as screenshots, travel as
+ * blob frames ahead of the JSON frame that ends the response, so they are
+ * neither base64-encoded nor JSON-escaped on the way. A blob payload starts
+ * with `<path length: u16 BE><path>`, the JSON array of keys and indexes
+ * where its value goes; the JSON frame has null there.
+ *
+ * Requests stay JSON lines. A daemon from before framing answers the hello
+ * with an error line, which tells the client to read lines instead.
+ */
+
+import type { Response } from './types.js';
+
+export const FRAMES_HELLO = 'AB-FRAMES 1';
+
+export const FRAME_JSON = 1;
+export const FRAME_BINARY = 2;
+export const FRAME_TEXT = 3;
+
+/** Strings shorter than this stay in the JSON frame */
+export const BLOB_MIN_BYTES = 16 * 1024;
+
+/**
+ * Bytes in a response. Serialized to JSON (line responses, the stream
+ * server) they become base64; in frames they are sent as they are.
+ */
+export class BinaryData {
+  constructor(readonly bytes: Buffer) {}
+
+  toJSON(): string {
+    return this.bytes.toString('base64');
+  }
+}
+
+function frame(kind: number, payload: Buffer): Buffer {
+  const header = Buffer.alloc(5);
+  header.writeUInt8(kind, 0);
+  header.writeUInt32BE(payload.length, 1);
+  return Buffer.concat([header, payload]);
+}
+
+function blobFrame(kind: number, path: Array<string | number>, bytes: Buffer): Buffer {
+  const location = Buffer.from(JSON.stringify(path));
+  const length = Buffer.alloc(2);
+  length.writeUInt16BE(location.length, 0);
+  return frame(kind, Buffer.concat([length, location, bytes]));
+}
+
+/** The response as frames: its blobs, then the JSON frame */
+export function encodeFrames(response: Response): Buffer {
+  const blobs: Buffer[] = [];
+  const strip = (value: unknown, path: Array<string | number>): unknown => {
+    if (value instanceof BinaryData) {
+      blobs.push(blobFrame(FRAME_BINARY, path, value.bytes));
+      return null;
+    }
+    if (typeof value === 'string') {
+      if (value.length < BLOB_MIN_BYTES) return value;
+      blobs.push(blobFrame(FRAME_TEXT, path, Buffer.from(value)));
+      return null;
+    }
+    if (Array.isArray(value)) return value.map((item, index) => strip(item, [...path, index]));
+    if (value && typeof value === 'object' && !('toJSON' in value)) {
+      const copy: Record<string, unknown> = {};
+      for (const [key, item] of Object.entries(value)) copy[key] = strip(item, [...path, key]);
+      return copy;
+    }
+    return value;
+  };
+  const json = JSON.stringify(strip(response, []));
+  return Buffer.concat([...blobs, frame(FRAME_JSON, Buffer.from(json))]);
+}
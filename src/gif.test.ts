@@ -0,0 +1,116 @@
+import { describe, it, expect } from 'vitest';
+import {
+  animationFormat,
+  decodePng,
+  encodeAnimation,
+  encodePng,
+  fitImage,
+  lzwEncode,
+  paletteIndex,
+  type RgbaImage,
+} from './gif.js';
+
+/** Reference GIF LZW decoder to check the encoder against */
+function lzwDecode(data: Buffer, minCodeSize: number): number[] {
+  const clearCode = 1 << minCodeSize;
+  let table: number[][] = [];
+  let codeSize = 0;
+  const reset = () => {
+    table = Array.from({ length: clearCode + 2 }, (_, i) => [i]);
+    codeSize = minCodeSize + 1;
+  };
+  reset();
+  const out: number[] = [];
+  let bit = 0;
+  let prev: number[] | null = null;
+  for (;;) {
+    let code = 0;
+    for (let i = 0; i < codeSize; i++, bit++) code |= ((data[bit >> 3] >> (bit & 7)) & 1) << i;
+    if (code === clearCode) {
+      reset();
+      prev = null;
+      continue;
+    }
+    if (code === clearCode + 1) return out;
+    const entry: number[] = code < table.length ? table[code] : [...prev!, prev![0]];
+    out.push(...entry);
+    if (prev && table.length < 4096) table.push([...prev, entry[0]]);
+    prev = entry;
+    if (table.length === 1 << codeSize && codeSize < 12) codeSize++;
+  }
+}
+
+function gradient(width: number, height: number): RgbaImage {
+  const pixels = new Uint8Array(width * height * 4);
+  for (let i = 0; i < width * height; i++) {
+    pixels.set([i % 256, (i * 7) % 256, (i * 13) % 256, 255], i * 4);
+  }
+  return { width, height, pixels };
+}
+
+describe('gif', () => {
+  it('picks the format from the output extension', () => {
+    expect(animationFormat('repro.gif')).toBe('gif');
+    expect(animationFormat('repro.APNG')).toBe('apng');
+    expect(animationFormat('repro.png')).toBe('apng');
+    expect(animationFormat('repro.webm')).toBeNull();
+  });
+
+  it('round-trips PNGs', () => {
+    const image = gradient(37, 21);
+    const decoded = decodePng(encodePng(image));
+    expect(decoded.width).toBe(37);
+    expect(decoded.height).toBe(21);
+    expect(Buffer.from(decoded.pixels).equals(Buffer.from(image.pixels))).toBe(true);
+  });
+
+  it('LZW-encodes data a decoder reads back, across table resets', () => {
+    for (const length of [1, 2, 100, 200_000]) {
+      const indices = new Uint8Array(length);
+      let seed = 1;
+      for (let i = 0; i < length; i++) {
+        seed = (seed * 1103515245 + 12345) & 0x7fffffff;
+        indices[i] = i % 300 < 150 ? (seed >> 16) & 0xff : 7;
+      }
+      expect(lzwDecode(lzwEncode(indices, 8), 8)).toEqual(Array.from(indices));
+    }
+  });
+
+  it('maps colors to the fixed palette', () => {
+    expect(paletteIndex(255, 255, 255)).toBe(255);
+    expect(paletteIndex(0, 0, 0)).toBe(216);
+    expect(paletteIndex(255, 0, 0)).toBe(5 * 36);
+    expect(paletteIndex(0, 102, 204)).toBe(2 * 6 + 4);
+  });
+
+  it('crops and pads frames to the first frame size', () => {
+    const fitted = fitImage(gradient(4, 2), 2, 3);
+    expect(fitted.width).toBe(2);
+    expect(Array.from(fitted.pixels.subarray(0, 8))).toEqual([0, 0, 0, 255, 1, 7, 13, 255]);
+    expect(Array.from(fitted.pixels.subarray(16))).toEqual(new Array(8).fill(255));
+  });
+
+  it('assembles a looping GIF', () => {
+    const frames = [encodePng(gradient(8, 8)), encodePng(gradient(4, 4))];
+    const gif = encodeAnimation(frames, 'gif', 500);
+    expect(gif.toString('latin1', 0, 6)).toBe('GIF89a');
+    expect(gif.readUInt16LE(6)).toBe(8);
+    expect(gif.includes(Buffer.from('NETSCAPE2.0'))).toBe(true);
+    // One graphic control block per frame, 50cs each
+    const control = Buffer.from([0x21, 0xf9, 0x04, 0x04, 50, 0]);
+    expect(gif.indexOf(control, gif.indexOf(control) + 1)).toBeGreaterThan(0);
+    expect(gif[gif.length - 1]).toBe(0x3b);
+  });
+
+  it('assembles an APNG whose first frame is the still image', () => {
+    const frame = encodePng(gradient(8, 8));
+    const apng = encodeAnimation([frame, frame], 'apng', 500);
+    expect(apng.includes(Buffer.from('acTL'))).toBe(true);
+    expect(apng.includes(Buffer.from('fdAT'))).toBe(true);
+    expect(decodePng(apng).width).toBe(8);
+  });
+
+  it('refuses to write an empty animation', () => {
+    expect(() => encodeAnimation([], 'gif', 500)).toThrow('No frames');
+  });
+});
@@ -0,0 +1,345 @@
+import { deflateSync, inflateSync } from 'node:zlib';
+
+/**
+ * Animated GIF and APNG export for `record gif`: one PNG screenshot per
+ * command, assembled into a looping animation when the capture ends.
+ * Built on node's zlib alone so the daemon needs no image library.
+ */
+export type AnimationFormat = 'gif' | 'apng';
+
+/** Frames to capture when `record gif` isn't given --commands */
+export const DEFAULT_GIF_COMMANDS = 10;
+
+/** How long each frame is shown when --delay isn't given */
+export const DEFAULT_GIF_DELAY_MS = 800;
+
+export interface RgbaImage {
+  width: number;
+  height: number;
+  /** 4 bytes per pixel, rows top to bottom */
+  pixels: Uint8Array;
+}
+
+/** The animation format an output path asks for, from its extension */
+export function animationFormat(path: string): AnimationFormat | null {
+  const lower = path.toLowerCase();
+  if (lower.endsWith('.gif')) return 'gif';
+  if (lower.endsWith('.png') || lower.endsWith('.apng')) return 'apng';
+  return null;
+}
+
+const PNG_SIGNATURE = Buffer.from([0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a]);
+
+const CRC_TABLE = (() => {
+  const table = new Uint32Array(256);
+  for (let n = 0; n < 256; n++) {
+    let c = n;
+    for (let k = 0; k < 8; k++) c = c & 1 ? 0xedb88320 ^ (c >>> 1) : c >>> 1;
+    table[n] = c >>> 0;
+  }
+  return table;
+})();
+
+function crc32(bytes: Uint8Array): number {
+  let crc = 0xffffffff;
+  for (const byte of bytes) crc = CRC_TABLE[(crc ^ byte) & 0xff] ^ (crc >>> 8);
+  return (crc ^ 0xffffffff) >>> 0;
+}
+
+function pngChunk(type: string, data: Uint8Array): Buffer {
+  const body = Buffer.concat([Buffer.from(type, 'latin1'), data]);
+  const chunk = Buffer.alloc(body.length + 8);
+  chunk.writeUInt32BE(data.length, 0);
+  body.copy(chunk, 4);
+  chunk.writeUInt32BE(crc32(body), body.length + 4);
+  return chunk;
+}
+
+/** Paeth predictor from the PNG spec */
+function paeth(a: number, b: number, c: number): number {
+  const p = a + b - c;
+  const pa = Math.abs(p - a);
+  const pb = Math.abs(p - b);
+  const pc = Math.abs(p - c);
+  if (pa <= pb && pa <= pc) return a;
+  return pb <= pc ? b : c;
+}
+
+/**
+ * Decode an 8-bit, non-interlaced PNG (what Chromium's screenshots are) to
+ * RGBA pixels
+ */
+export function decodePng(png: Buffer): RgbaImage {
+  if (png.length < 8 || !png.subarray(0, 8).equals(PNG_SIGNATURE)) {
+    throw new Error('Not a PNG image');
+  }
+  let width = 0;
+  let height = 0;
+  let channels = 0;
+  const idat: Buffer[] = [];
+  for (let offset = 8; offset + 8 <= png.length; ) {
+    const length = png.readUInt32BE(offset);
+    const type = png.toString('latin1', offset + 4, offset + 8);
+    const data = png.subarray(offset + 8, offset + 8 + length);
+    if (type === 'IHDR') {
+      width = data.readUInt32BE(0);
+      height = data.readUInt32BE(4);
+      const [bitDepth, colorType, , , interlace] = data.subarray(8, 13);
+      channels = ({ 0: 1, 2: 3, 4: 2, 6: 4 } as Record<number, number>)[colorType] ?? 0;
+      if (bitDepth !== 8 || channels === 0 || interlace !== 0) {
+        throw new Error(`Unsupported PNG (bit depth ${bitDepth}, color type ${colorType})`);
+      }
+    } else if (type === 'IDAT') {
+      idat.push(data);
+    } else if (type === 'IEND') {
+      break;
+    }
+    offset += length + 12;
+  }
+  if (width === 0 || height === 0) throw new Error('PNG has no image header');
+
+  const raw = inflateSync(Buffer.concat(idat));
+  const stride = width * channels;
+  const rows = new Uint8Array(stride * height);
+  for (let y = 0; y < height; y++) {
+    const filter = raw[y * (stride + 1)];
+    const line = raw.subarray(y * (stride + 1) + 1, (y + 1) * (stride + 1));
+    const out = y * stride;
+    for (let x = 0; x < stride; x++) {
+      const left = x >= channels ? rows[out + x - channels] : 0;
+      const up = y > 0 ? rows[out - stride + x] : 0;
+      const upLeft = y > 0 && x >= channels ? rows[out - stride + x - channels] : 0;
+      const predictor = [0, left, up, (left + up) >> 1, paeth(left, up, upLeft)][filter];
+      if (predictor === undefined) throw new Error(`Bad PNG filter type ${filter}`);
+      rows[out + x] = (line[x] + predictor) & 0xff;
+    }
+  }
+
+  const pixels = new Uint8Array(width * height * 4);
+  for (let i = 0; i < width * height; i++) {
+    const p = rows.subarray(i * channels, (i + 1) * channels);
+    const [r, g, b, a] =
+      channels === 1
+        ? [p[0], p[0], p[0], 255]
+        : channels === 2
+          ? [p[0], p[0], p[0], p[1]]
+          : [p[0], p[1], p[2], channels === 4 ? p[3] : 255];
+    pixels.set([r, g, b, a], i * 4);
+  }
+  return { width, height, pixels };
+}
+
+/**
+ * An image on a canvas of another size: cropped where it's larger, padded
+ * with white where it's smaller. Frames taken across a viewport change still
+ * share the first frame's size.
+ */
+export function fitImage(image: RgbaImage, width: number, height: number): RgbaImage {
+  if (image.width === width && image.height === height) return image;
+  const pixels = new Uint8Array(width * height * 4).fill(255);
+  const copyWidth = Math.min(width, image.width) * 4;
+  for (let y = 0; y < Math.min(height, image.height); y++) {
+    const from = y * image.width * 4;
+    pixels.set(image.pixels.subarray(from, from + copyWidth), y * width * 4);
+  }
+  return { width, height, pixels };
+}
+
+/** Scanlines of an RGBA image, each with filter type 0, deflated for IDAT/fdAT */
+function compressRows(image: RgbaImage): Buffer {
+  const stride = image.width * 4;
+  const raw = Buffer.alloc((stride + 1) * image.height);
+  for (let y = 0; y < image.height; y++) {
+    raw.set(image.pixels.subarray(y * stride, (y + 1) * stride), y * (stride + 1) + 1);
+  }
+  return deflateSync(raw);
+}
+
+function header(width: number, height: number): Buffer {
+  const ihdr = Buffer.alloc(13);
+  ihdr.writeUInt32BE(width, 0);
+  ihdr.writeUInt32BE(height, 4);
+  ihdr[8] = 8; // bit depth
+  ihdr[9] = 6; // RGBA
+  return pngChunk('IHDR', ihdr);
+}
+
+/** A still RGBA PNG */
+export function encodePng(image: RgbaImage): Buffer {
+  return Buffer.concat([
+    PNG_SIGNATURE,
+    header(image.width, image.height),
+    pngChunk('IDAT', compressRows(image)),
+    pngChunk('IEND', Buffer.alloc(0)),
+  ]);
+}
+
+/** A looping APNG showing each frame for `delayMs` */
+export function encodeApng(frames: RgbaImage[], delayMs: number): Buffer {
+  const { width, height } = frames[0];
+  const actl = Buffer.alloc(8);
+  actl.writeUInt32BE(frames.length, 0); // frames; 0 plays = loop forever
+  const chunks = [PNG_SIGNATURE, header(width, height), pngChunk('acTL', actl)];
+  let sequence = 0;
+  for (const [index, frame] of frames.entries()) {
+    const fctl = Buffer.alloc(26);
+    fctl.writeUInt32BE(sequence++, 0);
+    fctl.writeUInt32BE(width, 4);
+    fctl.writeUInt32BE(height, 8);
+    fctl.writeUInt16BE(delayMs, 20);
+    fctl.writeUInt16BE(1000, 22);
+    chunks.push(pngChunk('fcTL', fctl));
+    const data = compressRows(fitImage(frame, width, height));
+    if (index === 0) {
+      chunks.push(pngChunk('IDAT', data));
+    } else {
+      const fdat = Buffer.alloc(data.length + 4);
+      fdat.writeUInt32BE(sequence++, 0);
+      data.copy(fdat, 4);
+      chunks.push(pngChunk('fdAT', fdat));
+    }
+  }
+  chunks.push(pngChunk('IEND', Buffer.alloc(0)));
+  return Buffer.concat(chunks);
+}
+
+/**
+ * The GIF palette: a 6x6x6 color cube plus 40 grays. Fixed rather than
+ * per-frame so frames need no local tables; pages are mostly flat color and
+ * text on gray-ish backgrounds, which the gray ramp keeps smooth.
+ */
+export const GIF_PALETTE: Uint8Array = (() => {
+  const palette = new Uint8Array(256 * 3);
+  for (let i = 0; i < 216; i++) {
+    palette.set([Math.floor(i / 36) * 51, (Math.floor(i / 6) % 6) * 51, (i % 6) * 51], i * 3);
+  }
+  for (let i = 0; i < 40; i++) {
+    const v = Math.round((i * 255) / 39);
+    palette.set([v, v, v], (216 + i) * 3);
+  }
+  return palette;
+})();
+
+/** Palette index of a color: the gray ramp for near-grays, the cube otherwise */
+export function paletteIndex(r: number, g: number, b: number): number {
+  if (Math.max(r, g, b) - Math.min(r, g, b) <= 12) {
+    return 216 + Math.round(((r + g + b) / 3) * (39 / 255));
+  }
+  return Math.round(r / 51) * 36 + Math.round(g / 51) * 6 + Math.round(b / 51);
+}
+
+/** GIF's variable-width LZW, starting at `minCodeSize + 1` bits */
+export function lzwEncode(indices: Uint8Array, minCodeSize: number): Buffer {
+  const clearCode = 1 << minCodeSize;
+  const endCode = clearCode + 1;
+  const out: number[] = [];
+  let codeSize = minCodeSize + 1;
+  let nextCode = endCode + 1;
+  let table = new Map<number, number>();
+  let bits = 0;
+  let bitCount = 0;
+  const emit = (code: number) => {
+    bits |= code << bitCount;
+    bitCount += codeSize;
+    while (bitCount >= 8) {
+      out.push(bits & 0xff);
+      bits >>>= 8;
+      bitCount -= 8;
+    }
+  };
+
+  emit(clearCode);
+  let prefix = indices[0];
+  for (let i = 1; i < indices.length; i++) {
+    const key = prefix * 256 + indices[i];
+    const code = table.get(key);
+    if (code !== undefined) {
+      prefix = code;
+      continue;
+    }
+    emit(prefix);
+    if (nextCode < 4096) {
+      if (nextCode >= 1 << codeSize) codeSize++;
+      table.set(key, nextCode++);
+    } else {
+      // Table full: start over rather than keep coding with stale entries
+      emit(clearCode);
+      table = new Map();
+      codeSize = minCodeSize + 1;
+      nextCode = endCode + 1;
+    }
+    prefix = indices[i];
+  }
+  emit(prefix);
+  emit(endCode);
+  if (bitCount > 0) out.push(bits & 0xff);
+  return Buffer.from(out);
+}
+
+/** Data split into the length-prefixed sub-blocks GIF stores it in */
+function subBlocks(data: Buffer): Buffer {
+  const blocks: Buffer[] = [];
+  for (let offset = 0; offset < data.length; offset += 255) {
+    const block = data.subarray(offset, offset + 255);
+    blocks.push(Buffer.from([block.length]), block);
+  }
+  blocks.push(Buffer.from([0]));
+  return Buffer.concat(blocks);
+}
+
+function u16(value: number): Buffer {
+  const buf = Buffer.alloc(2);
+  buf.writeUInt16LE(value);
+  return buf;
+}
+
+/** A looping GIF showing each frame for `delayMs` (rounded to GIF's 10ms steps) */
+export function encodeGif(frames: RgbaImage[], delayMs: number): Buffer {
+  const { width, height } = frames[0];
+  const parts: Buffer[] = [
+    Buffer.from('GIF89a', 'latin1'),
+    u16(width),
+    u16(height),
+    // Global color table of 256 entries, 8 bits per primary
+    Buffer.from([0xf7, 0, 0]),
+    Buffer.from(GIF_PALETTE),
+    // Loop forever
+    Buffer.from([0x21, 0xff, 0x0b]),
+    Buffer.from('NETSCAPE2.0', 'latin1'),
+    Buffer.from([0x03, 0x01, 0x00, 0x00, 0x00]),
+  ];
+  const delay = Math.max(1, Math.round(delayMs / 10));
+  for (const frame of frames) {
+    const { pixels } = fitImage(frame, width, height);
+    const indices = new Uint8Array(width * height);
+    for (let i = 0; i < indices.length; i++) {
+      indices[i] = paletteIndex(pixels[i * 4], pixels[i * 4 + 1], pixels[i * 4 + 2]);
+    }
+    parts.push(
+      // Graphic control: keep the frame in place, no transparency
+      Buffer.from([0x21, 0xf9, 0x04, 0x04]),
+      u16(delay),
+      Buffer.from([0x00, 0x00]),
+      Buffer.from([0x2c]),
+      u16(0),
+      u16(0),
+      u16(width),
+      u16(height),
+      Buffer.from([0x00, 8]),
+      subBlocks(lzwEncode(indices, 8))
+    );
+  }
+  parts.push(Buffer.from([0x3b]));
+  return Buffer.concat(parts);
+}
+
+/** Screenshots (PNG) assembled into an animation */
+export function encodeAnimation(
+  screenshots: Buffer[],
+  format: AnimationFormat,
+  delayMs: number
+): Buffer {
+  if (screenshots.length === 0) throw new Error('No frames were captured');
+  const frames = screenshots.map(decodePng);
+  return format === 'gif' ? encodeGif(frames, delayMs) : encodeApng(frames, delayMs);
+}
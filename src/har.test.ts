@@ -0,0 +1,65 @@
+import { describe, it, expect } from 'vitest';
+import * as fs from 'fs';
+import * as os from 'os';
+import * as path from 'path';
+import { loadHar, findHarEntry, harResponse } from './har.js';
+
+const entry = (method: string, url: string, body: string, postData?: string) => ({
+  request: { method, url, postData: postData ? { text: postData } : undefined },
+  response: {
+    status: 200,
+    headers: [
+      { name: 'Content-Type', value: 'application/json' },
+      { name: 'Content-Length', value: '999' },
+      { name: 'Set-Cookie', value: 'a=1' },
+      { name: 'set-cookie', value: 'b=2' },
+    ],
+    content: { text: body },
+  },
+});
+
+describe('har', () => {
+  const entries = [
+    entry('GET', 'https://app.test/api/items?page=1', '[1]'),
+    entry('POST', 'https://app.test/api/search', '{"q":"a"}', '{"q":"a"}'),
+    entry('POST', 'https://app.test/api/search', '{"q":"b"}', '{"q":"b"}'),
+  ];
+
+  it('matches method and URL, preferring the same request body', () => {
+    expect(findHarEntry(entries, 'get', 'https://app.test/api/items?page=1#top')).toBe(entries[0]);
+    expect(findHarEntry(entries, 'GET', 'https://app.test/api/items?page=2')).toBeUndefined();
+    expect(findHarEntry(entries, 'POST', 'https://app.test/api/search', '{"q":"b"}')).toBe(
+      entries[2]
+    );
+    expect(findHarEntry(entries, 'POST', 'https://app.test/api/search', '{"q":"c"}')).toBe(
+      entries[1]
+    );
+  });
+
+  it('builds the replayed response', () => {
+    const response = harResponse(entries[0]);
+    expect(response.headers['content-length']).toBeUndefined();
+    expect(response.headers['set-cookie']).toBe('a=1, b=2');
+    expect(response.body.toString()).toBe('[1]');
+
+    const image = {
+      ...entries[0],
+      response: {
+        status: 200,
+        content: { text: 'aGk=', encoding: 'base64', mimeType: 'image/png' },
+      },
+    };
+    expect(harResponse(image).body.toString()).toBe('hi');
+    expect(harResponse(image).headers['content-type']).toBe('image/png');
+  });
+
+  it('loads entries and rejects other JSON', () => {
+    const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'har-test-'));
+    const file = path.join(dir, 'session.har');
+    fs.writeFileSync(file, JSON.stringify({ log: { entries } }));
+    expect(loadHar(file)).toHaveLength(3);
+    fs.writeFileSync(file, '{}');
+    expect(() => loadHar(file)).toThrow('not a HAR file');
+    fs.rmSync(dir, { recursive: true });
+  });
+});
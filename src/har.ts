@@ -0,0 +1,93 @@
+import * as fs from 'fs';
+
+/** Response headers that no longer describe the body once it is replayed */
+const DROPPED_HEADERS = new Set(['content-length', 'content-encoding', 'transfer-encoding']);
+
+interface HarHeader {
+  name: string;
+  value: string;
+}
+
+interface HarEntry {
+  request: {
+    method: string;
+    url: string;
+    postData?: { text?: string };
+  };
+  response: {
+    status: number;
+    headers?: HarHeader[];
+    content?: { text?: string; encoding?: string; mimeType?: string };
+  };
+}
+
+export interface HarResponse {
+  status: number;
+  headers: Record<string, string>;
+  body: Buffer;
+}
+
+/**
+ * Read the entries of a HAR file
+ */
+export function loadHar(filePath: string): HarEntry[] {
+  let har: { log?: { entries?: HarEntry[] } };
+  try {
+    har = JSON.parse(fs.readFileSync(filePath, 'utf-8'));
+  } catch (err) {
+    throw new Error(`Cannot read HAR ${filePath}: ${err instanceof Error ? err.message : err}`);
+  }
+  const entries = har.log?.entries;
+  if (!Array.isArray(entries)) {
+    throw new Error(`${filePath} is not a HAR file (no log.entries)`);
+  }
+  return entries.filter((e) => e?.request?.url && e?.response);
+}
+
+function stripFragment(url: string): string {
+  const hash = url.indexOf('#');
+  return hash === -1 ? url : url.slice(0, hash);
+}
+
+/**
+ * Find the recorded entry for a request. The method and URL must match; when
+ * several entries do, one with the same request body wins, otherwise the
+ * first recorded.
+ */
+export function findHarEntry(
+  entries: HarEntry[],
+  method: string,
+  url: string,
+  postData?: string | null
+): HarEntry | undefined {
+  const target = stripFragment(url);
+  const candidates = entries.filter(
+    (e) =>
+      e.request.method.toUpperCase() === method.toUpperCase() &&
+      stripFragment(e.request.url) === target
+  );
+  if (postData) {
+    const sameBody = candidates.find((e) => e.request.postData?.text === postData);
+    if (sameBody) return sameBody;
+  }
+  return candidates[0];
+}
+
+/**
+ * The response to fulfill a request with
+ */
+export function harResponse(entry: HarEntry): HarResponse {
+  const headers: Record<string, string> = {};
+  for (const { name, value } of entry.response.headers ?? []) {
+    const key = name.toLowerCase();
+    // HTTP/2 pseudo-headers (":status") can't be sent back
+    if (DROPPED_HEADERS.has(key) || key.startsWith(':')) continue;
+    headers[key] = headers[key] ? `${headers[key]}, ${value}` : value;
+  }
+  const content = entry.response.content ?? {};
+  if (content.mimeType && !headers['content-type']) {
+    headers['content-type'] = content.mimeType;
+  }
+  const body = Buffer.from(content.text ?? '', content.encoding === 'base64' ? 'base64' : 'utf-8');
+  return { status: entry.response.status, headers, body };
+}
@@ -0,0 +1,22 @@
+import { describe, it, expect } from 'vitest';
+import { headlessLaunch } from './headless.js';
+
+describe('headless', () => {
+  it('launches the full browser for new headless', () => {
+    expect(headlessLaunch('new', 131, false)).toEqual({
+      args: ['--headless=new'],
+      channel: 'chromium',
+    });
+    expect(headlessLaunch('new', null, true).channel).toBeUndefined();
+    expect(() => headlessLaunch('new', 100, true)).toThrow('Chromium 109 or later');
+  });
+
+  it('falls back to the headless shell once old headless is gone', () => {
+    expect(headlessLaunch('old', 120, true)).toEqual({ args: ['--headless=old'] });
+    const fallback = headlessLaunch('old', 140, false);
+    expect(fallback.args).toEqual([]);
+    expect(fallback.warning).toContain('chrome-headless-shell');
+    expect(() => headlessLaunch('old', 140, true)).toThrow('removed in Chromium 132');
+    expect(headlessLaunch('shell', 140, false)).toEqual({ args: [] });
+  });
+});
@@ -0,0 +1,78 @@
+import { execFileSync } from 'child_process';
+
+/**
+ * Chromium headless implementations (--headless-mode):
+ * - new: the full browser without a window, same code paths as headed Chrome
+ * - old: the separate legacy implementation, removed from Chrome in 132
+ * - shell: chrome-headless-shell, the old implementation shipped on its own
+ */
+export type HeadlessMode = 'new' | 'old' | 'shell';
+
+/** First Chromium where --headless=new exists */
+const NEW_HEADLESS_SINCE = 109;
+/** First Chromium without --headless=old */
+const OLD_HEADLESS_REMOVED = 132;
+
+/**
+ * Major version of a Chromium executable, from `--version`
+ * ("Chromium 131.0.6778.33", "Google Chrome 120.0.6099.71")
+ */
+export function chromiumMajorVersion(executablePath: string): number | null {
+  try {
+    const output = execFileSync(executablePath, ['--version'], {
+      encoding: 'utf-8',
+      timeout: 10_000,
+      stdio: ['ignore', 'pipe', 'ignore'],
+    });
+    const match = /(\d+)\.\d+\.\d+/.exec(output);
+    return match ? Number(match[1]) : null;
+  } catch {
+    return null;
+  }
+}
+
+export interface HeadlessLaunch {
+  /** Switches appended after Playwright's own --headless */
+  args: string[];
+  /** Launch Playwright's full Chromium instead of its headless shell */
+  channel?: 'chromium';
+  warning?: string;
+}
+
+/**
+ * How to launch for a headless mode. `version` is the major version of the
+ * browser that would be launched (null when unknown) and `customExecutable`
+ * whether the user picked the binary, in which case it's launched as-is.
+ */
+export function headlessLaunch(
+  mode: HeadlessMode,
+  version: number | null,
+  customExecutable: boolean
+): HeadlessLaunch {
+  // Playwright's default headless binary
+  if (mode === 'shell') return { args: [] };
+
+  if (mode === 'new') {
+    if (version !== null && version < NEW_HEADLESS_SINCE) {
+      throw new Error(
+        `--headless-mode new needs Chromium ${NEW_HEADLESS_SINCE} or later (found ${version})`
+      );
+    }
+    return { args: ['--headless=new'], channel: customExecutable ? undefined : 'chromium' };
+  }
+
+  if (version !== null && version >= OLD_HEADLESS_REMOVED) {
+    if (customExecutable) {
+      throw new Error(
+        `--headless-mode old was removed in Chromium ${OLD_HEADLESS_REMOVED} (found ${version}); ` +
+          'use --headless-mode shell or an older --executable-path'
+      );
+    }
+    // The old implementation lives on as the headless shell
+    return {
+      args: [],
+      warning: `Chromium ${version} has no old headless mode; using chrome-headless-shell`,
+    };
+  }
+  return { args: ['--headless=old'] };
+}
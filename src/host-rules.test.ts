@@ -0,0 +1,46 @@
+import { describe, it, expect } from 'vitest';
+import {
+  HostRules,
+  hostBlockedResponse,
+  hostMatches,
+  parseHostList,
+  urlHost,
+} from './host-rules.js';
+
+describe('host rules', () => {
+  it('parses comma separated host lists', () => {
+    expect(parseHostList(' Docs.rs, *.mycorp.com,,')).toEqual(['docs.rs', '*.mycorp.com']);
+    expect(parseHostList(undefined)).toEqual([]);
+  });
+
+  it('matches wildcard patterns against subdomains only', () => {
+    expect(hostMatches('*.mycorp.com', 'a.b.mycorp.com')).toBe(true);
+    expect(hostMatches('*.mycorp.com', 'mycorp.com')).toBe(false);
+    expect(hostMatches('*.mycorp.com', 'evilmycorp.com')).toBe(false);
+    expect(hostMatches('docs.rs', 'sub.docs.rs')).toBe(false);
+  });
+
+  it('reads the host of network URLs only', () => {
+    expect(urlHost('http://user:pw@App.mycorp.com:8080/x')).toBe('app.mycorp.com');
+    expect(urlHost('http://[::1]:3000/')).toBe('::1');
+    expect(urlHost('data:text/html,hi')).toBeNull();
+    expect(urlHost('about:blank')).toBeNull();
+  });
+
+  it('lets blocked hosts win over allowed ones', () => {
+    const rules = new HostRules(['*.mycorp.com'], ['admin.mycorp.com']);
+    expect(rules.check('https://app.mycorp.com/')).toBeNull();
+    expect(rules.check('https://admin.mycorp.com/')?.reason).toBe(
+      "admin.mycorp.com matches --block-hosts rule 'admin.mycorp.com'"
+    );
+    expect(rules.check('https://example.com/')?.reason).toBe('example.com is not in --allow-hosts');
+    expect(rules.check('about:blank')).toBeNull();
+  });
+
+  it('reports blocked navigations with the host', () => {
+    const violation = { host: 'x.com', reason: 'x.com is not in --allow-hosts' };
+    const response = hostBlockedResponse('1', violation);
+    expect(response.error).toBe('Navigation blocked: x.com is not in --allow-hosts');
+    expect(response.data).toEqual({ type: 'host_blocked', host: 'x.com' });
+  });
+});
@@ -0,0 +1,83 @@
+import type { ErrorResponse } from './types.js';
+
+export interface HostViolation {
+  host: string;
+  reason: string;
+}
+
+/** Split a comma separated host list (AGENT_BROWSER_ALLOW_HOSTS), lowercased */
+export function parseHostList(list: string | undefined): string[] {
+  return (list ?? '')
+    .split(',')
+    .map((host) => host.trim().toLowerCase())
+    .filter((host) => host.length > 0);
+}
+
+/**
+ * `*.example.com` matches any subdomain of example.com (but not example.com
+ * itself); other patterns match the host exactly
+ */
+export function hostMatches(pattern: string, host: string): boolean {
+  if (pattern.startsWith('*.')) {
+    const domain = pattern.slice(2);
+    return host.length > domain.length && host.endsWith(`.${domain}`);
+  }
+  return host === pattern;
+}
+
+/** Lowercased host of an http(s) or ws(s) URL */
+export function urlHost(url: string): string | null {
+  let parsed: URL;
+  try {
+    parsed = new URL(url);
+  } catch {
+    return null;
+  }
+  if (!['http:', 'https:', 'ws:', 'wss:'].includes(parsed.protocol)) return null;
+  // IPv6 literals keep their brackets in URL.hostname
+  return parsed.hostname.replace(/^\[(.*)\]$/, '$1') || null;
+}
+
+/**
+ * --allow-hosts / --block-hosts, the same rules the CLI checks navigation URLs
+ * against, applied by the daemon to every request the browser makes
+ */
+export class HostRules {
+  constructor(
+    public allow: string[],
+    public block: string[]
+  ) {}
+
+  isEmpty(): boolean {
+    return this.allow.length === 0 && this.block.length === 0;
+  }
+
+  /**
+   * Blocked hosts win over allowed ones, and URLs without a network host
+   * (about:, data:, blob:) are always allowed
+   */
+  check(url: string): HostViolation | null {
+    const host = urlHost(url);
+    if (!host) return null;
+    const rule = this.block.find((pattern) => hostMatches(pattern, host));
+    if (rule) {
+      return { host, reason: `${host} matches --block-hosts rule '${rule}'` };
+    }
+    if (this.allow.length > 0 && !this.allow.some((pattern) => hostMatches(pattern, host))) {
+      return { host, reason: `${host} is not in --allow-hosts` };
+    }
+    return null;
+  }
+}
+
+/**
+ * Error response for a navigation the host rules refused
+ */
+export function hostBlockedResponse(id: string, violation: HostViolation): ErrorResponse {
+  return {
+    id,
+    success: false,
+    error: `Navigation blocked: ${violation.reason}`,
+    data: { type: 'host_blocked', host: violation.host },
+  };
+}
@@ -0,0 +1,43 @@
+import { describe, it, expect } from 'vitest';
+import { contextsToClose, tabsToClose, type JanitorTab } from './janitor.js';
+
+const tab = (overrides: Partial<JanitorTab>): JanitorTab => ({
+  url: 'https://example.com/',
+  active: false,
+  tracked: true,
+  openerClosed: false,
+  idleMs: 120_000,
+  ...overrides,
+});
+
+describe('janitor', () => {
+  it('closes idle blank tabs and orphaned popups, never the active tab', () => {
+    const tabs = [
+      tab({ url: 'about:blank', active: true }),
+      tab({ url: 'about:blank' }),
+      tab({ url: 'about:blank', idleMs: 5_000 }),
+      tab({ tracked: false }),
+      tab({ openerClosed: true }),
+      tab({}),
+    ];
+    const closing = tabsToClose(tabs, ['blank', 'popups']);
+    expect(closing.map(({ index, kind }) => [index, kind])).toEqual([
+      [1, 'blank-tab'],
+      [3, 'popup'],
+      [4, 'popup'],
+    ]);
+    expect(tabsToClose(tabs, ['blank']).map(({ index }) => index)).toEqual([1]);
+    expect(tabsToClose(tabs, ['popups'])[0].reason).toBe('not in the tab list, idle 120s');
+  });
+
+  it('closes extra contexts once they have been empty for the grace period', () => {
+    const contexts = [
+      { pages: 0, primary: true, idleMs: 120_000 },
+      { pages: 0, primary: false, idleMs: 120_000 },
+      { pages: 0, primary: false, idleMs: 1_000 },
+      { pages: 2, primary: false, idleMs: 0 },
+    ];
+    expect(contextsToClose(contexts, ['contexts'])).toEqual([1]);
+    expect(contextsToClose(contexts, ['blank', 'popups'])).toEqual([]);
+  });
+});
@@ -0,0 +1,73 @@
+/**
+ * What the janitor (--janitor) cleans up in long-running sessions:
+ * - blank: about:blank tabs left behind by failed navigations and new tabs
+ * - popups: windows the page opened whose opener has closed, or that never
+ *   made it into the tab list
+ * - contexts: extra windows (window new) whose pages are all closed
+ */
+export type JanitorRule = 'blank' | 'popups' | 'contexts';
+
+export const JANITOR_RULES: JanitorRule[] = ['blank', 'popups', 'contexts'];
+
+/** How often the janitor sweeps */
+export const JANITOR_INTERVAL_MS = 30_000;
+
+/** How long something must sit unused before it is closed */
+export const JANITOR_GRACE_MS = 60_000;
+
+export interface JanitorTab {
+  url: string;
+  active: boolean;
+  /** In the session's tab list; pages the site opened may never be added */
+  tracked: boolean;
+  /** Opened by another page that has since closed */
+  openerClosed: boolean;
+  idleMs: number;
+}
+
+export interface JanitorContext {
+  pages: number;
+  /** The session's main context, or the one a recording runs in */
+  primary: boolean;
+  idleMs: number;
+}
+
+export interface CleanupEntry {
+  time: string;
+  kind: 'blank-tab' | 'popup' | 'context';
+  url?: string;
+  reason: string;
+}
+
+/** Tabs the rules close, with the entry to log for each */
+export function tabsToClose(
+  tabs: JanitorTab[],
+  rules: JanitorRule[],
+  grace: number = JANITOR_GRACE_MS
+): { index: number; kind: CleanupEntry['kind']; reason: string }[] {
+  const closing: { index: number; kind: CleanupEntry['kind']; reason: string }[] = [];
+  for (const [index, tab] of tabs.entries()) {
+    if (tab.active || tab.idleMs < grace) continue;
+    const idle = `idle ${Math.round(tab.idleMs / 1000)}s`;
+    if (rules.includes('popups') && !tab.tracked) {
+      closing.push({ index, kind: 'popup', reason: `not in the tab list, ${idle}` });
+    } else if (rules.includes('popups') && tab.openerClosed) {
+      closing.push({ index, kind: 'popup', reason: `opener closed, ${idle}` });
+    } else if (rules.includes('blank') && tab.url === 'about:blank') {
+      closing.push({ index, kind: 'blank-tab', reason: `blank, ${idle}` });
+    }
+  }
+  return closing;
+}
+
+/** Contexts the rules close: extra ones that have had no pages for the grace period */
+export function contextsToClose(
+  contexts: JanitorContext[],
+  rules: JanitorRule[],
+  grace: number = JANITOR_GRACE_MS
+): number[] {
+  if (!rules.includes('contexts')) return [];
+  return [...contexts.entries()]
+    .filter(([, context]) => !context.primary && context.pages === 0 && context.idleMs >= grace)
+    .map(([index]) => index);
+}
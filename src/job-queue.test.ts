@@ -0,0 +1,89 @@
+import { describe, it, expect } from 'vitest';
+import { CommandQueue, jobQueryResponse } from './job-queue.js';
+import type { Response } from './types.js';
+
+function deferred() {
+  let resolve!: () => void;
+  const promise = new Promise<void>((r) => (resolve = r));
+  return { promise, resolve };
+}
+
+describe('CommandQueue', () => {
+  it('runs tasks one at a time in order', async () => {
+    const queue = new CommandQueue();
+    const gate = deferred();
+    const order: string[] = [];
+    const first = queue.run(async () => {
+      await gate.promise;
+      order.push('first');
+    });
+    const second = queue.run(async () => {
+      order.push('second');
+    });
+    gate.resolve();
+    await Promise.all([first, second]);
+    expect(order).toEqual(['first', 'second']);
+  });
+
+  it('keeps running after a task fails', async () => {
+    const queue = new CommandQueue();
+    await expect(queue.run(() => Promise.reject(new Error('boom')))).rejects.toThrow('boom');
+    await expect(queue.run(async () => 'ok')).resolves.toBe('ok');
+  });
+
+  it('tracks background jobs through to their result', async () => {
+    const queue = new CommandQueue();
+    const gate = deferred();
+    const blocker = queue.run(() => gate.promise);
+    const job = queue.enqueue('navigate', async (): Promise<Response> => {
+      return { id: 'x', success: true, data: { url: 'https://example.com' } };
+    });
+    const waiting = queue.enqueue('click', async () => ({ id: 'y', success: false, error: 'nope' }));
+
+    expect(job.status).toBe('queued');
+    expect(queue.position(waiting.id)).toBe(1);
+    const pending = jobQueryResponse(queue, { id: '1', action: 'job_result', jobId: job.id });
+    expect(pending.success).toBe(false);
+
+    gate.resolve();
+    await blocker;
+    await queue.run(async () => undefined);
+
+    const status = jobQueryResponse(queue, { id: '2', action: 'job_status', jobId: job.id });
+    expect(status.success && (status.data as { status: string }).status).toBe('completed');
+    const result = jobQueryResponse(queue, { id: '3', action: 'job_result', jobId: job.id });
+    expect(result.success && (result.data as { result: unknown }).result).toEqual({
+      url: 'https://example.com',
+    });
+    const failed = jobQueryResponse(queue, { id: '4', action: 'job_result', jobId: waiting.id });
+    expect(!failed.success && failed.error).toContain('nope');
+    const unknown = jobQueryResponse(queue, { id: '5', action: 'job_status', jobId: 'job-99' });
+    expect(unknown.success).toBe(false);
+  });
+
+  it('lists jobs and cancels the ones still queued', async () => {
+    const queue = new CommandQueue();
+    const gate = deferred();
+    const blocker = queue.run(() => gate.promise);
+    let ran = false;
+    const job = queue.enqueue('navigate', async () => {
+      ran = true;
+      return { id: 'x', success: true, data: {} };
+    });
+
+    const cancelled = jobQueryResponse(queue, { id: '1', action: 'job_cancel', jobId: job.id });
+    expect(cancelled.success && (cancelled.data as { status: string }).status).toBe('cancelled');
+    const again = jobQueryResponse(queue, { id: '2', action: 'job_cancel', jobId: job.id });
+    expect(!again.success && again.error).toBe(`Job ${job.id} is already cancelled`);
+
+    gate.resolve();
+    await blocker;
+    await queue.run(async () => undefined);
+    expect(ran).toBe(false);
+
+    const list = jobQueryResponse(queue, { id: '3', action: 'job_list' });
+    expect(list.success && list.data).toMatchObject({
+      jobs: [{ jobId: job.id, action: 'navigate', status: 'cancelled' }],
+    });
+  });
+});
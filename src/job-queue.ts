@@ -0,0 +1,169 @@
+import { successResponse, errorResponse } from './protocol.js';
+import type {
+  JobStatusCommand,
+  JobResultCommand,
+  JobListCommand,
+  JobCancelCommand,
+  Response,
+} from './types.js';
+
+export type JobStatus = 'queued' | 'running' | 'completed' | 'failed' | 'cancelled';
+
+export interface Job {
+  id: string;
+  action: string;
+  status: JobStatus;
+  enqueuedAt: number;
+  startedAt?: number;
+  finishedAt?: number;
+  response?: Response;
+}
+
+/** Finished jobs kept for `job status`/`job result` before the oldest are dropped */
+const MAX_FINISHED_JOBS = 100;
+
+/**
+ * FIFO queue running a session's commands one at a time.
+ *
+ * Commands from concurrent clients used to run interleaved against the same
+ * page, so two navigations could race. Every command now goes through `run`,
+ * which starts it once everything queued before it has settled. Commands sent
+ * with `noWait` are `enqueue`d instead: the caller gets a job id back at once
+ * and reads the outcome later with `job_status`/`job_result`, or drops it with
+ * `job_cancel` while it waits.
+ */
+export class CommandQueue {
+  private tail: Promise<unknown> = Promise.resolve();
+  private jobs = new Map<string, Job>();
+  private nextId = 1;
+
+  /**
+   * Run a task after all previously queued tasks
+   */
+  run<T>(task: () => Promise<T>): Promise<T> {
+    const result = this.tail.then(task, task);
+    this.tail = result.catch(() => undefined);
+    return result;
+  }
+
+  /**
+   * Queue a command as a background job. `task` resolves to the command's
+   * response; a rejection is recorded as a failed job.
+   */
+  enqueue(action: string, task: () => Promise<Response>): Job {
+    const job: Job = {
+      id: `job-${this.nextId++}`,
+      action,
+      status: 'queued',
+      enqueuedAt: Date.now(),
+    };
+    this.jobs.set(job.id, job);
+    void this.run(async () => {
+      if (job.status === 'cancelled') return;
+      job.status = 'running';
+      job.startedAt = Date.now();
+      try {
+        job.response = await task();
+      } catch (err) {
+        const message = err instanceof Error ? err.message : String(err);
+        job.response = { id: job.id, success: false, error: message };
+      }
+      job.status = job.response.success ? 'completed' : 'failed';
+      job.finishedAt = Date.now();
+      this.prune();
+    });
+    return job;
+  }
+
+  get(id: string): Job | undefined {
+    return this.jobs.get(id);
+  }
+
+  /** Jobs in the order they were queued */
+  list(): Job[] {
+    return [...this.jobs.values()];
+  }
+
+  /**
+   * Drop a queued job before it starts. A running command can't be stopped
+   * part way, so only queued jobs can be cancelled.
+   */
+  cancel(id: string): Job {
+    const job = this.jobs.get(id);
+    if (!job) throw new Error(`Unknown job: ${id}`);
+    if (job.status !== 'queued') throw new Error(`Job ${id} is already ${job.status}`);
+    job.status = 'cancelled';
+    job.finishedAt = Date.now();
+    job.response = { id: job.id, success: false, error: 'cancelled' };
+    this.prune();
+    return job;
+  }
+
+  /**
+   * Number of queued jobs ahead of a queued job (0 when it runs next)
+   */
+  position(id: string): number {
+    let ahead = 0;
+    for (const job of this.jobs.values()) {
+      if (job.id === id) return ahead;
+      if (job.status === 'queued') ahead++;
+    }
+    return ahead;
+  }
+
+  private prune(): void {
+    const finished = [...this.jobs.values()].filter((j) => j.finishedAt !== undefined);
+    for (const job of finished.slice(0, Math.max(0, finished.length - MAX_FINISHED_JOBS))) {
+      this.jobs.delete(job.id);
+    }
+  }
+}
+
+function jobInfo(queue: CommandQueue, job: Job) {
+  return {
+    jobId: job.id,
+    action: job.action,
+    status: job.status,
+    ...(job.status === 'queued' && { position: queue.position(job.id) }),
+    enqueuedAt: job.enqueuedAt,
+    startedAt: job.startedAt,
+    finishedAt: job.finishedAt,
+  };
+}
+
+/**
+ * Answer `job_status` (progress of a job), `job_result` (the finished
+ * command's data or error), `job_list` and `job_cancel`
+ */
+export function jobQueryResponse(
+  queue: CommandQueue,
+  command: JobStatusCommand | JobResultCommand | JobListCommand | JobCancelCommand
+): Response {
+  if (command.action === 'job_list') {
+    return successResponse(command.id, { jobs: queue.list().map((job) => jobInfo(queue, job)) });
+  }
+  if (command.action === 'job_cancel') {
+    try {
+      return successResponse(command.id, jobInfo(queue, queue.cancel(command.jobId)));
+    } catch (error) {
+      return errorResponse(command.id, error instanceof Error ? error.message : String(error));
+    }
+  }
+  const job = queue.get(command.jobId);
+  if (!job) {
+    return errorResponse(command.id, `Unknown job: ${command.jobId}`);
+  }
+  const info = jobInfo(queue, job);
+  if (command.action === 'job_status') {
+    return successResponse(command.id, info);
+  }
+  if (!job.response) {
+    return errorResponse(command.id, `Job ${job.id} is still ${job.status}`);
+  }
+  if (job.status === 'cancelled') {
+    return errorResponse(command.id, `Job ${job.id} was cancelled`);
+  }
+  return job.response.success
+    ? successResponse(command.id, { ...info, result: job.response.data })
+    : errorResponse(command.id, `Job ${job.id} failed: ${job.response.error}`);
+}
@@ -0,0 +1,103 @@
+import { describe, it, expect } from 'vitest';
+import { checkLink, isBroken, linkTargets, mapConcurrent, type LinkFetch } from './link-check.js';
+import { HostRules } from './host-rules.js';
+
+/** A site of canned answers: status and Location per URL */
+function site(pages: Record<string, [number, string?]>): LinkFetch {
+  return async (url, method) => {
+    const page = pages[url];
+    if (!page) throw new Error(`getaddrinfo ENOTFOUND ${new URL(url).hostname}\nCall log: ...`);
+    if (url.endsWith('/no-head') && method === 'HEAD') return { status: 405, location: null };
+    return { status: page[0], location: page[1] ?? null };
+  };
+}
+
+describe('check-links', () => {
+  it('keeps http(s) links once, without fragments', () => {
+    const targets = linkTargets(
+      [
+        { href: '/docs#intro', text: ' Docs ' },
+        { href: 'https://example.com/docs', text: 'Docs again' },
+        { href: 'mailto:hi@example.com', text: 'Mail' },
+        { href: 'https://other.example.net/', text: 'Other' },
+      ],
+      'https://example.com/',
+      false
+    );
+    expect(targets).toEqual([
+      { href: 'https://example.com/docs', text: 'Docs' },
+      { href: 'https://other.example.net/', text: 'Other' },
+    ]);
+    const own = linkTargets(targets, 'https://example.com/', true);
+    expect(own.map((t) => t.href)).toEqual(['https://example.com/docs']);
+  });
+
+  it('follows redirect chains hop by hop', async () => {
+    const fetch = site({
+      'https://a.test/old': [301, '/newer'],
+      'https://a.test/newer': [302, 'https://a.test/new'],
+      'https://a.test/new': [200],
+    });
+    const result = await checkLink({ href: 'https://a.test/old', text: 'Old' }, fetch);
+    expect(result.status).toBe(200);
+    expect(result.finalUrl).toBe('https://a.test/new');
+    expect(result.redirects).toEqual([
+      { url: 'https://a.test/old', status: 301 },
+      { url: 'https://a.test/newer', status: 302 },
+    ]);
+    expect(isBroken(result)).toBe(false);
+  });
+
+  it('reports broken links, unreachable hosts and loops', async () => {
+    const fetch = site({
+      'https://a.test/gone': [404],
+      'https://a.test/no-head': [200],
+      'https://a.test/loop': [302, '/loop'],
+    });
+    const gone = await checkLink({ href: 'https://a.test/gone', text: '' }, fetch);
+    expect(isBroken(gone)).toBe(true);
+    expect((await checkLink({ href: 'https://a.test/no-head', text: '' }, fetch)).status).toBe(200);
+    const down = await checkLink({ href: 'https://down.test/', text: '' }, fetch);
+    expect(down.status).toBeNull();
+    expect(down.error).toBe('getaddrinfo ENOTFOUND down.test');
+    const loop = await checkLink({ href: 'https://a.test/loop', text: '' }, fetch);
+    expect(loop.error).toBe('more than 10 redirects');
+  });
+
+  it('reports blocked hosts without requesting them', async () => {
+    const requested: string[] = [];
+    const canned = site({
+      'https://a.test/out': [302, 'https://ads.test/track'],
+      'https://ads.test/track': [200],
+    });
+    const fetch: LinkFetch = (url, method) => {
+      requested.push(url);
+      return canned(url, method);
+    };
+    const rules = new HostRules([], ['ads.test']);
+    const blocker = (url: string) => rules.check(url)?.reason ?? null;
+    const direct = await checkLink({ href: 'https://ads.test/track', text: '' }, fetch, blocker);
+    expect(direct.status).toBeNull();
+    expect(direct.blocked).toBe("ads.test matches --block-hosts rule 'ads.test'");
+    expect(isBroken(direct)).toBe(false);
+    const hop = await checkLink({ href: 'https://a.test/out', text: '' }, fetch, blocker);
+    expect(hop.redirects).toEqual([{ url: 'https://a.test/out', status: 302 }]);
+    expect(hop.finalUrl).toBe('https://ads.test/track');
+    expect(hop.blocked).toContain('--block-hosts');
+    expect(requested).toEqual(['https://a.test/out']);
+  });
+
+  it('runs tasks concurrently in order', async () => {
+    let running = 0;
+    let most = 0;
+    const results = await mapConcurrent([5, 1, 3, 2], 2, async (n) => {
+      running++;
+      most = Math.max(most, running);
+      await new Promise((resolve) => setTimeout(resolve, n));
+      running--;
+      return n * 10;
+    });
+    expect(results).toEqual([50, 10, 30, 20]);
+    expect(most).toBe(2);
+  });
+});
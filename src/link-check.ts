@@ -0,0 +1,125 @@
+/**
+ * `check-links`: request every link on the current page and report the
+ * broken ones (4xx, 5xx, no answer) and the redirect chains. Redirects are
+ * followed one hop at a time so each step's status is kept. HEAD is tried
+ * first; servers that refuse it get a GET. Hosts the session's host rules
+ * refuse, on the link or on a redirect hop, are reported as blocked and never
+ * requested.
+ */
+
+/** Redirect hops followed before giving up on a chain */
+export const MAX_REDIRECTS = 10;
+
+export const DEFAULT_LINK_CONCURRENCY = 8;
+
+/** A link as found on the page */
+export interface PageLink {
+  href: string;
+  text: string;
+}
+
+export interface LinkHop {
+  url: string;
+  status: number;
+}
+
+export interface LinkResult {
+  url: string;
+  /** Text of the first link pointing here */
+  text: string;
+  /** Final status, null when there was no answer */
+  status: number | null;
+  /** Hops before the final URL, when it redirected */
+  redirects: LinkHop[];
+  finalUrl: string;
+  error?: string;
+  /** Why the host rules refused the link or one of its redirects */
+  blocked?: string;
+}
+
+/** One request without following redirects: the status and any Location */
+export type LinkFetch = (
+  url: string,
+  method: 'HEAD' | 'GET'
+) => Promise<{ status: number; location: string | null }>;
+
+/**
+ * The links worth checking: http(s) only, without fragments, each URL once
+ * (keeping the first text), and with sameOrigin only the page's own
+ */
+export function linkTargets(links: PageLink[], pageUrl: string, sameOrigin: boolean): PageLink[] {
+  const origin = new URL(pageUrl).origin;
+  const seen = new Map<string, PageLink>();
+  for (const link of links) {
+    let url: URL;
+    try {
+      url = new URL(link.href, pageUrl);
+    } catch {
+      continue;
+    }
+    if (url.protocol !== 'http:' && url.protocol !== 'https:') continue;
+    if (sameOrigin && url.origin !== origin) continue;
+    url.hash = '';
+    if (!seen.has(url.href)) seen.set(url.href, { href: url.href, text: link.text.trim() });
+  }
+  return [...seen.values()];
+}
+
+/** The reason a URL may not be requested, or null when it may */
+export type LinkBlocker = (url: string) => string | null;
+
+export async function checkLink(
+  link: PageLink,
+  fetch: LinkFetch,
+  blocker: LinkBlocker = () => null
+): Promise<LinkResult> {
+  const redirects: LinkHop[] = [];
+  let url = link.href;
+  try {
+    for (;;) {
+      const blocked = blocker(url);
+      if (blocked) {
+        return { url: link.href, text: link.text, status: null, redirects, finalUrl: url, blocked };
+      }
+      let { status, location } = await fetch(url, 'HEAD');
+      if (status === 405 || status === 501) {
+        ({ status, location } = await fetch(url, 'GET'));
+      }
+      if (status < 300 || status >= 400 || !location) {
+        return { url: link.href, text: link.text, status, redirects, finalUrl: url };
+      }
+      redirects.push({ url, status });
+      if (redirects.length > MAX_REDIRECTS) {
+        throw new Error(`more than ${MAX_REDIRECTS} redirects`);
+      }
+      url = new URL(location, url).href;
+    }
+  } catch (error) {
+    const message = error instanceof Error ? error.message.split('\n')[0] : String(error);
+    const failed = { url: link.href, text: link.text, status: null, redirects, finalUrl: url };
+    return { ...failed, error: message };
+  }
+}
+
+/** Run `task` over `items`, at most `concurrency` at a time, keeping their order */
+export async function mapConcurrent<T, R>(
+  items: T[],
+  concurrency: number,
+  task: (item: T) => Promise<R>
+): Promise<R[]> {
+  const results: R[] = new Array(items.length);
+  let next = 0;
+  const worker = async (): Promise<void> => {
+    while (next < items.length) {
+      const index = next++;
+      results[index] = await task(items[index]);
+    }
+  };
+  await Promise.all(Array.from({ length: Math.min(concurrency, items.length) }, worker));
+  return results;
+}
+
+export function isBroken(result: LinkResult): boolean {
+  if (result.blocked) return false;
+  return result.status === null || result.status >= 400;
+}
@@ -0,0 +1,33 @@
+import { describe, it, expect } from 'vitest';
+import { encodeDnsQuery, parseDnsAnswers, resolveHost, splitHostPort } from './local-proxy.js';
+
+describe('local-proxy', () => {
+  it('encodes a query for one name', () => {
+    expect(encodeDnsQuery('example.com.', 28).toString('hex')).toBe(
+      '000001000001000000000000076578616d706c6503636f6d00001c0001'
+    );
+  });
+
+  it('reads A and AAAA answers with compressed names', () => {
+    const query = encodeDnsQuery('example.com', 1);
+    const header = Buffer.from([0, 0, 0x81, 0x80, 0, 1, 0, 2, 0, 0, 0, 0]);
+    const a = Buffer.from([0xc0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 93, 184, 216, 34]);
+    const aaaa = Buffer.concat([
+      Buffer.from([0xc0, 12, 0, 28, 0, 1, 0, 0, 0, 60, 0, 16]),
+      Buffer.from('20010db8000000000000000000000001', 'hex'),
+    ]);
+    const response = Buffer.concat([header, query.subarray(12), a, aaaa]);
+    expect(parseDnsAnswers(response, 1)).toEqual(['93.184.216.34']);
+    expect(parseDnsAnswers(response, 28)).toEqual(['2001:db8:0:0:0:0:0:1']);
+
+    const refused = Buffer.from(header);
+    refused[3] = 0x85;
+    expect(() => parseDnsAnswers(refused, 1)).toThrow('error code 5');
+  });
+
+  it('keeps IP literals and splits CONNECT targets', async () => {
+    expect(await resolveHost('[::1]', { family: 4 })).toBe('::1');
+    expect(splitHostPort('[::1]:8443')).toEqual(['::1', 8443]);
+    expect(splitHostPort('example.com:443')).toEqual(['example.com', 443]);
+  });
+});
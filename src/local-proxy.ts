@@ -0,0 +1,265 @@
+import * as http from 'http';
+import * as https from 'https';
+import * as net from 'net';
+import * as dns from 'dns/promises';
+import {
+  proxyAddress,
+  proxyAuthorization,
+  tunnelThroughPool,
+  type ProxyPool,
+} from './proxy-pool.js';
+
+/**
+ * How the browser's host names are resolved (--ip-family, --dns-over-https)
+ */
+export interface DnsOptions {
+  family?: 4 | 6;
+  dohUrl?: string;
+}
+
+const TYPE_A = 1;
+const TYPE_AAAA = 28;
+
+/**
+ * A DNS query message (RFC 1035) for one name and record type
+ */
+export function encodeDnsQuery(host: string, type: number): Buffer {
+  // ID 0 as RFC 8484 recommends for cacheability, recursion desired
+  const header = Buffer.from([0, 0, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+  const labels = host
+    .replace(/\.$/, '')
+    .split('.')
+    .map((label) => Buffer.concat([Buffer.from([label.length]), Buffer.from(label, 'ascii')]));
+  const question = Buffer.from([0, type >> 8, type & 0xff, 0, 1]);
+  return Buffer.concat([header, ...labels, question]);
+}
+
+function skipName(message: Buffer, offset: number): number {
+  while (offset < message.length) {
+    const length = message[offset];
+    if (length === 0) return offset + 1;
+    // Compression pointer: two bytes, ends the name
+    if ((length & 0xc0) === 0xc0) return offset + 2;
+    offset += length + 1;
+  }
+  throw new Error('Truncated DNS name');
+}
+
+/**
+ * Addresses of the given record type in a DNS response message
+ */
+export function parseDnsAnswers(message: Buffer, type: number): string[] {
+  if (message.length < 12) throw new Error('Truncated DNS response');
+  const rcode = message[3] & 0x0f;
+  if (rcode !== 0 && rcode !== 3) throw new Error(`DNS server returned error code ${rcode}`);
+  const questions = message.readUInt16BE(4);
+  const answers = message.readUInt16BE(6);
+  let offset = 12;
+  for (let i = 0; i < questions; i++) {
+    offset = skipName(message, offset) + 4;
+  }
+  const addresses: string[] = [];
+  for (let i = 0; i < answers; i++) {
+    offset = skipName(message, offset);
+    const recordType = message.readUInt16BE(offset);
+    const length = message.readUInt16BE(offset + 8);
+    const data = message.subarray(offset + 10, offset + 10 + length);
+    offset += 10 + length;
+    if (recordType === TYPE_A && type === TYPE_A && length === 4) {
+      addresses.push(Array.from(data).join('.'));
+    } else if (recordType === TYPE_AAAA && type === TYPE_AAAA && length === 16) {
+      const groups = [];
+      for (let g = 0; g < 16; g += 2) groups.push(data.readUInt16BE(g).toString(16));
+      addresses.push(groups.join(':'));
+    }
+  }
+  return addresses;
+}
+
+async function queryDoh(dohUrl: string, host: string, type: number): Promise<string[]> {
+  const response = await fetch(dohUrl, {
+    method: 'POST',
+    headers: { 'content-type': 'application/dns-message', accept: 'application/dns-message' },
+    body: encodeDnsQuery(host, type),
+  });
+  if (!response.ok) throw new Error(`DNS-over-HTTPS server returned ${response.status}`);
+  return parseDnsAnswers(Buffer.from(await response.arrayBuffer()), type);
+}
+
+/**
+ * Resolve a host to one address following the options
+ */
+export async function resolveHost(host: string, options: DnsOptions): Promise<string> {
+  const bare = host.replace(/^\[|\]$/g, '');
+  if (net.isIP(bare)) return bare;
+  if (options.dohUrl) {
+    const types =
+      options.family === 6 ? [TYPE_AAAA] : options.family === 4 ? [TYPE_A] : [TYPE_A, TYPE_AAAA];
+    for (const type of types) {
+      const [address] = await queryDoh(options.dohUrl, bare, type);
+      if (address) return address;
+    }
+    throw new Error(`${bare} has no ${options.family ? `IPv${options.family} ` : ''}address`);
+  }
+  const { address } = await dns.lookup(bare, { family: options.family ?? 0 });
+  return address;
+}
+
+const BODYLESS_METHODS = new Set(['GET', 'HEAD', 'OPTIONS']);
+
+export interface LocalProxyOptions {
+  dns?: DnsOptions;
+  pool?: ProxyPool;
+}
+
+export interface LocalProxy {
+  url: string;
+  close: () => Promise<void>;
+  /** End open CONNECT tunnels so new connections pick the current upstream */
+  dropTunnels: () => void;
+}
+
+/**
+ * Connect to host:port directly or through the proxy pool
+ */
+async function openTunnel(
+  host: string,
+  port: number,
+  options: LocalProxyOptions
+): Promise<net.Socket> {
+  if (options.pool) return tunnelThroughPool(options.pool, host, port);
+  const address = await resolveHost(host, options.dns ?? {});
+  return new Promise((resolve, reject) => {
+    const socket = net.connect({ host: address, port }, () => {
+      socket.off('error', reject);
+      resolve(socket);
+    });
+    socket.once('error', reject);
+  });
+}
+
+/**
+ * Forward a plain HTTP request to its origin or to the pool's current proxy
+ */
+async function forwardRequest(
+  target: URL,
+  req: http.IncomingMessage,
+  options: LocalProxyOptions,
+  onResponse: (res: http.IncomingMessage) => void
+): Promise<http.ClientRequest> {
+  const proxy = options.pool?.current();
+  if (!proxy) {
+    const address = await resolveHost(target.hostname, options.dns ?? {});
+    return http.request(
+      {
+        host: address,
+        port: target.port || 80,
+        method: req.method,
+        path: target.pathname + target.search,
+        headers: { ...req.headers, host: target.host },
+      },
+      onResponse
+    );
+  }
+  const address = proxyAddress(proxy.server);
+  const auth = proxyAuthorization(proxy);
+  const upstream = (address.tls ? https : http).request(
+    {
+      host: address.host,
+      port: address.port,
+      method: req.method,
+      path: target.href,
+      headers: { ...req.headers, ...(auth && { 'proxy-authorization': auth }) },
+    },
+    onResponse
+  );
+  upstream.on('error', () => options.pool?.markFailed(proxy));
+  return upstream;
+}
+
+/**
+ * Start a local HTTP proxy between the browser and the network. It resolves
+ * host names itself, so connections follow --ip-family and --dns-over-https,
+ * or forwards everything through the current proxy of a --proxy-pool. HTTPS
+ * goes through CONNECT tunnels, plain HTTP is forwarded.
+ */
+export async function startLocalProxy(options: LocalProxyOptions): Promise<LocalProxy> {
+  const sockets = new Set<net.Socket>();
+  const tunnels = new Set<net.Socket>();
+  const server = http.createServer((req, res) => {
+    let target: URL;
+    try {
+      target = new URL(req.url ?? '');
+    } catch {
+      res.writeHead(400).end();
+      return;
+    }
+    const send = (attemptsLeft: number) => {
+      forwardRequest(target, req, options, (upstreamRes) => {
+        res.writeHead(upstreamRes.statusCode ?? 502, upstreamRes.headers);
+        upstreamRes.pipe(res);
+      })
+        .then((upstream) => {
+          upstream.on('error', () => {
+            // A request without a body can be sent again through the next proxy
+            const retry = attemptsLeft > 1 && BODYLESS_METHODS.has(req.method ?? '');
+            if (retry && !res.headersSent) {
+              send(attemptsLeft - 1);
+              return;
+            }
+            if (!res.headersSent) res.writeHead(502);
+            res.end();
+          });
+          if (req.readableEnded) upstream.end();
+          else req.pipe(upstream);
+        })
+        .catch(() => res.writeHead(502).end());
+    };
+    send(options.pool?.proxies.length ?? 1);
+  });
+
+  server.on('connect', (req: http.IncomingMessage, client: net.Socket, head: Buffer) => {
+    const [host, port] = splitHostPort(req.url ?? '');
+    client.on('error', () => client.destroy());
+    openTunnel(host, port, options)
+      .then((upstream) => {
+        client.write('HTTP/1.1 200 Connection Established\r\n\r\n');
+        upstream.write(head);
+        upstream.pipe(client);
+        client.pipe(upstream);
+        tunnels.add(client);
+        upstream.on('error', () => client.destroy());
+        client.on('close', () => {
+          tunnels.delete(client);
+          upstream.destroy();
+        });
+      })
+      .catch(() => client.end('HTTP/1.1 502 Bad Gateway\r\n\r\n'));
+  });
+  server.on('connection', (socket) => {
+    sockets.add(socket);
+    socket.on('close', () => sockets.delete(socket));
+  });
+
+  await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+  const { port } = server.address() as net.AddressInfo;
+  return {
+    url: `http://127.0.0.1:${port}`,
+    close: () =>
+      new Promise<void>((resolve) => {
+        for (const socket of sockets) socket.destroy();
+        server.close(() => resolve());
+      }),
+    dropTunnels: () => {
+      for (const tunnel of tunnels) tunnel.destroy();
+    },
+  };
+}
+
+/**
+ * "host:port" or "[v6]:port" from a CONNECT target
+ */
+export function splitHostPort(target: string): [string, number] {
+  const match = /^\[?([^\]]+?)\]?:(\d+)$/.exec(target);
+  return match ? [match[1], Number(match[2])] : [target, 443];
+}
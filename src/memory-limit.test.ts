@@ -0,0 +1,21 @@
+import { describe, it, expect } from 'vitest';
+import { formatBytes, parseRss, processMemory } from './memory-limit.js';
+
+describe('memory limit', () => {
+  it('sums the resident memory ps reports in kilobytes', () => {
+    expect(parseRss(' 204800\n  51200\n')).toBe(250 * 1024 * 1024);
+    expect(parseRss('')).toBe(0);
+  });
+
+  it('measures this process', async () => {
+    if (process.platform === 'win32') return;
+    const used = await processMemory([process.pid]);
+    expect(used).toBeGreaterThan(1024 * 1024);
+  });
+
+  it('formats sizes for messages', () => {
+    expect(formatBytes(1.5 * 1024 * 1024 * 1024)).toBe('1.5GB');
+    expect(formatBytes(300 * 1024 * 1024)).toBe('300MB');
+    expect(formatBytes(512)).toBe('512B');
+  });
+});
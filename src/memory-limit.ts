@@ -0,0 +1,40 @@
+import { execFile } from 'child_process';
+
+/** How often the browser's memory is sampled under --max-memory */
+export const MEMORY_SAMPLE_INTERVAL_MS = 5_000;
+
+/** Total resident set size in bytes from `ps -o rss=` output (kilobytes per line) */
+export function parseRss(output: string): number {
+  return output
+    .split('\n')
+    .map((line) => Number(line.trim()))
+    .filter((kb) => Number.isFinite(kb) && kb > 0)
+    .reduce((total, kb) => total + kb * 1024, 0);
+}
+
+/**
+ * Resident memory of a set of processes, the browser and its renderer, GPU
+ * and utility children. Null where `ps` isn't available (Windows).
+ */
+export function processMemory(pids: number[]): Promise<number | null> {
+  if (process.platform === 'win32' || pids.length === 0) return Promise.resolve(null);
+  return new Promise((resolve) => {
+    // ps exits non-zero when a process has already gone, but still lists the rest
+    execFile('ps', ['-o', 'rss=', '-p', pids.join(',')], { timeout: 5_000 }, (error, stdout) => {
+      const total = parseRss(String(stdout ?? ''));
+      resolve(error && total === 0 ? null : total);
+    });
+  });
+}
+
+/** Bytes as "1.5GB" / "300MB" for messages */
+export function formatBytes(bytes: number): string {
+  const units = ['B', 'KB', 'MB', 'GB'];
+  let value = bytes;
+  let unit = 0;
+  while (value >= 1024 && unit < units.length - 1) {
+    value /= 1024;
+    unit++;
+  }
+  return `${Number(value.toFixed(1))}${units[unit]}`;
+}
@@ -0,0 +1,58 @@
+import { describe, it, expect } from 'vitest';
+import { ObserverHub, observedArgs, type Observer } from './observers.js';
+import type { Command } from './types.js';
+
+function observer(screenshots = false): Observer & { lines: string[] } {
+  const lines: string[] = [];
+  return { lines, screenshots, write: (line) => lines.push(line) };
+}
+
+describe('session observers', () => {
+  it('hides values that may be secret and shortens long ones', () => {
+    const fill = { id: '1', action: 'fill', selector: '#password', value: 'hunter2' } as Command;
+    expect(observedArgs(fill)).toEqual({ selector: '#password', value: '[hidden]' });
+    const evaluate = { id: '2', action: 'evaluate', script: 'x'.repeat(200) } as Command;
+    expect((observedArgs(evaluate).script as string).length).toBe(120);
+  });
+
+  it('sends every command and its result to each observer', () => {
+    const hub = new ObserverHub();
+    const a = observer();
+    const b = observer();
+    hub.add(a);
+    hub.add(b);
+    const click = { id: '7', action: 'click', selector: '#buy' } as Command;
+    hub.commandStarted(click);
+    hub.commandFinished(click, { id: '7', success: false, error: 'Element not found' }, 42);
+    expect(a.lines.map((line) => JSON.parse(line))).toEqual([
+      { event: 'command', id: '7', action: 'click', args: { selector: '#buy' } },
+      {
+        event: 'result',
+        id: '7',
+        action: 'click',
+        success: false,
+        error: 'Element not found',
+        durationMs: 42,
+      },
+    ]);
+    expect(b.lines).toEqual(a.lines);
+
+    hub.remove(b);
+    hub.broadcast({ event: 'closed' });
+    expect(a.lines.length).toBe(3);
+    expect(b.lines.length).toBe(2);
+  });
+
+  it('sends screenshots only to observers that asked for them', () => {
+    const hub = new ObserverHub();
+    const plain = observer();
+    hub.add(plain);
+    expect(hub.wantsScreenshots()).toBe(false);
+    const watching = observer(true);
+    hub.add(watching);
+    expect(hub.wantsScreenshots()).toBe(true);
+    hub.broadcast({ event: 'screenshot', id: '1', action: 'navigate', data: 'aGk=' });
+    expect(plain.lines).toEqual([]);
+    expect(watching.lines.length).toBe(1);
+  });
+});
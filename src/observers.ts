@@ -0,0 +1,113 @@
+/**
+ * `observe`: clients attached to a session to watch it, not drive it. Each
+ * observer connection gets a line of JSON for every command the session
+ * runs, before and after, and a screenshot after each one if it asked for
+ * them. Anything an observer sends after attaching is refused.
+ */
+
+import type { Command, Response } from './types.js';
+
+/** Values that may hold secrets are left out of what observers see */
+const HIDDEN_FIELDS = new Set([
+  'value',
+  'text',
+  'password',
+  'headers',
+  'credentials',
+  'body',
+  'token',
+  'secret',
+]);
+
+const MAX_FIELD_LENGTH = 120;
+
+export type ObserverEvent =
+  | { event: 'command'; id: string; action: string; args: Record<string, unknown> }
+  | {
+      event: 'result';
+      id: string;
+      action: string;
+      success: boolean;
+      error?: string;
+      durationMs: number;
+    }
+  | { event: 'screenshot'; id: string; action: string; data: string }
+  | { event: 'closed' };
+
+export interface Observer {
+  write(line: string): void;
+  screenshots: boolean;
+}
+
+/**
+ * The arguments of a command as observers see them: scalars only, long
+ * strings shortened and possibly secret values replaced
+ */
+export function observedArgs(command: Command): Record<string, unknown> {
+  const args: Record<string, unknown> = {};
+  for (const [key, value] of Object.entries(command)) {
+    if (key === 'id' || key === 'action' || value === undefined) continue;
+    if (HIDDEN_FIELDS.has(key)) {
+      args[key] = '[hidden]';
+    } else if (typeof value === 'string') {
+      args[key] =
+        value.length > MAX_FIELD_LENGTH ? `${value.slice(0, MAX_FIELD_LENGTH - 3)}...` : value;
+    } else if (typeof value === 'number' || typeof value === 'boolean') {
+      args[key] = value;
+    }
+  }
+  return args;
+}
+
+export class ObserverHub {
+  private observers = new Set<Observer>();
+
+  add(observer: Observer): void {
+    this.observers.add(observer);
+  }
+
+  remove(observer: Observer): void {
+    this.observers.delete(observer);
+  }
+
+  get size(): number {
+    return this.observers.size;
+  }
+
+  /** Whether a screenshot after each command is worth taking */
+  wantsScreenshots(): boolean {
+    return [...this.observers].some((observer) => observer.screenshots);
+  }
+
+  broadcast(event: ObserverEvent): void {
+    const line = JSON.stringify(event) + '\n';
+    for (const observer of this.observers) {
+      if (event.event === 'screenshot' && !observer.screenshots) continue;
+      try {
+        observer.write(line);
+      } catch {
+        // A client that went away is removed when its socket closes
+      }
+    }
+  }
+
+  commandStarted(command: Command): void {
+    this.broadcast({
+      event: 'command',
+      id: command.id,
+      action: command.action,
+      args: observedArgs(command),
+    });
+  }
+
+  commandFinished(command: Command, response: Response, durationMs: number): void {
+    this.broadcast({
+      event: 'result',
+      id: command.id,
+      action: command.action,
+      success: response.success,
+      ...(!response.success && { error: response.error }),
+      durationMs,
+    });
+  }
+}
@@ -31,6 +31,39 @@ describe('parseCommand', () => {
       }
     });
 
+    it('should parse navigate with headers for the navigation only', () => {
+      const nav = { id: '1', action: 'navigate', url: 'https://example.com', headers: { A: 'b' } };
+      const result = parseCommand(cmd({ ...nav, headersScope: 'navigation' }));
+      expect(result.success).toBe(true);
+      if (result.success && result.command.action === 'navigate') {
+        expect(result.command.headersScope).toBe('navigation');
+      }
+      expect(parseCommand(cmd({ ...nav, headersScope: 'tab' })).success).toBe(false);
+    });
+
+    it('should parse navigate with origin-scoped headers', () => {
+      const originHeaders = { 'api.example.com': { Authorization: 'Bearer token' } };
+      const result = parseCommand(
+        cmd({ id: '1', action: 'navigate', url: 'https://example.com', originHeaders })
+      );
+      expect(result.success).toBe(true);
+      if (result.success && result.command.action === 'navigate') {
+        expect(result.command.originHeaders).toEqual(originHeaders);
+      }
+    });
+
+    it('should parse navigate with a rotated identity', () => {
+      const rotation = { userAgent: 'UA-1', viewport: { width: 1280, height: 800 } };
+      const nav = { id: '1', action: 'navigate', url: 'https://example.com' };
+      const result = parseCommand(cmd({ ...nav, rotation }));
+      expect(result.success).toBe(true);
+      if (result.success && result.command.action === 'navigate') {
+        expect(result.command.rotation).toEqual(rotation);
+      }
+      const invalid = { ...rotation, viewport: { width: 0, height: 800 } };
+      expect(parseCommand(cmd({ ...nav, rotation: invalid })).success).toBe(false);
+    });
+
     it('should reject navigate without url', () => {
       const result = parseCommand(cmd({ id: '1', action: 'navigate' }));
       expect(result.success).toBe(false);
@@ -50,6 +83,63 @@ describe('parseCommand', () => {
       const result = parseCommand(cmd({ id: '1', action: 'reload' }));
       expect(result.success).toBe(true);
     });
+
+    it('should parse history steps, hard reload, stop and url with title', () => {
+      const back = parseCommand(cmd({ id: '1', action: 'back', steps: 3 }));
+      expect(back.success && back.command.action === 'back' && back.command.steps).toBe(3);
+      expect(parseCommand(cmd({ id: '1', action: 'forward', steps: 0 })).success).toBe(false);
+      const reload = parseCommand(cmd({ id: '1', action: 'reload', ignoreCache: true }));
+      expect(
+        reload.success && reload.command.action === 'reload' && reload.command.ignoreCache
+      ).toBe(true);
+      expect(parseCommand(cmd({ id: '1', action: 'stop' })).success).toBe(true);
+      const url = parseCommand(cmd({ id: '1', action: 'url', includeTitle: true }));
+      expect(url.success && url.command.action === 'url' && url.command.includeTitle).toBe(true);
+    });
+
+    it('should parse navigate with commit, referer and new tab', () => {
+      const result = parseCommand(
+        cmd({
+          id: '1',
+          action: 'navigate',
+          url: 'https://example.com',
+          waitUntil: 'commit',
+          referer: 'https://google.com/',
+          newTab: true,
+        })
+      );
+      expect(result.success).toBe(true);
+      if (result.success && result.command.action === 'navigate') {
+        expect(result.command.waitUntil).toBe('commit');
+        expect(result.command.referer).toBe('https://google.com/');
+        expect(result.command.newTab).toBe(true);
+      }
+    });
+
+    it('should parse client-side route changes and waits', () => {
+      const spa = parseCommand(
+        cmd({ id: '1', action: 'spanavigate', route: '#/settings', mode: 'hash' })
+      );
+      expect(spa.success).toBe(true);
+      if (spa.success && spa.command.action === 'spanavigate') {
+        expect(spa.command.route).toBe('#/settings');
+        expect(spa.command.mode).toBe('hash');
+      }
+      expect(
+        parseCommand(cmd({ id: '1', action: 'spanavigate', route: '/a', mode: 'reload' })).success
+      ).toBe(false);
+      const wait = parseCommand(
+        cmd({ id: '1', action: 'waitforroute', route: '**/settings', timeout: 5000 })
+      );
+      expect(wait.success).toBe(true);
+      if (wait.success && wait.command.action === 'waitforroute') {
+        expect(wait.command.route).toBe('**/settings');
+        expect(wait.command.timeout).toBe(5000);
+      }
+      expect(parseCommand(cmd({ id: '1', action: 'waitforroute', route: '' })).success).toBe(
+        false
+      );
+    });
   });
 
   describe('click', () => {
@@ -66,6 +156,82 @@ describe('parseCommand', () => {
       const result = parseCommand(cmd({ id: '1', action: 'click' }));
       expect(result.success).toBe(false);
     });
+
+    it('should parse click expecting a navigation', () => {
+      const result = parseCommand(
+        cmd({
+          id: '1',
+          action: 'click',
+          selector: '#btn',
+          expectNavigation: true,
+          waitUntil: 'commit',
+        })
+      );
+      expect(result.success).toBe(true);
+      const invalid = parseCommand(
+        cmd({
+          id: '1',
+          action: 'click',
+          selector: '#btn',
+          expectNavigation: true,
+          waitUntil: 'idle',
+        })
+      );
+      expect(invalid.success).toBe(false);
+    });
+
+    it('should parse click expecting a popup', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'click', selector: '@e4', expectPopup: true })
+      );
+      expect(result.success).toBe(true);
+      if (result.success && result.command.action === 'click') {
+        expect(result.command.expectPopup).toBe(true);
+      }
+    });
+
+    it('should parse click expecting a clipboard write', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'click', selector: 'button.copy', expectClipboard: true })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse click intercepting a form submission', () => {
+      const intercept = { interceptSubmit: true, allowSubmit: true };
+      const result = parseCommand(cmd({ id: '1', action: 'click', selector: '#pay', ...intercept }));
+      expect(result.success).toBe(true);
+    });
+
+    it('should parse a download with or without a path', () => {
+      const saved = parseCommand(
+        cmd({ id: '1', action: 'download', selector: '#export', path: './report.csv' })
+      );
+      expect(saved.success).toBe(true);
+      const kept = parseCommand(cmd({ id: '1', action: 'download', selector: '#export' }));
+      expect(kept.success).toBe(true);
+    });
+
+    it('should parse download limits', () => {
+      const limits = { maxBytes: 10485760, blockedTypes: ['video/*'] };
+      const download = parseCommand(
+        cmd({ id: '1', action: 'download', selector: '#export', path: './a.csv', ...limits })
+      );
+      expect(download.success).toBe(true);
+      if (download.success && download.command.action === 'download') {
+        expect(download.command.maxBytes).toBe(10485760);
+        expect(download.command.blockedTypes).toEqual(['video/*']);
+      }
+      const wait = parseCommand(cmd({ id: '1', action: 'waitfordownload', ...limits }));
+      expect(wait.success).toBe(true);
+      if (wait.success && wait.command.action === 'waitfordownload') {
+        expect(wait.command.maxBytes).toBe(10485760);
+        expect(wait.command.blockedTypes).toEqual(['video/*']);
+      }
+      expect(
+        parseCommand(cmd({ id: '1', action: 'waitfordownload', maxBytes: '10MB' })).success
+      ).toBe(false);
+    });
   });
 
   describe('type', () => {
@@ -129,6 +295,30 @@ describe('parseCommand', () => {
       );
       expect(result.success).toBe(true);
     });
+
+    it('should parse screenshot and pdf with a handoff directory', () => {
+      const handoff = '/tmp/agent-browser-handoff-abc';
+      for (const extra of [{ action: 'screenshot' }, { action: 'pdf', path: '/tmp/a.pdf' }]) {
+        const result = parseCommand(cmd({ id: '1', ...extra, handoff }));
+        expect(result.success).toBe(true);
+        if (result.success) {
+          expect(result.command.handoff).toBe(handoff);
+        }
+      }
+    });
+
+    it('should parse screenshot with annotate', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'screenshot', annotate: true }));
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.annotate).toBe(true);
+      }
+    });
+
+    it('should parse screenshot with grid', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'screenshot', grid: 50 })).success).toBe(true);
+      expect(parseCommand(cmd({ id: '1', action: 'screenshot', grid: 5 })).success).toBe(false);
+    });
   });
 
   describe('cookies', () => {
@@ -351,6 +541,24 @@ describe('parseCommand', () => {
     });
   });
 
+  describe('indexeddb', () => {
+    it('should parse indexeddb list and dump', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'indexeddb_list' })).success).toBe(true);
+      const dump = { id: '1', action: 'indexeddb_dump', db: 'app', store: 'drafts', limit: 50 };
+      expect(parseCommand(cmd(dump)).success).toBe(true);
+      expect(parseCommand(cmd({ id: '1', action: 'indexeddb_dump' })).success).toBe(false);
+    });
+  });
+
+  describe('storage origins', () => {
+    it('should parse storage_origins and storage_clear_origin', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'storage_origins' })).success).toBe(true);
+      const clear = { id: '1', action: 'storage_clear_origin', origin: 'https://example.com' };
+      expect(parseCommand(cmd(clear)).success).toBe(true);
+      expect(parseCommand(cmd({ id: '1', action: 'storage_clear_origin' })).success).toBe(false);
+    });
+  });
+
   describe('semantic locators', () => {
     it('should parse getbyrole', () => {
       const result = parseCommand(
@@ -450,6 +658,23 @@ describe('parseCommand', () => {
       }
     });
 
+    it('should parse snapshot with noCache', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'snapshot', noCache: true }));
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.noCache).toBe(true);
+      }
+    });
+
+    it('should parse snapshot with diffFrom', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'snapshot', diffFrom: 's3' }));
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.diffFrom).toBe('s3');
+      }
+      expect(parseCommand(cmd({ id: '1', action: 'snapshot', diffFrom: '' })).success).toBe(false);
+    });
+
     it('should parse snapshot with selector scope', () => {
       const result = parseCommand(cmd({ id: '1', action: 'snapshot', selector: '#main' }));
       expect(result.success).toBe(true);
@@ -510,6 +735,30 @@ describe('parseCommand', () => {
       const result = parseCommand(cmd({ id: '1', action: 'launch', cdpPort: 'invalid' }));
       expect(result.success).toBe(false);
     });
+
+    it('should keep TLS options', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'launch', ignoreHTTPSErrors: true, extraCaCerts: '/ca.pem' })
+      );
+      expect(result.success).toBe(true);
+      if (result.success && result.command.action === 'launch') {
+        expect(result.command.ignoreHTTPSErrors).toBe(true);
+        expect(result.command.extraCaCerts).toBe('/ca.pem');
+      }
+    });
+
+    it('should keep host rules', () => {
+      const hosts = { allowHosts: ['*.example.com'], blockHosts: ['ads.example.com'] };
+      const result = parseCommand(cmd({ id: '1', action: 'launch', ...hosts }));
+      expect(result.success).toBe(true);
+      if (result.success && result.command.action === 'launch') {
+        expect(result.command.allowHosts).toEqual(hosts.allowHosts);
+        expect(result.command.blockHosts).toEqual(hosts.blockHosts);
+      }
+      expect(parseCommand(cmd({ id: '1', action: 'launch', blockHosts: [''] })).success).toBe(
+        false
+      );
+    });
   });
 
   describe('mouse actions', () => {
@@ -522,6 +771,20 @@ describe('parseCommand', () => {
       }
     });
 
+    it('should parse click_at, move_at and drag_at', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'click_at', x: 412, y: 380 })).success).toBe(true);
+      expect(parseCommand(cmd({ id: '1', action: 'move_at', x: 1, y: 2, css: true })).success).toBe(
+        true
+      );
+      const drag = cmd({ id: '1', action: 'drag_at', fromX: 1, fromY: 2, toX: 3, toY: 4 });
+      expect(parseCommand(drag).success).toBe(true);
+    });
+
+    it('should reject click_at with a negative point', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'click_at', x: -1, y: 380 }));
+      expect(result.success).toBe(false);
+    });
+
     it('should parse mousedown', () => {
       const result = parseCommand(cmd({ id: '1', action: 'mousedown', button: 'left' }));
       expect(result.success).toBe(true);
@@ -550,9 +813,41 @@ describe('parseCommand', () => {
       const result = parseCommand(cmd({ id: '1', action: 'scrollintoview', selector: '#element' }));
       expect(result.success).toBe(true);
     });
+
+    it('should parse a scroll margin on scrollintoview and click', () => {
+      const scrolled = parseCommand(
+        cmd({ id: '1', action: 'scrollintoview', selector: '#pricing', scrollMargin: 80 })
+      );
+      expect(scrolled.success).toBe(true);
+      const clicked = parseCommand(
+        cmd({ id: '1', action: 'click', selector: '#buy', scrollMargin: 80 })
+      );
+      expect(clicked.success).toBe(true);
+      const negative = parseCommand(
+        cmd({ id: '1', action: 'click', selector: '#buy', scrollMargin: -1 })
+      );
+      expect(negative.success).toBe(false);
+    });
   });
 
   describe('element state', () => {
+    it('should parse styles with props', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'styles', selector: '.error', props: ['color', '--brand-color'] })
+      );
+      expect(result.success).toBe(true);
+      const invalid = parseCommand(
+        cmd({ id: '1', action: 'styles', selector: '.error', props: ['color;x'] })
+      );
+      expect(invalid.success).toBe(false);
+    });
+
+    it('should parse elementstate', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'elementstate', selector: '@e3' }));
+      expect(result.success).toBe(true);
+      expect(parseCommand(cmd({ id: '1', action: 'elementstate' })).success).toBe(false);
+    });
+
     it('should parse isvisible', () => {
       const result = parseCommand(cmd({ id: '1', action: 'isvisible', selector: '#btn' }));
       expect(result.success).toBe(true);
@@ -628,6 +923,147 @@ describe('parseCommand', () => {
       expect(result.success).toBe(true);
     });
 
+    it('should parse dialog_events with clear', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'dialog_events', clear: true }));
+      expect(result.success).toBe(true);
+    });
+  });
+
+  describe('jobs', () => {
+    it('should keep noWait on queued commands', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'navigate', url: 'https://example.com', noWait: true })
+      );
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.noWait).toBe(true);
+      }
+    });
+
+    it('should parse job_status and job_result', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'job_status', jobId: 'job-1' })).success).toBe(true);
+      expect(parseCommand(cmd({ id: '1', action: 'job_result', jobId: 'job-1' })).success).toBe(true);
+    });
+
+    it('should parse job_list and job_cancel', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'job_list' })).success).toBe(true);
+      const cancel = parseCommand(cmd({ id: '1', action: 'job_cancel', jobId: 'job-1' }));
+      expect(cancel.success && cancel.command.action === 'job_cancel' && cancel.command.jobId).toBe(
+        'job-1'
+      );
+      expect(parseCommand(cmd({ id: '1', action: 'job_cancel' })).success).toBe(false);
+    });
+
+    it('should parse prelaunch and warm_claim', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'prelaunch' })).success).toBe(true);
+      const claim = { id: '1', action: 'warm_claim', session: 'agent-1' };
+      expect(parseCommand(cmd(claim)).success).toBe(true);
+      expect(parseCommand(cmd({ ...claim, session: '../agent-1' })).success).toBe(false);
+      expect(parseCommand(cmd({ ...claim, session: '.warm-1' })).success).toBe(false);
+    });
+
+    it('should reject job_status without a job id', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'job_status' })).success).toBe(false);
+    });
+
+    it('should accept a cache TTL in ms', () => {
+      const read = { id: '1', action: 'gettext', selector: 'main' };
+      expect(parseCommand(cmd({ ...read, cache: 300000 })).success).toBe(true);
+      expect(parseCommand(cmd({ ...read, cache: 0 })).success).toBe(false);
+      expect(parseCommand(cmd({ ...read, cache: '5m' })).success).toBe(false);
+    });
+
+    it('should accept an access token on any command', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'url', token: 'abc' }));
+      expect(result.success && result.command.token).toBe('abc');
+    });
+
+    it('should parse observe', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'observe' })).success).toBe(true);
+      const result = parseCommand(cmd({ id: '1', action: 'observe', screenshots: true }));
+      expect(
+        result.success && result.command.action === 'observe' && result.command.screenshots
+      ).toBe(true);
+    });
+  });
+
+  describe('network limits', () => {
+    it('should keep network budgets on commands', () => {
+      const result = parseCommand(
+        cmd({
+          id: '1',
+          action: 'navigate',
+          url: 'https://example.com',
+          budget: { maxBytes: 52428800, maxRequests: 500, scope: 'session' },
+        })
+      );
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.budget).toEqual({
+          maxBytes: 52428800,
+          maxRequests: 500,
+          scope: 'session',
+        });
+      }
+    });
+
+    it('should keep blocked resource types and reject unknown ones', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'navigate', url: 'https://example.com', block: ['image', 'font'] })
+      );
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.block).toEqual(['image', 'font']);
+      }
+      expect(parseCommand(cmd({ id: '1', action: 'reload', block: ['video'] })).success).toBe(false);
+    });
+
+    it('should reject non-positive limits', () => {
+      const budget = { maxRequests: 0 };
+      expect(parseCommand(cmd({ id: '1', action: 'reload', budget })).success).toBe(false);
+    });
+
+    it('should parse request assertions', () => {
+      const assertion = { id: '1', action: 'assert_request', pattern: '**/api/track', sent: true };
+      expect(parseCommand(cmd({ ...assertion, status: '2xx', bodyPath: '$.event' })).success).toBe(
+        true
+      );
+      expect(parseCommand(cmd({ ...assertion, status: '20' })).success).toBe(false);
+      expect(parseCommand(cmd({ ...assertion, bodyPath: 'event' })).success).toBe(false);
+    });
+
+    it('should parse security_audit', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'security_audit' })).success).toBe(true);
+    });
+
+    it('should parse check_links', () => {
+      const links = { id: '1', action: 'check_links', sameOrigin: true };
+      expect(parseCommand(cmd({ ...links, concurrency: 4 })).success).toBe(true);
+      expect(parseCommand(cmd({ ...links, concurrency: 0 })).success).toBe(false);
+    });
+  });
+
+  describe('schedules', () => {
+    it('should parse schedule_add with commands and jitter', () => {
+      const result = parseCommand(
+        cmd({
+          id: '1',
+          action: 'schedule_add',
+          cron: '0 7 * * *',
+          commands: [{ id: 'a', action: 'navigate', url: 'https://example.com' }],
+          jitterMs: 300000,
+        })
+      );
+      expect(result.success).toBe(true);
+    });
+
+    it('should reject schedule_add without commands', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'schedule_add', cron: '0 7 * * *', commands: [] })
+      );
+      expect(result.success).toBe(false);
+    });
+
     it('should parse dialog accept with prompt text', () => {
       const result = parseCommand(
         cmd({ id: '1', action: 'dialog', response: 'accept', promptText: 'hello' })
@@ -651,6 +1087,42 @@ describe('parseCommand', () => {
     });
   });
 
+  describe('text', () => {
+    it('should parse text with or without a selector', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'text' })).success).toBe(true);
+      const result = parseCommand(
+        cmd({ id: '1', action: 'text', selector: 'main', savePdf: true })
+      );
+      expect(result.success).toBe(true);
+      if (result.success && result.command.action === 'text') {
+        expect(result.command.selector).toBe('main');
+        expect(result.command.savePdf).toBe(true);
+      }
+    });
+
+    it('should reject an empty selector', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'text', selector: '' })).success).toBe(false);
+    });
+
+    it('should parse content hash exclusions', () => {
+      const hashIgnore = { selectors: ['.ad'], patterns: ['\\d+:\\d+'] };
+      expect(parseCommand(cmd({ id: '1', action: 'text', hashIgnore })).success).toBe(true);
+      expect(
+        parseCommand(cmd({ id: '1', action: 'innerhtml', selector: 'main', hashIgnore })).success
+      ).toBe(true);
+      const empty = { selectors: [''] };
+      expect(
+        parseCommand(cmd({ id: '1', action: 'gettext', selector: 'p', hashIgnore: empty })).success
+      ).toBe(false);
+    });
+  });
+
+  describe('article', () => {
+    it('should parse article', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'article' })).success).toBe(true);
+    });
+  });
+
   describe('screencast', () => {
     it('should parse screencast_start with defaults', () => {
       const result = parseCommand(cmd({ id: '1', action: 'screencast_start' }));
@@ -1136,5 +1608,31 @@ describe('parseCommand', () => {
       const result = parseCommand(cmd({ id: '1', action: 'state_rename', oldName: 'test' }));
       expect(result.success).toBe(false);
     });
+
+    it('should parse state_restore command', () => {
+      const result = parseCommand(
+        cmd({ id: '1', action: 'state_restore', path: './auth.json', backup: 2 })
+      );
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.action).toBe('state_restore');
+      }
+    });
+
+    it('should reject state_restore with backup 0', () => {
+      const result = parseCommand(cmd({ id: '1', action: 'state_restore', path: 'a', backup: 0 }));
+      expect(result.success).toBe(false);
+    });
+
+    it('should parse state_migrate command', () => {
+      expect(parseCommand(cmd({ id: '1', action: 'state_migrate' })).success).toBe(true);
+      const result = parseCommand(
+        cmd({ id: '1', action: 'state_migrate', path: './auth.json', dryRun: true })
+      );
+      expect(result.success).toBe(true);
+      if (result.success) {
+        expect(result.command.action).toBe('state_migrate');
+      }
+    });
   });
 });
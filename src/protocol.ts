@@ -5,6 +5,22 @@ import type { Command, Response } from './types.js';
 const baseCommandSchema = z.object({
   id: z.string(),
   action: z.string(),
+  // Queue the command and return a job id instead of waiting for it
+  noWait: z.boolean().optional(),
+  // Network limits while the command runs (or for the whole session)
+  budget: z
+    .object({
+      maxBytes: z.number().int().positive().optional(),
+      maxRequests: z.number().int().positive().optional(),
+      scope: z.enum(['command', 'session']).optional(),
+    })
+    .optional(),
+  // Resource types to abort while the command runs
+  block: z.array(z.enum(['image', 'font', 'media', 'stylesheet', 'script'])).optional(),
+  // Access token, required once the tokens file lists any
+  token: z.string().optional(),
+  // Reads only: answer from a response to the same read younger than this (ms)
+  cache: z.number().int().positive().optional(),
 });
 
 // Individual action schemas
@@ -45,13 +61,61 @@ const launchSchema = baseCommandSchema.extend({
   args: z.array(z.string()).optional(),
   userAgent: z.string().optional(),
   provider: z.string().optional(),
+  headlessMode: z.enum(['new', 'old', 'shell']).optional(),
+  dns: z
+    .object({
+      family: z.union([z.literal(4), z.literal(6)]).optional(),
+      dohUrl: z.string().url().startsWith('https://').optional(),
+    })
+    .optional(),
+  proxyPool: z
+    .object({
+      proxies: z
+        .array(
+          z.object({
+            server: z.string().regex(/^https?:\/\//),
+            username: z.string().optional(),
+            password: z.string().optional(),
+          })
+        )
+        .min(1),
+      rotate: z.enum(['per-navigation', 'per-session']).optional(),
+    })
+    .optional(),
+  maxMemory: z.number().int().positive().optional(),
+  janitor: z.array(z.enum(['blank', 'popups', 'contexts'])).optional(),
+  pool: z
+    .object({
+      url: z.string().regex(/^(wss?|https?):\/\//, {
+        message: 'Pool URL must start with ws://, wss://, http://, or https://',
+      }),
+      capabilities: z.record(z.unknown()).optional(),
+    })
+    .optional(),
+  ignoreHTTPSErrors: z.boolean().optional(),
+  extraCaCerts: z.string().min(1).optional(),
+  allowHosts: z.array(z.string().min(1)).optional(),
+  blockHosts: z.array(z.string().min(1)).optional(),
 });
 
 const navigateSchema = baseCommandSchema.extend({
   action: z.literal('navigate'),
   url: z.string().min(1),
-  waitUntil: z.enum(['load', 'domcontentloaded', 'networkidle']).optional(),
+  waitUntil: z.enum(['load', 'domcontentloaded', 'networkidle', 'commit']).optional(),
+  referer: z.string().min(1).optional(),
+  newTab: z.boolean().optional(),
   headers: z.record(z.string()).optional(),
+  headersScope: z.enum(['origin', 'navigation']).optional(),
+  originHeaders: z.record(z.record(z.string())).optional(),
+  rotation: z
+    .object({
+      userAgent: z.string().min(1).optional(),
+      acceptLanguage: z.string().min(1).optional(),
+      viewport: z
+        .object({ width: z.number().int().positive(), height: z.number().int().positive() })
+        .optional(),
+    })
+    .optional(),
 });
 
 const clickSchema = baseCommandSchema.extend({
@@ -60,7 +124,15 @@ const clickSchema = baseCommandSchema.extend({
   button: z.enum(['left', 'right', 'middle']).optional(),
   clickCount: z.number().positive().optional(),
   delay: z.number().nonnegative().optional(),
+  force: z.boolean().optional(),
+  scrollMargin: z.number().int().nonnegative().optional(),
   newTab: z.boolean().optional(),
+  expectNavigation: z.boolean().optional(),
+  expectPopup: z.boolean().optional(),
+  expectClipboard: z.boolean().optional(),
+  interceptSubmit: z.boolean().optional(),
+  allowSubmit: z.boolean().optional(),
+  waitUntil: z.enum(['load', 'domcontentloaded', 'networkidle', 'commit']).optional(),
 });
 
 const typeSchema = baseCommandSchema.extend({
@@ -69,6 +141,7 @@ const typeSchema = baseCommandSchema.extend({
   text: z.string(),
   delay: z.number().nonnegative().optional(),
   clear: z.boolean().optional(),
+  force: z.boolean().optional(),
 });
 
 const fillSchema = baseCommandSchema.extend({
@@ -193,6 +266,26 @@ const storageClearSchema = baseCommandSchema.extend({
   type: z.enum(['local', 'session']),
 });
 
+const indexedDbListSchema = baseCommandSchema.extend({
+  action: z.literal('indexeddb_list'),
+});
+
+const indexedDbDumpSchema = baseCommandSchema.extend({
+  action: z.literal('indexeddb_dump'),
+  db: z.string().min(1),
+  store: z.string().min(1).optional(),
+  limit: z.number().int().positive().optional(),
+});
+
+const storageOriginsSchema = baseCommandSchema.extend({
+  action: z.literal('storage_origins'),
+});
+
+const storageClearOriginSchema = baseCommandSchema.extend({
+  action: z.literal('storage_clear_origin'),
+  origin: z.string().min(1),
+});
+
 const dialogSchema = baseCommandSchema.extend({
   action: z.literal('dialog'),
   response: z.enum(['accept', 'dismiss']),
@@ -205,6 +298,7 @@ const pdfSchema = baseCommandSchema.extend({
   format: z
     .enum(['Letter', 'Legal', 'Tabloid', 'Ledger', 'A0', 'A1', 'A2', 'A3', 'A4', 'A5', 'A6'])
     .optional(),
+  handoff: z.string().min(1).optional(),
 });
 
 const routeSchema = baseCommandSchema.extend({
@@ -221,6 +315,13 @@ const routeSchema = baseCommandSchema.extend({
   abort: z.boolean().optional(),
 });
 
+const routeHarSchema = baseCommandSchema.extend({
+  action: z.literal('route_har'),
+  path: z.string().min(1),
+  url: z.string().min(1).optional(),
+  notFound: z.enum(['fallback', 'abort']).optional(),
+});
+
 const unrouteSchema = baseCommandSchema.extend({
   action: z.literal('unroute'),
   url: z.string().optional(),
@@ -232,10 +333,36 @@ const requestsSchema = baseCommandSchema.extend({
   clear: z.boolean().optional(),
 });
 
+const assertRequestSchema = baseCommandSchema.extend({
+  action: z.literal('assert_request'),
+  pattern: z.string().min(1),
+  sent: z.boolean(),
+  method: z.string().min(1).optional(),
+  status: z
+    .string()
+    .regex(/^[1-5](\d\d|xx)$/i, 'Status must be a code like 204 or a class like 2xx')
+    .optional(),
+  bodyPath: z.string().startsWith('$').optional(),
+  equals: z.string().optional(),
+});
+
+const securityAuditSchema = baseCommandSchema.extend({
+  action: z.literal('security_audit'),
+});
+
+const checkLinksSchema = baseCommandSchema.extend({
+  action: z.literal('check_links'),
+  sameOrigin: z.boolean().optional(),
+  concurrency: z.number().int().min(1).max(64).optional(),
+  timeout: z.number().int().positive().optional(),
+});
+
 const downloadSchema = baseCommandSchema.extend({
   action: z.literal('download'),
   selector: z.string().min(1),
-  path: z.string().min(1),
+  path: z.string().min(1).optional(),
+  maxBytes: z.number().int().positive().optional(),
+  blockedTypes: z.array(z.string().min(1)).optional(),
 });
 
 const geolocationSchema = baseCommandSchema.extend({
@@ -269,18 +396,26 @@ const deviceSchema = baseCommandSchema.extend({
 
 const backSchema = baseCommandSchema.extend({
   action: z.literal('back'),
+  steps: z.number().int().positive().optional(),
 });
 
 const forwardSchema = baseCommandSchema.extend({
   action: z.literal('forward'),
+  steps: z.number().int().positive().optional(),
 });
 
 const reloadSchema = baseCommandSchema.extend({
   action: z.literal('reload'),
+  ignoreCache: z.boolean().optional(),
+});
+
+const stopSchema = baseCommandSchema.extend({
+  action: z.literal('stop'),
 });
 
 const urlSchema = baseCommandSchema.extend({
   action: z.literal('url'),
+  includeTitle: z.boolean().optional(),
 });
 
 const titleSchema = baseCommandSchema.extend({
@@ -293,9 +428,28 @@ const getAttributeSchema = baseCommandSchema.extend({
   attribute: z.string().min(1),
 });
 
+const hashIgnoreSchema = z
+  .object({
+    selectors: z.array(z.string().min(1)).optional(),
+    patterns: z.array(z.string().min(1)).optional(),
+  })
+  .optional();
+
 const getTextSchema = baseCommandSchema.extend({
   action: z.literal('gettext'),
   selector: z.string().min(1),
+  hashIgnore: hashIgnoreSchema,
+});
+
+const textSchema = baseCommandSchema.extend({
+  action: z.literal('text'),
+  selector: z.string().min(1).optional(),
+  savePdf: z.boolean().optional(),
+  hashIgnore: hashIgnoreSchema,
+});
+
+const articleSchema = baseCommandSchema.extend({
+  action: z.literal('article'),
 });
 
 const isVisibleSchema = baseCommandSchema.extend({
@@ -313,6 +467,11 @@ const isCheckedSchema = baseCommandSchema.extend({
   selector: z.string().min(1),
 });
 
+const elementStateSchema = baseCommandSchema.extend({
+  action: z.literal('elementstate'),
+  selector: z.string().min(1),
+});
+
 const countSchema = baseCommandSchema.extend({
   action: z.literal('count'),
   selector: z.string().min(1),
@@ -326,6 +485,10 @@ const boundingBoxSchema = baseCommandSchema.extend({
 const stylesSchema = baseCommandSchema.extend({
   action: z.literal('styles'),
   selector: z.string().min(1),
+  props: z
+    .array(z.string().regex(/^(--)?[a-zA-Z][a-zA-Z0-9-]*$/, 'Invalid CSS property name'))
+    .min(1)
+    .optional(),
 });
 
 const videoStartSchema = baseCommandSchema.extend({
@@ -354,6 +517,17 @@ const recordingRestartSchema = baseCommandSchema.extend({
   url: z.string().min(1).optional(),
 });
 
+const gifStartSchema = baseCommandSchema.extend({
+  action: z.literal('gif_start'),
+  path: z.string().regex(/\.(gif|a?png)$/i, 'Output must end in .gif, .png or .apng'),
+  commands: z.number().int().positive().max(1000).optional(),
+  delay: z.number().int().min(10).max(60000).optional(),
+});
+
+const gifStopSchema = baseCommandSchema.extend({
+  action: z.literal('gif_stop'),
+});
+
 const traceStartSchema = baseCommandSchema.extend({
   action: z.literal('trace_start'),
   screenshots: z.boolean().optional(),
@@ -410,11 +584,96 @@ const stateRenameSchema = baseCommandSchema.extend({
   newName: z.string().min(1),
 });
 
+const stateRestoreSchema = baseCommandSchema.extend({
+  action: z.literal('state_restore'),
+  path: z.string().min(1),
+  backup: z.number().int().positive().optional(),
+});
+
+const stateMigrateSchema = baseCommandSchema.extend({
+  action: z.literal('state_migrate'),
+  path: z.string().min(1).optional(),
+  dryRun: z.boolean().optional(),
+});
+
 const consoleSchema = baseCommandSchema.extend({
   action: z.literal('console'),
   clear: z.boolean().optional(),
 });
 
+const dialogEventsSchema = baseCommandSchema.extend({
+  action: z.literal('dialog_events'),
+  clear: z.boolean().optional(),
+});
+
+const janitorSchema = baseCommandSchema.extend({
+  action: z.literal('janitor'),
+  run: z.boolean().optional(),
+  rules: z.array(z.enum(['blank', 'popups', 'contexts'])).min(1).optional(),
+  clear: z.boolean().optional(),
+});
+
+const jobStatusSchema = baseCommandSchema.extend({
+  action: z.literal('job_status'),
+  jobId: z.string().min(1),
+});
+
+const jobResultSchema = baseCommandSchema.extend({
+  action: z.literal('job_result'),
+  jobId: z.string().min(1),
+});
+
+const jobListSchema = baseCommandSchema.extend({
+  action: z.literal('job_list'),
+});
+
+const jobCancelSchema = baseCommandSchema.extend({
+  action: z.literal('job_cancel'),
+  jobId: z.string().min(1),
+});
+
+const prelaunchSchema = baseCommandSchema.extend({
+  action: z.literal('prelaunch'),
+});
+
+// A session name only: it becomes the names of the daemon's files
+const warmClaimSchema = baseCommandSchema.extend({
+  action: z.literal('warm_claim'),
+  session: z.string().regex(/^[^./\\][^/\\]*$/, 'Invalid session name'),
+});
+
+const observeSchema = baseCommandSchema.extend({
+  action: z.literal('observe'),
+  screenshots: z.boolean().optional(),
+});
+
+const scheduleAddSchema = baseCommandSchema.extend({
+  action: z.literal('schedule_add'),
+  cron: z.string().min(1),
+  commands: z.array(z.record(z.unknown())).min(1),
+  script: z.string().optional(),
+  jitterMs: z.number().int().nonnegative().optional(),
+});
+
+const scheduleListSchema = baseCommandSchema.extend({
+  action: z.literal('schedule_list'),
+});
+
+const scheduleRemoveSchema = baseCommandSchema.extend({
+  action: z.literal('schedule_remove'),
+  scheduleId: z.string().min(1),
+});
+
+const scheduleHistorySchema = baseCommandSchema.extend({
+  action: z.literal('schedule_history'),
+  scheduleId: z.string().min(1),
+});
+
+const scheduleRunSchema = baseCommandSchema.extend({
+  action: z.literal('schedule_run'),
+  scheduleId: z.string().min(1),
+});
+
 const errorsSchema = baseCommandSchema.extend({
   action: z.literal('errors'),
   clear: z.boolean().optional(),
@@ -466,6 +725,7 @@ const innerTextSchema = baseCommandSchema.extend({
 const innerHtmlSchema = baseCommandSchema.extend({
   action: z.literal('innerhtml'),
   selector: z.string().min(1),
+  hashIgnore: hashIgnoreSchema,
 });
 
 const inputValueSchema = baseCommandSchema.extend({
@@ -565,6 +825,18 @@ const waitForUrlSchema = baseCommandSchema.extend({
   timeout: z.number().positive().optional(),
 });
 
+const waitForRouteSchema = baseCommandSchema.extend({
+  action: z.literal('waitforroute'),
+  route: z.string().min(1),
+  timeout: z.number().positive().optional(),
+});
+
+const spaNavigateSchema = baseCommandSchema.extend({
+  action: z.literal('spanavigate'),
+  route: z.string().min(1),
+  mode: z.enum(['hash', 'history']),
+});
+
 const waitForLoadStateSchema = baseCommandSchema.extend({
   action: z.literal('waitforloadstate'),
   state: z.enum(['load', 'domcontentloaded', 'networkidle']),
@@ -608,6 +880,30 @@ const mouseUpSchema = baseCommandSchema.extend({
   button: z.enum(['left', 'right', 'middle']).optional(),
 });
 
+const clickAtSchema = baseCommandSchema.extend({
+  action: z.literal('click_at'),
+  x: z.number().nonnegative(),
+  y: z.number().nonnegative(),
+  button: z.enum(['left', 'right', 'middle']).optional(),
+  css: z.boolean().optional(),
+});
+
+const moveAtSchema = baseCommandSchema.extend({
+  action: z.literal('move_at'),
+  x: z.number().nonnegative(),
+  y: z.number().nonnegative(),
+  css: z.boolean().optional(),
+});
+
+const dragAtSchema = baseCommandSchema.extend({
+  action: z.literal('drag_at'),
+  fromX: z.number().nonnegative(),
+  fromY: z.number().nonnegative(),
+  toX: z.number().nonnegative(),
+  toY: z.number().nonnegative(),
+  css: z.boolean().optional(),
+});
+
 const bringToFrontSchema = baseCommandSchema.extend({
   action: z.literal('bringtofront'),
 });
@@ -621,6 +917,7 @@ const waitForFunctionSchema = baseCommandSchema.extend({
 const scrollIntoViewSchema = baseCommandSchema.extend({
   action: z.literal('scrollintoview'),
   selector: z.string().min(1),
+  scrollMargin: z.number().int().nonnegative().optional(),
 });
 
 const addInitScriptSchema = baseCommandSchema.extend({
@@ -653,6 +950,8 @@ const waitForDownloadSchema = baseCommandSchema.extend({
   action: z.literal('waitfordownload'),
   path: z.string().optional(),
   timeout: z.number().positive().optional(),
+  maxBytes: z.number().int().positive().optional(),
+  blockedTypes: z.array(z.string().min(1)).optional(),
 });
 
 const responseBodySchema = baseCommandSchema.extend({
@@ -723,6 +1022,9 @@ const screenshotSchema = baseCommandSchema.extend({
   selector: z.string().min(1).nullish(),
   format: z.enum(['png', 'jpeg']).optional(),
   quality: z.number().min(0).max(100).optional(),
+  annotate: z.boolean().optional(),
+  grid: z.number().int().min(10).max(2000).optional(),
+  handoff: z.string().min(1).optional(),
 });
 
 const snapshotSchema = baseCommandSchema.extend({
@@ -731,6 +1033,8 @@ const snapshotSchema = baseCommandSchema.extend({
   maxDepth: z.number().nonnegative().optional(),
   compact: z.boolean().optional(),
   selector: z.string().optional(),
+  noCache: z.boolean().optional(),
+  diffFrom: z.string().min(1).optional(),
 });
 
 const evaluateSchema = baseCommandSchema.extend({
@@ -843,13 +1147,21 @@ const commandSchema = z.discriminatedUnion('action', [
   cookiesSetSchema,
   cookiesClearSchema,
   storageGetSchema,
+  indexedDbListSchema,
+  indexedDbDumpSchema,
+  storageOriginsSchema,
+  storageClearOriginSchema,
   storageSetSchema,
   storageClearSchema,
   dialogSchema,
   pdfSchema,
   routeSchema,
+  routeHarSchema,
   unrouteSchema,
   requestsSchema,
+  assertRequestSchema,
+  securityAuditSchema,
+  checkLinksSchema,
   downloadSchema,
   geolocationSchema,
   permissionsSchema,
@@ -859,13 +1171,17 @@ const commandSchema = z.discriminatedUnion('action', [
   backSchema,
   forwardSchema,
   reloadSchema,
+  stopSchema,
   urlSchema,
   titleSchema,
   getAttributeSchema,
   getTextSchema,
+  textSchema,
+  articleSchema,
   isVisibleSchema,
   isEnabledSchema,
   isCheckedSchema,
+  elementStateSchema,
   countSchema,
   boundingBoxSchema,
   stylesSchema,
@@ -874,6 +1190,8 @@ const commandSchema = z.discriminatedUnion('action', [
   recordingStartSchema,
   recordingStopSchema,
   recordingRestartSchema,
+  gifStartSchema,
+  gifStopSchema,
   traceStartSchema,
   traceStopSchema,
   harStartSchema,
@@ -885,7 +1203,23 @@ const commandSchema = z.discriminatedUnion('action', [
   stateShowSchema,
   stateCleanSchema,
   stateRenameSchema,
+  stateRestoreSchema,
+  stateMigrateSchema,
   consoleSchema,
+  dialogEventsSchema,
+  janitorSchema,
+  jobStatusSchema,
+  jobResultSchema,
+  jobListSchema,
+  jobCancelSchema,
+  prelaunchSchema,
+  warmClaimSchema,
+  observeSchema,
+  scheduleAddSchema,
+  scheduleListSchema,
+  scheduleRemoveSchema,
+  scheduleHistorySchema,
+  scheduleRunSchema,
   errorsSchema,
   keyboardSchema,
   wheelSchema,
@@ -912,6 +1246,8 @@ const commandSchema = z.discriminatedUnion('action', [
   getByTestIdSchema,
   nthSchema,
   waitForUrlSchema,
+  waitForRouteSchema,
+  spaNavigateSchema,
   waitForLoadStateSchema,
   setContentSchema,
   timezoneSchema,
@@ -920,6 +1256,9 @@ const commandSchema = z.discriminatedUnion('action', [
   mouseMoveSchema,
   mouseDownSchema,
   mouseUpSchema,
+  clickAtSchema,
+  moveAtSchema,
+  dragAtSchema,
   bringToFrontSchema,
   waitForFunctionSchema,
   scrollIntoViewSchema,
@@ -0,0 +1,73 @@
+import { describe, it, expect } from 'vitest';
+import * as net from 'net';
+import { ProxyPool, proxyAuthorization, tunnelThroughPool } from './proxy-pool.js';
+
+/** A fake upstream proxy answering every CONNECT with `reply` */
+async function fakeProxy(reply: string): Promise<{ server: string; close: () => void }> {
+  const server = net.createServer((socket) => {
+    socket.once('data', () => socket.write(reply));
+    socket.on('error', () => {});
+  });
+  await new Promise<void>((resolve) => server.listen(0, '127.0.0.1', resolve));
+  const { port } = server.address() as net.AddressInfo;
+  return { server: `http://127.0.0.1:${port}`, close: () => server.close() };
+}
+
+describe('proxy-pool', () => {
+  it('rotates and skips failed proxies until their cooldown ends', () => {
+    let now = 0;
+    const pool = new ProxyPool(
+      [{ server: 'http://a:1' }, { server: 'http://b:1' }, { server: 'http://c:1' }],
+      'per-navigation',
+      () => now
+    );
+    expect(pool.current().server).toBe('http://a:1');
+    pool.markFailed(pool.proxies[1]);
+    expect(pool.next().server).toBe('http://c:1');
+    expect(pool.next().server).toBe('http://a:1');
+    pool.markFailed(pool.current());
+    expect(pool.current().server).toBe('http://c:1');
+    expect(pool.status().filter((p) => p.healthy)).toHaveLength(1);
+
+    now = 60_000;
+    expect(pool.next().server).toBe('http://a:1');
+    expect(() => new ProxyPool([])).toThrow('empty');
+  });
+
+  it('builds basic proxy credentials', () => {
+    expect(proxyAuthorization({ server: 'http://p:1', username: 'u', password: 'p' })).toBe(
+      'Basic dTpw'
+    );
+    expect(proxyAuthorization({ server: 'http://p:1' })).toBeUndefined();
+  });
+
+  it('fails over when a proxy is unreachable or rejects credentials', async () => {
+    const denied = await fakeProxy('HTTP/1.1 407 Proxy Authentication Required\r\n\r\n');
+    const working = await fakeProxy('HTTP/1.1 200 Connection Established\r\n\r\n');
+    try {
+      const pool = new ProxyPool([
+        { server: 'http://127.0.0.1:1' },
+        { server: denied.server },
+        { server: working.server },
+      ]);
+      const socket = await tunnelThroughPool(pool, 'example.com', 443);
+      socket.destroy();
+      expect(pool.current().server).toBe(working.server);
+      expect(pool.status().map((p) => p.healthy)).toEqual([false, false, true]);
+    } finally {
+      denied.close();
+      working.close();
+    }
+  });
+
+  it('does not blame the proxy for an unreachable target', async () => {
+    const gateway = await fakeProxy('HTTP/1.1 502 Bad Gateway\r\n\r\n');
+    try {
+      const pool = new ProxyPool([{ server: gateway.server }, { server: 'http://b:1' }]);
+      await expect(tunnelThroughPool(pool, 'example.com', 443)).rejects.toThrow('status 502');
+      expect(pool.current().server).toBe(gateway.server);
+    } finally {
+      gateway.close();
+    }
+  });
+});
@@ -0,0 +1,192 @@
+import * as net from 'net';
+import * as tls from 'tls';
+
+export interface UpstreamProxy {
+  server: string;
+  username?: string;
+  password?: string;
+}
+
+export type RotateMode = 'per-navigation' | 'per-session';
+
+/** How long a proxy that failed to connect is skipped */
+const FAILURE_COOLDOWN_MS = 60_000;
+const CONNECT_TIMEOUT_MS = 15_000;
+
+/**
+ * The upstream proxies of --proxy-pool. One proxy is current at a time; it
+ * changes on rotation (every navigation with --rotate per-navigation) and when
+ * it fails to connect. Failed proxies are skipped until their cooldown ends.
+ */
+export class ProxyPool {
+  private index = 0;
+  private failedAt = new Map<number, number>();
+
+  constructor(
+    readonly proxies: UpstreamProxy[],
+    readonly rotate: RotateMode = 'per-session',
+    private now: () => number = Date.now
+  ) {
+    if (proxies.length === 0) throw new Error('Proxy pool is empty');
+  }
+
+  current(): UpstreamProxy {
+    return this.proxies[this.index];
+  }
+
+  private healthy(index: number): boolean {
+    const failedAt = this.failedAt.get(index);
+    return failedAt === undefined || this.now() - failedAt >= FAILURE_COOLDOWN_MS;
+  }
+
+  /**
+   * Move to the next healthy proxy, or just the next one when none is healthy
+   */
+  next(): UpstreamProxy {
+    const count = this.proxies.length;
+    for (let step = 1; step <= count; step++) {
+      const index = (this.index + step) % count;
+      if (this.healthy(index)) {
+        this.index = index;
+        return this.current();
+      }
+    }
+    this.index = (this.index + 1) % count;
+    return this.current();
+  }
+
+  /**
+   * Record a connect failure, failing over if it was the current proxy
+   */
+  markFailed(proxy: UpstreamProxy): void {
+    const index = this.proxies.indexOf(proxy);
+    if (index === -1) return;
+    this.failedAt.set(index, this.now());
+    if (index === this.index) this.next();
+  }
+
+  markHealthy(proxy: UpstreamProxy): void {
+    this.failedAt.delete(this.proxies.indexOf(proxy));
+  }
+
+  status(): { server: string; healthy: boolean; current: boolean }[] {
+    return this.proxies.map((proxy, index) => ({
+      server: proxy.server,
+      healthy: this.healthy(index),
+      current: index === this.index,
+    }));
+  }
+}
+
+/**
+ * Value of the Proxy-Authorization header for a proxy with credentials
+ */
+export function proxyAuthorization(proxy: UpstreamProxy): string | undefined {
+  if (!proxy.username) return undefined;
+  const credentials = `${proxy.username}:${proxy.password ?? ''}`;
+  return `Basic ${Buffer.from(credentials).toString('base64')}`;
+}
+
+/** The proxy answered CONNECT with something other than 200 */
+export class TunnelRefused extends Error {
+  constructor(
+    readonly status: number,
+    server: string
+  ) {
+    super(`${server} refused the tunnel with status ${status}`);
+  }
+}
+
+/**
+ * Host and port to connect to for a proxy server URL
+ */
+export function proxyAddress(server: string): { host: string; port: number; tls: boolean } {
+  const url = new URL(server);
+  const secure = url.protocol === 'https:';
+  return {
+    host: url.hostname.replace(/^\[|\]$/g, ''),
+    port: Number(url.port) || (secure ? 443 : 80),
+    tls: secure,
+  };
+}
+
+/**
+ * Open a CONNECT tunnel to host:port through an upstream proxy. The socket
+ * comes back paused with anything read past the proxy's reply unshifted.
+ */
+export function tunnelThrough(
+  proxy: UpstreamProxy,
+  host: string,
+  port: number
+): Promise<net.Socket> {
+  return new Promise((resolve, reject) => {
+    const address = proxyAddress(proxy.server);
+    const socket = address.tls
+      ? tls.connect({
+          host: address.host,
+          port: address.port,
+          servername: net.isIP(address.host) ? undefined : address.host,
+        })
+      : net.connect({ host: address.host, port: address.port });
+    const fail = (err: Error) => {
+      socket.destroy();
+      reject(err);
+    };
+    socket.setTimeout(CONNECT_TIMEOUT_MS, () => fail(new Error(`${proxy.server} timed out`)));
+    socket.once('error', fail);
+
+    const target = net.isIPv6(host) ? `[${host}]:${port}` : `${host}:${port}`;
+    const auth = proxyAuthorization(proxy);
+    socket.write(
+      `CONNECT ${target} HTTP/1.1\r\nHost: ${target}\r\n` +
+        (auth ? `Proxy-Authorization: ${auth}\r\n` : '') +
+        '\r\n'
+    );
+
+    let buffered = Buffer.alloc(0);
+    const onData = (chunk: Buffer) => {
+      buffered = Buffer.concat([buffered, chunk]);
+      const end = buffered.indexOf('\r\n\r\n');
+      if (end === -1) return;
+      socket.off('data', onData);
+      socket.pause();
+      const status = Number(/^HTTP\/[\d.]+ (\d{3})/.exec(buffered.toString('latin1', 0, end))?.[1]);
+      if (status !== 200) {
+        fail(new TunnelRefused(status || 502, proxy.server));
+        return;
+      }
+      socket.off('error', fail);
+      socket.setTimeout(0);
+      const rest = buffered.subarray(end + 4);
+      if (rest.length > 0) socket.unshift(rest);
+      resolve(socket);
+    };
+    socket.on('data', onData);
+  });
+}
+
+/**
+ * Tunnel through the pool's current proxy, failing over to the next one when
+ * it can't be reached or rejects the credentials. Other refusals (the target
+ * is unreachable from the proxy) are not held against the proxy.
+ */
+export async function tunnelThroughPool(
+  pool: ProxyPool,
+  host: string,
+  port: number
+): Promise<net.Socket> {
+  let lastError: unknown;
+  for (let attempt = 0; attempt < pool.proxies.length; attempt++) {
+    const proxy = pool.current();
+    try {
+      const socket = await tunnelThrough(proxy, host, port);
+      pool.markHealthy(proxy);
+      return socket;
+    } catch (err) {
+      if (err instanceof TunnelRefused && err.status !== 407) throw err;
+      pool.markFailed(proxy);
+      lastError = err;
+    }
+  }
+  throw lastError;
+}
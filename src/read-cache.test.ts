@@ -0,0 +1,51 @@
+import { describe, it, expect } from 'vitest';
+import { ReadCache, readCacheKey, READ_CACHE_SIZE } from './read-cache.js';
+import { errorResponse, successResponse } from './protocol.js';
+import type { Command } from './types.js';
+
+const url = 'https://docs.example.com/api';
+
+function read(fields: Record<string, unknown>): Command {
+  return { id: 'r1', action: 'gettext', selector: 'main', ...fields } as Command;
+}
+
+describe('read cache', () => {
+  it('keys reads by URL and options, not by id or TTL', () => {
+    const key = readCacheKey(url, read({ cache: 300000 }));
+    expect(readCacheKey(url, read({ id: 'r2', cache: 1000, token: 't' }))).toBe(key);
+    expect(readCacheKey(`${url}#v2`, read({}))).not.toBe(key);
+    expect(readCacheKey(url, read({ selector: 'nav' }))).not.toBe(key);
+    expect(readCacheKey(url, { id: '1', action: 'click', selector: 'a' } as Command)).toBeNull();
+  });
+
+  it('answers within the TTL under the new id', () => {
+    let now = 1000;
+    const cache = new ReadCache(() => now);
+    const key = readCacheKey(url, read({}));
+    cache.set(key, successResponse('r1', { text: 'Reference' }));
+    now += 4000;
+    expect(cache.get(key, 5000, 'r2')).toEqual({
+      id: 'r2',
+      success: true,
+      data: { text: 'Reference' },
+      meta: { cache: true, cacheAge: 4000 },
+    });
+    expect(cache.get(key, 4000, 'r3')).toBeNull();
+    expect(cache.get(null, 5000, 'r4')).toBeNull();
+  });
+
+  it('keeps no errors or PDF files, and only the latest reads', () => {
+    const cache = new ReadCache(() => 0);
+    const key = readCacheKey(url, read({}));
+    cache.set(key, errorResponse('r1', 'Timeout'));
+    expect(cache.get(key, 1000, 'r2')).toBeNull();
+    cache.set(key, successResponse('r1', { pdf: { path: '/tmp/pdf-1.pdf' } }));
+    expect(cache.get(key, 1000, 'r2')).toBeNull();
+
+    cache.set(key, successResponse('r1', { text: 'first' }));
+    for (let i = 0; i < READ_CACHE_SIZE; i++) {
+      cache.set(readCacheKey(`${url}/${i}`, read({})), successResponse('r1', { text: `${i}` }));
+    }
+    expect(cache.get(key, 1000, 'r2')).toBeNull();
+  });
+});
@@ -0,0 +1,73 @@
+/**
+ * Opt-in cache for reads that don't change the page (`--cache 5m`). A read
+ * sent with `cache` (a TTL in ms) is answered with the response to the same
+ * read of the same URL if that was stored less than the TTL ago, so agents
+ * that re-read reference pages don't redo the work. Each read states its own
+ * TTL; the cache only keeps the latest READ_CACHE_SIZE responses.
+ */
+
+import type { Command, Response, SuccessResponse } from './types.js';
+
+/** Reads that may be answered from the cache */
+export const CACHEABLE_ACTIONS = new Set([
+  'text',
+  'article',
+  'gettext',
+  'innerhtml',
+  'getattribute',
+  'title',
+  'check_links',
+]);
+
+export const READ_CACHE_SIZE = 100;
+
+/** Fields that route or limit a command instead of saying what it reads */
+const IGNORED_FIELDS = new Set(['id', 'token', 'cache', 'noWait', 'budget', 'block', 'path']);
+
+/**
+ * Key for a read of `url`: the action and its options. Null for commands
+ * that aren't cacheable reads.
+ */
+export function readCacheKey(url: string, command: Command): string | null {
+  if (!CACHEABLE_ACTIONS.has(command.action)) return null;
+  const options = Object.entries(command)
+    .filter(([name, value]) => !IGNORED_FIELDS.has(name) && value !== undefined)
+    .sort(([a], [b]) => a.localeCompare(b));
+  return JSON.stringify({ url, options });
+}
+
+/** Responses worth keeping: successful, and not pointing at a file (a PDF's text) */
+function storable(response: Response): response is SuccessResponse {
+  if (!response.success) return false;
+  const data = response.data as Record<string, unknown> | null;
+  return !(data && typeof data === 'object' && 'pdf' in data);
+}
+
+export class ReadCache {
+  private entries = new Map<string, { response: SuccessResponse; storedAt: number }>();
+
+  constructor(private now: () => number = Date.now) {}
+
+  /** The stored response to the read, if younger than `ttlMs`, under the new id */
+  get(key: string | null, ttlMs: number, id: string): Response | null {
+    const entry = key === null ? undefined : this.entries.get(key);
+    const age = entry ? this.now() - entry.storedAt : Infinity;
+    if (!entry || age >= ttlMs) return null;
+    return { ...entry.response, id, meta: { ...entry.response.meta, cache: true, cacheAge: age } };
+  }
+
+  set(key: string | null, response: Response): void {
+    if (key === null || !storable(response)) return;
+    this.entries.delete(key);
+    // A copy: the daemon adds to the data of the response it sends
+    this.entries.set(key, { response: structuredClone(response), storedAt: this.now() });
+    if (this.entries.size > READ_CACHE_SIZE) {
+      const oldest = this.entries.keys().next().value;
+      if (oldest !== undefined) this.entries.delete(oldest);
+    }
+  }
+
+  clear(): void {
+    this.entries.clear();
+  }
+}
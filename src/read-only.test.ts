@@ -0,0 +1,27 @@
+import { describe, it, expect } from 'vitest';
+import { insideForm, isMutatingMethod } from './read-only.js';
+
+/** Just enough of an element for insideForm */
+function element(closestForm: boolean, formOwner: boolean): Element {
+  return {
+    closest: (selector: string) => (selector === 'form' && closestForm ? {} : null),
+    form: formOwner ? {} : null,
+  } as unknown as Element;
+}
+
+describe('read-only sessions', () => {
+  it('lets only safe methods through', () => {
+    expect(isMutatingMethod('GET')).toBe(false);
+    expect(isMutatingMethod('head')).toBe(false);
+    expect(isMutatingMethod('OPTIONS')).toBe(false);
+    for (const method of ['POST', 'PUT', 'PATCH', 'DELETE']) {
+      expect(isMutatingMethod(method)).toBe(true);
+    }
+  });
+
+  it('finds elements in forms by ancestry or form attribute', () => {
+    expect(insideForm(element(true, false))).toBe(true);
+    expect(insideForm(element(false, true))).toBe(true);
+    expect(insideForm(element(false, false))).toBe(false);
+  });
+});
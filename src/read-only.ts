@@ -0,0 +1,37 @@
+/**
+ * `--read-only`: browse without changing anything on the server. Requests
+ * other than GET, HEAD and OPTIONS are aborted, forms can't be submitted and
+ * clicks on elements inside forms are refused.
+ */
+
+const SAFE_METHODS = new Set(['GET', 'HEAD', 'OPTIONS']);
+
+export function isMutatingMethod(method: string): boolean {
+  return !SAFE_METHODS.has(method.toUpperCase());
+}
+
+/**
+ * Cancels every form submission in the page: submit events (buttons,
+ * Enter, requestSubmit) in the capture phase before page handlers see them,
+ * and form.submit(), which fires no event. Logged to the page console.
+ */
+export const READ_ONLY_FORM_SCRIPT = `(() => {
+  if (window.__agentBrowserReadOnly) return;
+  Object.defineProperty(window, '__agentBrowserReadOnly', { value: true });
+  const blocked = (form) =>
+    console.warn('[agent-browser] read-only session: blocked form submission to ' +
+      (form.action || location.href));
+  window.addEventListener('submit', (event) => {
+    event.preventDefault();
+    event.stopImmediatePropagation();
+    blocked(event.target);
+  }, true);
+  HTMLFormElement.prototype.submit = function () {
+    blocked(this);
+  };
+})();`;
+
+/** Whether an element is in a form, by ancestry or by its form attribute */
+export function insideForm(element: Element): boolean {
+  return !!element.closest('form') || !!(element as HTMLButtonElement).form;
+}
@@ -0,0 +1,90 @@
+import { describe, it, expect } from 'vitest';
+import {
+  checkRequests,
+  parseBodyPath,
+  urlMatches,
+  type CapturedRequest,
+} from './request-assert.js';
+
+const requests: CapturedRequest[] = [
+  { url: 'https://shop.example.com/cart', method: 'GET', postData: null, status: 200 },
+  {
+    url: 'https://shop.example.com/api/track',
+    method: 'POST',
+    postData: JSON.stringify({ event: 'purchase', items: [{ id: 42 }] }),
+    status: 204,
+  },
+  {
+    url: 'https://stats.example.net/collect?v=2',
+    method: 'POST',
+    postData: 'tid=UA-1&t=pageview',
+    status: 503,
+  },
+];
+
+describe('request assertions', () => {
+  it('matches URL globs and substrings', () => {
+    expect(urlMatches('https://shop.example.com/api/track', '**/api/track')).toBe(true);
+    expect(urlMatches('https://shop.example.com/api/track?x=1', '**/api/track')).toBe(false);
+    expect(urlMatches('https://shop.example.com/api/track?x=1', '**/api/track*')).toBe(true);
+    expect(urlMatches('https://a.example.com/x', 'https://*.example.com/x')).toBe(true);
+    expect(urlMatches('https://a.b.com/x/y', 'https://*/y')).toBe(false);
+    expect(urlMatches('https://cdn.example.com/a.js', '**/*.{js,css}')).toBe(true);
+    expect(urlMatches('https://stats.example.net/collect?v=2', 'collect')).toBe(true);
+  });
+
+  it('parses body paths', () => {
+    expect(parseBodyPath('$.items[0].id')).toEqual(['items', 0, 'id']);
+    expect(parseBodyPath("$['user-id']")).toEqual(['user-id']);
+    expect(parseBodyPath('$')).toEqual([]);
+    expect(() => parseBodyPath('event')).toThrow('start it with $');
+    expect(() => parseBodyPath('$.a[x]')).toThrow('at "[x]"');
+  });
+
+  it('checks that a request was or was not sent', () => {
+    expect(checkRequests(requests, { pattern: '**/api/track', sent: true }).passed).toBe(true);
+    const sent = checkRequests(requests, { pattern: '**/api/track', sent: false });
+    expect(sent.passed).toBe(false);
+    expect(sent.message).toContain(
+      '1 request matched: POST https://shop.example.com/api/track 204'
+    );
+    expect(checkRequests(requests, { pattern: 'doubleclick', sent: false }).passed).toBe(true);
+    const missing = checkRequests(requests, { pattern: 'doubleclick', sent: true });
+    expect(missing.message).toContain('no request matched of 3 captured');
+  });
+
+  it('checks method, status and body values', () => {
+    const check = (extra: Partial<Parameters<typeof checkRequests>[1]>) =>
+      checkRequests(requests, { pattern: '**/api/track', sent: true, ...extra }).passed;
+    expect(check({ method: 'post', status: '204' })).toBe(true);
+    expect(check({ method: 'GET' })).toBe(false);
+    expect(check({ status: '2xx' })).toBe(true);
+    expect(check({ status: '200' })).toBe(false);
+    expect(check({ bodyPath: '$.event', equals: 'purchase' })).toBe(true);
+    expect(check({ bodyPath: '$.items[0].id', equals: '42' })).toBe(true);
+    expect(check({ bodyPath: '$.event', equals: 'refund' })).toBe(false);
+    expect(check({ bodyPath: '$.coupon' })).toBe(false);
+    // Form bodies are read as fields
+    const form = checkRequests(requests, {
+      pattern: '**/collect*',
+      sent: true,
+      bodyPath: '$.t',
+      equals: 'pageview',
+      status: '5xx',
+    });
+    expect(form.passed).toBe(true);
+  });
+
+  it('explains a miss when the URL matched', () => {
+    const check = checkRequests(requests, {
+      pattern: '**/api/track',
+      sent: true,
+      bodyPath: '$.event',
+      equals: 'refund',
+    });
+    expect(check.message).toBe(
+      'Expected request **/api/track, $.event = refund, but 1 matched the URL but not the rest: ' +
+        'POST https://shop.example.com/api/track 204'
+    );
+  });
+});
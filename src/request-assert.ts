@@ -0,0 +1,171 @@
+/**
+ * `assert request <pattern>`: checks over the requests captured by request
+ * tracking, for privacy and analytics checks in CI ("the tracker never
+ * fired", "the purchase event was sent with the order id").
+ *
+ * A pattern with `*` is a URL glob matched against the whole URL, as with
+ * `network route`: `**` spans `/`, `*` doesn't, `{a,b}` is either. Without
+ * one it matches any URL containing it, as `network requests --filter`.
+ */
+
+export interface CapturedRequest {
+  url: string;
+  method: string;
+  postData?: string | null;
+  status?: number | null;
+}
+
+export interface RequestExpectation {
+  pattern: string;
+  /** false: no request matching everything else may have been sent */
+  sent: boolean;
+  method?: string;
+  /** Response status: a code or a class like `4xx` */
+  status?: string;
+  /** Path into the request body, `$.event` or `$.items[0].id` */
+  bodyPath?: string;
+  /** What the value at bodyPath must be; without it, the path must exist */
+  equals?: string;
+}
+
+export interface RequestCheck {
+  passed: boolean;
+  matches: CapturedRequest[];
+  /** Why the check failed */
+  message?: string;
+}
+
+export function globToRegExp(glob: string): RegExp {
+  let source = '';
+  let inGroup = false;
+  for (let i = 0; i < glob.length; i++) {
+    const c = glob[i];
+    if (c === '*' && glob[i + 1] === '*') {
+      source += '.*';
+      i++;
+    } else if (c === '*') {
+      source += '[^/]*';
+    } else if (c === '{') {
+      source += '(?:';
+      inGroup = true;
+    } else if (c === '}' && inGroup) {
+      source += ')';
+      inGroup = false;
+    } else if (c === ',' && inGroup) {
+      source += '|';
+    } else {
+      source += c.replace(/[.+?^$|()[\]\\/{}]/g, '\\$&');
+    }
+  }
+  return new RegExp(`^${source}$`);
+}
+
+export function urlMatches(url: string, pattern: string): boolean {
+  return pattern.includes('*') ? globToRegExp(pattern).test(url) : url.includes(pattern);
+}
+
+/** Steps of a `$.a.b[0]` path; throws on anything else */
+export function parseBodyPath(path: string): (string | number)[] {
+  if (!path.startsWith('$')) {
+    throw new Error(`Invalid body path "${path}": start it with $, e.g. $.event`);
+  }
+  const steps: (string | number)[] = [];
+  const step = /\.([^.[\]]+)|\[(\d+)\]|\[["']([^"']*)["']\]/y;
+  step.lastIndex = 1;
+  while (step.lastIndex < path.length) {
+    const at = step.lastIndex;
+    const match = step.exec(path);
+    if (!match) {
+      throw new Error(`Invalid body path "${path}" at "${path.slice(at)}"`);
+    }
+    steps.push(match[2] !== undefined ? Number(match[2]) : (match[1] ?? match[3]));
+  }
+  return steps;
+}
+
+/** A request body as an object: JSON, else form fields */
+export function parseBody(postData: string | null | undefined): unknown {
+  if (!postData) return undefined;
+  try {
+    return JSON.parse(postData);
+  } catch {
+    const params = new URLSearchParams(postData);
+    return params.toString() ? Object.fromEntries(params) : undefined;
+  }
+}
+
+function valueAt(body: unknown, steps: (string | number)[]): unknown {
+  let value = body;
+  for (const step of steps) {
+    if (value === null || typeof value !== 'object') return undefined;
+    value = (value as Record<string | number, unknown>)[step];
+  }
+  return value;
+}
+
+function statusMatches(status: number | null | undefined, expected: string): boolean {
+  if (status === null || status === undefined) return false;
+  const code = String(status);
+  return /^\dxx$/i.test(expected) ? code[0] === expected[0] : code === expected;
+}
+
+/** Whether a value from the body is the one given on the command line */
+function valueEquals(value: unknown, expected: string): boolean {
+  if (value === undefined) return false;
+  return typeof value === 'string' ? value === expected : JSON.stringify(value) === expected;
+}
+
+function expectationText(expectation: RequestExpectation): string {
+  const parts = [`${expectation.method ?? 'request'} ${expectation.pattern}`];
+  if (expectation.status) parts.push(`status ${expectation.status}`);
+  if (expectation.bodyPath) {
+    parts.push(
+      expectation.equals === undefined
+        ? `body with ${expectation.bodyPath}`
+        : `${expectation.bodyPath} = ${expectation.equals}`
+    );
+  }
+  return parts.join(', ');
+}
+
+function summarize(requests: CapturedRequest[]): string {
+  const shown = requests
+    .slice(0, 3)
+    .map((r) => `${r.method} ${r.url}${r.status ? ` ${r.status}` : ''}`);
+  if (requests.length > 3) shown.push(`${requests.length - 3} more`);
+  return shown.join('; ');
+}
+
+export function checkRequests(
+  requests: CapturedRequest[],
+  expectation: RequestExpectation
+): RequestCheck {
+  const steps = expectation.bodyPath ? parseBodyPath(expectation.bodyPath) : null;
+  const method = expectation.method?.toUpperCase();
+  const sameUrl = requests.filter(
+    (r) => urlMatches(r.url, expectation.pattern) && (!method || r.method === method)
+  );
+  const matches = sameUrl.filter((r) => {
+    if (expectation.status && !statusMatches(r.status, expectation.status)) return false;
+    if (!steps) return true;
+    const value = valueAt(parseBody(r.postData), steps);
+    return expectation.equals === undefined
+      ? value !== undefined
+      : valueEquals(value, expectation.equals);
+  });
+
+  if (!expectation.sent) {
+    if (matches.length === 0) return { passed: true, matches };
+    const noun = matches.length === 1 ? 'request' : 'requests';
+    const found = `${matches.length} ${noun} matched: ${summarize(matches)}`;
+    const message = `Expected no ${expectationText(expectation)}, but ${found}`;
+    return { passed: false, matches, message };
+  }
+  if (matches.length > 0) return { passed: true, matches };
+  const found =
+    sameUrl.length === 0
+      ? `no request matched of ${requests.length} captured`
+      : `${sameUrl.length} matched the URL but not the rest: ${summarize(sameUrl)}`;
+  const message = `Expected ${expectationText(expectation)}, but ${found}`;
+  return { passed: false, matches, message };
+}
@@ -0,0 +1,86 @@
+import { describe, it, expect } from 'vitest';
+import { parseCron, nextRun, Scheduler } from './scheduler.js';
+import type { Command } from './types.js';
+
+describe('parseCron', () => {
+  it('parses lists, ranges and steps', () => {
+    const cron = parseCron('*/15 9-17 * * 1-5');
+    expect([...cron.minutes]).toEqual([0, 15, 30, 45]);
+    expect(cron.hours.has(9) && cron.hours.has(17) && !cron.hours.has(18)).toBe(true);
+    expect([...cron.weekdays]).toEqual([1, 2, 3, 4, 5]);
+    expect(cron.dayOr).toBe(false);
+  });
+
+  it('treats 7 as Sunday and expands macros', () => {
+    expect(parseCron('0 0 * * 7').weekdays.has(0)).toBe(true);
+    expect([...parseCron('@daily').hours]).toEqual([0]);
+  });
+
+  it('rejects invalid expressions', () => {
+    expect(() => parseCron('0 7 * *')).toThrow('expected 5 fields');
+    expect(() => parseCron('60 * * * *')).toThrow('Invalid cron field');
+    expect(() => parseCron('*/0 * * * *')).toThrow('Invalid cron field');
+  });
+});
+
+describe('nextRun', () => {
+  it('finds the next matching minute', () => {
+    const from = new Date(2026, 0, 5, 6, 59, 30);
+    expect(nextRun(parseCron('0 7 * * *'), from)).toEqual(new Date(2026, 0, 5, 7, 0));
+    const after = new Date(2026, 0, 5, 7, 0, 0);
+    expect(nextRun(parseCron('0 7 * * *'), after)).toEqual(new Date(2026, 0, 6, 7, 0));
+  });
+
+  it('skips to matching weekdays and months', () => {
+    // 2026-01-09 is a Friday
+    const friday = new Date(2026, 0, 9, 12, 0);
+    expect(nextRun(parseCron('30 8 * * 1'), friday)).toEqual(new Date(2026, 0, 12, 8, 30));
+    expect(nextRun(parseCron('0 0 1 3 *'), friday)).toEqual(new Date(2026, 2, 1, 0, 0));
+  });
+
+  it('matches either day field when both are restricted', () => {
+    // The 15th or any Monday, whichever comes first
+    const from = new Date(2026, 0, 13, 0, 0);
+    expect(nextRun(parseCron('0 0 15 * 1'), from)).toEqual(new Date(2026, 0, 15, 0, 0));
+  });
+});
+
+describe('Scheduler', () => {
+  const open = { id: 'a', action: 'navigate', url: 'https://example.com' };
+  const click = { id: 'b', action: 'click', selector: '#report' };
+
+  it('runs commands in order and records the run', async () => {
+    const seen: string[] = [];
+    const scheduler = new Scheduler(async (command: Command) => {
+      seen.push(command.action);
+      return { success: true };
+    });
+    const schedule = scheduler.add({ cron: '0 7 * * *', commands: [open, click] });
+    expect(schedule.nextRunAt).toBeGreaterThan(Date.now());
+
+    const run = await scheduler.runNow(schedule.id);
+    scheduler.stop();
+    expect(seen).toEqual(['navigate', 'click']);
+    expect(run?.status).toBe('success');
+    expect(scheduler.get(schedule.id)?.history).toHaveLength(1);
+  });
+
+  it('stops at the first failing command', async () => {
+    const scheduler = new Scheduler(async (command: Command) =>
+      command.action === 'navigate' ? { success: false, error: 'offline' } : { success: true }
+    );
+    const schedule = scheduler.add({ cron: '@hourly', commands: [open, click] });
+    const run = await scheduler.runNow(schedule.id);
+    scheduler.stop();
+    expect(run).toMatchObject({ status: 'failed', steps: 1, error: 'offline' });
+  });
+
+  it('rejects invalid commands and cron expressions', () => {
+    const scheduler = new Scheduler(async () => ({ success: true }));
+    expect(() => scheduler.add({ cron: 'daily', commands: [open] })).toThrow('Invalid cron');
+    expect(() =>
+      scheduler.add({ cron: '@daily', commands: [{ id: 'x', action: 'fly' }] })
+    ).toThrow('Command 1');
+    expect(scheduler.list()).toHaveLength(0);
+  });
+});
@@ -0,0 +1,405 @@
+import * as fs from 'fs';
+import * as path from 'path';
+import * as os from 'os';
+import { parseCommand, successResponse, errorResponse } from './protocol.js';
+import type {
+  Command,
+  Response,
+  ScheduleAddCommand,
+  ScheduleListCommand,
+  ScheduleRemoveCommand,
+  ScheduleHistoryCommand,
+  ScheduleRunCommand,
+} from './types.js';
+
+/** Runs kept per schedule for `schedule history` */
+const MAX_HISTORY = 20;
+
+/** Longest single timer; later runs are re-armed when it fires */
+const MAX_TIMER_MS = 24 * 60 * 60 * 1000;
+
+interface CronField {
+  min: number;
+  max: number;
+}
+
+const FIELDS: CronField[] = [
+  { min: 0, max: 59 }, // minute
+  { min: 0, max: 23 }, // hour
+  { min: 1, max: 31 }, // day of month
+  { min: 1, max: 12 }, // month
+  { min: 0, max: 7 }, // day of week (0 and 7 are Sunday)
+];
+
+const MACROS: Record<string, string> = {
+  '@hourly': '0 * * * *',
+  '@daily': '0 0 * * *',
+  '@midnight': '0 0 * * *',
+  '@weekly': '0 0 * * 0',
+  '@monthly': '0 0 1 * *',
+  '@yearly': '0 0 1 1 *',
+  '@annually': '0 0 1 1 *',
+};
+
+export interface CronSchedule {
+  minutes: Set<number>;
+  hours: Set<number>;
+  days: Set<number>;
+  months: Set<number>;
+  weekdays: Set<number>;
+  /** Day of month and day of week were both restricted: either may match */
+  dayOr: boolean;
+}
+
+function parseField(source: string, field: CronField): Set<number> {
+  const values = new Set<number>();
+  for (const part of source.split(',')) {
+    const [range, stepText] = part.split('/');
+    const step = stepText === undefined ? 1 : Number(stepText);
+    let [start, end] = [field.min, field.max];
+    if (range !== '*') {
+      const bounds = range.split('-').map(Number);
+      start = bounds[0];
+      end = bounds.length > 1 ? bounds[1] : stepText === undefined ? start : field.max;
+    }
+    const valid =
+      Number.isInteger(start) &&
+      Number.isInteger(end) &&
+      Number.isInteger(step) &&
+      step > 0 &&
+      start >= field.min &&
+      end <= field.max &&
+      start <= end;
+    if (!valid) {
+      throw new Error(`Invalid cron field '${source}'`);
+    }
+    for (let v = start; v <= end; v += step) values.add(v);
+  }
+  return values;
+}
+
+/**
+ * Parse a five-field cron expression (minute hour day-of-month month
+ * day-of-week) or one of the @hourly/@daily/@weekly/@monthly/@yearly macros
+ */
+export function parseCron(expression: string): CronSchedule {
+  const source = MACROS[expression.trim()] ?? expression.trim();
+  const parts = source.split(/\s+/);
+  if (parts.length !== 5) {
+    throw new Error(
+      `Invalid cron expression '${expression}': expected 5 fields (minute hour day month weekday)`
+    );
+  }
+  const [minutes, hours, days, months, weekdays] = parts.map((p, i) => parseField(p, FIELDS[i]));
+  if (weekdays.delete(7)) weekdays.add(0);
+  return {
+    minutes,
+    hours,
+    days,
+    months,
+    weekdays,
+    dayOr: parts[2] !== '*' && parts[4] !== '*',
+  };
+}
+
+function dayMatches(cron: CronSchedule, date: Date): boolean {
+  const day = cron.days.has(date.getDate());
+  const weekday = cron.weekdays.has(date.getDay());
+  return cron.dayOr ? day || weekday : day && weekday;
+}
+
+/**
+ * First time strictly after `from` (local time, whole minutes) the schedule
+ * matches
+ */
+export function nextRun(cron: CronSchedule, from: Date): Date {
+  const date = new Date(from.getTime());
+  date.setSeconds(0, 0);
+  date.setMinutes(date.getMinutes() + 1);
+  // Five years covers every valid expression, including Feb 29
+  const limit = from.getTime() + 5 * 366 * 24 * 60 * 60 * 1000;
+  while (date.getTime() <= limit) {
+    if (!cron.months.has(date.getMonth() + 1)) {
+      date.setMonth(date.getMonth() + 1, 1);
+      date.setHours(0, 0);
+    } else if (!dayMatches(cron, date)) {
+      date.setDate(date.getDate() + 1);
+      date.setHours(0, 0);
+    } else if (!cron.hours.has(date.getHours())) {
+      date.setHours(date.getHours() + 1, 0);
+    } else if (!cron.minutes.has(date.getMinutes())) {
+      date.setMinutes(date.getMinutes() + 1);
+    } else {
+      return date;
+    }
+  }
+  throw new Error('Cron expression never matches');
+}
+
+export interface ScheduleRun {
+  startedAt: number;
+  finishedAt: number;
+  status: 'success' | 'failed';
+  /** Commands that ran, up to and including a failing one */
+  steps: number;
+  error?: string;
+}
+
+export interface Schedule {
+  id: string;
+  cron: string;
+  /** Script the commands were read from, for listings */
+  script?: string;
+  /** Protocol commands run in order on every run */
+  commands: Record<string, unknown>[];
+  /** Upper bound of the random delay added to each run */
+  jitterMs: number;
+  createdAt: number;
+  nextRunAt?: number;
+  history: ScheduleRun[];
+}
+
+export type ScheduleRunner = (command: Command) => Promise<{ success: boolean; error?: string }>;
+
+/**
+ * Directory holding each session's schedules, so they survive daemon restarts
+ */
+export function getScheduleDir(): string {
+  return (
+    process.env.AGENT_BROWSER_SCHEDULE_DIR || path.join(os.homedir(), '.agent-browser', 'schedules')
+  );
+}
+
+/**
+ * Recurring runs of a command list, managed by a session's daemon.
+ *
+ * Each schedule keeps one timer armed for its next cron match plus a random
+ * jitter. A run sends the commands through `runner` (the daemon's command
+ * queue) one by one and stops at the first failure; the outcome is kept in
+ * the schedule's history. Schedules are written to `file` whenever they
+ * change and loaded again when the daemon starts.
+ */
+export class Scheduler {
+  private schedules = new Map<string, Schedule>();
+  private timers = new Map<string, ReturnType<typeof setTimeout>>();
+  private running = new Set<string>();
+  private nextId = 1;
+
+  constructor(
+    private runner: ScheduleRunner,
+    private file?: string
+  ) {}
+
+  /**
+   * Load saved schedules and arm their timers
+   */
+  start(): void {
+    if (!this.file || !fs.existsSync(this.file)) return;
+    try {
+      const saved = JSON.parse(fs.readFileSync(this.file, 'utf8')) as Schedule[];
+      for (const schedule of saved) {
+        this.schedules.set(schedule.id, schedule);
+        const n = Number(schedule.id.replace(/^sched-/, ''));
+        if (Number.isInteger(n) && n >= this.nextId) this.nextId = n + 1;
+        this.arm(schedule);
+      }
+    } catch (err) {
+      if (process.env.AGENT_BROWSER_DEBUG === '1') {
+        console.error(`[DEBUG] Failed to load schedules from ${this.file}:`, err);
+      }
+    }
+  }
+
+  stop(): void {
+    for (const timer of this.timers.values()) clearTimeout(timer);
+    this.timers.clear();
+  }
+
+  add(options: {
+    cron: string;
+    commands: Record<string, unknown>[];
+    script?: string;
+    jitterMs?: number;
+  }): Schedule {
+    parseCron(options.cron);
+    options.commands.forEach((command, i) => {
+      const result = parseCommand(JSON.stringify(command));
+      if (!result.success) {
+        throw new Error(`Command ${i + 1} of the schedule is invalid: ${result.error}`);
+      }
+    });
+    const schedule: Schedule = {
+      id: `sched-${this.nextId++}`,
+      cron: options.cron,
+      script: options.script,
+      commands: options.commands,
+      jitterMs: options.jitterMs ?? 0,
+      createdAt: Date.now(),
+      history: [],
+    };
+    this.schedules.set(schedule.id, schedule);
+    this.arm(schedule);
+    this.save();
+    return schedule;
+  }
+
+  remove(id: string): boolean {
+    const timer = this.timers.get(id);
+    if (timer) clearTimeout(timer);
+    this.timers.delete(id);
+    const removed = this.schedules.delete(id);
+    if (removed) this.save();
+    return removed;
+  }
+
+  get(id: string): Schedule | undefined {
+    return this.schedules.get(id);
+  }
+
+  list(): Schedule[] {
+    return [...this.schedules.values()];
+  }
+
+  /**
+   * Run a schedule's commands now, in order, stopping at the first failure
+   */
+  async runNow(id: string): Promise<ScheduleRun | undefined> {
+    const schedule = this.schedules.get(id);
+    if (!schedule || this.running.has(id)) return undefined;
+    this.running.add(id);
+    const run: ScheduleRun = { startedAt: Date.now(), finishedAt: 0, status: 'success', steps: 0 };
+    try {
+      for (const raw of schedule.commands) {
+        run.steps++;
+        const parsed = parseCommand(JSON.stringify(raw));
+        const response = parsed.success
+          ? await this.runner(parsed.command)
+          : { success: false, error: parsed.error };
+        if (!response.success) {
+          run.status = 'failed';
+          run.error = response.error;
+          break;
+        }
+      }
+    } catch (err) {
+      run.status = 'failed';
+      run.error = err instanceof Error ? err.message : String(err);
+    } finally {
+      this.running.delete(id);
+    }
+    run.finishedAt = Date.now();
+    schedule.history.push(run);
+    schedule.history.splice(0, Math.max(0, schedule.history.length - MAX_HISTORY));
+    this.save();
+    return run;
+  }
+
+  private arm(schedule: Schedule): void {
+    const jitter = Math.floor(Math.random() * (schedule.jitterMs + 1));
+    const due = nextRun(parseCron(schedule.cron), new Date()).getTime() + jitter;
+    schedule.nextRunAt = due;
+    this.setTimer(schedule.id, due);
+  }
+
+  private setTimer(id: string, due: number): void {
+    const delay = Math.max(0, due - Date.now());
+    const timer = setTimeout(
+      () => {
+        const schedule = this.schedules.get(id);
+        if (!schedule) return;
+        if (delay > MAX_TIMER_MS) {
+          this.setTimer(id, due);
+          return;
+        }
+        void this.runNow(id).finally(() => {
+          if (this.schedules.has(id)) this.arm(schedule);
+        });
+      },
+      Math.min(delay, MAX_TIMER_MS)
+    );
+    // Schedules alone don't keep a process alive (the daemon's socket does)
+    timer.unref?.();
+    this.timers.set(id, timer);
+  }
+
+  private save(): void {
+    if (!this.file) return;
+    try {
+      fs.mkdirSync(path.dirname(this.file), { recursive: true });
+      fs.writeFileSync(this.file, JSON.stringify(this.list(), null, 2));
+    } catch (err) {
+      if (process.env.AGENT_BROWSER_DEBUG === '1') {
+        console.error(`[DEBUG] Failed to save schedules to ${this.file}:`, err);
+      }
+    }
+  }
+}
+
+export type ScheduleCommand =
+  | ScheduleAddCommand
+  | ScheduleListCommand
+  | ScheduleRemoveCommand
+  | ScheduleHistoryCommand
+  | ScheduleRunCommand;
+
+export function isScheduleCommand(command: Command): command is ScheduleCommand {
+  return command.action.startsWith('schedule_');
+}
+
+function summary(schedule: Schedule) {
+  const last = schedule.history[schedule.history.length - 1];
+  return {
+    id: schedule.id,
+    cron: schedule.cron,
+    script: schedule.script,
+    steps: schedule.commands.length,
+    jitterMs: schedule.jitterMs,
+    nextRunAt: schedule.nextRunAt,
+    lastRunAt: last?.startedAt,
+    lastStatus: last?.status,
+    lastError: last?.error,
+  };
+}
+
+/**
+ * Answer the schedule_* commands
+ */
+export async function scheduleResponse(
+  scheduler: Scheduler,
+  command: ScheduleCommand
+): Promise<Response> {
+  try {
+    switch (command.action) {
+      case 'schedule_add': {
+        const schedule = scheduler.add(command);
+        return successResponse(command.id, summary(schedule));
+      }
+      case 'schedule_list':
+        return successResponse(command.id, { schedules: scheduler.list().map(summary) });
+      case 'schedule_remove':
+        return scheduler.remove(command.scheduleId)
+          ? successResponse(command.id, { removed: command.scheduleId })
+          : errorResponse(command.id, `Unknown schedule: ${command.scheduleId}`);
+      case 'schedule_history': {
+        const schedule = scheduler.get(command.scheduleId);
+        return schedule
+          ? successResponse(command.id, { id: schedule.id, history: schedule.history })
+          : errorResponse(command.id, `Unknown schedule: ${command.scheduleId}`);
+      }
+      case 'schedule_run': {
+        if (!scheduler.get(command.scheduleId)) {
+          return errorResponse(command.id, `Unknown schedule: ${command.scheduleId}`);
+        }
+        const run = await scheduler.runNow(command.scheduleId);
+        if (!run) {
+          return errorResponse(command.id, `Schedule ${command.scheduleId} is already running`);
+        }
+        return run.status === 'success'
+          ? successResponse(command.id, { id: command.scheduleId, ...run })
+          : errorResponse(command.id, `Schedule ${command.scheduleId} failed: ${run.error}`);
+      }
+    }
+  } catch (err) {
+    return errorResponse(command.id, err instanceof Error ? err.message : String(err));
+  }
+}
@@ -0,0 +1,83 @@
+import { describe, it, expect } from 'vitest';
+import {
+  cookieIssues,
+  cspReport,
+  mixedContent,
+  siteOf,
+  thirdPartyOrigins,
+} from './security-audit.js';
+
+describe('security audit', () => {
+  it('flags cookie attributes', () => {
+    const cookie = { name: 'sid', domain: 'example.com', path: '/' };
+    expect(cookieIssues({ ...cookie, secure: true, httpOnly: true, sameSite: 'Lax' })).toEqual([]);
+    expect(cookieIssues({ ...cookie, secure: false, httpOnly: false, sameSite: 'None' })).toEqual([
+      'missing Secure',
+      'missing HttpOnly',
+      'SameSite=None without Secure',
+    ]);
+    expect(cookieIssues({ ...cookie, secure: true, httpOnly: true, sameSite: 'None' })).toEqual([
+      'SameSite=None',
+    ]);
+  });
+
+  it('groups hosts by site', () => {
+    expect(siteOf('www.example.com')).toBe('example.com');
+    expect(siteOf('cdn.shop.example.co.uk')).toBe('example.co.uk');
+    expect(siteOf('example.de')).toBe('example.de');
+    expect(siteOf('127.0.0.1')).toBe('127.0.0.1');
+  });
+
+  it('lists third-party origins', () => {
+    const origins = thirdPartyOrigins('https://www.example.com/shop', [
+      'https://cdn.example.com/app.js',
+      'https://www.google-analytics.com/collect?a=1',
+      'https://www.google-analytics.com/collect?a=2',
+      'https://fonts.gstatic.com/font.woff2',
+      'data:image/png;base64,AAAA',
+    ]);
+    expect(origins).toEqual([
+      { origin: 'https://www.google-analytics.com', requests: 2 },
+      { origin: 'https://fonts.gstatic.com', requests: 1 },
+    ]);
+  });
+
+  it('finds mixed content on https pages only', () => {
+    const resources = {
+      refs: [
+        { tag: 'img', url: 'http://example.com/logo.png' },
+        { tag: 'script', url: 'https://example.com/app.js' },
+      ],
+      loaded: ['http://example.com/logo.png', 'http://tracker.example.net/p.gif'],
+      metaCsp: [],
+    };
+    expect(mixedContent('https://example.com/', resources)).toEqual([
+      { tag: 'img', url: 'http://example.com/logo.png' },
+      { tag: 'resource', url: 'http://tracker.example.net/p.gif' },
+    ]);
+    expect(mixedContent('http://example.com/', resources)).toEqual([]);
+  });
+
+  it('reports the CSP and its weak spots', () => {
+    expect(cspReport({}, []).issues).toEqual(['no Content-Security-Policy']);
+    const reportOnlyHeader = { 'content-security-policy-report-only': "default-src 'self'" };
+    const reportOnly = cspReport(reportOnlyHeader, []);
+    expect(reportOnly.present).toBe(false);
+    expect(reportOnly.issues).toEqual(['report-only, not enforced']);
+
+    const header = cspReport(
+      { 'content-security-policy': "default-src 'self'; script-src 'self' 'unsafe-inline' https:" },
+      ["default-src 'none'"]
+    );
+    expect(header.source).toBe('header');
+    expect(header.issues).toEqual([
+      "scripts allow 'unsafe-inline'",
+      'scripts allowed from any host',
+    ]);
+
+    const meta = cspReport(null, ["script-src 'self' 'nonce-abc' 'unsafe-inline'"]);
+    expect(meta.source).toBe('meta');
+    expect(meta.issues).toEqual([]);
+    expect(cspReport(null, ["img-src 'self'"]).issues).toEqual(['no script-src or default-src']);
+  });
+});
@@ -0,0 +1,141 @@
+/**
+ * `audit security`: the current page's security posture for review
+ * pipelines. Cookies missing Secure, HttpOnly or a restrictive SameSite,
+ * subresources loaded over http from an https page, the Content Security
+ * Policy (from the document's response headers or a meta tag) and the
+ * third-party origins the page loaded from.
+ */
+
+export interface AuditCookie {
+  name: string;
+  domain: string;
+  path: string;
+  secure: boolean;
+  httpOnly: boolean;
+  sameSite: 'Strict' | 'Lax' | 'None';
+}
+
+/** What the page references and loaded, collected in the page */
+export interface PageResources {
+  /** Subresources referenced by elements: tag and resolved URL */
+  refs: { tag: string; url: string }[];
+  /** URLs from the Resource Timing buffer */
+  loaded: string[];
+  /** Content of <meta http-equiv="Content-Security-Policy"> tags */
+  metaCsp: string[];
+}
+
+export interface CspReport {
+  present: boolean;
+  source: 'header' | 'meta' | null;
+  policy: string | null;
+  reportOnly: string | null;
+  issues: string[];
+}
+
+export function cookieIssues(cookie: AuditCookie): string[] {
+  const issues: string[] = [];
+  if (!cookie.secure) issues.push('missing Secure');
+  if (!cookie.httpOnly) issues.push('missing HttpOnly');
+  if (cookie.sameSite === 'None') {
+    issues.push(cookie.secure ? 'SameSite=None' : 'SameSite=None without Secure');
+  }
+  return issues;
+}
+
+/**
+ * The registrable part of a host, near enough without the public suffix
+ * list: the last two labels, or three under a short second-level label
+ * such as co.uk or com.au
+ */
+export function siteOf(host: string): string {
+  if (/^[\d.]+$/.test(host) || host.includes(':')) return host;
+  const labels = host.split('.');
+  const last = labels[labels.length - 1] ?? '';
+  const second = labels[labels.length - 2] ?? '';
+  const keep = last.length === 2 && second.length <= 3 && labels.length > 2 ? 3 : 2;
+  return labels.slice(-keep).join('.');
+}
+
+function parseUrl(url: string): URL | null {
+  try {
+    return new URL(url);
+  } catch {
+    return null;
+  }
+}
+
+/** Origins on another site than the page, with how many URLs came from each */
+export function thirdPartyOrigins(
+  pageUrl: string,
+  urls: string[]
+): { origin: string; requests: number }[] {
+  const page = parseUrl(pageUrl);
+  if (!page) return [];
+  const site = siteOf(page.hostname);
+  const counts = new Map<string, number>();
+  for (const url of new Set(urls)) {
+    const parsed = parseUrl(url);
+    if (!parsed || !/^(https?|wss?):$/.test(parsed.protocol)) continue;
+    if (siteOf(parsed.hostname) === site) continue;
+    counts.set(parsed.origin, (counts.get(parsed.origin) ?? 0) + 1);
+  }
+  return [...counts]
+    .map(([origin, requests]) => ({ origin, requests }))
+    .sort((a, b) => b.requests - a.requests || a.origin.localeCompare(b.origin));
+}
+
+/** Subresources fetched over plain http by an https page */
+export function mixedContent(
+  pageUrl: string,
+  resources: PageResources
+): { tag: string; url: string }[] {
+  if (!pageUrl.startsWith('https:')) return [];
+  const found = new Map<string, string>();
+  for (const ref of resources.refs) {
+    if (ref.url.startsWith('http:')) found.set(ref.url, ref.tag);
+  }
+  for (const url of resources.loaded) {
+    if (url.startsWith('http:') && !found.has(url)) found.set(url, 'resource');
+  }
+  return [...found].map(([url, tag]) => ({ tag, url }));
+}
+
+function policyIssues(policy: string): string[] {
+  const directives = new Map<string, string[]>();
+  for (const part of policy.split(';')) {
+    const [name, ...values] = part.trim().split(/\s+/);
+    if (name && !directives.has(name.toLowerCase())) {
+      directives.set(name.toLowerCase(), values);
+    }
+  }
+  const scripts = directives.get('script-src') ?? directives.get('default-src');
+  if (!scripts) return ['no script-src or default-src'];
+  const issues: string[] = [];
+  if (scripts.includes("'unsafe-inline'") && !scripts.some((v) => /^'(nonce|sha\d+)-/.test(v))) {
+    issues.push("scripts allow 'unsafe-inline'");
+  }
+  if (scripts.includes("'unsafe-eval'")) issues.push("scripts allow 'unsafe-eval'");
+  if (scripts.includes('*') || scripts.includes('https:') || scripts.includes('http:')) {
+    issues.push('scripts allowed from any host');
+  }
+  return issues;
+}
+
+/** The CSP in force, from the response headers (lowercased names) or meta tags */
+export function cspReport(headers: Record<string, string> | null, metaCsp: string[]): CspReport {
+  const header = headers?.['content-security-policy'] ?? null;
+  const reportOnly = headers?.['content-security-policy-report-only'] ?? null;
+  const policy = header ?? (metaCsp.length > 0 ? metaCsp.join('; ') : null);
+  if (!policy) {
+    const issues = [reportOnly ? 'report-only, not enforced' : 'no Content-Security-Policy'];
+    return { present: false, source: null, policy: null, reportOnly, issues };
+  }
+  return {
+    present: true,
+    source: header ? 'header' : 'meta',
+    policy,
+    reportOnly,
+    issues: policyIssues(policy),
+  };
+}
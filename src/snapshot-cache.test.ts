@@ -0,0 +1,32 @@
+import { describe, it, expect } from 'vitest';
+import { SnapshotCache, snapshotCacheKey } from './snapshot-cache.js';
+
+const snapshot = {
+  tree: '- button "Buy" [ref=e1]',
+  refs: { e1: { selector: 'getByRole(\'button\', { name: "Buy" })', role: 'button', name: 'Buy' } },
+};
+
+describe('snapshot cache', () => {
+  it('keys on the URL, options and every frame generation', () => {
+    const key = snapshotCacheKey('https://a.test/', { interactive: true }, ['x:1', 'y:0']);
+    expect(snapshotCacheKey('https://a.test/', { interactive: true }, ['x:1', 'y:0'])).toBe(key);
+    const changed = snapshotCacheKey('https://a.test/', { interactive: true }, ['x:2', 'y:0']);
+    expect(changed).not.toBe(key);
+    expect(snapshotCacheKey('https://a.test/', {}, ['x:1', 'y:0'])).not.toBe(key);
+    expect(snapshotCacheKey('https://a.test/', {}, ['x:1', null])).toBeNull();
+  });
+
+  it('returns the snapshot only for the key it was taken under', () => {
+    const cache = new SnapshotCache();
+    cache.set('a', snapshot);
+    expect(cache.get('a')).toBe(snapshot);
+    expect(cache.get('b')).toBeNull();
+    expect(cache.get(null)).toBeNull();
+
+    cache.set(null, snapshot);
+    expect(cache.get('a')).toBeNull();
+    cache.set('a', snapshot);
+    cache.clear();
+    expect(cache.get('a')).toBeNull();
+  });
+});
@@ -0,0 +1,105 @@
+/**
+ * Reuse of the last snapshot while the page hasn't changed. Each frame gets a
+ * counter, the DOM generation, that goes up with every DOM mutation and with
+ * the events that change what a snapshot shows without one (typing, focus,
+ * hover, resizes, finished animations). A snapshot is cached under the
+ * frames' generations, and the next snapshot with the same options is served
+ * from the cache while they are unchanged, which keeps polling agents from
+ * rebuilding the accessibility tree of an idle page.
+ */
+
+import type { Page } from 'playwright-core';
+import type { EnhancedSnapshot } from './snapshot.js';
+
+/**
+ * Runs in a frame: installs the counter on first use and returns
+ * `<document id>:<generation>`. The id is new for every document, so a
+ * navigation never matches a key from before it.
+ */
+export function trackDomGeneration(): string {
+  interface Tracker {
+    id: string;
+    generation: number;
+  }
+  const w = window as unknown as { __agentBrowserDom?: Tracker };
+  if (!w.__agentBrowserDom) {
+    const tracker: Tracker = { id: Math.random().toString(36).slice(2), generation: 0 };
+    const bump = () => {
+      tracker.generation++;
+    };
+    const observer = new MutationObserver(bump);
+    const watch = (root: Node) =>
+      observer.observe(root, {
+        subtree: true,
+        childList: true,
+        attributes: true,
+        characterData: true,
+      });
+    watch(document);
+    // Mutations inside shadow roots don't reach the document's observer
+    for (const el of Array.from(document.querySelectorAll('*'))) {
+      if (el.shadowRoot) watch(el.shadowRoot);
+    }
+    const attachShadow = Element.prototype.attachShadow;
+    Element.prototype.attachShadow = function (this: Element, init: ShadowRootInit) {
+      const root = attachShadow.call(this, init);
+      watch(root);
+      bump();
+      return root;
+    };
+    const events = [
+      'input',
+      'change',
+      'focusin',
+      'focusout',
+      'mouseover',
+      'mouseout',
+      'resize',
+      'toggle',
+      'transitionend',
+      'animationend',
+    ];
+    for (const type of events) window.addEventListener(type, bump, true);
+    w.__agentBrowserDom = tracker;
+  }
+  return `${w.__agentBrowserDom.id}:${w.__agentBrowserDom.generation}`;
+}
+
+/**
+ * Key for a snapshot of the page as it is now: the URL, the snapshot options
+ * and every frame's generation. Null when a frame couldn't be read, in which
+ * case nothing is cached.
+ */
+export function snapshotCacheKey(
+  url: string,
+  options: object,
+  generations: Array<string | null>
+): string | null {
+  if (generations.some((generation) => generation === null)) return null;
+  return JSON.stringify({ url, options, generations });
+}
+
+/** The page's current cache key, reading the generation of each frame */
+export async function readSnapshotCacheKey(page: Page, options: object): Promise<string | null> {
+  const generations = await Promise.all(
+    page.frames().map((frame) => frame.evaluate(trackDomGeneration).catch(() => null))
+  );
+  return snapshotCacheKey(page.url(), options, generations);
+}
+
+/** The last snapshot and the key it was taken under */
+export class SnapshotCache {
+  private entry: { key: string; snapshot: EnhancedSnapshot } | null = null;
+
+  get(key: string | null): EnhancedSnapshot | null {
+    return key !== null && this.entry?.key === key ? this.entry.snapshot : null;
+  }
+
+  set(key: string | null, snapshot: EnhancedSnapshot): void {
+    this.entry = key === null ? null : { key, snapshot };
+  }
+
+  clear(): void {
+    this.entry = null;
+  }
+}
@@ -0,0 +1,86 @@
+import { describe, it, expect } from 'vitest';
+import { diffSnapshots, SnapshotHistory, SNAPSHOT_HISTORY_SIZE } from './snapshot-diff.js';
+
+const before = [
+  '- navigation "Main":',
+  '  - link "Home" [ref=e1]',
+  '  - link "Cart (0)" [ref=e2]',
+  '- main:',
+  '  - heading "Shoes" [ref=e3] [level=1]',
+  '  - textbox "Search" [ref=e4]',
+  '  - button "Add to cart" [ref=e5]',
+  '  - paragraph: 12 results',
+].join('\n');
+
+function refsFor(tree: string) {
+  const refs: Record<string, { selector: string; role: string; name: string }> = {};
+  for (const [, role, name, ref] of tree.matchAll(/- (\w+) "([^"]*)" \[ref=(e\d+)\]/g)) {
+    refs[ref] = { selector: `getByRole('${role}')`, role, name };
+  }
+  return refs;
+}
+
+function snapshot(tree: string) {
+  return { tree, refs: refsFor(tree) };
+}
+
+describe('snapshot diff', () => {
+  it('shows nothing when only refs were renumbered', () => {
+    const after = before.replace(/ref=e(\d)/g, (_, n) => `ref=e${Number(n) + 10}`);
+    const diff = diffSnapshots(before, snapshot(after));
+    expect(diff).toEqual({ tree: '', refs: {}, added: 0, removed: 0, changed: 0 });
+  });
+
+  it('marks added, changed and removed lines with their ancestors as context', () => {
+    const after = [
+      '- navigation "Main":',
+      '  - link "Home" [ref=e1]',
+      '  - link "Cart (1)" [ref=e2]',
+      '- main:',
+      '  - heading "Shoes" [ref=e3] [level=1]',
+      '  - textbox "Search" [ref=e4]: boots',
+      '  - alert: Added to cart',
+      '  - paragraph: 12 results',
+    ].join('\n');
+    const diff = diffSnapshots(before, snapshot(after));
+    expect(diff.tree.split('\n')).toEqual([
+      '  - navigation "Main":',
+      '+   - link "Cart (1)" [ref=e2]',
+      '  - main:',
+      '~   - textbox "Search" [ref=e4]: boots',
+      '+   - alert: Added to cart',
+      '  - navigation "Main":',
+      '-   - link "Cart (0)"',
+      '  - main:',
+      '-   - button "Add to cart"',
+    ]);
+    expect(diff).toMatchObject({ added: 2, removed: 2, changed: 1 });
+    expect(Object.keys(diff.refs)).toEqual(['e2', 'e4']);
+  });
+
+  it('pairs repeated lines in order so an insertion only adds', () => {
+    const list = (items: string[]) =>
+      ['- list:', ...items.map((item) => `  - listitem: ${item}`)].join('\n');
+    const diff = diffSnapshots(list(['a', 'b', 'c']), snapshot(list(['new', 'a', 'b', 'c'])));
+    expect(diff.tree).toBe('  - list:\n+   - listitem: new');
+    expect(diff).toMatchObject({ added: 1, removed: 0, changed: 0 });
+  });
+});
+
+describe('snapshot history', () => {
+  it('keeps only the latest snapshots', () => {
+    const history = new SnapshotHistory();
+    const first = history.add('- button "A"', { interactive: true });
+    expect(history.get(first)).toEqual({
+      id: first,
+      tree: '- button "A"',
+      options: { interactive: true },
+    });
+    for (let i = 0; i < SNAPSHOT_HISTORY_SIZE; i++) history.add('- button "B"', {});
+    expect(history.get(first)).toBeUndefined();
+    const last = history.add('- button "C"', {});
+    expect(last).toBe(`s${SNAPSHOT_HISTORY_SIZE + 2}`);
+    history.clear();
+    expect(history.get(last)).toBeUndefined();
+  });
+});
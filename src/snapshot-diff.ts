@@ -0,0 +1,172 @@
+/**
+ * Delta snapshots (`snapshot --diff-from <id>`). Every snapshot gets an id,
+ * and the last few are kept; a snapshot taken with diffFrom returns only the
+ * lines added, removed or changed since that one, with their ancestors as
+ * context, so an agent doesn't re-read a whole large page after each action.
+ *
+ * Lines are matched by where they sit in the tree: the role and name of each
+ * ancestor, then the line's own role and name. A line whose content is the
+ * same under the same ancestors is unchanged; one left over that shares a
+ * role and name with a left-over line of the other snapshot has changed (a
+ * new value, state or text); the rest were added or removed. Refs are
+ * ignored when comparing, since they are renumbered by every snapshot.
+ */
+
+import type { EnhancedSnapshot, RefMap, SnapshotOptions } from './snapshot.js';
+
+/** How many snapshots diffFrom can refer back to */
+export const SNAPSHOT_HISTORY_SIZE = 10;
+
+export interface SnapshotHistoryEntry {
+  id: string;
+  tree: string;
+  options: SnapshotOptions;
+}
+
+/** The last SNAPSHOT_HISTORY_SIZE snapshots, by id */
+export class SnapshotHistory {
+  private entries: SnapshotHistoryEntry[] = [];
+  private counter = 0;
+
+  /** Record a snapshot and return its id */
+  add(tree: string, options: SnapshotOptions): string {
+    const id = `s${++this.counter}`;
+    this.entries.push({ id, tree, options });
+    if (this.entries.length > SNAPSHOT_HISTORY_SIZE) this.entries.shift();
+    return id;
+  }
+
+  get(id: string): SnapshotHistoryEntry | undefined {
+    return this.entries.find((entry) => entry.id === id);
+  }
+
+  clear(): void {
+    this.entries = [];
+  }
+}
+
+interface TreeLine {
+  text: string;
+  /** The line without refs, what is compared */
+  content: string;
+  depth: number;
+  /** Ancestors' and own role and name */
+  path: string;
+  parent: number | null;
+}
+
+const REF_PATTERN = / \[ref=e\d+\]/g;
+
+/** Role and name of a line: `button "Save"`, `paragraph`, `text`, `/url` */
+function identity(content: string): string {
+  const match = content.trim().match(/^- ([^\s:"[]+)(?: "((?:[^"\\]|\\.)*)")?/);
+  if (!match) return content.trim();
+  return match[2] === undefined ? match[1] : `${match[1]} "${match[2]}"`;
+}
+
+function parseTree(tree: string): TreeLine[] {
+  const lines: TreeLine[] = [];
+  const stack: number[] = [];
+  for (const text of tree.split('\n')) {
+    if (!text.trim()) continue;
+    const depth = text.length - text.trimStart().length;
+    while (stack.length > 0 && lines[stack[stack.length - 1]].depth >= depth) stack.pop();
+    const parent = stack.length > 0 ? stack[stack.length - 1] : null;
+    const content = text.replace(REF_PATTERN, '');
+    const path = `${parent === null ? '' : lines[parent].path}/${identity(content)}`;
+    stack.push(lines.length);
+    lines.push({ text, content, depth, path, parent });
+  }
+  return lines;
+}
+
+/**
+ * Pair lines of two snapshots by key, in order. Returns, for each line of
+ * `after`, the index of its partner in `before`, skipping lines already paired.
+ */
+function pairBy(
+  before: TreeLine[],
+  after: TreeLine[],
+  pairs: Array<number | null>,
+  taken: Set<number>,
+  key: (line: TreeLine) => string
+): void {
+  const free = new Map<string, number[]>();
+  before.forEach((line, index) => {
+    if (taken.has(index)) return;
+    const k = key(line);
+    free.set(k, [...(free.get(k) ?? []), index]);
+  });
+  after.forEach((line, index) => {
+    if (pairs[index] !== null) return;
+    const match = free.get(key(line))?.shift();
+    if (match === undefined) return;
+    pairs[index] = match;
+    taken.add(match);
+  });
+}
+
+export interface SnapshotDiff {
+  /** The delta: `+` added, `~` changed, `-` removed, unmarked lines are context */
+  tree: string;
+  /** Refs on the lines shown, from the new snapshot */
+  refs: RefMap;
+  added: number;
+  removed: number;
+  changed: number;
+}
+
+/** Lines to show: the marked ones and every ancestor of one */
+function withContext(lines: TreeLine[], marks: Map<number, string>): number[] {
+  const shown = new Set<number>();
+  for (const index of marks.keys()) {
+    for (let i: number | null = index; i !== null && !shown.has(i); i = lines[i].parent) {
+      shown.add(i);
+    }
+  }
+  return [...shown].sort((a, b) => a - b);
+}
+
+/** What changed from `before` (a tree from an earlier snapshot) to `after` */
+export function diffSnapshots(before: string, after: EnhancedSnapshot): SnapshotDiff {
+  const oldLines = parseTree(before);
+  const newLines = parseTree(after.tree);
+  const pairs: Array<number | null> = newLines.map(() => null);
+  const taken = new Set<number>();
+  pairBy(oldLines, newLines, pairs, taken, (line) => `${line.path}\n${line.content.trim()}`);
+  const unchanged = new Set(pairs.filter((pair): pair is number => pair !== null));
+  pairBy(oldLines, newLines, pairs, taken, (line) => line.path);
+
+  const marks = new Map<number, string>();
+  let added = 0;
+  let changed = 0;
+  pairs.forEach((pair, index) => {
+    if (pair === null) {
+      marks.set(index, '+');
+      added++;
+    } else if (!unchanged.has(pair)) {
+      marks.set(index, '~');
+      changed++;
+    }
+  });
+  const removedMarks = new Map<number, string>();
+  oldLines.forEach((_, index) => {
+    if (!taken.has(index)) removedMarks.set(index, '-');
+  });
+
+  const output: string[] = [];
+  const refs: RefMap = {};
+  for (const index of withContext(newLines, marks)) {
+    const line = newLines[index];
+    output.push(`${marks.get(index) ?? ' '} ${line.text}`);
+    for (const [, ref] of line.text.matchAll(/\[ref=(e\d+)\]/g)) {
+      if (after.refs[ref]) refs[ref] = after.refs[ref];
+    }
+  }
+  // Removed lines have no element any more, so their refs are left out
+  for (const index of withContext(oldLines, removedMarks)) {
+    output.push(`${removedMarks.get(index) ?? ' '} ${oldLines[index].content}`);
+  }
+
+  return { tree: output.join('\n'), refs, added, removed: removedMarks.size, changed };
+}
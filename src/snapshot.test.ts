@@ -0,0 +1,29 @@
+import { describe, it, expect } from 'vitest';
+import { ariaRoleAndName } from './snapshot.js';
+
+describe('ariaRoleAndName', () => {
+  it('reads the role and name of the root line', () => {
+    expect(ariaRoleAndName('- button "Submit"')).toEqual({ role: 'button', name: 'Submit' });
+    expect(ariaRoleAndName('- heading "Title" [level=1]')).toEqual({
+      role: 'heading',
+      name: 'Title',
+    });
+    expect(ariaRoleAndName('- link "Docs":\n  - /url: /docs')).toEqual({
+      role: 'link',
+      name: 'Docs',
+    });
+  });
+
+  it('unescapes quotes in names', () => {
+    expect(ariaRoleAndName('- button "Say \\"hi\\""')).toEqual({
+      role: 'button',
+      name: 'Say "hi"',
+    });
+  });
+
+  it('has no name or role when the snapshot has none', () => {
+    expect(ariaRoleAndName('- checkbox [checked]')).toEqual({ role: 'checkbox', name: null });
+    expect(ariaRoleAndName('- text: Hello')).toEqual({ role: null, name: null });
+    expect(ariaRoleAndName('')).toEqual({ role: null, name: null });
+  });
+});
@@ -298,6 +298,19 @@ function getIndentLevel(line: string): number {
   return match ? Math.floor(match[1].length / 2) : 0;
 }
 
+/**
+ * The role and accessible name of an element, from the first line of its
+ * ARIA snapshot (`- button "Submit"`). Text-only or hidden elements have no
+ * role.
+ */
+export function ariaRoleAndName(ariaTree: string): { role: string | null; name: string | null } {
+  const first = ariaTree.split('\n')[0] ?? '';
+  const match = first.match(/^-\s*(\w+)(?:\s+"((?:[^"\\]|\\.)*)")?/);
+  if (!match || match[1] === 'text') return { role: null, name: null };
+  const name = match[2] === undefined ? null : match[2].replace(/\\(.)/g, '$1');
+  return { role: match[1], name };
+}
+
 /**
  * Process a single line: add ref if needed, filter if requested
  */
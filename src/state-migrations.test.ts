@@ -0,0 +1,62 @@
+import { describe, it, expect } from 'vitest';
+import { migrateState, STATE_VERSION, UnsupportedStateVersionError } from './state-migrations.js';
+
+const cookie = {
+  name: 'sid',
+  value: 'abc',
+  domain: 'example.com',
+  path: '/',
+  expires: -1,
+  httpOnly: true,
+  secure: true,
+  sameSite: 'Lax',
+};
+
+describe('state migrations', () => {
+  it('leaves current state as it is', () => {
+    const state = { version: STATE_VERSION, cookies: [cookie], origins: [] };
+    const result = migrateState(state);
+    expect(result.changes).toEqual([]);
+    expect(result.state).toEqual(state);
+  });
+
+  it('upgrades unversioned state without dropping cookies', () => {
+    const result = migrateState({
+      cookies: [
+        { name: 'sid', value: 'abc', domain: 'example.com', sameSite: 'lax' },
+        { name: 'pref', value: 'dark', url: 'https://shop.example.com/cart' },
+      ],
+      origins: [{ origin: 'https://example.com' }],
+    });
+    expect(result.from).toBe(1);
+    expect(result.state.version).toBe(STATE_VERSION);
+    expect(result.state.cookies).toEqual([
+      { ...cookie, httpOnly: false, secure: false },
+      {
+        name: 'pref',
+        value: 'dark',
+        domain: 'shop.example.com',
+        path: '/',
+        expires: -1,
+        httpOnly: false,
+        secure: true,
+        sameSite: 'Lax',
+      },
+    ]);
+    expect(result.state.origins).toEqual([{ origin: 'https://example.com', localStorage: [] }]);
+    expect(result.changes[0]).toBe(`version 1 -> ${STATE_VERSION}`);
+    expect(result.changes).toContain('cookie sid@example.com: sameSite lax -> Lax');
+  });
+
+  it('reports cookies it cannot keep', () => {
+    const result = migrateState({ cookies: [{ value: 'x' }], origins: [] });
+    expect(result.state.cookies).toEqual([]);
+    expect(result.changes).toContain('cookie ?: dropped, it has no name or domain');
+  });
+
+  it('refuses state from a newer release', () => {
+    expect(() => migrateState({ version: STATE_VERSION + 1, cookies: [] })).toThrow(
+      UnsupportedStateVersionError
+    );
+  });
+});
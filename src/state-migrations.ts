@@ -0,0 +1,153 @@
+/**
+ * Versioning of persisted session state. Every state file records the
+ * `version` of the format it was written in; files from before versions
+ * existed are version 1. Reading a file runs the migrations from its version
+ * up to STATE_VERSION, so state saved by an older release keeps its cookies
+ * instead of being rejected by Playwright and silently replaced with a fresh
+ * session. `state migrate` rewrites the files on disk the same way.
+ */
+
+export interface StoredCookie {
+  name: string;
+  value: string;
+  domain: string;
+  path: string;
+  expires: number;
+  httpOnly: boolean;
+  secure: boolean;
+  sameSite: 'Strict' | 'Lax' | 'None';
+}
+
+export interface StoredState {
+  version?: number;
+  cookies: StoredCookie[];
+  origins: Array<{ origin: string; localStorage: Array<{ name: string; value: string }> }>;
+  [key: string]: unknown;
+}
+
+interface Migration {
+  /** Version the migration upgrades from, to the next one */
+  from: number;
+  /** Returns the upgraded state, noting each change it made in `changes` */
+  migrate: (state: Record<string, unknown>, changes: string[]) => Record<string, unknown>;
+}
+
+const SAME_SITE: Record<string, StoredCookie['sameSite']> = {
+  strict: 'Strict',
+  lax: 'Lax',
+  none: 'None',
+};
+
+/**
+ * 1 -> 2: fill in the cookie fields Playwright requires and older releases
+ * could leave out (expires, path, flags), normalize sameSite, turn url-only
+ * cookies into domain and path, and give origins their localStorage list
+ */
+function normalizeCookies(
+  state: Record<string, unknown>,
+  changes: string[]
+): Record<string, unknown> {
+  const cookies: unknown[] = Array.isArray(state.cookies) ? state.cookies : [];
+  if (!Array.isArray(state.cookies)) changes.push('added an empty cookies list');
+  const normalized: StoredCookie[] = [];
+  for (const raw of cookies) {
+    const cookie = { ...(raw as Record<string, unknown>) };
+    const label = `cookie ${String(cookie.name ?? '?')}`;
+    if (!cookie.domain && typeof cookie.url === 'string') {
+      try {
+        const url = new URL(cookie.url);
+        cookie.domain = url.hostname;
+        cookie.path ??= '/';
+        cookie.secure ??= url.protocol === 'https:';
+        changes.push(`${label}: domain ${url.hostname} from its url`);
+      } catch {
+        // Left without a domain, dropped below
+      }
+      delete cookie.url;
+    }
+    if (typeof cookie.name !== 'string' || typeof cookie.domain !== 'string') {
+      changes.push(`${label}: dropped, it has no name or domain`);
+      continue;
+    }
+    cookie.value ??= '';
+    if (cookie.path === undefined) {
+      cookie.path = '/';
+      changes.push(`${label}@${cookie.domain}: path /`);
+    }
+    if (typeof cookie.expires !== 'number') {
+      cookie.expires = -1;
+      changes.push(`${label}@${cookie.domain}: expires -1 (session cookie)`);
+    }
+    cookie.httpOnly = Boolean(cookie.httpOnly);
+    cookie.secure = Boolean(cookie.secure);
+    const sameSite = SAME_SITE[String(cookie.sameSite ?? 'lax').toLowerCase()] ?? 'Lax';
+    if (cookie.sameSite !== sameSite) {
+      changes.push(`${label}@${cookie.domain}: sameSite ${String(cookie.sameSite)} -> ${sameSite}`);
+      cookie.sameSite = sameSite;
+    }
+    normalized.push(cookie as unknown as StoredCookie);
+  }
+
+  const origins = (Array.isArray(state.origins) ? state.origins : []).map((raw) => {
+    const origin = raw as Record<string, unknown>;
+    if (Array.isArray(origin.localStorage)) return origin;
+    changes.push(`origin ${String(origin.origin)}: added an empty localStorage list`);
+    return { ...origin, localStorage: [] };
+  });
+  if (!Array.isArray(state.origins)) changes.push('added an empty origins list');
+
+  return { ...state, cookies: normalized, origins };
+}
+
+const MIGRATIONS: Migration[] = [{ from: 1, migrate: normalizeCookies }];
+
+/** Format version written by this release */
+export const STATE_VERSION = MIGRATIONS.length + 1;
+
+/** State written by a newer release, in a format this one doesn't know */
+export class UnsupportedStateVersionError extends Error {
+  constructor(version: number) {
+    super(
+      `State was saved by a newer agent-browser (format version ${version}, this release reads ` +
+        `up to ${STATE_VERSION}); upgrade agent-browser to load it`
+    );
+    this.name = 'UnsupportedStateVersionError';
+  }
+}
+
+export interface MigrationResult {
+  state: StoredState;
+  /** Version the state was in */
+  from: number;
+  to: number;
+  /** One line per change, empty when the state was already current */
+  changes: string[];
+}
+
+/**
+ * Upgrade state of any earlier version to STATE_VERSION
+ *
+ * @throws UnsupportedStateVersionError for state written by a newer release
+ */
+export function migrateState(data: object): MigrationResult {
+  const { version, ...rest } = data as Record<string, unknown>;
+  const from = version === undefined ? 1 : Number(version);
+  if (!Number.isInteger(from) || from < 1) {
+    throw new Error(`Invalid state version: ${String(version)}`);
+  }
+  if (from > STATE_VERSION) throw new UnsupportedStateVersionError(from);
+  const changes: string[] = [];
+  let state: Record<string, unknown> = rest;
+  for (const migration of MIGRATIONS) {
+    if (migration.from >= from) state = migration.migrate(state, changes);
+  }
+  if (from < STATE_VERSION) {
+    changes.unshift(`version ${from} -> ${STATE_VERSION}`);
+  }
+  return {
+    state: { version: STATE_VERSION, ...state } as StoredState,
+    from,
+    to: STATE_VERSION,
+    changes,
+  };
+}
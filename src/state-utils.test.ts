@@ -20,7 +20,14 @@ import {
   safeHeaderMerge,
   listStateFiles,
   cleanupExpiredStates,
+  writeStateFile,
+  readStateFile,
+  restoreStateBackup,
+  removeStateFile,
+  getStateBackupPath,
+  CorruptStateError,
 } from './state-utils.js';
+import { STATE_VERSION } from './state-migrations.js';
 
 describe('state-utils', () => {
   beforeEach(() => {
@@ -67,6 +74,14 @@ describe('state-utils', () => {
       expect(isValidSessionName('./current')).toBe(false);
     });
 
+    it('should reject Windows device names', () => {
+      expect(isValidSessionName('CON')).toBe(false);
+      expect(isValidSessionName('nul')).toBe(false);
+      expect(isValidSessionName('Com1')).toBe(false);
+      expect(isValidSessionName('console')).toBe(true);
+      expect(isValidSessionName('com10')).toBe(true);
+    });
+
     it('should reject names with slashes', () => {
       expect(isValidSessionName('path/to/file')).toBe(false);
       expect(isValidSessionName('path\\to\\file')).toBe(false);
@@ -171,6 +186,103 @@ describe('state-utils', () => {
     });
   });
 
+  describe('getSessionsDir', () => {
+    it('should honor AGENT_BROWSER_STATE_DIR', () => {
+      const stateDir = path.join(tempHome, 'ci', 'sessions');
+      vi.stubEnv('AGENT_BROWSER_STATE_DIR', stateDir);
+      try {
+        expect(getSessionsDir()).toBe(stateDir);
+        expect(getAutoStateFilePath('twitter', 'default')!.startsWith(stateDir)).toBe(true);
+      } finally {
+        vi.unstubAllEnvs();
+      }
+    });
+  });
+
+  describe('writeStateFile', () => {
+    const cookie = {
+      name: 'sid',
+      value: '1',
+      domain: 'example.com',
+      path: '/',
+      expires: -1,
+      httpOnly: false,
+      secure: false,
+      sameSite: 'Lax',
+    };
+    const state = { cookies: [cookie], origins: [] };
+
+    it('should write a checksummed file that reads back without the checksum', () => {
+      const file = path.join(tempHome, 'auth.json');
+      writeStateFile(file, state, { encrypt: false });
+      const keys = Object.keys(JSON.parse(fs.readFileSync(file, 'utf-8')));
+      expect(keys.slice(0, 2)).toEqual(['checksum', 'version']);
+      expect(readStateFile(file).data).toEqual({ version: STATE_VERSION, ...state });
+      expect(fs.readdirSync(tempHome)).toEqual(['auth.json']);
+    });
+
+    it('should accept and migrate files written without a checksum or version', () => {
+      const file = path.join(tempHome, 'old.json');
+      fs.writeFileSync(file, JSON.stringify(state));
+      const { data, version } = readStateFile(file);
+      expect(data).toEqual({ version: STATE_VERSION, ...state });
+      expect(version).toBe(1);
+    });
+
+    it('should detect truncated and modified files', () => {
+      const file = path.join(tempHome, 'auth.json');
+      writeStateFile(file, state, { encrypt: false });
+      const content = fs.readFileSync(file, 'utf-8');
+      fs.writeFileSync(file, content.slice(0, content.length / 2));
+      expect(() => readStateFile(file)).toThrow(CorruptStateError);
+      fs.writeFileSync(file, content.replace('"1"', '"2"'));
+      expect(() => readStateFile(file)).toThrow('checksum mismatch');
+    });
+
+    it('should compress large state when asked to', () => {
+      const file = path.join(tempHome, 'big.json');
+      const localStorage = [{ name: 'cache', value: 'x'.repeat(200_000) }];
+      const big = { ...state, origins: [{ origin: 'https://example.com', localStorage }] };
+      expect(writeStateFile(file, big, { encrypt: false, compress: true }).compressed).toBe(true);
+      expect(fs.statSync(file).size).toBeLessThan(10_000);
+      const result = readStateFile(file);
+      expect(result.wasCompressed).toBe(true);
+      expect(result.data).toEqual({ version: STATE_VERSION, ...big });
+
+      const small = writeStateFile(file, state, { encrypt: false, compress: true });
+      expect(small.compressed).toBe(false);
+    });
+
+    it('should rotate backups and restore from them', () => {
+      vi.stubEnv('AGENT_BROWSER_STATE_BACKUPS', '2');
+      try {
+        const file = path.join(tempHome, 'auth.json');
+        for (const value of ['1', '2', '3', '4']) {
+          writeStateFile(file, { cookies: [{ name: 'sid', value }] }, { encrypt: false });
+        }
+        expect(fs.existsSync(getStateBackupPath(file, 2))).toBe(true);
+        expect(fs.existsSync(getStateBackupPath(file, 3))).toBe(false);
+
+        fs.writeFileSync(file, '{"cookies": [');
+        // A corrupted file is not rotated into the backups
+        writeStateFile(file, { cookies: [{ name: 'sid', value: '5' }] }, { encrypt: false });
+        fs.writeFileSync(file, '{"cookies": [');
+        expect(restoreStateBackup(file).backup).toBe(1);
+        expect(readStateFile(file).data).toEqual({
+          version: STATE_VERSION,
+          cookies: [{ name: 'sid', value: '3' }],
+        });
+        expect(restoreStateBackup(file, 2).backup).toBe(2);
+        expect(() => restoreStateBackup(file, 5)).toThrow('No backup 5');
+
+        removeStateFile(file);
+        expect(fs.readdirSync(tempHome)).toEqual([]);
+      } finally {
+        vi.unstubAllEnvs();
+      }
+    });
+  });
+
   describe('safeHeaderMerge', () => {
     it('should merge two header objects', () => {
       const base = { 'Content-Type': 'application/json', Accept: 'text/html' };
@@ -2,9 +2,11 @@
  * Shared utilities for session state management.
  */
 
+import * as crypto from 'crypto';
 import * as fs from 'fs';
 import * as path from 'path';
 import * as os from 'os';
+import { gunzipSync, gzipSync } from 'zlib';
 import {
   getEncryptionKey,
   encryptData,
@@ -13,12 +15,20 @@ import {
   type EncryptedPayload,
   ENCRYPTION_KEY_ENV,
 } from './encryption.js';
+import { migrateState, STATE_VERSION } from './state-migrations.js';
 
 /**
  * Get the session persistence directory.
- * Located at ~/.agent-browser/sessions/
+ * AGENT_BROWSER_STATE_DIR when set (by a workspace or --state-dir), otherwise
+ * ~/.agent-browser/sessions/, or %APPDATA%\agent-browser\sessions\ on Windows
  */
 export function getSessionsDir(): string {
+  if (process.env.AGENT_BROWSER_STATE_DIR) {
+    return process.env.AGENT_BROWSER_STATE_DIR;
+  }
+  if (process.platform === 'win32' && process.env.APPDATA) {
+    return path.join(process.env.APPDATA, 'agent-browser', 'sessions');
+  }
   return path.join(os.homedir(), '.agent-browser', 'sessions');
 }
 
@@ -36,10 +46,11 @@ export function ensureSessionsDir(): string {
 
 /**
  * Validate a session ID to prevent path traversal attacks.
- * Only allows alphanumeric characters, hyphens, and underscores.
+ * Only allows alphanumeric characters, hyphens, and underscores, plus the
+ * '%' escapes and '~' hash separator of names encoded by --unicode-sessions.
  */
 function isValidSessionId(id: string): boolean {
-  return /^[a-zA-Z0-9_-]+$/.test(id);
+  return /^[a-zA-Z0-9_%~-]+$/.test(id);
 }
 
 /**
@@ -49,7 +60,14 @@ function isValidSessionId(id: string): boolean {
  * from environment variables which can be set by attackers bypassing CLI validation.
  */
 export function isValidSessionName(name: string): boolean {
-  return /^[a-zA-Z0-9_-]+$/.test(name);
+  return /^[a-zA-Z0-9_-]+$/.test(name) && !isWindowsReservedName(name);
+}
+
+/**
+ * Check for device names Windows reserves in every directory (CON, NUL, COM1, ...).
+ */
+export function isWindowsReservedName(name: string): boolean {
+  return /^(con|prn|aux|nul|com[1-9]|lpt[1-9])(\..*)?$/i.test(name);
 }
 
 /**
@@ -90,38 +108,206 @@ export function autoStateFileExists(sessionName: string, sessionId: string): boo
   return filePath ? fs.existsSync(filePath) : false;
 }
 
+/** Number of previous versions kept of each state file */
+export const DEFAULT_STATE_BACKUPS = 3;
+
+/** Key of the checksum written first in every state file */
+const CHECKSUM_KEY = 'checksum';
+
+/**
+ * Raised when a state file is truncated or its checksum doesn't match, so
+ * callers can point at `state restore --from-backup`
+ */
+export class CorruptStateError extends Error {
+  constructor(filepath: string, reason: string) {
+    super(
+      `State file ${filepath} is corrupted (${reason}); ` +
+        `recover it with: agent-browser state restore ${filepath} --from-backup`
+    );
+    this.name = 'CorruptStateError';
+  }
+}
+
+/** AGENT_BROWSER_STATE_BACKUPS, or DEFAULT_STATE_BACKUPS; 0 keeps none */
+export function getStateBackupCount(): number {
+  const value = process.env.AGENT_BROWSER_STATE_BACKUPS;
+  if (value === undefined || value === '') return DEFAULT_STATE_BACKUPS;
+  const count = Number(value);
+  return Number.isInteger(count) && count >= 0 ? count : DEFAULT_STATE_BACKUPS;
+}
+
+/** `<file>.bak.1` is the newest backup, `<file>.bak.<count>` the oldest */
+export function getStateBackupPath(filepath: string, n: number): string {
+  return `${filepath}.bak.${n}`;
+}
+
+function sha256(text: string): string {
+  return 'sha256:' + crypto.createHash('sha256').update(text).digest('hex');
+}
+
+/**
+ * Serialize state with a checksum of the rest of the document as its first
+ * key. The file stays plain JSON, so Playwright and other tools still read it.
+ */
+export function serializeState(data: object): string {
+  const body = JSON.stringify(data, null, 2);
+  return JSON.stringify({ [CHECKSUM_KEY]: sha256(body), ...data }, null, 2);
+}
+
+/**
+ * Parse a state file's content and check its checksum, returning the state
+ * without it. Files written before checksums existed are accepted as they are.
+ */
+export function parseStateContent(content: string, filepath: string): object {
+  let parsed: Record<string, unknown>;
+  try {
+    parsed = JSON.parse(content);
+  } catch {
+    throw new CorruptStateError(filepath, 'not valid JSON');
+  }
+  if (typeof parsed !== 'object' || parsed === null || Array.isArray(parsed)) {
+    throw new CorruptStateError(filepath, 'not a JSON object');
+  }
+  if (!(CHECKSUM_KEY in parsed)) return parsed;
+  const { [CHECKSUM_KEY]: checksum, ...data } = parsed;
+  if (checksum !== sha256(JSON.stringify(data, null, 2))) {
+    throw new CorruptStateError(filepath, 'checksum mismatch');
+  }
+  return data;
+}
+
+/** Whether a file holds state that parses and matches its checksum */
+function isIntactStateFile(filepath: string): boolean {
+  try {
+    parseStateContent(fs.readFileSync(filepath, 'utf-8'), filepath);
+    return true;
+  } catch {
+    return false;
+  }
+}
+
+/**
+ * Write a file so a crash leaves either the old or the new content: write a
+ * temp file next to it, flush it to disk, then rename it over the target
+ */
+export function writeFileAtomic(filepath: string, content: string, mode = 0o600): void {
+  const tmp = `${filepath}.tmp-${process.pid}-${crypto.randomBytes(4).toString('hex')}`;
+  try {
+    const fd = fs.openSync(tmp, 'w', mode);
+    try {
+      fs.writeFileSync(fd, content);
+      fs.fsyncSync(fd);
+    } finally {
+      fs.closeSync(fd);
+    }
+    fs.renameSync(tmp, filepath);
+  } catch (err) {
+    fs.rmSync(tmp, { force: true });
+    throw err;
+  }
+}
+
+/**
+ * Shift the backups of a state file down by one and copy the current file
+ * to `.bak.1`. A corrupted current file is not kept, so it can't push out
+ * the intact backups it would need to be restored from.
+ */
+export function rotateStateBackups(filepath: string, count = getStateBackupCount()): void {
+  if (count <= 0 || !fs.existsSync(filepath) || !isIntactStateFile(filepath)) return;
+  for (let n = count - 1; n >= 1; n--) {
+    const from = getStateBackupPath(filepath, n);
+    if (fs.existsSync(from)) fs.renameSync(from, getStateBackupPath(filepath, n + 1));
+  }
+  fs.copyFileSync(filepath, getStateBackupPath(filepath, 1));
+  fs.chmodSync(getStateBackupPath(filepath, 1), 0o600);
+}
+
+/** State smaller than this is written as plain JSON even when compression is asked for */
+export const COMPRESS_MIN_BYTES = 64 * 1024;
+
+/** State JSON gzipped and base64-encoded, for sessions with large localStorage */
+export interface CompressedPayload {
+  compressed: 'gzip';
+  data: string;
+}
+
+export function isCompressedPayload(data: unknown): data is CompressedPayload {
+  return (
+    typeof data === 'object' &&
+    data !== null &&
+    (data as CompressedPayload).compressed === 'gzip' &&
+    typeof (data as CompressedPayload).data === 'string'
+  );
+}
+
+export function compressState(json: string): CompressedPayload {
+  return { compressed: 'gzip', data: gzipSync(json).toString('base64') };
+}
+
+export function decompressState(payload: CompressedPayload): object {
+  return JSON.parse(gunzipSync(Buffer.from(payload.data, 'base64')).toString('utf-8'));
+}
+
 /**
  * Write state data to file, encrypting if encryption key is available.
+ * The write is atomic, carries a checksum, and rotates the previous version
+ * into the backups.
  *
  * @param filepath - Path to write the state file
  * @param data - State data object to write
- * @returns Object indicating whether the file was encrypted
+ * @param options - encrypt: false writes plain JSON even with a key set;
+ *   compress: true gzips state of COMPRESS_MIN_BYTES or more
+ * @returns Object indicating whether the file was encrypted and compressed
  */
-export function writeStateFile(filepath: string, data: object): { encrypted: boolean } {
-  const key = getEncryptionKey();
-  const jsonData = JSON.stringify(data, null, 2);
-
+export function writeStateFile(
+  filepath: string,
+  data: object,
+  options: { encrypt?: boolean; compress?: boolean } = {}
+): { encrypted: boolean; compressed: boolean } {
+  const key = options.encrypt === false ? null : getEncryptionKey();
+  // The format version goes first, replacing any older one
+  const versioned: Record<string, unknown> = { version: STATE_VERSION, ...data };
+  versioned.version = STATE_VERSION;
+  let jsonData = JSON.stringify(versioned, null, 2);
+  let payload: object = versioned;
+  const compressed = options.compress === true && Buffer.byteLength(jsonData) >= COMPRESS_MIN_BYTES;
+  if (compressed) {
+    // Compact JSON: the indentation would only be compressed away
+    payload = compressState(JSON.stringify(versioned));
+    jsonData = JSON.stringify(payload);
+  }
   if (key) {
-    const encrypted = encryptData(jsonData, key);
-    fs.writeFileSync(filepath, JSON.stringify(encrypted, null, 2));
-    return { encrypted: true };
+    payload = encryptData(jsonData, key);
   }
 
-  fs.writeFileSync(filepath, jsonData);
-  return { encrypted: false };
+  rotateStateBackups(filepath);
+  writeFileAtomic(filepath, serializeState(payload));
+  return { encrypted: Boolean(key), compressed };
 }
 
 /**
- * Read state data from file, decrypting if necessary.
+ * Read state data from file, decrypting if necessary, and migrate it to the
+ * current format version.
  *
  * @param filepath - Path to the state file
- * @returns Object containing the data and whether it was encrypted
- * @throws Error if file is encrypted but no key is available
+ * @returns Object containing the data, whether it was encrypted or
+ *   compressed, the version it was stored in and the changes migrating it made
+ * @throws CorruptStateError if the file is truncated or fails its checksum
+ * @throws Error if file is encrypted but no key is available, or was written
+ *   by a newer release
  */
-export function readStateFile(filepath: string): { data: object; wasEncrypted: boolean } {
+export function readStateFile(filepath: string): {
+  data: object;
+  wasEncrypted: boolean;
+  wasCompressed: boolean;
+  version: number;
+  changes: string[];
+} {
   const content = fs.readFileSync(filepath, 'utf-8');
-  const parsed = JSON.parse(content);
+  const parsed = parseStateContent(content, filepath);
 
+  let data: object = parsed;
+  let wasEncrypted = false;
   if (isEncryptedPayload(parsed)) {
     const key = getEncryptionKey();
     if (!key) {
@@ -130,11 +316,61 @@ export function readStateFile(filepath: string): { data: object; wasEncrypted: b
           `Set the environment variable to decrypt.`
       );
     }
-    const decrypted = decryptData(parsed, key);
-    return { data: JSON.parse(decrypted), wasEncrypted: true };
+    data = JSON.parse(decryptData(parsed, key));
+    wasEncrypted = true;
   }
+  const wasCompressed = isCompressedPayload(data);
+  if (wasCompressed) {
+    try {
+      data = decompressState(data);
+    } catch {
+      throw new CorruptStateError(filepath, 'compressed data is damaged');
+    }
+  }
+
+  const { state, from, changes } = migrateState(data);
+  return { data: state, wasEncrypted, wasCompressed, version: from, changes };
+}
 
-  return { data: parsed, wasEncrypted: false };
+/**
+ * Replace a state file with a backup: the given one, or else the newest that
+ * is intact. The backups themselves are left as they are.
+ *
+ * @returns The backup number and path used
+ * @throws Error if that backup is missing or corrupted, or none is intact
+ */
+export function restoreStateBackup(
+  filepath: string,
+  backup?: number
+): { backup: number; from: string } {
+  const count = Math.max(getStateBackupCount(), backup ?? 0);
+  const candidates = backup ? [backup] : Array.from({ length: count }, (_, i) => i + 1);
+  for (const n of candidates) {
+    const from = getStateBackupPath(filepath, n);
+    if (!fs.existsSync(from)) continue;
+    if (!isIntactStateFile(from)) {
+      if (backup) throw new CorruptStateError(from, 'backup failed its checks');
+      continue;
+    }
+    writeFileAtomic(filepath, fs.readFileSync(from, 'utf-8'));
+    return { backup: n, from };
+  }
+  throw new Error(
+    backup
+      ? `No backup ${backup} of ${filepath}`
+      : `No intact backup of ${filepath} (checked .bak.1 to .bak.${count})`
+  );
+}
+
+/** Delete a state file together with its backups */
+export function removeStateFile(filepath: string): void {
+  fs.rmSync(filepath, { force: true });
+  const dir = path.dirname(filepath);
+  const prefix = `${path.basename(filepath)}.bak.`;
+  if (!fs.existsSync(dir)) return;
+  for (const file of fs.readdirSync(dir)) {
+    if (file.startsWith(prefix)) fs.rmSync(path.join(dir, file), { force: true });
+  }
 }
 
 /**
@@ -172,7 +408,7 @@ export function cleanupExpiredStates(days: number): string[] {
     try {
       const stats = fs.statSync(filepath);
       if (now - stats.mtime.getTime() > maxAge) {
-        fs.unlinkSync(filepath);
+        removeStateFile(filepath);
         deleted.push(file);
       }
     } catch {
@@ -0,0 +1,54 @@
+import { describe, it, expect } from 'vitest';
+import {
+  addCookieOrigins,
+  cookieMatchesHost,
+  normalizeOrigin,
+  originReports,
+  storageAmount,
+  type OriginData,
+  type StoredCookie,
+} from './storage-origins.js';
+
+function cookie(domain: string, name = 'sid', value = 'abc', secure = true): StoredCookie {
+  return { name, value, domain, secure };
+}
+
+describe('storage origins', () => {
+  it('normalizes origins from user input', () => {
+    expect(normalizeOrigin('example.com')).toBe('https://example.com');
+    expect(normalizeOrigin('http://localhost:3000/app?x=1')).toBe('http://localhost:3000');
+    expect(() => normalizeOrigin('file:///tmp/x')).toThrow('only http and https');
+  });
+
+  it('matches cookie domains to hosts', () => {
+    expect(cookieMatchesHost('example.com', 'example.com')).toBe(true);
+    expect(cookieMatchesHost('example.com', 'www.example.com')).toBe(false);
+    expect(cookieMatchesHost('.example.com', 'www.example.com')).toBe(true);
+    expect(cookieMatchesHost('.example.com', 'badexample.com')).toBe(false);
+  });
+
+  it('sizes key/value storage as UTF-16', () => {
+    expect(storageAmount([['k', 'vv']])).toEqual({ keys: 1, bytes: 6 });
+  });
+
+  it('reports origins largest first with their cookies', () => {
+    const origins = new Map<string, OriginData>([
+      ['https://www.example.com', { localStorage: storageAmount([['theme', 'dark']]) }],
+      ['https://app.test', { indexedDB: 4096, cacheStorage: null }],
+      ['https://empty.test', {}],
+    ]);
+    const cookies = [cookie('.example.com'), cookie('tracker.net', 'id', 'x', false)];
+    addCookieOrigins(origins, cookies);
+    const reports = originReports(origins, cookies);
+    expect(reports.map((r) => r.origin)).toEqual([
+      'https://app.test',
+      'https://www.example.com',
+      'http://tracker.net',
+    ]);
+    expect(reports[0].indexedDB).toBe(4096);
+    expect(reports[0].cacheStorage).toBeNull();
+    expect(reports[1].cookies).toEqual({ count: 1, bytes: 6 });
+    expect(reports[1].total).toBe(6 + 18);
+    expect(reports[2].cookies.count).toBe(1);
+  });
+});
@@ -0,0 +1,109 @@
+/**
+ * `storage origins`: every origin the session holds data for, with what
+ * kind and how much. Cookies belong to domains rather than origins, so each
+ * is counted for every origin whose host it would be sent to; a cookie
+ * domain no known origin receives gets an origin of its own.
+ */
+
+export interface StoredCookie {
+  name: string;
+  value: string;
+  domain: string;
+  secure: boolean;
+}
+
+export interface StorageAmount {
+  keys: number;
+  bytes: number;
+}
+
+/** What was found for one origin, before cookies are matched up */
+export interface OriginData {
+  localStorage?: StorageAmount;
+  sessionStorage?: StorageAmount;
+  /** From the browser's usage figures; null when it doesn't report them */
+  indexedDB?: number | null;
+  cacheStorage?: number | null;
+}
+
+export interface OriginReport {
+  origin: string;
+  cookies: { count: number; bytes: number };
+  localStorage: StorageAmount;
+  sessionStorage: StorageAmount;
+  indexedDB: number | null;
+  cacheStorage: number | null;
+  /** Bytes across all of the above */
+  total: number;
+}
+
+/** An origin from user input: https:// is assumed when there's no scheme */
+export function normalizeOrigin(input: string): string {
+  const withScheme = /^[a-z][a-z0-9+.-]*:\/\//i.test(input) ? input : `https://${input}`;
+  let url: URL;
+  try {
+    url = new URL(withScheme);
+  } catch {
+    throw new Error(`Invalid origin "${input}": use e.g. https://example.com`);
+  }
+  if (url.protocol !== 'http:' && url.protocol !== 'https:') {
+    throw new Error(`Invalid origin "${input}": only http and https origins hold storage`);
+  }
+  return url.origin;
+}
+
+/** Whether a cookie for `domain` is sent to `host` */
+export function cookieMatchesHost(domain: string, host: string): boolean {
+  const bare = domain.startsWith('.') ? domain.slice(1) : domain;
+  return host === bare || (domain.startsWith('.') && host.endsWith(`.${bare}`));
+}
+
+/** Bytes of key/value storage: JavaScript strings are UTF-16 */
+export function storageAmount(entries: [string, string][]): StorageAmount {
+  const bytes = entries.reduce((sum, [key, value]) => sum + (key.length + value.length) * 2, 0);
+  return { keys: entries.length, bytes };
+}
+
+/** Add an origin for each cookie domain no known origin receives */
+export function addCookieOrigins(origins: Map<string, OriginData>, cookies: StoredCookie[]): void {
+  for (const cookie of cookies) {
+    const hosts = [...origins.keys()].map((origin) => new URL(origin).hostname);
+    if (!hosts.some((host) => cookieMatchesHost(cookie.domain, host))) {
+      const bare = cookie.domain.replace(/^\./, '');
+      origins.set(`${cookie.secure ? 'https' : 'http'}://${bare}`, {});
+    }
+  }
+}
+
+export function originReports(
+  origins: Map<string, OriginData>,
+  cookies: StoredCookie[]
+): OriginReport[] {
+  const empty = { keys: 0, bytes: 0 };
+  const reports = [...origins].map(([origin, data]) => {
+    const host = new URL(origin).hostname;
+    const sent = cookies.filter((cookie) => cookieMatchesHost(cookie.domain, host));
+    const cookieBytes = sent.reduce((sum, c) => sum + c.name.length + c.value.length, 0);
+    const report: OriginReport = {
+      origin,
+      cookies: { count: sent.length, bytes: cookieBytes },
+      localStorage: data.localStorage ?? empty,
+      sessionStorage: data.sessionStorage ?? empty,
+      indexedDB: data.indexedDB ?? null,
+      cacheStorage: data.cacheStorage ?? null,
+      total: 0,
+    };
+    report.total =
+      report.cookies.bytes +
+      report.localStorage.bytes +
+      report.sessionStorage.bytes +
+      (report.indexedDB ?? 0) +
+      (report.cacheStorage ?? 0);
+    return report;
+  });
+  return reports
+    .filter(
+      (r) => r.total > 0 || r.cookies.count + r.localStorage.keys + r.sessionStorage.keys > 0
+    )
+    .sort((a, b) => b.total - a.total || a.origin.localeCompare(b.origin));
+}
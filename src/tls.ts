@@ -0,0 +1,29 @@
+import { createHash, X509Certificate } from 'crypto';
+import { readFileSync } from 'fs';
+
+/**
+ * --extra-ca: Chromium has no switch to add a CA file, but it ignores
+ * certificate errors for chains that contain a key listed in
+ * --ignore-certificate-errors-spki-list. The CLI also points the daemon's
+ * NODE_EXTRA_CA_CERTS at the file for requests Node makes itself.
+ */
+
+const PEM_CERTIFICATE = /-----BEGIN CERTIFICATE-----[\s\S]+?-----END CERTIFICATE-----/g;
+
+/** Base64 SHA-256 of each certificate's public key (SPKI) in a PEM bundle */
+export function spkiHashes(pem: string): string[] {
+  const certificates = pem.match(PEM_CERTIFICATE) ?? [];
+  if (certificates.length === 0) {
+    throw new Error('--extra-ca: expected PEM-encoded certificate(s)');
+  }
+  return certificates.map((certificate) => {
+    const spki = new X509Certificate(certificate).publicKey.export({ type: 'spki', format: 'der' });
+    return createHash('sha256').update(spki).digest('base64');
+  });
+}
+
+/** Chromium switches that trust the certificates in a PEM file */
+export function extraCaArgs(path: string): string[] {
+  const hashes = spkiHashes(readFileSync(path, 'utf-8'));
+  return [`--ignore-certificate-errors-spki-list=${hashes.join(',')}`];
+}
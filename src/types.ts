@@ -1,11 +1,28 @@
 import type { Page, Browser, BrowserContext } from 'playwright-core';
+import type { BinaryData } from './framing.js';
 
 // Base command structure
 export interface BaseCommand {
   id: string;
   action: string;
+  /** Queue the command and return a job id instead of waiting for it */
+  noWait?: boolean;
+  /** Network limits while the command runs (or for the whole session) */
+  budget?: {
+    maxBytes?: number;
+    maxRequests?: number;
+    scope?: 'command' | 'session';
+  };
+  /** Resource types to abort while the command runs */
+  block?: BlockableResourceType[];
+  /** Access token, required once the tokens file lists any */
+  token?: string;
+  /** Reads only: answer from a response to the same read younger than this (ms) */
+  cache?: number;
 }
 
+export type BlockableResourceType = 'image' | 'font' | 'media' | 'stylesheet' | 'script';
+
 // Action-specific command types
 export interface LaunchCommand extends BaseCommand {
   action: 'launch';
@@ -27,6 +44,28 @@ export interface LaunchCommand extends BaseCommand {
   args?: string[];
   userAgent?: string;
   provider?: string;
+  // Chromium headless implementation (--headless-mode)
+  headlessMode?: 'new' | 'old' | 'shell';
+  // Resolve host names through a local proxy (--ip-family, --dns-over-https)
+  dns?: { family?: 4 | 6; dohUrl?: string };
+  // Upstream proxies to rotate between (--proxy-pool, --rotate)
+  proxyPool?: {
+    proxies: { server: string; username?: string; password?: string }[];
+    rotate?: 'per-navigation' | 'per-session';
+  };
+  // Restart the browser, keeping its state, when it uses more memory (--max-memory, bytes)
+  maxMemory?: number;
+  // Close stray tabs, popups and empty contexts periodically (--janitor)
+  janitor?: ('blank' | 'popups' | 'contexts')[];
+  // Acquire the browser from a Selenium Grid or CDP pool (--pool, --pool-capabilities)
+  pool?: { url: string; capabilities?: Record<string, unknown> };
+  // Accept invalid TLS certificates, e.g. self-signed staging certs (--ignore-https-errors)
+  ignoreHTTPSErrors?: boolean;
+  // PEM file of extra CA certificates to trust (--extra-ca)
+  extraCaCerts?: string;
+  // Restrict requests by host; "*.domain" matches subdomains (--allow-hosts, --block-hosts)
+  allowHosts?: string[];
+  blockHosts?: string[];
   // Auto-load state file for session persistence
   autoStateFilePath?: string;
 }
@@ -34,8 +73,21 @@ export interface LaunchCommand extends BaseCommand {
 export interface NavigateCommand extends BaseCommand {
   action: 'navigate';
   url: string;
-  waitUntil?: 'load' | 'domcontentloaded' | 'networkidle';
+  waitUntil?: 'load' | 'domcontentloaded' | 'networkidle' | 'commit';
+  referer?: string;
+  // Open the URL in a new tab of the current context and switch to it
+  newTab?: boolean;
   headers?: Record<string, string>;
+  // 'navigation': send `headers` with this navigation only instead of the origin's later requests
+  headersScope?: 'origin' | 'navigation';
+  // Headers attached only to requests for their origin (--headers-for)
+  originHeaders?: Record<string, Record<string, string>>;
+  // Browser identity to present from this navigation on (--rotate-profile)
+  rotation?: {
+    userAgent?: string;
+    acceptLanguage?: string;
+    viewport?: { width: number; height: number };
+  };
 }
 
 export interface ClickCommand extends BaseCommand {
@@ -44,7 +96,22 @@ export interface ClickCommand extends BaseCommand {
   button?: 'left' | 'right' | 'middle';
   clickCount?: number;
   delay?: number;
+  // Skip the actionability checks (visible, stable, enabled, not covered)
+  force?: boolean;
+  // Scroll the element at least this many pixels below the top first (sticky headers)
+  scrollMargin?: number;
   newTab?: boolean;
+  // Wait for the navigation the click starts, armed before clicking
+  expectNavigation?: boolean;
+  // Capture the window the click opens as a new tab and switch to it
+  expectPopup?: boolean;
+  // Return what the click writes to the clipboard (copy buttons)
+  expectClipboard?: boolean;
+  // Return what the form submission the click starts would send
+  interceptSubmit?: boolean;
+  // With interceptSubmit: let the submission go through instead of cancelling it
+  allowSubmit?: boolean;
+  waitUntil?: 'load' | 'domcontentloaded' | 'networkidle' | 'commit';
 }
 
 export interface TypeCommand extends BaseCommand {
@@ -53,6 +120,7 @@ export interface TypeCommand extends BaseCommand {
   text: string;
   delay?: number;
   clear?: boolean;
+  force?: boolean;
 }
 
 export interface FillCommand extends BaseCommand {
@@ -175,6 +243,26 @@ export interface StorageClearCommand extends BaseCommand {
   type: 'local' | 'session';
 }
 
+export interface IndexedDbListCommand extends BaseCommand {
+  action: 'indexeddb_list';
+}
+
+export interface IndexedDbDumpCommand extends BaseCommand {
+  action: 'indexeddb_dump';
+  db: string;
+  store?: string; // all stores when omitted
+  limit?: number; // records per store
+}
+
+export interface StorageOriginsCommand extends BaseCommand {
+  action: 'storage_origins';
+}
+
+export interface StorageClearOriginCommand extends BaseCommand {
+  action: 'storage_clear_origin';
+  origin: string;
+}
+
 export interface DialogCommand extends BaseCommand {
   action: 'dialog';
   response: 'accept' | 'dismiss';
@@ -196,6 +284,8 @@ export interface PdfCommand extends BaseCommand {
     | 'A4'
     | 'A5'
     | 'A6';
+  /** Directory the CLI created; seeing it means sharing its filesystem */
+  handoff?: string;
 }
 
 // Network interception
@@ -211,6 +301,13 @@ export interface RouteCommand extends BaseCommand {
   abort?: boolean;
 }
 
+export interface RouteHarCommand extends BaseCommand {
+  action: 'route_har';
+  path: string;
+  url?: string; // URL pattern to serve from the HAR, default all
+  notFound?: 'fallback' | 'abort'; // Requests missing from the HAR
+}
+
 export interface UnrouteCommand extends BaseCommand {
   action: 'unroute';
   url?: string; // If not provided, remove all routes
@@ -223,11 +320,39 @@ export interface RequestsCommand extends BaseCommand {
   clear?: boolean;
 }
 
+// Assertions over the captured requests (assert request)
+export interface AssertRequestCommand extends BaseCommand {
+  action: 'assert_request';
+  pattern: string; // URL glob, or a substring
+  sent: boolean; // false: --not-sent
+  method?: string;
+  status?: string; // 204 or 2xx
+  bodyPath?: string; // $.event
+  equals?: string;
+}
+
+// Security posture of the current page (audit security)
+export interface SecurityAuditCommand extends BaseCommand {
+  action: 'security_audit';
+}
+
+// Request every link on the page (check-links)
+export interface CheckLinksCommand extends BaseCommand {
+  action: 'check_links';
+  sameOrigin?: boolean;
+  concurrency?: number;
+  timeout?: number; // per request, ms
+}
+
 // Download handling
 export interface DownloadCommand extends BaseCommand {
   action: 'download';
   selector: string;
-  path: string;
+  // Without a path (`click --expect-download`) the file stays where the browser saved it
+  path?: string;
+  // --max-download (bytes) and --block-download-types
+  maxBytes?: number;
+  blockedTypes?: string[];
 }
 
 // Geolocation
@@ -267,19 +392,30 @@ export interface DeviceCommand extends BaseCommand {
 // Go back/forward
 export interface BackCommand extends BaseCommand {
   action: 'back';
+  // Entries of history to go back
+  steps?: number;
 }
 
 export interface ForwardCommand extends BaseCommand {
   action: 'forward';
+  steps?: number;
 }
 
 export interface ReloadCommand extends BaseCommand {
   action: 'reload';
+  // Bypass the cache (`reload --hard`)
+  ignoreCache?: boolean;
+}
+
+// Stop the navigation in progress
+export interface StopCommand extends BaseCommand {
+  action: 'stop';
 }
 
 // Get URL/Title
 export interface UrlCommand extends BaseCommand {
   action: 'url';
+  includeTitle?: boolean;
 }
 
 export interface TitleCommand extends BaseCommand {
@@ -293,9 +429,33 @@ export interface GetAttributeCommand extends BaseCommand {
   attribute: string;
 }
 
+// Regions and patterns left out of the content hash of text/html results
+export interface HashIgnore {
+  /** CSS selectors of regions inside the element, e.g. ads or a clock */
+  selectors?: string[];
+  /** Regular expressions, e.g. timestamps */
+  patterns?: string[];
+}
+
 export interface GetTextCommand extends BaseCommand {
   action: 'gettext';
   selector: string;
+  hashIgnore?: HashIgnore;
+}
+
+// Page text: the DOM's rendered text, or the PDF itself (saved to `path`)
+// when the page is a PDF
+export interface TextCommand extends BaseCommand {
+  action: 'text';
+  selector?: string;
+  /** Save a PDF page to a temp file for the CLI to extract, instead of reading the DOM */
+  savePdf?: boolean;
+  hashIgnore?: HashIgnore;
+}
+
+// Readable article text of the current page with its hero image and metadata
+export interface ArticleCommand extends BaseCommand {
+  action: 'article';
 }
 
 export interface IsVisibleCommand extends BaseCommand {
@@ -313,6 +473,12 @@ export interface IsCheckedCommand extends BaseCommand {
   selector: string;
 }
 
+// Everything `is` checks and more, at once (`state <selector>`)
+export interface ElementStateCommand extends BaseCommand {
+  action: 'elementstate';
+  selector: string;
+}
+
 export interface CountCommand extends BaseCommand {
   action: 'count';
   selector: string;
@@ -328,6 +494,8 @@ export interface BoundingBoxCommand extends BaseCommand {
 export interface StylesCommand extends BaseCommand {
   action: 'styles';
   selector: string;
+  // Computed properties to return instead of the default set (`style --props`)
+  props?: string[];
 }
 
 // More semantic locators
@@ -368,6 +536,20 @@ export interface WaitForUrlCommand extends BaseCommand {
   timeout?: number;
 }
 
+// Wait for a client-side route (matches pushState/hash changes too)
+export interface WaitForRouteCommand extends BaseCommand {
+  action: 'waitforroute';
+  route: string; // URL glob, "/path" or "#hash"
+  timeout?: number;
+}
+
+// Client-side route change without a document load
+export interface SpaNavigateCommand extends BaseCommand {
+  action: 'spanavigate';
+  route: string;
+  mode: 'hash' | 'history';
+}
+
 // Wait for load state
 export interface WaitForLoadStateCommand extends BaseCommand {
   action: 'waitforloadstate';
@@ -417,6 +599,32 @@ export interface MouseUpCommand extends BaseCommand {
   button?: 'left' | 'right' | 'middle';
 }
 
+// Pointer commands at coordinates, for vision agents. Points are screenshot
+// pixels (CSS pixels times devicePixelRatio) unless `css` is set.
+export interface ClickAtCommand extends BaseCommand {
+  action: 'click_at';
+  x: number;
+  y: number;
+  button?: 'left' | 'right' | 'middle';
+  css?: boolean;
+}
+
+export interface MoveAtCommand extends BaseCommand {
+  action: 'move_at';
+  x: number;
+  y: number;
+  css?: boolean;
+}
+
+export interface DragAtCommand extends BaseCommand {
+  action: 'drag_at';
+  fromX: number;
+  fromY: number;
+  toX: number;
+  toY: number;
+  css?: boolean;
+}
+
 // Bring to front
 export interface BringToFrontCommand extends BaseCommand {
   action: 'bringtofront';
@@ -433,6 +641,7 @@ export interface WaitForFunctionCommand extends BaseCommand {
 export interface ScrollIntoViewCommand extends BaseCommand {
   action: 'scrollintoview';
   selector: string;
+  scrollMargin?: number;
 }
 
 // Add init script (runs on every navigation)
@@ -470,6 +679,8 @@ export interface WaitForDownloadCommand extends BaseCommand {
   action: 'waitfordownload';
   path?: string;
   timeout?: number;
+  maxBytes?: number;
+  blockedTypes?: string[];
 }
 
 // Get response body from intercepted request
@@ -549,6 +760,18 @@ export interface RecordingRestartCommand extends BaseCommand {
   url?: string;
 }
 
+// Animated GIF/APNG of the next commands (one frame per command)
+export interface GifStartCommand extends BaseCommand {
+  action: 'gif_start';
+  path: string;
+  commands?: number;
+  delay?: number;
+}
+
+export interface GifStopCommand extends BaseCommand {
+  action: 'gif_stop';
+}
+
 // Tracing
 export interface TraceStartCommand extends BaseCommand {
   action: 'trace_start';
@@ -609,12 +832,105 @@ export interface StateRenameCommand extends BaseCommand {
   newName: string; // New filename (without .json extension)
 }
 
+export interface StateRestoreCommand extends BaseCommand {
+  action: 'state_restore';
+  path: string; // State file to replace
+  backup?: number; // Backup to restore (1 is the newest); the newest intact one if omitted
+}
+
+export interface StateMigrateCommand extends BaseCommand {
+  action: 'state_migrate';
+  path?: string; // State file to migrate; every file in the sessions directory if omitted
+  dryRun?: boolean; // Report the changes without writing them
+}
+
 // Console logs
 export interface ConsoleCommand extends BaseCommand {
   action: 'console';
   clear?: boolean;
 }
 
+// Dialogs shown by the page (read by lifecycle hooks)
+export interface DialogEventsCommand extends BaseCommand {
+  action: 'dialog_events';
+  clear?: boolean;
+}
+
+// Cleanup done by the janitor; `run` sweeps now
+export interface JanitorCommand extends BaseCommand {
+  action: 'janitor';
+  run?: boolean;
+  rules?: ('blank' | 'popups' | 'contexts')[];
+  clear?: boolean;
+}
+
+// Queued jobs (commands sent with noWait), answered by the daemon itself
+export interface JobStatusCommand extends BaseCommand {
+  action: 'job_status';
+  jobId: string;
+}
+
+export interface JobResultCommand extends BaseCommand {
+  action: 'job_result';
+  jobId: string;
+}
+
+export interface JobListCommand extends BaseCommand {
+  action: 'job_list';
+}
+
+// Drop a job that hasn't started yet
+export interface JobCancelCommand extends BaseCommand {
+  action: 'job_cancel';
+  jobId: string;
+}
+
+// Launch the browser now instead of on the first command
+export interface PrelaunchCommand extends BaseCommand {
+  action: 'prelaunch';
+}
+
+// Hand a warm daemon (`daemon warm`) to a session, answered by the daemon itself
+export interface WarmClaimCommand extends BaseCommand {
+  action: 'warm_claim';
+  session: string;
+}
+
+// Attach to the session to watch it, answered by the daemon itself
+export interface ObserveCommand extends BaseCommand {
+  action: 'observe';
+  /** Send a screenshot after each command */
+  screenshots?: boolean;
+}
+
+// Recurring runs, managed by the daemon itself
+export interface ScheduleAddCommand extends BaseCommand {
+  action: 'schedule_add';
+  cron: string;
+  commands: Record<string, unknown>[];
+  script?: string;
+  jitterMs?: number;
+}
+
+export interface ScheduleListCommand extends BaseCommand {
+  action: 'schedule_list';
+}
+
+export interface ScheduleRemoveCommand extends BaseCommand {
+  action: 'schedule_remove';
+  scheduleId: string;
+}
+
+export interface ScheduleHistoryCommand extends BaseCommand {
+  action: 'schedule_history';
+  scheduleId: string;
+}
+
+export interface ScheduleRunCommand extends BaseCommand {
+  action: 'schedule_run';
+  scheduleId: string;
+}
+
 // Page errors
 export interface ErrorsCommand extends BaseCommand {
   action: 'errors';
@@ -675,6 +991,7 @@ export interface InnerTextCommand extends BaseCommand {
 export interface InnerHtmlCommand extends BaseCommand {
   action: 'innerhtml';
   selector: string;
+  hashIgnore?: HashIgnore;
 }
 
 // Input value
@@ -762,6 +1079,12 @@ export interface ScreenshotCommand extends BaseCommand {
   selector?: string;
   format?: 'png' | 'jpeg';
   quality?: number;
+  /** Draw the latest snapshot's refs as labeled boxes */
+  annotate?: boolean;
+  /** Draw a labeled grid with a line every this many image pixels */
+  grid?: number;
+  /** Directory the CLI created; seeing it means sharing its filesystem */
+  handoff?: string;
 }
 
 export interface SnapshotCommand extends BaseCommand {
@@ -873,13 +1196,21 @@ export type Command =
   | CookiesSetCommand
   | CookiesClearCommand
   | StorageGetCommand
+  | IndexedDbListCommand
+  | IndexedDbDumpCommand
+  | StorageOriginsCommand
+  | StorageClearOriginCommand
   | StorageSetCommand
   | StorageClearCommand
   | DialogCommand
   | PdfCommand
   | RouteCommand
+  | RouteHarCommand
   | UnrouteCommand
   | RequestsCommand
+  | AssertRequestCommand
+  | SecurityAuditCommand
+  | CheckLinksCommand
   | DownloadCommand
   | GeolocationCommand
   | PermissionsCommand
@@ -889,13 +1220,17 @@ export type Command =
   | BackCommand
   | ForwardCommand
   | ReloadCommand
+  | StopCommand
   | UrlCommand
   | TitleCommand
   | GetAttributeCommand
   | GetTextCommand
+  | TextCommand
+  | ArticleCommand
   | IsVisibleCommand
   | IsEnabledCommand
   | IsCheckedCommand
+  | ElementStateCommand
   | CountCommand
   | BoundingBoxCommand
   | StylesCommand
@@ -904,6 +1239,8 @@ export type Command =
   | RecordingStartCommand
   | RecordingStopCommand
   | RecordingRestartCommand
+  | GifStartCommand
+  | GifStopCommand
   | TraceStartCommand
   | TraceStopCommand
   | HarStartCommand
@@ -915,7 +1252,23 @@ export type Command =
   | StateShowCommand
   | StateCleanCommand
   | StateRenameCommand
+  | StateRestoreCommand
+  | StateMigrateCommand
   | ConsoleCommand
+  | DialogEventsCommand
+  | JanitorCommand
+  | JobStatusCommand
+  | JobResultCommand
+  | JobListCommand
+  | JobCancelCommand
+  | PrelaunchCommand
+  | WarmClaimCommand
+  | ObserveCommand
+  | ScheduleAddCommand
+  | ScheduleListCommand
+  | ScheduleRemoveCommand
+  | ScheduleHistoryCommand
+  | ScheduleRunCommand
   | ErrorsCommand
   | KeyboardCommand
   | WheelCommand
@@ -942,6 +1295,8 @@ export type Command =
   | GetByTestIdCommand
   | NthCommand
   | WaitForUrlCommand
+  | WaitForRouteCommand
+  | SpaNavigateCommand
   | WaitForLoadStateCommand
   | SetContentCommand
   | TimezoneCommand
@@ -950,6 +1305,9 @@ export type Command =
   | MouseMoveCommand
   | MouseDownCommand
   | MouseUpCommand
+  | ClickAtCommand
+  | MoveAtCommand
+  | DragAtCommand
   | BringToFrontCommand
   | WaitForFunctionCommand
   | ScrollIntoViewCommand
@@ -971,12 +1329,29 @@ export interface SuccessResponse<T = unknown> {
   id: string;
   success: true;
   data: T;
+  meta?: ResponseMeta;
 }
 
 export interface ErrorResponse {
   id: string;
   success: false;
   error: string;
+  /** Structured details for errors callers may handle programmatically */
+  data?: Record<string, unknown>;
+  meta?: ResponseMeta;
+}
+
+/** About how the command ran rather than its result */
+export interface ResponseMeta {
+  /** Requests aborted by --block, per resource type */
+  blocked?: Partial<Record<BlockableResourceType, number>>;
+  /**
+   * Served from a cache: a snapshot of a page that hasn't changed since, or
+   * a read sent with --cache
+   */
+  cache?: boolean;
+  /** --cache: ms since the cached response was produced */
+  cacheAge?: number;
 }
 
 export type Response<T = unknown> = SuccessResponse<T> | ErrorResponse;
@@ -985,11 +1360,29 @@ export type Response<T = unknown> = SuccessResponse<T> | ErrorResponse;
 export interface NavigateData {
   url: string;
   title: string;
+  /** The URL was a PDF, which headless Chromium can't display */
+  pdf?: boolean;
+  /** Index of the tab opened by newTab */
+  tab?: number;
 }
 
 export interface ScreenshotData {
   path?: string;
-  base64?: string;
+  /** The image; base64 once serialized to JSON */
+  base64?: string | BinaryData;
+  /** Where the image was written in the handoff directory, instead of base64 */
+  handoff?: string;
+  /** The image for a client on another filesystem to write to path */
+  bytes?: BinaryData;
+  /** Refs drawn by --annotate, with their boxes in image pixels */
+  annotations?: RefAnnotation[];
+}
+
+export interface RefAnnotation {
+  ref: string;
+  role: string;
+  name?: string;
+  box: { x: number; y: number; width: number; height: number };
 }
 
 export interface SnapshotData {
@@ -1060,6 +1453,17 @@ export interface RecordingRestartData {
   stopped: boolean;
 }
 
+export interface GifStartData {
+  started: boolean;
+  path: string;
+  commands: number;
+}
+
+export interface GifStopData {
+  path: string;
+  frames: number;
+}
+
 export interface InputEventData {
   injected: boolean;
 }
@@ -1069,21 +1473,38 @@ export interface ElementStyleInfo {
   tag: string;
   text: string | null;
   box: { x: number; y: number; width: number; height: number };
-  styles: {
-    fontSize: string;
-    fontWeight: string;
-    fontFamily: string;
-    color: string;
-    backgroundColor: string;
-    borderRadius: string;
-    border: string | null;
-    boxShadow: string | null;
+  // The default set below, or the requested props by the names given
+  styles:
+    | {
+        fontSize: string;
+        fontWeight: string;
+        fontFamily: string;
+        color: string;
+        backgroundColor: string;
+        borderRadius: string;
+        border: string | null;
+        boxShadow: string | null;
+        padding: string;
+      }
+    | Record<string, string>;
+  layout: {
+    display: string;
+    position: string;
+    visibility: string;
+    opacity: number;
+    zIndex: string;
+    margin: string;
     padding: string;
+    // Any part of the element is inside the viewport
+    inViewport: boolean;
+    // The content is larger than the element (scrolled or cut off)
+    overflowing: boolean;
   };
 }
 
 export interface StylesData {
   elements: ElementStyleInfo[];
+  props?: string[];
 }
 
 // Browser state